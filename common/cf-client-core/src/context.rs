@@ -0,0 +1,80 @@
+use alloc::string::ToString;
+
+use crate::{ibc, Neighbourhood, Result};
+
+/// Context allowing accessing consensus states.
+///
+/// `ClientState` and `ConsensusState` are the light client’s client and
+/// consensus state types.  Since the trait is generic over them, a single
+/// host context can implement it for each of the light clients it supports.
+/// Light client crates usually define their own `CommonContext` trait which
+/// is a shorthand for this trait specialised for the client’s types.
+pub trait CommonContext<ClientState, ConsensusState> {
+    type ConversionError: ToString;
+    type AnyClientState: From<ClientState>;
+    type AnyConsensusState: TryInto<ConsensusState, Error = Self::ConversionError>
+        + From<ConsensusState>;
+
+    fn host_metadata(&self) -> Result<(ibc::Timestamp, ibc::Height)>;
+
+    fn set_client_state(
+        &mut self,
+        client_id: &ibc::ClientId,
+        state: Self::AnyClientState,
+    ) -> Result<()>;
+
+    fn consensus_state(
+        &self,
+        client_id: &ibc::ClientId,
+        height: ibc::Height,
+    ) -> Result<Self::AnyConsensusState>;
+
+    /// Returns consensus at given height or its neighbours.
+    ///
+    /// If consensus state at given height returns `This(state)` for that state.
+    /// Otherwise, returns `Neighbours(prev, next)` where `prev` and `next` are
+    /// states with lower and greater height respectively if they exist.
+    fn consensus_state_neighbourhood(
+        &self,
+        client_id: &ibc::ClientId,
+        height: ibc::Height,
+    ) -> Result<Neighbourhood<Self::AnyConsensusState>>;
+
+    fn store_consensus_state_and_metadata(
+        &mut self,
+        client_id: &ibc::ClientId,
+        height: ibc::Height,
+        consensus: Self::AnyConsensusState,
+        host_timestamp: ibc::Timestamp,
+        host_height: ibc::Height,
+    ) -> Result;
+
+    fn delete_consensus_state_and_metadata(
+        &mut self,
+        client_id: &ibc::ClientId,
+        height: ibc::Height,
+    ) -> Result;
+
+    /// Returns earliest consensus state for given client.
+    fn earliest_consensus_state(
+        &self,
+        client_id: &ibc::ClientId,
+    ) -> Result<Option<(ibc::Height, Self::AnyConsensusState)>>;
+}
+
+/// Removes the earliest consensus state of the client if it has expired.
+///
+/// `has_expired` is used to check whether the state expired.
+pub fn prune_oldest_consensus_state<ClientState, ConsensusState>(
+    ctx: &mut impl CommonContext<ClientState, ConsensusState>,
+    client_id: &ibc::ClientId,
+    has_expired: impl FnOnce(&ConsensusState) -> bool,
+) -> Result {
+    if let Some((height, state)) = ctx.earliest_consensus_state(client_id)? {
+        let state = state.try_into().map_err(crate::error)?;
+        if has_expired(&state) {
+            ctx.delete_consensus_state_and_metadata(client_id, height)?;
+        }
+    }
+    Ok(())
+}
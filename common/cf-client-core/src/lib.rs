@@ -0,0 +1,72 @@
+#![no_std]
+extern crate alloc;
+
+use alloc::string::ToString;
+
+mod context;
+mod message;
+mod neighbourhood;
+
+pub use context::{prune_oldest_consensus_state, CommonContext};
+pub use neighbourhood::Neighbourhood;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use proto_utils;
+}
+
+mod ibc {
+    pub use ibc_core_client_context::types::error::ClientError;
+    pub use ibc_core_client_context::types::Height;
+    pub use ibc_core_host::types::identifiers::ClientId;
+    pub use ibc_primitives::Timestamp;
+}
+
+type Result<T = (), E = ibc::ClientError> = ::core::result::Result<T, E>;
+
+/// Constructs a generic client error with given message.
+pub fn error(msg: impl ToString) -> ibc::ClientError {
+    ibc::ClientError::Other { description: msg.to_string() }
+}
+
+/// Checks client id’s client type is what’s expected and then parses the id as
+/// `ClientIdx`.
+///
+/// Checks that client id which was used in generating the path (if any) follows
+/// `<client-type>-<counter>` format where `<counter>` is a non-empty sequence
+/// of digits and `<client-type>` equals `client_type`.  Doesn’t check leading
+/// zeros in the counter nor whether the value is too large.
+pub fn parse_client_id(
+    client_type: &str,
+    client_id: &ibc::ClientId,
+) -> Result<trie_ids::ClientIdx> {
+    let (what, value) = match trie_ids::ClientIdx::parse(client_id) {
+        Ok((got, idx)) if got == client_type => return Ok(idx),
+        Ok((got, _)) => ("type", got),
+        Err(_) => ("id", client_id.as_str()),
+    };
+    let description = alloc::format!("invalid client {what}: {value}");
+    Err(ibc::ClientError::ClientSpecific { description })
+}
+
+#[test]
+fn test_parse_client_id() {
+    use core::str::FromStr;
+
+    for (ok, id) in [
+        (true, "foo-bar-0"),
+        (true, "foo-bar-42"),
+        (false, "foo-bar1"),
+        (false, "foo-bar-"),
+        (false, "foo-bar--42"),
+        (false, "foo-bar-baz-42"),
+        (false, "foo-ba-42"),
+    ] {
+        let client_id = ibc::ClientId::from_str(id).unwrap();
+        assert_eq!(
+            ok,
+            parse_client_id("foo-bar", &client_id).is_ok(),
+            "id={id}"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+/// Implements conversions for a light client’s `ClientMessage` type.
+///
+/// The client message must be an enum with `Header` and `Misbehaviour`
+/// variants holding given header and misbehaviour types.  `proto` is the
+/// module with the client’s protocol message types; it must define
+/// `ClientMessage`, `Header` and `Misbehaviour` messages, the
+/// `client_message::Message` oneof enum as well as `BadMessage` and
+/// `DecodeError` error types.  The header and misbehaviour types must
+/// already be convertible to and from their protocol messages.
+///
+/// The macro implements conversions between the client message and its
+/// protocol messages (including the oneof enum), conversions from the header
+/// and misbehaviour into the protocol messages, the encoding methods (see
+/// [`proto_utils::define_wrapper`]) and [`proto_utils::AnyConvert`] which
+/// accepts `Any` messages holding the client message, header or misbehaviour.
+///
+/// Example usage:
+///
+/// ```ignore
+/// cf_client_core::impl_client_message! {
+///     proto: proto,
+///     message: ClientMessage,
+///     header: Header<PK>,
+///     misbehaviour: Misbehaviour<PK>,
+///     where PK: guestchain::PubKey = guestchain::validators::MockPubKey
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_client_message {
+    (
+        proto: $proto:ident,
+        message: $Msg:ident,
+        header: $Header:ty,
+        misbehaviour: $Misbehaviour:ty
+        $(, where $T:ident: $bound:path = $concrete:path)? $(,)?
+    ) => {
+        // Conversions directly to and from the Message enum.
+
+        impl $(<$T: $bound>)? From<$Msg $(<$T>)?>
+            for $proto::client_message::Message
+        {
+            fn from(msg: $Msg $(<$T>)?) -> Self { Self::from(&msg) }
+        }
+
+        impl $(<$T: $bound>)? From<&$Msg $(<$T>)?>
+            for $proto::client_message::Message
+        {
+            fn from(msg: &$Msg $(<$T>)?) -> Self {
+                match msg {
+                    $Msg::Header(msg) => Self::Header(msg.into()),
+                    $Msg::Misbehaviour(msg) => Self::Misbehaviour(msg.into()),
+                }
+            }
+        }
+
+        impl $(<$T: $bound>)? TryFrom<$proto::client_message::Message>
+            for $Msg $(<$T>)?
+        {
+            type Error = $proto::BadMessage;
+            fn try_from(
+                msg: $proto::client_message::Message,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                Self::try_from(&msg)
+            }
+        }
+
+        impl $(<$T: $bound>)? TryFrom<&$proto::client_message::Message>
+            for $Msg $(<$T>)?
+        {
+            type Error = $proto::BadMessage;
+            fn try_from(
+                msg: &$proto::client_message::Message,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                match msg {
+                    $proto::client_message::Message::Header(msg) => {
+                        msg.try_into().map(Self::Header)
+                    }
+                    $proto::client_message::Message::Misbehaviour(msg) => {
+                        msg.try_into().map(Self::Misbehaviour)
+                    }
+                }
+            }
+        }
+
+
+        // Conversions directly into the Message enum from variant types.
+
+        impl $(<$T: $bound>)? From<$Header> for $proto::client_message::Message {
+            fn from(msg: $Header) -> Self { Self::Header(msg.into()) }
+        }
+
+        impl $(<$T: $bound>)? From<&$Header> for $proto::client_message::Message {
+            fn from(msg: &$Header) -> Self { Self::Header(msg.into()) }
+        }
+
+        impl $(<$T: $bound>)? From<$Misbehaviour>
+            for $proto::client_message::Message
+        {
+            fn from(msg: $Misbehaviour) -> Self {
+                Self::Misbehaviour(msg.into())
+            }
+        }
+
+        impl $(<$T: $bound>)? From<&$Misbehaviour>
+            for $proto::client_message::Message
+        {
+            fn from(msg: &$Misbehaviour) -> Self {
+                Self::Misbehaviour(msg.into())
+            }
+        }
+
+
+        // Conversion into ClientMessage proto from variant types.
+
+        impl $(<$T: $bound>)? From<$Header> for $proto::ClientMessage {
+            fn from(msg: $Header) -> Self { Self { message: Some(msg.into()) } }
+        }
+
+        impl $(<$T: $bound>)? From<&$Header> for $proto::ClientMessage {
+            fn from(msg: &$Header) -> Self {
+                Self { message: Some(msg.into()) }
+            }
+        }
+
+        impl $(<$T: $bound>)? From<$Misbehaviour> for $proto::ClientMessage {
+            fn from(msg: $Misbehaviour) -> Self {
+                Self { message: Some(msg.into()) }
+            }
+        }
+
+        impl $(<$T: $bound>)? From<&$Misbehaviour> for $proto::ClientMessage {
+            fn from(msg: &$Misbehaviour) -> Self {
+                Self { message: Some(msg.into()) }
+            }
+        }
+
+
+        // And finally, conversions between proto and Rust type
+
+        impl $(<$T: $bound>)? From<$Msg $(<$T>)?> for $proto::ClientMessage {
+            fn from(msg: $Msg $(<$T>)?) -> Self { Self::from(&msg) }
+        }
+
+        impl $(<$T: $bound>)? From<&$Msg $(<$T>)?> for $proto::ClientMessage {
+            fn from(msg: &$Msg $(<$T>)?) -> Self {
+                let message = Some(match msg {
+                    $Msg::Header(msg) => msg.into(),
+                    $Msg::Misbehaviour(msg) => msg.into(),
+                });
+                Self { message }
+            }
+        }
+
+        impl $(<$T: $bound>)? TryFrom<$proto::ClientMessage> for $Msg $(<$T>)? {
+            type Error = $proto::BadMessage;
+            fn try_from(
+                msg: $proto::ClientMessage,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                Self::try_from(&msg)
+            }
+        }
+
+        impl $(<$T: $bound>)? TryFrom<&$proto::ClientMessage> for $Msg $(<$T>)? {
+            type Error = $proto::BadMessage;
+            fn try_from(
+                msg: &$proto::ClientMessage,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                msg.message
+                    .as_ref()
+                    .ok_or($proto::BadMessage)
+                    .and_then(Self::try_from)
+            }
+        }
+
+
+        $crate::__private::proto_utils::define_wrapper! {
+            proto: $proto::ClientMessage,
+            wrapper: $Msg $(<$T> where $T: $bound = $concrete)?,
+            custom_any
+        }
+
+        impl $(<$T: $bound>)? $crate::__private::proto_utils::AnyConvert
+            for $Msg $(<$T>)?
+        {
+            fn to_any(&self) -> (&'static str, ::alloc::vec::Vec<u8>) {
+                use $crate::__private::proto_utils::AnyConvert;
+                match self {
+                    Self::Header(msg) => msg.to_any(),
+                    Self::Misbehaviour(msg) => msg.to_any(),
+                }
+            }
+
+            fn try_from_any(
+                type_url: &str,
+                value: &[u8],
+            ) -> ::core::result::Result<Self, $proto::DecodeError> {
+                if type_url.ends_with($proto::ClientMessage::IBC_TYPE_URL) {
+                    Self::decode(value)
+                } else if type_url.ends_with($proto::Header::IBC_TYPE_URL) {
+                    <$Header>::decode(value).map(Self::Header)
+                } else if type_url.ends_with($proto::Misbehaviour::IBC_TYPE_URL)
+                {
+                    <$Misbehaviour>::decode(value).map(Self::Misbehaviour)
+                } else {
+                    Err($proto::DecodeError::BadType)
+                }
+            }
+        }
+    };
+}
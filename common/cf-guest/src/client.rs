@@ -33,6 +33,9 @@ pub struct ClientState<PK> {
     /// Whether client is frozen.
     pub is_frozen: bool,
 
+    /// Format of fingerprints signed by the chain’s validators.
+    pub fingerprint_version: guestchain::block::FingerprintVersion,
+
     _ph: core::marker::PhantomData<PK>,
 }
 
@@ -54,10 +57,22 @@ impl<PK: guestchain::PubKey> ClientState<PK> {
             epoch_commitment,
             prev_epoch_commitment,
             is_frozen,
+            fingerprint_version: Default::default(),
             _ph: core::marker::PhantomData,
         }
     }
 
+    /// Sets format of fingerprints signed by the chain’s validators.
+    ///
+    /// Must match the version configured on the chain.  See
+    /// [`guestchain::block::FingerprintVersion`].
+    pub fn with_fingerprint_version(
+        self,
+        fingerprint_version: guestchain::block::FingerprintVersion,
+    ) -> Self {
+        Self { fingerprint_version, ..self }
+    }
+
     #[cfg(test)]
     pub fn from_genesis(genesis: &guestchain::BlockHeader) -> Self {
         let epoch_commitment = genesis.next_epoch_commitment.unwrap();
@@ -69,6 +84,7 @@ impl<PK: guestchain::PubKey> ClientState<PK> {
             epoch_commitment,
             prev_epoch_commitment,
             is_frozen: false,
+            fingerprint_version: Default::default(),
             _ph: core::marker::PhantomData,
         }
     }
@@ -111,6 +127,12 @@ impl<PK: guestchain::PubKey> From<&ClientState<PK>> for proto::ClientState {
             } else {
                 state.prev_epoch_commitment.to_vec()
             };
+        let fingerprint_domain = match &state.fingerprint_version {
+            guestchain::block::FingerprintVersion::V0 => alloc::vec::Vec::new(),
+            guestchain::block::FingerprintVersion::V1 { domain } => {
+                domain.to_vec()
+            }
+        };
         Self {
             genesis_hash: state.genesis_hash.to_vec(),
             latest_height: state.latest_height.into(),
@@ -118,6 +140,7 @@ impl<PK: guestchain::PubKey> From<&ClientState<PK>> for proto::ClientState {
             epoch_commitment: state.epoch_commitment.to_vec(),
             prev_epoch_commitment,
             is_frozen: state.is_frozen,
+            fingerprint_domain,
         }
     }
 }
@@ -143,6 +166,12 @@ impl<PK: guestchain::PubKey> TryFrom<&proto::ClientState> for ClientState<PK> {
         } else {
             make_hash(&msg.prev_epoch_commitment)?
         };
+        let fingerprint_version = if msg.fingerprint_domain.is_empty() {
+            guestchain::block::FingerprintVersion::V0
+        } else {
+            let domain = make_hash(&msg.fingerprint_domain)?;
+            guestchain::block::FingerprintVersion::V1 { domain }
+        };
         Ok(Self {
             genesis_hash,
             latest_height: msg.latest_height.into(),
@@ -150,6 +179,7 @@ impl<PK: guestchain::PubKey> TryFrom<&proto::ClientState> for ClientState<PK> {
             epoch_commitment,
             prev_epoch_commitment,
             is_frozen: msg.is_frozen,
+            fingerprint_version,
             _ph: core::marker::PhantomData,
         })
     }
@@ -193,6 +223,7 @@ fn test_decode() {
         epoch_commitment: EPOCH_COMMITMENT.to_vec(),
         prev_epoch_commitment: Default::default(),
         is_frozen: false,
+        fingerprint_domain: Default::default(),
     };
 
     let want_state = ClientState::<MockPubKey> {
@@ -202,6 +233,7 @@ fn test_decode() {
         epoch_commitment: CryptoHash::from(EPOCH_COMMITMENT),
         prev_epoch_commitment: CryptoHash::from(EPOCH_COMMITMENT),
         is_frozen: false,
+        fingerprint_version: Default::default(),
         _ph: Default::default(),
     };
 
@@ -219,3 +251,35 @@ fn test_decode() {
     .unwrap();
     assert_eq!(want_state, state);
 }
+
+#[test]
+fn test_fingerprint_version_round_trip() {
+    use guestchain::block::FingerprintVersion;
+    use guestchain::validators::MockPubKey;
+
+    let state = ClientState::<MockPubKey>::new(
+        CryptoHash::test(1),
+        2.into(),
+        3,
+        CryptoHash::test(4),
+        None,
+        false,
+    );
+    let msg = proto::ClientState::from(&state);
+    assert!(msg.fingerprint_domain.is_empty());
+    assert_eq!(Ok(state.clone()), ClientState::try_from(&msg));
+
+    let domain = CryptoHash::test(5);
+    let state =
+        state.with_fingerprint_version(FingerprintVersion::V1 { domain });
+    let msg = proto::ClientState::from(&state);
+    assert_eq!(domain.to_vec(), msg.fingerprint_domain);
+    assert_eq!(Ok(state), ClientState::try_from(&msg));
+
+    let bad =
+        proto::ClientState { fingerprint_domain: alloc::vec![1; 5], ..msg };
+    assert_eq!(
+        Err(proto::BadMessage),
+        ClientState::<MockPubKey>::try_from(&bad)
+    );
+}
@@ -1,6 +1,7 @@
 use alloc::string::ToString;
 use alloc::vec::Vec;
 
+use cf_client_core::{error, CommonContext as CoreContext};
 use guestchain::PubKey;
 
 use crate::proto::Any;
@@ -31,129 +32,21 @@ mod ibc {
 
 type Result<T = (), E = ibc::ClientError> = ::core::result::Result<T, E>;
 
-/// Representation of neiberhood of given object.
-///
-/// Used by [`CommonContext::consensus_state_neighbourhood`] method to either
-/// return element at given height or (if such element doesn’t exist) elements
-/// immediately prior and immediately after the height.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
-pub enum Neighbourhood<T> {
-    This(T),
-    Neighbours(Option<T>, Option<T>),
-}
-
-impl<T> Default for Neighbourhood<T> {
-    fn default() -> Self { Self::Neighbours(None, None) }
-}
-
-impl<T> Neighbourhood<T> {
-    /// Maps `Neighbourhood<T>` to `Neighbourhood<O>` by applying a function to
-    /// contained values (if any).
-    ///
-    /// ```
-    /// use cf_guest::Neighbourhood;
-    ///
-    /// assert_eq!(
-    ///     Neighbourhood::This(3),
-    ///     Neighbourhood::This("foo").map(<str>::len),
-    /// );
-    /// assert_eq!(
-    ///     Neighbourhood::Neighbours(None, Some(4)),
-    ///     Neighbourhood::Neighbours(None, Some("quux")).map(<str>::len),
-    /// );
-    /// ```
-    pub fn map<O>(self, mut func: impl FnMut(T) -> O) -> Neighbourhood<O> {
-        match self {
-            Self::This(it) => Neighbourhood::This(func(it)),
-            Self::Neighbours(prev, next) => Neighbourhood::Neighbours(
-                prev.map(&mut func),
-                next.map(&mut func),
-            ),
-        }
-    }
-
-    /// Maps `Neighbourhood<T>` to `Neighbourhood<O>` by applying a function to
-    /// contained values (if any).  Fails with error if conversion function
-    /// fails.
-    ///
-    /// ```
-    /// use cf_guest::Neighbourhood;
-    ///
-    /// assert_eq!(
-    ///     Result::<_, ()>::Ok(Neighbourhood::Neighbours(None, Some(4))),
-    ///     Neighbourhood::Neighbours(None, Some("quux")).try_map(|v| Ok(v.len())),
-    /// );
-    /// assert_eq!(
-    ///     Result::<Neighbourhood<usize>, _>::Err("failed"),
-    ///     Neighbourhood::Neighbours(None, Some("quux")).try_map(|_| Err("failed")),
-    /// );
-    /// ```
-    pub fn try_map<O, E>(
-        self,
-        mut func: impl FnMut(T) -> Result<O, E>,
-    ) -> Result<Neighbourhood<O>, E> {
-        match self {
-            Self::This(it) => func(it).map(Neighbourhood::This),
-            Self::Neighbours(prev, next) => Ok(Neighbourhood::Neighbours(
-                prev.map(&mut func).transpose()?,
-                next.map(&mut func).transpose()?,
-            )),
-        }
-    }
-}
+pub use cf_client_core::Neighbourhood;
 
 /// Context allowing accessing consensus states.
-pub trait CommonContext<PK: guestchain::PubKey> {
-    type ConversionError: ToString;
-    type AnyClientState: From<ClientState<PK>>;
-    type AnyConsensusState: TryInto<ConsensusState, Error = Self::ConversionError>
-        + From<ConsensusState>;
-
-    fn host_metadata(&self) -> Result<(ibc::Timestamp, ibc::Height)>;
-
-    fn set_client_state(
-        &mut self,
-        client_id: &ibc::ClientId,
-        state: Self::AnyClientState,
-    ) -> Result<()>;
-
-    fn consensus_state(
-        &self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result<Self::AnyConsensusState>;
-
-    /// Returns consensus at given height or its neighbours.
-    ///
-    /// If consensus state at given height returns `This(state)` for that state.
-    /// Otherwise, returns `Neighbours(prev, next)` where `prev` and `next` are
-    /// states with lower and greater height respectively if they exist.
-    fn consensus_state_neighbourhood(
-        &self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result<Neighbourhood<Self::AnyConsensusState>>;
-
-    fn store_consensus_state_and_metadata(
-        &mut self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-        consensus: Self::AnyConsensusState,
-        host_timestamp: ibc::Timestamp,
-        host_height: ibc::Height,
-    ) -> Result;
-
-    fn delete_consensus_state_and_metadata(
-        &mut self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result;
+///
+/// This is [`cf_client_core::CommonContext`] specialised for the guest
+/// blockchain client’s types.  It’s implemented for all types implementing
+/// that trait.
+pub trait CommonContext<PK: guestchain::PubKey>:
+    cf_client_core::CommonContext<ClientState<PK>, ConsensusState>
+{
+}
 
-    /// Returns earliest consensus state for given client.
-    fn earliest_consensus_state(
-        &self,
-        client_id: &ibc::ClientId,
-    ) -> Result<Option<(ibc::Height, Self::AnyConsensusState)>>;
+impl<PK: guestchain::PubKey, T> CommonContext<PK> for T where
+    T: cf_client_core::CommonContext<ClientState<PK>, ConsensusState>
+{
 }
 
 impl<PK: PubKey> ibc::ClientStateCommon for ClientState<PK> {
@@ -379,7 +272,7 @@ where
         }
 
         let height = ibc::Height::new(1, self.latest_height.into())?;
-        let consensus = CommonContext::consensus_state(ctx, client_id, height)
+        let consensus = CoreContext::consensus_state(ctx, client_id, height)
             .and_then(|state| state.try_into().map_err(error));
         let consensus = match consensus {
             Ok(consensus) => consensus,
@@ -394,7 +287,7 @@ where
             Err(err) => return Err(err),
         };
 
-        let (host_timestamp, _) = CommonContext::host_metadata(ctx)?;
+        let (host_timestamp, _) = CoreContext::host_metadata(ctx)?;
         Ok(if self.consensus_has_expired(&consensus, host_timestamp) {
             ibc::Status::Expired
         } else {
@@ -414,11 +307,11 @@ impl<PK: PubKey> ClientState<PK> {
         let header_height =
             ibc::Height::new(1, header.block_header.block_height.into())?;
 
-        let (host_timestamp, host_height) = CommonContext::host_metadata(ctx)?;
+        let (host_timestamp, host_height) = CoreContext::host_metadata(ctx)?;
         self.prune_oldest_consensus_state(ctx, client_id, host_timestamp)?;
 
         let maybe_existing_consensus =
-            CommonContext::consensus_state(ctx, client_id, header_height).ok();
+            CoreContext::consensus_state(ctx, client_id, header_height).ok();
         if maybe_existing_consensus.is_none() {
             let new_consensus_state = ConsensusState::from(&header);
             let new_client_state = self.with_header(&header);
@@ -494,6 +387,7 @@ impl<PK: PubKey> ClientState<PK> {
             }
             let fp = guestchain::block::Fingerprint::from_hash(
                 &header.genesis_hash,
+                &self.fingerprint_version,
                 header.block_header.block_height,
                 &header.block_hash,
             );
@@ -636,41 +530,20 @@ impl<PK: PubKey> ClientState<PK> {
         client_id: &ibc::ClientId,
         host_timestamp: ibc::Timestamp,
     ) -> Result {
-        if let Some((height, state)) =
-            ctx.earliest_consensus_state(client_id)?
-        {
-            let state = state.try_into().map_err(error)?;
-            if self.consensus_has_expired(&state, host_timestamp) {
-                ctx.delete_consensus_state_and_metadata(client_id, height)?;
-            }
-        }
-        Ok(())
+        cf_client_core::prune_oldest_consensus_state(ctx, client_id, |state| {
+            self.consensus_has_expired(state, host_timestamp)
+        })
     }
 }
 
 
-fn error(msg: impl ToString) -> ibc::ClientError {
-    ibc::ClientError::Other { description: msg.to_string() }
-}
-
-
 /// Checks client id’s client type is what’s expected and then parses the id as
 /// `ClientIdx`.
 ///
-/// Checks that client id which was used in generating the path (if any) follows
-/// `<client-type>-<counter>` format where `<counter>` is a non-empty sequence
-/// of digits.  Doesn’t check leading zeros in the counter nor whether the value
-/// is too large.
-///
-/// Expected client type is [`surpe::CLIENT_TYPE`].
+/// Expected client type is [`crate::CLIENT_TYPE`].  See
+/// [`cf_client_core::parse_client_id`] for details.
 fn parse_client_id(client_id: &ibc::ClientId) -> Result<trie_ids::ClientIdx> {
-    let (what, value) = match trie_ids::ClientIdx::parse(client_id) {
-        Ok((crate::CLIENT_TYPE, idx)) => return Ok(idx),
-        Ok((client_type, _)) => ("type", client_type),
-        Err(_) => ("id", client_id.as_str()),
-    };
-    let description = alloc::format!("invalid client {what}: {value}");
-    Err(ibc::ClientError::ClientSpecific { description })
+    cf_client_core::parse_client_id(crate::CLIENT_TYPE, client_id)
 }
 
 
@@ -12,7 +12,7 @@ mod ibc {
     pub use ibc_primitives::Timestamp;
 }
 
-use crate::{ClientMessage, CommonContext, ConsensusState, Misbehaviour};
+use crate::{ClientMessage, ConsensusState, Misbehaviour};
 
 type ClientState = crate::ClientState<MockPubKey>;
 type Header = crate::Header<MockPubKey>;
@@ -246,6 +246,7 @@ impl TestContext {
             .header;
         let fingerprint = guestchain::block::Fingerprint::new(
             &self.client_state.genesis_hash,
+            &self.client_state.fingerprint_version,
             &header,
         );
         let header = crate::Header::new(
@@ -307,7 +308,9 @@ impl guestchain::Verifier<MockPubKey> for TestContext {
     }
 }
 
-impl CommonContext<MockPubKey> for TestContext {
+impl cf_client_core::CommonContext<ClientState, ConsensusState>
+    for TestContext
+{
     type ConversionError = core::convert::Infallible;
     type AnyClientState = ClientState;
     type AnyConsensusState = ConsensusState;
@@ -0,0 +1,172 @@
+//! ICS-23 representation of the sealable trie proofs.
+//!
+//! Counterparty chains based on ibc-go expect client states to declare ICS-23
+//! proof specs describing how commitment proofs are constructed.  Nodes of the
+//! sealable trie map directly onto ICS-23 inner operations: hash of each node
+//! is `sha256(prefix || child || suffix)` where the prefix and suffix hold the
+//! node’s tag, sibling hash or Extension key.
+//!
+//! The leaf is different though.  The trie stores bare value hashes with the
+//! key committed by the path to the value rather than by the leaf.  ICS-23 has
+//! no leaf operation which doesn’t include the key so the proofs use
+//! a declarative no-op [`ics23::LeafOp`] with the existence proof’s value
+//! holding the value hash.  Consequently the proofs need to be verified with
+//! [`verify_membership`] rather than with a generic ICS-23 verifier.
+//!
+//! Non-membership proofs aren’t supported.  Extension nodes make it impossible
+//! to express a missing key as a pair of neighbouring existence proofs.
+
+use alloc::vec::Vec;
+
+use ibc_core_commitment_types::proto::ics23;
+use ibc_core_commitment_types::specs::ProofSpecs;
+use lib::hash::CryptoHash;
+use sealable_trie::proof::Membership;
+
+/// Returns the leaf operation used in the sealable trie proofs.
+///
+/// The value of an existence proof is the value hash stored in the trie and
+/// it’s used as is.
+pub fn leaf_op() -> ics23::LeafOp {
+    ics23::LeafOp {
+        hash: ics23::HashOp::NoHash.into(),
+        prehash_key: ics23::HashOp::NoHash.into(),
+        prehash_value: ics23::HashOp::NoHash.into(),
+        length: ics23::LengthOp::NoPrefix.into(),
+        prefix: Vec::new(),
+    }
+}
+
+/// Returns ICS-23 proof specification of the sealable trie.
+pub fn proof_spec() -> ics23::ProofSpec {
+    ics23::ProofSpec {
+        leaf_spec: Some(leaf_op()),
+        inner_spec: Some(ics23::InnerSpec {
+            child_order: alloc::vec![0, 1],
+            child_size: 32,
+            // Branch nodes have one-byte tag optionally followed by the left
+            // sibling’s hash.  Extension nodes have up to 36-byte key.
+            min_prefix_length: 1,
+            max_prefix_length: 36,
+            empty_child: Vec::new(),
+            hash: ics23::HashOp::Sha256.into(),
+        }),
+        max_depth: 0,
+        min_depth: 0,
+        prehash_key_before_comparison: false,
+    }
+}
+
+/// Returns proof specs to be published in the client state.
+pub fn proof_specs() -> ProofSpecs { alloc::vec![proof_spec()].into() }
+
+/// Converts membership proof of `value_hash` stored at `key` into ICS-23
+/// existence proof.
+///
+/// `key` is the trie key (see [`trie_ids::PathInfo`]) rather than IBC path.
+/// Returns `None` if the proof doesn’t match the key.
+pub fn existence_proof(
+    proof: &Membership,
+    key: &[u8],
+    value_hash: &CryptoHash,
+) -> Option<ics23::ExistenceProof> {
+    let path = proof
+        .hash_steps(key)?
+        .into_iter()
+        .map(|step| ics23::InnerOp {
+            hash: ics23::HashOp::Sha256.into(),
+            prefix: step.prefix,
+            suffix: step.suffix,
+        })
+        .collect();
+    Some(ics23::ExistenceProof {
+        key: key.to_vec(),
+        value: value_hash.to_vec(),
+        leaf: Some(leaf_op()),
+        path,
+    })
+}
+
+/// Verifies ICS-23 existence proof of `value_hash` stored at `key` in trie
+/// with given root.
+///
+/// The proof must be in the form produced by [`existence_proof`].
+pub fn verify_membership(
+    proof: &ics23::ExistenceProof,
+    root: &CryptoHash,
+    key: &[u8],
+    value_hash: &CryptoHash,
+) -> bool {
+    if proof.key != key ||
+        proof.value != value_hash.as_slice() ||
+        proof.leaf.as_ref() != Some(&leaf_op()) ||
+        proof.path.iter().any(|op| op.hash != ics23::HashOp::Sha256 as i32)
+    {
+        return false;
+    }
+    let steps = proof
+        .path
+        .iter()
+        .map(|op| (op.prefix.as_slice(), op.suffix.as_slice()));
+    let membership = match Membership::from_hash_steps(steps) {
+        Some(membership) => membership,
+        None => return false,
+    };
+    // Make sure the proof is in canonical form.  In particular, that Branch
+    // steps agree with the key on which child is ours.
+    let canonical = match membership.hash_steps(key) {
+        Some(steps) => steps,
+        None => return false,
+    };
+    let same = canonical.len() == proof.path.len() &&
+        canonical.iter().zip(proof.path.iter()).all(|(step, op)| {
+            step.prefix == op.prefix && step.suffix == op.suffix
+        });
+    same && membership.verify(root, key, value_hash)
+}
+
+#[test]
+fn test_existence_proof() {
+    use sealable_trie::nodes::RawNode;
+
+    let alloc = memory::test_utils::TestAllocator::<RawNode>::new(100);
+    let mut trie = sealable_trie::Trie::new(alloc);
+    let keys: [&[u8]; 5] = [b"foo", b"bar", b"baz", b"qux", b"quux"];
+    for (idx, key) in keys.iter().enumerate() {
+        trie.set(key, &CryptoHash::test(idx)).unwrap();
+    }
+    let root = *trie.hash();
+
+    for (idx, key) in keys.iter().enumerate() {
+        let hash = CryptoHash::test(idx);
+        let proof = match trie.prove(key).unwrap() {
+            (Some(_), sealable_trie::proof::Proof::Positive(proof)) => proof,
+            got => panic!("Unexpected proof for {key:?}: {got:?}"),
+        };
+        let proof = existence_proof(&proof, key, &hash).unwrap();
+        assert!(verify_membership(&proof, &root, key, &hash));
+
+        // Inner operations compute the root hash.
+        let got = proof.path.iter().fold(hash, |hash, op| {
+            CryptoHash::digestv(&[&op.prefix, hash.as_slice(), &op.suffix])
+        });
+        assert_eq!(root, got);
+
+        // Wrong root, key or value.
+        let other = CryptoHash::test(100);
+        assert!(!verify_membership(&proof, &other, key, &hash));
+        assert!(!verify_membership(&proof, &root, b"fox", &hash));
+        assert!(!verify_membership(&proof, &root, key, &other));
+
+        // Tampered path.
+        let mut bad = proof.clone();
+        bad.path[0].prefix[0] ^= 1;
+        assert!(!verify_membership(&bad, &root, key, &hash));
+        let mut bad = proof.clone();
+        bad.path.pop();
+        assert!(!verify_membership(&bad, &root, key, &hash));
+        let mut bad = proof;
+        bad.leaf = None;
+        assert!(!verify_membership(&bad, &root, key, &hash));
+    }
+}
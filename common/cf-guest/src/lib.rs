@@ -7,6 +7,7 @@ extern crate std;
 mod client;
 mod consensus;
 mod header;
+pub mod ics23;
 mod message;
 mod misbehaviour;
 pub mod proof;
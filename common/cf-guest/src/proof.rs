@@ -34,6 +34,31 @@ impl IbcProof {
     }
 }
 
+/// Proof of values stored at multiple paths; see [`generate_batch_for_block`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IbcBatchProof {
+    /// Serialised proof.
+    pub proof: Vec<u8>,
+    /// Commitment root.
+    pub root: CryptoHash,
+    /// Values stored at the paths (if they exist) in the order paths were
+    /// given.
+    pub values: Vec<Option<CryptoHash>>,
+}
+
+impl IbcBatchProof {
+    /// Returns commitment prefix to use during verification.
+    pub fn prefix(&self) -> ibc::CommitmentPrefix { Default::default() }
+
+    /// Returns commitment root.
+    pub fn root(&self) -> ibc::CommitmentRoot { self.root.to_vec().into() }
+
+    /// Consumes object and returns commitment proof.
+    pub fn proof(self) -> ibc::CommitmentProofBytes {
+        self.proof.try_into().unwrap()
+    }
+}
+
 
 #[derive(Clone, Debug, PartialEq, Eq, derive_more::From)]
 pub enum GenerateError {
@@ -99,11 +124,45 @@ pub fn generate_for_trie<A: sealable_trie::Allocator>(
     generate_impl((), trie, path)
 }
 
+/// Generates a proof for multiple paths at once.
+///
+/// When relayer needs to prove several values at the same height (e.g. many
+/// packet commitments on a busy channel), proofs for all of them share the
+/// block header and trie nodes near the root.  Batch proof includes those only
+/// once making it considerably smaller than separate proofs for each path.
+///
+/// # Proof format
+///
+/// The proof is Borsh-serialised `(guestchain::BlockHeader,
+/// sealable_trie::proof::Batch)` pair followed, for each path which is one of
+/// `SeqSend`, `SeqRecv` or `SeqAck` and has a value, by the two other sequence
+/// numbers as described in [`generate_for_block`].  The sequence numbers are
+/// included in the order of the paths.
+pub fn generate_batch_for_block<A: sealable_trie::Allocator>(
+    block_header: &BlockHeader,
+    trie: &sealable_trie::Trie<A>,
+    paths: impl IntoIterator<Item = ibc::path::Path>,
+) -> Result<IbcBatchProof, GenerateError> {
+    generate_batch_impl(block_header, trie, paths)
+}
+
+/// Generates a proof for multiple paths without including the Guest block
+/// header.
+///
+/// This is a batch equivalent of [`generate_for_trie`].  Otherwise this
+/// behaves the same way as [`generate_batch_for_block`].
+pub fn generate_batch_for_trie<A: sealable_trie::Allocator>(
+    trie: &sealable_trie::Trie<A>,
+    paths: impl IntoIterator<Item = ibc::path::Path>,
+) -> Result<IbcBatchProof, GenerateError> {
+    generate_batch_impl((), trie, paths)
+}
+
 trait GenerateContext: Copy {
     fn get_root(self, root: &CryptoHash) -> Result<CryptoHash, GenerateError>;
-    fn serialise_proof(
+    fn serialise_proof<T: borsh::BorshSerialize>(
         self,
-        proof: sealable_trie::proof::Proof,
+        proof: &T,
     ) -> borsh::maybestd::io::Result<Vec<u8>>;
 }
 
@@ -116,11 +175,11 @@ impl<'a> GenerateContext for &'a BlockHeader {
         }
     }
 
-    fn serialise_proof(
+    fn serialise_proof<T: borsh::BorshSerialize>(
         self,
-        proof: sealable_trie::proof::Proof,
+        proof: &T,
     ) -> borsh::maybestd::io::Result<Vec<u8>> {
-        borsh::to_vec(&(self, &proof))
+        borsh::to_vec(&(self, proof))
     }
 }
 
@@ -128,11 +187,11 @@ impl GenerateContext for () {
     fn get_root(self, root: &CryptoHash) -> Result<CryptoHash, GenerateError> {
         Ok(*root)
     }
-    fn serialise_proof(
+    fn serialise_proof<T: borsh::BorshSerialize>(
         self,
-        proof: sealable_trie::proof::Proof,
+        proof: &T,
     ) -> borsh::maybestd::io::Result<Vec<u8>> {
-        borsh::to_vec(&proof)
+        borsh::to_vec(proof)
     }
 }
 
@@ -144,18 +203,51 @@ fn generate_impl<A: sealable_trie::Allocator>(
     let root = context.get_root(trie.hash())?;
     let trie_ids::PathInfo { key, seq_kind, .. } = path.try_into()?;
     let (value, proof) = trie.prove(&key)?;
-    let mut proof = context.serialise_proof(proof).unwrap();
-
+    let mut proof = context.serialise_proof(&proof).unwrap();
     if let Some((value, seq_kind)) = value.as_ref().zip(seq_kind) {
-        proof.reserve(16);
-        for (idx, val) in value.as_array().chunks_exact(8).take(3).enumerate() {
-            if idx != seq_kind as usize {
-                proof.extend_from_slice(val);
-            }
+        append_other_sequences(&mut proof, value, seq_kind);
+    }
+    Ok(IbcProof { proof, root, value })
+}
+
+fn generate_batch_impl<A: sealable_trie::Allocator>(
+    context: impl GenerateContext,
+    trie: &sealable_trie::Trie<A>,
+    paths: impl IntoIterator<Item = ibc::path::Path>,
+) -> Result<IbcBatchProof, GenerateError> {
+    let root = context.get_root(trie.hash())?;
+    let mut values = Vec::new();
+    let mut proofs = Vec::new();
+    let mut sequences = Vec::new();
+    for path in paths {
+        let trie_ids::PathInfo { key, seq_kind, .. } = path.try_into()?;
+        let (value, proof) = trie.prove(&key)?;
+        if let Some((value, seq_kind)) = value.as_ref().zip(seq_kind) {
+            append_other_sequences(&mut sequences, value, seq_kind);
         }
+        values.push(value);
+        proofs.push(proof);
     }
 
-    Ok(IbcProof { proof, root, value })
+    let batch = sealable_trie::proof::Batch::new(proofs);
+    let mut proof = context.serialise_proof(&batch).unwrap();
+    proof.extend_from_slice(&sequences);
+    Ok(IbcBatchProof { proof, root, values })
+}
+
+/// Appends to the proof sequence numbers other than `seq_kind` stored in
+/// `value`; see [`generate_for_block`].
+fn append_other_sequences(
+    proof: &mut Vec<u8>,
+    value: &CryptoHash,
+    seq_kind: trie_ids::path_info::SequenceKind,
+) {
+    proof.reserve(16);
+    for (idx, val) in value.as_array().chunks_exact(8).take(3).enumerate() {
+        if idx != seq_kind as usize {
+            proof.extend_from_slice(val);
+        }
+    }
 }
 
 
@@ -252,6 +344,35 @@ pub fn verify_for_trie(
     verify_impl::<false>(prefix, proof_bytes, root, path, value)
 }
 
+/// Verifies a batch proof for multiple entries or lack of entries.
+///
+/// `prefix` must be empty, `proof` and `root` must follow format described in
+/// [`generate_batch_for_block`] function.  `entries` lists IBC paths and
+/// values (or lack thereof) expected at them in the same order as paths
+/// the proof has been generated for.  See [`verify_for_block`] for description
+/// how values are hashed.
+pub fn verify_batch_for_block<'a>(
+    prefix: &[u8],
+    proof_bytes: &[u8],
+    root: &[u8],
+    entries: impl IntoIterator<Item = (ibc::path::Path, Option<&'a [u8]>)>,
+) -> Result<(), VerifyError> {
+    verify_batch_impl::<true>(prefix, proof_bytes, root, entries)
+}
+
+/// Verifies a batch proof without a block header.
+///
+/// This is a batch equivalent of [`verify_for_trie`].  Otherwise this behaves
+/// the same way as [`verify_batch_for_block`].
+pub fn verify_batch_for_trie<'a>(
+    prefix: &[u8],
+    proof_bytes: &[u8],
+    root: &[u8],
+    entries: impl IntoIterator<Item = (ibc::path::Path, Option<&'a [u8]>)>,
+) -> Result<(), VerifyError> {
+    verify_batch_impl::<false>(prefix, proof_bytes, root, entries)
+}
+
 fn verify_impl<const WITH_BLOCK: bool>(
     prefix: &[u8],
     mut proof_bytes: &[u8],
@@ -259,82 +380,154 @@ fn verify_impl<const WITH_BLOCK: bool>(
     path: ibc::path::Path,
     value: Option<&[u8]>,
 ) -> Result<(), VerifyError> {
+    let root = check_prefix_and_root(prefix, root)?;
+    let (path, is_packet_commitment) = parse_path(path)?;
+    let (state_root, proof) = decode_proof::<
+        WITH_BLOCK,
+        sealable_trie::proof::Proof,
+    >(&mut proof_bytes, root)?;
+    let value =
+        value_hash(&path, is_packet_commitment, value, &mut proof_bytes)?;
+
+    if !proof_bytes.is_empty() {
+        Err(VerifyError::ProofDecodingFailure("Spurious bytes".into()))
+    } else if proof.verify(&state_root, &path.key, value.as_ref()) {
+        Ok(())
+    } else {
+        Err(VerifyError::VerificationFailed)
+    }
+}
+
+fn verify_batch_impl<'a, const WITH_BLOCK: bool>(
+    prefix: &[u8],
+    mut proof_bytes: &[u8],
+    root: &[u8],
+    entries: impl IntoIterator<Item = (ibc::path::Path, Option<&'a [u8]>)>,
+) -> Result<(), VerifyError> {
+    let root = check_prefix_and_root(prefix, root)?;
+    let (state_root, batch) = decode_proof::<
+        WITH_BLOCK,
+        sealable_trie::proof::Batch,
+    >(&mut proof_bytes, root)?;
+
+    let mut proofs = batch.into_proofs().into_iter();
+    let mut ok = true;
+    for (path, value) in entries {
+        let (path, is_packet_commitment) = parse_path(path)?;
+        let value =
+            value_hash(&path, is_packet_commitment, value, &mut proof_bytes)?;
+        let proof = proofs.next().ok_or_else(|| {
+            VerifyError::ProofDecodingFailure("Missing proofs".into())
+        })?;
+        ok = ok && proof.verify(&state_root, &path.key, value.as_ref());
+    }
+
+    if proofs.next().is_some() {
+        Err(VerifyError::ProofDecodingFailure("Spurious proofs".into()))
+    } else if !proof_bytes.is_empty() {
+        Err(VerifyError::ProofDecodingFailure("Spurious bytes".into()))
+    } else if ok {
+        Ok(())
+    } else {
+        Err(VerifyError::VerificationFailed)
+    }
+}
+
+/// Checks that prefix is empty and root is a 32-byte hash.
+fn check_prefix_and_root<'a>(
+    prefix: &[u8],
+    root: &'a [u8],
+) -> Result<&'a CryptoHash, VerifyError> {
     if !prefix.is_empty() {
         return Err(VerifyError::BadPrefix);
     }
-    let root =
-        <&CryptoHash>::try_from(root).map_err(|_| VerifyError::BadRoot)?;
+    <&CryptoHash>::try_from(root).map_err(|_| VerifyError::BadRoot)
+}
+
+/// Converts IBC path into trie path and checks whether it’s a path of
+/// a packet commitment.
+fn parse_path(
+    path: ibc::path::Path,
+) -> Result<(trie_ids::PathInfo, bool), VerifyError> {
     let is_packet_commitment = matches!(
         path,
         ibc::path::Path::Commitment(_) |
             ibc::path::Path::Receipt(_) |
             ibc::path::Path::Ack(_)
     );
-    let path = trie_ids::PathInfo::try_from(path)?;
+    Ok((trie_ids::PathInfo::try_from(path)?, is_packet_commitment))
+}
 
-    let (state_root, proof) = if WITH_BLOCK {
-        let (header, proof): (BlockHeader, sealable_trie::proof::Proof) =
-            borsh::BorshDeserialize::deserialize_reader(&mut proof_bytes)?;
+/// Decodes the proof object and returns it together with the state root.
+///
+/// If `WITH_BLOCK` is set, the proof is preceded by a block header which must
+/// match the `root` commitment.  Otherwise, `root` is the state root.
+fn decode_proof<const WITH_BLOCK: bool, T: borsh::BorshDeserialize>(
+    proof_bytes: &mut &[u8],
+    root: &CryptoHash,
+) -> Result<(CryptoHash, T), VerifyError> {
+    if WITH_BLOCK {
+        let (header, proof): (BlockHeader, T) =
+            borsh::BorshDeserialize::deserialize_reader(proof_bytes)?;
         if root != &header.calc_hash() {
             return Err(VerifyError::BadBlock);
         }
-        (header.state_root, proof)
+        Ok((header.state_root, proof))
     } else {
-        let proof: sealable_trie::proof::Proof =
-            borsh::BorshDeserialize::deserialize_reader(&mut proof_bytes)?;
-        (*root, proof)
-    };
+        Ok((*root, T::deserialize_reader(proof_bytes)?))
+    }
+}
 
-    let value = if let Some(value) = value {
-        Some(if let Some(seq_kind) = path.seq_kind {
-            debug_assert!(path.client_id.is_none());
-            // If path.seq_kind is set, `value` must be encoded
-            // `google.protobuf.UInt64Value` holding the sequence number.
-            let seq = <u64 as prost::Message>::decode(value)?.to_be_bytes();
-
-            // Proof is followed by two more sequence numbers this time in
-            // big-endian.  We’re keeping sequence numbers together and we
-            // need all of them to figure out the hash kept in the trie.
-            let (head, tail) = stdx::split_at::<16, u8>(proof_bytes)
-                .ok_or_else(|| {
-                    VerifyError::ProofDecodingFailure(
-                        "Missing sequences".into(),
-                    )
-                })?;
-            let (a, b) = stdx::split_array_ref(head);
-            proof_bytes = tail;
-
-            let hash = match seq_kind as u8 {
-                0 => [seq, *a, *b, [0u8; 8]],
-                1 => [*a, seq, *b, [0u8; 8]],
-                2 => [*a, *b, seq, [0u8; 8]],
-                _ => unreachable!(),
-            };
-            CryptoHash(bytemuck::must_cast(hash))
-        } else if let Some(id) = path.client_id.as_ref() {
-            // If path includes client id, hash stored in the trie is calculated
-            // with the id mixed in.
-            super::digest_with_client_id(id, value)
-        } else if is_packet_commitment {
-            // If this is packet commitment than the value is already a hash and
-            // we don’t hash it again.
-            CryptoHash::try_from(value)
-                .map_err(|_| VerifyError::BadPacketCommitment)?
-        } else {
-            // Otherwise, simply hash the value.
-            CryptoHash::digest(value)
-        })
-    } else {
-        None
+/// Calculates hash of the value stored in the trie at given path.
+///
+/// For sequence paths, consumes the other two sequence numbers from
+/// `proof_bytes`.
+fn value_hash(
+    path: &trie_ids::PathInfo,
+    is_packet_commitment: bool,
+    value: Option<&[u8]>,
+    proof_bytes: &mut &[u8],
+) -> Result<Option<CryptoHash>, VerifyError> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
     };
-
-    if !proof_bytes.is_empty() {
-        Err(VerifyError::ProofDecodingFailure("Spurious bytes".into()))
-    } else if proof.verify(&state_root, &path.key, value.as_ref()) {
-        Ok(())
+    Ok(Some(if let Some(seq_kind) = path.seq_kind {
+        debug_assert!(path.client_id.is_none());
+        // If path.seq_kind is set, `value` must be encoded
+        // `google.protobuf.UInt64Value` holding the sequence number.
+        let seq = <u64 as prost::Message>::decode(value)?.to_be_bytes();
+
+        // Proof is followed by two more sequence numbers this time in
+        // big-endian.  We’re keeping sequence numbers together and we
+        // need all of them to figure out the hash kept in the trie.
+        let (head, tail) =
+            stdx::split_at::<16, u8>(*proof_bytes).ok_or_else(|| {
+                VerifyError::ProofDecodingFailure("Missing sequences".into())
+            })?;
+        let (a, b) = stdx::split_array_ref(head);
+        *proof_bytes = tail;
+
+        let hash = match seq_kind as u8 {
+            0 => [seq, *a, *b, [0u8; 8]],
+            1 => [*a, seq, *b, [0u8; 8]],
+            2 => [*a, *b, seq, [0u8; 8]],
+            _ => unreachable!(),
+        };
+        CryptoHash(bytemuck::must_cast(hash))
+    } else if let Some(id) = path.client_id.as_ref() {
+        // If path includes client id, hash stored in the trie is calculated
+        // with the id mixed in.
+        super::digest_with_client_id(id, value)
+    } else if is_packet_commitment {
+        // If this is packet commitment than the value is already a hash and
+        // we don’t hash it again.
+        CryptoHash::try_from(value)
+            .map_err(|_| VerifyError::BadPacketCommitment)?
     } else {
-        Err(VerifyError::VerificationFailed)
-    }
+        // Otherwise, simply hash the value.
+        CryptoHash::digest(value)
+    }))
 }
 
 
@@ -672,9 +865,137 @@ mod tests {
         }; raw hash);
     }
 
+    fn do_test_batch_proofs(for_block: bool) {
+        let trie = sealable_trie::Trie::new(
+            memory::test_utils::TestAllocator::new(100),
+        );
+        let mut trie = Trie {
+            header: BlockHeader::generate_genesis(
+                guestchain::BlockHeight::from(0),
+                guestchain::HostHeight::from(42),
+                core::num::NonZeroU64::new(24).unwrap(),
+                *trie.hash(),
+                CryptoHash::test(86),
+            ),
+            trie,
+        };
+
+        let port_id = identifiers::PortId::transfer();
+        let channel_id = identifiers::ChannelId::new(5);
+        let commitment = |seq: u64| -> ibc::path::Path {
+            ibc::path::CommitmentPath {
+                port_id: port_id.clone(),
+                channel_id: channel_id.clone(),
+                sequence: identifiers::Sequence::from(seq),
+            }
+            .into()
+        };
+        let seq_path: ibc::path::Path =
+            ibc::path::SeqRecvPath(port_id.clone(), channel_id.clone()).into();
+
+        let hashes = [CryptoHash::test(1), CryptoHash::test(2)];
+        for (seq, hash) in (1..).zip(hashes.iter()) {
+            let key =
+                trie_ids::PathInfo::try_from(commitment(seq)).unwrap().key;
+            trie.set(&key, *hash);
+        }
+        let seq_value = prost::Message::encode_to_vec(&20u64);
+        let seq_hash = CryptoHash(bytemuck::must_cast([
+            [0u8; 8],
+            20u64.to_be_bytes(),
+            [0u8; 8],
+            [0u8; 8],
+        ]));
+        let key = trie_ids::PathInfo::try_from(seq_path.clone()).unwrap().key;
+        trie.set(&key, seq_hash);
+
+        let paths = [commitment(1), commitment(2), seq_path, commitment(3)];
+        let values = [
+            Some(hashes[0].as_slice()),
+            Some(hashes[1].as_slice()),
+            Some(seq_value.as_slice()),
+            None,
+        ];
+        let entries = || paths.iter().cloned().zip(values.iter().copied());
+
+        let proof = if for_block {
+            generate_batch_for_block(&trie.header, &trie.trie, paths.clone())
+        } else {
+            generate_batch_for_trie(&trie.trie, paths.clone())
+        }
+        .unwrap();
+        assert_eq!(
+            alloc::vec![Some(hashes[0]), Some(hashes[1]), Some(seq_hash), None],
+            proof.values
+        );
+
+        let verify =
+            |proof_bytes: &[u8],
+             entries: Vec<(ibc::path::Path, Option<&[u8]>)>| {
+                if for_block {
+                    verify_batch_for_block(
+                        &[],
+                        proof_bytes,
+                        proof.root.as_slice(),
+                        entries,
+                    )
+                } else {
+                    verify_batch_for_trie(
+                        &[],
+                        proof_bytes,
+                        proof.root.as_slice(),
+                        entries,
+                    )
+                }
+            };
+        verify(&proof.proof, entries().collect::<Vec<_>>()).unwrap();
+
+        // Batch proof is smaller than individual proofs combined.
+        let total_len: usize = paths
+            .iter()
+            .map(|path| generate(for_block, &trie, path).unwrap().proof.len())
+            .sum();
+        assert!(proof.proof.len() < total_len);
+
+        // Wrong value.
+        let mut bad = entries().collect::<Vec<_>>();
+        bad[1].1 = Some(hashes[0].as_slice());
+        assert_eq!(
+            Err(VerifyError::VerificationFailed),
+            verify(&proof.proof, bad)
+        );
+
+        // Wrong number of entries.
+        assert_eq!(
+            Err(VerifyError::ProofDecodingFailure("Spurious proofs".into())),
+            verify(&proof.proof, entries().take(3).collect())
+        );
+        let mut more = entries().collect::<Vec<_>>();
+        more.push((commitment(4), None));
+        assert_eq!(
+            Err(VerifyError::ProofDecodingFailure("Missing proofs".into())),
+            verify(&proof.proof, more)
+        );
+
+        // Spurious bytes at the end of the proof.
+        assert_eq!(
+            Err(VerifyError::ProofDecodingFailure("Spurious bytes".into())),
+            verify(
+                &[proof.proof.as_slice(), b"\0"].concat(),
+                entries().collect()
+            )
+        );
+    }
+
     #[test]
     fn test_proofs_for_block() { do_test_proofs(true) }
 
     #[test]
     fn test_proofs_for_trie() { do_test_proofs(false) }
+
+    #[test]
+    fn test_batch_proofs_for_block() { do_test_batch_proofs(true) }
+
+    #[test]
+    fn test_batch_proofs_for_trie() { do_test_batch_proofs(false) }
 }
@@ -22,6 +22,7 @@ define_proto!(ClientState; test_client_state; Self {
     prev_epoch_commitment: lib::hash::CryptoHash::test(12).to_vec(),
     is_frozen: false,
     trusting_period_ns: 30 * 24 * 3600 * 1_000_000_000,
+    fingerprint_domain: alloc::vec::Vec::new(),
 });
 
 define_proto!(ConsensusState; test_consensus_state; {
@@ -0,0 +1,280 @@
+//! Off-chain helper for assembling [`Header`] from data returned by Solana RPC
+//! and the Witnessed Trie Geyser plugin.
+//!
+//! Relayers collect pieces of the header from different sources: block hashes
+//! and signature count come from `getBlock` RPC call while the accounts delta
+//! hash and the witness account come from the Geyser plugin.  [`HeaderBuilder`]
+//! gathers those pieces, assembles Merkle proof of the witness account if
+//! necessary and verifies that everything is consistent before producing
+//! a header which can be submitted in `MsgUpdateClient`.
+
+use alloc::vec::Vec;
+use core::num::NonZeroU64;
+
+use lib::hash::CryptoHash;
+
+use crate::proof::{AccountHashData, AccountProof, DeltaHashProof};
+use crate::types::PubKey;
+use crate::Header;
+
+/// Error building a [`Header`].
+#[derive(Clone, Debug, PartialEq, Eq, derive_more::Display)]
+pub enum BuildError {
+    /// Slot number is zero.
+    #[display(fmt = "slot number must be non-zero")]
+    ZeroSlot,
+
+    /// Block hash returned by RPC is not a valid base58-encoded hash.
+    #[display(fmt = "invalid block hash: {}", _0)]
+    BadBlockhash(alloc::string::String),
+
+    /// A required piece of data hasn’t been provided.
+    #[display(fmt = "missing {}", _0)]
+    Missing(&'static str),
+
+    /// Witness account is not among accounts modified in the slot.
+    #[display(fmt = "witness account not found in the slot’s accounts")]
+    WitnessNotFound,
+
+    /// Accounts delta hash calculated from the witness proof doesn’t match
+    /// the hash reported for the slot.
+    #[display(fmt = "accounts delta hash mismatch: got {}, want {}", _0, _1)]
+    DeltaHashMismatch(CryptoHash, CryptoHash),
+
+    /// Bank hash calculated from the delta hash proof doesn’t match expected
+    /// bank hash.
+    #[display(fmt = "bank hash mismatch: got {}, want {}", _0, _1)]
+    BankHashMismatch(CryptoHash, CryptoHash),
+}
+
+impl std::error::Error for BuildError {}
+
+/// Builder of the [`Header`].
+///
+/// At the very least, the builder needs block information (see
+/// [`Self::block`] and [`Self::rpc_block`]) and witness account proof (see
+/// [`Self::witness_proof`] and [`Self::witness_account`]).  Alternatively,
+/// the entire [`DeltaHashProof`] as reported by the Geyser plugin can be set
+/// with [`Self::delta_hash_proof`].
+#[derive(Clone, Debug, Default)]
+pub struct HeaderBuilder {
+    slot: u64,
+    parent_blockhash: Option<CryptoHash>,
+    blockhash: Option<CryptoHash>,
+    num_sigs: u64,
+    accounts_delta_hash: Option<CryptoHash>,
+    epoch_accounts_hash: Option<CryptoHash>,
+    witness: Option<Witness>,
+    bank_hash: Option<CryptoHash>,
+}
+
+#[derive(Clone, Debug)]
+enum Witness {
+    Proof(AccountProof),
+    Account(AccountHashData, Vec<(PubKey, CryptoHash)>),
+}
+
+impl HeaderBuilder {
+    /// Creates a new builder for header at given slot.
+    pub fn new(slot: u64) -> Self { Self { slot, ..Default::default() } }
+
+    /// Sets block information.
+    ///
+    /// `num_sigs` is the total number of signatures of all transactions in the
+    /// block.
+    pub fn block(
+        mut self,
+        parent_blockhash: CryptoHash,
+        blockhash: CryptoHash,
+        num_sigs: u64,
+    ) -> Self {
+        self.parent_blockhash = Some(parent_blockhash);
+        self.blockhash = Some(blockhash);
+        self.num_sigs = num_sigs;
+        self
+    }
+
+    /// Sets block information from fields of a `getBlock` RPC response.
+    ///
+    /// `previous_blockhash` and `blockhash` are base58-encoded hashes as
+    /// returned by the RPC while `signatures` is an iterator over number of
+    /// signatures of each transaction in the block.
+    pub fn rpc_block(
+        self,
+        previous_blockhash: &str,
+        blockhash: &str,
+        signatures: impl IntoIterator<Item = usize>,
+    ) -> Result<Self, BuildError> {
+        let parent_blockhash = parse_bs58_hash(previous_blockhash)?;
+        let blockhash = parse_bs58_hash(blockhash)?;
+        let num_sigs = signatures.into_iter().sum::<usize>() as u64;
+        Ok(self.block(parent_blockhash, blockhash, num_sigs))
+    }
+
+    /// Sets the entire delta hash proof, e.g. as reported by the Geyser
+    /// plugin.
+    ///
+    /// Overrides any block information previously set.
+    pub fn delta_hash_proof(mut self, proof: DeltaHashProof) -> Self {
+        self.parent_blockhash = Some(proof.parent_blockhash);
+        self.blockhash = Some(proof.blockhash);
+        self.num_sigs = proof.num_sigs;
+        self.accounts_delta_hash = Some(proof.accounts_delta_hash);
+        self.epoch_accounts_hash = proof.epoch_accounts_hash;
+        self
+    }
+
+    /// Sets accounts delta hash of the slot.
+    ///
+    /// If not set, the delta hash is taken to be the root of the witness
+    /// proof.  Setting it allows the builder to verify the witness proof.
+    pub fn accounts_delta_hash(mut self, hash: CryptoHash) -> Self {
+        self.accounts_delta_hash = Some(hash);
+        self
+    }
+
+    /// Sets epoch accounts hash for slots which include it in bank hash.
+    pub fn epoch_accounts_hash(mut self, hash: Option<CryptoHash>) -> Self {
+        self.epoch_accounts_hash = hash;
+        self
+    }
+
+    /// Sets an already assembled proof of the witness account.
+    pub fn witness_proof(mut self, proof: AccountProof) -> Self {
+        self.witness = Some(Witness::Proof(proof));
+        self
+    }
+
+    /// Sets the witness account and hashes of all accounts modified in the
+    /// slot from which the Merkle proof of the witness is assembled.
+    ///
+    /// `accounts` doesn’t need to be sorted.
+    pub fn witness_account(
+        mut self,
+        witness: AccountHashData,
+        accounts: Vec<(PubKey, CryptoHash)>,
+    ) -> Self {
+        self.witness = Some(Witness::Account(witness, accounts));
+        self
+    }
+
+    /// Sets bank hash the calculated hash is verified against.
+    pub fn expected_bank_hash(mut self, hash: CryptoHash) -> Self {
+        self.bank_hash = Some(hash);
+        self
+    }
+
+    /// Assembles and verifies the header.
+    pub fn build(self) -> Result<Header, BuildError> {
+        let slot = NonZeroU64::new(self.slot).ok_or(BuildError::ZeroSlot)?;
+        let parent_blockhash =
+            self.parent_blockhash.ok_or(BuildError::Missing("block"))?;
+        let blockhash = self.blockhash.ok_or(BuildError::Missing("block"))?;
+
+        let witness_proof = match self.witness {
+            None => return Err(BuildError::Missing("witness")),
+            Some(Witness::Proof(proof)) => proof,
+            Some(Witness::Account(witness, mut accounts)) => {
+                witness
+                    .generate_proof(&mut accounts)
+                    .ok_or(BuildError::WitnessNotFound)?
+                    .1
+            }
+        };
+
+        let root = witness_proof.expected_root();
+        let accounts_delta_hash = match self.accounts_delta_hash {
+            Some(hash) if hash != root => {
+                return Err(BuildError::DeltaHashMismatch(root, hash))
+            }
+            _ => root,
+        };
+
+        let delta_hash_proof = DeltaHashProof {
+            parent_blockhash,
+            accounts_delta_hash,
+            num_sigs: self.num_sigs,
+            blockhash,
+            epoch_accounts_hash: self.epoch_accounts_hash,
+        };
+        let bank_hash = delta_hash_proof.calculate_bank_hash();
+        if let Some(want) = self.bank_hash {
+            if want != bank_hash {
+                return Err(BuildError::BankHashMismatch(bank_hash, want));
+            }
+        }
+
+        Ok(Header { slot, bank_hash, delta_hash_proof, witness_proof })
+    }
+}
+
+fn parse_bs58_hash(hash: &str) -> Result<CryptoHash, BuildError> {
+    let mut buf = [0; 32];
+    match bs58::decode(hash).onto(&mut buf[..]) {
+        Ok(32) => Ok(CryptoHash(buf)),
+        _ => Err(BuildError::BadBlockhash(hash.into())),
+    }
+}
+
+
+#[test]
+fn test_build() {
+    use alloc::string::{String, ToString};
+
+    let encode = |hash: &CryptoHash| {
+        let mut buf = [0; 45];
+        let len = bs58::encode(hash.as_slice()).onto(&mut buf[..]).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    };
+    let want = Header::test(&[1; 40]);
+    let witness = want.witness_proof.account_hash_data.clone();
+    let accounts = alloc::vec![
+        ([15; 32].into(), [69; 32].into()),
+        ([10; 32].into(), witness.calculate_hash()),
+        ([7; 32].into(), [42; 32].into()),
+    ];
+    let proof = &want.delta_hash_proof;
+
+    let builder = HeaderBuilder::new(want.slot.get())
+        .rpc_block(
+            &encode(&proof.parent_blockhash),
+            &encode(&proof.blockhash),
+            [400, 20],
+        )
+        .unwrap()
+        .accounts_delta_hash(proof.accounts_delta_hash)
+        .expected_bank_hash(want.bank_hash);
+
+    let got = builder
+        .clone()
+        .witness_account(witness.clone(), accounts.clone())
+        .build();
+    assert_eq!(Ok(&want), got.as_ref());
+    let got = builder.clone().witness_proof(want.witness_proof.clone()).build();
+    assert_eq!(Ok(&want), got.as_ref());
+    let got = HeaderBuilder::new(want.slot.get())
+        .delta_hash_proof(proof.clone())
+        .witness_proof(want.witness_proof.clone())
+        .build();
+    assert_eq!(Ok(&want), got.as_ref());
+
+    let got = builder
+        .clone()
+        .witness_account(witness.clone(), accounts[..1].to_vec())
+        .build();
+    assert_eq!(Err(BuildError::WitnessNotFound), got);
+    let got = builder
+        .clone()
+        .expected_bank_hash(CryptoHash::default())
+        .witness_proof(want.witness_proof.clone())
+        .build();
+    assert!(matches!(got, Err(BuildError::BankHashMismatch(..))));
+    let got = builder
+        .accounts_delta_hash(CryptoHash::default())
+        .witness_proof(want.witness_proof.clone())
+        .build();
+    assert!(matches!(got, Err(BuildError::DeltaHashMismatch(..))));
+
+    let got = HeaderBuilder::new(1).rpc_block("bogus!", "", []).unwrap_err();
+    assert_eq!("invalid block hash: bogus!", got.to_string());
+}
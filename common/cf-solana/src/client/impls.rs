@@ -2,6 +2,7 @@ use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::num::NonZeroU64;
 
+use cf_client_core::{error, CommonContext as CoreContext};
 use ibc_core_client_context::consensus_state::ConsensusState as _;
 
 use crate::proto::Any;
@@ -33,60 +34,20 @@ use ibc::ClientStateCommon;
 
 type Result<T = (), E = ibc::ClientError> = ::core::result::Result<T, E>;
 
-pub use cf_guest::Neighbourhood;
+pub use cf_client_core::Neighbourhood;
 
 /// Context allowing accessing consensus states.
-pub trait CommonContext {
-    type ConversionError: ToString;
-    type AnyClientState: From<ClientState>;
-    type AnyConsensusState: TryInto<ConsensusState, Error = Self::ConversionError>
-        + From<ConsensusState>;
-
-    fn host_metadata(&self) -> Result<(ibc::Timestamp, ibc::Height)>;
-
-    fn set_client_state(
-        &mut self,
-        client_id: &ibc::ClientId,
-        state: Self::AnyClientState,
-    ) -> Result<()>;
-
-    fn consensus_state(
-        &self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result<Self::AnyConsensusState>;
-
-    /// Returns consensus at given height or its neighbours.
-    ///
-    /// If consensus state at given height returns `This(state)` for that state.
-    /// Otherwise, returns `Neighbours(prev, next)` where `prev` and `next` are
-    /// states with lower and greater height respectively if they exist.
-    fn consensus_state_neighbourhood(
-        &self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result<Neighbourhood<Self::AnyConsensusState>>;
-
-    fn store_consensus_state_and_metadata(
-        &mut self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-        consensus: Self::AnyConsensusState,
-        host_timestamp: ibc::Timestamp,
-        host_height: ibc::Height,
-    ) -> Result;
-
-    fn delete_consensus_state_and_metadata(
-        &mut self,
-        client_id: &ibc::ClientId,
-        height: ibc::Height,
-    ) -> Result;
+///
+/// This is [`cf_client_core::CommonContext`] specialised for the Solana
+/// client’s types.  It’s implemented for all types implementing that trait.
+pub trait CommonContext:
+    cf_client_core::CommonContext<ClientState, ConsensusState>
+{
+}
 
-    /// Returns earliest consensus state for given client.
-    fn earliest_consensus_state(
-        &self,
-        client_id: &ibc::ClientId,
-    ) -> Result<Option<(ibc::Height, Self::AnyConsensusState)>>;
+impl<T> CommonContext for T where
+    T: cf_client_core::CommonContext<ClientState, ConsensusState>
+{
 }
 
 impl ibc::ClientStateCommon for ClientState {
@@ -287,7 +248,7 @@ where
         }
 
         let height = self.latest_height();
-        let consensus = CommonContext::consensus_state(ctx, client_id, height)
+        let consensus = CoreContext::consensus_state(ctx, client_id, height)
             .and_then(|state| state.try_into().map_err(error));
         let consensus = match consensus {
             Ok(consensus) => consensus,
@@ -302,7 +263,7 @@ where
             Err(err) => return Err(err),
         };
 
-        let (host_timestamp, _) = CommonContext::host_metadata(ctx)?;
+        let (host_timestamp, _) = CoreContext::host_metadata(ctx)?;
         Ok(if self.consensus_has_expired(&consensus, host_timestamp) {
             ibc::Status::Expired
         } else {
@@ -319,11 +280,11 @@ impl ClientState {
         header: Header,
     ) -> Result<Vec<ibc::Height>> {
         let header_height = header.ibc_height();
-        let (host_timestamp, host_height) = CommonContext::host_metadata(ctx)?;
+        let (host_timestamp, host_height) = CoreContext::host_metadata(ctx)?;
         self.prune_oldest_consensus_state(ctx, client_id, host_timestamp)?;
 
         let maybe_existing_consensus =
-            CommonContext::consensus_state(ctx, client_id, header_height).ok();
+            CoreContext::consensus_state(ctx, client_id, header_height).ok();
         if maybe_existing_consensus.is_none() {
             let new_consensus_state = ConsensusState::try_from(&header)?;
             let new_client_state = self.with_header(&header);
@@ -498,39 +459,19 @@ impl ClientState {
         client_id: &ibc::ClientId,
         host_timestamp: ibc::Timestamp,
     ) -> Result {
-        if let Some((height, state)) =
-            ctx.earliest_consensus_state(client_id)?
-        {
-            let state = state.try_into().map_err(error)?;
-            if self.consensus_has_expired(&state, host_timestamp) {
-                ctx.delete_consensus_state_and_metadata(client_id, height)?;
-            }
-        }
-        Ok(())
+        cf_client_core::prune_oldest_consensus_state(ctx, client_id, |state| {
+            self.consensus_has_expired(state, host_timestamp)
+        })
     }
 }
 
-fn error(msg: impl ToString) -> ibc::ClientError {
-    ibc::ClientError::Other { description: msg.to_string() }
-}
-
 /// Checks client id’s client type is what’s expected and then parses the id as
 /// `ClientIdx`.
 ///
-/// Checks that client id which was used in generating the path (if any) follows
-/// `<client-type>-<counter>` format where `<counter>` is a non-empty sequence
-/// of digits.  Doesn’t check leading zeros in the counter nor whether the value
-/// is too large.
-///
-/// Expected client type is [`surpe::CLIENT_TYPE`].
+/// Expected client type is [`crate::CLIENT_TYPE`].  See
+/// [`cf_client_core::parse_client_id`] for details.
 fn parse_client_id(client_id: &ibc::ClientId) -> Result<trie_ids::ClientIdx> {
-    let (what, value) = match trie_ids::ClientIdx::parse(client_id) {
-        Ok((crate::CLIENT_TYPE, idx)) => return Ok(idx),
-        Ok((client_type, _)) => ("type", client_type),
-        Err(_) => ("id", client_id.as_str()),
-    };
-    let description = alloc::format!("invalid client {what}: {value}");
-    Err(ibc::ClientError::ClientSpecific { description })
+    cf_client_core::parse_client_id(crate::CLIENT_TYPE, client_id)
 }
 
 #[test]
@@ -5,11 +5,14 @@ extern crate alloc;
 extern crate std;
 
 mod blake3;
+#[cfg(any(feature = "std", test))]
+pub mod builder;
 mod client;
 mod consensus;
 mod header;
 mod message;
 mod misbehaviour;
+mod params;
 pub mod proof;
 pub mod proto;
 #[cfg(feature = "serde")]
@@ -22,6 +25,10 @@ pub use consensus::ConsensusState;
 pub use header::Header;
 pub use message::ClientMessage;
 pub use misbehaviour::Misbehaviour;
+pub use params::{
+    MsgUpdateClientParams, UpdateClientParamsError, MAX_TRUSTING_PERIOD_NS,
+    MIN_TRUSTING_PERIOD_NS,
+};
 pub use proof::IbcProof;
 
 /// Client type of the Solana blockchain’s light client.
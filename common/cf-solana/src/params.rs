@@ -0,0 +1,108 @@
+use crate::{proto, ClientState};
+
+/// Smallest trusting period which can be set with [`MsgUpdateClientParams`].
+pub const MIN_TRUSTING_PERIOD_NS: u64 = 3600 * 1_000_000_000;
+
+/// Largest trusting period which can be set with [`MsgUpdateClientParams`].
+pub const MAX_TRUSTING_PERIOD_NS: u64 = 365 * 24 * 3600 * 1_000_000_000;
+
+/// Governance request adjusting parameters of an existing client.
+///
+/// Authorising the request is up to the host.  The light client only checks
+/// that the new values are within the bounds.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MsgUpdateClientParams {
+    /// New duration of the trusting period.
+    pub trusting_period_ns: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, derive_more::Display)]
+pub enum UpdateClientParamsError {
+    /// The client is frozen and its parameters cannot be changed.
+    ClientFrozen,
+    /// The trusting period is outside of the
+    /// [`MIN_TRUSTING_PERIOD_NS`]..=[`MAX_TRUSTING_PERIOD_NS`] range.
+    TrustingPeriodOutOfBounds,
+}
+
+impl ClientState {
+    /// Returns a copy of the client state with parameters changed as
+    /// requested by the message.
+    pub fn with_params(
+        &self,
+        msg: &MsgUpdateClientParams,
+    ) -> Result<Self, UpdateClientParamsError> {
+        if self.is_frozen {
+            return Err(UpdateClientParamsError::ClientFrozen);
+        }
+        if !(MIN_TRUSTING_PERIOD_NS..=MAX_TRUSTING_PERIOD_NS)
+            .contains(&msg.trusting_period_ns)
+        {
+            return Err(UpdateClientParamsError::TrustingPeriodOutOfBounds);
+        }
+        Ok(Self { trusting_period_ns: msg.trusting_period_ns, ..self.clone() })
+    }
+}
+
+impl From<MsgUpdateClientParams> for proto::MsgUpdateClientParams {
+    fn from(msg: MsgUpdateClientParams) -> Self { Self::from(&msg) }
+}
+
+impl From<&MsgUpdateClientParams> for proto::MsgUpdateClientParams {
+    fn from(msg: &MsgUpdateClientParams) -> Self {
+        Self { trusting_period_ns: msg.trusting_period_ns }
+    }
+}
+
+impl TryFrom<proto::MsgUpdateClientParams> for MsgUpdateClientParams {
+    type Error = proto::BadMessage;
+    fn try_from(
+        msg: proto::MsgUpdateClientParams,
+    ) -> Result<Self, Self::Error> {
+        Self::try_from(&msg)
+    }
+}
+
+impl TryFrom<&proto::MsgUpdateClientParams> for MsgUpdateClientParams {
+    type Error = proto::BadMessage;
+    fn try_from(
+        msg: &proto::MsgUpdateClientParams,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self { trusting_period_ns: msg.trusting_period_ns })
+    }
+}
+
+proto_utils::define_wrapper! {
+    proto: proto::MsgUpdateClientParams,
+    wrapper: MsgUpdateClientParams,
+}
+
+#[test]
+fn test_with_params() {
+    let state = ClientState {
+        latest_slot: core::num::NonZeroU64::MIN,
+        witness_account: crate::types::PubKey([42; 32]),
+        trusting_period_ns: MIN_TRUSTING_PERIOD_NS,
+        is_frozen: false,
+    };
+    let msg =
+        MsgUpdateClientParams { trusting_period_ns: 2 * 3600 * 1_000_000_000 };
+    let got = state.with_params(&msg).unwrap();
+    assert_eq!(msg.trusting_period_ns, got.trusting_period_ns);
+    assert_eq!(state.latest_slot, got.latest_slot);
+
+    for trusting_period_ns in
+        [0, MIN_TRUSTING_PERIOD_NS - 1, MAX_TRUSTING_PERIOD_NS + 1]
+    {
+        let msg = MsgUpdateClientParams { trusting_period_ns };
+        assert_eq!(
+            Err(UpdateClientParamsError::TrustingPeriodOutOfBounds),
+            state.with_params(&msg)
+        );
+    }
+
+    assert_eq!(
+        Err(UpdateClientParamsError::ClientFrozen),
+        state.frozen().with_params(&msg)
+    );
+}
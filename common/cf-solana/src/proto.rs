@@ -36,6 +36,10 @@ define_proto!(Misbehaviour; test_misbehaviour; Self {
     header2: Some(Header::test()),
 });
 
+define_proto!(MsgUpdateClientParams; test_msg_update_client_params; Self {
+    trusting_period_ns: 7 * 24 * 3600 * 1_000_000_000,
+});
+
 impl From<Header> for ClientMessage {
     #[inline]
     fn from(msg: Header) -> Self {
@@ -152,6 +152,34 @@ where
 #[repr(transparent)]
 pub struct Fingerprint([u8; 72]);
 
+/// Format of block fingerprints.
+///
+/// Version zero fingerprints identify the chain by its genesis hash only.  If
+/// the chain is redeployed with the same genesis block, signatures made for
+/// one deployment are valid for the other.  Version one mixes in a domain
+/// which identifies the deployment on the host, e.g. hash of the host program
+/// id and a recent host block hash.
+///
+/// The version is part of the chain configuration so that the chain can switch
+/// to a new format once all parties are ready.  Validators are unaffected
+/// since they sign fingerprints as calculated by the chain.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum FingerprintVersion {
+    #[default]
+    V0,
+    V1 {
+        domain: CryptoHash,
+    },
+}
+
 /// Error while generating new block.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
 pub enum GenerateError {
@@ -271,30 +299,60 @@ impl Default for Fingerprint {
     fn default() -> Self { Self([0; 72]) }
 }
 
+impl FingerprintVersion {
+    /// Returns chain identifier included in fingerprints of this version.
+    ///
+    /// For version zero that’s the genesis hash.  For version one it’s hash
+    /// of the genesis hash and the domain.
+    pub fn chain_id(&self, genesis_hash: &CryptoHash) -> CryptoHash {
+        match self {
+            Self::V0 => *genesis_hash,
+            Self::V1 { domain } => CryptoHash::digestv(&[
+                b"guestchain/fingerprint/v1".as_slice(),
+                genesis_hash.as_slice(),
+                domain.as_slice(),
+            ]),
+        }
+    }
+}
+
 impl Fingerprint {
     /// Calculates the fingerprint of the given block.
-    pub fn new(genesis_hash: &CryptoHash, header: &BlockHeader) -> Self {
-        Self::from_hash(genesis_hash, header.block_height, &header.calc_hash())
+    pub fn new(
+        genesis_hash: &CryptoHash,
+        version: &FingerprintVersion,
+        header: &BlockHeader,
+    ) -> Self {
+        Self::from_hash(
+            genesis_hash,
+            version,
+            header.block_height,
+            &header.calc_hash(),
+        )
     }
 
     /// Constructs the fingerprint of a block at given height and with given
     /// hash.
     pub fn from_hash(
         genesis_hash: &CryptoHash,
+        version: &FingerprintVersion,
         block_height: crate::BlockHeight,
         block_hash: &CryptoHash,
     ) -> Self {
         let mut fp = Self::default();
-        let (genesis, rest) = stdx::split_array_mut::<32, 40, 72>(&mut fp.0);
+        let (chain, rest) = stdx::split_array_mut::<32, 40, 72>(&mut fp.0);
         let (height, hash) = stdx::split_array_mut::<8, 32, 40>(rest);
-        *genesis = genesis_hash.into();
+        *chain = version.chain_id(genesis_hash).into();
         *height = u64::from(block_height).to_le_bytes();
         *hash = block_hash.into();
         fp
     }
 
-    /// Parses the fingerprint extracting genesis hash, block height and block
-    /// hash from it.
+    /// Parses the fingerprint extracting chain identifier, block height and
+    /// block hash from it.
+    ///
+    /// The chain identifier is the genesis hash for [`FingerprintVersion::V0`]
+    /// fingerprints; see [`FingerprintVersion::chain_id`].
     pub fn parse(&self) -> (&CryptoHash, crate::BlockHeight, &CryptoHash) {
         let (genesis, rest) = stdx::split_array_ref::<32, 40, 72>(&self.0);
         let (height, hash) = stdx::split_array_ref::<8, 32, 40>(rest);
@@ -330,7 +388,7 @@ impl Fingerprint {
 impl core::fmt::Debug for Fingerprint {
     fn fmt(&self, fmtr: &mut core::fmt::Formatter) -> core::fmt::Result {
         let (genesis, height, hash) = self.parse();
-        write!(fmtr, "FP(genesis={genesis}, height={height}, block={hash})")
+        write!(fmtr, "FP(chain={genesis}, height={height}, block={hash})")
     }
 }
 
@@ -438,7 +496,8 @@ fn test_signatures() {
     let height = 2.into();
     let hash = CryptoHash::test(3);
 
-    let fingerprint = Fingerprint::from_hash(&genesis, height, &hash);
+    let v0 = FingerprintVersion::V0;
+    let fingerprint = Fingerprint::from_hash(&genesis, &v0, height, &hash);
 
     assert_eq!((&genesis, height, &hash), fingerprint.parse());
 
@@ -452,15 +511,27 @@ fn test_signatures() {
     assert!(!fingerprint.verify(&pk, &MockSignature((0, 0, 0), pk), &()));
 
     let fingerprint =
-        Fingerprint::from_hash(&CryptoHash::test(66), height, &hash);
+        Fingerprint::from_hash(&CryptoHash::test(66), &v0, height, &hash);
     assert!(!fingerprint.verify(&pk, &signature, &()));
 
-    let fingerprint = Fingerprint::from_hash(&genesis, 66.into(), &hash);
+    let fingerprint = Fingerprint::from_hash(&genesis, &v0, 66.into(), &hash);
     assert!(!fingerprint.verify(&pk, &signature, &()));
 
     let fingerprint =
-        Fingerprint::from_hash(&genesis, height, &CryptoHash::test(66));
+        Fingerprint::from_hash(&genesis, &v0, height, &CryptoHash::test(66));
     assert!(!fingerprint.verify(&pk, &signature, &()));
+
+    // Version one fingerprints differ between domains even with the same
+    // genesis.
+    let v1 =
+        |domain| FingerprintVersion::V1 { domain: CryptoHash::test(domain) };
+    let fp_a = Fingerprint::from_hash(&genesis, &v1(7), height, &hash);
+    let fp_b = Fingerprint::from_hash(&genesis, &v1(8), height, &hash);
+    assert_ne!(fp_a, fp_b);
+    assert_eq!((&v1(7).chain_id(&genesis), height, &hash), fp_a.parse());
+    let signature = fp_a.sign(&signer);
+    assert!(fp_a.verify(&pk, &signature, &()));
+    assert!(!fp_b.verify(&pk, &signature, &()));
 }
 
 #[test]
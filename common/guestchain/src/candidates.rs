@@ -13,7 +13,7 @@ mod tests;
 )]
 pub struct Candidates<PK> {
     /// Maximum number of validators in a validator set.
-    max_validators: NonZeroU16,
+    pub(crate) max_validators: NonZeroU16,
 
     /// Set of validators which are interested in participating in the
     /// blockchain.
@@ -24,7 +24,7 @@ pub struct Candidates<PK> {
     /// Whether the set changed in a way which affects the epoch.
     ///
     /// If this is true, as soon as possible a new epoch will be started.
-    changed: bool,
+    pub(crate) changed: bool,
 
     /// Sum of the top `max_validators` stakes.
     head_stake: u128,
@@ -57,6 +57,16 @@ pub enum UpdateCandidateError {
     NotEnoughValidators,
 }
 
+/// Error while rotating candidate’s key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RotateCandidateError {
+    /// Candidate with the old key doesn’t exist.
+    UnknownCandidate,
+
+    /// A candidate with the new key already exists.
+    KeyInUse,
+}
+
 impl<PK: crate::PubKey> Candidates<PK> {
     /// Creates a new candidates set from the given list.
     ///
@@ -77,7 +87,7 @@ impl<PK: crate::PubKey> Candidates<PK> {
         )
     }
 
-    fn from_candidates(
+    pub(crate) fn from_candidates(
         max_validators: NonZeroU16,
         mut candidates: Vec<Candidate<PK>>,
     ) -> Self {
@@ -170,6 +180,41 @@ impl<PK: crate::PubKey> Candidates<PK> {
         res.map_err(E::from)
     }
 
+    /// Replaces public key of an existing candidate keeping its stake.
+    ///
+    /// If the candidate is (or as a result becomes) one of the top
+    /// `max_validators` candidates, marks the set as changed so that the new
+    /// key becomes part of the validators set on next epoch change.  Until
+    /// then, the old key stays in the current epoch.
+    pub fn rotate_key(
+        &mut self,
+        old: &PK,
+        new: PK,
+    ) -> Result<(), RotateCandidateError> {
+        if self.candidates.iter().any(|el| el.pubkey == new) {
+            return Err(RotateCandidateError::KeyInUse);
+        }
+        let old_pos = self
+            .candidates
+            .iter()
+            .position(|el| el.pubkey == *old)
+            .ok_or(RotateCandidateError::UnknownCandidate)?;
+        let candidate =
+            Candidate { pubkey: new, stake: self.candidates[old_pos].stake };
+        // Stake doesn’t change so the candidate can move only among
+        // candidates with the same stake.  head_stake stays the same.
+        let mut new_pos =
+            self.candidates.binary_search(&candidate).unwrap_err();
+        if new_pos > old_pos {
+            new_pos -= 1;
+        }
+        let max = self.max_validators();
+        self.changed |= old_pos < max || new_pos < max;
+        *rotate(self.candidates.as_mut_slice(), old_pos, new_pos) = candidate;
+        self.debug_verify_state();
+        Ok(())
+    }
+
     /// Adds a new candidates or updates existing candidate’s stake.
     fn do_update(
         &mut self,
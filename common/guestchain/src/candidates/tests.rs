@@ -237,6 +237,39 @@ fn test_candidates_0() {
     check([('D', 4), ('A', 3), ('B', 3), ('C', 2)], &candidates);
 }
 
+#[test]
+fn test_rotate_key() {
+    use candidate as c;
+    use RotateCandidateError::*;
+
+    fn pk(pubkey: char) -> MockPubKey { MockPubKey(pubkey as u32) }
+
+    let mut candidates = Candidates::from_candidates(
+        NonZeroU16::new(2).unwrap(),
+        [c('B', 2), c('D', 2), c('F', 2), c('G', 1)].to_vec(),
+    );
+    candidates.clear_changed_flag();
+
+    assert_eq!(Err(UnknownCandidate), candidates.rotate_key(&pk('A'), pk('H')));
+    assert_eq!(Err(KeyInUse), candidates.rotate_key(&pk('B'), pk('D')));
+    check([('B', 2), ('D', 2), ('F', 2), ('G', 1)], &candidates);
+
+    // Rotating key outside of the head doesn’t affect the epoch.
+    candidates.rotate_key(&pk('G'), pk('A')).unwrap();
+    check([('B', 2), ('D', 2), ('F', 2), ('A', 1)], &candidates);
+    assert!(!candidates.changed);
+
+    // Candidate may move among candidates with the same stake.
+    candidates.rotate_key(&pk('B'), pk('E')).unwrap();
+    check([('D', 2), ('E', 2), ('F', 2), ('A', 1)], &candidates);
+    assert!(candidates.changed);
+
+    candidates.clear_changed_flag();
+    candidates.rotate_key(&pk('F'), pk('C')).unwrap();
+    check([('C', 2), ('D', 2), ('E', 2), ('A', 1)], &candidates);
+    assert!(candidates.changed);
+}
+
 #[test]
 fn test_candidiates_1() {
     use candidate as c;
@@ -0,0 +1,211 @@
+use alloc::collections::BTreeMap;
+use core::num::NonZeroU128;
+
+use borsh::maybestd::io;
+use lib::hash::CryptoHash;
+
+/// Balances of the guest chain’s native token.
+///
+/// The ledger maps account public keys to their balance.  Accounts with zero
+/// balance are not stored.  Tokens enter the ledger through [`Ledger::mint`]
+/// (e.g. when paying out block rewards), move between accounts through
+/// [`Ledger::transfer`] and leave it through [`Ledger::burn`] (e.g. when fees
+/// are collected).
+///
+/// The state of the ledger is committed to by [`Ledger::calc_commitment`]
+/// which is stored in the provable trie so that counterparty chains can verify
+/// balances.  At the moment the only ledger is the block rewards ledger (see
+/// [`crate::Rewards`]) whose commitment is stored at the start of each epoch.
+#[derive(Clone, Debug, PartialEq, Eq, borsh::BorshSerialize)]
+pub struct Ledger<PK> {
+    /// Version of the structure.  Used to support forward-compatibility.  At
+    /// the moment this is always zero.
+    version: crate::common::VersionZero,
+
+    /// Non-zero balances of all accounts.
+    balances: BTreeMap<PK, NonZeroU128>,
+
+    /// Total supply of the token.
+    ///
+    /// This is always `sum(balances.values())`.
+    // We don’t serialise it because we calculate it when deserializing to make
+    // sure that it’s always a correct value.
+    #[borsh_skip]
+    total_supply: u128,
+}
+
+/// Error while updating the ledger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
+pub enum LedgerError {
+    /// Source account doesn’t hold enough tokens.
+    InsufficientBalance,
+    /// Operation would overflow total supply.
+    SupplyOverflow,
+}
+
+impl<PK: Ord> Default for Ledger<PK> {
+    fn default() -> Self {
+        Self {
+            version: crate::common::VersionZero,
+            balances: BTreeMap::new(),
+            total_supply: 0,
+        }
+    }
+}
+
+impl<PK> borsh::BorshDeserialize for Ledger<PK>
+where
+    PK: Ord + borsh::BorshDeserialize,
+{
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let version = crate::common::VersionZero::deserialize_reader(reader)?;
+        let balances = BTreeMap::<PK, NonZeroU128>::deserialize_reader(reader)?;
+        let mut total_supply: u128 = 0;
+        for balance in balances.values() {
+            total_supply = total_supply
+                .checked_add(balance.get())
+                .ok_or(io::ErrorKind::InvalidData)?;
+        }
+        Ok(Self { version, balances, total_supply })
+    }
+}
+
+impl<PK: Ord> Ledger<PK> {
+    /// Returns balance of given account.
+    pub fn balance(&self, account: &PK) -> u128 {
+        self.balances.get(account).map_or(0, |balance| balance.get())
+    }
+
+    /// Returns total supply of the token.
+    pub fn total_supply(&self) -> u128 { self.total_supply }
+
+    /// Returns iterator over all accounts with non-zero balance.
+    pub fn iter(&self) -> impl Iterator<Item = (&PK, NonZeroU128)> {
+        self.balances.iter().map(|(pk, balance)| (pk, *balance))
+    }
+
+    /// Creates `amount` new tokens and credits them to given account.
+    pub fn mint(
+        &mut self,
+        account: PK,
+        amount: u128,
+    ) -> Result<(), LedgerError> {
+        let total_supply = self
+            .total_supply
+            .checked_add(amount)
+            .ok_or(LedgerError::SupplyOverflow)?;
+        // Can’t overflow since balance ≤ total_supply.
+        let balance = self.balance(&account) + amount;
+        self.set(account, balance);
+        self.total_supply = total_supply;
+        Ok(())
+    }
+
+    /// Destroys `amount` tokens held by given account.
+    pub fn burn(
+        &mut self,
+        account: &PK,
+        amount: u128,
+    ) -> Result<(), LedgerError>
+    where
+        PK: Clone,
+    {
+        let balance = self
+            .balance(account)
+            .checked_sub(amount)
+            .ok_or(LedgerError::InsufficientBalance)?;
+        self.set(account.clone(), balance);
+        self.total_supply -= amount;
+        Ok(())
+    }
+
+    /// Moves `amount` tokens from `from` account to `to` account.
+    ///
+    /// Total supply is not affected.  Transferring to self is a no-op (but
+    /// still fails if the account doesn’t hold `amount` tokens).
+    pub fn transfer(
+        &mut self,
+        from: &PK,
+        to: PK,
+        amount: u128,
+    ) -> Result<(), LedgerError>
+    where
+        PK: Clone,
+    {
+        let from_balance = self
+            .balance(from)
+            .checked_sub(amount)
+            .ok_or(LedgerError::InsufficientBalance)?;
+        self.set(from.clone(), from_balance);
+        // Can’t overflow since sum of balances equals total_supply.
+        let to_balance = self.balance(&to) + amount;
+        self.set(to, to_balance);
+        Ok(())
+    }
+
+    /// Calculates commitment (i.e. hash) of the ledger.
+    pub fn calc_commitment(&self) -> CryptoHash
+    where
+        PK: borsh::BorshSerialize,
+    {
+        let mut builder = CryptoHash::builder();
+        borsh::to_writer(&mut builder, self).unwrap();
+        builder.build()
+    }
+
+    /// Sets balance of an account removing the entry if balance is zero.
+    fn set(&mut self, account: PK, balance: u128) {
+        match NonZeroU128::new(balance) {
+            Some(balance) => {
+                self.balances.insert(account, balance);
+            }
+            None => {
+                self.balances.remove(&account);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ledger() {
+    use borsh::BorshDeserialize;
+
+    use crate::validators::MockPubKey;
+
+    let ali = MockPubKey(1);
+    let bob = MockPubKey(2);
+
+    let mut ledger = Ledger::default();
+    assert_eq!(0, ledger.total_supply());
+
+    ledger.mint(ali, 100).unwrap();
+    ledger.mint(bob, 10).unwrap();
+    assert_eq!(
+        (100, 10, 110),
+        (ledger.balance(&ali), ledger.balance(&bob), ledger.total_supply())
+    );
+    assert_eq!(Err(LedgerError::SupplyOverflow), ledger.mint(ali, u128::MAX));
+
+    ledger.transfer(&ali, bob, 60).unwrap();
+    assert_eq!(
+        (40, 70, 110),
+        (ledger.balance(&ali), ledger.balance(&bob), ledger.total_supply())
+    );
+    assert_eq!(
+        Err(LedgerError::InsufficientBalance),
+        ledger.transfer(&ali, bob, 41)
+    );
+    ledger.transfer(&ali, ali, 40).unwrap();
+    assert_eq!(40, ledger.balance(&ali));
+
+    ledger.burn(&ali, 40).unwrap();
+    assert_eq!((0, 70), (ledger.balance(&ali), ledger.total_supply()));
+    assert_eq!(1, ledger.iter().count());
+    assert_eq!(Err(LedgerError::InsufficientBalance), ledger.burn(&ali, 1));
+
+    let serialised = borsh::to_vec(&ledger).unwrap();
+    let got = Ledger::<MockPubKey>::try_from_slice(&serialised).unwrap();
+    assert_eq!(ledger, got);
+    assert_eq!(ledger.calc_commitment(), got.calc_commitment());
+    assert_ne!(Ledger::default().calc_commitment(), ledger.calc_commitment());
+}
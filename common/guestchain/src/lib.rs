@@ -10,7 +10,11 @@ mod common;
 pub mod config;
 pub mod epoch;
 pub mod height;
+pub mod ledger;
 pub mod manager;
+pub mod proto;
+pub mod rewards;
+pub mod stats;
 pub mod validators;
 
 pub use block::{Block, BlockHeader};
@@ -18,7 +22,10 @@ pub use candidates::{Candidate, Candidates};
 pub use config::Config;
 pub use epoch::Epoch;
 pub use height::{BlockDelta, BlockHeight, HostDelta, HostHeight};
+pub use ledger::Ledger;
 pub use manager::ChainManager;
+pub use rewards::Rewards;
+pub use stats::{EpochStats, ValidatorStats};
 pub use validators::{
     BadFormat, PubKey, Signature, Signer, Validator, Verifier,
 };
@@ -10,41 +10,106 @@ use std::collections::HashSet as Set;
 use lib::hash::CryptoHash;
 
 use crate::candidates::Candidate;
-pub use crate::candidates::UpdateCandidateError;
+pub use crate::candidates::{RotateCandidateError, UpdateCandidateError};
 use crate::config::{UpdateConfig, UpdateConfigError};
 use crate::{BlockHeight, Validator};
 
 const MAX_CONSENSUS_STATES: usize = 20;
 
+/// Number of most recent finalised blocks whose state roots are remembered.
+///
+/// See [`ChainManager::state_root_at`].
+const MAX_STATE_ROOTS: usize = 32;
+
 #[derive(Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct ChainManager<PK> {
     /// Configuration specifying limits for block generation.
-    config: crate::Config,
+    pub(crate) config: crate::Config,
 
     /// Hash of the chain’s genesis block.
-    genesis: CryptoHash,
+    pub(crate) genesis: CryptoHash,
 
     /// Current latest block which has been signed by quorum of validators.
-    header: crate::BlockHeader,
+    pub(crate) header: crate::BlockHeader,
 
     /// Epoch of the next block.  In other words, epoch which specifies
     /// validators set for `pending_block`.
-    next_epoch: crate::Epoch<PK>,
+    pub(crate) next_epoch: crate::Epoch<PK>,
 
     /// Next block which is waiting for quorum of validators to sign.
-    pending_block: Option<PendingBlock<PK>>,
+    pub(crate) pending_block: Option<PendingBlock<PK>>,
 
     /// Height at which current epoch was defined.
-    epoch_height: crate::HostHeight,
+    pub(crate) epoch_height: crate::HostHeight,
 
     /// Set of validator candidates to consider for the next epoch.
     // TODO(mina86): This is Boxed to help solana-ibc with stack usage.  It’s
     // not entirely clear how this affects the stack but without boxing this we
     // end up with failing contract.  Ideally this field would not be boxed.
-    candidates: Box<crate::Candidates<PK>>,
+    pub(crate) candidates: Box<crate::Candidates<PK>>,
 
     /// previous Consensus states
     pub consensus_states: VecDeque<LocalConsensusState>,
+
+    /// Block rewards accrued by validators.
+    pub(crate) rewards: crate::Rewards<PK>,
+
+    /// Performance statistics of validators in the current epoch.
+    pub(crate) epoch_stats: crate::EpochStats<PK>,
+
+    /// Performance statistics of validators in the previous epoch, i.e. the
+    /// last epoch summary.  `None` if no epoch has ended yet.
+    pub(crate) last_epoch_stats: Option<crate::EpochStats<PK>>,
+
+    /// Number of the epoch of the latest finalised block.
+    ///
+    /// Genesis epoch has number zero and the number is incremented each time
+    /// a block committing to a new epoch is finalised.
+    pub(crate) epoch_number: u64,
+
+    /// Stake changes which haven’t taken effect in validators set yet in the
+    /// order they were made.
+    pub(crate) stake_changes: Vec<StakeChange<PK>>,
+
+    /// Format of fingerprints of newly generated blocks.
+    pub(crate) fingerprint_version: crate::block::FingerprintVersion,
+
+    /// State roots of the most recent finalised blocks, oldest first.
+    ///
+    /// Holds at most [`MAX_STATE_ROOTS`] entries with the last one being
+    /// state root of the latest finalised block.
+    pub(crate) state_roots: VecDeque<FinalisedRoot>,
+}
+
+/// State root of a finalised block.
+///
+/// See [`ChainManager::state_root_at`].
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct FinalisedRoot {
+    /// Height of the finalised block.
+    pub height: BlockHeight,
+
+    /// State root of the finalised block.
+    pub state_root: CryptoHash,
+}
+
+/// A change of candidate’s stake waiting to take effect at an epoch boundary.
+///
+/// See [`ChainManager::queue_stake_change`].
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct StakeChange<PK> {
+    /// Candidate whose stake changes.
+    pub pubkey: PK,
+
+    /// Amount added to (if positive) or removed from (if negative) the stake.
+    pub delta: i128,
+
+    /// Number of the first epoch whose validators set includes the change.
+    pub activation_epoch: u64,
 }
 
 #[derive(Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
@@ -74,6 +139,76 @@ pub struct PendingBlock<PK> {
 
     /// Sum of stake of validators who have signed the block.
     signing_stake: u128,
+
+    /// Validator who has generated the block, if known.
+    proposer: Option<PK>,
+}
+
+/// Original layout of [`ChainManager`].
+///
+/// Chain managers serialised before the manager started tracking block
+/// rewards, epoch statistics, queued stake changes, fingerprint version and
+/// state roots use this layout.  It’s only used to read such data which then
+/// can be converted into [`ChainManager`] with [`From`].
+#[derive(borsh::BorshDeserialize)]
+pub struct ChainManagerV0<PK> {
+    config: crate::Config,
+    genesis: CryptoHash,
+    header: crate::BlockHeader,
+    next_epoch: crate::Epoch<PK>,
+    pending_block: Option<PendingBlockV0<PK>>,
+    epoch_height: crate::HostHeight,
+    candidates: Box<crate::Candidates<PK>>,
+    consensus_states: VecDeque<LocalConsensusState>,
+}
+
+/// Original layout of [`PendingBlock`].
+#[derive(borsh::BorshDeserialize)]
+struct PendingBlockV0<PK> {
+    next_block: crate::Block<PK>,
+    fingerprint: crate::block::Fingerprint,
+    signers: Set<PK>,
+    signing_stake: u128,
+}
+
+impl<PK: crate::PubKey> From<ChainManagerV0<PK>> for ChainManager<PK> {
+    /// Converts chain manager from the original layout.
+    ///
+    /// Fields missing in the original layout start empty, as they do for a
+    /// new chain, with the exception of state roots which start with the
+    /// latest finalised block.  In particular, since number of epochs the
+    /// chain went through isn’t known, epoch numbering starts from zero.
+    fn from(old: ChainManagerV0<PK>) -> Self {
+        let pending_block = old.pending_block.map(|pending| PendingBlock {
+            next_block: pending.next_block,
+            fingerprint: pending.fingerprint,
+            signers: pending.signers,
+            signing_stake: pending.signing_stake,
+            proposer: None,
+        });
+        let mut state_roots = VecDeque::with_capacity(MAX_STATE_ROOTS);
+        state_roots.push_back(FinalisedRoot {
+            height: old.header.block_height,
+            state_root: old.header.state_root,
+        });
+        Self {
+            config: old.config,
+            genesis: old.genesis,
+            header: old.header,
+            next_epoch: old.next_epoch,
+            pending_block,
+            epoch_height: old.epoch_height,
+            candidates: old.candidates,
+            consensus_states: old.consensus_states,
+            rewards: Default::default(),
+            epoch_stats: Default::default(),
+            last_epoch_stats: None,
+            epoch_number: 0,
+            stake_changes: Vec::new(),
+            fingerprint_version: Default::default(),
+            state_roots,
+        }
+    }
 }
 
 /// Provided genesis block is invalid.
@@ -96,6 +231,24 @@ pub enum GenerateError {
     Inner(crate::block::GenerateError),
 }
 
+/// Error while queuing a stake change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueueStakeError {
+    /// Trying to remove stake from a candidate which doesn’t exist.
+    UnknownCandidate,
+    /// Trying to remove more stake than the candidate holds.
+    InsufficientStake,
+    /// The stake would overflow.
+    Overflow,
+    /// The candidates set rejected the change, e.g. because the candidate
+    /// would end up with less than minimum stake.
+    Rejected(UpdateCandidateError),
+}
+
+impl From<UpdateCandidateError> for QueueStakeError {
+    fn from(err: UpdateCandidateError) -> Self { Self::Rejected(err) }
+}
+
 /// Error while accepting a signature from a validator.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AddSignatureError {
@@ -134,6 +287,11 @@ impl<PK: crate::PubKey> ChainManager<PK> {
         }
         let header = genesis.header;
         let next_epoch = genesis.next_epoch.ok_or(BadGenesis)?;
+        let mut state_roots = VecDeque::with_capacity(MAX_STATE_ROOTS);
+        state_roots.push_back(FinalisedRoot {
+            height: header.block_height,
+            state_root: header.state_root,
+        });
         let candidates = crate::Candidates::new(
             config.max_validators,
             next_epoch.validators(),
@@ -147,6 +305,13 @@ impl<PK: crate::PubKey> ChainManager<PK> {
             candidates: Box::new(candidates),
             header,
             consensus_states: VecDeque::with_capacity(MAX_CONSENSUS_STATES),
+            rewards: Default::default(),
+            epoch_stats: Default::default(),
+            last_epoch_stats: None,
+            epoch_number: 0,
+            stake_changes: Vec::new(),
+            fingerprint_version: Default::default(),
+            state_roots,
         })
     }
 
@@ -211,8 +376,11 @@ impl<PK: crate::PubKey> ChainManager<PK> {
             state_root,
             next_epoch,
         )?;
-        let fingerprint =
-            crate::block::Fingerprint::new(&self.genesis, &next_block);
+        let fingerprint = crate::block::Fingerprint::new(
+            &self.genesis,
+            &self.fingerprint_version,
+            &next_block,
+        );
         if self.consensus_states.len() == MAX_CONSENSUS_STATES {
             self.consensus_states.pop_front();
         }
@@ -226,6 +394,7 @@ impl<PK: crate::PubKey> ChainManager<PK> {
             next_block,
             signers: Set::new(),
             signing_stake: 0,
+            proposer: None,
         });
 
         if has_next_epoch {
@@ -287,9 +456,7 @@ impl<PK: crate::PubKey> ChainManager<PK> {
         &self,
         host_height: crate::HostHeight,
     ) -> Option<crate::Epoch<PK>> {
-        if !host_height
-            .check_delta_from(self.epoch_height, self.config.min_epoch_length)
-        {
+        if !self.can_end_epoch(host_height) {
             return None;
         }
         crate::Epoch::new_with(self.candidates.maybe_get_head()?, |total| {
@@ -301,6 +468,29 @@ impl<PK: crate::PubKey> ChainManager<PK> {
         })
     }
 
+    /// Returns whether current epoch is long enough to be ended by a block
+    /// generated at given host height.
+    fn can_end_epoch(&self, host_height: crate::HostHeight) -> bool {
+        host_height
+            .check_delta_from(self.epoch_height, self.config.min_epoch_length)
+    }
+
+    /// Records validator who has generated the pending block.
+    ///
+    /// The proposer is credited in epoch statistics once the block is
+    /// finalised.  Does nothing if there’s no pending block, proposer has
+    /// already been set or `proposer` isn’t a validator of the pending block’s
+    /// epoch.
+    pub fn set_proposer(&mut self, proposer: PK) {
+        let pending = match self.pending_block.as_mut() {
+            Some(pending) if pending.proposer.is_none() => pending,
+            _ => return,
+        };
+        if self.next_epoch.validator(&proposer).is_some() {
+            pending.proposer = Some(proposer);
+        }
+    }
+
     /// Adds a signature to pending block.
     pub fn add_signature(
         &mut self,
@@ -331,11 +521,32 @@ impl<PK: crate::PubKey> ChainManager<PK> {
             return Ok(AddSignatureEffect::NoQuorumYet);
         }
 
-        let block = self.pending_block.take().unwrap().next_block;
+        let pending = self.pending_block.take().unwrap();
+        self.rewards.on_block_finalised();
+        self.epoch_stats.on_block_finalised(
+            pending.next_block.block_height,
+            pending.proposer.as_ref(),
+            pending.signers.iter(),
+        );
+        let block = pending.next_block;
         self.header = block.header;
+        if self.state_roots.len() == MAX_STATE_ROOTS {
+            self.state_roots.pop_front();
+        }
+        self.state_roots.push_back(FinalisedRoot {
+            height: self.header.block_height,
+            state_root: self.header.state_root,
+        });
         if let Some(epoch) = block.next_epoch {
+            let stats = core::mem::take(&mut self.epoch_stats);
+            self.rewards.on_epoch_end(&stats, self.config.max_validators);
+            self.last_epoch_stats = Some(stats);
             self.next_epoch = epoch;
             self.epoch_height = self.header.host_height;
+            self.epoch_number += 1;
+            let epoch_number = self.epoch_number;
+            self.stake_changes
+                .retain(|change| change.activation_epoch > epoch_number);
         }
         Ok(AddSignatureEffect::GotQuorum)
     }
@@ -358,6 +569,118 @@ impl<PK: crate::PubKey> ChainManager<PK> {
         self.candidates.update(&self.config, pubkey, new_stake_fn)
     }
 
+    /// Changes validator candidate’s stake by given amount and records when
+    /// the change takes effect.
+    ///
+    /// The candidates set is updated immediately so the change is validated
+    /// (and fails if e.g. the candidate would end up with less than minimum
+    /// stake) when it’s made rather than when it takes effect.  Like with
+    /// [`Self::update_candidate`], the new stake affects validators set of the
+    /// next epoch.  If there’s a pending block which already commits to a new
+    /// epoch, that’s the epoch after.
+    ///
+    /// Returns number of the first epoch whose validators set includes the
+    /// change (see [`Self::stake_activation_epoch`]).  Until that epoch starts
+    /// (see [`Self::epoch_number`]), the change is listed in
+    /// [`Self::stake_changes`] and tokens backing removed stake shouldn’t be
+    /// released.  Changes of the same candidate taking effect in the same
+    /// epoch are merged into a single entry so there are at most two entries
+    /// per candidate.
+    pub fn queue_stake_change(
+        &mut self,
+        pubkey: PK,
+        delta: i128,
+    ) -> Result<u64, QueueStakeError> {
+        let activation_epoch = self.stake_activation_epoch();
+        let queued = self.stake_changes.iter().position(|change| {
+            change.pubkey == pubkey &&
+                change.activation_epoch == activation_epoch
+        });
+        let merged = match queued {
+            Some(idx) => self.stake_changes[idx]
+                .delta
+                .checked_add(delta)
+                .ok_or(QueueStakeError::Overflow)?,
+            None => delta,
+        };
+
+        self.candidates.update(&self.config, pubkey.clone(), |candidate| {
+            let stake = candidate.map_or(0, |candidate| candidate.stake.get());
+            stake.checked_add_signed(delta).ok_or(if delta > 0 {
+                QueueStakeError::Overflow
+            } else if candidate.is_none() {
+                QueueStakeError::UnknownCandidate
+            } else {
+                QueueStakeError::InsufficientStake
+            })
+        })?;
+
+        match queued {
+            Some(idx) if merged == 0 => {
+                self.stake_changes.remove(idx);
+            }
+            Some(idx) => self.stake_changes[idx].delta = merged,
+            None if delta == 0 => (),
+            None => self.stake_changes.push(StakeChange {
+                pubkey,
+                delta,
+                activation_epoch,
+            }),
+        }
+        Ok(activation_epoch)
+    }
+
+    /// Returns number of the first epoch whose validators set would include
+    /// a stake change made now.
+    ///
+    /// That’s the next epoch or, if there’s a pending block which already
+    /// commits to a new epoch, the epoch after.
+    pub fn stake_activation_epoch(&self) -> u64 {
+        let pending_epoch = self
+            .pending_block
+            .as_ref()
+            .is_some_and(|pending| pending.next_block.next_epoch.is_some());
+        self.epoch_number + 1 + u64::from(pending_epoch)
+    }
+
+    /// Returns stake changes which haven’t taken effect yet in the order they
+    /// were made.
+    pub fn stake_changes(&self) -> &[StakeChange<PK>] { &self.stake_changes }
+
+    /// Returns number of the epoch of the latest finalised block.
+    ///
+    /// Genesis epoch has number zero and the number is incremented each time
+    /// a block committing to a new epoch is finalised.
+    pub fn epoch_number(&self) -> u64 { self.epoch_number }
+
+    /// Replaces public key of validator candidate keeping its stake.
+    ///
+    /// The new key becomes part of the validators set on next epoch change.
+    /// If the candidate is a validator in the current epoch, it continues
+    /// signing blocks with the old key until then.  Stake changes queued for
+    /// the old key are moved to the new key.  Fails if there are stake changes
+    /// queued for the new key.
+    pub fn rotate_candidate_key(
+        &mut self,
+        old: &PK,
+        new: PK,
+    ) -> Result<(), RotateCandidateError> {
+        if self.stake_changes.iter().any(|change| change.pubkey == new) {
+            return Err(RotateCandidateError::KeyInUse);
+        }
+        self.candidates.rotate_key(old, new.clone())?;
+        for change in self.stake_changes.iter_mut() {
+            if change.pubkey == *old {
+                change.pubkey = new.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the current epoch, i.e. the one whose validators sign new
+    /// blocks.
+    pub fn epoch(&self) -> &crate::Epoch<PK> { &self.next_epoch }
+
     pub fn validators(&self) -> &[Validator<PK>] {
         self.next_epoch.validators()
     }
@@ -368,7 +691,116 @@ impl<PK: crate::PubKey> ChainManager<PK> {
 
     pub fn epoch_height(&self) -> crate::HostHeight { self.epoch_height }
 
+    pub fn config(&self) -> &crate::Config { &self.config }
+
     pub fn genesis(&self) -> &CryptoHash { &self.genesis }
+
+    /// Returns block rewards accounting state.
+    pub fn rewards(&self) -> &crate::Rewards<PK> { &self.rewards }
+
+    /// Sets reward for each block finalised by quorum of validators.
+    ///
+    /// See [`crate::Rewards`] for description of how rewards are distributed.
+    pub fn set_block_reward(&mut self, block_reward: u64) {
+        self.rewards.set_block_reward(block_reward)
+    }
+
+    /// Claims all rewards accrued by given validator.
+    ///
+    /// Returns the claimed amount which the caller is responsible for paying
+    /// out to the validator.
+    pub fn claim_rewards(&mut self, validator: &PK) -> u128 {
+        self.rewards.claim(validator)
+    }
+
+    /// Returns format of fingerprints of newly generated blocks.
+    pub fn fingerprint_version(&self) -> &crate::block::FingerprintVersion {
+        &self.fingerprint_version
+    }
+
+    /// Sets format of fingerprints of newly generated blocks.
+    ///
+    /// Pending block, if any, keeps its fingerprint.  Light clients verifying
+    /// the chain need to be configured with the same version before they can
+    /// verify blocks generated after the change.
+    pub fn set_fingerprint_version(
+        &mut self,
+        version: crate::block::FingerprintVersion,
+    ) {
+        self.fingerprint_version = version;
+    }
+
+    /// Returns state root of the finalised block at given height.
+    ///
+    /// Only state roots of the last [`MAX_STATE_ROOTS`] finalised blocks are
+    /// remembered.  Returns `None` if `height` is older than that, hasn’t
+    /// been finalised yet or the block was generated before the chain started
+    /// tracking state roots.  This lets counterparties verify proofs
+    /// generated against a recent but not the latest block.
+    pub fn state_root_at(&self, height: BlockHeight) -> Option<&CryptoHash> {
+        let idx = self
+            .state_roots
+            .binary_search_by_key(&height, |root| root.height)
+            .ok()?;
+        Some(&self.state_roots[idx].state_root)
+    }
+
+    /// Returns state roots of the most recent finalised blocks, oldest first.
+    pub fn state_roots(&self) -> &VecDeque<FinalisedRoot> { &self.state_roots }
+
+    /// Returns performance statistics of validators in the current epoch.
+    pub fn epoch_stats(&self) -> &crate::EpochStats<PK> { &self.epoch_stats }
+
+    /// Returns summary of the last finished epoch or `None` if no epoch has
+    /// finished yet.
+    pub fn last_epoch_stats(&self) -> Option<&crate::EpochStats<PK>> {
+        self.last_epoch_stats.as_ref()
+    }
+}
+
+#[cfg(test)]
+impl ChainManager<crate::validators::MockPubKey> {
+    /// Creates a chain manager for tests.
+    ///
+    /// Genesis epoch has three validators with keys one, two and three, each
+    /// with stake of two.  Configuration allows up to three validators with
+    /// given minimum block length and minimum epoch length of eight.
+    pub(crate) fn test(min_block_length: u64) -> Self {
+        let epoch = crate::Epoch::test(&[(1, 2), (2, 2), (3, 2)]);
+        let genesis = crate::Block::generate_genesis(
+            1.into(),
+            1.into(),
+            NonZeroU64::MIN,
+            CryptoHash::default(),
+            epoch,
+        )
+        .unwrap();
+        let config = crate::Config {
+            min_validators: core::num::NonZeroU16::MIN,
+            max_validators: core::num::NonZeroU16::new(3).unwrap(),
+            min_validator_stake: core::num::NonZeroU128::MIN,
+            min_total_stake: core::num::NonZeroU128::MIN,
+            min_quorum_stake: core::num::NonZeroU128::MIN,
+            min_block_length: min_block_length.into(),
+            max_block_age_ns: 1000,
+            min_epoch_length: 8.into(),
+        };
+        Self::new(config, genesis).unwrap()
+    }
+
+    /// Signs head of the chain on behalf of given validator.
+    pub(crate) fn sign_head(
+        &mut self,
+        validator: &Validator<crate::validators::MockPubKey>,
+    ) -> Result<AddSignatureEffect, AddSignatureError> {
+        let signature = crate::block::Fingerprint::new(
+            &self.genesis,
+            &self.fingerprint_version,
+            self.head().1,
+        )
+        .sign(&validator.pubkey().make_signer());
+        self.add_signature(*validator.pubkey(), &signature, &())
+    }
 }
 
 #[test]
@@ -430,16 +862,6 @@ fn test_generate() {
         mgr.generate_next(5.into(), one, CryptoHash::test(1))
     );
 
-    fn sign_head(
-        mgr: &mut ChainManager<MockPubKey>,
-        validator: &crate::validators::Validator<MockPubKey>,
-    ) -> Result<AddSignatureEffect, AddSignatureError> {
-        let signature =
-            crate::block::Fingerprint::new(&mgr.genesis, mgr.head().1)
-                .sign(&validator.pubkey().make_signer());
-        mgr.add_signature(*validator.pubkey(), &signature, &())
-    }
-
     mgr.generate_next(5.into(), two, CryptoHash::test(1)).unwrap();
     // The head hasn’t been fully signed yet.
     assert_eq!(
@@ -447,12 +869,12 @@ fn test_generate() {
         mgr.generate_next(10.into(), three, CryptoHash::test(2))
     );
 
-    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), sign_head(&mut mgr, &ali));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
     assert_eq!(
         Err(GenerateError::HasPendingBlock),
         mgr.generate_next(10.into(), three, CryptoHash::test(2))
     );
-    assert_eq!(Ok(AddSignatureEffect::Duplicate), sign_head(&mut mgr, &ali));
+    assert_eq!(Ok(AddSignatureEffect::Duplicate), mgr.sign_head(&ali));
     assert_eq!(
         Err(GenerateError::HasPendingBlock),
         mgr.generate_next(10.into(), three, CryptoHash::test(2))
@@ -460,8 +882,12 @@ fn test_generate() {
 
     // Signatures are verified
     let pubkey = MockPubKey(42);
-    let signature = crate::block::Fingerprint::new(&mgr.genesis, mgr.head().1)
-        .sign(&pubkey.make_signer());
+    let signature = crate::block::Fingerprint::new(
+        &mgr.genesis,
+        &mgr.fingerprint_version,
+        mgr.head().1,
+    )
+    .sign(&pubkey.make_signer());
     assert_eq!(
         Err(AddSignatureError::BadValidator),
         mgr.add_signature(pubkey, &signature, &())
@@ -476,11 +902,11 @@ fn test_generate() {
         mgr.generate_next(10.into(), three, CryptoHash::test(2))
     );
 
-    assert_eq!(Ok(AddSignatureEffect::GotQuorum), sign_head(&mut mgr, &bob));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
     mgr.generate_next(10.into(), three, CryptoHash::test(2)).unwrap();
 
-    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), sign_head(&mut mgr, &ali));
-    assert_eq!(Ok(AddSignatureEffect::GotQuorum), sign_head(&mut mgr, &bob));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
 
     // State hasn’t changed, no need for new block.  However, changing epoch can
     // trigger new block.
@@ -493,8 +919,8 @@ fn test_generate() {
     })
     .unwrap();
     mgr.generate_next(15.into(), four, CryptoHash::test(2)).unwrap();
-    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), sign_head(&mut mgr, &ali));
-    assert_eq!(Ok(AddSignatureEffect::GotQuorum), sign_head(&mut mgr, &bob));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
 
     // Epoch has minimum length.  Even if the head of candidates changes but not
     // enough host blockchain passed, the epoch won’t be changed.
@@ -507,8 +933,8 @@ fn test_generate() {
         mgr.generate_next(20.into(), five, CryptoHash::test(2))
     );
     mgr.generate_next(30.into(), five, CryptoHash::test(2)).unwrap();
-    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), sign_head(&mut mgr, &ali));
-    assert_eq!(Ok(AddSignatureEffect::GotQuorum), sign_head(&mut mgr, &bob));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
 
     //Adding candidates past the head (i.e. in a way which wouldn’t affect the
     // epoch) doesn’t change the state.
@@ -525,8 +951,8 @@ fn test_generate() {
     })
     .unwrap();
     mgr.generate_next(40.into(), six, CryptoHash::test(2)).unwrap();
-    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), sign_head(&mut mgr, &ali));
-    assert_eq!(Ok(AddSignatureEffect::GotQuorum), sign_head(&mut mgr, &bob));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
 
     // Even if nothing changed, block may be generate if the current one is too
     // old.
@@ -590,3 +1016,285 @@ fn test_generate() {
         mgr.update_config(update_chain_config)
     );
 }
+
+#[test]
+fn test_block_rewards() {
+    use crate::validators::MockPubKey;
+
+    let mut mgr = ChainManager::test(4);
+    let ali = mgr.validators()[0].clone();
+    let bob = mgr.validators()[1].clone();
+    let eve = mgr.validators()[2].clone();
+    mgr.set_block_reward(10);
+
+    let rewards_state = |mgr: &ChainManager<MockPubKey>| {
+        let stats = mgr.epoch_stats();
+        (
+            mgr.rewards().epoch_pool(),
+            stats.get(ali.pubkey()).signatures,
+            stats.get(bob.pubkey()).signatures,
+            stats.get(eve.pubkey()).signatures,
+        )
+    };
+
+    // Pending blocks don’t count.
+    let two = NonZeroU64::new(2).unwrap();
+    mgr.generate_next(5.into(), two, CryptoHash::test(1)).unwrap();
+    mgr.set_proposer(*eve.pubkey());
+    mgr.set_proposer(*ali.pubkey());
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!((0, 0, 0, 0), rewards_state(&mgr));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
+    assert_eq!((10, 1, 1, 0), rewards_state(&mgr));
+
+    // Block changing the epoch distributes the pool.
+    mgr.update_candidate(*eve.pubkey(), |_| {
+        Result::<u128, UpdateCandidateError>::Ok(1)
+    })
+    .unwrap();
+    let three = NonZeroU64::new(3).unwrap();
+    mgr.generate_next(15.into(), three, CryptoHash::test(1)).unwrap();
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
+    assert_eq!((0, 0, 0, 0), rewards_state(&mgr));
+    let summary = mgr.last_epoch_stats().unwrap();
+    assert_eq!(2, summary.blocks());
+    assert_eq!(1, summary.get(eve.pubkey()).blocks_proposed);
+    assert_eq!(0, summary.get(ali.pubkey()).blocks_proposed);
+    assert_eq!(10_000, summary.uptime_bps(ali.pubkey()));
+    assert_eq!(0, summary.uptime_bps(eve.pubkey()));
+    assert_eq!(10, mgr.rewards().accrued(ali.pubkey()));
+    assert_eq!(10, mgr.rewards().accrued(bob.pubkey()));
+    assert_eq!(0, mgr.rewards().accrued(eve.pubkey()));
+
+    assert_eq!(10, mgr.claim_rewards(ali.pubkey()));
+    assert_eq!(0, mgr.claim_rewards(ali.pubkey()));
+    assert_eq!(10, mgr.rewards().total_accrued());
+}
+
+#[test]
+fn test_stake_queue() {
+    use crate::validators::MockPubKey;
+
+    let mut mgr = ChainManager::test(4);
+    mgr.config.min_validator_stake = NonZeroU128::new(2).unwrap();
+    let ali = mgr.validators()[0].clone();
+    let bob = mgr.validators()[1].clone();
+    let dan = MockPubKey(4);
+    let rejected = |err| Err::<u64, _>(QueueStakeError::Rejected(err));
+
+    // Changes are validated against the candidates set when they are made.
+    assert_eq!(
+        Err(QueueStakeError::InsufficientStake),
+        mgr.queue_stake_change(*ali.pubkey(), -3)
+    );
+    assert_eq!(
+        rejected(UpdateCandidateError::NotEnoughValidatorStake),
+        mgr.queue_stake_change(*ali.pubkey(), -1)
+    );
+    assert_eq!(
+        Err(QueueStakeError::UnknownCandidate),
+        mgr.queue_stake_change(MockPubKey(9), -1)
+    );
+    assert_eq!(
+        rejected(UpdateCandidateError::NotEnoughValidatorStake),
+        mgr.queue_stake_change(MockPubKey(9), 1)
+    );
+    assert_eq!(Ok(1), mgr.queue_stake_change(*bob.pubkey(), i128::MAX));
+    assert_eq!(
+        Err(QueueStakeError::Overflow),
+        mgr.queue_stake_change(*bob.pubkey(), i128::MAX)
+    );
+    assert_eq!(Ok(1), mgr.queue_stake_change(*bob.pubkey(), -i128::MAX));
+    assert_eq!(0, mgr.stake_changes().len());
+
+    assert_eq!(Ok(1), mgr.queue_stake_change(*ali.pubkey(), -2));
+    assert_eq!(Ok(1), mgr.queue_stake_change(dan, 3));
+    assert_eq!(
+        &[
+            StakeChange {
+                pubkey: *ali.pubkey(),
+                delta: -2,
+                activation_epoch: 1
+            },
+            StakeChange { pubkey: dan, delta: 3, activation_epoch: 1 },
+        ],
+        mgr.stake_changes()
+    );
+    assert!(!mgr.candidates().iter().any(|c| c.pubkey == *ali.pubkey()));
+
+    // Removed stake keeps counting until the epoch changes.
+    let two = NonZeroU64::new(2).unwrap();
+    mgr.generate_next(5.into(), two, CryptoHash::test(1)).unwrap();
+    assert_eq!(None, mgr.pending_block.as_ref().unwrap().next_block.next_epoch);
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
+    assert_eq!(2, mgr.stake_changes().len());
+    assert_eq!(0, mgr.epoch_number());
+    assert_eq!(1, mgr.stake_activation_epoch());
+    assert!(mgr.epoch().validator(ali.pubkey()).is_some());
+
+    // Once epoch can end, new validators set is generated.
+    let three = NonZeroU64::new(3).unwrap();
+    mgr.generate_next(15.into(), three, CryptoHash::test(1)).unwrap();
+    let pending = mgr.pending_block.as_ref().unwrap();
+    let next_epoch = pending.next_block.next_epoch.as_ref().unwrap();
+    assert_eq!(3, next_epoch.validator(&dan).unwrap().stake().get());
+    assert_eq!(None, next_epoch.validator(ali.pubkey()));
+
+    // Changes made while new epoch is pending take effect in the one after.
+    assert_eq!(2, mgr.stake_activation_epoch());
+    assert_eq!(Ok(2), mgr.queue_stake_change(dan, -1));
+    assert_eq!(Ok(AddSignatureEffect::NoQuorumYet), mgr.sign_head(&ali));
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), mgr.sign_head(&bob));
+    assert_eq!(1, mgr.epoch_number());
+    assert_eq!(
+        &[StakeChange { pubkey: dan, delta: -1, activation_epoch: 2 }],
+        mgr.stake_changes()
+    );
+    assert_eq!(None, mgr.epoch().validator(ali.pubkey()));
+    assert_eq!(3, mgr.epoch().validator(&dan).unwrap().stake().get());
+    assert_eq!(2, mgr.stake_activation_epoch());
+
+    // Rotating key moves recorded changes to the new key.
+    mgr.rotate_candidate_key(&dan, MockPubKey(5)).unwrap();
+    assert_eq!(
+        &[StakeChange {
+            pubkey: MockPubKey(5),
+            delta: -1,
+            activation_epoch: 2
+        }],
+        mgr.stake_changes()
+    );
+    assert_eq!(Ok(2), mgr.queue_stake_change(*bob.pubkey(), -2));
+    assert_eq!(
+        Err(RotateCandidateError::KeyInUse),
+        mgr.rotate_candidate_key(&MockPubKey(5), *bob.pubkey())
+    );
+
+    // Changes taking effect in the same epoch are merged.
+    assert_eq!(Ok(2), mgr.queue_stake_change(MockPubKey(5), 2));
+    assert_eq!(
+        &[
+            StakeChange {
+                pubkey: MockPubKey(5),
+                delta: 1,
+                activation_epoch: 2
+            },
+            StakeChange {
+                pubkey: *bob.pubkey(),
+                delta: -2,
+                activation_epoch: 2
+            },
+        ],
+        mgr.stake_changes()
+    );
+    assert_eq!(Ok(2), mgr.queue_stake_change(MockPubKey(5), -1));
+    assert_eq!(
+        &[StakeChange {
+            pubkey: *bob.pubkey(),
+            delta: -2,
+            activation_epoch: 2
+        }],
+        mgr.stake_changes()
+    );
+}
+
+#[test]
+fn test_fingerprint_version() {
+    use crate::block::{Fingerprint, FingerprintVersion};
+
+    let mut mgr = ChainManager::test(4);
+    let ali = mgr.validators()[0].clone();
+    assert_eq!(&FingerprintVersion::V0, mgr.fingerprint_version());
+
+    let version = FingerprintVersion::V1 { domain: CryptoHash::test(42) };
+    mgr.set_fingerprint_version(version.clone());
+    mgr.generate_next(
+        5.into(),
+        NonZeroU64::new(2).unwrap(),
+        CryptoHash::test(1),
+    )
+    .unwrap();
+
+    let header = mgr.head().1.clone();
+    let want = Fingerprint::new(&mgr.genesis, &version, &header);
+    assert_eq!(want, mgr.pending_block.as_ref().unwrap().fingerprint);
+
+    let signer = ali.pubkey().make_signer();
+    let old = Fingerprint::new(&mgr.genesis, &FingerprintVersion::V0, &header);
+    assert_eq!(
+        Err(AddSignatureError::BadSignature),
+        mgr.add_signature(*ali.pubkey(), &old.sign(&signer), &())
+    );
+    assert!(mgr.add_signature(*ali.pubkey(), &want.sign(&signer), &()).is_ok());
+}
+
+#[test]
+fn test_state_root_at() {
+    let mut mgr = ChainManager::test(1);
+    let ali = mgr.validators()[0].clone();
+    let bob = mgr.validators()[1].clone();
+    assert_eq!(Some(&CryptoHash::default()), mgr.state_root_at(1.into()));
+    assert_eq!(None, mgr.state_root_at(2.into()));
+
+    for n in 1..=(MAX_STATE_ROOTS as u64) {
+        let timestamp = NonZeroU64::new(n + 1).unwrap();
+        let state_root = CryptoHash::test(n as usize);
+        mgr.generate_next((n * 2).into(), timestamp, state_root).unwrap();
+        let height = mgr.head().1.block_height;
+
+        // Pending block’s root isn’t available until it’s finalised.
+        assert_eq!(None, mgr.state_root_at(height));
+        for validator in [&ali, &bob] {
+            mgr.sign_head(validator).unwrap();
+        }
+        assert_eq!(Some(&state_root), mgr.state_root_at(height));
+    }
+
+    // Oldest roots, including genesis’, have been dropped.
+    assert_eq!(MAX_STATE_ROOTS, mgr.state_roots().len());
+    assert_eq!(None, mgr.state_root_at(1.into()));
+    assert_eq!(Some(&CryptoHash::test(1)), mgr.state_root_at(2.into()));
+    let head = mgr.head().1;
+    assert_eq!(Some(&head.state_root), mgr.state_root_at(head.block_height));
+}
+
+#[test]
+fn test_from_v0() {
+    use borsh::BorshDeserialize;
+
+    use crate::validators::MockPubKey;
+
+    let mut mgr = ChainManager::test(4);
+    let ali = mgr.validators()[0].clone();
+    let bob = mgr.validators()[1].clone();
+    let timestamp = NonZeroU64::new(2).unwrap();
+    mgr.generate_next(5.into(), timestamp, CryptoHash::test(1)).unwrap();
+    mgr.sign_head(&ali).unwrap();
+
+    // Serialise the manager using the original layout.
+    let pending = mgr.pending_block.as_ref().unwrap();
+    let pending = (
+        &pending.next_block,
+        &pending.fingerprint,
+        &pending.signers,
+        pending.signing_stake,
+    );
+    let serialised = borsh::to_vec(&(
+        &mgr.config,
+        &mgr.genesis,
+        &mgr.header,
+        &mgr.next_epoch,
+        Some(pending),
+        &mgr.epoch_height,
+        &mgr.candidates,
+        &mgr.consensus_states,
+    ))
+    .unwrap();
+
+    let old = ChainManagerV0::<MockPubKey>::try_from_slice(&serialised);
+    let mut got = ChainManager::from(old.unwrap());
+    assert_eq!(borsh::to_vec(&mgr).unwrap(), borsh::to_vec(&got).unwrap());
+    assert_eq!(Ok(AddSignatureEffect::GotQuorum), got.sign_head(&bob));
+}
@@ -1,38 +1,365 @@
+//! Protocol buffer definitions of the chain manager state.
+//!
+//! Fields with types which are of no interest outside of the guest blockchain
+//! (e.g. configuration or block headers) are stored as Borsh-serialised
+//! bytes.
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::num::{NonZeroU128, NonZeroU16, NonZeroU64};
+
 pub use proto_utils::{Any, AnyConvert, BadMessage, DecodeError};
 
 mod pb {
     include!(concat!(env!("OUT_DIR"), "/messages.rs"));
 }
 
-impl_proto!(ClientState; test_client_state Self {
-    genesis_hash: lib::hash::CryptoHash::test(24).to_vec(),
-    latest_height: 8,
-    epoch_commitment: lib::hash::CryptoHash::test(11).to_vec(),
-    is_frozen: false,
-    trusting_period_ns: 30 * 24 * 3600 * 1_000_000_000,
-});
+pub use pb::guestchain::v1::{LocalConsensusState, Validator};
 
-impl_proto!(ConsensusState; test_consensus_state {
-    let block_hash = lib::hash::CryptoHash::test(42).to_vec();
-    Self { block_hash, timestamp_ns: 1 }
-});
+macro_rules! define_proto {
+    ($Msg:ident; $test:ident; $test_object:expr) => {
+        proto_utils::define_message! {
+            pub use pb::guestchain::v1::$Msg as $Msg;
+            $test $test_object;
+        }
+    };
+}
 
-impl_proto!(Header; test_header {
-    // TODO(mina86): Construct a proper signed header.
-    Self {
-        genesis_hash: alloc::vec![0; 32],
-        block_header: alloc::vec![1; 10],
-        epoch: alloc::vec![2; 10],
-        signatures: alloc::vec![],
-    }
+define_proto!(Epoch; test_epoch; Self {
+    validators: alloc::vec![Validator::test()],
+    quorum_stake: "3".into(),
 });
 
-impl_proto!(Signature; test_signature Self {
-    index: 1,
-    signature: alloc::vec![0; 64],
+define_proto!(Candidates; test_candidates; Self {
+    max_validators: 2,
+    candidates: alloc::vec![Validator::test()],
+    changed: true,
 });
 
-impl_proto!(Misbehaviour; test_misbehaviour Self {
-    header1: Some(Header::test()),
-    header2: Some(Header::test()),
+define_proto!(ChainManager; test_chain_manager; Self {
+    config: alloc::vec![1; 2],
+    genesis_hash: alloc::vec![2; 2],
+    header: alloc::vec![3; 2],
+    next_epoch: Some(Epoch::test()),
+    pending_block: Vec::new(),
+    epoch_height: 4,
+    candidates: Some(Candidates::test()),
+    consensus_states: alloc::vec![LocalConsensusState {
+        height: 5,
+        timestamp_ns: 6,
+        block_hash: alloc::vec![7; 2],
+    }],
+    rewards: alloc::vec![8; 2],
+    epoch_stats: alloc::vec![9; 2],
+    last_epoch_stats: alloc::vec![10; 2],
+    epoch_number: 11,
+    stake_changes: alloc::vec![12; 2],
+    fingerprint_version: Vec::new(),
+    state_roots: Vec::new(),
 });
+
+#[cfg(test)]
+impl Validator {
+    fn test() -> Self { Self { pubkey: alloc::vec![1; 4], stake: "5".into() } }
+}
+
+fn parse_stake(stake: &str) -> Result<NonZeroU128, BadMessage> {
+    stake.parse().map_err(|_| BadMessage)
+}
+
+fn borsh_decode<T: borsh::BorshDeserialize>(
+    bytes: &[u8],
+) -> Result<T, BadMessage> {
+    borsh::from_slice(bytes).map_err(|_| BadMessage)
+}
+
+// Conversions for Validator.
+
+impl<PK: crate::PubKey> From<&crate::Validator<PK>> for Validator {
+    fn from(validator: &crate::Validator<PK>) -> Self {
+        Self {
+            pubkey: validator.pubkey().to_vec(),
+            stake: validator.stake().to_string(),
+        }
+    }
+}
+
+impl<PK: crate::PubKey> TryFrom<&Validator> for crate::Validator<PK> {
+    type Error = BadMessage;
+    fn try_from(msg: &Validator) -> Result<Self, Self::Error> {
+        let pubkey = PK::from_bytes(&msg.pubkey).map_err(|_| BadMessage)?;
+        Ok(Self::new(pubkey, parse_stake(&msg.stake)?))
+    }
+}
+
+impl<PK: crate::PubKey> From<&crate::Candidate<PK>> for Validator {
+    fn from(candidate: &crate::Candidate<PK>) -> Self {
+        Self::from(&crate::Validator::from(candidate))
+    }
+}
+
+impl<PK: crate::PubKey> TryFrom<&Validator> for crate::Candidate<PK> {
+    type Error = BadMessage;
+    fn try_from(msg: &Validator) -> Result<Self, Self::Error> {
+        crate::Validator::try_from(msg).map(|validator| Self::from(&validator))
+    }
+}
+
+// Conversions for Epoch.
+
+impl<PK: crate::PubKey> From<&crate::Epoch<PK>> for Epoch {
+    fn from(epoch: &crate::Epoch<PK>) -> Self {
+        Self {
+            validators: epoch.validators().iter().map(Into::into).collect(),
+            quorum_stake: epoch.quorum_stake().to_string(),
+        }
+    }
+}
+
+impl<PK: crate::PubKey> TryFrom<&Epoch> for crate::Epoch<PK> {
+    type Error = BadMessage;
+    fn try_from(msg: &Epoch) -> Result<Self, Self::Error> {
+        let validators = msg
+            .validators
+            .iter()
+            .map(TryFrom::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let quorum_stake = parse_stake(&msg.quorum_stake)?;
+        Self::new(validators, quorum_stake).ok_or(BadMessage)
+    }
+}
+
+// Conversions for Candidates.
+
+impl<PK: crate::PubKey> From<&crate::Candidates<PK>> for Candidates {
+    fn from(candidates: &crate::Candidates<PK>) -> Self {
+        Self {
+            max_validators: candidates.max_validators.get().into(),
+            candidates: candidates.candidates.iter().map(Into::into).collect(),
+            changed: candidates.changed,
+        }
+    }
+}
+
+impl<PK: crate::PubKey> TryFrom<&Candidates> for crate::Candidates<PK> {
+    type Error = BadMessage;
+    fn try_from(msg: &Candidates) -> Result<Self, Self::Error> {
+        let max_validators = u16::try_from(msg.max_validators)
+            .ok()
+            .and_then(NonZeroU16::new)
+            .ok_or(BadMessage)?;
+        let candidates = msg
+            .candidates
+            .iter()
+            .map(TryFrom::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut this = Self::from_candidates(max_validators, candidates);
+        this.changed = msg.changed;
+        Ok(this)
+    }
+}
+
+// Conversions for LocalConsensusState.
+
+impl From<&crate::manager::LocalConsensusState> for LocalConsensusState {
+    fn from(state: &crate::manager::LocalConsensusState) -> Self {
+        Self {
+            height: state.height.into(),
+            timestamp_ns: state.timestamp.get(),
+            block_hash: state.blockhash.clone(),
+        }
+    }
+}
+
+impl TryFrom<&LocalConsensusState> for crate::manager::LocalConsensusState {
+    type Error = BadMessage;
+    fn try_from(msg: &LocalConsensusState) -> Result<Self, Self::Error> {
+        Ok(Self {
+            height: msg.height.into(),
+            timestamp: NonZeroU64::new(msg.timestamp_ns).ok_or(BadMessage)?,
+            blockhash: msg.block_hash.clone(),
+        })
+    }
+}
+
+// Conversions for ChainManager.
+
+impl<PK> From<&crate::ChainManager<PK>> for ChainManager
+where
+    PK: crate::PubKey,
+{
+    fn from(manager: &crate::ChainManager<PK>) -> Self {
+        let pending_block = manager
+            .pending_block
+            .as_ref()
+            .map_or_else(Vec::new, |block| borsh::to_vec(block).unwrap());
+        let rewards = if manager.rewards == Default::default() {
+            Vec::new()
+        } else {
+            borsh::to_vec(&manager.rewards).unwrap()
+        };
+        let epoch_stats = if manager.epoch_stats == Default::default() {
+            Vec::new()
+        } else {
+            borsh::to_vec(&manager.epoch_stats).unwrap()
+        };
+        let last_epoch_stats = manager
+            .last_epoch_stats
+            .as_ref()
+            .map_or_else(Vec::new, |stats| borsh::to_vec(stats).unwrap());
+        let stake_changes = if manager.stake_changes.is_empty() {
+            Vec::new()
+        } else {
+            borsh::to_vec(&manager.stake_changes).unwrap()
+        };
+        let fingerprint_version =
+            if manager.fingerprint_version == Default::default() {
+                Vec::new()
+            } else {
+                borsh::to_vec(&manager.fingerprint_version).unwrap()
+            };
+        let state_roots = if manager.state_roots.is_empty() {
+            Vec::new()
+        } else {
+            borsh::to_vec(&manager.state_roots).unwrap()
+        };
+        Self {
+            config: borsh::to_vec(&manager.config).unwrap(),
+            genesis_hash: manager.genesis.to_vec(),
+            header: borsh::to_vec(&manager.header).unwrap(),
+            next_epoch: Some((&manager.next_epoch).into()),
+            pending_block,
+            epoch_height: manager.epoch_height.into(),
+            candidates: Some((&*manager.candidates).into()),
+            consensus_states: manager
+                .consensus_states
+                .iter()
+                .map(Into::into)
+                .collect(),
+            rewards,
+            epoch_stats,
+            last_epoch_stats,
+            epoch_number: manager.epoch_number,
+            stake_changes,
+            fingerprint_version,
+            state_roots,
+        }
+    }
+}
+
+impl<PK> TryFrom<&ChainManager> for crate::ChainManager<PK>
+where
+    PK: crate::PubKey,
+{
+    type Error = BadMessage;
+    fn try_from(msg: &ChainManager) -> Result<Self, Self::Error> {
+        let genesis =
+            lib::hash::CryptoHash::try_from(msg.genesis_hash.as_slice())
+                .map_err(|_| BadMessage)?;
+        let pending_block = if msg.pending_block.is_empty() {
+            None
+        } else {
+            Some(borsh_decode(&msg.pending_block)?)
+        };
+        let rewards = if msg.rewards.is_empty() {
+            Default::default()
+        } else {
+            borsh_decode(&msg.rewards)?
+        };
+        let epoch_stats = if msg.epoch_stats.is_empty() {
+            Default::default()
+        } else {
+            borsh_decode(&msg.epoch_stats)?
+        };
+        let last_epoch_stats = if msg.last_epoch_stats.is_empty() {
+            None
+        } else {
+            Some(borsh_decode(&msg.last_epoch_stats)?)
+        };
+        let stake_changes = if msg.stake_changes.is_empty() {
+            Vec::new()
+        } else {
+            borsh_decode(&msg.stake_changes)?
+        };
+        let fingerprint_version = if msg.fingerprint_version.is_empty() {
+            Default::default()
+        } else {
+            borsh_decode(&msg.fingerprint_version)?
+        };
+        let state_roots = if msg.state_roots.is_empty() {
+            Default::default()
+        } else {
+            borsh_decode(&msg.state_roots)?
+        };
+        let next_epoch = msg.next_epoch.as_ref().ok_or(BadMessage)?;
+        let candidates = msg.candidates.as_ref().ok_or(BadMessage)?;
+        Ok(Self {
+            config: borsh_decode(&msg.config)?,
+            genesis,
+            header: borsh_decode(&msg.header)?,
+            next_epoch: next_epoch.try_into()?,
+            pending_block,
+            epoch_height: msg.epoch_height.into(),
+            candidates: Box::new(candidates.try_into()?),
+            consensus_states: msg
+                .consensus_states
+                .iter()
+                .map(TryFrom::try_from)
+                .collect::<Result<_, _>>()?,
+            rewards,
+            epoch_stats,
+            last_epoch_stats,
+            epoch_number: msg.epoch_number,
+            stake_changes,
+            fingerprint_version,
+            state_roots,
+        })
+    }
+}
+
+#[test]
+fn test_chain_manager_round_trip() {
+    use crate::validators::MockPubKey;
+
+    let mut mgr = crate::ChainManager::test(4);
+    let timestamp = NonZeroU64::new(2).unwrap();
+    let state_root = lib::hash::CryptoHash::test(1);
+    mgr.generate_next(5.into(), timestamp, state_root).unwrap();
+    mgr.set_block_reward(10);
+    mgr.queue_stake_change(MockPubKey(1), 1).unwrap();
+    mgr.set_fingerprint_version(crate::block::FingerprintVersion::V1 {
+        domain: lib::hash::CryptoHash::test(2),
+    });
+
+    let msg = ChainManager::from(&mgr);
+    let got = crate::ChainManager::<MockPubKey>::try_from(&msg).unwrap();
+    assert_eq!(borsh::to_vec(&mgr).unwrap(), borsh::to_vec(&got).unwrap());
+    assert_eq!(msg, ChainManager::from(&got));
+
+    let mut bad = msg.clone();
+    bad.next_epoch.as_mut().unwrap().quorum_stake = "7".into();
+    assert_eq!(
+        Err(BadMessage),
+        crate::ChainManager::<MockPubKey>::try_from(&bad).map(|_| ())
+    );
+}
+
+#[test]
+fn test_candidates_round_trip() {
+    use crate::validators::MockPubKey;
+
+    let validators =
+        crate::Epoch::test(&[(1, 2), (2, 3), (3, 4)]).validators().to_vec();
+    let candidates =
+        crate::Candidates::new(NonZeroU16::new(2).unwrap(), &validators);
+    let msg = Candidates::from(&candidates);
+    assert!(msg.changed);
+    assert_eq!(Ok(candidates), crate::Candidates::<MockPubKey>::try_from(&msg));
+
+    let mut bad = msg.clone();
+    bad.max_validators = 0;
+    assert_eq!(
+        Err(BadMessage),
+        crate::Candidates::<MockPubKey>::try_from(&bad)
+    );
+}
@@ -0,0 +1,207 @@
+use core::num::NonZeroU16;
+
+use crate::ledger::Ledger;
+use crate::stats::EpochStats;
+
+/// Block rewards accounting.
+///
+/// Each block finalised by quorum of validators adds [`Rewards::block_reward`]
+/// to the current epoch’s reward pool.  When the epoch ends, the pool is split
+/// between validators proportionally to the number of blocks they’ve signed
+/// (as recorded in the epoch’s [`EpochStats`]) and credited to their accounts
+/// in the rewards [`Ledger`].  Validators then claim accrued rewards
+/// which are paid out by the host.
+///
+/// Rounding remainder of the split is carried over to the next epoch’s pool so
+/// no rewards are lost.
+///
+/// To bound size of the structure, at most twice `max_validators` validators
+/// can have unclaimed rewards at a time.  This leaves room for validators who
+/// left the set without claiming their rewards while current validators keep
+/// accruing theirs.  If there’s no room for a validator, its share stays in
+/// the pool and is distributed in the next epoch.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct Rewards<PK> {
+    /// Version of the structure.  Used to support forward-compatibility.  At
+    /// the moment this is always zero.
+    version: crate::common::VersionZero,
+
+    /// Reward added to the epoch’s pool for each finalised block.
+    block_reward: u64,
+
+    /// Rewards to distribute at the end of the current epoch.
+    epoch_pool: u128,
+
+    /// Rewards accrued by validators which haven’t been claimed yet.
+    accrued: Ledger<PK>,
+}
+
+impl<PK: Ord> Default for Rewards<PK> {
+    fn default() -> Self {
+        Self {
+            version: crate::common::VersionZero,
+            block_reward: 0,
+            epoch_pool: 0,
+            accrued: Ledger::default(),
+        }
+    }
+}
+
+impl<PK: Ord + Clone> Rewards<PK> {
+    /// Returns reward added to the epoch’s pool for each finalised block.
+    pub fn block_reward(&self) -> u64 { self.block_reward }
+
+    /// Sets reward added to the epoch’s pool for each finalised block.
+    ///
+    /// The change affects blocks finalised after the call.  Rewards already
+    /// added to the pool are not affected.
+    pub fn set_block_reward(&mut self, block_reward: u64) {
+        self.block_reward = block_reward;
+    }
+
+    /// Returns rewards waiting to be distributed at the end of current epoch.
+    pub fn epoch_pool(&self) -> u128 { self.epoch_pool }
+
+    /// Returns rewards accrued by given validator which can be claimed.
+    pub fn accrued(&self, validator: &PK) -> u128 {
+        self.accrued.balance(validator)
+    }
+
+    /// Returns ledger of rewards accrued by validators.
+    pub fn ledger(&self) -> &Ledger<PK> { &self.accrued }
+
+    /// Returns sum of all accrued and not yet claimed rewards.
+    ///
+    /// This is the amount the host needs to keep in reserve to be able to pay
+    /// out all the claims.
+    pub fn total_accrued(&self) -> u128 { self.accrued.total_supply() }
+
+    /// Adds block reward of a finalised block to the epoch’s pool.
+    pub(crate) fn on_block_finalised(&mut self) {
+        self.epoch_pool =
+            self.epoch_pool.saturating_add(self.block_reward.into());
+    }
+
+    /// Distributes the epoch’s pool between validators who signed blocks in
+    /// the epoch according to the epoch’s statistics.
+    pub(crate) fn on_epoch_end(
+        &mut self,
+        stats: &EpochStats<PK>,
+        max_validators: NonZeroU16,
+    ) {
+        let total = stats.iter().fold(0u64, |total, (_, stats)| {
+            total.saturating_add(stats.signatures)
+        });
+        if total == 0 {
+            return;
+        }
+        let max_accounts = usize::from(max_validators.get()) * 2;
+        let total = u128::from(total);
+        let (quot, rem) = (self.epoch_pool / total, self.epoch_pool % total);
+        for (validator, stats) in stats.iter() {
+            if self.accrued.balance(validator) == 0 &&
+                self.accrued.iter().count() >= max_accounts
+            {
+                continue;
+            }
+            let count = u128::from(stats.signatures);
+            // rem < total ≤ u64::MAX and count ≤ u64::MAX so this doesn’t
+            // overflow.
+            let amount = quot * count + rem * count / total;
+            if self.accrued.mint(validator.clone(), amount).is_ok() {
+                self.epoch_pool -= amount;
+            }
+        }
+    }
+
+    /// Claims all rewards accrued by given validator.
+    ///
+    /// Returns the claimed amount which the caller is responsible for paying
+    /// out.  Returns zero if validator has no rewards to claim.
+    pub fn claim(&mut self, validator: &PK) -> u128 {
+        let amount = self.accrued.balance(validator);
+        // Can’t fail since we’re burning the entire balance.
+        self.accrued.burn(validator, amount).unwrap();
+        amount
+    }
+}
+
+#[test]
+fn test_rewards() {
+    use borsh::BorshDeserialize;
+
+    use crate::validators::MockPubKey;
+
+    let ali = MockPubKey(1);
+    let bob = MockPubKey(2);
+    let eve = MockPubKey(3);
+
+    let max_validators = NonZeroU16::new(3).unwrap();
+    let mut rewards = Rewards::default();
+    let mut stats = EpochStats::default();
+    rewards.on_block_finalised();
+    stats.on_block_finalised(2.into(), None, [ali, bob].iter());
+    assert_eq!(0, rewards.epoch_pool());
+
+    rewards.set_block_reward(100);
+    rewards.on_block_finalised();
+    stats.on_block_finalised(3.into(), None, [ali, bob].iter());
+    rewards.on_block_finalised();
+    stats.on_block_finalised(4.into(), None, [ali, eve].iter());
+    assert_eq!(200, rewards.epoch_pool());
+
+    // 6 signatures in total: ali signed 3, bob 2 and eve 1.
+    rewards.on_epoch_end(&stats, max_validators);
+    assert_eq!(
+        (100, 66, 33, 1),
+        (
+            rewards.accrued(&ali),
+            rewards.accrued(&bob),
+            rewards.accrued(&eve),
+            rewards.epoch_pool()
+        )
+    );
+    assert_eq!(199, rewards.total_accrued());
+
+    // Nobody signed anything so the pool carries over.
+    rewards.on_epoch_end(&EpochStats::default(), max_validators);
+    assert_eq!(1, rewards.epoch_pool());
+
+    let serialised = borsh::to_vec(&rewards).unwrap();
+    let got = Rewards::<MockPubKey>::try_from_slice(&serialised).unwrap();
+    assert_eq!(rewards, got);
+
+    assert_eq!(66, rewards.claim(&bob));
+    assert_eq!(0, rewards.claim(&bob));
+    assert_eq!(133, rewards.total_accrued());
+
+    // Validators who don’t fit leave their share in the pool.  With
+    // max_validators = 3 there’s room for six accounts.
+    const MAX_ACCOUNTS: usize = 6;
+    let validators = (0..MAX_ACCOUNTS as u32 + 1)
+        .map(MockPubKey)
+        .collect::<alloc::vec::Vec<_>>();
+    let (head, last) = validators.split_at(MAX_ACCOUNTS);
+    let mut rewards = Rewards::default();
+    rewards.set_block_reward(10 * MAX_ACCOUNTS as u64);
+    let mut stats = EpochStats::default();
+    rewards.on_block_finalised();
+    stats.on_block_finalised(2.into(), None, head.iter());
+    rewards.on_epoch_end(&stats, max_validators);
+    assert_eq!((10, 0), (rewards.accrued(&head[0]), rewards.epoch_pool()));
+
+    let mut stats = EpochStats::default();
+    rewards.on_block_finalised();
+    stats.on_block_finalised(3.into(), None, last.iter());
+    rewards.on_epoch_end(&stats, max_validators);
+    assert_eq!(0, rewards.accrued(&last[0]));
+    assert_eq!(10 * MAX_ACCOUNTS as u128, rewards.epoch_pool());
+
+    // Once a validator claims its rewards, there’s room again.
+    assert_eq!(10, rewards.claim(&head[0]));
+    rewards.on_epoch_end(&stats, max_validators);
+    assert_eq!(10 * MAX_ACCOUNTS as u128, rewards.accrued(&last[0]));
+    assert_eq!(0, rewards.epoch_pool());
+}
@@ -0,0 +1,188 @@
+use alloc::collections::BTreeMap;
+
+use lib::hash::CryptoHash;
+
+/// Performance statistics of a single validator in an epoch.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct ValidatorStats {
+    /// Number of finalised blocks the validator has generated.
+    pub blocks_proposed: u64,
+
+    /// Number of finalised blocks the validator has signed.
+    pub signatures: u64,
+}
+
+/// Performance statistics of validators in an epoch.
+///
+/// Statistics are accumulated as blocks are finalised.  Once the last block of
+/// an epoch is finalised, the statistics become the epoch summary whose hash
+/// (see [`EpochStats::calc_hash`]) the host commits to in its state so that
+/// it’s included in the first block of the next epoch.
+///
+/// Only validators of the epoch propose and sign its blocks so the number of
+/// tracked validators is bounded by size of the epoch’s validator set, i.e. by
+/// the `max_validators` configuration option.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct EpochStats<PK> {
+    /// Version of the structure.  Used to support forward-compatibility.  At
+    /// the moment this is always zero.
+    version: crate::common::VersionZero,
+
+    /// Height of the last finalised block in the epoch.
+    last_block_height: crate::BlockHeight,
+
+    /// Number of blocks finalised in the epoch.
+    blocks: u64,
+
+    /// Statistics of validators who proposed or signed at least one block.
+    validators: BTreeMap<PK, ValidatorStats>,
+}
+
+impl<PK: Ord> Default for EpochStats<PK> {
+    fn default() -> Self {
+        Self {
+            version: crate::common::VersionZero,
+            last_block_height: crate::BlockHeight::from(0),
+            blocks: 0,
+            validators: BTreeMap::new(),
+        }
+    }
+}
+
+impl<PK: Ord + Clone> EpochStats<PK> {
+    /// Returns height of the last finalised block in the epoch.
+    pub fn last_block_height(&self) -> crate::BlockHeight {
+        self.last_block_height
+    }
+
+    /// Returns number of blocks finalised in the epoch.
+    pub fn blocks(&self) -> u64 { self.blocks }
+
+    /// Returns statistics of given validator.
+    pub fn get(&self, validator: &PK) -> ValidatorStats {
+        self.validators.get(validator).copied().unwrap_or_default()
+    }
+
+    /// Returns iterator over statistics of validators who proposed or signed
+    /// at least one block.
+    pub fn iter(&self) -> impl Iterator<Item = (&PK, &ValidatorStats)> {
+        self.validators.iter()
+    }
+
+    /// Returns ratio of blocks signed by the validator to all blocks finalised
+    /// in the epoch in basis points.
+    ///
+    /// Returns 10000 if no blocks have been finalised.
+    pub fn uptime_bps(&self, validator: &PK) -> u16 {
+        match self.blocks {
+            0 => 10_000,
+            blocks => {
+                let signatures = u128::from(self.get(validator).signatures);
+                (signatures * 10_000 / u128::from(blocks)) as u16
+            }
+        }
+    }
+
+    /// Calculates hash of the statistics.
+    pub fn calc_hash(&self) -> CryptoHash
+    where
+        PK: borsh::BorshSerialize,
+    {
+        let mut builder = CryptoHash::builder();
+        borsh::to_writer(&mut builder, self).unwrap();
+        builder.build()
+    }
+
+    /// Records a finalised block.
+    pub(crate) fn on_block_finalised<'a>(
+        &mut self,
+        block_height: crate::BlockHeight,
+        proposer: Option<&PK>,
+        signers: impl Iterator<Item = &'a PK>,
+    ) where
+        PK: 'a,
+    {
+        self.last_block_height = block_height;
+        self.blocks = self.blocks.saturating_add(1);
+        if let Some(proposer) = proposer {
+            let stats = self.validators.entry(proposer.clone()).or_default();
+            stats.blocks_proposed = stats.blocks_proposed.saturating_add(1);
+        }
+        for signer in signers {
+            let stats = self.validators.entry(signer.clone()).or_default();
+            stats.signatures = stats.signatures.saturating_add(1);
+        }
+    }
+}
+
+#[test]
+fn test_epoch_stats() {
+    use borsh::BorshDeserialize;
+
+    use crate::validators::MockPubKey;
+
+    let ali = MockPubKey(1);
+    let bob = MockPubKey(2);
+    let eve = MockPubKey(3);
+
+    let mut stats = EpochStats::default();
+    assert_eq!(10_000, stats.uptime_bps(&ali));
+
+    stats.on_block_finalised(2.into(), Some(&ali), [ali, bob].iter());
+    stats.on_block_finalised(3.into(), None, [ali].iter());
+    stats.on_block_finalised(4.into(), Some(&eve), [ali, bob].iter());
+
+    assert_eq!((4.into(), 3), (stats.last_block_height(), stats.blocks()));
+    assert_eq!(
+        ValidatorStats { blocks_proposed: 1, signatures: 3 },
+        stats.get(&ali)
+    );
+    assert_eq!(
+        ValidatorStats { blocks_proposed: 0, signatures: 2 },
+        stats.get(&bob)
+    );
+    assert_eq!(
+        ValidatorStats { blocks_proposed: 1, signatures: 0 },
+        stats.get(&eve)
+    );
+    assert_eq!(
+        (10_000, 6666, 0),
+        (
+            stats.uptime_bps(&ali),
+            stats.uptime_bps(&bob),
+            stats.uptime_bps(&eve)
+        )
+    );
+    assert_eq!(3, stats.iter().count());
+
+    let serialised = borsh::to_vec(&stats).unwrap();
+    let got = EpochStats::<MockPubKey>::try_from_slice(&serialised).unwrap();
+    assert_eq!(stats, got);
+    assert_eq!(stats.calc_hash(), got.calc_hash());
+    assert_ne!(EpochStats::default().calc_hash(), stats.calc_hash());
+
+    // Statistics of all validators are tracked no matter how many there are.
+    let validators = (0..100).map(MockPubKey).collect::<alloc::vec::Vec<_>>();
+    let mut stats = EpochStats::default();
+    stats.on_block_finalised(
+        2.into(),
+        Some(&validators[99]),
+        validators.iter(),
+    );
+    assert_eq!(100, stats.iter().count());
+    assert_eq!(
+        ValidatorStats { blocks_proposed: 1, signatures: 1 },
+        stats.get(&validators[99])
+    );
+}
@@ -0,0 +1,221 @@
+//! Memo of ICS-20 transfers directed at the bridge escrow program.
+//!
+//! When tokens are bridged to fulfil an intent, the transfer’s memo carries
+//! accounts which the bridge escrow program needs, identifier of the intent
+//! and an embedded memo passed to the program.  The format is shared by the
+//! solana-ibc transfer hook, the bridge escrow program as well as the
+//! off-chain auctioneer and solvers.
+//!
+//! Version 1 encoding is a JSON object:
+//!
+//! ```json
+//! {"version":1,"accounts":["account-0","account-1"],"intent_id":"42","memo":"…"}
+//! ```
+//!
+//! Legacy encoding is a comma-separated string of the form
+//! `N,account-0,…,account-N-1,intent-id,embedded-memo` where the embedded memo
+//! may contain commas.  It is accepted by [`IntentMemo::decode`] but never
+//! produced by [`IntentMemo::encode`].
+
+#![no_std]
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Version of the encoding produced by [`IntentMemo::encode`].
+pub const VERSION: u32 = 1;
+
+/// Decoded memo of a transfer directed at the bridge escrow program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntentMemo {
+    /// Addresses of accounts the relayer needs to pass to the bridge escrow
+    /// program.
+    pub accounts: Vec<String>,
+
+    /// Identifier of the intent the transfer fulfils.
+    pub intent_id: String,
+
+    /// Memo passed to the bridge escrow program.
+    pub memo: String,
+}
+
+/// Error decoding the memo.
+#[derive(Clone, Debug, PartialEq, Eq, derive_more::Display)]
+pub enum DecodeError {
+    /// The memo is not a valid JSON object or misses required fields.
+    #[display(fmt = "BadJson: {}", _0)]
+    BadJson(String),
+
+    /// The version in the JSON object is not supported.
+    #[display(fmt = "UnsupportedVersion: {}", _0)]
+    UnsupportedVersion(u32),
+
+    /// Legacy memo doesn’t start with a number of accounts.
+    BadAccountsCount,
+
+    /// Legacy memo has fewer accounts than declared.
+    MissingAccounts,
+
+    /// Legacy memo has no intent identifier or embedded memo.
+    MissingIntentId,
+}
+
+/// Version 1 representation of the memo on the wire.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Wire {
+    version: u32,
+    accounts: Vec<String>,
+    intent_id: String,
+    memo: String,
+}
+
+/// Representation used to determine version of a JSON memo before decoding
+/// the rest of the fields.
+#[derive(serde::Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl IntentMemo {
+    /// Encodes the memo using the current version of the encoding.
+    pub fn encode(&self) -> String {
+        serde_json::to_string(&Wire {
+            version: VERSION,
+            accounts: self.accounts.clone(),
+            intent_id: self.intent_id.clone(),
+            memo: self.memo.clone(),
+        })
+        .unwrap()
+    }
+
+    /// Decodes the memo.
+    ///
+    /// Memo which starts with `{` is decoded as a JSON object.  Otherwise it’s
+    /// decoded using the legacy comma-separated encoding.
+    pub fn decode(memo: &str) -> Result<Self, DecodeError> {
+        if memo.trim_start().starts_with('{') {
+            Self::decode_json(memo)
+        } else {
+            Self::decode_legacy(memo)
+        }
+    }
+
+    fn decode_json(memo: &str) -> Result<Self, DecodeError> {
+        let bad_json =
+            |err: serde_json::Error| DecodeError::BadJson(err.to_string());
+        let Version { version } =
+            serde_json::from_str(memo).map_err(bad_json)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let wire: Wire = serde_json::from_str(memo).map_err(bad_json)?;
+        Ok(Self {
+            accounts: wire.accounts,
+            intent_id: wire.intent_id,
+            memo: wire.memo,
+        })
+    }
+
+    fn decode_legacy(memo: &str) -> Result<Self, DecodeError> {
+        let (count, mut rest) =
+            memo.split_once(',').ok_or(DecodeError::BadAccountsCount)?;
+        let count = count
+            .parse::<usize>()
+            .map_err(|_| DecodeError::BadAccountsCount)?;
+        let mut accounts = Vec::with_capacity(count.min(32));
+        for _ in 0..count {
+            let (account, tail) =
+                rest.split_once(',').ok_or(DecodeError::MissingAccounts)?;
+            accounts.push(account.to_string());
+            rest = tail;
+        }
+        let (intent_id, memo) =
+            rest.split_once(',').ok_or(DecodeError::MissingIntentId)?;
+        Ok(Self {
+            accounts,
+            intent_id: intent_id.to_string(),
+            memo: memo.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+fn make_memo(accounts: &[&str], intent_id: &str, memo: &str) -> IntentMemo {
+    IntentMemo {
+        accounts: accounts.iter().map(|acc| acc.to_string()).collect(),
+        intent_id: intent_id.into(),
+        memo: memo.into(),
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    for memo in [
+        make_memo(&[], "", ""),
+        make_memo(&[], "intent", "memo"),
+        make_memo(&["account0"], "intent", "memo,with,comma"),
+        make_memo(&["account0", "account1", "account2"], "42", r#"{"x":1}"#),
+    ] {
+        let encoded = memo.encode();
+        assert_eq!(Ok(memo), IntentMemo::decode(&encoded), "{encoded}");
+    }
+}
+
+#[test]
+fn test_encode() {
+    let memo = make_memo(&["account0"], "intent", "memo");
+    assert_eq!(
+        concat!(
+            r#"{"version":1,"accounts":["account0"],"#,
+            r#""intent_id":"intent","memo":"memo"}"#,
+        ),
+        memo.encode()
+    );
+}
+
+#[test]
+fn test_decode_legacy() {
+    for (want, data) in [
+        (make_memo(&[], "intent", "memo"), "0,intent,memo"),
+        (make_memo(&[], "intent", "memo,comma"), "0,intent,memo,comma"),
+        (make_memo(&["account0"], "intent", "memo"), "1,account0,intent,memo"),
+        (
+            make_memo(&["account0", "account1"], "intent", ""),
+            "2,account0,account1,intent,",
+        ),
+        (make_memo(&["account0"], "", ""), "1,account0,,"),
+    ] {
+        assert_eq!(Ok(want), IntentMemo::decode(data), "{data}");
+    }
+
+    for (want, data) in [
+        (DecodeError::BadAccountsCount, ""),
+        (DecodeError::BadAccountsCount, "-1,intent,memo"),
+        (DecodeError::BadAccountsCount, "foo,intent,memo"),
+        (DecodeError::BadAccountsCount, ",intent,memo"),
+        (DecodeError::MissingAccounts, "2,account0"),
+        (DecodeError::MissingIntentId, "1,account0,intent"),
+        (DecodeError::MissingIntentId, "0,intent"),
+    ] {
+        assert_eq!(Err(want), IntentMemo::decode(data), "{data}");
+    }
+}
+
+#[test]
+fn test_decode_json_errors() {
+    assert_eq!(
+        Err(DecodeError::UnsupportedVersion(2)),
+        IntentMemo::decode(r#"{"version":2}"#)
+    );
+    for data in [
+        "{",
+        r#"{"accounts":[],"intent_id":"","memo":""}"#,
+        r#"{"version":1,"accounts":[],"intent_id":""}"#,
+        r#"{"version":1,"accounts":[],"intent_id":"","memo":"","extra":0}"#,
+    ] {
+        let got = IntentMemo::decode(data);
+        assert!(matches!(got, Err(DecodeError::BadJson(_))), "{data}: {got:?}");
+    }
+}
@@ -27,6 +27,24 @@ pub struct OutOfMemory;
 #[derive(Copy, Clone, Debug, PartialEq, Eq, derive_more::Display)]
 pub struct AddressTooLarge(pub NonZeroU32);
 
+/// Statistics of an allocator’s memory pool.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of blocks currently allocated.
+    pub allocated: usize,
+
+    /// Number of blocks which have been freed and are available for reuse.
+    ///
+    /// Those blocks still occupy space in the memory pool.  High number
+    /// compared to [`Self::allocated`] indicates fragmentation.
+    pub free: usize,
+}
+
+impl Stats {
+    /// Returns total number of blocks in the memory pool.
+    pub fn total(&self) -> usize { self.allocated + self.free }
+}
+
 impl Ptr {
     /// Largest value that can be stored in the pointer.
     // The two most significant bits are used internally in RawNode encoding
@@ -133,6 +151,24 @@ pub trait Allocator {
 
     /// Frees a block.
     fn free(&mut self, ptr: Ptr);
+
+    /// Returns statistics of the memory pool or `None` if the allocator
+    /// doesn’t track them.
+    fn stats(&self) -> Option<Stats> { None }
+
+    /// Moves value stored at given pointer to a block at a lower address.
+    ///
+    /// This is a hook for compaction.  If allocator has a free block at
+    /// a lower address available, it copies the value there, frees the old
+    /// block and returns pointer to the new location.  Caller is responsible
+    /// for updating all references to the old pointer.  Returns `None` if the
+    /// value has not been moved.
+    ///
+    /// Default implementation never moves blocks.
+    fn relocate(&mut self, ptr: Ptr) -> Option<Ptr> {
+        let _ = ptr;
+        None
+    }
 }
 
 /// A write log which can be committed or rolled back.
@@ -154,6 +190,11 @@ pub trait Allocator {
 /// write log.  The assumption is that nothing outside of the client of the
 /// write log knows the pointer thus in practice they cannot refer to those
 /// allocated but not-yet-committed nodes.
+///
+/// Changes can be partially rolled back with savepoints.  [`Self::savepoint`]
+/// marks the current state of the log and [`Self::rollback_to`] reverts all
+/// changes made since then while keeping the earlier ones.  This allows
+/// discarding a single failed operation without aborting the whole log.
 pub struct WriteLog<'a, A: Allocator> {
     /// Allocator to pass requests to.
     alloc: &'a mut A,
@@ -166,6 +207,27 @@ pub struct WriteLog<'a, A: Allocator> {
 
     /// List of nodes freed during the transaction.
     freed: Vec<Ptr>,
+
+    /// Stack of savepoints which can be rolled back to.
+    savepoints: Vec<Savepoint>,
+
+    /// Identifier to use for the next savepoint.
+    next_savepoint: u32,
+}
+
+/// Identifier of a savepoint in a [`WriteLog`]; see [`WriteLog::savepoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SavepointId(u32);
+
+/// State of a [`WriteLog`] at the moment a savepoint was created.
+struct Savepoint {
+    id: SavepointId,
+    /// Length of the `write_log` vector.
+    write_log: usize,
+    /// Length of the `allocated` vector.
+    allocated: usize,
+    /// Length of the `freed` vector.
+    freed: usize,
 }
 
 impl<'a, A: Allocator> WriteLog<'a, A> {
@@ -175,6 +237,51 @@ impl<'a, A: Allocator> WriteLog<'a, A> {
             write_log: Vec::new(),
             allocated: Vec::new(),
             freed: Vec::new(),
+            savepoints: Vec::new(),
+            next_savepoint: 0,
+        }
+    }
+
+    /// Creates a savepoint which changes can later be rolled back to.
+    ///
+    /// Savepoints nest.  Rolling back to a savepoint reverts changes made
+    /// after any savepoints created after it as well.
+    pub fn savepoint(&mut self) -> SavepointId {
+        let id = SavepointId(self.next_savepoint);
+        self.next_savepoint = self.next_savepoint.wrapping_add(1);
+        self.savepoints.push(Savepoint {
+            id,
+            write_log: self.write_log.len(),
+            allocated: self.allocated.len(),
+            freed: self.freed.len(),
+        });
+        id
+    }
+
+    /// Reverts all changes made since given savepoint has been created.
+    ///
+    /// The savepoint stays valid and can be rolled back to again.  Savepoints
+    /// created after it are discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the savepoint has been discarded (i.e. a savepoint created
+    /// before it has been rolled back to).  Savepoint identifiers are only
+    /// unique within a single log.  Passing identifier obtained from another
+    /// log is a logic error which may panic or roll back to an unrelated
+    /// savepoint.
+    pub fn rollback_to(&mut self, id: SavepointId) {
+        let idx = self
+            .savepoints
+            .iter()
+            .rposition(|savepoint| savepoint.id == id)
+            .expect("Invalid savepoint");
+        self.savepoints.truncate(idx + 1);
+        let savepoint = &self.savepoints[idx];
+        self.write_log.truncate(savepoint.write_log);
+        self.freed.truncate(savepoint.freed);
+        for ptr in self.allocated.drain(savepoint.allocated..) {
+            self.alloc.free(ptr)
         }
     }
 
@@ -196,7 +303,12 @@ impl<'a, A: Allocator> WriteLog<'a, A> {
     pub fn allocator(&self) -> &A { &*self.alloc }
 
     pub fn alloc(&mut self, value: A::Value) -> Result<Ptr, OutOfMemory> {
-        Ok(if let Some(ptr) = self.freed.pop() {
+        // Nodes freed before the last savepoint mustn’t be reused since
+        // rolling back to it would then lose track of them being freed.
+        let reusable = self.savepoints.last().map_or(0, |sp| sp.freed);
+        let ptr =
+            if self.freed.len() > reusable { self.freed.pop() } else { None };
+        Ok(if let Some(ptr) = ptr {
             self.set(ptr, value);
             ptr
         } else {
@@ -243,6 +355,17 @@ pub mod test_utils {
 
         pub fn count(&self) -> usize { self.count }
 
+        /// Returns set of currently allocated blocks.
+        ///
+        /// Together with [`AllocSnapshot::diff`] this can be used to find
+        /// blocks which have been leaked by a sequence of operations.
+        pub fn snapshot(&self) -> AllocSnapshot {
+            (0..self.pool.len())
+                .map(Self::ptr_from_index)
+                .filter(|ptr| !self.free_list.contains(ptr))
+                .collect()
+        }
+
         /// Gets index in the memory pool for the given pointer.
         ///
         /// Panics if the value of the pointer overflows `usize`.  This can only
@@ -275,6 +398,66 @@ pub mod test_utils {
         }
     }
 
+    /// Set of allocated blocks.
+    ///
+    /// Can be obtained from [`TestAllocator::snapshot`] or collected from an
+    /// iterator of pointers, e.g. nodes reachable from a data structure’s
+    /// root.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct AllocSnapshot(std::collections::BTreeSet<Ptr>);
+
+    /// Difference between two [`AllocSnapshot`]s.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct AllocDiff {
+        /// Blocks present in the later snapshot but not in the earlier one.
+        pub allocated: Vec<Ptr>,
+        /// Blocks present in the earlier snapshot but not in the later one.
+        pub freed: Vec<Ptr>,
+    }
+
+    impl AllocSnapshot {
+        /// Returns number of blocks in the snapshot.
+        pub fn len(&self) -> usize { self.0.len() }
+
+        /// Returns whether the snapshot is empty.
+        pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+        /// Returns whether given block is in the snapshot.
+        pub fn contains(&self, ptr: Ptr) -> bool { self.0.contains(&ptr) }
+
+        /// Returns blocks allocated and freed between `self` and `later`
+        /// snapshots.
+        pub fn diff(&self, later: &Self) -> AllocDiff {
+            AllocDiff {
+                allocated: later.0.difference(&self.0).copied().collect(),
+                freed: self.0.difference(&later.0).copied().collect(),
+            }
+        }
+    }
+
+    impl FromIterator<Ptr> for AllocSnapshot {
+        fn from_iter<I: IntoIterator<Item = Ptr>>(iter: I) -> Self {
+            Self(iter.into_iter().collect())
+        }
+    }
+
+    impl AllocDiff {
+        /// Returns whether the two snapshots were the same.
+        pub fn is_empty(&self) -> bool {
+            self.allocated.is_empty() && self.freed.is_empty()
+        }
+    }
+
+    impl fmt::Display for AllocDiff {
+        fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                fmtr,
+                "allocated: {:?}; freed: {:?}",
+                self.allocated, self.freed
+            )
+        }
+    }
+
     impl<T> Allocator for TestAllocator<T> {
         type Value = T;
 
@@ -323,6 +506,26 @@ pub mod test_utils {
             }
             self.count -= 1;
         }
+
+        fn stats(&self) -> Option<Stats> {
+            Some(Stats { allocated: self.count, free: self.free_list.len() })
+        }
+
+        /// Moves the value to the free block with the lowest address if it’s
+        /// lower than `ptr`.
+        #[track_caller]
+        fn relocate(&mut self, ptr: Ptr) -> Option<Ptr> {
+            let idx = self.check_allocated("relocate", ptr);
+            let new_ptr = self.free_list.iter().copied().min()?;
+            if new_ptr > ptr {
+                return None;
+            }
+            self.free_list.remove(&new_ptr);
+            self.pool.swap(idx, Self::index_from_ptr(new_ptr));
+            self.count += 1;
+            self.free(ptr);
+            Some(new_ptr)
+        }
     }
 }
 
@@ -417,6 +620,137 @@ mod test_write_log {
         assert_nodes(5, &alloc, &ptrs[..5], 0);
     }
 
+    #[test]
+    fn test_savepoint_rollback() {
+        let (mut alloc, ptrs) = make_allocator();
+        let mut wlog = WriteLog::new(&mut alloc);
+        wlog.set(ptrs[0], 100);
+        wlog.free(ptrs[9]);
+
+        let savepoint = wlog.savepoint();
+        wlog.set(ptrs[1], 101);
+        wlog.free(ptrs[8]);
+        // ptrs[8] has been freed after the savepoint so it can be reused but
+        // ptrs[9] mustn’t be.
+        assert_eq!(ptrs[8], wlog.alloc(102).unwrap());
+        assert_ne!(ptrs[9], wlog.alloc(103).unwrap());
+        assert_eq!(11, wlog.allocator().count());
+
+        wlog.rollback_to(savepoint);
+        assert_eq!(10, wlog.allocator().count());
+
+        // Savepoint remains valid after rolling back to it.
+        wlog.set(ptrs[2], 104);
+        wlog.rollback_to(savepoint);
+        wlog.commit();
+
+        assert_eq!(100, *alloc.get(ptrs[0]));
+        assert_nodes(9, &alloc, &ptrs[1..9], 1);
+    }
+
+    #[test]
+    fn test_nested_savepoints() {
+        let (mut alloc, ptrs) = make_allocator();
+        let mut wlog = WriteLog::new(&mut alloc);
+        wlog.savepoint();
+        wlog.set(ptrs[0], 100);
+        let inner = wlog.savepoint();
+        wlog.set(ptrs[1], 101);
+        wlog.free(ptrs[2]);
+        wlog.rollback_to(inner);
+        wlog.set(ptrs[3], 103);
+        wlog.commit();
+        assert_eq!(10, alloc.count());
+        assert_eq!(100, *alloc.get(ptrs[0]));
+        assert_eq!(1, *alloc.get(ptrs[1]));
+        assert_eq!(103, *alloc.get(ptrs[3]));
+
+        let mut wlog = WriteLog::new(&mut alloc);
+        let outer = wlog.savepoint();
+        wlog.set(ptrs[0], 200);
+        wlog.savepoint();
+        wlog.alloc(201).unwrap();
+        wlog.rollback_to(outer);
+        wlog.commit();
+        assert_eq!(10, alloc.count());
+        assert_eq!(100, *alloc.get(ptrs[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid savepoint")]
+    fn test_discarded_savepoint() {
+        let (mut alloc, _) = make_allocator();
+        let mut wlog = WriteLog::new(&mut alloc);
+        let outer = wlog.savepoint();
+        let inner = wlog.savepoint();
+        wlog.rollback_to(outer);
+        wlog.rollback_to(inner);
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let (mut alloc, ptrs) = make_allocator();
+        let before = alloc.snapshot();
+        assert_eq!(10, before.len());
+        assert!(ptrs.iter().all(|ptr| before.contains(*ptr)));
+
+        // Rolled back allocations don’t leak.
+        let mut wlog = WriteLog::new(&mut alloc);
+        (10..20).for_each(|num| {
+            let _ = wlog.alloc(num).unwrap();
+        });
+        core::mem::drop(wlog);
+        assert!(before.diff(&alloc.snapshot()).is_empty());
+
+        // Committed allocations and frees are reported.
+        let mut wlog = WriteLog::new(&mut alloc);
+        let new_ptr = wlog.alloc(20).unwrap();
+        wlog.free(ptrs[3]);
+        wlog.commit();
+        let diff = before.diff(&alloc.snapshot());
+        assert_eq!(
+            test_utils::AllocDiff {
+                allocated: vec![new_ptr],
+                freed: vec![ptrs[3]]
+            },
+            diff
+        );
+        assert!(!diff.is_empty());
+
+        let want: test_utils::AllocSnapshot = ptrs
+            .iter()
+            .copied()
+            .filter(|ptr| *ptr != ptrs[3])
+            .chain(Some(new_ptr))
+            .collect();
+        assert_eq!(want, alloc.snapshot());
+    }
+
+    #[test]
+    fn test_stats_and_relocate() {
+        let (mut alloc, ptrs) = make_allocator();
+        assert_eq!(Some(Stats { allocated: 10, free: 0 }), alloc.stats());
+
+        // Nothing to relocate to.
+        assert_eq!(None, alloc.relocate(ptrs[8]));
+
+        alloc.free(ptrs[2]);
+        alloc.free(ptrs[5]);
+        assert_eq!(Some(Stats { allocated: 8, free: 2 }), alloc.stats());
+
+        // Blocks are moved to lower addresses only.
+        assert_eq!(None, alloc.relocate(ptrs[1]));
+        assert_eq!(Some(ptrs[2]), alloc.relocate(ptrs[8]));
+        assert_eq!(8, *alloc.get(ptrs[2]));
+        assert_eq!(Some(Stats { allocated: 8, free: 2 }), alloc.stats());
+
+        // Relocating the last block shrinks the pool.
+        assert_eq!(Some(ptrs[5]), alloc.relocate(ptrs[9]));
+        assert_eq!(9, *alloc.get(ptrs[5]));
+        assert_eq!(Some(Stats { allocated: 8, free: 1 }), alloc.stats());
+        assert_eq!(8, alloc.count());
+    }
+
     #[test]
     fn test_free_rollback() {
         let (mut alloc, ptrs) = make_allocator();
@@ -851,6 +851,37 @@ impl fmt::Debug for Owned {
     }
 }
 
+// Encoding: <offset as u8> <length as u16> <bytes>
+#[cfg(feature = "borsh")]
+impl borsh::BorshSerialize for Owned {
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        wr: &mut W,
+    ) -> borsh::maybestd::io::Result<()> {
+        let header = (u8::from(self.offset), self.length);
+        borsh::BorshSerialize::serialize(&header, wr)?;
+        wr.write_all(&self.bytes[..])
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl borsh::BorshDeserialize for Owned {
+    fn deserialize_reader<R: borsh::maybestd::io::Read>(
+        rd: &mut R,
+    ) -> borsh::maybestd::io::Result<Self> {
+        let (offset, length): (u8, u16) =
+            borsh::BorshDeserialize::deserialize_reader(rd)?;
+        let offset = U3::try_from(offset).map_err(|_| {
+            borsh::maybestd::io::Error::from(
+                borsh::maybestd::io::ErrorKind::InvalidData,
+            )
+        })?;
+        let mut bytes = alloc::vec![0; bytes_len(offset, length)];
+        rd.read_exact(&mut bytes)?;
+        Ok(Self { offset, length, bytes })
+    }
+}
+
 /// Internal function for debug formatting objects objects.
 fn debug_fmt(
     name: &str,
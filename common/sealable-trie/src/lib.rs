@@ -8,7 +8,7 @@ pub mod nodes;
 pub mod proof;
 pub mod trie;
 
-pub use trie::{Error, Trie};
+pub use trie::{Error, Snapshot, Trie};
 
 pub trait Allocator: memory::Allocator<Value = nodes::RawNode> {}
 
@@ -29,6 +29,16 @@ pub struct Membership(Vec<Item>);
 #[derive(Clone, PartialEq)]
 pub struct NonMembership(Option<Box<Actual>>, Vec<Item>);
 
+/// Proofs of membership or non-membership of multiple keys.
+///
+/// Proofs of keys sharing a prefix end with the same items since those
+/// correspond to nodes near the root of the trie.  The batch stores each such
+/// common tail only once: each proof records how many of its final items are
+/// the same as final items of the preceding proof.  Sorting keys before
+/// creating the batch therefore maximises the savings.
+#[derive(Clone, Default, PartialEq)]
+pub struct Batch(Vec<(u16, Proof)>);
+
 /// A single item in a proof corresponding to a node in the trie.
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Item {
@@ -66,6 +76,18 @@ pub(crate) struct OwnedRef {
 /// Builder for the proof.
 pub(crate) struct Builder(Vec<Item>);
 
+/// A single hashing step of a membership proof.
+///
+/// Hash of each node on the path from the value to the root of the trie is
+/// `sha256(prefix || child || suffix)` where `child` is the hash calculated in
+/// the previous step (or the value hash in the first step).  This is the same
+/// form as inner operations of ICS-23 proofs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashStep {
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+}
+
 impl Proof {
     /// Verifies that this object proves membership or non-membership of given
     /// key.
@@ -99,6 +121,97 @@ impl Proof {
 
     /// Creates a builder which allows creation of proofs.
     pub(crate) fn builder() -> Builder { Builder(Vec::new()) }
+
+    /// Returns items of the proof ordered from the leaf to the root.
+    fn items(&self) -> &Vec<Item> {
+        match self {
+            Self::Positive(proof) => &proof.0,
+            Self::Negative(proof) => &proof.1,
+        }
+    }
+
+    /// Returns mutable items of the proof ordered from the leaf to the root.
+    fn items_mut(&mut self) -> &mut Vec<Item> {
+        match self {
+            Self::Positive(proof) => &mut proof.0,
+            Self::Negative(proof) => &mut proof.1,
+        }
+    }
+}
+
+impl Batch {
+    /// Creates a batch from given proofs.
+    ///
+    /// Order of the proofs is preserved.
+    pub fn new(proofs: impl IntoIterator<Item = Proof>) -> Self {
+        let mut prev = Vec::new();
+        let entries = proofs
+            .into_iter()
+            .map(|mut proof| {
+                let items = proof.items_mut();
+                let shared = common_suffix_len(&prev, items);
+                let own = items.len() - usize::from(shared);
+                prev = items.clone();
+                items.truncate(own);
+                (shared, proof)
+            })
+            .collect();
+        Self(entries)
+    }
+
+    /// Returns number of proofs in the batch.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns whether the batch is empty.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Returns all proofs in the batch in the order they were added.
+    pub fn into_proofs(self) -> Vec<Proof> {
+        let mut proofs = Vec::<Proof>::with_capacity(self.0.len());
+        for (shared, mut proof) in self.0 {
+            if let Some(prev) = proofs.last() {
+                let prev = prev.items();
+                let tail = &prev[prev.len() - usize::from(shared)..];
+                proof.items_mut().extend_from_slice(tail);
+            }
+            proofs.push(proof);
+        }
+        proofs
+    }
+
+    /// Verifies that proofs in the batch prove membership or non-membership
+    /// of given keys.
+    ///
+    /// `entries` are paired with proofs in the batch in order.  Verification
+    /// fails if the number of entries doesn’t match number of proofs.  See
+    /// [`Proof::verify`] for description of the `value_hash` argument.
+    pub fn verify<'a>(
+        self,
+        root_hash: &CryptoHash,
+        entries: impl IntoIterator<Item = (&'a [u8], Option<&'a CryptoHash>)>,
+    ) -> bool {
+        let mut proofs = self.into_proofs().into_iter();
+        for (key, value_hash) in entries {
+            match proofs.next() {
+                Some(proof) if proof.verify(root_hash, key, value_hash) => (),
+                _ => return false,
+            }
+        }
+        proofs.next().is_none()
+    }
+}
+
+/// Returns number of equal items at the ends of both slices.
+fn common_suffix_len(lhs: &[Item], rhs: &[Item]) -> u16 {
+    let len = lhs
+        .iter()
+        .rev()
+        .zip(rhs.iter().rev())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    // Proofs longer than u16::MAX items cannot be serialised anyway so
+    // limiting the shared length is never a problem in practice.
+    u16::try_from(len).unwrap_or(u16::MAX)
 }
 
 impl Membership {
@@ -118,6 +231,68 @@ impl Membership {
             false
         }
     }
+
+    /// Converts the proof into hashing steps from the value to the root.
+    ///
+    /// The proof doesn’t include the key so it must be provided by the caller.
+    /// Returns `None` if the proof doesn’t match the key, i.e. if key length
+    /// doesn’t agree with the path the proof describes.
+    pub fn hash_steps(&self, key: &[u8]) -> Option<Vec<HashStep>> {
+        let mut key = bits::Slice::from_bytes(key)?;
+        let mut is_value = true;
+        let mut steps = Vec::with_capacity(self.0.len());
+        for item in self.0.iter() {
+            let step = match item {
+                Item::Branch(them) => {
+                    let theirs = them.hash.as_slice();
+                    if key.pop_back()? {
+                        let tag =
+                            (u8::from(them.is_value) << 1) | u8::from(is_value);
+                        HashStep {
+                            prefix: [&[tag][..], theirs].concat(),
+                            suffix: Vec::new(),
+                        }
+                    } else {
+                        let tag =
+                            (u8::from(is_value) << 1) | u8::from(them.is_value);
+                        HashStep {
+                            prefix: alloc::vec![tag],
+                            suffix: theirs.to_vec(),
+                        }
+                    }
+                }
+                Item::Extension(length) => {
+                    let key = key.pop_back_slice(length.get())?;
+                    let key = ExtKey::try_from(key).ok()?;
+                    let mut buf = [0; 36];
+                    let tag = 0x80 | (u8::from(is_value) << 4);
+                    let len = key.encode_into(&mut buf, tag);
+                    HashStep { prefix: buf[..len].to_vec(), suffix: Vec::new() }
+                }
+            };
+            steps.push(step);
+            is_value = false;
+        }
+        key.is_empty().then_some(steps)
+    }
+
+    /// Reconstructs the proof from hashing steps.
+    ///
+    /// This is an inverse of [`Self::hash_steps`].  Returns `None` if any of
+    /// the steps doesn’t correspond to a trie node.  Note that Branch steps
+    /// don’t carry information about which child is ours once converted into
+    /// a proof (that’s determined by the key during verification) so callers
+    /// which care about the exact form of the steps should compare them with
+    /// [`Self::hash_steps`] of the result.
+    pub fn from_hash_steps<'a>(
+        steps: impl IntoIterator<Item = (&'a [u8], &'a [u8])>,
+    ) -> Option<Self> {
+        steps
+            .into_iter()
+            .map(|(prefix, suffix)| Item::from_hash_step(prefix, suffix))
+            .collect::<Option<Vec<_>>>()
+            .map(Self)
+    }
 }
 
 impl NonMembership {
@@ -241,6 +416,33 @@ impl Item {
     pub fn extension(length: u16) -> Option<Self> {
         NonZeroU16::new(length).map(Self::Extension)
     }
+
+    /// Parses a hashing step as produced by [`Membership::hash_steps`].
+    fn from_hash_step(prefix: &[u8], suffix: &[u8]) -> Option<Self> {
+        let (&tag, rest) = prefix.split_first()?;
+        if tag & 0xE0 == 0x80 {
+            // tag = 0b100v_???? — an Extension node.  Re-encode the key to
+            // make sure the prefix is in canonical form.
+            let key = ExtKey::decode(prefix, tag & 0xF0)?;
+            let mut buf = [0; 36];
+            let len = key.encode_into(&mut buf, tag & 0xF0);
+            (suffix.is_empty() && &buf[..len] == prefix)
+                .then(|| Self::extension(key.len()))
+                .flatten()
+        } else if tag & !3 != 0 {
+            None
+        } else if rest.is_empty() {
+            // We’re the left child; their hash is in the suffix.
+            let hash = CryptoHash::try_from(suffix).ok()?;
+            Some(Self::Branch(OwnedRef { is_value: tag & 1 != 0, hash }))
+        } else if suffix.is_empty() {
+            // We’re the right child; their hash is in the prefix.
+            let hash = CryptoHash::try_from(rest).ok()?;
+            Some(Self::Branch(OwnedRef { is_value: tag & 2 != 0, hash }))
+        } else {
+            None
+        }
+    }
 }
 
 impl Builder {
@@ -400,6 +602,14 @@ impl fmt::Debug for NonMembership {
     }
 }
 
+impl fmt::Debug for Batch {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_list()
+            .entries(self.0.iter().map(|(shared, proof)| (shared, proof)))
+            .finish()
+    }
+}
+
 impl fmt::Debug for OwnedRef {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
         let what = if self.is_value { "value:" } else { "node:" };
@@ -453,6 +663,92 @@ fn test_simple_success() {
     }
 }
 
+#[test]
+fn test_batch() {
+    let mut trie = crate::trie::Trie::test(1000);
+    for (idx, key) in ["foo", "bar", "baz", "qux", "quux"].iter().enumerate() {
+        trie.set(key.as_bytes(), &CryptoHash::test(idx)).unwrap();
+    }
+
+    let keys = ["bar", "bay", "baz", "foo", "fo", "quux", "qux"];
+    let (values, proofs): (Vec<_>, Vec<_>) =
+        keys.iter().map(|key| trie.prove(key.as_bytes()).unwrap()).unzip();
+
+    let batch = Batch::new(proofs.clone());
+    assert_eq!(keys.len(), batch.len());
+    // Proofs of neighbouring keys share items near the root.
+    let own_items = |batch: &Batch| -> usize {
+        batch.0.iter().map(|(_, proof)| proof.items().len()).sum()
+    };
+    let all_items: usize = proofs.iter().map(|proof| proof.items().len()).sum();
+    assert!(own_items(&batch) < all_items);
+    assert_eq!(proofs, batch.clone().into_proofs());
+
+    let entries = || {
+        keys.iter()
+            .map(|key| key.as_bytes())
+            .zip(values.iter().map(Option::as_ref))
+    };
+    assert!(batch.clone().verify(trie.hash(), entries()));
+
+    // Too few or too many entries.
+    assert!(!batch.clone().verify(trie.hash(), entries().skip(1)));
+    assert!(!batch.clone().verify(
+        trie.hash(),
+        entries().chain(core::iter::once(("foo".as_bytes(), None)))
+    ));
+
+    // Mismatched entry.
+    let wrong = CryptoHash::test(1);
+    let entries = entries().map(|(key, value)| {
+        (key, if key == b"bay" { Some(&wrong) } else { value })
+    });
+    assert!(!batch.clone().verify(trie.hash(), entries));
+
+    // Empty batch.
+    assert!(Batch::new([]).verify(trie.hash(), core::iter::empty()));
+}
+
+#[test]
+fn test_hash_steps() {
+    let mut trie = crate::trie::Trie::test(1000);
+    for (idx, key) in ["foo", "bar", "baz", "qux", "quux"].iter().enumerate() {
+        trie.set(key.as_bytes(), &CryptoHash::test(idx)).unwrap();
+    }
+
+    for (idx, key) in ["foo", "bar", "baz", "qux", "quux"].iter().enumerate() {
+        let key = key.as_bytes();
+        let proof = match trie.prove(key).unwrap() {
+            (Some(_), Proof::Positive(proof)) => proof,
+            got => panic!("Unexpected proof for {key:?}: {got:?}"),
+        };
+        let steps = proof.hash_steps(key).unwrap();
+
+        // Applying the steps to the value hash yields the root hash.
+        let root = steps.iter().fold(CryptoHash::test(idx), |hash, step| {
+            CryptoHash::digestv(&[&step.prefix, hash.as_slice(), &step.suffix])
+        });
+        assert_eq!(trie.hash(), &root, "key: {key:?}");
+
+        // Steps can be converted back into the proof.
+        let got = Membership::from_hash_steps(
+            steps.iter().map(|step| (&step.prefix[..], &step.suffix[..])),
+        );
+        assert_eq!(Some(&proof), got.as_ref(), "key: {key:?}");
+
+        // Steps don’t match a different key.
+        assert_eq!(None, proof.hash_steps(b"fo"));
+    }
+
+    // Malformed steps.
+    let bad: [(&[u8], &[u8]); 4] =
+        [(&[], &[]), (&[4], &[0; 32]), (&[0], &[0; 31]), (&[0; 33], &[0; 32])];
+    for (prefix, suffix) in bad {
+        let got = Membership::from_hash_steps([(prefix, suffix)]);
+        assert_eq!(None, got, "prefix: {prefix:?}; suffix: {suffix:?}");
+    }
+}
+
 #[test]
 fn test_debug() {
     use alloc::format;
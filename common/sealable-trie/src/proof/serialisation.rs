@@ -11,7 +11,7 @@ use lib::hash::CryptoHash;
 #[cfg(test)]
 use pretty_assertions::assert_eq;
 
-use super::{Actual, Item, OwnedRef, Proof};
+use super::{Actual, Batch, Item, OwnedRef, Proof};
 
 const NON_MEMBERSHIP_SHIFT: u32 = 15;
 
@@ -94,6 +94,44 @@ impl BorshDeserialize for Proof {
     }
 }
 
+// Encoding: <proofs.len() as u32> (<shared as u16> <proof>)*
+impl BorshSerialize for Batch {
+    fn serialize<W: io::Write>(&self, wr: &mut W) -> io::Result<()> {
+        u32::try_from(self.0.len())
+            .map_err(|_| {
+                invalid_data(format!("batch too long: {}", self.0.len()))
+            })?
+            .serialize(wr)?;
+        for (shared, proof) in self.0.iter() {
+            shared.serialize(wr)?;
+            proof.serialize(wr)?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for Batch {
+    fn deserialize_reader<R: io::Read>(rd: &mut R) -> io::Result<Self> {
+        let len = u32::deserialize_reader(rd)?;
+        let mut entries = Vec::new();
+        // Number of items in the previous proof once shared items are
+        // included.  Each proof may share at most that many items.
+        let mut prev_len = 0;
+        for _ in 0..len {
+            let shared = u16::deserialize_reader(rd)?;
+            let proof = Proof::deserialize_reader(rd)?;
+            if usize::from(shared) > prev_len {
+                return Err(invalid_data(format!(
+                    "too many shared items: {shared} > {prev_len}"
+                )));
+            }
+            prev_len = proof.items().len() + usize::from(shared);
+            entries.push((shared, proof));
+        }
+        Ok(Self(entries))
+    }
+}
+
 // Encoding:
 //  - 0x00 <hash>  — Branch with node child
 //  - 0x10 <hash>  — Branch with value child
@@ -498,3 +536,36 @@ fn test_proof_borsh() {
         ],
     );
 }
+
+#[test]
+fn test_batch_borsh() {
+    use alloc::vec;
+
+    let item = Item::Extension(NonZeroU16::new(42).unwrap());
+    let other = Item::Extension(NonZeroU16::new(24).unwrap());
+    let positive =
+        |items: &[Item]| -> Proof { super::Membership(items.to_vec()).into() };
+    let proofs = vec![
+        positive(&[item.clone(), item.clone()]),
+        positive(&[other.clone(), item.clone()]),
+        Proof::Negative(super::NonMembership(None, vec![other.clone()])),
+    ];
+    let batch = Batch::new(proofs.clone());
+
+    #[rustfmt::skip]
+    let want_bytes: &[u8] = &[
+        /* len: */ 3, 0, 0, 0,
+        /* shared: */ 0, 0, /* proof: */ 2, 0, 32, 42, 32, 42,
+        /* shared: */ 1, 0, /* proof: */ 1, 0, 32, 24,
+        /* shared: */ 0, 0, /* proof: */ 1, 0x80, 32, 24,
+    ];
+    assert_eq!(want_bytes, borsh::to_vec(&batch).unwrap().as_slice());
+    let got = Batch::try_from_slice(want_bytes).unwrap();
+    assert_eq!(batch, got);
+    assert_eq!(proofs, got.into_proofs());
+
+    // Can’t share more items than the previous proof had.
+    let got = Batch::try_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0])
+        .map_err(|err| err.to_string());
+    assert_eq!(Err("too many shared items: 1 > 0".into()), got);
+}
@@ -1,5 +1,5 @@
 use alloc::vec::Vec;
-use core::num::NonZeroU16;
+use core::num::{NonZeroU16, NonZeroUsize};
 
 use lib::hash::CryptoHash;
 use memory::Ptr;
@@ -14,6 +14,8 @@ mod set;
 #[cfg(test)]
 mod tests;
 
+pub use iter::{Cursor, Entry};
+
 /// Root trie hash if the trie is empty.
 pub const EMPTY_TRIE_ROOT: CryptoHash = CryptoHash::DEFAULT;
 
@@ -131,6 +133,12 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
     /// Returns whether the trie is empty.
     pub fn is_empty(&self) -> bool { self.root_hash == EMPTY_TRIE_ROOT }
 
+    /// Returns statistics of the allocator’s memory pool or `None` if the
+    /// allocator doesn’t track them.
+    ///
+    /// Can be used to monitor fragmentation of the memory used by the trie.
+    pub fn alloc_stats(&self) -> Option<memory::Stats> { self.alloc.stats() }
+
     /// Deconstructs the object into the individual parts — allocator, root
     /// pointer and root hash.
     pub fn into_parts(self) -> (A, Option<Ptr>, CryptoHash) {
@@ -150,13 +158,19 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
         Self { root_ptr, root_hash, alloc }
     }
 
+    /// Returns a read-only snapshot of the trie.
+    ///
+    /// See [`Snapshot`] for more details.
+    pub fn snapshot(&self) -> Snapshot<'_, A> {
+        Snapshot::new(&self.alloc, self.root_ptr, self.root_hash)
+    }
+
     /// Retrieves value at given key.
     ///
     /// Returns `None` if there’s no value at given key.  Returns an error if
     /// the value (or its ancestor) has been sealed.
     pub fn get(&self, key: &[u8]) -> Result<Option<CryptoHash>> {
-        let (value, _) = self.get_impl(key, false)?;
-        Ok(value)
+        self.snapshot().get(key)
     }
 
     /// Retrieves value at given key and provides proof of the result.
@@ -167,82 +181,42 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
         &self,
         key: &[u8],
     ) -> Result<(Option<CryptoHash>, proof::Proof)> {
-        let (value, proof) = self.get_impl(key, true)?;
-        Ok((value, proof.unwrap()))
+        self.snapshot().prove(key)
     }
 
-    fn get_impl(
-        &self,
-        key: &[u8],
-        include_proof: bool,
-    ) -> Result<(Option<CryptoHash>, Option<proof::Proof>)> {
-        let mut key = bits::Slice::from_bytes(key).ok_or(Error::KeyTooLong)?;
-        if self.root_hash == EMPTY_TRIE_ROOT {
-            let proof = include_proof.then(proof::Proof::empty_trie);
-            return Ok((None, proof));
-        }
-
-        let mut proof = include_proof.then(proof::Proof::builder);
-        let mut node_ptr = self.root_ptr;
-        let mut node_hash = self.root_hash;
-        loop {
-            let node = self.alloc.get(node_ptr.ok_or(Error::Sealed)?);
-            let node = node.decode()?;
-            debug_assert_eq!(node_hash, node.hash());
-
-            let child = match node {
-                Node::Branch { children } => {
-                    if let Some(us) = key.pop_front() {
-                        proof!(proof push proof::Item::branch(us, &children));
-                        children[usize::from(us)]
-                    } else {
-                        let proof = proof!(proof rev.reached_branch(children));
-                        return Ok((None, proof));
-                    }
-                }
-
-                Node::Extension { key: ext_key, child } => {
-                    if key.strip_prefix(ext_key.into()) {
-                        proof!(proof push proof::Item::extension(ext_key.len()).unwrap());
-                        child
-                    } else {
-                        let proof = proof!(proof rev.reached_extension(key.len(), ext_key, child));
-                        return Ok((None, proof));
-                    }
-                }
-            };
+    /// Returns all keys and values in a given subtrie.
+    pub fn get_subtrie(&self, key: &[u8]) -> Result<Vec<iter::Entry>> {
+        self.snapshot().get_subtrie(key)
+    }
 
-            match child {
-                Reference::Node(node) => {
-                    node_ptr = node.ptr;
-                    node_hash = *node.hash;
-                }
-                Reference::Value(value) => {
-                    return if value.is_sealed {
-                        Err(Error::Sealed)
-                    } else if let Some(len) = NonZeroU16::new(key.len()) {
-                        let proof =
-                            proof!(proof rev.lookup_key_left(len, *value.hash));
-                        Ok((None, proof))
-                    } else {
-                        let proof = proof!(proof rev.build());
-                        Ok((Some(*value.hash), proof))
-                    };
-                }
-            };
-        }
+    /// Returns iterator over all keys and values whose key starts with given
+    /// prefix.
+    ///
+    /// Returned keys include the prefix and are ordered lexicographically.
+    /// Since values of sealed keys are no longer known, sealed entries are
+    /// skipped.  Use [`Self::get_subtrie`] to learn about sealed subtries.
+    pub fn iter_prefix(
+        &self,
+        prefix: &[u8],
+    ) -> Result<impl Iterator<Item = (Vec<u8>, CryptoHash)>> {
+        self.snapshot().iter_prefix(prefix)
     }
 
-    /// Returns all keys and values in a given subtrie.
-    pub fn get_subtrie<'a>(
-        &'a self,
-        key: &'a [u8],
-    ) -> Result<Vec<iter::Entry>> {
-        if self.is_empty() {
-            Ok(Vec::new())
-        } else {
-            iter::get_entries(&self.alloc, self.root_ptr, key)
-        }
+    /// Returns at most `limit` keys and values in a given subtrie.
+    ///
+    /// Entries are returned in lexicographic order of their keys.  If `cursor`
+    /// is given, listing starts with the first entry following the cursor.  If
+    /// there are more entries than `limit`, returns a cursor which can be
+    /// passed to a subsequent call to continue the listing.  This allows
+    /// enumerating large subtries in bounded chunks, e.g. over multiple
+    /// transactions.
+    pub fn get_subtrie_page(
+        &self,
+        key: &[u8],
+        cursor: Option<&iter::Cursor>,
+        limit: NonZeroUsize,
+    ) -> Result<(Vec<iter::Entry>, Option<iter::Cursor>)> {
+        self.snapshot().get_subtrie_page(key, cursor, limit)
     }
 
     /// Inserts a new value hash at given key.
@@ -270,6 +244,55 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
         Ok(())
     }
 
+    /// Inserts multiple value hashes at given keys.
+    ///
+    /// Behaves as if [`Self::set`] was called for each of the entries except
+    /// that hashes of nodes on paths shared by multiple keys are computed only
+    /// once.  If the same key appears multiple times, the last value is used.
+    /// The operation is atomic: if setting any of the keys fails, none of the
+    /// values are inserted.
+    ///
+    /// Note that the shape of the resulting trie (and thus its root hash) may
+    /// differ from one created with a series of [`Self::set`] calls when
+    /// keys longer than 34 bytes are used.  This is no different than when
+    /// calling [`Self::set`] with the same keys in different order.
+    pub fn set_many<'k>(
+        &mut self,
+        entries: impl IntoIterator<Item = (&'k [u8], CryptoHash)>,
+    ) -> Result<()> {
+        let mut entries = entries.into_iter().collect::<Vec<_>>();
+        entries.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+        entries.dedup_by(|later, earlier| {
+            let duplicate = later.0 == earlier.0;
+            if duplicate {
+                earlier.1 = later.1;
+            }
+            duplicate
+        });
+        let mut entries = entries
+            .into_iter()
+            .map(|(key, hash)| {
+                let key =
+                    bits::Slice::from_bytes(key).ok_or(Error::KeyTooLong)?;
+                if key.is_empty() {
+                    return Err(Error::EmptyKey);
+                }
+                Ok((key, hash))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let (ptr, hash) = set::ManyContext::new(&mut self.alloc).set(
+            self.root_ptr,
+            &self.root_hash,
+            &mut entries,
+        )?;
+        self.root_ptr = Some(ptr);
+        self.root_hash = hash;
+        Ok(())
+    }
+
     /// Seals value at given key as well as all descendant values.
     ///
     /// Once value is sealed, its hash can no longer be retrieved nor can it be
@@ -333,6 +356,33 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
         }
     }
 
+    /// Returns pointers to all nodes reachable from the root.  Used for
+    /// testing only.
+    ///
+    /// Any other node allocated in the allocator has been leaked.
+    #[cfg(test)]
+    pub(crate) fn reachable_nodes(&self) -> Vec<Ptr> {
+        let mut nodes = Vec::new();
+        let mut stack: Vec<Ptr> = self.root_ptr.into_iter().collect();
+        while let Some(ptr) = stack.pop() {
+            nodes.push(ptr);
+            let mut push = |rf| {
+                if let Reference::Node(NodeRef { ptr: Some(ptr), .. }) = rf {
+                    stack.push(ptr);
+                }
+            };
+            match self.alloc.get(ptr).decode() {
+                Ok(Node::Branch { children }) => {
+                    push(children[0]);
+                    push(children[1]);
+                }
+                Ok(Node::Extension { child, .. }) => push(child),
+                Err(err) => panic!("{ptr}: {err}"),
+            }
+        }
+        nodes
+    }
+
     /// Prints the trie.  Used for testing and debugging only.
     #[cfg(test)]
     pub(crate) fn print(&self) {
@@ -383,6 +433,195 @@ impl<A: memory::Allocator<Value = Value>> Trie<A> {
 }
 
 
+/// Read-only view of a trie.
+///
+/// The snapshot captures the root of the trie and a shared reference to the
+/// allocator.  It offers all the operations which don’t modify the trie, e.g.
+/// getting values or generating proofs.  Unlike [`Trie`], it is `Copy` and it
+/// is `Send` and `Sync` so long as the allocator is `Sync`.  This allows
+/// serving read requests from multiple threads concurrently.
+///
+/// Since the snapshot borrows the allocator, the trie cannot be modified while
+/// the snapshot is alive.
+#[derive(Debug)]
+pub struct Snapshot<'a, A> {
+    /// Pointer to the root node. `None` if the trie is empty or the root node
+    /// has been sealed.
+    root_ptr: Option<Ptr>,
+
+    /// Hash of the root node; [`EMPTY_TRIE_ROOT`] if trie is empty.
+    root_hash: CryptoHash,
+
+    /// Allocator used to access nodes.
+    alloc: &'a A,
+}
+
+impl<A> Clone for Snapshot<'_, A> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<A> Copy for Snapshot<'_, A> {}
+
+impl<'a, A: memory::Allocator<Value = Value>> Snapshot<'a, A> {
+    /// Creates a new snapshot from individual parts.
+    ///
+    /// It’s up to the caller to guarantee that the `root_ptr` and `root_hash`
+    /// values are correct and correspond to nodes stored within the pool
+    /// allocator `alloc`.
+    pub fn new(
+        alloc: &'a A,
+        root_ptr: Option<Ptr>,
+        root_hash: CryptoHash,
+    ) -> Self {
+        Self { root_ptr, root_hash, alloc }
+    }
+
+    /// Returns hash of the root node.
+    pub fn hash(&self) -> &CryptoHash { &self.root_hash }
+
+    /// Returns whether the trie is empty.
+    pub fn is_empty(&self) -> bool { self.root_hash == EMPTY_TRIE_ROOT }
+
+    /// Retrieves value at given key.
+    ///
+    /// Returns `None` if there’s no value at given key.  Returns an error if
+    /// the value (or its ancestor) has been sealed.
+    pub fn get(&self, key: &[u8]) -> Result<Option<CryptoHash>> {
+        let (value, _) = self.get_impl(key, false)?;
+        Ok(value)
+    }
+
+    /// Retrieves value at given key and provides proof of the result.
+    ///
+    /// Returns `None` if there’s no value at given key.  Returns an error if
+    /// the value (or its ancestor) has been sealed.
+    pub fn prove(
+        &self,
+        key: &[u8],
+    ) -> Result<(Option<CryptoHash>, proof::Proof)> {
+        let (value, proof) = self.get_impl(key, true)?;
+        Ok((value, proof.unwrap()))
+    }
+
+    fn get_impl(
+        &self,
+        key: &[u8],
+        include_proof: bool,
+    ) -> Result<(Option<CryptoHash>, Option<proof::Proof>)> {
+        let mut key = bits::Slice::from_bytes(key).ok_or(Error::KeyTooLong)?;
+        if self.root_hash == EMPTY_TRIE_ROOT {
+            let proof = include_proof.then(proof::Proof::empty_trie);
+            return Ok((None, proof));
+        }
+
+        let mut proof = include_proof.then(proof::Proof::builder);
+        let mut node_ptr = self.root_ptr;
+        let mut node_hash = self.root_hash;
+        loop {
+            let node = self.alloc.get(node_ptr.ok_or(Error::Sealed)?);
+            let node = node.decode()?;
+            debug_assert_eq!(node_hash, node.hash());
+
+            let child = match node {
+                Node::Branch { children } => {
+                    if let Some(us) = key.pop_front() {
+                        proof!(proof push proof::Item::branch(us, &children));
+                        children[usize::from(us)]
+                    } else {
+                        let proof = proof!(proof rev.reached_branch(children));
+                        return Ok((None, proof));
+                    }
+                }
+
+                Node::Extension { key: ext_key, child } => {
+                    if key.strip_prefix(ext_key.into()) {
+                        proof!(proof push proof::Item::extension(ext_key.len()).unwrap());
+                        child
+                    } else {
+                        let proof = proof!(proof rev.reached_extension(key.len(), ext_key, child));
+                        return Ok((None, proof));
+                    }
+                }
+            };
+
+            match child {
+                Reference::Node(node) => {
+                    node_ptr = node.ptr;
+                    node_hash = *node.hash;
+                }
+                Reference::Value(value) => {
+                    return if value.is_sealed {
+                        Err(Error::Sealed)
+                    } else if let Some(len) = NonZeroU16::new(key.len()) {
+                        let proof =
+                            proof!(proof rev.lookup_key_left(len, *value.hash));
+                        Ok((None, proof))
+                    } else {
+                        let proof = proof!(proof rev.build());
+                        Ok((Some(*value.hash), proof))
+                    };
+                }
+            };
+        }
+    }
+
+    /// Returns all keys and values in a given subtrie.
+    pub fn get_subtrie(&self, key: &[u8]) -> Result<Vec<iter::Entry>> {
+        if self.is_empty() {
+            Ok(Vec::new())
+        } else {
+            iter::get_entries(self.alloc, self.root_ptr, key)
+        }
+    }
+
+    /// Returns iterator over all keys and values whose key starts with given
+    /// prefix.
+    ///
+    /// Returned keys include the prefix and are ordered lexicographically.
+    /// Since values of sealed keys are no longer known, sealed entries are
+    /// skipped.  Use [`Self::get_subtrie`] to learn about sealed subtries.
+    pub fn iter_prefix(
+        &self,
+        prefix: &[u8],
+    ) -> Result<impl Iterator<Item = (Vec<u8>, CryptoHash)>> {
+        let entries = self.get_subtrie(prefix)?;
+        let prefix = prefix.to_vec();
+        Ok(entries.into_iter().filter_map(move |entry| {
+            let hash = entry.hash.filter(|_| !entry.is_sealed)?;
+            let sub_key: &[u8] = entry.sub_key.as_slice().try_into().ok()?;
+            Some(([prefix.as_slice(), sub_key].concat(), hash))
+        }))
+    }
+
+    /// Returns at most `limit` keys and values in a given subtrie.
+    ///
+    /// Entries are returned in lexicographic order of their keys.  If `cursor`
+    /// is given, listing starts with the first entry following the cursor.  If
+    /// there are more entries than `limit`, returns a cursor which can be
+    /// passed to a subsequent call to continue the listing.  This allows
+    /// enumerating large subtries in bounded chunks, e.g. over multiple
+    /// transactions.
+    pub fn get_subtrie_page(
+        &self,
+        key: &[u8],
+        cursor: Option<&iter::Cursor>,
+        limit: NonZeroUsize,
+    ) -> Result<(Vec<iter::Entry>, Option<iter::Cursor>)> {
+        if self.is_empty() {
+            Ok((Vec::new(), None))
+        } else {
+            iter::get_entries_page(
+                self.alloc,
+                self.root_ptr,
+                key,
+                cursor,
+                limit,
+            )
+        }
+    }
+}
+
+
 #[cfg(test)]
 impl Trie<memory::test_utils::TestAllocator<Value>> {
     /// Creates a test trie using a TestAllocator with given capacity.
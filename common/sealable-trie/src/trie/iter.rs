@@ -1,4 +1,5 @@
 use alloc::vec::Vec;
+use core::num::NonZeroUsize;
 
 use lib::hash::CryptoHash;
 use memory::Ptr;
@@ -31,23 +32,81 @@ pub struct Entry {
     pub hash: Option<CryptoHash>,
 }
 
+/// A position within a subtrie from which iteration can be resumed.
+///
+/// Cursor is returned by [`super::Trie::get_subtrie_page`] when there are more
+/// entries in the subtrie than requested.  Passing it to a subsequent call
+/// continues listing entries from where the previous call stopped.  Cursor
+/// can be Borsh-serialised (if `borsh` feature is enabled) which allows it to
+/// be passed between transactions.
+///
+/// The cursor holds the `sub_key` of the last returned entry.  If the trie is
+/// modified between calls, the iteration continues with the first entry
+/// following that key (in bit-wise lexicographic order).
+#[derive(Clone, PartialEq, derive_more::From, derive_more::Into)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct Cursor(bits::Owned);
+
+impl core::fmt::Debug for Cursor {
+    fn fmt(&self, fmtr: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fmtr.debug_tuple("Cursor").field(&self.0).finish()
+    }
+}
+
 /// Returns all entries of a sub-trie of at given `key`.
 pub(super) fn get_entries<A: memory::Allocator<Value = super::Value>>(
     alloc: &A,
     root_ptr: Option<Ptr>,
     key: &[u8],
 ) -> Result<Vec<Entry>> {
+    get_entries_page(alloc, root_ptr, key, None, NonZeroUsize::MAX)
+        .map(|(entries, _)| entries)
+}
+
+/// Returns at most `limit` entries of a sub-trie at given `key` which follow
+/// entry at the `cursor`.
+///
+/// If there are more entries in the sub-trie, returns cursor which can be used
+/// to resume the iteration.  `limit` is non-zero since an empty page couldn’t
+/// carry a cursor to resume from.
+pub(super) fn get_entries_page<A: memory::Allocator<Value = super::Value>>(
+    alloc: &A,
+    root_ptr: Option<Ptr>,
+    key: &[u8],
+    cursor: Option<&Cursor>,
+    limit: NonZeroUsize,
+) -> Result<(Vec<Entry>, Option<Cursor>)> {
     let key = bits::Slice::from_bytes(key).ok_or(Error::KeyTooLong)?;
-    match get_subtrie_root(alloc, root_ptr, key) {
-        GetSubtrieRootResult::Root(prefix, node_ptr) => {
-            Context { alloc, prefix, entries: Vec::new() }
-                .get(node_ptr)
-                .map_err(Error::from)
+    let (prefix, root) = match get_subtrie_root(alloc, root_ptr, key) {
+        GetSubtrieRootResult::Root(prefix, node_ptr) => (prefix, Ok(node_ptr)),
+        GetSubtrieRootResult::Empty => return Ok((Vec::new(), None)),
+        GetSubtrieRootResult::Single(Entry { is_sealed, sub_key, hash }) => {
+            (sub_key, Err((is_sealed, hash)))
         }
-        GetSubtrieRootResult::Empty => Ok(Vec::new()),
-        GetSubtrieRootResult::Single(entry) => Ok(alloc::vec![entry]),
-        GetSubtrieRootResult::Err(err) => Err(err.into()),
+        GetSubtrieRootResult::Err(err) => return Err(err.into()),
+    };
+    let mut ctx = Context {
+        alloc,
+        prefix,
+        entries: Vec::new(),
+        cursor: cursor.map(|cursor| cursor.0.as_slice()),
+        limit: limit.get(),
+        truncated: false,
+    };
+    let len = ctx.prefix.len();
+    match root {
+        Ok(node_ptr) => ctx.handle_node(node_ptr, len)?,
+        Err((is_sealed, hash)) => ctx.push(is_sealed, hash, len),
     }
+    let cursor = if ctx.truncated {
+        ctx.entries.last().map(|entry| Cursor(entry.sub_key.clone()))
+    } else {
+        None
+    };
+    Ok((ctx.entries, cursor))
 }
 
 /// Result returned from [`get_subtrie_root`] method.
@@ -138,8 +197,34 @@ fn get_subtrie_root<A: memory::Allocator<Value = super::Value>>(
     GetSubtrieRootResult::Root(prefix, node_ptr)
 }
 
+/// Position of a node relative to the cursor.
+enum Position {
+    /// The node and all its descendants lie before the cursor.
+    Before,
+    /// The node is the cursor or its ancestor.
+    Contains,
+    /// The node and all its descendants lie after the cursor.
+    After,
+}
+
+impl Position {
+    /// Determines position of node at key `node` relative to `cursor`.
+    fn new(mut node: bits::Slice, mut cursor: bits::Slice) -> Self {
+        loop {
+            match (node.pop_front(), cursor.pop_front()) {
+                (None, _) => break Self::Contains,
+                (Some(_), None) => break Self::After,
+                (Some(lhs), Some(rhs)) if lhs == rhs => (),
+                (Some(lhs), Some(_)) => {
+                    break if lhs { Self::After } else { Self::Before }
+                }
+            }
+        }
+    }
+}
+
 /// Context for iterating the trie to get subtrie entries.
-struct Context<'a, A> {
+struct Context<'a, 'c, A> {
     /// Allocator used to fetch the trie nodes.
     alloc: &'a A,
 
@@ -147,14 +232,22 @@ struct Context<'a, A> {
     prefix: bits::Owned,
 
     entries: Vec<Entry>,
-}
 
-impl<'a, A: memory::Allocator<Value = super::Value>> Context<'a, A> {
-    fn get(mut self, node_ptr: Option<Ptr>) -> Result<Vec<Entry>, DecodeError> {
-        self.handle_node(node_ptr, self.prefix.len())?;
-        Ok(self.entries)
-    }
+    /// Sub key of the last entry returned previously.
+    ///
+    /// Entries at or before this key are skipped.  Set to `None` once
+    /// iteration passes the cursor.
+    cursor: Option<bits::Slice<'c>>,
+
+    /// Maximum number of entries to collect.
+    limit: usize,
+
+    /// Whether iteration stopped because `limit` has been reached and there
+    /// are further entries in the subtrie.
+    truncated: bool,
+}
 
+impl<'a, 'c, A: memory::Allocator<Value = super::Value>> Context<'a, 'c, A> {
     fn handle_node(
         &mut self,
         node_ptr: Option<Ptr>,
@@ -165,14 +258,13 @@ impl<'a, A: memory::Allocator<Value = super::Value>> Context<'a, A> {
         let ptr = if let Some(ptr) = node_ptr {
             ptr
         } else {
-            self.entries.push(Entry {
-                is_sealed: true,
-                sub_key: self.prefix.clone(),
-                hash: None,
-            });
-            self.prefix.truncate(len);
+            self.push(true, None, len);
             return Ok(());
         };
+        if self.truncated || self.is_before_cursor(false) {
+            self.prefix.truncate(len);
+            return Ok(());
+        }
 
         match self.alloc.get(ptr).decode()? {
             Node::Branch { children } => {
@@ -210,12 +302,41 @@ impl<'a, A: memory::Allocator<Value = super::Value>> Context<'a, A> {
         hash: &'a CryptoHash,
         len: u16,
     ) {
+        self.push(is_sealed, Some(*hash), len);
+    }
+
+    /// Adds entry at current prefix unless it lies before the cursor or limit
+    /// has been reached.
+    fn push(&mut self, is_sealed: bool, hash: Option<CryptoHash>, len: u16) {
         debug_assert!(len <= self.prefix.len());
-        self.entries.push(Entry {
-            is_sealed,
-            sub_key: self.prefix.clone(),
-            hash: Some(*hash),
-        });
+        if self.truncated || self.is_before_cursor(true) {
+            // Skip
+        } else if self.entries.len() >= self.limit {
+            self.truncated = true;
+        } else {
+            let sub_key = self.prefix.clone();
+            self.entries.push(Entry { is_sealed, sub_key, hash });
+        }
         self.prefix.truncate(len);
     }
+
+    /// Checks whether node at current prefix lies before the cursor.
+    ///
+    /// A leaf at the cursor is considered to be before it since it’s been
+    /// already returned.  Once node after the cursor is encountered, the
+    /// cursor is cleared since all following nodes lie after it as well.
+    fn is_before_cursor(&mut self, is_leaf: bool) -> bool {
+        let cursor = match self.cursor {
+            None => return false,
+            Some(cursor) => cursor,
+        };
+        match Position::new(self.prefix.as_slice(), cursor) {
+            Position::Before => true,
+            Position::Contains => is_leaf,
+            Position::After => {
+                self.cursor = None;
+                false
+            }
+        }
+    }
 }
@@ -279,3 +279,318 @@ impl OwnedRef {
         }
     }
 }
+
+/// Context for [`super::Trie::set_many`] operation.
+///
+/// Rather than inserting keys one at a time, the keys are sorted and inserted
+/// into the trie in a single traversal.  Each node on paths to the inserted
+/// values is visited, updated and hashed only once even if multiple keys pass
+/// through it.
+pub(super) struct ManyContext<'a, A: memory::Allocator<Value = super::Value>> {
+    /// Allocator used to allocate new nodes.
+    wlog: memory::WriteLog<'a, A>,
+}
+
+/// Part of the key yet to be traversed together with hash to insert at it.
+pub(super) type Entry<'a> = (bits::Slice<'a>, CryptoHash);
+
+impl<'a, A: memory::Allocator<Value = super::Value>> ManyContext<'a, A> {
+    pub(super) fn new(alloc: &'a mut A) -> Self {
+        Self { wlog: memory::WriteLog::new(alloc) }
+    }
+
+    /// Inserts value hashes into the trie.
+    ///
+    /// `entries` must be non-empty, sorted by key and mustn’t contain
+    /// duplicate keys.  If any of the insertions fails, the trie is left
+    /// unmodified.
+    pub(super) fn set(
+        mut self,
+        root_ptr: Option<Ptr>,
+        root_hash: &CryptoHash,
+        entries: &mut [Entry<'_>],
+    ) -> Result<(Ptr, CryptoHash)> {
+        let res = if let Some(ptr) = root_ptr {
+            // Trie is non-empty, handle normally.
+            self.handle(ptr, root_hash, entries)
+        } else if *root_hash != super::EMPTY_TRIE_ROOT {
+            // Trie is sealed (it’s not empty but ptr is None).
+            Err(Error::Sealed)
+        } else if let OwnedRef::Node(ptr, hash) = self.insert_values(entries)? {
+            Ok((ptr, hash))
+        } else {
+            // insert_values returns a value reference only if there was
+            // a single entry with an empty key.
+            Err(Error::EmptyKey)
+        };
+        if res.is_ok() {
+            self.wlog.commit();
+        }
+        res
+    }
+
+    /// Inserts values into the subtrie rooted at node at given address.
+    fn handle(
+        &mut self,
+        nptr: Ptr,
+        hash: &CryptoHash,
+        entries: &mut [Entry<'_>],
+    ) -> Result<(Ptr, CryptoHash)> {
+        let node = *self.wlog.allocator().get(nptr);
+        let node = node.decode()?;
+        debug_assert_eq!(*hash, node.hash());
+        match node {
+            Node::Branch { children } => {
+                self.handle_branch(nptr, children, entries)
+            }
+            Node::Extension { key, child } => self.handle_extension(
+                Some(nptr),
+                key.into_slice(),
+                child,
+                entries,
+            ),
+        }
+    }
+
+    /// Inserts values assuming current node is a Branch with given children.
+    fn handle_branch(
+        &mut self,
+        nptr: Ptr,
+        children: [Reference<'_>; 2],
+        entries: &mut [Entry<'_>],
+    ) -> Result<(Ptr, CryptoHash)> {
+        let (left, right) = split_on_bit(entries)?;
+        let left = self.handle_group(children[0], left)?;
+        let right = self.handle_group(children[1], right)?;
+        let left = left.as_ref().map_or(children[0], OwnedRef::to_ref);
+        let right = right.as_ref().map_or(children[1], OwnedRef::to_ref);
+        self.set_node(nptr, RawNode::branch(left, right))
+    }
+
+    /// Inserts values assuming current node is an Extension with given key and
+    /// child.
+    ///
+    /// If `nptr` is `None` the Extension is not stored in the trie.  This
+    /// happens when an existing Extension is split and the operation continues
+    /// with its suffix.  In that case, rather than updating node in place, new
+    /// nodes are allocated.
+    fn handle_extension(
+        &mut self,
+        nptr: Option<Ptr>,
+        ext_key: bits::Slice<'_>,
+        child: Reference<'_>,
+        entries: &mut [Entry<'_>],
+    ) -> Result<(Ptr, CryptoHash)> {
+        let ext = ExtKey::try_from(ext_key).unwrap();
+        let common = entries.iter().fold(ext_key.len(), |common, (key, _)| {
+            let mut key = *key;
+            let (prefix, _) = key.forward_common_prefix(ext);
+            common.min(prefix.map_or(0, |prefix| prefix.len()))
+        });
+        for (key, _) in entries.iter_mut() {
+            key.pop_front_slice(common).unwrap();
+        }
+
+        if common == ext_key.len() {
+            // The entire extension key matched for all the keys.  Handle the
+            // child reference and update the node.
+            let owned_ref = self.handle_reference(child, entries)?;
+            let node = RawNode::extension(ext, owned_ref.to_ref());
+            return self.put_node(nptr, node);
+        }
+
+        // Some of the keys diverge from the extension key.  The Extension needs
+        // to be split with a Branch node in between.  See comment in
+        // Context::handle_extension for illustration.  Keys which don’t diverge
+        // continue into the suffix of the Extension.
+        let (prefix, mut suffix) = ext_key.split_at(common).unwrap();
+        let theirs = suffix.pop_front().unwrap();
+        let (zeros, ones) = split_on_bit(entries)?;
+        let (ours, their_entries) =
+            if theirs { (zeros, ones) } else { (ones, zeros) };
+
+        let our_ref = self.insert_values(ours)?;
+        let their_ref = if suffix.is_empty() {
+            self.handle_group(child, their_entries)?
+        } else if their_entries.is_empty() {
+            // Suffix of an existing Extension key is never too long.
+            let node =
+                RawNode::extension(ExtKey::try_from(suffix).unwrap(), child);
+            let (ptr, hash) = self.alloc_node(node)?;
+            Some(OwnedRef::Node(ptr, hash))
+        } else {
+            let (ptr, hash) =
+                self.handle_extension(None, suffix, child, their_entries)?;
+            Some(OwnedRef::Node(ptr, hash))
+        };
+
+        let mut children =
+            [their_ref.as_ref().map_or(child, OwnedRef::to_ref); 2];
+        children[usize::from(!theirs)] = our_ref.to_ref();
+        let node = RawNode::branch(children[0], children[1]);
+        match ExtKey::try_from(prefix) {
+            Ok(prefix) => {
+                let (ptr, hash) = self.alloc_node(node)?;
+                let child = Reference::node(Some(ptr), &hash);
+                self.put_node(nptr, RawNode::extension(prefix, child))
+            }
+            Err(_) => self.put_node(nptr, node),
+        }
+    }
+
+    /// Handles a group of entries which lead to given reference.
+    ///
+    /// Returns `None` if the group is empty and thus reference doesn’t change.
+    fn handle_group(
+        &mut self,
+        child: Reference<'_>,
+        entries: &mut [Entry<'_>],
+    ) -> Result<Option<OwnedRef>> {
+        if entries.is_empty() {
+            Ok(None)
+        } else {
+            self.handle_reference(child, entries).map(Some)
+        }
+    }
+
+    /// Handles a reference which can either point at a node or a value.
+    ///
+    /// Returns a new value for the reference updating it such that it points at
+    /// the subtrie updated with the inserted values.
+    fn handle_reference(
+        &mut self,
+        child: Reference<'_>,
+        entries: &mut [Entry<'_>],
+    ) -> Result<OwnedRef> {
+        match child {
+            Reference::Node(node) => {
+                let ptr = node.ptr.ok_or(Error::Sealed)?;
+                let (ptr, hash) = self.handle(ptr, node.hash, entries)?;
+                Ok(OwnedRef::Node(ptr, hash))
+            }
+            Reference::Value(value) => match entries {
+                // Since there are no duplicate keys, if there’s more than one
+                // entry, at least one of the keys is longer than existing key.
+                [(key, hash)] if key.is_empty() => {
+                    if value.is_sealed {
+                        Err(Error::Sealed)
+                    } else {
+                        Ok(OwnedRef::Value(*hash))
+                    }
+                }
+                _ => Err(Error::BadKeyPrefix),
+            },
+        }
+    }
+
+    /// Inserts values into a new subtrie and returns reference to it.
+    ///
+    /// If there’s a single entry with an empty key, doesn’t insert any nodes
+    /// and instead returns a value reference to the value.
+    fn insert_values(&mut self, entries: &mut [Entry<'_>]) -> Result<OwnedRef> {
+        let (first, last) = match entries {
+            [] => unreachable!(),
+            [(key, hash)] => {
+                return self.insert_extensions(*key, OwnedRef::Value(*hash))
+            }
+            [(first, _), .., (last, _)] => (*first, *last),
+        };
+
+        // Since entries are sorted, common prefix of the first and last key is
+        // common prefix of all the keys.
+        let common = common_prefix_len(first, last);
+        let prefix = first.split_at(common).unwrap().0;
+        for (key, _) in entries.iter_mut() {
+            key.pop_front_slice(common).unwrap();
+        }
+
+        let (left, right) = split_on_bit(entries)?;
+        let left = self.insert_values(left)?;
+        let right = self.insert_values(right)?;
+        let node = RawNode::branch(left.to_ref(), right.to_ref());
+        let (ptr, hash) = self.alloc_node(node)?;
+        self.insert_extensions(prefix, OwnedRef::Node(ptr, hash))
+    }
+
+    /// Inserts Extension nodes (as many as necessary given length of the key)
+    /// leading to given reference.  Returns reference to the first one.
+    ///
+    /// If the key is empty, returns the reference unchanged.
+    fn insert_extensions(
+        &mut self,
+        key: bits::Slice<'_>,
+        mut owned_ref: OwnedRef,
+    ) -> Result<OwnedRef> {
+        for chunk in key.chunks().rev() {
+            let node = RawNode::extension(chunk, owned_ref.to_ref());
+            let (ptr, hash) = self.alloc_node(node)?;
+            owned_ref = OwnedRef::Node(ptr, hash);
+        }
+        Ok(owned_ref)
+    }
+
+    /// Sets value of a node cell at given address if one is given or allocates
+    /// a new node otherwise.
+    fn put_node(
+        &mut self,
+        nptr: Option<Ptr>,
+        node: RawNode,
+    ) -> Result<(Ptr, CryptoHash)> {
+        match nptr {
+            Some(ptr) => {
+                let hash = node.decode()?.hash();
+                self.wlog.set(ptr, node);
+                Ok((ptr, hash))
+            }
+            None => self.alloc_node(node),
+        }
+    }
+
+    /// Sets value of a node cell at given address and returns its hash.
+    fn set_node(
+        &mut self,
+        ptr: Ptr,
+        node: RawNode,
+    ) -> Result<(Ptr, CryptoHash)> {
+        self.put_node(Some(ptr), node)
+    }
+
+    /// Allocates a new node and sets it to given value.
+    fn alloc_node(&mut self, node: RawNode) -> Result<(Ptr, CryptoHash)> {
+        let hash = node.decode()?.hash();
+        let ptr = self.wlog.alloc(node)?;
+        Ok((ptr, hash))
+    }
+}
+
+/// Pops the first bit of each key and splits entries into ones whose key
+/// started with zero and ones whose key started with one.
+///
+/// Relies on entries being sorted.  Returns [`Error::BadKeyPrefix`] if any
+/// of the keys is empty since that means it’s a prefix of another key.
+fn split_on_bit<'e, 'k>(
+    entries: &'e mut [Entry<'k>],
+) -> Result<(&'e mut [Entry<'k>], &'e mut [Entry<'k>])> {
+    let mut zeros = 0;
+    for (key, _) in entries.iter_mut() {
+        if !key.pop_front().ok_or(Error::BadKeyPrefix)? {
+            zeros += 1;
+        }
+    }
+    Ok(entries.split_at_mut(zeros))
+}
+
+/// Returns length of the common prefix of two slices.
+fn common_prefix_len(
+    mut lhs: bits::Slice<'_>,
+    mut rhs: bits::Slice<'_>,
+) -> u16 {
+    let mut len = 0;
+    while let (Some(l), Some(r)) = (lhs.pop_front(), rhs.pop_front()) {
+        if l != r {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
@@ -1,10 +1,13 @@
+use alloc::vec;
 use alloc::vec::Vec;
+use core::num::NonZeroUsize;
 use std::collections::HashMap;
 use std::println;
+use std::string::String;
 
 use hex_literal::hex;
 use lib::hash::CryptoHash;
-use memory::test_utils::TestAllocator;
+use memory::test_utils::{AllocSnapshot, TestAllocator};
 use rand::seq::SliceRandom;
 use rand::Rng;
 
@@ -233,6 +236,28 @@ fn test_del_extension_1() {
     assert_eq!(1, trie.nodes_count());
 }
 
+/// Tests that nodes allocated by a `set` which failed due to running out of
+/// memory are freed.
+#[test]
+fn test_no_leaks_on_out_of_memory() {
+    let mut trie = TestTrie::new(10);
+    let mut keys = (0..=255u8).map(|num| [num, !num]);
+    let err = loop {
+        let key = keys.next().unwrap();
+        let before = trie.trie.alloc.snapshot();
+        match trie.try_set(&key, false) {
+            Ok(()) => trie.check_no_leaks(),
+            Err(err) => {
+                assert!(before.diff(&trie.trie.alloc.snapshot()).is_empty());
+                break err;
+            }
+        }
+    };
+    assert_eq!(super::Error::OutOfMemory, err);
+    trie.check_no_leaks();
+    trie.check_all_reads();
+}
+
 #[test]
 fn test_get_subtrie() {
     let trie = make_trie_from_keys(
@@ -263,6 +288,111 @@ fn test_get_subtrie() {
     test!("z", {});
 }
 
+#[test]
+fn test_get_subtrie_page() {
+    let trie = make_trie_from_keys(
+        IterKeyGen::new([
+            "foo".as_bytes(),
+            "bar".as_bytes(),
+            "baz".as_bytes(),
+            "qux".as_bytes(),
+        ]),
+        None,
+        true,
+    );
+
+    let page = |cursor: Option<&super::iter::Cursor>, limit: usize| {
+        let limit = NonZeroUsize::new(limit).unwrap();
+        let (entries, cursor) =
+            trie.trie.get_subtrie_page(b"", cursor, limit).unwrap();
+        let keys = entries
+            .into_iter()
+            .map(|entry| {
+                let key: &[u8] = entry.sub_key.as_slice().try_into().unwrap();
+                String::from(core::str::from_utf8(key).unwrap())
+            })
+            .collect::<Vec<_>>();
+        (keys, cursor)
+    };
+
+    // Page boundary in the middle of the subtrie returns a cursor.
+    let (keys, cursor) = page(None, 1);
+    assert_eq!(["bar"], keys.as_slice());
+    let (keys, cursor) = page(cursor.as_ref(), 2);
+    assert_eq!(["baz", "foo"], keys.as_slice());
+    let (keys, cursor) = page(cursor.as_ref(), 2);
+    assert_eq!(["qux"], keys.as_slice());
+    assert_eq!(None, cursor);
+
+    // Page ending exactly at the last entry doesn’t return a cursor.
+    let (keys, cursor) = page(None, 4);
+    assert_eq!(4, keys.len());
+    assert_eq!(None, cursor);
+    let (keys, cursor) = page(None, 3);
+    assert_eq!(["bar", "baz", "foo"], keys.as_slice());
+    let (keys, cursor) = page(cursor.as_ref(), 1);
+    assert_eq!(["qux"], keys.as_slice());
+    assert_eq!(None, cursor);
+}
+
+#[test]
+fn test_iter_prefix() {
+    let mut trie = make_trie_from_keys(
+        IterKeyGen::new([
+            "foo".as_bytes(),
+            "bar".as_bytes(),
+            "baz".as_bytes(),
+            "qux".as_bytes(),
+        ]),
+        None,
+        true,
+    );
+    let bar = (b"bar".to_vec(), trie.mapping[&Key::from("bar")]);
+    let baz = (b"baz".to_vec(), trie.mapping[&Key::from("baz")]);
+
+    let iter = |trie: &TestTrie, prefix: &str| {
+        trie.trie.iter_prefix(prefix.as_bytes()).unwrap().collect::<Vec<_>>()
+    };
+    assert_eq!(vec![bar.clone(), baz.clone()], iter(&trie, "b"));
+    assert_eq!(vec![baz.clone()], iter(&trie, "baz"));
+    assert_eq!(Vec::<(Vec<u8>, CryptoHash)>::new(), iter(&trie, "z"));
+    assert_eq!(4, iter(&trie, "").len());
+
+    trie.seal(b"baz", true);
+    assert_eq!(vec![bar], iter(&trie, "b"));
+}
+
+#[test]
+fn test_snapshot() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let trie = make_trie_from_keys(
+        IterKeyGen::new([
+            "foo".as_bytes(),
+            "bar".as_bytes(),
+            "baz".as_bytes(),
+            "qux".as_bytes(),
+        ]),
+        None,
+        true,
+    );
+    let snapshot = trie.trie.snapshot();
+    assert_send_sync(&snapshot);
+    assert_eq!(trie.hash(), snapshot.hash());
+
+    let trie = &trie.trie;
+    std::thread::scope(|scope| {
+        for key in ["foo", "bar", "baz", "qux", "quux"] {
+            scope.spawn(move || {
+                let key = key.as_bytes();
+                let (value, proof) = snapshot.prove(key).unwrap();
+                assert_eq!(trie.get(key).unwrap(), value);
+                assert!(proof.verify(trie.hash(), key, value.as_ref()));
+            });
+        }
+    });
+}
+
 struct RandKeys<'a> {
     buf: &'a mut [u8],
     rng: rand::rngs::ThreadRng,
@@ -385,6 +515,68 @@ fn stress_test_iter() {
     }
 }
 
+/// Tests inserting keys in batches with `set_many` and compares result with
+/// inserting them one at a time.
+#[test]
+fn test_set_many() {
+    let count = lib::test_utils::get_iteration_count(2000).max(5);
+    let mut rand_keys =
+        RandKeys { buf: &mut [0; 35][..], rng: rand::thread_rng(), count };
+    let mut batched = TestTrie::new(count * 32);
+    let mut sequential = TestTrie::new(count * 32);
+
+    for _ in 0..count {
+        let mut known = batched.mapping.clone();
+        let mut keys = Vec::<Key>::new();
+        for _ in 0..rand_keys.rng.gen_range(1..10) {
+            let key = Key::from(rand_keys.generate(&known));
+            known.insert(key.clone(), CryptoHash::default());
+            keys.push(key);
+        }
+        // Overwrite some existing key and set one of the new keys twice.
+        if let Some(key) = batched.mapping.keys().next() {
+            keys.push(key.clone());
+        }
+        keys.push(keys[0].clone());
+        keys.shuffle(&mut rand_keys.rng);
+
+        let keys = keys.iter().map(Key::as_bytes).collect::<Vec<_>>();
+        batched.set_many(&keys, false);
+        for key in keys {
+            sequential.set(key, false);
+        }
+        assert_eq!(sequential.hash(), batched.hash());
+        assert_eq!(sequential.nodes_count(), batched.nodes_count());
+    }
+}
+
+/// Tests that failed `set_many` leaves the trie unmodified.
+#[test]
+fn test_set_many_errors() {
+    let (mut trie, _) = make_trie(true, false);
+    let hash = *trie.hash();
+    let value = CryptoHash::test(1);
+    let mut check = |entries: &[&[u8]], want: super::Error| {
+        let entries = entries.iter().map(|key| (*key, value));
+        assert_eq!(Err(want), trie.trie.set_many(entries));
+        assert_eq!(&hash, trie.hash());
+        trie.check_all_reads();
+        trie.check_no_leaks();
+    };
+
+    check(&[b"foo", b"0x"], super::Error::BadKeyPrefix);
+    check(&[b"foo", b"foobar"], super::Error::BadKeyPrefix);
+    check(&[b"foo", b""], super::Error::EmptyKey);
+    check(&[b"foo", &[0; 8192]], super::Error::KeyTooLong);
+
+    trie.seal(b"1", false);
+    let hash = *trie.hash();
+    let entries = [(&b"foo"[..], value), (&b"1"[..], value)];
+    assert_eq!(Err(super::Error::Sealed), trie.trie.set_many(entries));
+    assert_eq!(&hash, trie.hash());
+    trie.check_no_leaks();
+}
+
 #[derive(Clone, Eq)]
 struct Key {
     len: u8,
@@ -507,6 +699,7 @@ impl TestTrie {
     pub fn set(&mut self, key: &[u8], verbose: bool) {
         self.try_set(key, verbose).unwrap();
         self.check_all_reads();
+        self.check_no_leaks();
     }
 
     fn try_set(
@@ -531,6 +724,29 @@ impl TestTrie {
         res
     }
 
+    pub fn set_many(&mut self, keys: &[&[u8]], verbose: bool) {
+        let entries = keys
+            .iter()
+            .map(|key| (*key, self.next_value()))
+            .collect::<Vec<_>>();
+        println!(
+            "{}Inserting {} keys",
+            if verbose { "\n" } else { "" },
+            keys.len()
+        );
+        self.trie
+            .set_many(entries.iter().map(|(key, value)| (*key, *value)))
+            .unwrap_or_else(|err| panic!("Failed setting keys: {err}"));
+        for (key, value) in entries {
+            self.mapping.insert(Key::from(key), value);
+        }
+        if verbose {
+            self.trie.print();
+        }
+        self.check_all_reads();
+        self.check_no_leaks();
+    }
+
     pub fn seal(&mut self, key: &[u8], verbose: bool) {
         println!("{}Sealing {key:?}", if verbose { "\n" } else { "" });
         self.trie
@@ -539,6 +755,7 @@ impl TestTrie {
         if verbose {
             self.trie.print();
         }
+        self.check_no_leaks();
         assert_eq!(
             Err(super::Error::Sealed),
             self.trie.get(key),
@@ -559,6 +776,7 @@ impl TestTrie {
         if verbose {
             self.trie.print();
         }
+        self.check_no_leaks();
         assert_eq!(
             Err(super::Error::Sealed),
             self.trie.get(key),
@@ -584,6 +802,7 @@ impl TestTrie {
             .unwrap_or_else(|err| panic!("Failed getting ‘{key:?}’: {err}"));
         assert_eq!(None, got.as_ref(), "Invalid value at ‘{key:?}’");
         self.check_all_reads();
+        self.check_no_leaks();
     }
 
     pub fn get_subtrie(&self, prefix: &[u8], verbose: bool) -> Vec<(Key, u32)> {
@@ -620,6 +839,50 @@ impl TestTrie {
         want.sort_by(|x, y| x.0.as_bytes().cmp(y.0.as_bytes()));
 
         assert_eq!(want, self.get_subtrie(prefix, verbose));
+        for limit in 1..=3 {
+            let limit = NonZeroUsize::new(limit).unwrap();
+            assert_eq!(want, self.get_subtrie_paged(prefix, limit));
+        }
+    }
+
+    /// Lists subtrie in pages of at most `limit` entries.
+    fn get_subtrie_paged(
+        &self,
+        prefix: &[u8],
+        limit: NonZeroUsize,
+    ) -> Vec<(Key, u32)> {
+        let mut got = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (entries, next) = self
+                .trie
+                .get_subtrie_page(prefix, cursor.as_ref(), limit)
+                .unwrap();
+            if next.is_some() {
+                assert_eq!(limit.get(), entries.len());
+            } else {
+                assert!(entries.len() <= limit.get());
+            }
+            got.extend(entries.into_iter().map(|entry| {
+                assert!(!entry.is_sealed);
+                let key: &[u8] = entry.sub_key.as_slice().try_into().unwrap();
+                Self::make_entry(key, entry.hash.as_ref().unwrap())
+            }));
+            cursor = match next {
+                None => break got,
+                next => next,
+            };
+        }
+    }
+
+    /// Checks that all nodes allocated in the allocator are reachable from
+    /// trie’s root and vice versa.
+    #[track_caller]
+    fn check_no_leaks(&self) {
+        let reachable: AllocSnapshot =
+            self.trie.reachable_nodes().into_iter().collect();
+        let diff = reachable.diff(&self.trie.alloc.snapshot());
+        assert!(diff.is_empty(), "Unreachable nodes: {diff}");
     }
 
     fn check_all_reads(&self) {
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
 use cf_solana::proof;
+use jsonrpc_pubsub::typed::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+use lib::hash::CryptoHash;
 
 type Result<T, E = jsonrpc_core::Error> = ::core::result::Result<T, E>;
 
@@ -15,6 +18,18 @@ pub struct SlotData {
     pub root_account: proof::AccountHashData,
 }
 
+/// Notification pushed to `proofsSubscribe` subscribers for every rooted slot
+/// in which the trie has changed.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SlotNotification {
+    /// Number of the rooted slot.
+    pub slot: u64,
+    /// Bank hash of the slot as calculated from the accounts delta hash proof.
+    pub bank_hash: CryptoHash,
+    /// Proofs and trie root account for the slot.
+    pub data: Arc<SlotData>,
+}
+
 #[jsonrpc_derive::rpc]
 pub trait Methods {
     type Metadata;
@@ -34,6 +49,57 @@ pub trait Methods {
         meta: Self::Metadata,
         slot: u64,
     ) -> Result<Option<Arc<SlotData>>>;
+
+    /// Returns data for slots in `start_slot..=end_slot` range ordered by slot
+    /// number.
+    ///
+    /// If the plugin has persistent store configured, this includes slots
+    /// rooted before the validator restarted as long as they are within store’s
+    /// retention window.  The number of returned entries is limited; to get
+    /// the rest, repeat the query starting past the last returned slot.
+    #[rpc(meta, name = "getSlotDataRange")]
+    fn get_slot_data_range(
+        &self,
+        meta: Self::Metadata,
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<Vec<(u64, Arc<SlotData>)>>;
+
+    /// Returns states of the IBC storage account in slots after `since_slot`
+    /// in which the account has changed.
+    ///
+    /// Returns an empty list if the plugin isn’t configured to watch the
+    /// storage account.
+    #[rpc(meta, name = "getStorageUpdates")]
+    fn get_storage_updates(
+        &self,
+        meta: Self::Metadata,
+        since_slot: u64,
+    ) -> Result<Vec<(u64, Arc<proof::AccountHashData>)>>;
+}
+
+#[jsonrpc_derive::rpc]
+pub trait PubSub {
+    type Metadata;
+
+    /// Subscribes to proofs generated for rooted slots.
+    ///
+    /// Subscriber receives a [`SlotNotification`] whenever the plugin adds
+    /// data for a new slot.  This lets clients stream the proofs rather than
+    /// poll `getLatestSlotData`.
+    #[pubsub(subscription = "proofs", subscribe, name = "proofsSubscribe")]
+    fn proofs_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<SlotNotification>,
+    );
+
+    #[pubsub(subscription = "proofs", unsubscribe, name = "proofsUnsubscribe")]
+    fn proofs_unsubscribe(
+        &self,
+        meta: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool>;
 }
 
 #[test]
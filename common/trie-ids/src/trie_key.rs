@@ -20,6 +20,10 @@ use super::{ibc, ids};
 ///     Receipts         { port_id: [u8; 9], channel_id: u32, sequence: u64 },
 ///     Acks             { port_id: [u8; 9], channel_id: u32, sequence: u64 },
 ///     UpgradeClient    { height: u64, type: u8 },
+///     EpochSummary     { height: u64 },
+///     Epoch            { epoch_number: u64 },
+///     AppState         { port_id: [u8; 9], key_hash: [u8; 32] },
+///     RewardsLedger    { epoch_number: u64 },
 /// }
 /// ```
 ///
@@ -29,8 +33,8 @@ use super::{ibc, ids};
 /// don’t just use Borsh encoding.
 #[derive(Clone, PartialEq, Eq)]
 pub struct TrieKey {
-    // tag (1) + port_id (9) + channel_id (4) + sequence (8) = max 22 bytes
-    bytes: [u8; 22],
+    // tag (1) + port_id (9) + key_hash (32) = max 42 bytes
+    bytes: [u8; 42],
     len: u8,
 }
 
@@ -47,6 +51,10 @@ pub enum Tag {
     Receipt = 6,
     Ack = 7,
     UpgradeClient = 8,
+    EpochSummary = 9,
+    Epoch = 10,
+    AppState = 11,
+    RewardsLedger = 12,
 }
 
 impl From<Tag> for u8 {
@@ -126,6 +134,49 @@ impl TrieKey {
         Self::new(Tag::UpgradeClient, (height, UpgradeType::ConsensusState))
     }
 
+    /// Constructs a new key for summary of the guest blockchain epoch whose
+    /// last block has given height.
+    ///
+    /// The hash stored under the key is `hash(borsh(epoch_stats))`.
+    #[inline]
+    pub fn for_epoch_summary(block_height: u64) -> Self {
+        Self::new(Tag::EpochSummary, block_height)
+    }
+
+    /// Constructs a new key for snapshot of the guest blockchain epoch with
+    /// given number.
+    ///
+    /// The hash stored under the key is `hash(borsh(epoch_snapshot))` where
+    /// the snapshot holds epoch number, host height at which the epoch
+    /// started and the epoch itself.
+    #[inline]
+    pub fn for_epoch(epoch_number: u64) -> Self {
+        Self::new(Tag::Epoch, epoch_number)
+    }
+
+    /// Constructs a new key for the block rewards ledger as of the start of
+    /// the guest blockchain epoch with given number.
+    ///
+    /// The hash stored under the key is `hash(borsh(ledger))`, i.e. the
+    /// ledger’s commitment, taken after rewards of the previous epoch have
+    /// been distributed.
+    #[inline]
+    pub fn for_rewards_ledger(epoch_number: u64) -> Self {
+        Self::new(Tag::RewardsLedger, epoch_number)
+    }
+
+    /// Constructs a new key for auxiliary state of an IBC application bound
+    /// to given port.
+    ///
+    /// `key_hash` is hash of application-defined key which lets applications
+    /// use keys of arbitrary length.  Since the port is part of the key, state
+    /// of different applications doesn’t overlap.  The hash stored under the
+    /// key is `hash(value)`.
+    #[inline]
+    pub fn for_app_state(app: &ids::PortKey, key_hash: &[u8; 32]) -> Self {
+        Self::new(Tag::AppState, (app, key_hash))
+    }
+
     /// Constructs a new key for a `(port_id, channel_id)` path.
     ///
     /// This is internal method used by other public-facing methods which use
@@ -157,7 +208,7 @@ impl TrieKey {
     /// For keys consisting of a multiple components, a tuple component can be
     /// used.
     pub fn new(tag: Tag, component: impl AsComponent) -> Self {
-        let mut key = TrieKey { bytes: [0; 22], len: 1 };
+        let mut key = TrieKey { bytes: [0; 42], len: 1 };
         key.bytes[0] = tag.into();
         component.append_into(&mut key);
         key
@@ -166,7 +217,7 @@ impl TrieKey {
     /// Creates a new key from given bytes.  Intended for tests only.
     #[cfg(test)]
     pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
-        let mut this = TrieKey { bytes: [0; 22], len: 0 };
+        let mut this = TrieKey { bytes: [0; 42], len: 0 };
         this.extend(bytes);
         this
     }
@@ -192,7 +243,7 @@ impl core::fmt::Display for TrieKey {
         const DIGITS: [ascii::AsciiChar; 16] =
             [_0, _1, _2, _3, _4, _5, _6, _7, _8, _9, a, b, c, d, e, f];
 
-        let mut out = [ascii::AsciiChar::Null; 44];
+        let mut out = [ascii::AsciiChar::Null; 84];
         for (dst, byte) in out.chunks_exact_mut(2).zip(self.iter()) {
             dst[0] = DIGITS[usize::from(byte >> 4)];
             dst[1] = DIGITS[usize::from(byte & 15)];
@@ -428,6 +479,16 @@ fn test_encoding() {
         sequence,
     });
 
+    check!("09 0000000000000007", TrieKey::for_epoch_summary(7));
+    check!("0a 0000000000000007", TrieKey::for_epoch(7));
+    check!(
+        "0b b6b6a7b1f7abffffff
+         4242424242424242424242424242424242424242424242424242424242424242",
+        TrieKey::for_app_state(
+            &ids::PortKey::try_from(&port_id).unwrap(),
+            &[0x42; 32]
+        )
+    );
     check!("01 00000001", TrieKey::new(Tag::ConsensusState, client));
     check!(
         "03 b6b6a7b1f7abffffff",
@@ -41,9 +41,32 @@ pub struct BumpAllocator<G> {
 
 struct Header<G> {
     end_pos: Cell<*mut u8>,
+    peak_pos: Cell<*mut u8>,
+    requested: Cell<usize>,
     global: G,
 }
 
+/// Heap usage statistics; see [`BumpAllocator::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Total number of bytes requested by allocations and reallocations.
+    ///
+    /// This includes memory which has been freed since.
+    pub requested: usize,
+
+    /// Number of bytes currently in use.
+    pub used: usize,
+
+    /// Highest number of bytes in use at any point.
+    pub peak: usize,
+
+    /// Number of bytes left in the part of the heap guaranteed to be
+    /// available, i.e. the first 32 KiB.
+    ///
+    /// If the transaction requested larger heap, more memory is available.
+    pub available: usize,
+}
+
 impl<G: bytemuck::Zeroable> BumpAllocator<G> {
     /// Creates a new global allocator.
     ///
@@ -128,6 +151,10 @@ impl<G: bytemuck::Zeroable> BumpAllocator<G> {
         };
         if ok {
             header.end_pos.set(end);
+            if end > header.peak_pos.get() {
+                header.peak_pos.set(end);
+            }
+            header.requested.set(header.requested.get() + layout.size());
             ptr
         } else {
             core::ptr::null_mut()
@@ -143,6 +170,36 @@ impl<G: bytemuck::Zeroable> BumpAllocator<G> {
     /// Note that by default `G` is a unit type which means that there is no
     /// reserved global state.
     pub fn global(&self) -> &G { &self.header().global }
+
+    /// Returns heap usage statistics.
+    pub fn stats(&self) -> HeapStats {
+        let header = self.header();
+        let start = ptr::end_addr_of_val(header);
+        let offset = |pos: *mut u8| (pos as usize).saturating_sub(start);
+        let peak = offset(header.peak_pos.get());
+        HeapStats {
+            requested: header.requested.get(),
+            used: offset(header.end_pos.get()),
+            peak,
+            available: offset(self.heap_range().end).saturating_sub(peak),
+        }
+    }
+
+    /// Logs heap usage statistics.
+    ///
+    /// Meant to be called at the end of an instruction to help choose heap
+    /// size requested with the compute budget instruction.
+    #[cfg(not(test))]
+    pub fn log_stats(&self) {
+        let stats = self.stats();
+        solana_program::msg!(
+            "Heap: requested {} bytes; used {}; peak {}; available {}",
+            stats.requested,
+            stats.used,
+            stats.peak,
+            stats.available
+        );
+    }
 }
 
 unsafe impl<G: bytemuck::Zeroable> GlobalAlloc for BumpAllocator<G> {
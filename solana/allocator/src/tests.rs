@@ -1,7 +1,7 @@
 use alloc::alloc::{GlobalAlloc, Layout};
 use core::cell::Cell;
 
-use crate::{ptr, BumpAllocator};
+use crate::{ptr, BumpAllocator, HeapStats};
 
 impl<G: bytemuck::Zeroable> BumpAllocator<G> {
     /// Creates a new allocator with given amount of available memory.
@@ -165,6 +165,42 @@ fn test_realloc_with_alloc() {
     assert_eq!(26, allocator.used());
 }
 
+#[test]
+fn test_stats() {
+    let allocator = BumpAllocator::<()>::new(64);
+    let available = allocator.stats().available;
+    assert_eq!(
+        HeapStats { requested: 0, used: 0, peak: 0, available },
+        allocator.stats()
+    );
+
+    let layout = Layout::array::<u8>(10).unwrap();
+    let first = allocator.check_alloc(layout).unwrap();
+    let second = allocator.check_alloc(layout).unwrap();
+    assert_eq!(
+        HeapStats {
+            requested: 20,
+            used: 20,
+            peak: 20,
+            available: available - 20
+        },
+        allocator.stats()
+    );
+
+    // Freeing memory doesn’t affect peak usage.
+    unsafe { allocator.dealloc(second, layout) };
+    let _ = allocator.check_realloc(first, layout, 5).unwrap();
+    assert_eq!(
+        HeapStats {
+            requested: 25,
+            used: 5,
+            peak: 20,
+            available: available - 20
+        },
+        allocator.stats()
+    );
+}
+
 #[test]
 fn test_global() {
     let allocator = BumpAllocator::<Cell<usize>>::new(64);
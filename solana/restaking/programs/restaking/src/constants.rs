@@ -4,6 +4,15 @@ pub const VAULT_SEED: &[u8] = b"vault";
 pub const TEST_SEED: &[u8] = b"abcdefg2";
 pub const ESCROW_RECEIPT_SEED: &[u8] = b"escrow_receipt";
 pub const REWARDS_SEED: &[u8] = b"rewards";
+pub const PARTIAL_WITHDRAWAL_SEED: &[u8] = b"partial_withdrawal";
+pub const ADMIN_ACTION_SEED: &[u8] = b"admin_action";
+pub const UNWRAP_SEED: &[u8] = b"unwrap";
+
+/// Maximum number of whitelisted tokens.
+///
+/// Must match `max_len` of the whitelist in `StakingParams` and of the new
+/// token mints in `AdminAction::UpdateTokenWhitelist`.
+pub const MAX_WHITELISTED_TOKENS: usize = 20;
 
 pub const TOKEN_NAME: &str = "Composable Restaking Position";
 pub const TOKEN_SYMBOL: &str = "CRP";
@@ -16,3 +25,10 @@ pub const TOKEN_URI: &str =
 /// feature enabled it’s set to one second for testing.
 pub const UNBONDING_PERIOD_IN_SEC: u64 =
     if cfg!(feature = "mocks") { 1 } else { 7 * 24 * 60 * 60 };
+
+/// Minimum time between two redelegations of the same vault.
+///
+/// Currently set to one day.  However, when code is compiled with `mocks`
+/// feature enabled it’s set to one second for testing.
+pub const REDELEGATION_COOLDOWN_IN_SEC: u64 =
+    if cfg!(feature = "mocks") { 1 } else { 24 * 60 * 60 };
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::metadata::{burn_nft, BurnNft, Metadata};
+use anchor_spl::token::spl_token::native_mint::ID as NATIVE_MINT;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use solana_ibc::chain::ChainData;
 use solana_ibc::cpi::accounts::SetStake;
@@ -12,8 +13,9 @@ mod token;
 mod validation;
 
 use constants::{
-    ESCROW_RECEIPT_SEED, REWARDS_SEED, STAKING_PARAMS_SEED, TEST_SEED,
-    VAULT_PARAMS_SEED, VAULT_SEED,
+    ADMIN_ACTION_SEED, ESCROW_RECEIPT_SEED, MAX_WHITELISTED_TOKENS,
+    PARTIAL_WITHDRAWAL_SEED, REWARDS_SEED, STAKING_PARAMS_SEED, TEST_SEED,
+    UNWRAP_SEED, VAULT_PARAMS_SEED, VAULT_SEED,
 };
 
 declare_id!("8n3FHwYxFgQCQc2FNFkwDUf9mcqupxXcCvgfHbApMLv3");
@@ -23,7 +25,9 @@ pub mod restaking {
 
     use anchor_spl::token::CloseAccount;
 
-    use self::constants::UNBONDING_PERIOD_IN_SEC;
+    use self::constants::{
+        REDELEGATION_COOLDOWN_IN_SEC, UNBONDING_PERIOD_IN_SEC,
+    };
     use super::*;
 
     pub fn initialize(
@@ -44,7 +48,8 @@ pub mod restaking {
     }
 
     /// Stakes the amount in the vault and if guest chain is initialized, a CPI call to the service is being
-    /// made to update the stake.
+    /// made to update the stake.  The stake change is queued on the guest chain and takes effect at the
+    /// next epoch boundary.
     ///
     /// We are sending the accounts needed for making CPI call to guest blockchain as [`remaining_accounts`]
     /// since we were running out of stack memory. Note that these accounts dont need to be sent until the
@@ -55,6 +60,9 @@ pub mod restaking {
     /// - trie
     /// - witness (if compiled with `witness` Cargo feature)
     /// - Guest blockchain program ID
+    ///
+    /// `depositor_token_account` must be provided unless native SOL is staked
+    /// in which case `deposit_sol` should be used.
     pub fn deposit<'a, 'info>(
         ctx: Context<'a, 'a, 'a, 'info, Deposit<'info>>,
         service: Service,
@@ -89,10 +97,32 @@ pub mod restaking {
         vault_params.stake_amount = amount;
         vault_params.stake_mint = ctx.accounts.token_mint.key();
         vault_params.last_received_rewards_height = 0;
+        vault_params.rent_payer = ctx.accounts.rent_payer.key();
+        vault_params.escrow_rent_payer = Pubkey::default();
+        vault_params.unbonding_epoch = None;
+        staking_params.sponsored_rent += sponsored_rent(
+            &vault_params.rent_payer,
+            &ctx.accounts.depositor.key(),
+            &vault_params.to_account_info(),
+        );
 
         // Transfer tokens to escrow
-
-        token::transfer(ctx.accounts.into(), &[], amount)?;
+        match ctx.accounts.depositor_token_account.as_ref() {
+            Some(depositor_token_account) => token::transfer(
+                token::TransferAccounts {
+                    from: depositor_token_account.to_account_info(),
+                    to: ctx.accounts.vault_token_account.to_account_info(),
+                    authority: ctx.accounts.depositor.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+                &[],
+                amount,
+            )?,
+            None if ctx.accounts.token_mint.key() == NATIVE_MINT => {
+                token::wrap_sol(ctx.accounts.into(), amount)?
+            }
+            None => return Err(error!(ErrorCodes::InvalidTokenAccount)),
+        }
 
         // Mint receipt tokens
         token::mint_nft(ctx.accounts.into())?;
@@ -111,19 +141,6 @@ pub mod restaking {
             ctx.remaining_accounts,
             &guest_chain_program_id,
         )?;
-        let borrowed_chain_data =
-            remaining_accounts.chain.try_borrow_data().unwrap();
-        let mut chain_data: &[u8] = &borrowed_chain_data;
-        let chain =
-            solana_ibc::chain::ChainData::try_deserialize(&mut chain_data)
-                .unwrap();
-        let validator = chain
-            .candidate(validator_key)
-            .map_err(|_| ErrorCodes::OperationNotAllowed)?;
-        let amount = validator.map_or(u128::from(amount), |val| {
-            u128::from(val.stake) + u128::from(amount)
-        });
-        core::mem::drop(borrowed_chain_data);
 
         let cpi_accounts = SetStake {
             sender: ctx.accounts.depositor.to_account_info(),
@@ -136,14 +153,40 @@ pub mod restaking {
         };
         let cpi_ctx =
             CpiContext::new(remaining_accounts.program.clone(), cpi_accounts);
-        solana_ibc::cpi::set_stake(cpi_ctx, validator_key, amount)
+        let stake_changes = vec![(validator_key.into(), i128::from(amount))];
+        solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)
+    }
+
+    /// Stakes native SOL.
+    ///
+    /// Works like `deposit` except that rather than transferring tokens from
+    /// depositor’s token account, `amount` lamports are transferred from the
+    /// depositor to the wrapped SOL vault and wrapped.  `token_mint` must be
+    /// the native mint (which has 9 decimals as the guest chain expects) and
+    /// `depositor_token_account` must not be provided.  The native mint has
+    /// to be whitelisted.
+    ///
+    /// On withdrawal, the stake is unwrapped and paid out in lamports.
+    pub fn deposit_sol<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, Deposit<'info>>,
+        service: Service,
+        amount: u64,
+    ) -> Result<()> {
+        if ctx.accounts.token_mint.key() != NATIVE_MINT {
+            return Err(error!(ErrorCodes::InvalidTokenMint));
+        }
+        if ctx.accounts.depositor_token_account.is_some() {
+            return Err(error!(ErrorCodes::InvalidTokenAccount));
+        }
+        deposit(ctx, service, amount)
     }
 
     /// Creates a withdrawal request by escrowing the receipt token. Once the unbonding
     /// period ends, the token from the escrow would be burnt and returned to the user.
     ///
-    /// This method transfers all the pending rewards to the user. The stake on the
-    /// guest chain is only updated after unbonding period ends in `withdraw` method.
+    /// This method transfers all the pending rewards to the user and removes
+    /// the stake from the guest chain.  The removal takes effect at a guest
+    /// epoch boundary and `withdraw` releases the tokens only once it has.
     ///
     /// Closes the receipt token account.
     pub fn withdrawal_request(ctx: Context<WithdrawalRequest>) -> Result<()> {
@@ -166,6 +209,12 @@ pub mod restaking {
             token_account: ctx.accounts.withdrawer_token_account.key(),
         };
         vault_params.withdrawal_request = Some(withdrawal_request_params);
+        vault_params.escrow_rent_payer = ctx.accounts.rent_payer.key();
+        staking_params.sponsored_rent += sponsored_rent(
+            &vault_params.escrow_rent_payer,
+            &ctx.accounts.withdrawer.key(),
+            &ctx.accounts.escrow_receipt_token_account.to_account_info(),
+        );
 
         let chain = &ctx.accounts.guest_chain;
         let service = vault_params
@@ -188,6 +237,29 @@ pub mod restaking {
 
         vault_params.last_received_rewards_height = current_height;
 
+        // Call Guest chain to remove the stake
+        let cpi_accounts = SetStake {
+            sender: ctx.accounts.withdrawer.to_account_info(),
+            chain: ctx.accounts.guest_chain.to_account_info(),
+            trie: ctx.accounts.trie.to_account_info(),
+            #[cfg(feature = "witness")]
+            witness: ctx.accounts.witness.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            instruction: validation::check_instructions_sysvar(
+                &ctx.accounts.instruction,
+            )?,
+        };
+        let cpi_program = ctx.accounts.guest_chain_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let stake_changes = vec![(
+            (*validator_key).into(),
+            -i128::from(vault_params.stake_amount),
+        )];
+        solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)?;
+        ctx.accounts.guest_chain.reload()?;
+        vault_params.unbonding_epoch =
+            Some(ctx.accounts.guest_chain.stake_activation_epoch()?);
+
         let bump = ctx.bumps.staking_params;
         let seeds =
             [STAKING_PARAMS_SEED, TEST_SEED, core::slice::from_ref(&bump)];
@@ -234,16 +306,63 @@ pub mod restaking {
     /// Even if the unbonding period is over and the withdraw is pending,
     /// this method would cancel the withdrawal request and return back the
     /// receipt NFT
+    ///
+    /// Rent of the escrow receipt token account is returned to the account
+    /// which paid it in `withdrawal_request`.  The stake removed from the guest
+    /// chain by the request is added back.
     pub fn cancel_withdrawal_request(
         ctx: Context<CancelWithdrawalRequest>,
     ) -> Result<()> {
         let vault_params = &mut ctx.accounts.vault_params;
+        let withdrawer = ctx.accounts.withdrawer.key();
 
         vault_params
             .withdrawal_request
             .ok_or(ErrorCodes::NoWithdrawalRequest)?;
 
+        check_rent_payer(
+            &vault_params.escrow_rent_payer,
+            &withdrawer,
+            &ctx.accounts.escrow_rent_payer,
+        )?;
+        let staking_params = &mut ctx.accounts.staking_params;
+        staking_params.sponsored_rent =
+            staking_params.sponsored_rent.saturating_sub(sponsored_rent(
+                ctx.accounts.escrow_rent_payer.key,
+                &withdrawer,
+                &ctx.accounts.escrow_receipt_token_account.to_account_info(),
+            ));
+
         vault_params.withdrawal_request = None;
+        vault_params.escrow_rent_payer = Pubkey::default();
+
+        // Requests made before the stake was removed at request time have no
+        // unbonding epoch and their stake is still on the guest chain.
+        if vault_params.unbonding_epoch.take().is_some() {
+            let validator_key = match vault_params.service {
+                Some(Service::GuestChain { validator }) => validator,
+                None => return Err(error!(ErrorCodes::MissingService)),
+            };
+            let cpi_accounts = SetStake {
+                sender: ctx.accounts.withdrawer.to_account_info(),
+                chain: ctx.accounts.guest_chain.to_account_info(),
+                trie: ctx.accounts.trie.to_account_info(),
+                #[cfg(feature = "witness")]
+                witness: ctx.accounts.witness.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                instruction: validation::check_instructions_sysvar(
+                    &ctx.accounts.instruction,
+                )?,
+            };
+            let cpi_program =
+                ctx.accounts.guest_chain_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            let stake_changes = vec![(
+                validator_key.into(),
+                i128::from(vault_params.stake_amount),
+            )];
+            solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)?;
+        }
 
         // If withdraw request is present, it means the amount has not been withdrew yet. So
         // we can just return the NFT from the escrow instead of checking the unbonding
@@ -264,7 +383,7 @@ pub mod restaking {
                 .accounts
                 .escrow_receipt_token_account
                 .to_account_info(),
-            destination: ctx.accounts.withdrawer.to_account_info(),
+            destination: ctx.accounts.escrow_rent_payer.to_account_info(),
             authority: ctx.accounts.staking_params.to_account_info(),
         };
 
@@ -287,8 +406,19 @@ pub mod restaking {
     /// withdrawal request. This is done so that we can enable automatic withdrawal
     /// after unbonding period. The amount is withdrawn to the account set during
     /// the request and the `vault_params` and `escrow_receipt_token_account` are
-    /// closed.
+    /// closed with their rent returned to the accounts which paid it.  Apart
+    /// from the unbonding period ending, removal of the stake from the guest
+    /// chain made by `withdrawal_request` must have taken effect.
+    ///
+    /// If the stake is native SOL, `unwrap_token_account` must be provided and
+    /// the stake is paid out to the withdrawer in lamports.  Rent of the
+    /// temporary unwrap account is returned to the signer who paid it.
     pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
+        check_unwrap_account(
+            &ctx.accounts.token_mint,
+            &ctx.accounts.unwrap_token_account,
+        )?;
+
         let vault_params = &mut ctx.accounts.vault_params;
         let staking_params = &mut ctx.accounts.staking_params;
         let stake_token_mint = ctx.accounts.token_mint.key();
@@ -315,6 +445,29 @@ pub mod restaking {
             return Err(error!(ErrorCodes::InvalidTokenAccount));
         };
 
+        let owner = withdrawal_request_params.owner;
+        check_rent_payer(
+            &vault_params.rent_payer,
+            &owner,
+            &ctx.accounts.rent_payer,
+        )?;
+        check_rent_payer(
+            &vault_params.escrow_rent_payer,
+            &owner,
+            &ctx.accounts.escrow_rent_payer,
+        )?;
+        let returned_rent = sponsored_rent(
+            ctx.accounts.rent_payer.key,
+            &owner,
+            &vault_params.to_account_info(),
+        ) + sponsored_rent(
+            ctx.accounts.escrow_rent_payer.key,
+            &owner,
+            &ctx.accounts.escrow_receipt_token_account.to_account_info(),
+        );
+        staking_params.sponsored_rent =
+            staking_params.sponsored_rent.saturating_sub(returned_rent);
+
         let unbonding_period = withdrawal_request_params.timestamp_in_sec +
             UNBONDING_PERIOD_IN_SEC;
 
@@ -331,12 +484,10 @@ pub mod restaking {
                 ErrorCodes::CannotWithdrawDuringUnbondingPeriod
             ));
         }
-
-        let chain = &ctx.accounts.guest_chain;
-        let service = vault_params
-            .service
-            .as_ref()
-            .ok_or_else(|| error!(ErrorCodes::MissingService))?;
+        let unbonding_epoch = vault_params.unbonding_epoch;
+        if !stake_unbonded(&ctx.accounts.guest_chain, unbonding_epoch)? {
+            return Err(error!(ErrorCodes::StakeStillBonded));
+        }
 
         // Since we dont have slashing yet, we would return the complete amount
         let amount = vault_params.stake_amount;
@@ -348,34 +499,28 @@ pub mod restaking {
         let seeds = seeds.as_ref();
         let seeds = core::slice::from_ref(&seeds);
 
-        // Call Guest chain to update the stake
-        let validator_key = match service {
-            Service::GuestChain { validator } => validator,
-        };
-        let validator = chain
-            .candidate(*validator_key)
-            .map_err(|_| ErrorCodes::OperationNotAllowed)?
-            .ok_or(ErrorCodes::MissingService)?;
-        let validator_stake = u128::from(validator.stake)
-            .checked_sub(u128::from(amount))
-            .ok_or(ErrorCodes::SubtractionOverflow)?;
-        let cpi_accounts = SetStake {
-            sender: ctx.accounts.withdrawer.to_account_info(),
-            chain: chain.to_account_info(),
-            trie: ctx.accounts.trie.to_account_info(),
-            #[cfg(feature = "witness")]
-            witness: ctx.accounts.witness.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
-            instruction: validation::check_instructions_sysvar(
-                &ctx.accounts.instruction,
+        // Transfer tokens from escrow unwrapping them if they are native SOL.
+        match ctx.accounts.unwrap_token_account.as_ref() {
+            Some(unwrap_token_account) => token::transfer_unwrapped(
+                token::TransferAccounts {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    to: unwrap_token_account.to_account_info(),
+                    authority: ctx.accounts.staking_params.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+                token::UnwrapAccounts {
+                    payer: ctx.accounts.signer.to_account_info(),
+                    destination: ctx.accounts.withdrawer.to_account_info(),
+                    system_program: ctx
+                        .accounts
+                        .system_program
+                        .to_account_info(),
+                },
+                seeds,
+                amount,
             )?,
-        };
-        let cpi_program = ctx.accounts.guest_chain_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        solana_ibc::cpi::set_stake(cpi_ctx, *validator_key, validator_stake)?;
-
-        // Transfer tokens from escrow
-        token::transfer(ctx.accounts.into(), seeds, amount)?;
+            None => token::transfer(ctx.accounts.into(), seeds, amount)?,
+        }
 
         // Burn receipt token from the escrow
         burn_nft(
@@ -401,135 +546,153 @@ pub mod restaking {
         )
     }
 
-    /// Whitelists new tokens
+    /// Requests withdrawal of a part of the stake held in the vault.
     ///
-    /// This method checks if any of the new token mints which are to be whitelisted
-    /// are already whitelisted. If they are the method fails to update the
-    /// whitelisted token list.
-    pub fn update_token_whitelist(
-        ctx: Context<UpdateStakingParams>,
-        new_token_mints: Vec<Pubkey>,
+    /// The vault is split in two.  The requested `amount` is moved to
+    /// a [`PartialWithdrawal`] account and can be withdrawn with
+    /// `withdraw_partial` once the unbonding period ends.  The remaining stake
+    /// is moved to a new vault for which a new receipt NFT is minted to the
+    /// withdrawer.  The old receipt NFT is burnt and the old vault params
+    /// account is closed with its rent returned to the account which paid it.
+    ///
+    /// Since from now on rewards accrue on the remaining stake only, this
+    /// method transfers all the pending rewards to the user.  The requested
+    /// amount is removed from validator’s stake on the guest chain and
+    /// `withdraw_partial` releases the tokens only once the removal has taken
+    /// effect.
+    pub fn request_partial_withdrawal(
+        ctx: Context<PartialWithdrawalRequest>,
+        amount: u64,
     ) -> Result<()> {
-        let staking_params = &mut ctx.accounts.staking_params;
-
-        let contains_mint = new_token_mints.iter().any(|token_mint| {
-            staking_params.whitelisted_tokens.contains(token_mint)
-        });
+        let vault_params = &ctx.accounts.vault_params;
 
-        if contains_mint {
-            return Err(error!(ErrorCodes::TokenAlreadyWhitelisted));
+        if ctx.accounts.staking_params.guest_chain_program_id.is_none() {
+            return Err(error!(ErrorCodes::OperationNotAllowed));
         }
 
-        staking_params
-            .whitelisted_tokens
-            .append(&mut new_token_mints.as_slice().to_vec());
-
-        Ok(())
-    }
-
-    /// Sets guest chain program ID
-    ///
-    /// After this method is called, CPI calls would be made to guest chain during deposit and stake would be
-    /// set to the validators. Users can also claim rewards or withdraw their stake
-    /// when the chain is initialized.
-    pub fn update_guest_chain_initialization(
-        ctx: Context<UpdateStakingParams>,
-        guest_chain_program_id: Pubkey,
-    ) -> Result<()> {
-        let staking_params = &mut ctx.accounts.staking_params;
-        if staking_params.guest_chain_program_id.is_some() {
-            return Err(error!(ErrorCodes::GuestChainAlreadyInitialized));
+        if ctx.accounts.token_mint.key() != vault_params.stake_mint {
+            return Err(error!(ErrorCodes::InvalidTokenMint));
         }
-        staking_params.guest_chain_program_id = Some(guest_chain_program_id);
-
-        Ok(())
-    }
-
-    /// Updating admin proposal created by the existing admin. Admin would only be changed
-    /// if the new admin accepts it in `accept_admin_change` instruction.
-    pub fn change_admin_proposal(
-        ctx: Context<UpdateStakingParams>,
-        new_admin: Pubkey,
-    ) -> Result<()> {
-        let staking_params = &mut ctx.accounts.staking_params;
-        msg!(
-            "Proposal for changing Admin from {} to {}",
-            staking_params.admin,
-            new_admin
-        );
 
-        staking_params.new_admin_proposal = Some(new_admin);
-        Ok(())
-    }
-
-    /// Accepting new admin change signed by the proposed admin. Admin would be changed if the
-    /// proposed admin calls the method. Would fail if there is no proposed admin and if the
-    /// signer is not the proposed admin.
-    pub fn accept_admin_change(ctx: Context<UpdateAdmin>) -> Result<()> {
-        let staking_params = &mut ctx.accounts.staking_params;
-        let new_admin = staking_params
-            .new_admin_proposal
-            .ok_or(ErrorCodes::NoProposedAdmin)?;
-        if new_admin != ctx.accounts.new_admin.key() {
-            return Err(error!(ErrorCode::ConstraintSigner));
+        if ctx.accounts.receipt_token_account.amount < 1 {
+            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
         }
 
-        msg!(
-            "Changing Admin from {} to {}",
-            staking_params.admin,
-            staking_params.new_admin_proposal.unwrap()
-        );
-        staking_params.admin = new_admin;
-
-        Ok(())
-    }
-
-    pub fn claim_rewards(ctx: Context<Claim>) -> Result<()> {
-        let staking_params = &ctx.accounts.staking_params;
-
-        if staking_params.guest_chain_program_id.is_none() {
-            return Err(error!(ErrorCodes::OperationNotAllowed));
+        if vault_params.withdrawal_request.is_some() {
+            return Err(error!(ErrorCodes::WithdrawalAlreadyRequested));
         }
 
-        let token_account = &ctx.accounts.receipt_token_account;
-        if token_account.amount < 1 {
-            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
+        // Withdrawing the entire stake must go through `withdrawal_request`
+        // so that we don’t create an empty vault.
+        if amount == 0 || amount >= vault_params.stake_amount {
+            return Err(error!(ErrorCodes::InvalidWithdrawalAmount));
         }
 
-        let vault_params = &mut ctx.accounts.vault_params;
-        let chain = &ctx.accounts.guest_chain;
-
-        let service = vault_params
-            .service
-            .as_ref()
-            .ok_or_else(|| error!(ErrorCodes::MissingService))?;
-        let validator_key = match service {
-            Service::GuestChain { validator } => validator,
+        let validator_key = match vault_params.service {
+            Some(Service::GuestChain { validator }) => validator,
+            None => return Err(error!(ErrorCodes::MissingService)),
         };
-        let stake_amount = vault_params.stake_amount;
-        let last_received_rewards_height =
-            vault_params.last_received_rewards_height;
 
         /*
          * Get the rewards from guest blockchain.
          */
 
-        let (rewards, current_height) = chain.calculate_rewards(
-            last_received_rewards_height,
-            *validator_key,
-            stake_amount,
-        )?;
+        let (rewards, current_height) =
+            ctx.accounts.guest_chain.calculate_rewards(
+                vault_params.last_received_rewards_height,
+                validator_key,
+                vault_params.stake_amount,
+            )?;
 
-        msg!(
-            "Current height {}, last claimed height {}",
-            current_height,
-            vault_params.last_received_rewards_height
+        // Call Guest chain to remove the withdrawn stake
+        let cpi_accounts = SetStake {
+            sender: ctx.accounts.withdrawer.to_account_info(),
+            chain: ctx.accounts.guest_chain.to_account_info(),
+            trie: ctx.accounts.trie.to_account_info(),
+            #[cfg(feature = "witness")]
+            witness: ctx.accounts.witness.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            instruction: validation::check_instructions_sysvar(
+                &ctx.accounts.instruction,
+            )?,
+        };
+        let cpi_program = ctx.accounts.guest_chain_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let stake_changes = vec![(validator_key.into(), -i128::from(amount))];
+        solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)?;
+        ctx.accounts.guest_chain.reload()?;
+        let unbonding_epoch =
+            ctx.accounts.guest_chain.stake_activation_epoch()?;
+
+        let withdrawer = ctx.accounts.withdrawer.key();
+        check_rent_payer(
+            &vault_params.rent_payer,
+            &withdrawer,
+            &ctx.accounts.vault_rent_payer,
+        )?;
+        let returned_rent = sponsored_rent(
+            ctx.accounts.vault_rent_payer.key,
+            &withdrawer,
+            &vault_params.to_account_info(),
         );
-        vault_params.last_received_rewards_height = current_height;
 
-        /*
-         * Get the current price of rewards token mint from the oracle
-         */
+        let new_vault_params = &mut ctx.accounts.new_vault_params;
+        new_vault_params.stake_timestamp_sec = vault_params.stake_timestamp_sec;
+        new_vault_params.service = vault_params.service;
+        new_vault_params.stake_amount = vault_params.stake_amount - amount;
+        new_vault_params.stake_mint = vault_params.stake_mint;
+        new_vault_params.last_received_rewards_height = current_height;
+        new_vault_params.withdrawal_request = None;
+        new_vault_params.last_redelegation_sec =
+            vault_params.last_redelegation_sec;
+        new_vault_params.rent_payer = ctx.accounts.rent_payer.key();
+        new_vault_params.escrow_rent_payer = Pubkey::default();
+        new_vault_params.unbonding_epoch = None;
+
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        let partial_withdrawal = &mut ctx.accounts.partial_withdrawal;
+        partial_withdrawal.validator = validator_key;
+        partial_withdrawal.amount = amount;
+        partial_withdrawal.request = WithdrawalRequestParams {
+            timestamp_in_sec: current_timestamp,
+            owner: ctx.accounts.withdrawer.key(),
+            token_account: ctx.accounts.withdrawer_token_account.key(),
+        };
+        partial_withdrawal.rent_payer = ctx.accounts.rent_payer.key();
+        partial_withdrawal.unbonding_epoch = unbonding_epoch;
+        let staking_params = &mut ctx.accounts.staking_params;
+        staking_params.sponsored_rent =
+            staking_params.sponsored_rent.saturating_sub(returned_rent) +
+                sponsored_rent(
+                    &partial_withdrawal.rent_payer,
+                    &withdrawer,
+                    &partial_withdrawal.to_account_info(),
+                ) +
+                sponsored_rent(
+                    &new_vault_params.rent_payer,
+                    &withdrawer,
+                    &new_vault_params.to_account_info(),
+                );
+
+        // Burn the old receipt token and mint one for the new vault.
+        burn_nft(
+            CpiContext::new(
+                ctx.accounts.metadata_program.to_account_info(),
+                BurnNft {
+                    metadata: ctx.accounts.nft_metadata.to_account_info(),
+                    owner: ctx.accounts.withdrawer.to_account_info(),
+                    spl_token: ctx.accounts.token_program.to_account_info(),
+                    mint: ctx.accounts.receipt_token_mint.to_account_info(),
+                    token: ctx.accounts.receipt_token_account.to_account_info(),
+                    edition: ctx
+                        .accounts
+                        .master_edition_account
+                        .to_account_info(),
+                },
+            ),
+            None,
+        )?;
+        token::mint_nft(ctx.accounts.into())?;
 
         let bump = ctx.bumps.staking_params;
         let seeds =
@@ -537,34 +700,396 @@ pub mod restaking {
         let seeds = seeds.as_ref();
         let seeds = core::slice::from_ref(&seeds);
 
-        // Transfer the tokens from the platfrom rewards token account to the user token account
-        token::transfer(ctx.accounts.into(), seeds, rewards)?;
-
-        Ok(())
+        // Transfer rewards from platform wallet
+        token::transfer(
+            token::TransferAccounts {
+                from: ctx
+                    .accounts
+                    .platform_rewards_token_account
+                    .to_account_info(),
+                to: ctx
+                    .accounts
+                    .depositor_rewards_token_account
+                    .to_account_info(),
+                authority: ctx.accounts.staking_params.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            seeds,
+            rewards,
+        )
     }
 
-    /// This method sets the service for the stake which was deposited before guest chain
-    /// initialization
+    /// Withdraws the tokens requested in `request_partial_withdrawal` once the
+    /// unbonding period has ended.
     ///
-    /// This method can only be called if the service was not set during the depositing and
-    /// can only be called once. Calling otherwise would panic.
+    /// Similarly to `withdraw`, this method can be called by anybody and
+    /// requires removal of the withdrawn stake from the guest chain to have
+    /// taken effect.  The tokens are transferred to the account set during the
+    /// request and the [`PartialWithdrawal`] account is closed with its rent
+    /// returned to the account which paid it.
     ///
-    /// The accounts for CPI are sent as remaining accounts similar to `deposit` method.
-    pub fn set_service<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, SetService<'info>>,
-        service: Service,
-    ) -> Result<()> {
-        let vault_params = &mut ctx.accounts.vault_params;
+    /// If the stake is native SOL, `unwrap_token_account` must be provided and
+    /// the tokens are paid out to the withdrawer in lamports.  Rent of the
+    /// temporary unwrap account is returned to the signer who paid it.
+    pub fn withdraw_partial(ctx: Context<WithdrawPartial>) -> Result<()> {
+        check_unwrap_account(
+            &ctx.accounts.token_mint,
+            &ctx.accounts.unwrap_token_account,
+        )?;
+
+        let partial_withdrawal = &ctx.accounts.partial_withdrawal;
         let staking_params = &mut ctx.accounts.staking_params;
+        let request = partial_withdrawal.request;
+        let amount = partial_withdrawal.amount;
 
-        let token_account = &ctx.accounts.receipt_token_account;
-        if token_account.amount < 1 {
-            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
+        if ctx.accounts.withdrawer.key() != request.owner {
+            return Err(error!(ErrorCodes::InvalidWithdrawer));
         }
-        let guest_chain_program_id = match staking_params.guest_chain_program_id
+
+        if ctx.accounts.withdrawer_token_account.key() != request.token_account
         {
-            Some(id) => id,
-            None => return Err(error!(ErrorCodes::OperationNotAllowed)),
+            return Err(error!(ErrorCodes::InvalidTokenAccount));
+        };
+
+        check_rent_payer(
+            &partial_withdrawal.rent_payer,
+            &request.owner,
+            &ctx.accounts.rent_payer,
+        )?;
+        staking_params.sponsored_rent =
+            staking_params.sponsored_rent.saturating_sub(sponsored_rent(
+                ctx.accounts.rent_payer.key,
+                &request.owner,
+                &partial_withdrawal.to_account_info(),
+            ));
+
+        let unbonding_period =
+            request.timestamp_in_sec + UNBONDING_PERIOD_IN_SEC;
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        if current_timestamp < unbonding_period {
+            return Err(error!(
+                ErrorCodes::CannotWithdrawDuringUnbondingPeriod
+            ));
+        }
+        let unbonding_epoch = Some(partial_withdrawal.unbonding_epoch);
+        if !stake_unbonded(&ctx.accounts.guest_chain, unbonding_epoch)? {
+            return Err(error!(ErrorCodes::StakeStillBonded));
+        }
+
+        staking_params.total_deposited_amount -= amount as u128;
+
+        let bump = ctx.bumps.staking_params;
+        let seeds =
+            [STAKING_PARAMS_SEED, TEST_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+
+        // Transfer tokens from escrow unwrapping them if they are native SOL.
+        let from = ctx.accounts.vault_token_account.to_account_info();
+        let authority = ctx.accounts.staking_params.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        match ctx.accounts.unwrap_token_account.as_ref() {
+            Some(unwrap_token_account) => token::transfer_unwrapped(
+                token::TransferAccounts {
+                    from,
+                    to: unwrap_token_account.to_account_info(),
+                    authority,
+                    token_program,
+                },
+                token::UnwrapAccounts {
+                    payer: ctx.accounts.signer.to_account_info(),
+                    destination: ctx.accounts.withdrawer.to_account_info(),
+                    system_program: ctx
+                        .accounts
+                        .system_program
+                        .to_account_info(),
+                },
+                seeds,
+                amount,
+            ),
+            None => token::transfer(
+                token::TransferAccounts {
+                    from,
+                    to: ctx.accounts.withdrawer_token_account.to_account_info(),
+                    authority,
+                    token_program,
+                },
+                seeds,
+                amount,
+            ),
+        }
+    }
+
+    /// Moves vault’s stake from its current validator to another one without
+    /// going through the unbonding period.
+    ///
+    /// The stake is moved on the guest chain with a single `update_stake` CPI
+    /// which queues reduction of stake of the old validator and increase of
+    /// stake of the new one by the same amount.  The queued changes take
+    /// effect at the next guest epoch boundary so until then the stake still
+    /// counts towards the old validator.
+    ///
+    /// Since rewards accrue per validator, this method transfers all the
+    /// pending rewards to the user.  The vault stops earning rewards of the
+    /// old validator immediately and starts earning rewards of the new one
+    /// once the move takes effect.
+    ///
+    /// Vault can be redelegated at most once per
+    /// [`REDELEGATION_COOLDOWN_IN_SEC`] seconds and not while withdrawal of
+    /// the entire stake has been requested.
+    pub fn redelegate(
+        ctx: Context<Redelegate>,
+        new_validator: Pubkey,
+    ) -> Result<()> {
+        let vault_params = &mut ctx.accounts.vault_params;
+
+        if ctx.accounts.staking_params.guest_chain_program_id.is_none() {
+            return Err(error!(ErrorCodes::OperationNotAllowed));
+        }
+
+        if ctx.accounts.receipt_token_account.amount < 1 {
+            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
+        }
+
+        if vault_params.withdrawal_request.is_some() {
+            return Err(error!(ErrorCodes::WithdrawalAlreadyRequested));
+        }
+
+        let old_validator = match vault_params.service {
+            Some(Service::GuestChain { validator }) => validator,
+            None => return Err(error!(ErrorCodes::MissingService)),
+        };
+        if old_validator == new_validator {
+            return Err(error!(ErrorCodes::SameValidator));
+        }
+
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        let cooldown_ends_at_sec =
+            vault_params.last_redelegation_sec + REDELEGATION_COOLDOWN_IN_SEC;
+        if current_timestamp < cooldown_ends_at_sec {
+            return Err(error!(ErrorCodes::RedelegationCooldown));
+        }
+
+        /*
+         * Get the rewards from guest blockchain.
+         */
+
+        let (rewards, current_height) =
+            ctx.accounts.guest_chain.calculate_rewards(
+                vault_params.last_received_rewards_height,
+                old_validator,
+                vault_params.stake_amount,
+            )?;
+
+        vault_params.last_received_rewards_height = current_height;
+        vault_params.service =
+            Some(Service::GuestChain { validator: new_validator });
+        vault_params.last_redelegation_sec = current_timestamp;
+
+        let amount = i128::from(vault_params.stake_amount);
+
+        let bump = ctx.bumps.staking_params;
+        let seeds =
+            [STAKING_PARAMS_SEED, TEST_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+
+        // Call Guest chain to move the stake
+        let cpi_accounts = SetStake {
+            sender: ctx.accounts.depositor.to_account_info(),
+            chain: ctx.accounts.guest_chain.to_account_info(),
+            trie: ctx.accounts.trie.to_account_info(),
+            #[cfg(feature = "witness")]
+            witness: ctx.accounts.witness.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            instruction: validation::check_instructions_sysvar(
+                &ctx.accounts.instruction,
+            )?,
+        };
+        let cpi_program = ctx.accounts.guest_chain_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let stake_changes = vec![
+            (old_validator.into(), -amount),
+            (new_validator.into(), amount),
+        ];
+        solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)?;
+
+        // Transfer rewards from platform wallet
+        token::transfer(
+            token::TransferAccounts {
+                from: ctx
+                    .accounts
+                    .platform_rewards_token_account
+                    .to_account_info(),
+                to: ctx
+                    .accounts
+                    .depositor_rewards_token_account
+                    .to_account_info(),
+                authority: ctx.accounts.staking_params.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            seeds,
+            rewards,
+        )
+    }
+
+    /// Whitelists new tokens
+    ///
+    /// This method checks if any of the new token mints which are to be whitelisted
+    /// are already whitelisted. If they are the method fails to update the
+    /// whitelisted token list.
+    ///
+    /// Fails if timelock is enabled in which case the change has to be queued
+    /// with `queue_admin_action`.
+    pub fn update_token_whitelist(
+        ctx: Context<UpdateStakingParams>,
+        new_token_mints: Vec<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts
+            .staking_params
+            .apply_now(AdminAction::UpdateTokenWhitelist { new_token_mints })
+    }
+
+    /// Sets guest chain program ID
+    ///
+    /// After this method is called, CPI calls would be made to guest chain during deposit and stake would be
+    /// set to the validators. Users can also claim rewards or withdraw their stake
+    /// when the chain is initialized.
+    ///
+    /// Fails if timelock is enabled in which case the change has to be queued
+    /// with `queue_admin_action`.
+    pub fn update_guest_chain_initialization(
+        ctx: Context<UpdateStakingParams>,
+        guest_chain_program_id: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.staking_params.apply_now(
+            AdminAction::UpdateGuestChainInitialization {
+                guest_chain_program_id,
+            },
+        )
+    }
+
+    /// Updating admin proposal created by the existing admin. Admin would only be changed
+    /// if the new admin accepts it in `accept_admin_change` instruction.
+    pub fn change_admin_proposal(
+        ctx: Context<UpdateStakingParams>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        let staking_params = &mut ctx.accounts.staking_params;
+        msg!(
+            "Proposal for changing Admin from {} to {}",
+            staking_params.admin,
+            new_admin
+        );
+
+        staking_params.new_admin_proposal = Some(new_admin);
+        Ok(())
+    }
+
+    /// Accepting new admin change signed by the proposed admin. Admin would be changed if the
+    /// proposed admin calls the method. Would fail if there is no proposed admin and if the
+    /// signer is not the proposed admin.
+    pub fn accept_admin_change(ctx: Context<UpdateAdmin>) -> Result<()> {
+        let staking_params = &mut ctx.accounts.staking_params;
+        let new_admin = staking_params
+            .new_admin_proposal
+            .ok_or(ErrorCodes::NoProposedAdmin)?;
+        if new_admin != ctx.accounts.new_admin.key() {
+            return Err(error!(ErrorCode::ConstraintSigner));
+        }
+
+        msg!(
+            "Changing Admin from {} to {}",
+            staking_params.admin,
+            staking_params.new_admin_proposal.unwrap()
+        );
+        staking_params.admin = new_admin;
+
+        Ok(())
+    }
+
+    pub fn claim_rewards(ctx: Context<Claim>) -> Result<()> {
+        let staking_params = &ctx.accounts.staking_params;
+
+        if staking_params.guest_chain_program_id.is_none() {
+            return Err(error!(ErrorCodes::OperationNotAllowed));
+        }
+
+        let token_account = &ctx.accounts.receipt_token_account;
+        if token_account.amount < 1 {
+            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
+        }
+
+        let vault_params = &mut ctx.accounts.vault_params;
+        let chain = &ctx.accounts.guest_chain;
+
+        let service = vault_params
+            .service
+            .as_ref()
+            .ok_or_else(|| error!(ErrorCodes::MissingService))?;
+        let validator_key = match service {
+            Service::GuestChain { validator } => validator,
+        };
+        let stake_amount = vault_params.stake_amount;
+        let last_received_rewards_height =
+            vault_params.last_received_rewards_height;
+
+        /*
+         * Get the rewards from guest blockchain.
+         */
+
+        let (rewards, current_height) = chain.calculate_rewards(
+            last_received_rewards_height,
+            *validator_key,
+            stake_amount,
+        )?;
+
+        msg!(
+            "Current height {}, last claimed height {}",
+            current_height,
+            vault_params.last_received_rewards_height
+        );
+        vault_params.last_received_rewards_height = current_height;
+
+        /*
+         * Get the current price of rewards token mint from the oracle
+         */
+
+        let bump = ctx.bumps.staking_params;
+        let seeds =
+            [STAKING_PARAMS_SEED, TEST_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+
+        // Transfer the tokens from the platfrom rewards token account to the user token account
+        token::transfer(ctx.accounts.into(), seeds, rewards)?;
+
+        Ok(())
+    }
+
+    /// This method sets the service for the stake which was deposited before guest chain
+    /// initialization
+    ///
+    /// This method can only be called if the service was not set during the depositing and
+    /// can only be called once. Calling otherwise would panic.
+    ///
+    /// The accounts for CPI are sent as remaining accounts similar to `deposit` method.
+    /// Like in `deposit`, the stake change is queued on the guest chain until the next epoch.
+    pub fn set_service<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetService<'info>>,
+        service: Service,
+    ) -> Result<()> {
+        let vault_params = &mut ctx.accounts.vault_params;
+        let staking_params = &mut ctx.accounts.staking_params;
+
+        let token_account = &ctx.accounts.receipt_token_account;
+        if token_account.amount < 1 {
+            return Err(error!(ErrorCodes::InsufficientReceiptTokenBalance));
+        }
+        let guest_chain_program_id = match staking_params.guest_chain_program_id
+        {
+            Some(id) => id,
+            None => return Err(error!(ErrorCodes::OperationNotAllowed)),
         };
         let remaining_accounts = validation::validate_remaining_accounts(
             ctx.remaining_accounts,
@@ -586,20 +1111,6 @@ pub mod restaking {
         let validator_key = match service {
             Service::GuestChain { validator } => validator,
         };
-        let borrowed_chain_data =
-            remaining_accounts.chain.try_borrow_data().unwrap();
-        let mut chain_data: &[u8] = &borrowed_chain_data;
-        let chain =
-            solana_ibc::chain::ChainData::try_deserialize(&mut chain_data)
-                .unwrap();
-        let validator = chain
-            .candidate(validator_key)
-            .map_err(|_| ErrorCodes::OperationNotAllowed)?;
-        let amount = validator.map_or(u128::from(amount), |val| {
-            u128::from(val.stake) + u128::from(amount)
-        });
-        // Drop refcount on chain data so we can use it in CPI call
-        core::mem::drop(borrowed_chain_data);
 
         let cpi_accounts = SetStake {
             sender: ctx.accounts.depositor.to_account_info(),
@@ -614,7 +1125,8 @@ pub mod restaking {
         };
         let cpi_ctx =
             CpiContext::new(remaining_accounts.program.clone(), cpi_accounts);
-        solana_ibc::cpi::set_stake(cpi_ctx, validator_key, amount)
+        let stake_changes = vec![(validator_key.into(), i128::from(amount))];
+        solana_ibc::cpi::update_stake(cpi_ctx, stake_changes)
     }
 
     /// This method would only be called by `Admin` to withdraw all the funds from the rewards account
@@ -640,23 +1152,211 @@ pub mod restaking {
         token::transfer(ctx.accounts.into(), seeds, rewards_balance)
     }
 
+    /// Increases the staking cap.
+    ///
+    /// Fails if timelock is enabled in which case the change has to be queued
+    /// with `queue_admin_action`.
     pub fn update_staking_cap(
         ctx: Context<UpdateStakingParams>,
         new_staking_cap: u128,
     ) -> Result<()> {
-        let staking_params = &mut ctx.accounts.staking_params;
-
-        if staking_params.staking_cap >= new_staking_cap {
-            return Err(error!(
-                ErrorCodes::NewStakingCapShouldBeMoreThanExistingOne
-            ));
-        }
-
-        staking_params.staking_cap = new_staking_cap;
-
-        Ok(())
+        ctx.accounts
+            .staking_params
+            .apply_now(AdminAction::UpdateStakingCap { new_staking_cap })
     }
-}
+
+    /// Sets the delay after which queued admin actions can be executed.
+    ///
+    /// Zero delay (the default) disables the timelock and admin actions take
+    /// effect immediately.  Once timelock is enabled, changing the delay
+    /// (including disabling the timelock) is itself subject to the timelock
+    /// and has to be queued with `queue_admin_action`.
+    pub fn set_timelock_delay(
+        ctx: Context<UpdateStakingParams>,
+        delay_sec: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .staking_params
+            .apply_now(AdminAction::SetTimelockDelay { delay_sec })
+    }
+
+    /// Queues an admin action to be executed once the timelock delay passes.
+    ///
+    /// Only callable by admin and only if timelock is enabled.  The action is
+    /// stored in a new account which address is derived from sequential
+    /// action id.  Once the delay passes, anyone can execute the action with
+    /// `execute_admin_action`.  Until then, admin can cancel it with
+    /// `cancel_admin_action`.
+    ///
+    /// Whitelist update can add at most [`MAX_WHITELISTED_TOKENS`] tokens
+    /// since that’s how many the queued action account has space for.
+    pub fn queue_admin_action(
+        ctx: Context<QueueAdminAction>,
+        action: AdminAction,
+    ) -> Result<()> {
+        let staking_params = &mut ctx.accounts.staking_params;
+        if staking_params.timelock_delay_sec == 0 {
+            return Err(error!(ErrorCodes::TimelockDisabled));
+        }
+        if let AdminAction::UpdateTokenWhitelist { new_token_mints } = &action {
+            if new_token_mints.len() > MAX_WHITELISTED_TOKENS {
+                return Err(error!(ErrorCodes::TooManyWhitelistedTokens));
+            }
+        }
+        let id = staking_params.next_admin_action_id;
+        staking_params.next_admin_action_id += 1;
+        let eta_sec = (Clock::get()?.unix_timestamp as u64)
+            .saturating_add(staking_params.timelock_delay_sec);
+
+        let queued_action = &mut ctx.accounts.queued_action;
+        queued_action.id = id;
+        queued_action.payer = ctx.accounts.admin.key();
+        queued_action.eta_sec = eta_sec;
+        queued_action.action = action.clone();
+
+        emit!(AdminActionQueued { id, action, eta_sec });
+        Ok(())
+    }
+
+    /// Executes queued admin action whose timelock delay has passed.
+    ///
+    /// Can be called by anyone.  The queued action account is closed and its
+    /// rent is returned to the admin who queued it.
+    pub fn execute_admin_action(
+        ctx: Context<ExecuteAdminAction>,
+    ) -> Result<()> {
+        let queued_action = &ctx.accounts.queued_action;
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        if current_timestamp < queued_action.eta_sec {
+            return Err(error!(ErrorCodes::AdminActionNotReady));
+        }
+        ctx.accounts.staking_params.apply(queued_action.action.clone())?;
+        emit!(AdminActionExecuted { id: queued_action.id });
+        Ok(())
+    }
+
+    /// Cancels queued admin action.
+    ///
+    /// Only callable by admin.  The queued action account is closed.
+    pub fn cancel_admin_action(ctx: Context<CancelAdminAction>) -> Result<()> {
+        emit!(AdminActionCancelled { id: ctx.accounts.queued_action.id });
+        Ok(())
+    }
+
+    /// Returns summary of the position identified by the receipt token mint.
+    ///
+    /// This is a read-only method meant to be simulated by clients.  It
+    /// aggregates state of the vault, rewards pending to be claimed and status
+    /// of the withdrawal request into a single [`Position`] which is returned
+    /// via return data.  Rewards are calculated the same way `claim_rewards`
+    /// calculates them.
+    pub fn get_position(ctx: Context<GetPosition>) -> Result<Position> {
+        let vault_params = &ctx.accounts.vault_params;
+        let staking_params = &ctx.accounts.staking_params;
+
+        let pending_rewards = match vault_params.service {
+            Some(Service::GuestChain { validator })
+                if staking_params.guest_chain_program_id.is_some() =>
+            {
+                ctx.accounts
+                    .guest_chain
+                    .calculate_rewards(
+                        vault_params.last_received_rewards_height,
+                        validator,
+                        vault_params.stake_amount,
+                    )
+                    .map_or(0, |(rewards, _)| rewards)
+            }
+            _ => 0,
+        };
+
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        let unbonding_ends_at_sec = vault_params
+            .withdrawal_request
+            .as_ref()
+            .map(|request| request.timestamp_in_sec + UNBONDING_PERIOD_IN_SEC);
+        let unbonded = stake_unbonded(
+            &ctx.accounts.guest_chain,
+            vault_params.unbonding_epoch,
+        )
+        .unwrap_or(false);
+        let can_withdraw = unbonded &&
+            unbonding_ends_at_sec
+                .map_or(false, |ends_at| ends_at <= current_timestamp);
+
+        Ok(Position {
+            stake_mint: vault_params.stake_mint,
+            stake_amount: vault_params.stake_amount,
+            stake_timestamp_sec: vault_params.stake_timestamp_sec,
+            service: vault_params.service,
+            last_received_rewards_height: vault_params
+                .last_received_rewards_height,
+            pending_rewards,
+            withdrawal_request: vault_params.withdrawal_request,
+            unbonding_ends_at_sec,
+            can_withdraw,
+        })
+    }
+}
+
+/// Returns lamports held by `account` if its rent was paid by a sponsor, i.e.
+/// `rent_payer` is different from the `user`; zero otherwise.
+///
+/// Used to keep [`StakingParams::sponsored_rent`] up to date when accounts
+/// are created and closed.
+fn sponsored_rent(
+    rent_payer: &Pubkey,
+    user: &Pubkey,
+    account: &AccountInfo<'_>,
+) -> u64 {
+    if rent_payer == user {
+        0
+    } else {
+        account.lamports()
+    }
+}
+
+/// Returns whether removal of stake which takes effect in given guest epoch
+/// has taken effect, i.e. whether tokens backing the stake can be released.
+///
+/// `None` epoch means the stake hasn’t been removed.
+fn stake_unbonded(
+    chain: &ChainData,
+    unbonding_epoch: Option<u64>,
+) -> Result<bool> {
+    let epoch = chain.epoch_number()?;
+    Ok(unbonding_epoch.is_some_and(|unbonding| epoch >= unbonding))
+}
+
+/// Checks that `account` is the one rent of a closed account is returned to.
+///
+/// `rent_payer` is the payer recorded when the account was created.  All
+/// zeros means the account was created before rent sponsoring was introduced
+/// in which case the rent is returned to the `owner`.
+fn check_rent_payer(
+    rent_payer: &Pubkey,
+    owner: &Pubkey,
+    account: &AccountInfo<'_>,
+) -> Result<()> {
+    let expected =
+        if *rent_payer == Pubkey::default() { owner } else { rent_payer };
+    if account.key != expected {
+        return Err(error!(ErrorCodes::InvalidRentPayer));
+    }
+    Ok(())
+}
+
+/// Checks that unwrap account is provided if and only if the stake is native
+/// SOL.
+fn check_unwrap_account(
+    token_mint: &Account<'_, Mint>,
+    unwrap_token_account: &Option<Box<Account<'_, TokenAccount>>>,
+) -> Result<()> {
+    if (token_mint.key() == NATIVE_MINT) != unwrap_token_account.is_some() {
+        return Err(error!(ErrorCodes::InvalidUnwrapAccount));
+    }
+    Ok(())
+}
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -679,7 +1379,12 @@ pub struct Deposit<'info> {
     #[account(mut)]
     pub depositor: Signer<'info>,
 
-    #[account(init, payer = depositor, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump, space = 8 + 1024)]
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the depositor or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(init, payer = rent_payer, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump, space = 8 + 1024)]
     pub vault_params: Box<Account<'info, Vault>>,
     #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
     pub staking_params: Box<Account<'info, StakingParams>>,
@@ -689,21 +1394,23 @@ pub struct Deposit<'info> {
     /// decimals, it would just be invalid.
     #[account(mut, mint::decimals = 9)]
     pub token_mint: Box<Account<'info, Mint>>,
+    /// Account the stake is transferred from.  Not used when staking native
+    /// SOL with `deposit_sol`.
     #[account(mut, token::mint = token_mint, token::authority = depositor.key())]
-    pub depositor_token_account: Box<Account<'info, TokenAccount>>,
+    pub depositor_token_account: Option<Box<Account<'info, TokenAccount>>>,
 
-    #[account(init_if_needed, payer = depositor, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    #[account(init_if_needed, payer = rent_payer, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
     pub vault_token_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         init,
-        payer = depositor,
+        payer = rent_payer,
         mint::decimals = 0,
         mint::authority = depositor,
         mint::freeze_authority = depositor,
     )]
     pub receipt_token_mint: Box<Account<'info, Mint>>,
-    #[account(init, payer = depositor, associated_token::mint = receipt_token_mint, associated_token::authority = depositor)]
+    #[account(init, payer = rent_payer, associated_token::mint = receipt_token_mint, associated_token::authority = depositor)]
     pub receipt_token_account: Box<Account<'info, TokenAccount>>,
 
     pub metadata_program: Program<'info, Metadata>,
@@ -711,15 +1418,252 @@ pub struct Deposit<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
-
-    /// The Instructions sysvar.
-    ///
-    /// CHECK: The account is passed on during CPI and destination contract
-    /// performs the validation so this is safe even if we don’t check the
-    /// address.  Nonetheless, the account is checked at each use.
-    #[account(address = solana_program::sysvar::instructions::ID)]
-    pub instruction: UncheckedAccount<'info>,
-
+
+    /// The Instructions sysvar.
+    ///
+    /// CHECK: The account is passed on during CPI and destination contract
+    /// performs the validation so this is safe even if we don’t check the
+    /// address.  Nonetheless, the account is checked at each use.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instruction: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            receipt_token_mint.key().as_ref(),
+            b"edition".as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK:
+    pub master_edition_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            receipt_token_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK:
+    pub nft_metadata: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawalRequest<'info> {
+    #[account(mut)]
+    pub withdrawer: Signer<'info>,
+
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the withdrawer or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub vault_params: Box<Account<'info, Vault>>,
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = rewards_token_mint)]
+    pub staking_params: Box<Account<'info, StakingParams>>,
+
+    #[account(mut, seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
+    pub guest_chain: Box<Account<'info, ChainData>>,
+    #[account(mut, seeds = [TRIE_SEED], bump, seeds::program = guest_chain_program.key())]
+    /// CHECK:
+    pub trie: AccountInfo<'info>,
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [solana_ibc::WITNESS_SEED, trie.key().as_ref()], bump)]
+    /// CHECK:
+    pub witness: AccountInfo<'info>,
+
+    pub token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, token::mint = token_mint, token::authority = withdrawer.key())]
+    pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub rewards_token_mint: Box<Account<'info, Mint>>,
+    #[account(init_if_needed, payer = rent_payer, associated_token::mint = rewards_token_mint, associated_token::authority = withdrawer)]
+    pub depositor_rewards_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [REWARDS_SEED, TEST_SEED], bump, token::mint = rewards_token_mint, token::authority = staking_params)]
+    pub platform_rewards_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        mint::decimals = 0,
+        mint::authority = master_edition_account,
+        // mint::freeze_authority = withdrawer,
+    )]
+    pub receipt_token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, token::mint = receipt_token_mint, token::authority = withdrawer)]
+    pub receipt_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Account which stores the receipt token until unbonding period ends.
+    #[account(init, payer = rent_payer, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
+    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub guest_chain_program: Program<'info, SolanaIbc>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            receipt_token_mint.key().as_ref(),
+            b"edition".as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK:
+    pub master_edition_account: UncheckedAccount<'info>,
+
+    /// The Instructions sysvar.
+    ///
+    /// CHECK: The account is passed on during CPI and destination contract
+    /// performs the validation so this is safe even if we don’t check the
+    /// address.  Nonetheless, the account is checked at each use.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instruction: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawalRequest<'info> {
+    #[account(mut)]
+    pub withdrawer: Signer<'info>,
+
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the withdrawer or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub vault_params: Box<Account<'info, Vault>>,
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
+    pub staking_params: Box<Account<'info, StakingParams>>,
+
+    #[account(mut, seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
+    pub guest_chain: Box<Account<'info, ChainData>>,
+    #[account(mut, seeds = [TRIE_SEED], bump, seeds::program = guest_chain_program.key())]
+    /// CHECK:
+    pub trie: AccountInfo<'info>,
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [solana_ibc::WITNESS_SEED, trie.key().as_ref()], bump)]
+    /// CHECK:
+    pub witness: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        mint::decimals = 0,
+        mint::authority = master_edition_account,
+        // mint::freeze_authority = withdrawer,
+    )]
+    pub receipt_token_mint: Box<Account<'info, Mint>>,
+    #[account(init, payer = rent_payer, associated_token::mint = receipt_token_mint, associated_token::authority = withdrawer)]
+    pub receipt_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Account which paid rent of the escrow receipt token account and gets
+    /// it back.
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub escrow_rent_payer: AccountInfo<'info>,
+
+    /// Account which stores the receipt token until unbonding period ends.
+    #[account(mut, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
+    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub guest_chain_program: Program<'info, SolanaIbc>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            receipt_token_mint.key().as_ref(),
+            b"edition".as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK:
+    pub master_edition_account: UncheckedAccount<'info>,
+
+    /// The Instructions sysvar.
+    ///
+    /// CHECK: The account is passed on during CPI and destination contract
+    /// performs the validation so this is safe even if we don’t check the
+    /// address.  Nonetheless, the account is checked at each use.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instruction: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// Account which requested withdrawal
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub withdrawer: AccountInfo<'info>,
+
+    /// Account which paid rent of the vault params account and gets it back.
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// Account which paid rent of the escrow receipt token account and gets
+    /// it back.
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub escrow_rent_payer: AccountInfo<'info>,
+
+    #[account(mut, close = rent_payer, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub vault_params: Box<Account<'info, Vault>>,
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
+    pub staking_params: Box<Account<'info, StakingParams>>,
+
+    #[account(seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
+    pub guest_chain: Box<Account<'info, ChainData>>,
+
+    pub token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, token::mint = token_mint)]
+    pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        mint::decimals = 0,
+        mint::authority = master_edition_account,
+        // mint::freeze_authority = withdrawer,
+    )]
+    pub receipt_token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, close = escrow_rent_payer, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
+    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub guest_chain_program: Program<'info, SolanaIbc>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub rent: Sysvar<'info, Rent>,
     #[account(
         mut,
         seeds = [
@@ -745,33 +1689,58 @@ pub struct Deposit<'info> {
     )]
     /// CHECK:
     pub nft_metadata: UncheckedAccount<'info>,
+
+    /// Temporary wrapped SOL account used to unwrap the stake.  Must be
+    /// provided if and only if the stake is native SOL.  The account is
+    /// closed before the instruction finishes.
+    #[account(init, payer = signer, seeds = [UNWRAP_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    pub unwrap_token_account: Option<Box<Account<'info, TokenAccount>>>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawalRequest<'info> {
+pub struct PartialWithdrawalRequest<'info> {
     #[account(mut)]
     pub withdrawer: Signer<'info>,
 
-    #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the withdrawer or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    /// Account which paid rent of the vault params account and gets it back.
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub vault_rent_payer: AccountInfo<'info>,
+
+    #[account(mut, close = vault_rent_payer, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
     pub vault_params: Box<Account<'info, Vault>>,
     #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = rewards_token_mint)]
     pub staking_params: Box<Account<'info, StakingParams>>,
 
+    #[account(init, payer = rent_payer, seeds = [PARTIAL_WITHDRAWAL_SEED, receipt_token_mint.key().as_ref()], bump, space = 8 + PartialWithdrawal::INIT_SPACE)]
+    pub partial_withdrawal: Box<Account<'info, PartialWithdrawal>>,
+
+    /// Vault holding the stake which isn’t withdrawn.
+    #[account(init, payer = rent_payer, seeds = [VAULT_PARAMS_SEED, new_receipt_token_mint.key().as_ref()], bump, space = 8 + 1024)]
+    pub new_vault_params: Box<Account<'info, Vault>>,
+
     #[account(mut, seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
     pub guest_chain: Box<Account<'info, ChainData>>,
     #[account(mut, seeds = [TRIE_SEED], bump, seeds::program = guest_chain_program.key())]
     /// CHECK:
     pub trie: AccountInfo<'info>,
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [solana_ibc::WITNESS_SEED, trie.key().as_ref()], bump)]
+    /// CHECK:
+    pub witness: AccountInfo<'info>,
 
     pub token_mint: Box<Account<'info, Mint>>,
-    #[account(mut, token::mint = token_mint, token::authority = withdrawer.key())]
+    #[account(token::mint = token_mint, token::authority = withdrawer.key())]
     pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
 
-    #[account(mut, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
-    pub vault_token_account: Box<Account<'info, TokenAccount>>,
-
     pub rewards_token_mint: Box<Account<'info, Mint>>,
-    #[account(init_if_needed, payer = withdrawer, associated_token::mint = rewards_token_mint, associated_token::authority = withdrawer)]
+    #[account(init_if_needed, payer = rent_payer, associated_token::mint = rewards_token_mint, associated_token::authority = withdrawer)]
     pub depositor_rewards_token_account: Box<Account<'info, TokenAccount>>,
 
     #[account(mut, seeds = [REWARDS_SEED, TEST_SEED], bump, token::mint = rewards_token_mint, token::authority = staking_params)]
@@ -781,22 +1750,29 @@ pub struct WithdrawalRequest<'info> {
         mut,
         mint::decimals = 0,
         mint::authority = master_edition_account,
-        // mint::freeze_authority = withdrawer,
     )]
     pub receipt_token_mint: Box<Account<'info, Mint>>,
     #[account(mut, token::mint = receipt_token_mint, token::authority = withdrawer)]
     pub receipt_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// Account which stores the receipt token until unbonding period ends.
-    #[account(init, payer = withdrawer, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
-    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = rent_payer,
+        mint::decimals = 0,
+        mint::authority = withdrawer,
+        mint::freeze_authority = withdrawer,
+    )]
+    pub new_receipt_token_mint: Box<Account<'info, Mint>>,
+    #[account(init, payer = rent_payer, associated_token::mint = new_receipt_token_mint, associated_token::authority = withdrawer)]
+    pub new_receipt_token_account: Box<Account<'info, TokenAccount>>,
 
     pub guest_chain_program: Program<'info, SolanaIbc>,
+    pub metadata_program: Program<'info, Metadata>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub metadata_program: Program<'info, Metadata>,
     pub rent: Sysvar<'info, Rent>,
+
     #[account(
         mut,
         seeds = [
@@ -810,54 +1786,56 @@ pub struct WithdrawalRequest<'info> {
     )]
     /// CHECK:
     pub master_edition_account: UncheckedAccount<'info>,
-}
-
-#[derive(Accounts)]
-pub struct CancelWithdrawalRequest<'info> {
-    #[account(mut)]
-    pub withdrawer: Signer<'info>,
-
-    #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
-    pub vault_params: Box<Account<'info, Vault>>,
-    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
-    pub staking_params: Box<Account<'info, StakingParams>>,
-
     #[account(
         mut,
-        mint::decimals = 0,
-        mint::authority = master_edition_account,
-        // mint::freeze_authority = withdrawer,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            receipt_token_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
     )]
-    pub receipt_token_mint: Box<Account<'info, Mint>>,
-    #[account(init, payer = withdrawer, associated_token::mint = receipt_token_mint, associated_token::authority = withdrawer)]
-    pub receipt_token_account: Box<Account<'info, TokenAccount>>,
-
-    /// Account which stores the receipt token until unbonding period ends.
-    #[account(mut, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
-    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK:
+    pub nft_metadata: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    pub metadata_program: Program<'info, Metadata>,
-    pub rent: Sysvar<'info, Rent>,
     #[account(
         mut,
         seeds = [
             b"metadata".as_ref(),
             metadata_program.key().as_ref(),
-            receipt_token_mint.key().as_ref(),
+            new_receipt_token_mint.key().as_ref(),
             b"edition".as_ref(),
         ],
         bump,
         seeds::program = metadata_program.key()
     )]
     /// CHECK:
-    pub master_edition_account: UncheckedAccount<'info>,
+    pub new_master_edition_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata".as_ref(),
+            metadata_program.key().as_ref(),
+            new_receipt_token_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    /// CHECK:
+    pub new_nft_metadata: UncheckedAccount<'info>,
+
+    /// The Instructions sysvar.
+    ///
+    /// CHECK: The account is passed on during CPI and destination contract
+    /// performs the validation so this is safe even if we don’t check the
+    /// address.  Nonetheless, the account is checked at each use.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instruction: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct WithdrawPartial<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
@@ -867,11 +1845,57 @@ pub struct Withdraw<'info> {
     #[account(mut)]
     pub withdrawer: AccountInfo<'info>,
 
-    #[account(mut, close = withdrawer, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
-    pub vault_params: Box<Account<'info, Vault>>,
+    /// Account which paid rent of the partial withdrawal account and gets it
+    /// back.
+    ///
+    /// CHECK: Validation is done in the method
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+
+    #[account(mut, close = rent_payer, seeds = [PARTIAL_WITHDRAWAL_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub partial_withdrawal: Box<Account<'info, PartialWithdrawal>>,
     #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
     pub staking_params: Box<Account<'info, StakingParams>>,
 
+    #[account(seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
+    pub guest_chain: Box<Account<'info, ChainData>>,
+
+    pub token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, token::mint = token_mint)]
+    pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Only used as a seed of the partial withdrawal account.
+    pub receipt_token_mint: UncheckedAccount<'info>,
+
+    pub guest_chain_program: Program<'info, SolanaIbc>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// Temporary wrapped SOL account used to unwrap the stake.  Must be
+    /// provided if and only if the stake is native SOL.  The account is
+    /// closed before the instruction finishes.
+    #[account(init, payer = signer, seeds = [UNWRAP_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
+    pub unwrap_token_account: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+#[derive(Accounts)]
+pub struct Redelegate<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the depositor or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub vault_params: Box<Account<'info, Vault>>,
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = rewards_token_mint)]
+    pub staking_params: Box<Account<'info, StakingParams>>,
+
     #[account(mut, seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
     pub guest_chain: Box<Account<'info, ChainData>>,
     #[account(mut, seeds = [TRIE_SEED], bump, seeds::program = guest_chain_program.key())]
@@ -882,53 +1906,22 @@ pub struct Withdraw<'info> {
     /// CHECK:
     pub witness: AccountInfo<'info>,
 
-    pub token_mint: Box<Account<'info, Mint>>,
-    #[account(mut, token::mint = token_mint)]
-    pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
+    pub rewards_token_mint: Box<Account<'info, Mint>>,
+    #[account(init_if_needed, payer = rent_payer, associated_token::mint = rewards_token_mint, associated_token::authority = depositor)]
+    pub depositor_rewards_token_account: Box<Account<'info, TokenAccount>>,
 
-    #[account(mut, seeds = [VAULT_SEED, token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_params)]
-    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, seeds = [REWARDS_SEED, TEST_SEED], bump, token::mint = rewards_token_mint, token::authority = staking_params)]
+    pub platform_rewards_token_account: Box<Account<'info, TokenAccount>>,
 
-    #[account(
-        mut,
-        mint::decimals = 0,
-        mint::authority = master_edition_account,
-        // mint::freeze_authority = withdrawer,
-    )]
+    #[account(mint::decimals = 0)]
     pub receipt_token_mint: Box<Account<'info, Mint>>,
-    #[account(mut, close = withdrawer, seeds = [ESCROW_RECEIPT_SEED, receipt_token_mint.key().as_ref()], bump, token::mint = receipt_token_mint, token::authority = staking_params)]
-    pub escrow_receipt_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(token::mint = receipt_token_mint, token::authority = depositor)]
+    pub receipt_token_account: Box<Account<'info, TokenAccount>>,
 
     pub guest_chain_program: Program<'info, SolanaIbc>,
     pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub metadata_program: Program<'info, Metadata>,
-    pub rent: Sysvar<'info, Rent>,
-    #[account(
-        mut,
-        seeds = [
-            b"metadata".as_ref(),
-            metadata_program.key().as_ref(),
-            receipt_token_mint.key().as_ref(),
-            b"edition".as_ref(),
-        ],
-        bump,
-        seeds::program = metadata_program.key()
-    )]
-    /// CHECK:
-    pub master_edition_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        seeds = [
-            b"metadata".as_ref(),
-            metadata_program.key().as_ref(),
-            receipt_token_mint.key().as_ref(),
-        ],
-        bump,
-        seeds::program = metadata_program.key()
-    )]
-    /// CHECK:
-    pub nft_metadata: UncheckedAccount<'info>,
 
     /// The Instructions sysvar.
     ///
@@ -948,6 +1941,50 @@ pub struct UpdateStakingParams<'info> {
     pub staking_params: Account<'info, StakingParams>,
 }
 
+#[derive(Accounts)]
+pub struct QueueAdminAction<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = admin)]
+    pub staking_params: Account<'info, StakingParams>,
+
+    #[account(init, payer = admin, seeds = [ADMIN_ACTION_SEED, staking_params.next_admin_action_id.to_le_bytes().as_ref()], bump, space = 8 + QueuedAdminAction::INIT_SPACE)]
+    pub queued_action: Account<'info, QueuedAdminAction>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAdminAction<'info> {
+    pub signer: Signer<'info>,
+
+    /// Admin who queued the action.
+    ///
+    /// CHECK: Only receives rent of the closed account.  Address is checked
+    /// against the queued action.
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
+    pub staking_params: Account<'info, StakingParams>,
+
+    #[account(mut, close = payer, has_one = payer)]
+    pub queued_action: Account<'info, QueuedAdminAction>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminAction<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = admin)]
+    pub staking_params: Account<'info, StakingParams>,
+
+    #[account(mut, close = admin)]
+    pub queued_action: Account<'info, QueuedAdminAction>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateAdmin<'info> {
     #[account(mut)]
@@ -963,6 +2000,11 @@ pub struct Claim<'info> {
     #[account(mut)]
     pub claimer: Signer<'info>,
 
+    /// Account which pays rent of the accounts created by the instruction.
+    /// May be the same as the claimer or a protocol sponsoring account creation.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
     #[account(mut, seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
     pub vault_params: Box<Account<'info, Vault>>,
     #[account(mut, seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump, has_one = rewards_token_mint)]
@@ -972,7 +2014,7 @@ pub struct Claim<'info> {
     pub guest_chain: Box<Account<'info, ChainData>>,
 
     pub rewards_token_mint: Box<Account<'info, Mint>>,
-    #[account(init_if_needed, payer = claimer, associated_token::mint = rewards_token_mint, associated_token::authority = claimer)]
+    #[account(init_if_needed, payer = rent_payer, associated_token::mint = rewards_token_mint, associated_token::authority = claimer)]
     pub depositor_rewards_token_account: Box<Account<'info, TokenAccount>>,
 
     #[account(mut, seeds = [REWARDS_SEED, TEST_SEED], bump, token::mint = rewards_token_mint, token::authority = staking_params)]
@@ -1035,6 +2077,22 @@ pub struct WithdrawRewardFunds<'info> {
     token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct GetPosition<'info> {
+    #[account(seeds = [VAULT_PARAMS_SEED, receipt_token_mint.key().as_ref()], bump)]
+    pub vault_params: Box<Account<'info, Vault>>,
+    #[account(seeds = [STAKING_PARAMS_SEED, TEST_SEED], bump)]
+    pub staking_params: Box<Account<'info, StakingParams>>,
+
+    #[account(seeds = [CHAIN_SEED], bump, seeds::program = guest_chain_program.key())]
+    pub guest_chain: Box<Account<'info, ChainData>>,
+
+    #[account(mint::decimals = 0)]
+    pub receipt_token_mint: Box<Account<'info, Mint>>,
+
+    pub guest_chain_program: Program<'info, SolanaIbc>,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct StakingParams {
@@ -1048,6 +2106,118 @@ pub struct StakingParams {
     pub staking_cap: u128,
     pub total_deposited_amount: u128,
     pub new_admin_proposal: Option<Pubkey>,
+    /// Delay with which admin actions take effect.  Zero means timelock is
+    /// disabled and admin actions are applied immediately.
+    pub timelock_delay_sec: u64,
+    /// Id of the next action queued with `queue_admin_action`.
+    pub next_admin_action_id: u64,
+    /// Total rent, in lamports, of vault params, escrow receipt token and
+    /// partial withdrawal accounts paid by a sponsoring rent payer rather
+    /// than the staker and not yet returned by closing the accounts.
+    pub sponsored_rent: u64,
+}
+
+impl StakingParams {
+    /// Applies admin action immediately.  Fails if timelock is enabled.
+    fn apply_now(&mut self, action: AdminAction) -> Result<()> {
+        if self.timelock_delay_sec != 0 {
+            return Err(error!(ErrorCodes::TimelockEnabled));
+        }
+        self.apply(action)
+    }
+
+    /// Applies admin action.
+    fn apply(&mut self, action: AdminAction) -> Result<()> {
+        match action {
+            AdminAction::UpdateTokenWhitelist { new_token_mints } => {
+                let contains_mint = new_token_mints.iter().any(|token_mint| {
+                    self.whitelisted_tokens.contains(token_mint)
+                });
+                if contains_mint {
+                    return Err(error!(ErrorCodes::TokenAlreadyWhitelisted));
+                }
+                let len = self.whitelisted_tokens.len() + new_token_mints.len();
+                if len > MAX_WHITELISTED_TOKENS {
+                    return Err(error!(ErrorCodes::TooManyWhitelistedTokens));
+                }
+                self.whitelisted_tokens.extend_from_slice(&new_token_mints);
+            }
+            AdminAction::UpdateGuestChainInitialization {
+                guest_chain_program_id,
+            } => {
+                if self.guest_chain_program_id.is_some() {
+                    return Err(error!(
+                        ErrorCodes::GuestChainAlreadyInitialized
+                    ));
+                }
+                self.guest_chain_program_id = Some(guest_chain_program_id);
+            }
+            AdminAction::UpdateStakingCap { new_staking_cap } => {
+                if self.staking_cap >= new_staking_cap {
+                    return Err(error!(
+                        ErrorCodes::NewStakingCapShouldBeMoreThanExistingOne
+                    ));
+                }
+                self.staking_cap = new_staking_cap;
+            }
+            AdminAction::SetTimelockDelay { delay_sec } => {
+                msg!(
+                    "Changing timelock delay from {} to {}",
+                    self.timelock_delay_sec,
+                    delay_sec
+                );
+                self.timelock_delay_sec = delay_sec;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Admin action which may be subject to a timelock.
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, InitSpace)]
+pub enum AdminAction {
+    UpdateTokenWhitelist {
+        #[max_len(20)]
+        new_token_mints: Vec<Pubkey>,
+    },
+    UpdateGuestChainInitialization {
+        guest_chain_program_id: Pubkey,
+    },
+    UpdateStakingCap {
+        new_staking_cap: u128,
+    },
+    SetTimelockDelay {
+        delay_sec: u64,
+    },
+}
+
+/// Admin action queued with `queue_admin_action`.
+#[account]
+#[derive(InitSpace)]
+pub struct QueuedAdminAction {
+    pub id: u64,
+    /// Admin who queued the action and paid for the account.
+    pub payer: Pubkey,
+    /// Timestamp after which the action can be executed.
+    pub eta_sec: u64,
+    pub action: AdminAction,
+}
+
+#[event]
+pub struct AdminActionQueued {
+    pub id: u64,
+    pub action: AdminAction,
+    pub eta_sec: u64,
+}
+
+#[event]
+pub struct AdminActionExecuted {
+    pub id: u64,
+}
+
+#[event]
+pub struct AdminActionCancelled {
+    pub id: u64,
 }
 
 /// Unused for now
@@ -1056,7 +2226,7 @@ pub enum Service {
     GuestChain { validator: Pubkey },
 }
 
-#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, Copy)]
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, Copy, InitSpace)]
 pub struct WithdrawalRequestParams {
     /// Timestamp when withdrawal was requested
     timestamp_in_sec: u64,
@@ -1066,6 +2236,24 @@ pub struct WithdrawalRequestParams {
     token_account: Pubkey,
 }
 
+/// Summary of a position returned by `get_position` method.
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug)]
+pub struct Position {
+    pub stake_mint: Pubkey,
+    pub stake_amount: u64,
+    pub stake_timestamp_sec: i64,
+    pub service: Option<Service>,
+    pub last_received_rewards_height: u64,
+    /// Rewards which would be transferred if `claim_rewards` was called now.
+    pub pending_rewards: u64,
+    pub withdrawal_request: Option<WithdrawalRequestParams>,
+    /// Timestamp at which unbonding period ends if withdrawal was requested.
+    pub unbonding_ends_at_sec: Option<u64>,
+    /// Whether unbonding period has ended, removal of the stake has taken
+    /// effect on the guest chain and `withdraw` can be called.
+    pub can_withdraw: bool,
+}
+
 #[account]
 pub struct Vault {
     pub stake_timestamp_sec: i64,
@@ -1077,6 +2265,40 @@ pub struct Vault {
     /// is 0 initially
     pub last_received_rewards_height: u64,
     pub withdrawal_request: Option<WithdrawalRequestParams>,
+    /// Timestamp of the last `redelegate` call; 0 if the vault has never been
+    /// redelegated.
+    pub last_redelegation_sec: u64,
+    /// Account which paid rent of the vault params account and gets it back
+    /// when the account is closed.  All zeros for vaults created before rent
+    /// sponsoring was introduced in which case the rent goes to the withdrawer.
+    pub rent_payer: Pubkey,
+    /// Account which paid rent of the escrow receipt token account created
+    /// by `withdrawal_request`.  All zeros if there’s no pending request.
+    pub escrow_rent_payer: Pubkey,
+    /// Guest epoch from which the stake removed by `withdrawal_request` no
+    /// longer counts towards validator’s voting power.  `None` if there’s no
+    /// pending request or it was made before the stake was removed at
+    /// request time; such requests have to be cancelled and made again.
+    pub unbonding_epoch: Option<u64>,
+}
+
+/// Pending withdrawal of a part of vault’s stake.
+///
+/// Created by `request_partial_withdrawal` and closed by `withdraw_partial`.
+#[account]
+#[derive(InitSpace)]
+pub struct PartialWithdrawal {
+    /// Validator the withdrawn stake was delegated to.
+    pub validator: Pubkey,
+    /// Amount of stake tokens to withdraw.
+    pub amount: u64,
+    pub request: WithdrawalRequestParams,
+    /// Account which paid rent of this account and gets it back once it’s
+    /// closed.
+    pub rent_payer: Pubkey,
+    /// Guest epoch from which the withdrawn stake no longer counts towards
+    /// validator’s voting power.
+    pub unbonding_epoch: u64,
 }
 
 #[error_code]
@@ -1129,4 +2351,30 @@ pub enum ErrorCodes {
          withdrawal"
     )]
     InvalidWithdrawer,
+    #[msg("Withdrawal of the entire stake has already been requested")]
+    WithdrawalAlreadyRequested,
+    #[msg(
+        "Partial withdrawal amount must be non-zero and less than the stake"
+    )]
+    InvalidWithdrawalAmount,
+    #[msg("Timelock is enabled. The action has to be queued")]
+    TimelockEnabled,
+    #[msg("Timelock is disabled. The action can be executed directly")]
+    TimelockDisabled,
+    #[msg("Timelock delay of the queued action has not passed yet")]
+    AdminActionNotReady,
+    #[msg(
+        "Unwrap account must be provided if and only if stake is native SOL"
+    )]
+    InvalidUnwrapAccount,
+    #[msg("Stake is already delegated to this validator")]
+    SameValidator,
+    #[msg("Vault has been redelegated recently. Try again after cooldown")]
+    RedelegationCooldown,
+    #[msg("Rent payer doesn’t match the account which paid rent")]
+    InvalidRentPayer,
+    #[msg("Removal of the stake hasn’t taken effect on the guest chain yet")]
+    StakeStillBonded,
+    #[msg("Too many whitelisted tokens. At most 20 tokens can be whitelisted")]
+    TooManyWhitelistedTokens,
 }
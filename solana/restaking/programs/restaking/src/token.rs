@@ -4,12 +4,15 @@ use anchor_spl::metadata::{
     create_master_edition_v3, create_metadata_accounts_v3,
     CreateMasterEditionV3, CreateMetadataAccountsV3,
 };
-use anchor_spl::token::{mint_to, MintTo, Transfer};
+use anchor_spl::token::{
+    close_account, mint_to, sync_native, CloseAccount, MintTo, SyncNative,
+    Transfer,
+};
 
 use crate::constants::{TOKEN_NAME, TOKEN_SYMBOL, TOKEN_URI};
 use crate::{
-    CancelWithdrawalRequest, Claim, Deposit, Withdraw, WithdrawRewardFunds,
-    WithdrawalRequest,
+    CancelWithdrawalRequest, Claim, Deposit, PartialWithdrawalRequest,
+    Withdraw, WithdrawRewardFunds, WithdrawalRequest,
 };
 
 /// Performs token transfer based on the given accounts and amount
@@ -43,6 +46,69 @@ pub fn transfer(
     Ok(())
 }
 
+/// Transfers wrapped SOL paying it out as lamports.
+///
+/// `accounts.to` must be a temporary wrapped SOL account whose authority is
+/// `accounts.authority` and whose rent has been paid by `unwrap.payer`.
+/// After the tokens are transferred to it, the account is closed and all of
+/// its lamports (i.e. the tokens and rent) are sent to the payer which then
+/// pays `amount` lamports out to `unwrap.destination`.  This way the rent
+/// goes back to whoever paid it.  Since the payer is a signer, we dont need
+/// signed invocation for the last transfer.
+pub fn transfer_unwrapped<'a>(
+    accounts: TransferAccounts<'a>,
+    unwrap: UnwrapAccounts<'a>,
+    seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    let token_program = accounts.token_program.clone();
+    let close_instruction = CloseAccount {
+        account: accounts.to.clone(),
+        destination: unwrap.payer.clone(),
+        authority: accounts.authority.clone(),
+    };
+    transfer(accounts, seeds, amount)?;
+    close_account(CpiContext::new_with_signer(
+        token_program,
+        close_instruction,
+        seeds,
+    ))?;
+    if unwrap.payer.key == unwrap.destination.key {
+        return Ok(());
+    }
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            unwrap.system_program,
+            anchor_lang::system_program::Transfer {
+                from: unwrap.payer,
+                to: unwrap.destination,
+            },
+        ),
+        amount,
+    )
+}
+
+/// Wraps lamports into SOL tokens.
+///
+/// Transfers `amount` lamports from the depositor to the vault, which must be
+/// a wrapped SOL token account, and synchronises vault’s token balance.  Since
+/// the depositor is a signer, we dont need signed invocation.
+pub fn wrap_sol(accounts: WrapSolAccounts<'_>, amount: u64) -> Result<()> {
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            accounts.system_program,
+            anchor_lang::system_program::Transfer {
+                from: accounts.from,
+                to: accounts.to.clone(),
+            },
+        ),
+        amount,
+    )?;
+    sync_native(CpiContext::new(accounts.token_program, SyncNative {
+        account: accounts.to,
+    }))
+}
+
 /// Mints NFT using the metaplex standard
 ///
 /// Since the NFT is minted by the depositor who is a signer,
@@ -110,6 +176,19 @@ pub struct TransferAccounts<'a> {
     pub token_program: AccountInfo<'a>,
 }
 
+pub struct UnwrapAccounts<'a> {
+    pub payer: AccountInfo<'a>,
+    pub destination: AccountInfo<'a>,
+    pub system_program: AccountInfo<'a>,
+}
+
+pub struct WrapSolAccounts<'a> {
+    pub from: AccountInfo<'a>,
+    pub to: AccountInfo<'a>,
+    pub system_program: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+}
+
 pub struct MintNftAccounts<'a> {
     token_mint: AccountInfo<'a>,
     payer: AccountInfo<'a>,
@@ -124,12 +203,12 @@ pub struct MintNftAccounts<'a> {
     edition: AccountInfo<'a>,
 }
 
-impl<'a> From<&mut Deposit<'a>> for TransferAccounts<'a> {
+impl<'a> From<&mut Deposit<'a>> for WrapSolAccounts<'a> {
     fn from(accounts: &mut Deposit<'a>) -> Self {
         Self {
-            from: accounts.depositor_token_account.to_account_info(),
+            from: accounts.depositor.to_account_info(),
             to: accounts.vault_token_account.to_account_info(),
-            authority: accounts.depositor.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
             token_program: accounts.token_program.to_account_info(),
         }
     }
@@ -186,6 +265,24 @@ impl<'a> From<&mut Deposit<'a>> for MintNftAccounts<'a> {
     }
 }
 
+impl<'a> From<&mut PartialWithdrawalRequest<'a>> for MintNftAccounts<'a> {
+    fn from(accounts: &mut PartialWithdrawalRequest<'a>) -> Self {
+        Self {
+            token_mint: accounts.new_receipt_token_mint.to_account_info(),
+            payer: accounts.withdrawer.to_account_info(),
+            mint_authority: accounts.withdrawer.to_account_info(),
+            to: accounts.new_receipt_token_account.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+            metadata_program: accounts.metadata_program.to_account_info(),
+            update_authority: accounts.withdrawer.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            rent: accounts.rent.to_account_info(),
+            metadata: accounts.new_nft_metadata.to_account_info(),
+            edition: accounts.new_master_edition_account.to_account_info(),
+        }
+    }
+}
+
 impl<'a> From<&mut WithdrawalRequest<'a>> for TransferAccounts<'a> {
     fn from(accounts: &mut WithdrawalRequest<'a>) -> Self {
         Self {
@@ -87,6 +87,33 @@ export const getEscrowReceiptTokenPDA = (token_mint: anchor.web3.PublicKey) => {
   return { escrowReceiptTokenPDA, escrowReceiptTokenBump };
 }
 
+export const getPartialWithdrawalPDA = (token_mint: anchor.web3.PublicKey) => {
+  const [partialWithdrawalPDA, partialWithdrawalBump] =
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("partial_withdrawal"), token_mint.toBuffer()],
+      restakingProgramID
+    );
+  return { partialWithdrawalPDA, partialWithdrawalBump };
+};
+
+export const getQueuedAdminActionPDA = (id: anchor.BN) => {
+  const [queuedAdminActionPDA, queuedAdminActionBump] =
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("admin_action"), id.toArrayLike(Buffer, "le", 8)],
+      restakingProgramID
+    );
+  return { queuedAdminActionPDA, queuedAdminActionBump };
+};
+
+export const getUnwrapTokenAccountPDA = (token_mint: anchor.web3.PublicKey) => {
+  const [unwrapTokenAccountPDA, unwrapTokenAccountBump] =
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("unwrap"), token_mint.toBuffer()],
+      restakingProgramID
+    );
+  return { unwrapTokenAccountPDA, unwrapTokenAccountBump };
+};
+
 export const getGuestChainAccounts = () => {
   const [guestChainPDA, guestChainBump] =
     anchor.web3.PublicKey.findProgramAddressSync(
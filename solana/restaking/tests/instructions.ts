@@ -7,9 +7,11 @@ import {
   getGuestChainAccounts,
   getMasterEditionPDA,
   getNftMetadataPDA,
+  getPartialWithdrawalPDA,
   getReceiptTokenMintPDA,
   getRewardsTokenAccountPDA,
   getStakingParamsPDA,
+  getUnwrapTokenAccountPDA,
   getVaultParamsPDA,
   getVaultTokenAccountPDA,
   guestChainProgramID,
@@ -17,12 +19,20 @@ import {
 } from "./helper";
 import { Transaction, TransactionInstruction } from "@solana/web3.js";
 
+// Returns the account to which rent paid by `rentPayer` is returned.  All-zero
+// key means the rent was paid by the owner.
+const rentRecipient = (
+  rentPayer: anchor.web3.PublicKey,
+  owner: anchor.web3.PublicKey
+) => (rentPayer.equals(anchor.web3.PublicKey.default) ? owner : rentPayer);
+
 export const depositInstruction = async (
   program: anchor.Program<Restaking>,
   stakeTokenMint: anchor.web3.PublicKey,
   staker: anchor.web3.PublicKey,
   stakeAmount: number,
-  receiptTokenKeypair?: anchor.web3.Keypair | undefined
+  receiptTokenKeypair?: anchor.web3.Keypair | undefined,
+  rentPayer?: anchor.web3.PublicKey | undefined
 ) => {
   if (!receiptTokenKeypair) {
     receiptTokenKeypair = anchor.web3.Keypair.generate();
@@ -58,6 +68,7 @@ export const depositInstruction = async (
     ])
     .accounts({
       depositor: staker, // staker
+      rentPayer: rentPayer ?? staker,
       vaultParams: vaultParamsPDA,
       stakingParams: stakingParamsPDA,
       tokenMint: stakeTokenMint, // token which they are staking
@@ -85,6 +96,71 @@ export const depositInstruction = async (
   return ix;
 };
 
+/// Stakes `stakeAmount` lamports of native SOL.
+export const depositSolInstruction = async (
+  program: anchor.Program<Restaking>,
+  staker: anchor.web3.PublicKey,
+  stakeAmount: number,
+  receiptTokenKeypair?: anchor.web3.Keypair | undefined
+) => {
+  if (!receiptTokenKeypair) {
+    receiptTokenKeypair = anchor.web3.Keypair.generate();
+  }
+  const receiptTokenPublicKey = receiptTokenKeypair.publicKey;
+  const stakeTokenMint = spl.NATIVE_MINT;
+
+  const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenPublicKey);
+  const { stakingParamsPDA } = getStakingParamsPDA();
+  const { guestChainPDA, triePDA } = getGuestChainAccounts();
+  const { vaultTokenAccountPDA } = getVaultTokenAccountPDA(stakeTokenMint);
+  const { masterEditionPDA } = getMasterEditionPDA(receiptTokenPublicKey);
+  const { nftMetadataPDA } = getNftMetadataPDA(receiptTokenPublicKey);
+
+  const receiptTokenAccount = await spl.getAssociatedTokenAddress(
+    receiptTokenPublicKey,
+    staker
+  );
+
+  const tx = await program.methods
+    .depositSol(
+      { guestChain: { validator: staker } },
+      new anchor.BN(stakeAmount)
+    )
+    .preInstructions([
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+        units: 1000000,
+      }),
+    ])
+    .accounts({
+      depositor: staker,
+      rentPayer: staker,
+      vaultParams: vaultParamsPDA,
+      stakingParams: stakingParamsPDA,
+      tokenMint: stakeTokenMint,
+      depositorTokenAccount: null,
+      vaultTokenAccount: vaultTokenAccountPDA,
+      receiptTokenMint: receiptTokenPublicKey,
+      receiptTokenAccount,
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      masterEditionAccount: masterEditionPDA,
+      nftMetadata: nftMetadataPDA,
+      instruction: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      metadataProgram: new anchor.web3.PublicKey(
+        mpl.MPL_TOKEN_METADATA_PROGRAM_ID
+      ),
+    })
+    .remainingAccounts([
+      { pubkey: guestChainPDA, isSigner: false, isWritable: true },
+      { pubkey: triePDA, isSigner: false, isWritable: true },
+      { pubkey: guestChainProgramID, isSigner: false, isWritable: true },
+    ])
+    .transaction();
+
+  return tx;
+};
+
 export const claimRewardsInstruction = async (
   program: anchor.Program<Restaking>,
   claimer: anchor.web3.PublicKey,
@@ -120,6 +196,7 @@ export const claimRewardsInstruction = async (
     ])
     .accounts({
       claimer: claimer,
+      rentPayer: claimer,
       vaultParams: vaultParamsPDA,
       stakingParams: stakingParamsPDA,
       guestChain: guestChainPDA,
@@ -138,17 +215,28 @@ export const claimRewardsInstruction = async (
   return tx;
 };
 
+/// Withdraws the stake once the unbonding period is over.
+///
+/// `signer` defaults to the `withdrawer`.  If the stake is native SOL, it pays
+/// rent of the temporary unwrap account which is returned to it once the
+/// stake is paid out in lamports.
 export const withdrawInstruction = async (
   program: anchor.Program<Restaking>,
   withdrawer: anchor.web3.PublicKey,
-  receiptTokenMint: anchor.web3.PublicKey
+  receiptTokenMint: anchor.web3.PublicKey,
+  signer?: anchor.web3.PublicKey | undefined
 ) => {
   const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenMint);
   const { stakingParamsPDA } = getStakingParamsPDA();
-  const { guestChainPDA, triePDA } = getGuestChainAccounts();
+  const { guestChainPDA } = getGuestChainAccounts();
 
   const vaultParams = await program.account.vault.fetch(vaultParamsPDA);
   const stakedTokenMint = vaultParams.stakeMint;
+  const rentPayer = rentRecipient(vaultParams.rentPayer, withdrawer);
+  const escrowRentPayer = rentRecipient(
+    vaultParams.escrowRentPayer,
+    withdrawer
+  );
 
   const { vaultTokenAccountPDA } = getVaultTokenAccountPDA(stakedTokenMint);
   const { masterEditionPDA } = getMasterEditionPDA(receiptTokenMint);
@@ -160,14 +248,29 @@ export const withdrawInstruction = async (
     withdrawer
   );
 
+  // Optional unwrap account is the last one and is omitted unless the stake is
+  // native SOL.
+  const unwrapKeys = [];
+  if (stakedTokenMint.equals(spl.NATIVE_MINT)) {
+    const { unwrapTokenAccountPDA } = getUnwrapTokenAccountPDA(
+      receiptTokenMint
+    );
+    unwrapKeys.push({
+      pubkey: unwrapTokenAccountPDA,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
   let instruction = new TransactionInstruction({
     keys: [
-      { pubkey: withdrawer, isSigner: true, isWritable: true },
+      { pubkey: signer ?? withdrawer, isSigner: true, isWritable: true },
       { pubkey: withdrawer, isSigner: false, isWritable: true },
+      { pubkey: rentPayer, isSigner: false, isWritable: true },
+      { pubkey: escrowRentPayer, isSigner: false, isWritable: true },
       { pubkey: vaultParamsPDA, isSigner: false, isWritable: true },
       { pubkey: stakingParamsPDA, isSigner: false, isWritable: true },
-      { pubkey: guestChainPDA, isSigner: false, isWritable: true },
-      { pubkey: triePDA, isSigner: false, isWritable: true },
+      { pubkey: guestChainPDA, isSigner: false, isWritable: false },
       { pubkey: stakedTokenMint, isSigner: false, isWritable: true },
       {
         pubkey: withdrawerStakedTokenAccount,
@@ -192,11 +295,7 @@ export const withdrawInstruction = async (
       { pubkey: anchor.web3.SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: true },
       { pubkey: masterEditionPDA, isSigner: false, isWritable: true },
       { pubkey: nftMetadataPDA, isSigner: false, isWritable: true },
-      {
-        pubkey: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
-        isSigner: false,
-        isWritable: true,
-      },
+      ...unwrapKeys,
     ],
     programId: restakingProgramID,
     data: Buffer.from([183, 18, 70, 156, 148, 109, 161, 34]),
@@ -261,6 +360,7 @@ export const withdrawalRequestInstruction = async (
     ])
     .accounts({
       withdrawer,
+      rentPayer: withdrawer,
       vaultParams: vaultParamsPDA,
       stakingParams: stakingParamsPDA,
       guestChain: guestChainPDA,
@@ -281,6 +381,151 @@ export const withdrawalRequestInstruction = async (
       metadataProgram: new anchor.web3.PublicKey(
         mpl.MPL_TOKEN_METADATA_PROGRAM_ID
       ),
+      instruction: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+    })
+    .transaction();
+
+  return tx;
+};
+
+/// Requests withdrawal of `amount` tokens out of the stake.
+///
+/// The remaining stake is moved to a new vault whose receipt NFT is minted
+/// with `newReceiptTokenKeypair`.
+export const partialWithdrawalRequestInstruction = async (
+  program: anchor.Program<Restaking>,
+  withdrawer: anchor.web3.PublicKey,
+  receiptTokenMint: anchor.web3.PublicKey,
+  newReceiptTokenKeypair: anchor.web3.Keypair,
+  amount: number
+) => {
+  const newReceiptTokenMint = newReceiptTokenKeypair.publicKey;
+  const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenMint);
+  const { vaultParamsPDA: newVaultParamsPDA } =
+    getVaultParamsPDA(newReceiptTokenMint);
+  const { partialWithdrawalPDA } = getPartialWithdrawalPDA(receiptTokenMint);
+  const { stakingParamsPDA } = getStakingParamsPDA();
+  const { guestChainPDA, triePDA } = getGuestChainAccounts();
+  const { rewardsTokenAccountPDA } = getRewardsTokenAccountPDA();
+
+  const vaultParams = await program.account.vault.fetch(vaultParamsPDA);
+  const stakedTokenMint = vaultParams.stakeMint;
+  const vaultRentPayer = rentRecipient(vaultParams.rentPayer, withdrawer);
+
+  const stakingParams = await program.account.stakingParams.fetch(
+    stakingParamsPDA
+  );
+  const { rewardsTokenMint } = stakingParams;
+
+  const { masterEditionPDA } = getMasterEditionPDA(receiptTokenMint);
+  const { nftMetadataPDA } = getNftMetadataPDA(receiptTokenMint);
+  const { masterEditionPDA: newMasterEditionPDA } =
+    getMasterEditionPDA(newReceiptTokenMint);
+  const { nftMetadataPDA: newNftMetadataPDA } =
+    getNftMetadataPDA(newReceiptTokenMint);
+
+  const withdrawerStakedTokenAccount = await spl.getAssociatedTokenAddress(
+    stakedTokenMint,
+    withdrawer
+  );
+  const withdrawerRewardsTokenAccount = await spl.getAssociatedTokenAddress(
+    rewardsTokenMint,
+    withdrawer
+  );
+  const receiptTokenAccount = await spl.getAssociatedTokenAddress(
+    receiptTokenMint,
+    withdrawer
+  );
+  const newReceiptTokenAccount = await spl.getAssociatedTokenAddress(
+    newReceiptTokenMint,
+    withdrawer
+  );
+
+  const tx = await program.methods
+    .requestPartialWithdrawal(new anchor.BN(amount))
+    .preInstructions([
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+        units: 1000000,
+      }),
+    ])
+    .accounts({
+      withdrawer,
+      rentPayer: withdrawer,
+      vaultRentPayer,
+      vaultParams: vaultParamsPDA,
+      stakingParams: stakingParamsPDA,
+      partialWithdrawal: partialWithdrawalPDA,
+      newVaultParams: newVaultParamsPDA,
+      guestChain: guestChainPDA,
+      trie: triePDA,
+      tokenMint: stakedTokenMint,
+      withdrawerTokenAccount: withdrawerStakedTokenAccount,
+      rewardsTokenMint,
+      depositorRewardsTokenAccount: withdrawerRewardsTokenAccount,
+      platformRewardsTokenAccount: rewardsTokenAccountPDA,
+      receiptTokenMint,
+      receiptTokenAccount,
+      newReceiptTokenMint,
+      newReceiptTokenAccount,
+      guestChainProgram: guestChainProgramID,
+      metadataProgram: new anchor.web3.PublicKey(
+        mpl.MPL_TOKEN_METADATA_PROGRAM_ID
+      ),
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      masterEditionAccount: masterEditionPDA,
+      nftMetadata: nftMetadataPDA,
+      newMasterEditionAccount: newMasterEditionPDA,
+      newNftMetadata: newNftMetadataPDA,
+      instruction: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+    })
+    .transaction();
+
+  return tx;
+};
+
+/// Withdraws tokens requested with `partialWithdrawalRequestInstruction` once
+/// the unbonding period is over.
+export const withdrawPartialInstruction = async (
+  program: anchor.Program<Restaking>,
+  withdrawer: anchor.web3.PublicKey,
+  receiptTokenMint: anchor.web3.PublicKey,
+  stakedTokenMint: anchor.web3.PublicKey
+) => {
+  const { partialWithdrawalPDA } = getPartialWithdrawalPDA(receiptTokenMint);
+  const { stakingParamsPDA } = getStakingParamsPDA();
+  const { guestChainPDA } = getGuestChainAccounts();
+  const { vaultTokenAccountPDA } = getVaultTokenAccountPDA(stakedTokenMint);
+
+  const partialWithdrawal = await program.account.partialWithdrawal.fetch(
+    partialWithdrawalPDA
+  );
+  const rentPayer = rentRecipient(partialWithdrawal.rentPayer, withdrawer);
+
+  const tx = await program.methods
+    .withdrawPartial()
+    .preInstructions([
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+        units: 1000000,
+      }),
+    ])
+    .accounts({
+      signer: withdrawer,
+      withdrawer,
+      rentPayer,
+      partialWithdrawal: partialWithdrawalPDA,
+      stakingParams: stakingParamsPDA,
+      guestChain: guestChainPDA,
+      tokenMint: stakedTokenMint,
+      withdrawerTokenAccount: partialWithdrawal.request.tokenAccount,
+      vaultTokenAccount: vaultTokenAccountPDA,
+      receiptTokenMint,
+      guestChainProgram: guestChainProgramID,
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      unwrapTokenAccount: null,
     })
     .transaction();
 
@@ -294,6 +539,13 @@ export const cancelWithdrawalRequestInstruction = async (
 ) => {
   const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenMint);
   const { stakingParamsPDA } = getStakingParamsPDA();
+  const { guestChainPDA, triePDA } = getGuestChainAccounts();
+
+  const vaultParams = await program.account.vault.fetch(vaultParamsPDA);
+  const escrowRentPayer = rentRecipient(
+    vaultParams.escrowRentPayer,
+    withdrawer
+  );
 
   const { masterEditionPDA } = getMasterEditionPDA(receiptTokenMint);
   const { escrowReceiptTokenPDA } = getEscrowReceiptTokenPDA(receiptTokenMint);
@@ -312,17 +564,23 @@ export const cancelWithdrawalRequestInstruction = async (
     ])
     .accounts({
       withdrawer,
+      rentPayer: withdrawer,
+      escrowRentPayer,
       vaultParams: vaultParamsPDA,
       stakingParams: stakingParamsPDA,
+      guestChain: guestChainPDA,
+      trie: triePDA,
       receiptTokenMint,
       receiptTokenAccount,
       escrowReceiptTokenAccount: escrowReceiptTokenPDA,
+      guestChainProgram: guestChainProgramID,
       tokenProgram: spl.TOKEN_PROGRAM_ID,
       masterEditionAccount: masterEditionPDA,
       systemProgram: anchor.web3.SystemProgram.programId,
       metadataProgram: new anchor.web3.PublicKey(
         mpl.MPL_TOKEN_METADATA_PROGRAM_ID
       ),
+      instruction: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
     })
     .transaction();
 
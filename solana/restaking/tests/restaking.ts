@@ -7,18 +7,27 @@ import assert from "assert";
 import bs58 from "bs58";
 import {
   guestChainProgramID,
+  getEscrowReceiptTokenPDA,
   getGuestChainAccounts,
+  getPartialWithdrawalPDA,
+  getQueuedAdminActionPDA,
   getRewardsTokenAccountPDA,
   getStakingParameters,
   getStakingParamsPDA,
+  getUnwrapTokenAccountPDA,
+  getVaultParameters,
   getVaultParamsPDA,
+  getVaultTokenAccountPDA,
 } from "./helper";
 import { restakingProgramId } from "./constants";
 import {
   cancelWithdrawalRequestInstruction,
   claimRewardsInstruction,
   depositInstruction,
+  depositSolInstruction,
+  partialWithdrawalRequestInstruction,
   setServiceInstruction,
+  withdrawPartialInstruction,
   withdrawInstruction,
   withdrawalRequestInstruction,
 } from "./instructions";
@@ -506,6 +515,342 @@ describe("restaking", () => {
     }
   });
 
+  const partialReceiptKeypair = anchor.web3.Keypair.generate();
+  const newReceiptKeypair = anchor.web3.Keypair.generate();
+  const partialAmount = 1000;
+
+  it("Request partial withdrawal", async () => {
+    const receiptTokenMint = partialReceiptKeypair.publicKey;
+    const newReceiptTokenMint = newReceiptKeypair.publicKey;
+
+    let tx = await depositInstruction(
+      program,
+      wSolMint,
+      depositor.publicKey,
+      depositAmount,
+      partialReceiptKeypair
+    );
+
+    try {
+      tx.feePayer = depositor.publicKey;
+      let sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [depositor, partialReceiptKeypair]
+      );
+      console.log("  Signature for Depositing: ", sig);
+      const oldVaultParams = await getVaultParameters(
+        program,
+        receiptTokenMint
+      );
+
+      // Withdrawing the entire stake has to go through withdrawal request.
+      tx = await partialWithdrawalRequestInstruction(
+        program,
+        depositor.publicKey,
+        receiptTokenMint,
+        newReceiptKeypair,
+        depositAmount
+      );
+      tx.feePayer = depositor.publicKey;
+      await expectException(async () => {
+        await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [
+          depositor,
+          newReceiptKeypair,
+        ]);
+      }, "Requested partial withdrawal of the entire stake");
+
+      tx = await partialWithdrawalRequestInstruction(
+        program,
+        depositor.publicKey,
+        receiptTokenMint,
+        newReceiptKeypair,
+        partialAmount
+      );
+      tx.feePayer = depositor.publicKey;
+      sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [depositor, newReceiptKeypair]
+      );
+      console.log("  Signature for Partial withdrawal request: ", sig);
+
+      // The old vault and its receipt NFT are gone.
+      const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenMint);
+      assert.equal(
+        await provider.connection.getAccountInfo(vaultParamsPDA),
+        null
+      );
+      const receiptTokenAccount = await spl.getAssociatedTokenAddress(
+        receiptTokenMint,
+        depositor.publicKey
+      );
+      await expectException(async () => {
+        await spl.getAccount(provider.connection, receiptTokenAccount);
+      }, "Old receipt NFT token account is not closed");
+
+      // The requested amount awaits withdrawal.
+      const { partialWithdrawalPDA } =
+        getPartialWithdrawalPDA(receiptTokenMint);
+      const partialWithdrawal = await program.account.partialWithdrawal.fetch(
+        partialWithdrawalPDA
+      );
+      assert.equal(partialWithdrawal.amount.toNumber(), partialAmount);
+      assert.ok(partialWithdrawal.request.owner.equals(depositor.publicKey));
+
+      // The rest of the stake is held by the new vault.
+      const newVaultParams = await getVaultParameters(
+        program,
+        newReceiptTokenMint
+      );
+      assert.equal(
+        newVaultParams.stakeAmount.toNumber(),
+        depositAmount - partialAmount
+      );
+      assert.ok(newVaultParams.stakeMint.equals(wSolMint));
+      assert.deepEqual(newVaultParams.service, oldVaultParams.service);
+      assert.equal(newVaultParams.withdrawalRequest, null);
+
+      // The new receipt NFT is owned by the withdrawer.
+      const newReceiptTokenAccount = await spl.getAssociatedTokenAddress(
+        newReceiptTokenMint,
+        depositor.publicKey
+      );
+      const newReceiptToken = await spl.getAccount(
+        provider.connection,
+        newReceiptTokenAccount
+      );
+      assert.equal(newReceiptToken.amount, 1);
+      assert.ok(newReceiptToken.owner.equals(depositor.publicKey));
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  it("Withdraw partial", async () => {
+    const receiptTokenMint = partialReceiptKeypair.publicKey;
+
+    // sleeping for unbonding period to end
+    await sleep(2000);
+    const depositorBalanceBefore = await spl.getAccount(
+      provider.connection,
+      depositorWSolTokenAccount
+    );
+
+    const tx = await withdrawPartialInstruction(
+      program,
+      depositor.publicKey,
+      receiptTokenMint,
+      wSolMint
+    );
+
+    try {
+      tx.feePayer = depositor.publicKey;
+      const sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [depositor]
+      );
+      console.log("  Signature for Withdrawing partial: ", sig);
+
+      const depositorBalanceAfter = await spl.getAccount(
+        provider.connection,
+        depositorWSolTokenAccount
+      );
+      assert.equal(
+        depositorBalanceAfter.amount - depositorBalanceBefore.amount,
+        partialAmount
+      );
+
+      const { partialWithdrawalPDA } =
+        getPartialWithdrawalPDA(receiptTokenMint);
+      assert.equal(
+        await provider.connection.getAccountInfo(partialWithdrawalPDA),
+        null
+      );
+
+      // The new vault keeps the rest of the stake.
+      const newVaultParams = await getVaultParameters(
+        program,
+        newReceiptKeypair.publicKey
+      );
+      assert.equal(
+        newVaultParams.stakeAmount.toNumber(),
+        depositAmount - partialAmount
+      );
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  const solReceiptKeypair = anchor.web3.Keypair.generate();
+
+  it("Whitelist native SOL", async () => {
+    const { stakingParamsPDA } = getStakingParamsPDA();
+    try {
+      const tx = await program.methods
+        .updateTokenWhitelist([spl.NATIVE_MINT])
+        .accounts({
+          admin: admin.publicKey,
+          stakingParams: stakingParamsPDA,
+        })
+        .signers([admin])
+        .rpc();
+      console.log("  Signature for Whitelisting native SOL: ", tx);
+      const stakingParameters = await getStakingParameters(program);
+      assert.ok(
+        stakingParameters.whitelistedTokens.some((mint) =>
+          mint.equals(spl.NATIVE_MINT)
+        )
+      );
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  it("Deposit native SOL", async () => {
+    const receiptTokenAccount = await spl.getAssociatedTokenAddress(
+      solReceiptKeypair.publicKey,
+      depositor.publicKey
+    );
+    // Withdrawal requires a wrapped SOL account even though the stake is
+    // paid out in lamports.
+    await spl.getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      depositor,
+      spl.NATIVE_MINT,
+      depositor.publicKey
+    );
+
+    const tx = await depositSolInstruction(
+      program,
+      depositor.publicKey,
+      depositAmount,
+      solReceiptKeypair
+    );
+
+    try {
+      tx.feePayer = depositor.publicKey;
+      const sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [depositor, solReceiptKeypair]
+      );
+
+      console.log("  Signature for Depositing native SOL: ", sig);
+
+      const vaultParams = await getVaultParameters(
+        program,
+        solReceiptKeypair.publicKey
+      );
+      assert.ok(vaultParams.stakeMint.equals(spl.NATIVE_MINT));
+      assert.equal(vaultParams.stakeAmount.toNumber(), depositAmount);
+
+      const { vaultTokenAccountPDA } = getVaultTokenAccountPDA(
+        spl.NATIVE_MINT
+      );
+      const vaultTokenAccount = await spl.getAccount(
+        provider.connection,
+        vaultTokenAccountPDA
+      );
+      assert.ok(vaultTokenAccount.amount >= BigInt(depositAmount));
+
+      const depositorReceiptTokenBalanceAfter = await spl.getAccount(
+        provider.connection,
+        receiptTokenAccount
+      );
+      assert.equal(depositorReceiptTokenBalanceAfter.amount, 1);
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  it("Request withdrawal and Withdraw native SOL", async () => {
+    const receiptTokenMint = solReceiptKeypair.publicKey;
+
+    let tx = await withdrawalRequestInstruction(
+      program,
+      depositor.publicKey,
+      receiptTokenMint
+    );
+
+    try {
+      tx.feePayer = depositor.publicKey;
+      let sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [depositor]
+      );
+      console.log("  Signature for Withdrawal request: ", sig);
+
+      // sleeping for unbonding period to end
+      await sleep(2000);
+
+      // The admin sends the transaction and pays rent of the unwrap account.
+      // The rent must be returned to the admin while the stake, together with
+      // rent of the closed vault accounts, is paid out to the depositor.
+      const { vaultParamsPDA } = getVaultParamsPDA(receiptTokenMint);
+      const { escrowReceiptTokenPDA } =
+        getEscrowReceiptTokenPDA(receiptTokenMint);
+      const closedRent =
+        (await provider.connection.getBalance(vaultParamsPDA)) +
+        (await provider.connection.getBalance(escrowReceiptTokenPDA));
+      const depositorBalanceBefore = await provider.connection.getBalance(
+        depositor.publicKey
+      );
+      const adminBalanceBefore = await provider.connection.getBalance(
+        admin.publicKey
+      );
+
+      tx = await withdrawInstruction(
+        program,
+        depositor.publicKey,
+        receiptTokenMint,
+        admin.publicKey
+      );
+      tx.feePayer = admin.publicKey;
+      sig = await anchor.web3.sendAndConfirmTransaction(
+        provider.connection,
+        tx,
+        [admin]
+      );
+      console.log("  Signature for Withdrawing native SOL: ", sig);
+
+      const fee = (
+        await provider.connection.getTransaction(sig, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        })
+      )!.meta!.fee;
+      const depositorBalanceAfter = await provider.connection.getBalance(
+        depositor.publicKey
+      );
+      const adminBalanceAfter = await provider.connection.getBalance(
+        admin.publicKey
+      );
+
+      assert.equal(
+        depositorBalanceAfter - depositorBalanceBefore,
+        depositAmount + closedRent
+      );
+      assert.equal(adminBalanceBefore - adminBalanceAfter, fee);
+
+      const { unwrapTokenAccountPDA } =
+        getUnwrapTokenAccountPDA(receiptTokenMint);
+      assert.equal(
+        await provider.connection.getAccountInfo(unwrapTokenAccountPDA),
+        null
+      );
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
   it("Update admin", async () => {
     const { stakingParamsPDA } = getStakingParamsPDA();
     try {
@@ -557,4 +902,154 @@ describe("restaking", () => {
       throw error;
     }
   });
+
+  const timelockDelay = 2; // seconds
+
+  // Queues admin action returning address of the queued action account.
+  const queueAdminAction = async (action: any) => {
+    const { stakingParamsPDA } = getStakingParamsPDA();
+    const stakingParameters = await getStakingParameters(program);
+    const { queuedAdminActionPDA } = getQueuedAdminActionPDA(
+      stakingParameters.nextAdminActionId
+    );
+    const tx = await program.methods
+      .queueAdminAction(action)
+      .accounts({
+        admin: depositor.publicKey,
+        stakingParams: stakingParamsPDA,
+        queuedAction: queuedAdminActionPDA,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([depositor])
+      .rpc();
+    console.log("  Signature for Queueing admin action: ", tx);
+    return queuedAdminActionPDA;
+  };
+
+  const executeAdminAction = async (queuedAction: anchor.web3.PublicKey) => {
+    const { stakingParamsPDA } = getStakingParamsPDA();
+    return await program.methods
+      .executeAdminAction()
+      .accounts({
+        signer: admin.publicKey,
+        payer: depositor.publicKey,
+        stakingParams: stakingParamsPDA,
+        queuedAction,
+      })
+      .signers([admin])
+      .rpc();
+  };
+
+  it("Admin actions cannot be queued without timelock", async () => {
+    await expectException(async () => {
+      await queueAdminAction({
+        updateStakingCap: { newStakingCap: new anchor.BN(newStakingCap + 1) },
+      });
+    }, "Queued admin action with timelock disabled");
+  });
+
+  it("Enable timelock", async () => {
+    const { stakingParamsPDA } = getStakingParamsPDA();
+    try {
+      const tx = await program.methods
+        .setTimelockDelay(new anchor.BN(timelockDelay))
+        .accounts({
+          admin: depositor.publicKey,
+          stakingParams: stakingParamsPDA,
+        })
+        .signers([depositor])
+        .rpc();
+      console.log("  Signature for Enabling timelock: ", tx);
+      const stakingParameters = await getStakingParameters(program);
+      assert.equal(stakingParameters.timelockDelaySec.toNumber(), timelockDelay);
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+
+    // With timelock enabled, admin actions can no longer be applied directly.
+    await expectException(async () => {
+      await program.methods
+        .updateStakingCap(new anchor.BN(newStakingCap + 1))
+        .accounts({
+          admin: depositor.publicKey,
+          stakingParams: stakingParamsPDA,
+        })
+        .signers([depositor])
+        .rpc();
+    }, "Updated staking cap directly with timelock enabled");
+  });
+
+  it("Queue and execute admin action after timelock delay", async () => {
+    const stakingCap = newStakingCap * 2;
+    try {
+      const queuedAction = await queueAdminAction({
+        updateStakingCap: { newStakingCap: new anchor.BN(stakingCap) },
+      });
+
+      // The delay hasn’t passed yet.
+      await expectException(async () => {
+        await executeAdminAction(queuedAction);
+      }, "Executed admin action before timelock delay passed");
+      let stakingParameters = await getStakingParameters(program);
+      assert.equal(stakingParameters.stakingCap.toNumber(), newStakingCap);
+
+      await sleep((timelockDelay + 1) * 1000);
+      const tx = await executeAdminAction(queuedAction);
+      console.log("  Signature for Executing admin action: ", tx);
+
+      stakingParameters = await getStakingParameters(program);
+      assert.equal(stakingParameters.stakingCap.toNumber(), stakingCap);
+      assert.equal(
+        await provider.connection.getAccountInfo(queuedAction),
+        null
+      );
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  it("Cancel queued admin action", async () => {
+    const { stakingParamsPDA } = getStakingParamsPDA();
+    try {
+      const queuedAction = await queueAdminAction({
+        setTimelockDelay: { delaySec: new anchor.BN(0) },
+      });
+      const tx = await program.methods
+        .cancelAdminAction()
+        .accounts({
+          admin: depositor.publicKey,
+          stakingParams: stakingParamsPDA,
+          queuedAction,
+        })
+        .signers([depositor])
+        .rpc();
+      console.log("  Signature for Cancelling admin action: ", tx);
+
+      assert.equal(
+        await provider.connection.getAccountInfo(queuedAction),
+        null
+      );
+      await sleep((timelockDelay + 1) * 1000);
+      await expectException(async () => {
+        await executeAdminAction(queuedAction);
+      }, "Executed cancelled admin action");
+      const stakingParameters = await getStakingParameters(program);
+      assert.equal(stakingParameters.timelockDelaySec.toNumber(), timelockDelay);
+    } catch (error) {
+      console.log(error);
+      throw error;
+    }
+  });
+
+  it("Queueing whitelist update with too many tokens fails", async () => {
+    const newTokenMints = Array.from(
+      { length: 21 },
+      () => anchor.web3.Keypair.generate().publicKey
+    );
+    await expectException(async () => {
+      await queueAdminAction({ updateTokenWhitelist: { newTokenMints } });
+    }, "Queued whitelist update with too many tokens");
+  });
 });
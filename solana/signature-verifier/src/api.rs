@@ -4,6 +4,19 @@ use solana_program::pubkey::Pubkey;
 
 type Result<T = (), E = ProgramError> = core::result::Result<T, E>;
 
+/// Flag set in the header of a Signatures account holding epoch-scoped
+/// entries.
+///
+/// Each entry of such account is a [`SignatureHash`] followed by little-endian
+/// `u64` height of the guest block the signature was collected for.  This
+/// allows signatures of different pending blocks to share an account and
+/// entries of blocks which are no longer pending to be garbage collected.
+/// Remaining bits of the header hold number of entries as usual.
+const SCOPED_FLAG: u32 = 1 << 31;
+
+/// Size of an epoch-scoped entry; see [`SCOPED_FLAG`].
+pub(crate) const SCOPED_ENTRY_SIZE: usize = SignatureHash::SIZE + 8;
+
 
 /// A signature hash as stored in the [`SignaturesAccount`].
 ///
@@ -16,14 +29,22 @@ type Result<T = (), E = ProgramError> = core::result::Result<T, E>;
 /// that it’s not possible to extract signatures that are stored in the account
 /// (but of course it is possible to check if known signature is present).
 #[derive(
-    Clone, Copy, Debug, Eq, PartialEq, derive_more::From, derive_more::Into,
+    Clone,
+    Copy,
+    Debug,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    derive_more::From,
+    derive_more::Into,
 )]
 #[repr(transparent)]
 pub struct SignatureHash([u8; 32]);
 
 impl SignatureHash {
     const ED25519_HASH_MAGIC: [u8; 8] = *b"ed25519\0";
-    const SIZE: usize = core::mem::size_of::<SignatureHash>();
+    pub(crate) const SIZE: usize = core::mem::size_of::<SignatureHash>();
 
     /// Constructs a new SignatureHash for given Ed25519 signature.
     #[inline]
@@ -109,14 +130,59 @@ impl<'a, 'info> SignaturesAccount<'a, 'info> {
     }
 
     /// Reads number of signatures saved in the account.
+    ///
+    /// Returns an error if the account holds epoch-scoped entries unless there
+    /// are no entries in which case the account can be reused as unscoped.
     #[cfg(any(test, not(feature = "library")))]
     pub(crate) fn read_count(&self) -> Result<u32> {
+        match self.read_header()? {
+            header if header & SCOPED_FLAG == 0 => Ok(header),
+            SCOPED_FLAG => Ok(0),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    /// Reads number of epoch-scoped signatures saved in the account.
+    ///
+    /// Returns an error if the account holds unscoped entries unless there are
+    /// no entries in which case the account can be reused as scoped.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn read_scoped_count(&self) -> Result<u32> {
+        match self.read_header()? {
+            0 => Ok(0),
+            header if header & SCOPED_FLAG != 0 => Ok(header & !SCOPED_FLAG),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    #[cfg(any(test, not(feature = "library")))]
+    fn read_header(&self) -> Result<u32> {
         let data = self.0.try_borrow_data()?;
         let (head, _) = stdx::split_at::<4, u8>(&data)
             .ok_or(ProgramError::AccountDataTooSmall)?;
         Ok(u32::from_le_bytes(*head))
     }
 
+    /// Checks whether given signature is among the first `count` entries
+    /// saved in the account.
+    ///
+    /// The entries must be sorted.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn contains(
+        &self,
+        count: u32,
+        signature: &SignatureHash,
+    ) -> Result<bool> {
+        let data = self.0.try_borrow_data()?;
+        let entries = stdx::as_chunks::<{ SignatureHash::SIZE }, _>(
+            data.get(4..).unwrap_or_default(),
+        )
+        .0
+        .get(..usize::try_from(count).unwrap())
+        .ok_or(ProgramError::AccountDataTooSmall)?;
+        Ok(entries.binary_search(signature.as_ref()).is_ok())
+    }
+
     /// Sets number of signatures saved in the account and sort the entries.
     #[cfg(any(test, not(feature = "library")))]
     pub(crate) fn write_count_and_sort(&self, count: u32) -> Result {
@@ -133,6 +199,120 @@ impl<'a, 'info> SignaturesAccount<'a, 'info> {
         Ok(())
     }
 
+    /// Checks whether given signature is among the first `count` entries of
+    /// an account holding epoch-scoped entries.
+    ///
+    /// The entries must be sorted.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn contains_scoped(
+        &self,
+        count: u32,
+        signature: &SignatureHash,
+    ) -> Result<bool> {
+        let data = self.0.try_borrow_data()?;
+        let entries = stdx::as_chunks::<SCOPED_ENTRY_SIZE, _>(
+            data.get(4..).unwrap_or_default(),
+        )
+        .0
+        .get(..usize::try_from(count).unwrap())
+        .ok_or(ProgramError::AccountDataTooSmall)?;
+        Ok(entries
+            .binary_search_by(|entry| {
+                entry[..SignatureHash::SIZE].cmp(&signature.0[..])
+            })
+            .is_ok())
+    }
+
+    /// Sets number of epoch-scoped signatures saved in the account and sorts
+    /// the entries.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn write_scoped_count_and_sort(&self, count: u32) -> Result {
+        if count & SCOPED_FLAG != 0 {
+            return Err(ProgramError::ArithmeticOverflow);
+        }
+        let mut data = self.0.try_borrow_mut_data()?;
+        #[allow(clippy::explicit_auto_deref)]
+        let (head, tail) = stdx::split_at_mut::<4, _>(*data)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        let entries = stdx::as_chunks_mut::<SCOPED_ENTRY_SIZE, _>(tail)
+            .0
+            .get_mut(..usize::try_from(count).unwrap())
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        *head = (count | SCOPED_FLAG).to_le_bytes();
+        entries.sort_unstable();
+        Ok(())
+    }
+
+    /// Writes epoch-scoped signature collected for block at given height at
+    /// given index.
+    ///
+    /// Unlike [`Self::write_signature`] doesn’t resize the account.  Caller
+    /// must make sure it’s large enough.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn write_scoped_signature(
+        &self,
+        index: u32,
+        signature: &SignatureHash,
+        height: u64,
+    ) -> Result {
+        let start = usize::try_from(index)
+            .ok()
+            .and_then(|index| index.checked_mul(SCOPED_ENTRY_SIZE))
+            .and_then(|offset| offset.checked_add(4))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let mut data = self.0.try_borrow_mut_data()?;
+        let entry = data
+            .get_mut(start..)
+            .and_then(|tail| stdx::split_at_mut::<SCOPED_ENTRY_SIZE, _>(tail))
+            .ok_or(ProgramError::AccountDataTooSmall)?
+            .0;
+        let (hash, tail) = stdx::split_array_mut::<
+            { SignatureHash::SIZE },
+            8,
+            SCOPED_ENTRY_SIZE,
+        >(entry);
+        *hash = signature.0;
+        *tail = height.to_le_bytes();
+        Ok(())
+    }
+
+    /// Removes epoch-scoped entries collected for blocks below given height.
+    ///
+    /// `count` is the number of entries stored in the account.  Remaining
+    /// entries are kept sorted and the new number of entries is written to the
+    /// account and returned.
+    #[cfg(any(test, not(feature = "library")))]
+    pub(crate) fn retain_scoped(
+        &self,
+        count: u32,
+        min_height: u64,
+    ) -> Result<u32> {
+        let mut data = self.0.try_borrow_mut_data()?;
+        #[allow(clippy::explicit_auto_deref)]
+        let (head, tail) = stdx::split_at_mut::<4, _>(*data)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        let entries = stdx::as_chunks_mut::<SCOPED_ENTRY_SIZE, _>(tail)
+            .0
+            .get_mut(..usize::try_from(count).unwrap())
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        let mut kept = 0;
+        for index in 0..entries.len() {
+            let height = stdx::split_array_ref::<
+                { SignatureHash::SIZE },
+                8,
+                SCOPED_ENTRY_SIZE,
+            >(&entries[index])
+            .1;
+            if u64::from_le_bytes(*height) >= min_height {
+                entries[kept] = entries[index];
+                kept += 1;
+            }
+        }
+        let kept = u32::try_from(kept).unwrap();
+        *head = (kept | SCOPED_FLAG).to_le_bytes();
+        Ok(kept)
+    }
+
     /// Writes signature at given index.
     ///
     /// If the account isn’t large enough to hold `index` entries, calls
@@ -177,13 +357,26 @@ pub(crate) fn find_sighash(
 ) -> Result<bool> {
     let (head, tail) = stdx::split_at::<4, _>(data)
         .ok_or(ProgramError::AccountDataTooSmall)?;
-    let count = usize::try_from(u32::from_le_bytes(*head))
+    let header = u32::from_le_bytes(*head);
+    let count = usize::try_from(header & !SCOPED_FLAG)
         .map_err(|_| ProgramError::InvalidAccountData)?;
-    let entries = stdx::as_chunks::<{ SignatureHash::SIZE }, _>(tail)
-        .0
-        .get(..count)
-        .ok_or(ProgramError::InvalidAccountData)?;
-    Ok(entries.binary_search(signature.as_ref()).is_ok())
+    if header & SCOPED_FLAG == 0 {
+        let entries = stdx::as_chunks::<{ SignatureHash::SIZE }, _>(tail)
+            .0
+            .get(..count)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(entries.binary_search(signature.as_ref()).is_ok())
+    } else {
+        let entries = stdx::as_chunks::<SCOPED_ENTRY_SIZE, _>(tail)
+            .0
+            .get(..count)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(entries
+            .binary_search_by(|entry| {
+                entry[..SignatureHash::SIZE].cmp(&signature.0[..])
+            })
+            .is_ok())
+    }
 }
 
 // Returns iterator over signature hashes in given account.
@@ -266,4 +459,66 @@ fn test_ed25519() {
     assert_eq!(yes, signatures.find_ed25519(&[11; 32], &[12; 64], b"foo"));
     assert_eq!(yes, signatures.find_ed25519(&[21; 32], &[22; 64], b"bar"));
     assert_eq!(yes, signatures.find_ed25519(&[31; 32], &[32; 64], b"baz"));
+
+    assert_eq!(yes, signatures.contains(3, &sig1));
+    assert_eq!(yes, signatures.contains(3, &sig3));
+    assert_eq!(nah, signatures.contains(0, &sig1));
+    let sig4 = SignatureHash::new_ed25519(&[41; 32], &[42; 64], b"qux");
+    assert_eq!(nah, signatures.contains(3, &sig4));
+    assert!(signatures.contains(4, &sig4).is_err());
+}
+
+#[test]
+fn test_scoped() {
+    let sig1 = SignatureHash::new_ed25519(&[11; 32], &[12; 64], b"foo");
+    let sig2 = SignatureHash::new_ed25519(&[21; 32], &[22; 64], b"bar");
+    let sig3 = SignatureHash::new_ed25519(&[31; 32], &[32; 64], b"baz");
+
+    let mut data = [0; 4 + 3 * SCOPED_ENTRY_SIZE];
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports: u64 = 42;
+
+    let account = AccountInfo {
+        key: &key,
+        lamports: alloc::rc::Rc::new(core::cell::RefCell::new(&mut lamports)),
+        data: alloc::rc::Rc::new(core::cell::RefCell::new(&mut data[..])),
+        owner: &owner,
+        rent_epoch: 42,
+        is_signer: false,
+        is_writable: false,
+        executable: false,
+    };
+    let signatures =
+        SignaturesAccount::new_checked_owner(&account, &owner).unwrap();
+
+    let yes = Ok(true);
+    let nah = Ok(false);
+
+    assert_eq!(Ok(0), signatures.read_scoped_count());
+    signatures.write_scoped_signature(0, &sig1, 10).unwrap();
+    signatures.write_scoped_signature(1, &sig2, 11).unwrap();
+    signatures.write_scoped_signature(2, &sig3, 12).unwrap();
+    assert!(signatures.write_scoped_signature(3, &sig3, 12).is_err());
+    signatures.write_scoped_count_and_sort(3).unwrap();
+
+    assert_eq!(Ok(3), signatures.read_scoped_count());
+    assert!(signatures.read_count().is_err());
+    assert_eq!(yes, signatures.contains_scoped(3, &sig1));
+    assert_eq!(yes, signatures.contains_scoped(3, &sig2));
+    assert_eq!(yes, signatures.find_ed25519(&[11; 32], &[12; 64], b"foo"));
+    assert_eq!(yes, signatures.find_ed25519(&[31; 32], &[32; 64], b"baz"));
+
+    assert_eq!(Ok(2), signatures.retain_scoped(3, 11));
+    assert_eq!(Ok(2), signatures.read_scoped_count());
+    assert_eq!(nah, signatures.find_ed25519(&[11; 32], &[12; 64], b"foo"));
+    assert_eq!(yes, signatures.find_ed25519(&[21; 32], &[22; 64], b"bar"));
+    assert_eq!(yes, signatures.find_ed25519(&[31; 32], &[32; 64], b"baz"));
+    assert_eq!(nah, signatures.contains_scoped(2, &sig1));
+    assert_eq!(yes, signatures.contains_scoped(2, &sig3));
+
+    assert_eq!(Ok(0), signatures.retain_scoped(2, 13));
+    assert_eq!(Ok(0), signatures.read_scoped_count());
+    assert_eq!(Ok(0), signatures.read_count());
+    assert_eq!(nah, signatures.find_ed25519(&[31; 32], &[32; 64], b"baz"));
 }
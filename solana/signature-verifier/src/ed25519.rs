@@ -109,6 +109,87 @@ impl guestchain::Signature for Signature {
     }
 }
 
+/// An Ed25519 secret key used by guest validators to sign guest blocks.
+///
+/// The key material is zeroed when the object is dropped and the `Debug`
+/// representation shows only the corresponding public key so that the secret
+/// doesn’t end up in logs by accident.
+#[cfg(feature = "keys")]
+#[derive(Clone, zeroize::Zeroize, zeroize::ZeroizeOnDrop)]
+pub struct SecretKey([u8; 32]);
+
+/// Secret key bytes are malformed or don’t match the public key.
+#[cfg(feature = "keys")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadSecretKey;
+
+#[cfg(feature = "keys")]
+impl SecretKey {
+    pub const LENGTH: usize = 32;
+
+    /// Constructs the key from a 32-byte seed.
+    pub fn from_seed(seed: &[u8; 32]) -> Self { Self(*seed) }
+
+    /// Constructs the key from a 64-byte `secret || public` keypair as used
+    /// by Solana keypair files.
+    ///
+    /// Verifies that the public half of the keypair matches the secret.
+    pub fn from_keypair_bytes(bytes: &[u8]) -> Result<Self, BadSecretKey> {
+        let (secret, public) = <&[u8; 64]>::try_from(bytes)
+            .map(stdx::split_array_ref::<32, 32, 64>)
+            .map_err(|_| BadSecretKey)?;
+        let this = Self::from_seed(secret);
+        if this.pubkey().0 == *public {
+            Ok(this)
+        } else {
+            Err(BadSecretKey)
+        }
+    }
+
+    /// Returns the key as a 64-byte `secret || public` keypair as used by
+    /// Solana keypair files.
+    pub fn to_keypair_bytes(&self) -> zeroize::Zeroizing<[u8; 64]> {
+        let mut bytes = zeroize::Zeroizing::new([0; 64]);
+        bytes[..32].copy_from_slice(&self.0[..]);
+        bytes[32..].copy_from_slice(&self.pubkey().0[..]);
+        bytes
+    }
+
+    /// Returns the public key corresponding to this secret key.
+    pub fn pubkey(&self) -> PubKey {
+        PubKey(ed25519_dalek::PublicKey::from(&self.dalek()).to_bytes())
+    }
+
+    /// Signs given message.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let secret = self.dalek();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let expanded = ed25519_dalek::ExpandedSecretKey::from(&secret);
+        Signature(expanded.sign(message, &public).to_bytes())
+    }
+
+    /// Returns the key as dalek’s secret key.
+    ///
+    /// `ed25519_dalek::SecretKey` zeroes itself on drop so the temporary
+    /// doesn’t leave a copy of the key behind.
+    fn dalek(&self) -> ed25519_dalek::SecretKey {
+        ed25519_dalek::SecretKey::from_bytes(&self.0[..]).unwrap()
+    }
+}
+
+#[cfg(feature = "keys")]
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "SecretKey({}, <redacted>)", self.pubkey())
+    }
+}
+
+#[cfg(all(feature = "keys", feature = "guest"))]
+impl guestchain::Signer<PubKey> for SecretKey {
+    #[inline]
+    fn sign(&self, message: &[u8]) -> Signature { self.sign(message) }
+}
+
 macro_rules! fmt_impl {
     (impl $trait:ident for $ty:ident, $func_name:ident) => {
         impl fmt::$trait for $ty {
@@ -141,3 +222,32 @@ fn base64_display(bytes: &[u8; 64], fmtr: &mut fmt::Formatter) -> fmt::Result {
 fn base58_display(bytes: &[u8; 32], fmtr: &mut fmt::Formatter) -> fmt::Result {
     <&lib::hash::CryptoHash>::from(bytes).fmt_bs58(fmtr)
 }
+
+#[test]
+#[cfg(feature = "keys")]
+fn test_secret_key() {
+    use ed25519_dalek::Verifier;
+
+    let secret = ed25519_dalek::SecretKey::from_bytes(&[42; 32]).unwrap();
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    let mut bytes = [0; 64];
+    bytes[..32].copy_from_slice(secret.as_bytes());
+    bytes[32..].copy_from_slice(public.as_bytes());
+
+    let key = SecretKey::from_keypair_bytes(&bytes[..]).unwrap();
+    assert_eq!(PubKey(public.to_bytes()), key.pubkey());
+    assert_eq!(bytes, *key.to_keypair_bytes());
+    assert_eq!(
+        format!("SecretKey({}, <redacted>)", key.pubkey()),
+        format!("{key:?}")
+    );
+
+    let signature = key.sign(b"message");
+    let signature = ed25519_dalek::Signature::from(signature.0);
+    public.verify(b"message", &signature).unwrap();
+
+    let mut bad = bytes;
+    bad[63] ^= 1;
+    assert_eq!(Err(BadSecretKey), SecretKey::from_keypair_bytes(&bad[..]));
+    SecretKey::from_keypair_bytes(&bytes[..32]).unwrap_err();
+}
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 use solana_program::account_info::AccountInfo;
 use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use solana_program::instruction::Instruction;
@@ -16,7 +18,8 @@ solana_program::entrypoint!(process_instruction);
 
 /// Processes the Solana instruction.
 ///
-/// The program supports two operations: Update and Free.
+/// The program supports five operations: Update, Free, Batch, Scoped Batch and
+/// Collect.
 ///
 /// # Update
 ///
@@ -73,6 +76,51 @@ solana_program::entrypoint!(process_instruction);
 /// 2. Signatures account (writable).
 ///
 /// It frees the Signatures account transferring all lamports to the payer.
+///
+/// # Batch
+///
+/// The Batch operation is represented by the same structure as Update except
+/// that the first byte is always 2u8 and takes the same accounts.
+///
+/// Rather than looking only at the instruction immediately preceding the
+/// current one, it collects signatures from all consecutive calls to the
+/// Ed25519 native program preceding it.  Signatures which are already stored
+/// in the Signatures account or repeated in the batch are skipped and the
+/// account is resized at most once so that all the new entries fit.  This
+/// lets a single instruction register signatures of a large validators set.
+///
+/// # Scoped Batch
+///
+/// The Scoped Batch operation is represented by the following pseudo-Rust
+/// structure:
+///
+/// ```ignore
+/// #[repr(C, packed)]
+/// struct Instruction {
+///     always_three: u8,  // always 3u8,
+///     seed_len: u8,  // at most 31
+///     seed: [u8; seed_len],
+///     bump: u8,
+///     height: u64,
+/// }
+/// ```
+///
+/// It takes the same accounts as Update and collects signatures the same way
+/// Batch does.  However, rather than truncating the account, each entry is
+/// tagged with `height` of the guest block whose fingerprint was signed.  This
+/// way signatures of concurrently pending blocks can be collected in a single
+/// account without one relayer discarding entries of another.  The account
+/// must be empty or hold only entries added by this operation.
+///
+/// # Collect
+///
+/// The Collect operation is represented by the same structure as Scoped Batch
+/// except that the first byte is always 4u8.  It takes the same accounts as
+/// Free.
+///
+/// It removes entries added by Scoped Batch for blocks below `height` and
+/// shrinks the Signatures account accordingly transferring lamports which are
+/// no longer needed for rent exemption to the payer.
 fn process_instruction<'a>(
     program_id: &'a Pubkey,
     mut accounts: &'a [AccountInfo],
@@ -87,6 +135,9 @@ fn process_instruction<'a>(
     match (tag, instruction.len()) {
         (0, _) => handle_update(ctx, accounts, instruction),
         (1, 0) => ctx.free_signatures_account(),
+        (2, _) => handle_batch(ctx, accounts, instruction),
+        (3, _) => handle_scoped_batch(ctx, accounts, instruction),
+        (4, _) => handle_collect(ctx, instruction),
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
@@ -101,13 +152,7 @@ fn handle_update(
     // Read `truncate` from instruction data.  If given, discard any signatures
     // stored in the Signatures account past given number.  The number may be larger
     // than the available count.
-    let truncate = if instruction.is_empty() {
-        u32::MAX
-    } else if let Ok(truncate) = instruction.try_into() {
-        u32::from_le_bytes(truncate)
-    } else {
-        return Err(ProgramError::InvalidInstructionData);
-    };
+    let truncate = read_truncate(instruction)?;
 
     // Initialise the Signatures account and read number of signatures stored there.
     ctx.initialise_signatures_account()?;
@@ -135,6 +180,114 @@ fn handle_update(
 }
 
 
+/// Handles the Batch operation.
+fn handle_batch(
+    ctx: Context,
+    accounts: &[AccountInfo],
+    instruction: &[u8],
+) -> Result {
+    let truncate = read_truncate(instruction)?;
+
+    ctx.initialise_signatures_account()?;
+    let count = ctx.signatures.read_count()?.min(truncate);
+
+    // Skip signatures which are already stored.  Stored entries are sorted so
+    // binary search can be used.
+    let mut new = Vec::new();
+    for signature in collect_signatures(accounts)? {
+        if !ctx.signatures.contains(count, &signature)? {
+            new.push(signature);
+        }
+    }
+
+    let total = u32::try_from(new.len())
+        .ok()
+        .and_then(|len| count.checked_add(len))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    ctx.reserve_signatures_account(total, crate::SignatureHash::SIZE)?;
+    for (index, signature) in (count..).zip(new.iter()) {
+        ctx.signatures.write_signature(index, signature, || {
+            Err(ProgramError::AccountDataTooSmall)
+        })?;
+    }
+
+    ctx.signatures.write_count_and_sort(total)
+}
+
+
+/// Handles the Scoped Batch operation.
+fn handle_scoped_batch(
+    ctx: Context,
+    accounts: &[AccountInfo],
+    instruction: &[u8],
+) -> Result {
+    let height = read_height(instruction)?;
+
+    ctx.initialise_signatures_account()?;
+    let count = ctx.signatures.read_scoped_count()?;
+
+    let mut new = Vec::new();
+    for signature in collect_signatures(accounts)? {
+        if !ctx.signatures.contains_scoped(count, &signature)? {
+            new.push(signature);
+        }
+    }
+
+    let total = u32::try_from(new.len())
+        .ok()
+        .and_then(|len| count.checked_add(len))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    ctx.reserve_signatures_account(total, crate::api::SCOPED_ENTRY_SIZE)?;
+    for (index, signature) in (count..).zip(new.iter()) {
+        ctx.signatures.write_scoped_signature(index, signature, height)?;
+    }
+
+    ctx.signatures.write_scoped_count_and_sort(total)
+}
+
+
+/// Handles the Collect operation.
+fn handle_collect(ctx: Context, instruction: &[u8]) -> Result {
+    let height = read_height(instruction)?;
+    let count = ctx.signatures.read_scoped_count()?;
+    let count = ctx.signatures.retain_scoped(count, height)?;
+    let size = usize::try_from(count)
+        .ok()
+        .and_then(|count| count.checked_mul(crate::api::SCOPED_ENTRY_SIZE))
+        .and_then(|len| len.checked_add(4))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    ctx.shrink_signatures_account(size)
+}
+
+
+/// Collects signatures from all calls to the Ed25519 native program directly
+/// preceding current instruction.
+///
+/// Returned signatures are sorted and deduplicated.
+fn collect_signatures(
+    accounts: &[AccountInfo],
+) -> Result<Vec<crate::SignatureHash>> {
+    let ix_sysvar =
+        accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let mut batch = Vec::new();
+    let mut offset = -1;
+    while let Ok(ix) = instructions::get_instruction_relative(offset, ix_sysvar)
+    {
+        if !solana_program::ed25519_program::check_id(&ix.program_id) {
+            break;
+        }
+        process_ed25519_instruction(ix, |signature| {
+            batch.push(signature);
+            Ok(())
+        })?;
+        offset -= 1;
+    }
+    batch.sort_unstable();
+    batch.dedup();
+    Ok(batch)
+}
+
+
 /// Extracts signatures from a call to Ed25519 native program.
 ///
 /// If the `instruction` doesn’t correspond to call to the Ed25519 signature
@@ -272,10 +425,37 @@ impl<'a, 'info> Context<'a, 'info> {
     /// Enlarges the Signatures account by 10 KiB (or to maximum allowable size).
     fn enlarge_signatures_account(&self) -> Result {
         let current_size = self.signatures.try_data_len()?;
-        let size = (current_size + MAX_PERMITTED_DATA_INCREASE)
-            .min(MAX_PERMITTED_DATA_LENGTH as usize);
+        self.resize_signatures_account(
+            current_size + MAX_PERMITTED_DATA_INCREASE,
+        )
+    }
+
+    /// Enlarges the Signatures account so it can hold `count` entries of
+    /// `entry_size` bytes each.
+    ///
+    /// Does nothing if the account is large enough already.  Note that the
+    /// runtime limits how much an account can grow in a single instruction
+    /// to [`MAX_PERMITTED_DATA_INCREASE`].
+    fn reserve_signatures_account(
+        &self,
+        count: u32,
+        entry_size: usize,
+    ) -> Result {
+        let size = usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(entry_size))
+            .and_then(|len| len.checked_add(4))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.resize_signatures_account(size)
+    }
+
+    /// Enlarges the Signatures account to given size (or to maximum allowable
+    /// size).
+    fn resize_signatures_account(&self, size: usize) -> Result {
+        let current_size = self.signatures.try_data_len()?;
+        let size = size.min(MAX_PERMITTED_DATA_LENGTH as usize);
 
-        // Do nothing if account is already maximum size.  We don’t report
+        // Do nothing if account is already large enough.  We don’t report
         // error.  Instead caller will fail trying to access data past account’s
         // size.
         if size <= current_size {
@@ -301,6 +481,27 @@ impl<'a, 'info> Context<'a, 'info> {
         self.signatures.realloc(size, false)
     }
 
+    /// Shrinks the Signatures account to given size transferring lamports no
+    /// longer needed for rent exemption to the payer.
+    ///
+    /// Does nothing if the account is already small enough.
+    fn shrink_signatures_account(&self, size: usize) -> Result {
+        if size >= self.signatures.try_data_len()? {
+            return Ok(());
+        }
+        self.signatures.realloc(size, false)?;
+
+        let required_lamports = Rent::get()?.minimum_balance(size);
+        let mut payer = self.payer.try_borrow_mut_lamports()?;
+        let mut lamports = self.signatures.try_borrow_mut_lamports()?;
+        let excess = lamports.saturating_sub(required_lamports);
+        **payer = payer
+            .checked_add(excess)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        **lamports -= excess;
+        Ok(())
+    }
+
     /// Returns seeds used to generate Signatures account PDA.
     fn write_seeds(&self) -> [&'a [u8]; 2] {
         [self.payer.key.as_ref(), self.seed_and_bump]
@@ -308,6 +509,27 @@ impl<'a, 'info> Context<'a, 'info> {
 }
 
 
+/// Reads optional truncate length of the Update and Batch operations.
+///
+/// Returns `u32::MAX` if the instruction data is empty.
+fn read_truncate(instruction: &[u8]) -> Result<u32> {
+    if instruction.is_empty() {
+        Ok(u32::MAX)
+    } else if let Ok(truncate) = instruction.try_into() {
+        Ok(u32::from_le_bytes(truncate))
+    } else {
+        Err(ProgramError::InvalidInstructionData)
+    }
+}
+
+/// Reads block height of the Scoped Batch and Collect operations.
+fn read_height(instruction: &[u8]) -> Result<u64> {
+    instruction
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| ProgramError::InvalidInstructionData)
+}
+
 /// Reads given object from the start of the slice advancing it.
 ///
 /// Returns an error if slice is too short.
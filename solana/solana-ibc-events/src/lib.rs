@@ -0,0 +1,234 @@
+//! Decoder of events emitted by the solana-ibc program.
+//!
+//! The program emits events as Borsh-serialised [`Event`] objects logged with
+//! `sol_log_data`.  They show up in transaction logs as `Program data:` lines
+//! with base64-encoded payload.  Since any program can log data, the decoder
+//! tracks program invocations in the logs and only considers lines logged
+//! while solana-ibc was executing.
+//!
+//! Similarly, query instructions (such as `pending_stake_changes`) return
+//! Borsh-serialised values as transaction return data which shows up as
+//! `Program return:` line in the logs.  [`return_data`] decodes those.
+//!
+//! With `rpc` feature enabled, [`rpc`] module offers helpers which operate
+//! directly on transaction metadata returned by Solana RPC.
+
+use base64::Engine;
+use borsh::BorshDeserialize;
+pub use solana_ibc::events::*;
+use solana_program::pubkey::Pubkey;
+
+/// Prefix of log lines with data logged via `sol_log_data`.
+const DATA_PREFIX: &str = "Program data: ";
+
+/// Prefix of log line with transaction’s return data.
+const RETURN_PREFIX: &str = "Program return: ";
+
+/// Error decoding an event or return data.
+#[derive(Debug, derive_more::From, derive_more::Display)]
+pub enum DecodeError {
+    #[display(fmt = "invalid base64: {}", _0)]
+    Base64(base64::DecodeError),
+
+    #[display(fmt = "invalid borsh: {}", _0)]
+    Borsh(borsh::maybestd::io::Error),
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes Borsh-serialised event.
+pub fn decode_event(data: &[u8]) -> Result<Event<'static>, DecodeError> {
+    Ok(Event::try_from_slice(data)?)
+}
+
+/// Decodes events emitted by solana-ibc program from transaction logs.
+///
+/// `program_id` is the address of the solana-ibc program; usually that’s
+/// [`solana_ibc::ID`].  Data logged by other programs (including those
+/// invoked from solana-ibc via CPI) is ignored.  Returns an error for data
+/// logged by the program which cannot be decoded as an [`Event`].
+pub fn events_from_logs<'a>(
+    program_id: &Pubkey,
+    logs: impl IntoIterator<Item = &'a str>,
+) -> impl Iterator<Item = Result<Event<'static>, DecodeError>> {
+    program_lines(program_id, logs).filter_map(|line| {
+        let data = line.strip_prefix(DATA_PREFIX)?;
+        Some(decode_base64(data).and_then(|data| decode_event(&data)))
+    })
+}
+
+/// Decodes return data set by solana-ibc program from transaction logs.
+///
+/// Returns `None` if the logs don’t include return data of the program
+/// `program_id`.  Since runtime only logs return data of the top-level
+/// instructions, this only works for instructions invoked directly.
+pub fn return_data<'a, T: BorshDeserialize>(
+    program_id: &Pubkey,
+    logs: impl IntoIterator<Item = &'a str>,
+) -> Option<Result<T, DecodeError>> {
+    let program_id = program_id.to_string();
+    let data = logs
+        .into_iter()
+        .filter_map(|line| {
+            let (id, data) =
+                line.strip_prefix(RETURN_PREFIX)?.split_once(' ')?;
+            (id == program_id).then_some(data)
+        })
+        .last()?;
+    Some(decode_base64(data).and_then(|data| Ok(T::try_from_slice(&data)?)))
+}
+
+/// Returns log lines emitted while given program was executing.
+///
+/// Tracks `invoke`, `success` and `failed` lines to determine which program
+/// logged each line.  Lines logged by programs invoked via CPI are attributed
+/// to the invoked program.
+fn program_lines<'a>(
+    program_id: &Pubkey,
+    logs: impl IntoIterator<Item = &'a str>,
+) -> impl Iterator<Item = &'a str> {
+    let program_id = program_id.to_string();
+    let mut stack = Vec::<&'a str>::new();
+    logs.into_iter().filter(move |line: &&'a str| {
+        let line: &'a str = line;
+        if let Some(rest) = line.strip_prefix("Program ") {
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(id), Some("invoke")) => {
+                    stack.push(id);
+                    return false;
+                }
+                (Some(id), Some("success" | "failed:")) => {
+                    if stack.last() == Some(&id) {
+                        stack.pop();
+                    }
+                    return false;
+                }
+                _ => (),
+            }
+        }
+        stack.last() == Some(&program_id.as_str())
+    })
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, DecodeError> {
+    Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+}
+
+/// Helpers operating on transaction metadata returned by Solana RPC.
+#[cfg(feature = "rpc")]
+pub mod rpc {
+    use solana_transaction_status::option_serializer::OptionSerializer;
+    use solana_transaction_status::UiTransactionStatusMeta;
+
+    use super::*;
+
+    /// Decodes events emitted by solana-ibc program in a transaction.
+    ///
+    /// See [`events_from_logs`](super::events_from_logs).  Returns no events
+    /// if the metadata doesn’t include logs.
+    pub fn events<'a>(
+        program_id: &Pubkey,
+        meta: &'a UiTransactionStatusMeta,
+    ) -> impl Iterator<Item = Result<Event<'static>, DecodeError>> + 'a {
+        events_from_logs(program_id, log_messages(meta))
+    }
+
+    /// Decodes return data set by solana-ibc program in a transaction.
+    ///
+    /// Returns `None` if the transaction has no return data or the data has
+    /// been set by a different program.
+    pub fn return_data<T: BorshDeserialize>(
+        program_id: &Pubkey,
+        meta: &UiTransactionStatusMeta,
+    ) -> Option<Result<T, DecodeError>> {
+        let ret = match &meta.return_data {
+            OptionSerializer::Some(ret) => ret,
+            _ => return None,
+        };
+        (ret.program_id == program_id.to_string()).then(|| {
+            decode_base64(&ret.data.0)
+                .and_then(|data| Ok(T::try_from_slice(&data)?))
+        })
+    }
+
+    fn log_messages(
+        meta: &UiTransactionStatusMeta,
+    ) -> impl Iterator<Item = &str> {
+        let logs = match &meta.log_messages {
+            OptionSerializer::Some(logs) => logs.as_slice(),
+            _ => &[],
+        };
+        logs.iter().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+
+    fn make_event(amount: u64) -> Event<'static> {
+        let validator = solana_ibc::chain::PubKey::from([1; 32]);
+        Event::from(BlockRewardsClaimed { validator, amount })
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        let ibc = solana_ibc::ID;
+        let other = Pubkey::new_from_array([2; 32]);
+        let data = |amount| {
+            let event = borsh::to_vec(&make_event(amount)).unwrap();
+            format!("{DATA_PREFIX}{}", encode(&event))
+        };
+        let logs = [
+            format!("Program {other} invoke [1]"),
+            data(1),
+            format!("Program {other} success"),
+            format!("Program {ibc} invoke [1]"),
+            "Program log: Instruction: ClaimRewards".into(),
+            data(2),
+            format!("Program {other} invoke [2]"),
+            data(3),
+            format!("Program {other} success"),
+            data(4),
+            format!("Program {ibc} consumed 4200 of 200000 compute units"),
+            format!("Program {ibc} success"),
+            data(5),
+        ];
+        let got = events_from_logs(&ibc, logs.iter().map(String::as_str))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![make_event(2), make_event(4)], got);
+
+        let logs = [
+            format!("Program {ibc} invoke [1]"),
+            format!("{DATA_PREFIX}{}", encode(b"bogus")),
+            format!("Program {ibc} failed: custom program error: 0x1"),
+        ];
+        let mut got = events_from_logs(&ibc, logs.iter().map(String::as_str));
+        assert!(matches!(got.next(), Some(Err(DecodeError::Borsh(_)))));
+        assert!(got.next().is_none());
+    }
+
+    #[test]
+    fn test_return_data() {
+        let ibc = solana_ibc::ID;
+        let other = Pubkey::new_from_array([2; 32]);
+        let data = encode(&borsh::to_vec(&42u64).unwrap());
+        let logs = [
+            format!("Program {ibc} invoke [1]"),
+            format!("{RETURN_PREFIX}{ibc} {data}"),
+            format!("Program {ibc} success"),
+        ];
+        let logs = logs.iter().map(String::as_str);
+        assert_eq!(
+            42,
+            return_data::<u64>(&ibc, logs.clone()).unwrap().unwrap()
+        );
+        assert!(return_data::<u64>(&other, logs).is_none());
+    }
+}
@@ -67,6 +67,10 @@ mod imp {
 
     /// Returns reference to the global state.
     pub(crate) fn global() -> &'static Global { ALLOCATOR.global() }
+
+    /// Logs heap usage statistics.
+    #[allow(dead_code)]
+    pub(crate) fn log_stats() { ALLOCATOR.log_stats() }
 }
 
 #[cfg(any(
@@ -105,6 +109,14 @@ mod imp {
     pub(crate) fn global() -> &'static Global {
         unimplemented!("global should never be called in tests or CPI")
     }
+
+    /// Logs heap usage statistics.
+    ///
+    /// Does nothing since the custom allocator isn’t used.
+    #[allow(dead_code)]
+    pub(crate) fn log_stats() {}
 }
 
 pub(crate) use imp::global;
+#[allow(unused_imports)]
+pub(crate) use imp::log_stats;
@@ -20,11 +20,90 @@ pub type BlockHeader = guestchain::BlockHeader;
 pub type Manager = guestchain::ChainManager<PubKey>;
 pub type Validator = guestchain::Validator<PubKey>;
 pub type Candidate = guestchain::Candidate<PubKey>;
+pub type EpochStats = guestchain::EpochStats<PubKey>;
 
 /// Guest blockchain data held in Solana account.
 #[account]
+#[derive(Default)]
 pub struct ChainData {
-    inner: Option<Box<ChainInner>>,
+    inner: VersionedChainInner,
+}
+
+/// Performance of a validator in the last finished guest blockchain epoch.
+///
+/// Returned by `epoch_validator_stats` instruction.
+#[derive(Clone, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct EpochValidatorStats {
+    /// Height of the last guest block of the epoch.  The epoch summary is
+    /// committed in the trie under this height.
+    pub last_block_height: u64,
+    /// Number of guest blocks finalised in the epoch.
+    pub blocks: u64,
+    /// Number of finalised blocks the validator has generated.
+    pub blocks_proposed: u64,
+    /// Number of finalised blocks the validator has signed.
+    pub signatures: u64,
+    /// Ratio of blocks signed by the validator to all blocks in basis points.
+    pub uptime_bps: u16,
+}
+
+/// Stake change which hasn’t taken effect yet.
+///
+/// Returned by `pending_stake_changes` instruction.
+#[derive(Clone, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct PendingStakeChange {
+    /// Validator candidate whose stake changes.
+    pub validator: Pubkey,
+    /// Amount added to (if positive) or removed from (if negative) the stake.
+    pub delta: i128,
+    /// Number of the first epoch whose validators set includes the change.
+    pub activation_epoch: u64,
+}
+
+/// Validators set of a guest blockchain epoch.
+///
+/// Hash of the snapshot is committed in the trie under
+/// [`trie_ids::TrieKey::for_epoch`] key when the epoch starts.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct EpochSnapshot {
+    /// Number of the epoch.  Genesis epoch has number zero.
+    pub epoch_number: u64,
+    /// Host height at which the epoch started, i.e. host height of the last
+    /// block of the previous epoch or of the genesis block.
+    pub host_height: u64,
+    /// Validators set of the epoch.
+    pub epoch: Epoch,
+}
+
+impl EpochSnapshot {
+    /// Calculates hash of the snapshot as committed in the trie.
+    pub fn calc_hash(&self) -> CryptoHash {
+        let mut builder = CryptoHash::builder();
+        borsh::to_writer(&mut builder, self).unwrap();
+        builder.build()
+    }
+}
+
+/// Epoch snapshot together with proof of its commitment.
+///
+/// Returned by `query_epoch` instruction.
+#[derive(Clone, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct EpochProof {
+    /// Snapshot of the epoch.  Only the current epoch is kept on chain so for
+    /// past epochs this is `None`.  Their validators sets can be recovered
+    /// from `NewBlock` events and checked against the `commitment`.
+    pub snapshot: Option<EpochSnapshot>,
+    /// Hash of the snapshot committed in the trie or `None` if the epoch
+    /// hasn’t been committed.
+    pub commitment: Option<CryptoHash>,
+    /// Root hash of the trie the proof has been generated for.
+    pub state_root: CryptoHash,
+    /// Borsh-serialised `sealable_trie::proof::Proof` of membership (or
+    /// non-membership if `commitment` is `None`) of the commitment under
+    /// [`trie_ids::TrieKey::for_epoch`] key.
+    pub proof: Vec<u8>,
 }
 
 /// Error indicating that the chain hasn’t been initialised yet, i.e. genesis
@@ -61,6 +140,18 @@ impl ChainData {
         }))
     }
 
+    /// Returns the state root of the finalised guest block at given height.
+    ///
+    /// Roots of a limited number of most recent finalised blocks are kept so
+    /// that proofs generated against a block which is no longer the head can
+    /// still be verified.  Returns `None` if the root at `height` isn’t known.
+    pub fn root_at(
+        &self,
+        height: guestchain::BlockHeight,
+    ) -> Result<Option<CryptoHash>, ChainNotInitialised> {
+        Ok(self.get()?.manager.state_root_at(height).copied())
+    }
+
     /// Initialises a new guest blockchain with given configuration and genesis
     /// epoch.
     ///
@@ -84,7 +175,7 @@ impl ChainData {
 
         let manager = Manager::new(config, genesis).map_err(Error::from)?;
 
-        if self.inner.is_some() {
+        if self.inner.0.is_some() {
             return Err(Error::ChainAlreadyInitialised.into());
         }
         let inner = ChainInner {
@@ -93,7 +184,8 @@ impl ChainData {
             _unused: UnusedPubkey,
             sig_verify_program_id: Box::new(sig_verify_program_id),
         };
-        let inner = self.inner.insert(Box::new(inner));
+        let inner = self.inner.0.insert(Box::new(inner));
+        commit_epoch(&inner.manager, trie)?;
         let (finalised, head) = inner.manager.head();
         assert!(finalised);
         events::emit(events::Initialised { genesis: events::header(head) })
@@ -112,8 +204,19 @@ impl ChainData {
     /// a new block.  In contrast, [`Self::maybe_generate_block`] is intended to
     /// create a new block opportunistically at the beginning of handling any
     /// smart contract request.
-    pub fn generate_block(&mut self, trie: &storage::TrieAccount) -> Result {
-        self.get_mut()?.generate_block(trie, true)
+    ///
+    /// If `proposer` is a validator, they are recorded as the proposer of the
+    /// new block and credited for it in epoch statistics once the block is
+    /// finalised.
+    pub fn generate_block(
+        &mut self,
+        trie: &storage::TrieAccount,
+        proposer: PubKey,
+    ) -> Result {
+        let inner = self.get_mut()?;
+        inner.generate_block(trie, true)?;
+        inner.manager.set_proposer(proposer);
+        Ok(())
     }
 
     /// Generates a new guest block if possible.
@@ -166,6 +269,80 @@ impl ChainData {
         Ok(res.got_quorum())
     }
 
+    /// Commits summary of the epoch which has just ended into the trie.
+    ///
+    /// Does nothing unless the head block has been finalised and it was the
+    /// last block of an epoch.  Otherwise, stores hash of the epoch’s
+    /// statistics under [`trie_ids::TrieKey::for_epoch_summary`] key, hash
+    /// of the new epoch’s snapshot under [`trie_ids::TrieKey::for_epoch`] key
+    /// and commitment of the rewards ledger under
+    /// [`trie_ids::TrieKey::for_rewards_ledger`] key such that all are
+    /// committed in state root of the first block of the next epoch.  Should
+    /// be called after [`Self::sign_block`] reports quorum and before the next
+    /// block is generated.
+    pub fn commit_epoch_summary(
+        &self,
+        trie: &mut storage::TrieAccount,
+    ) -> Result {
+        let manager = &self.get()?.manager;
+        let (finalised, head) = manager.head();
+        let stats = match manager.last_epoch_stats() {
+            Some(stats)
+                if finalised && head.next_epoch_commitment.is_some() =>
+            {
+                stats
+            }
+            _ => return Ok(()),
+        };
+        if stats.last_block_height() != head.block_height {
+            return Ok(());
+        }
+        let key =
+            trie_ids::TrieKey::for_epoch_summary(head.block_height.into());
+        trie.set(&key, &stats.calc_hash())
+            .map_err(|_| Error::Internal("EpochSummary"))?;
+        commit_epoch(manager, trie)
+    }
+
+    /// Returns snapshot of epoch with given number.
+    ///
+    /// Only the current epoch is kept on chain so returns `None` for any
+    /// other epoch.
+    pub fn epoch_snapshot(
+        &self,
+        epoch_number: u64,
+    ) -> Result<Option<EpochSnapshot>, ChainNotInitialised> {
+        let manager = &self.get()?.manager;
+        Ok((manager.epoch_number() == epoch_number)
+            .then(|| epoch_snapshot(manager)))
+    }
+
+    /// Returns summary of the last finished epoch or `None` if no epoch has
+    /// finished yet.
+    pub fn last_epoch_stats(
+        &self,
+    ) -> Result<Option<&EpochStats>, ChainNotInitialised> {
+        Ok(self.get()?.manager.last_epoch_stats())
+    }
+
+    /// Returns performance of given validator in the last finished epoch.
+    ///
+    /// Fails if no epoch has finished yet.
+    pub fn epoch_validator_stats(
+        &self,
+        validator: &PubKey,
+    ) -> Result<EpochValidatorStats> {
+        let stats = self.last_epoch_stats()?.ok_or(Error::NoEpochSummary)?;
+        let validator_stats = stats.get(validator);
+        Ok(EpochValidatorStats {
+            last_block_height: stats.last_block_height().into(),
+            blocks: stats.blocks(),
+            blocks_proposed: validator_stats.blocks_proposed,
+            signatures: validator_stats.signatures,
+            uptime_bps: stats.uptime_bps(validator),
+        })
+    }
+
     /// Updates validator’s stake.
     pub fn set_stake(&mut self, pubkey: PubKey, amount: u128) -> Result<()> {
         self.get_mut()?
@@ -174,49 +351,126 @@ impl ChainData {
             .map_err(Into::into)
     }
 
-    /// Updates multiple validators’ stake.
+    /// Queues changes of multiple validators’ stake.
+    ///
+    /// The changes are applied to the candidates set immediately and take
+    /// effect at the next epoch boundary; see
+    /// [`guestchain::ChainManager::queue_stake_change`] for details.  Emits
+    /// [`events::StakeChangeQueued`] event for each change.
     ///
     /// Fails when trying to remove stake from a non-existent validator,
-    /// removing more stake than a validator holds or if as a result configured
-    /// block minimums won’t be held.
+    /// removing more stake than a validator holds or when the change would
+    /// leave the candidate below minimum stake or the candidates set below
+    /// minimum total stake or number of validators.
     pub fn update_stake(
         &mut self,
         stake_changes: Vec<(PubKey, i128)>,
     ) -> Result<()> {
-        #[derive(derive_more::From)]
-        enum InnerError {
-            Update(guestchain::manager::UpdateCandidateError),
-            Error(Error),
-            Program(ProgramError),
+        for (pubkey, delta) in stake_changes {
+            self.queue_stake_change(pubkey, delta)?;
         }
+        Ok(())
+    }
 
-        impl From<InnerError> for anchor_lang::error::Error {
-            fn from(err: InnerError) -> Self {
-                match err {
-                    InnerError::Update(err) => Error::from(err).into(),
-                    InnerError::Error(err) => err.into(),
-                    InnerError::Program(err) => err.into(),
-                }
-            }
-        }
+    /// Queues change of a single validator’s stake.
+    ///
+    /// Returns number of the first guest epoch whose validators set includes
+    /// the change.  See [`Self::update_stake`] for details.
+    pub fn queue_stake_change(
+        &mut self,
+        pubkey: PubKey,
+        delta: i128,
+    ) -> Result<u64> {
+        use guestchain::manager::QueueStakeError;
 
-        let inner = self.get_mut()?;
-        for (pubkey, amount) in stake_changes {
-            inner.manager.update_candidate(pubkey, |candidate| {
-                candidate
-                    .map_or(0, |c| c.stake.get())
-                    .checked_add_signed(amount)
-                    .ok_or_else(|| {
-                        if amount > 0 {
-                            ProgramError::ArithmeticOverflow.into()
-                        } else if candidate.is_none() {
-                            InnerError::Error(Error::CandidateNotFound)
-                        } else {
-                            InnerError::Error(Error::InsufficientStake)
-                        }
-                    })
+        let activation_epoch = self
+            .get_mut()?
+            .manager
+            .queue_stake_change(pubkey.clone(), delta)
+            .map_err(|err| match err {
+                QueueStakeError::UnknownCandidate => {
+                    Error::CandidateNotFound.into()
+                }
+                QueueStakeError::InsufficientStake => {
+                    Error::InsufficientStake.into()
+                }
+                QueueStakeError::Overflow => anchor_lang::error::Error::from(
+                    ProgramError::ArithmeticOverflow,
+                ),
+                QueueStakeError::Rejected(err) => Error::from(err).into(),
             })?;
+        events::emit(events::StakeChangeQueued {
+            validator: pubkey,
+            delta,
+            activation_epoch,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(activation_epoch)
+    }
+
+    /// Returns number of the current guest epoch.
+    pub fn epoch_number(&self) -> Result<u64, ChainNotInitialised> {
+        Ok(self.get()?.manager.epoch_number())
+    }
+
+    /// Returns number of the first guest epoch whose validators set would
+    /// include a stake change made now.
+    ///
+    /// Once that epoch starts, tokens backing stake removed now can be
+    /// released; see [`guestchain::ChainManager::stake_activation_epoch`].
+    pub fn stake_activation_epoch(&self) -> Result<u64, ChainNotInitialised> {
+        Ok(self.get()?.manager.stake_activation_epoch())
+    }
+
+    /// Returns minimum stake of a single validator.
+    pub fn min_validator_stake(&self) -> Result<u128, ChainNotInitialised> {
+        Ok(self.get()?.manager.config().min_validator_stake.get())
+    }
+
+    /// Returns stake changes which haven’t taken effect yet in the order they
+    /// were made.
+    pub fn pending_stake_changes(&self) -> Result<Vec<PendingStakeChange>> {
+        let changes = self.get()?.manager.stake_changes();
+        Ok(changes
+            .iter()
+            .map(|change| PendingStakeChange {
+                validator: change.pubkey.clone().into(),
+                delta: change.delta,
+                activation_epoch: change.activation_epoch,
+            })
+            .collect())
+    }
+
+    /// Replaces validator candidate’s key with a new one keeping its stake.
+    ///
+    /// `proof_of_possession` must be a signature of [`key_rotation_message`]
+    /// made with the new key.  It’s caller’s responsibility to verify that
+    /// the request has been authorised by the holder of the old key.
+    ///
+    /// The new key becomes part of the validators set on next epoch change.
+    /// If the candidate is a validator in the current epoch, it needs to keep
+    /// signing blocks with the old key until then.  Stake changes queued for
+    /// the old key are moved to the new one.
+    pub fn rotate_validator_key(
+        &mut self,
+        old: PubKey,
+        new: PubKey,
+        proof_of_possession: &Signature,
+        verifier: &Verifier,
+    ) -> Result {
+        let manager = &mut self.get_mut()?.manager;
+        let message = key_rotation_message(manager.genesis(), &old, &new);
+        if !guestchain::Verifier::verify(
+            verifier,
+            &message,
+            &new,
+            proof_of_possession,
+        ) {
+            return Err(Error::BadSignature.into());
         }
+        manager.rotate_candidate_key(&old, new.clone()).map_err(Error::from)?;
+        events::emit(events::ValidatorKeyRotated { old, new })
+            .map_err(ProgramError::BorshIoError)?;
         Ok(())
     }
 
@@ -310,19 +564,82 @@ impl ChainData {
         self.get_mut()?.manager.update_config(config).map_err(into_error)
     }
 
+    /// Sets format of fingerprints of newly generated guest blocks.
+    pub fn set_fingerprint_version(
+        &mut self,
+        version: guestchain::block::FingerprintVersion,
+    ) -> Result {
+        self.get_mut()?.manager.set_fingerprint_version(version);
+        Ok(())
+    }
+
+    /// Sets reward for each guest block finalised by quorum of validators.
+    ///
+    /// Returns previous value of the reward.  See [`guestchain::Rewards`] for
+    /// description of how rewards are distributed among validators.
+    pub fn set_block_reward(&mut self, block_reward: u64) -> Result<u64> {
+        let manager = &mut self.get_mut()?.manager;
+        let previous = manager.rewards().block_reward();
+        manager.set_block_reward(block_reward);
+        Ok(previous)
+    }
+
+    /// Returns block rewards accrued by given validator which haven’t been
+    /// claimed yet.
+    pub fn block_rewards(
+        &self,
+        validator: &PubKey,
+    ) -> Result<u128, ChainNotInitialised> {
+        Ok(self.get()?.manager.rewards().accrued(validator))
+    }
+
+    /// Returns sum of block rewards accrued by all validators which haven’t
+    /// been claimed yet.
+    pub fn unclaimed_rewards(&self) -> Result<u128, ChainNotInitialised> {
+        Ok(self.get()?.manager.rewards().total_accrued())
+    }
+
+    /// Claims all block rewards accrued by given validator.
+    ///
+    /// Returns amount which needs to be paid out to the validator.  Fails if
+    /// the validator has nothing to claim.
+    pub fn claim_block_rewards(&mut self, validator: &PubKey) -> Result<u64> {
+        let amount = self.get_mut()?.manager.claim_rewards(validator);
+        if amount == 0 {
+            return Err(Error::NoRewardsToClaim.into());
+        }
+        u64::try_from(amount)
+            .map_err(|_| ProgramError::ArithmeticOverflow.into())
+    }
+
     /// Returns a shared reference the inner chain data if it has been
     /// initialised.
     fn get(&self) -> Result<&ChainInner, ChainNotInitialised> {
-        self.inner.as_deref().ok_or(ChainNotInitialised)
+        self.inner.0.as_deref().ok_or(ChainNotInitialised)
     }
 
     /// Returns an exclusive reference the inner chain data if it has been
     /// initialised.
     fn get_mut(&mut self) -> Result<&mut ChainInner, ChainNotInitialised> {
-        self.inner.as_deref_mut().ok_or(ChainNotInitialised)
+        self.inner.0.as_deref_mut().ok_or(ChainNotInitialised)
     }
 }
 
+/// Returns message which needs to be signed with the new key when rotating
+/// validator’s key.
+///
+/// The message includes hash of the genesis block so that the signature can’t
+/// be replayed on a different guest chain.
+pub fn key_rotation_message(
+    genesis: &CryptoHash,
+    old: &PubKey,
+    new: &PubKey,
+) -> Vec<u8> {
+    let old: &[u8; 32] = old.as_ref();
+    let new: &[u8; 32] = new.as_ref();
+    [b"guest-key-rotation".as_slice(), genesis.as_slice(), old, new].concat()
+}
+
 /// The inner chain data
 #[derive(Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
 struct ChainInner {
@@ -389,6 +706,73 @@ impl ChainInner {
     }
 }
 
+/// Original layout of [`ChainInner`].
+///
+/// See [`VersionedChainInner`].
+#[derive(borsh::BorshDeserialize)]
+struct ChainInnerV0 {
+    last_check_height: guestchain::HostHeight,
+    manager: guestchain::manager::ChainManagerV0<PubKey>,
+    _unused: UnusedPubkey,
+    sig_verify_program_id: Box<Pubkey>,
+}
+
+impl From<ChainInnerV0> for ChainInner {
+    fn from(old: ChainInnerV0) -> Self {
+        Self {
+            last_check_height: old.last_check_height,
+            manager: old.manager.into(),
+            _unused: old._unused,
+            sig_verify_program_id: old.sig_verify_program_id,
+        }
+    }
+}
+
+/// The inner chain data, if initialised, serialised together with version of
+/// its layout.
+///
+/// Originally, the inner data was serialised as `Option<Box<ChainInner>>`
+/// which resulted in a zero byte if the chain wasn’t initialised and a one
+/// byte followed by the data otherwise.  Since then, [`Manager`] gained new
+/// fields which changed the layout of the data.  To be able to read data
+/// written by older versions of the program, the current layout is tagged
+/// with a two byte.  Data in the original layout is converted when read and
+/// saved in the current layout next time the account is written.
+///
+/// Future changes to the layout should introduce a new tag.
+#[derive(Clone, Default)]
+struct VersionedChainInner(Option<Box<ChainInner>>);
+
+impl VersionedChainInner {
+    const NONE: u8 = 0;
+    const V0: u8 = 1;
+    const V1: u8 = 2;
+}
+
+impl borsh::BorshSerialize for VersionedChainInner {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self.0.as_deref() {
+            None => Self::NONE.serialize(writer),
+            Some(inner) => {
+                Self::V1.serialize(writer)?;
+                inner.serialize(writer)
+            }
+        }
+    }
+}
+
+impl borsh::BorshDeserialize for VersionedChainInner {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let inner = match u8::deserialize_reader(reader)? {
+            Self::NONE => None,
+            Self::V0 => Some(ChainInnerV0::deserialize_reader(reader)?.into()),
+            Self::V1 => Some(ChainInner::deserialize_reader(reader)?),
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        };
+        Ok(Self(inner.map(Box::new)))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct UnusedPubkey;
 
@@ -414,8 +798,8 @@ impl borsh::BorshDeserialize for UnusedPubkey {
 /// blockchain the guest blockchain is running on, i.e. Solana.  However, in
 /// context of IBC protocol and code implementing it, ‘host’ refers to our side
 /// of the IBC connection, i.e. the guest blockchain.
-fn get_host_head() -> Result<(guestchain::HostHeight, NonZeroU64)> {
-    let clock = Clock::get()?;
+pub(crate) fn get_host_head() -> Result<(guestchain::HostHeight, NonZeroU64)> {
+    let clock = crate::mocks::clock()?;
     // Convert Solana Unix timestamp which is in second to timestamp guest block
     // is using which is in nanoseconds.
     let timestamp = u64::try_from(clock.unix_timestamp)
@@ -426,6 +810,28 @@ fn get_host_head() -> Result<(guestchain::HostHeight, NonZeroU64)> {
     Ok((clock.slot.into(), timestamp))
 }
 
+/// Returns snapshot of the current epoch.
+fn epoch_snapshot(manager: &Manager) -> EpochSnapshot {
+    EpochSnapshot {
+        epoch_number: manager.epoch_number(),
+        host_height: manager.epoch_height().into(),
+        epoch: manager.epoch().clone(),
+    }
+}
+
+/// Commits snapshot of the current epoch and state of the rewards ledger at
+/// its start into the trie.
+fn commit_epoch(manager: &Manager, trie: &mut storage::TrieAccount) -> Result {
+    let snapshot = epoch_snapshot(manager);
+    let key = trie_ids::TrieKey::for_epoch(snapshot.epoch_number);
+    trie.set(&key, &snapshot.calc_hash())
+        .map_err(|_| Error::Internal("Epoch"))?;
+    let key = trie_ids::TrieKey::for_rewards_ledger(snapshot.epoch_number);
+    let ledger = manager.rewards().ledger();
+    trie.set(&key, &ledger.calc_commitment())
+        .map_err(|_| Error::Internal("RewardsLedger").into())
+}
+
 impl From<ChainNotInitialised> for Error {
     fn from(_: ChainNotInitialised) -> Self { Error::ChainNotInitialised }
 }
@@ -452,7 +858,7 @@ impl From<ChainNotInitialised> for ibc::ContextError {
 
 impl core::fmt::Debug for ChainData {
     fn fmt(&self, fmtr: &mut core::fmt::Formatter) -> core::fmt::Result {
-        match &self.inner {
+        match &self.inner.0 {
             None => fmtr.write_str("None"),
             Some(inner) => (**inner).fmt(fmtr),
         }
@@ -462,3 +868,133 @@ impl core::fmt::Debug for ChainData {
 fn into_error<E: Into<Error>>(err: E) -> anchor_lang::error::Error {
     err.into().into()
 }
+
+#[cfg(test)]
+impl ChainData {
+    /// Creates chain with a single genesis validator for tests.
+    ///
+    /// Configuration requires minimum validator stake of ten and lets epoch
+    /// change with every block.
+    fn test(validator: PubKey, stake: u128) -> Self {
+        use core::num::{NonZeroU128, NonZeroU16};
+
+        let validator =
+            Validator::new(validator, NonZeroU128::new(stake).unwrap());
+        let epoch = Epoch::new(vec![validator], NonZeroU128::MIN).unwrap();
+        let genesis = Block::generate_genesis(
+            1.into(),
+            1.into(),
+            NonZeroU64::MIN,
+            CryptoHash::default(),
+            epoch,
+        )
+        .unwrap();
+        let config = Config {
+            min_validators: NonZeroU16::MIN,
+            max_validators: NonZeroU16::new(3).unwrap(),
+            min_validator_stake: NonZeroU128::new(10).unwrap(),
+            min_total_stake: NonZeroU128::MIN,
+            min_quorum_stake: NonZeroU128::MIN,
+            min_block_length: 1.into(),
+            max_block_age_ns: u64::MAX,
+            min_epoch_length: 1.into(),
+        };
+        let inner = ChainInner {
+            last_check_height: 1.into(),
+            manager: Manager::new(config, genesis).unwrap(),
+            _unused: UnusedPubkey,
+            sig_verify_program_id: Box::new(Pubkey::default()),
+        };
+        Self { inner: VersionedChainInner(Some(Box::new(inner))) }
+    }
+
+    /// Generates a new block at given host height.
+    fn test_generate_block(&mut self, host_height: u64) {
+        let timestamp = NonZeroU64::new(host_height).unwrap();
+        self.get_mut()
+            .unwrap()
+            .manager
+            .generate_next(host_height.into(), timestamp, CryptoHash::default())
+            .unwrap();
+    }
+
+    /// Finalises pending block with signatures of current validators.
+    fn test_sign_block(&mut self) {
+        struct AnySignature;
+        impl guestchain::Verifier<PubKey> for AnySignature {
+            fn verify(&self, _: &[u8], _: &PubKey, _: &Signature) -> bool {
+                true
+            }
+        }
+
+        let manager = &mut self.get_mut().unwrap().manager;
+        for validator in manager.validators().to_vec() {
+            let signature = Signature::from([0; 64]);
+            let effect = manager
+                .add_signature(
+                    validator.pubkey().clone(),
+                    &signature,
+                    &AnySignature,
+                )
+                .unwrap();
+            if effect.got_quorum() {
+                return;
+            }
+        }
+        panic!("Block hasn’t been finalised");
+    }
+}
+
+#[test]
+fn test_candidate_bond() {
+    let genesis_validator = PubKey::from([1; 32]);
+    let candidate = Pubkey::new_from_array([2; 32]);
+    let mut chain = ChainData::test(genesis_validator, 10);
+
+    // Bond below minimum stake is rejected.
+    assert!(chain.queue_stake_change(candidate.into(), 5).is_err());
+
+    // register_candidate
+    let activation_epoch =
+        chain.queue_stake_change(candidate.into(), 10).unwrap();
+    assert_eq!(1, activation_epoch);
+    let mut bond = storage::CandidateBond {
+        validator: candidate,
+        amount: 10,
+        unbonding_epoch: None,
+    };
+    assert!(!bond.is_unlocked(&chain).unwrap());
+
+    chain.test_generate_block(2);
+    chain.test_sign_block();
+    assert_eq!(1, chain.epoch_number().unwrap());
+    let validators = chain.get().unwrap().manager.validators();
+    assert!(validators.iter().any(|v| *v.pubkey() == candidate));
+    assert!(!bond.is_unlocked(&chain).unwrap());
+
+    // unbond_candidate while a block committing to a new epoch is pending.
+    // The new epoch’s validators set has already been chosen so removal of
+    // the stake takes effect only in the epoch after.
+    chain.queue_stake_change(genesis_validator.clone(), 10).unwrap();
+    chain.test_generate_block(3);
+    assert!(chain.get().unwrap().manager.pending_epoch().is_some());
+    let activation_epoch =
+        chain.queue_stake_change(candidate.into(), -10).unwrap();
+    assert_eq!(3, activation_epoch);
+    bond.unbonding_epoch = Some(activation_epoch);
+    assert!(!bond.is_unlocked(&chain).unwrap());
+
+    chain.test_sign_block();
+    assert_eq!(2, chain.epoch_number().unwrap());
+    let validators = chain.get().unwrap().manager.validators();
+    assert!(validators.iter().any(|v| *v.pubkey() == candidate));
+    assert!(!bond.is_unlocked(&chain).unwrap());
+
+    // withdraw_candidate_bond
+    chain.test_generate_block(4);
+    chain.test_sign_block();
+    assert_eq!(3, chain.epoch_number().unwrap());
+    let validators = chain.get().unwrap().manager.validators();
+    assert!(!validators.iter().any(|v| *v.pubkey() == candidate));
+    assert!(bond.is_unlocked(&chain).unwrap());
+}
@@ -1,7 +1,5 @@
 use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::borsh::maybestd::io;
-use anchor_lang::solana_program::sysvar::clock::Clock;
-use anchor_lang::solana_program::sysvar::Sysvar;
 
 use crate::consensus_state::AnyConsensusState;
 use crate::ibc;
@@ -144,6 +142,23 @@ impl AnyClientState {
             }
         }
     }
+
+    /// Returns client’s trusting period in nanoseconds.
+    ///
+    /// Returns `None` for clients which don’t have a trusting period known to
+    /// us (i.e. WASM and mock clients).
+    pub fn trusting_period_ns(&self) -> Option<u64> {
+        match self {
+            Self::Tendermint(state) => {
+                u64::try_from(state.inner().trusting_period.as_nanos()).ok()
+            }
+            Self::Wasm(_) => None,
+            Self::Rollup(state) => Some(state.trusting_period_ns),
+            Self::Guest(state) => Some(state.trusting_period_ns),
+            #[cfg(any(test, feature = "mocks"))]
+            Self::Mock(_) => None,
+        }
+    }
 }
 
 impl From<ibc::tm::types::ClientState> for AnyClientState {
@@ -230,8 +245,11 @@ impl ibc::tm::CommonContext for IbcStorage<'_, '_> {
     }
 }
 
-impl cf_guest::CommonContext<sigverify::ed25519::PubKey>
-    for IbcStorage<'_, '_>
+impl
+    cf_client_core::CommonContext<
+        cf_guest::ClientState<sigverify::ed25519::PubKey>,
+        cf_guest::ConsensusState,
+    > for IbcStorage<'_, '_>
 {
     type ConversionError = &'static str;
     type AnyClientState = AnyClientState;
@@ -239,7 +257,7 @@ impl cf_guest::CommonContext<sigverify::ed25519::PubKey>
 
     fn host_metadata(&self) -> Result<(ibc::Timestamp, ibc::Height)> {
         let (timestamp_ns, height) = if cfg!(feature = "witness") {
-            let clock = Clock::get().map_err(|e| {
+            let clock = crate::mocks::clock().map_err(|e| {
                 ibc::ClientError::ClientSpecific { description: e.to_string() }
             })?;
             (clock.unix_timestamp as u64 * 10u64.pow(9), clock.slot)
@@ -275,7 +293,7 @@ impl cf_guest::CommonContext<sigverify::ed25519::PubKey>
         &self,
         client_id: &ibc::ClientId,
         height: ibc::Height,
-    ) -> Result<cf_guest::Neighbourhood<Self::AnyConsensusState>> {
+    ) -> Result<cf_client_core::Neighbourhood<Self::AnyConsensusState>> {
         use core::cmp::Ordering;
 
         let height = (height.revision_number(), height.revision_height());
@@ -290,7 +308,9 @@ impl cf_guest::CommonContext<sigverify::ed25519::PubKey>
                 Ordering::Less if key >= prev.0 => prev = (key, Some(value)),
                 Ordering::Greater if key <= next.0 => next = (key, Some(value)),
                 Ordering::Equal => {
-                    return value.state().map(cf_guest::Neighbourhood::This)
+                    return value
+                        .state()
+                        .map(cf_client_core::Neighbourhood::This)
                 }
                 _ => (),
             }
@@ -298,7 +318,7 @@ impl cf_guest::CommonContext<sigverify::ed25519::PubKey>
 
         let prev = prev.1.map(|state| state.state()).transpose()?;
         let next = next.1.map(|state| state.state()).transpose()?;
-        Ok(cf_guest::Neighbourhood::Neighbours(prev, next))
+        Ok(cf_client_core::Neighbourhood::Neighbours(prev, next))
     }
 
     fn store_consensus_state_and_metadata(
@@ -353,7 +373,12 @@ impl guestchain::Verifier<sigverify::ed25519::PubKey> for IbcStorage<'_, '_> {
     }
 }
 
-impl cf_solana::CommonContext for IbcStorage<'_, '_> {
+impl
+    cf_client_core::CommonContext<
+        cf_solana::ClientState,
+        cf_solana::ConsensusState,
+    > for IbcStorage<'_, '_>
+{
     type ConversionError = &'static str;
     type AnyClientState = AnyClientState;
     type AnyConsensusState = AnyConsensusState;
@@ -391,7 +416,7 @@ impl cf_solana::CommonContext for IbcStorage<'_, '_> {
         &self,
         client_id: &ibc::ClientId,
         height: ibc::Height,
-    ) -> Result<cf_guest::Neighbourhood<Self::AnyConsensusState>> {
+    ) -> Result<cf_client_core::Neighbourhood<Self::AnyConsensusState>> {
         use core::cmp::Ordering;
 
         let height = (height.revision_number(), height.revision_height());
@@ -406,7 +431,9 @@ impl cf_solana::CommonContext for IbcStorage<'_, '_> {
                 Ordering::Less if key >= prev.0 => prev = (key, Some(value)),
                 Ordering::Greater if key <= next.0 => next = (key, Some(value)),
                 Ordering::Equal => {
-                    return value.state().map(cf_guest::Neighbourhood::This)
+                    return value
+                        .state()
+                        .map(cf_client_core::Neighbourhood::This)
                 }
                 _ => (),
             }
@@ -414,7 +441,7 @@ impl cf_solana::CommonContext for IbcStorage<'_, '_> {
 
         let prev = prev.1.map(|state| state.state()).transpose()?;
         let next = next.1.map(|state| state.state()).transpose()?;
-        Ok(cf_guest::Neighbourhood::Neighbours(prev, next))
+        Ok(cf_client_core::Neighbourhood::Neighbours(prev, next))
     }
 
     fn store_consensus_state_and_metadata(
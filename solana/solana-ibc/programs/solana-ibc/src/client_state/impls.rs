@@ -7,8 +7,8 @@
 use anchor_lang::solana_program;
 
 use super::AnyClientState;
-use crate::ibc;
 use crate::storage::IbcStorage;
+use crate::{events, ibc};
 
 type Result<T = (), E = ibc::ClientError> = core::result::Result<T, E>;
 
@@ -222,12 +222,36 @@ impl<'a, 'b> ibc::ClientStateExecution<IbcStorage<'a, 'b>> for AnyClientState {
         client_id: &ibc::ClientId,
         header: Option<ibc_client_tendermint_types::Header>,
     ) -> Result<Vec<ibc::Height>>);
-    delegate!(fn update_state_on_misbehaviour(
+
+    /// Freezes the client and emits [`events::ClientMisbehaviour`] event.
+    fn update_state_on_misbehaviour(
         &self,
         ctx: &mut IbcStorage<'a, 'b>,
         client_id: &ibc::ClientId,
         client_message: ibc::Any,
-    ) -> Result);
+    ) -> Result {
+        match self {
+            AnyClientState::Tendermint(cs) => {
+                cs.update_state_on_misbehaviour(ctx, client_id, client_message)
+            }
+            AnyClientState::Wasm(_) => unimplemented!(),
+            AnyClientState::Rollup(cs) => {
+                cs.update_state_on_misbehaviour(ctx, client_id, client_message)
+            }
+            AnyClientState::Guest(cs) => {
+                cs.update_state_on_misbehaviour(ctx, client_id, client_message)
+            }
+            #[cfg(any(test, feature = "mocks"))]
+            AnyClientState::Mock(cs) => {
+                cs.update_state_on_misbehaviour(ctx, client_id, client_message)
+            }
+        }?;
+        events::emit(events::ClientMisbehaviour {
+            client_id: events::client_id(client_id),
+        })
+        .map_err(|description| ibc::ClientError::Other { description })
+    }
+
     delegate!(fn update_state_on_upgrade(
         &self,
         ctx: &mut IbcStorage<'a, 'b>,
@@ -398,3 +422,141 @@ fn test_merkle_hash() {
     let two = Sha256::digest(b"bar");
     assert_eq!(theirs.inner_hash(one, two), ours.inner_hash(one, two));
 }
+
+/// Tests that submitting misbehaviour of a guest client, i.e. two conflicting
+/// headers at the same height, freezes the client and emits
+/// [`events::ClientMisbehaviour`] event.
+#[test]
+fn test_guest_misbehaviour_freezes_client() {
+    use core::cell::RefCell;
+    use core::num::{NonZeroU128, NonZeroU64};
+
+    use anchor_lang::prelude::borsh::BorshDeserialize;
+    use anchor_lang::prelude::{AccountInfo, Pubkey};
+    use ibc::ClientStateExecution;
+    use lib::hash::CryptoHash;
+    use sigverify::ed25519::PubKey;
+
+    std::thread_local! {
+        static LOGGED: RefCell<Vec<Vec<u8>>> = RefCell::new(Vec::new());
+    }
+
+    struct Stubs;
+
+    impl solana_program::program_stubs::SyscallStubs for Stubs {
+        fn sol_log(&self, _message: &str) {}
+
+        fn sol_log_data(&self, fields: &[&[u8]]) {
+            LOGGED.with(|logged| {
+                logged.borrow_mut().extend(fields.iter().map(|f| f.to_vec()))
+            });
+        }
+    }
+
+    let epoch = guestchain::Epoch::new(
+        vec![guestchain::Validator::new(
+            PubKey::from([1; 32]),
+            NonZeroU128::MIN,
+        )],
+        NonZeroU128::MIN,
+    )
+    .unwrap();
+    let genesis = guestchain::BlockHeader::generate_genesis(
+        1.into(),
+        1.into(),
+        NonZeroU64::MIN,
+        CryptoHash::test(1),
+        epoch.calc_commitment(),
+    );
+    let header = |state_root| {
+        let block = genesis
+            .generate_next::<PubKey>(
+                2.into(),
+                NonZeroU64::new(2).unwrap(),
+                CryptoHash::test(state_root),
+                None,
+            )
+            .unwrap();
+        cf_guest::Header::new(
+            genesis.calc_hash(),
+            block.header,
+            epoch.clone(),
+            Vec::new(),
+        )
+    };
+    let misbehaviour =
+        cf_guest::Misbehaviour { header1: header(2), header2: header(3) };
+
+    let key = Pubkey::new_unique();
+    let mut lamports = 1;
+    let mut data = vec![0u8; 10 * 1024];
+    let trie = AccountInfo::new(
+        &key,
+        false,
+        true,
+        &mut lamports,
+        &mut data,
+        &crate::ID,
+        false,
+        0,
+    );
+    let payer_key = Pubkey::new_unique();
+    let mut payer_lamports = 0;
+    let mut payer_data: [u8; 0] = [];
+    let payer = AccountInfo::new(
+        &payer_key,
+        true,
+        true,
+        &mut payer_lamports,
+        &mut payer_data,
+        &solana_program::system_program::ID,
+        false,
+        0,
+    );
+
+    let mut private = crate::storage::PrivateStorage::default();
+    let mut chain = crate::chain::ChainData::default();
+    let mut ctx = IbcStorage::new(crate::storage::IbcStorageInner {
+        private: &mut private,
+        provable: crate::storage::TrieAccount::from_account_with_payer(
+            &trie,
+            &crate::ID,
+            &payer,
+        )
+        .unwrap(),
+        accounts: Default::default(),
+        chain: &mut chain,
+        hook_error: None,
+    });
+
+    let client_id = ibc::ClientId::new("cf-guest", 0).unwrap();
+    let state =
+        AnyClientState::from(cf_guest::ClientState::from_genesis(&genesis));
+    ctx.store_client_state_impl(&client_id, state.clone()).unwrap();
+
+    let prev =
+        solana_program::program_stubs::set_syscall_stubs(Box::new(Stubs));
+    let res = state.update_state_on_misbehaviour(
+        &mut ctx,
+        &client_id,
+        ibc::Any::from(misbehaviour),
+    );
+    solana_program::program_stubs::set_syscall_stubs(prev);
+    res.unwrap();
+
+    let state =
+        ctx.borrow().private.client(&client_id).unwrap().client_state.get();
+    match state.unwrap() {
+        AnyClientState::Guest(state) => assert!(state.is_frozen),
+        state => panic!("Unexpected client state: {state:?}"),
+    }
+
+    let want = events::Event::from(events::ClientMisbehaviour {
+        client_id: events::client_id(&client_id),
+    });
+    let logged = LOGGED.with(|logged| logged.take());
+    assert!(logged.iter().any(|data| {
+        events::Event::try_from_slice(data.as_slice()).ok().as_ref() ==
+            Some(&want)
+    }));
+}
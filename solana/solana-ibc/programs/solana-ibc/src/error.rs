@@ -129,6 +129,99 @@ pub enum Error {
     /// blocks would never get finalized until more stake is added and quorum
     /// stake is less than head stake.
     MinQuorumStakeHigherThanTotalStake,
+
+    /// Candidate with given public key already exists.
+    ValidatorKeyInUse,
+
+    /// The operation has been halted by the fee collector.
+    Halted,
+
+    /// Light client parameters update is malformed, targets a client which
+    /// doesn’t support it or sets values outside of allowed bounds.
+    InvalidClientParams,
+
+    /// Multisig account isn’t owned by the specified multisig program.
+    InvalidMultisig,
+
+    /// Validator has no accrued block rewards to claim.
+    NoRewardsToClaim,
+
+    /// Fee account doesn’t hold enough lamports to pay out block rewards.
+    InsufficientRewardsFunds,
+
+    /// Pending claim account holds no tokens.
+    NoPendingTransfer,
+
+    /// No guest blockchain epoch has ended yet.
+    NoEpochSummary,
+
+    /// Token with given hashed denom hasn’t been registered.
+    UnknownAsset,
+
+    /// Denom metadata override is empty or doesn’t fit in Metaplex metadata.
+    InvalidDenomMetadata,
+
+    /// Token has no denom metadata override.
+    NoDenomMetadata,
+
+    /// Client has no consensus states which can be pruned.
+    NothingToPrune,
+
+    /// Transfers over the channel have been paused by the fee collector.
+    ChannelPaused,
+
+    /// Error handling of ICS-721 NFT transfer.
+    NftTransferError(crate::nft_transfer::NftTransferError),
+
+    /// Upgrade governance parameters are invalid or governance has already
+    /// been configured.
+    InvalidUpgradeGovernance,
+
+    /// Upgrade governance hasn’t been configured.
+    UpgradeGovernanceNotConfigured,
+
+    /// Signer isn’t one of upgrade approvers.
+    NotUpgradeApprover,
+
+    /// There’s no pending upgrade proposal for given buffer.
+    NoUpgradeProposal,
+
+    /// Upgrade proposal doesn’t have enough approvals yet.
+    InsufficientUpgradeApprovals,
+
+    /// Upgrade timelock hasn’t passed yet.
+    UpgradeTimelockActive,
+
+    /// Fee schedule is invalid; basis points exceed 100% or the minimum is
+    /// greater than the maximum.
+    InvalidFeeSchedule,
+
+    /// SlotHashes sysvar account is empty or malformed.
+    InvalidSlotHashes,
+
+    /// The program’s address lookup table has already been created.
+    LookupTableAlreadyCreated,
+
+    /// Lookup table account doesn’t match the program’s lookup table.
+    InvalidLookupTable,
+
+    /// Transfer would exceed token’s rate limit quota.
+    RateLimitExceeded,
+
+    /// Rate limit configuration is invalid; the window is empty or basis
+    /// points exceed 100%.
+    InvalidRateLimit,
+
+    /// Candidate’s bond is already being unbonded.
+    CandidateBondUnbonding,
+
+    /// Candidate’s bond can’t be withdrawn because it hasn’t been unbonded or
+    /// unbonding hasn’t taken effect yet.
+    CandidateBondLocked,
+
+    /// Account isn’t a program buffer whose authority is the upgrade
+    /// authority PDA or its contents changed since the upgrade was proposed.
+    InvalidUpgradeBuffer,
 }
 
 impl Error {
@@ -145,6 +238,7 @@ impl core::fmt::Display for Error {
             Self::Internal(msg) => fmtr.write_str(msg.as_ref()),
             Self::ContextError(err) => err.fmt(fmtr),
             Self::TokenTransferError(err) => err.fmt(fmtr),
+            Self::NftTransferError(err) => err.fmt(fmtr),
             err => fmtr.write_str(&err.name()),
         }
     }
@@ -216,6 +310,16 @@ impl From<manager::UpdateCandidateError> for Error {
     }
 }
 
+impl From<manager::RotateCandidateError> for Error {
+    fn from(err: manager::RotateCandidateError) -> Self {
+        use manager::RotateCandidateError as Err;
+        match err {
+            Err::UnknownCandidate => Self::CandidateNotFound,
+            Err::KeyInUse => Self::ValidatorKeyInUse,
+        }
+    }
+}
+
 impl From<ibc::ClientError> for Error {
     #[inline]
     fn from(err: ibc::ClientError) -> Self {
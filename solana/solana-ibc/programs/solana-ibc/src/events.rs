@@ -1,4 +1,4 @@
-use anchor_lang::prelude::borsh;
+use anchor_lang::prelude::{borsh, Pubkey};
 use anchor_lang::solana_program;
 use lib::hash::CryptoHash;
 
@@ -23,6 +23,25 @@ pub enum Event<'a> {
     BlockSigned(BlockSigned),
     BlockFinalised(BlockFinalised),
     ClientStateUpdate(ClientStateUpdate<'a>),
+    MinTransferAmountUpdate(MinTransferAmountUpdate),
+    AcknowledgementProof(AcknowledgementProof),
+    ClientMisbehaviour(ClientMisbehaviour<'a>),
+    FeeExemptionUsed(FeeExemptionUsed),
+    ValidatorKeyRotated(ValidatorKeyRotated),
+    HaltFlagsUpdate(HaltFlagsUpdate),
+    ClientParamsUpdate(ClientParamsUpdate<'a>),
+    BlockRewardsClaimed(BlockRewardsClaimed),
+    DenomMetadataUpdate(DenomMetadataUpdate),
+    ChannelPauseUpdate(ChannelPauseUpdate),
+    StakeChangeQueued(StakeChangeQueued),
+    UpgradeProposed(UpgradeProposed),
+    UpgradeApproved(UpgradeApproved),
+    UpgradeExecuted(UpgradeExecuted),
+    FingerprintDomainUpdate(FingerprintDomainUpdate),
+    SequenceGap(SequenceGap),
+    CandidateBonded(CandidateBonded),
+    CandidateUnbonding(CandidateUnbonding),
+    CandidateBondWithdrawn(CandidateBondWithdrawn),
 }
 
 /// Event emitted once blockchain is implemented.
@@ -135,6 +154,409 @@ pub struct ClientStateUpdate<'a> {
     pub state: alloc::borrow::Cow<'a, [u8]>,
 }
 
+/// Event emitted when minimum transfer amount of a token changes.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct MinTransferAmountUpdate {
+    /// Hash of the full denom of the token.
+    pub hashed_full_denom: CryptoHash,
+
+    /// New minimum amount; zero if there is no minimum.
+    pub amount: u64,
+}
+
+/// Event emitted after a packet acknowledgement is written.
+///
+/// Only emitted when the program is built with `ack-proof` feature.  The
+/// proof is generated for the trie root at the end of the `deliver`
+/// instruction (which is the root recorded in the local consensus state) so
+/// relayers can construct `MsgAcknowledgement` for the counterparty without
+/// querying for the proof separately.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct AcknowledgementProof {
+    /// Port on which the packet was received.
+    pub port_id: ibc::PortId,
+
+    /// Channel on which the packet was received.
+    pub channel_id: ibc::ChannelId,
+
+    /// Sequence number of the packet.
+    pub sequence: u64,
+
+    /// Trie root the proof is for.
+    pub root: CryptoHash,
+
+    /// Serialised proof of the acknowledgement commitment as accepted by
+    /// the rollup light client.
+    pub proof: Vec<u8>,
+}
+
+/// Event emitted when misbehaviour has been submitted for a client and the
+/// client has been frozen as a result.
+///
+/// A [`ClientStateUpdate`] event with the frozen state is emitted as well.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct ClientMisbehaviour<'a> {
+    /// Client identifier which got frozen.
+    pub client_id: CowClientId<'a>,
+}
+
+/// Event emitted when a transfer is sent without charging the fee because
+/// the sender is on the fee exemption list.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct FeeExemptionUsed {
+    /// The exempted sender of the transfer.
+    pub sender: Pubkey,
+
+    /// Fee which would have been charged otherwise.
+    pub fee_in_lamports: u64,
+}
+
+/// Event emitted when validator candidate’s key has been rotated.
+///
+/// The new key becomes part of the validators set on next epoch change.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct ValidatorKeyRotated {
+    /// Key the candidate has been using so far.
+    pub old: crate::chain::PubKey,
+
+    /// Key the candidate is now using.
+    pub new: crate::chain::PubKey,
+}
+
+/// Event emitted when halt flags are changed.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct HaltFlagsUpdate {
+    /// New state of the flags.
+    pub flags: crate::storage::HaltFlags,
+}
+
+/// Event emitted when parameters of a light client are changed by the fee
+/// collector.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct ClientParamsUpdate<'a> {
+    /// Client identifier whose parameters changed.
+    pub client_id: CowClientId<'a>,
+    /// Trusting period before the change.
+    pub old_trusting_period_ns: u64,
+    /// Trusting period after the change.
+    pub new_trusting_period_ns: u64,
+}
+
+/// Event emitted when a validator claims accrued block rewards.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct BlockRewardsClaimed {
+    /// Validator who claimed the rewards.
+    pub validator: crate::chain::PubKey,
+
+    /// Amount of lamports paid out to the validator.
+    pub amount: u64,
+}
+
+/// Event emitted when the fee collector sets or removes display metadata
+/// override of a wrapped token.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct DenomMetadataUpdate {
+    /// Hash of the full denom of the token.
+    pub hashed_full_denom: CryptoHash,
+
+    /// New override; `None` if the override has been removed.
+    pub metadata: Option<crate::storage::DenomMetadata>,
+}
+
+/// Event emitted when the fee collector changes pause flags of a channel.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct ChannelPauseUpdate {
+    /// Port of the channel.
+    pub port_id: ibc::PortId,
+
+    /// Channel whose flags changed.
+    pub channel_id: ibc::ChannelId,
+
+    /// New state of the flags.
+    pub pause: crate::storage::ChannelPause,
+}
+
+/// Event emitted when a change of validator candidate’s stake is queued.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct StakeChangeQueued {
+    /// Validator candidate whose stake changes.
+    pub validator: crate::chain::PubKey,
+
+    /// Amount added to (if positive) or removed from (if negative) the stake.
+    pub delta: i128,
+
+    /// Number of the first guest epoch whose validators set includes the
+    /// change.
+    pub activation_epoch: u64,
+}
+
+/// Event emitted when a program upgrade is proposed.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct UpgradeProposed {
+    /// Approver who proposed the upgrade.
+    pub proposer: Pubkey,
+
+    /// Buffer account holding the new program.
+    pub buffer: Pubkey,
+
+    /// Hash of the new program held in the buffer.
+    pub buffer_hash: CryptoHash,
+
+    /// Unix timestamp after which the upgrade can be executed provided it
+    /// gathers enough approvals.
+    pub executable_at: i64,
+}
+
+/// Event emitted when a pending program upgrade is approved.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct UpgradeApproved {
+    /// Approver who approved the upgrade.
+    pub approver: Pubkey,
+
+    /// Buffer account holding the new program.
+    pub buffer: Pubkey,
+
+    /// Number of approvals the proposal has.
+    pub approvals: u8,
+}
+
+/// Event emitted when the program is upgraded.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct UpgradeExecuted {
+    /// Buffer account the new program has been loaded from.
+    pub buffer: Pubkey,
+}
+
+/// Event emitted when the guest blockchain switches to version one block
+/// fingerprints.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct FingerprintDomainUpdate {
+    /// Domain mixed into fingerprints of new blocks.  Light clients of the
+    /// chain need to be configured with it.
+    pub domain: CryptoHash,
+
+    /// Host slot whose hash has been mixed into the domain.
+    pub slot: u64,
+}
+
+/// Event emitted when a delivered packet skips over sequences which haven’t
+/// been processed yet.
+///
+/// This usually means relayer transactions carrying the missing packets got
+/// lost.  The gap is reported once; missing packets delivered later don’t
+/// emit further events.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct SequenceGap {
+    /// Port of the channel the gap was detected on.
+    pub port_id: ibc::PortId,
+
+    /// Channel the gap was detected on.
+    pub channel_id: ibc::ChannelId,
+
+    /// Whether the gap is in received packets or in acknowledgements (and
+    /// timeouts) of sent packets.
+    pub kind: crate::storage::SeenSequenceKind,
+
+    /// First missing sequence.
+    pub first_missing: u64,
+
+    /// Last missing sequence (inclusive).
+    pub last_missing: u64,
+}
+
+/// Event emitted when a validator candidate registers by bonding SOL with
+/// `register_candidate` instruction.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct CandidateBonded {
+    /// The candidate.
+    pub validator: Pubkey,
+
+    /// Bonded amount in lamports.
+    pub amount: u64,
+
+    /// Number of the first guest epoch whose validators set includes the
+    /// candidate.
+    pub activation_epoch: u64,
+}
+
+/// Event emitted when a validator candidate starts unbonding SOL with
+/// `unbond_candidate` instruction.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct CandidateUnbonding {
+    /// The candidate.
+    pub validator: Pubkey,
+
+    /// Unbonded amount in lamports.
+    pub amount: u64,
+
+    /// Number of the first guest epoch whose validators set doesn’t include
+    /// the bonded stake.
+    pub activation_epoch: u64,
+}
+
+/// Event emitted when a validator candidate withdraws unbonded SOL with
+/// `withdraw_candidate_bond` instruction.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    derive_more::From,
+)]
+pub struct CandidateBondWithdrawn {
+    /// The candidate.
+    pub validator: Pubkey,
+
+    /// Withdrawn amount in lamports (not including rent of the bond account).
+    pub amount: u64,
+}
+
 impl Event<'_> {
     pub fn emit(&self) -> Result<(), String> {
         borsh::BorshSerialize::try_to_vec(self)
@@ -116,6 +116,38 @@ impl IbcStorage<'_, '_> {
         let key = trie_ids::TrieKey::for_consensus_state(client.index, height);
         store.provable.del(&key).map(|_| ()).map_err(client_error)
     }
+
+    /// Removes consensus states of given client which have been stored longer
+    /// than client’s trusting period ago.
+    ///
+    /// [`storage::MIN_CONSENSUS_STATES`] most recent states are always kept.
+    /// Age of a state is determined by host time at which it has been stored
+    /// which is never earlier than consensus state’s own timestamp.  Does
+    /// nothing if the client has no trusting period.  Returns number of
+    /// removed states.
+    pub(crate) fn prune_consensus_states_impl(
+        &mut self,
+        client_id: &ibc::ClientId,
+    ) -> Result<usize, ibc::ClientError> {
+        msg!("prune_consensus_states({})", client_id);
+        let mut store = self.borrow_mut();
+        let now_ns = store.chain.head()?.timestamp_ns.get();
+        let mut client = store.private.client_mut(client_id, false)?;
+        let trusting_period_ns =
+            match client.client_state.get()?.trusting_period_ns() {
+                Some(period) => period,
+                None => return Ok(0),
+            };
+        let cutoff_ns = now_ns.saturating_sub(trusting_period_ns);
+        let index = client.index;
+        let heights = client
+            .prune_consensus_states(cutoff_ns, storage::MIN_CONSENSUS_STATES);
+        for height in heights.iter() {
+            let key = trie_ids::TrieKey::for_consensus_state(index, *height);
+            store.provable.del(&key).map_err(client_error)?;
+        }
+        Ok(heights.len())
+    }
 }
 
 
@@ -325,6 +357,46 @@ impl storage::IbcStorage<'_, '_> {
         store.provable.set(&key, &hash).map_err(client_error)
     }
 
+    /// Returns commitment of auxiliary state of IBC application bound to given
+    /// port stored under given key.
+    ///
+    /// Applications can use it to keep custom state which counterparty can
+    /// verify with membership proofs.  Trie only holds hash of the value; the
+    /// value itself must be stored by the application.  See
+    /// [`trie_ids::TrieKey::for_app_state`] for format of the trie key.
+    pub fn app_state(
+        &self,
+        port_id: &ibc::PortId,
+        key: &[u8],
+    ) -> Result<Option<CryptoHash>> {
+        let key = app_state_key(port_id, key)?;
+        self.borrow().provable.get(&key).map_err(ctx_error)
+    }
+
+    /// Stores commitment of auxiliary state of IBC application bound to given
+    /// port under given key.
+    ///
+    /// The hash stored in the trie is `CryptoHash::digest(value)`.
+    pub fn store_app_state(
+        &mut self,
+        port_id: &ibc::PortId,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result {
+        let key = app_state_key(port_id, key)?;
+        self.store_commitment(key, CryptoHash::digest(value).as_slice())
+    }
+
+    /// Deletes auxiliary state of IBC application bound to given port stored
+    /// under given key.
+    pub fn delete_app_state(
+        &mut self,
+        port_id: &ibc::PortId,
+        key: &[u8],
+    ) -> Result {
+        self.delete_commitment(app_state_key(port_id, key)?)
+    }
+
     fn store_commitment(
         &mut self,
         key: trie_ids::TrieKey,
@@ -363,6 +435,17 @@ impl storage::IbcStorage<'_, '_> {
     }
 }
 
+/// Returns trie key for auxiliary state of application bound to given port.
+fn app_state_key(
+    port_id: &ibc::PortId,
+    key: &[u8],
+) -> Result<trie_ids::TrieKey, ibc::ContextError> {
+    let port_key = trie_ids::PortKey::try_from(port_id)
+        .map_err(|_| ctx_error(format!("unsupported port id: {port_id}")))?;
+    let key_hash = CryptoHash::digest(key);
+    Ok(trie_ids::TrieKey::for_app_state(&port_key, key_hash.as_array()))
+}
+
 fn client_error(description: impl ToString) -> ibc::ClientError {
     ibc::ClientError::Other { description: description.to_string() }
 }
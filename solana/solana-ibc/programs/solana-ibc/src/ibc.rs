@@ -5,14 +5,16 @@ pub use ibc::apps::transfer::types::error::TokenTransferError;
 pub use ibc::apps::transfer::types::msgs::transfer::MsgTransfer;
 pub use ibc::core::channel::context::SendPacketValidationContext;
 pub use ibc::core::channel::types::acknowledgement::{
-    Acknowledgement, AcknowledgementStatus,
+    Acknowledgement, AcknowledgementStatus, StatusValue,
 };
 pub use ibc::core::channel::types::channel::ChannelEnd;
 pub use ibc::core::channel::types::commitment::{
     AcknowledgementCommitment, PacketCommitment,
 };
 pub use ibc::core::channel::types::error::{ChannelError, PacketError};
-pub use ibc::core::channel::types::msgs::{MsgRecvPacket, PacketMsg};
+pub use ibc::core::channel::types::msgs::{
+    MsgRecvPacket, MsgTimeout, PacketMsg,
+};
 pub use ibc::core::channel::types::packet::{Packet, Receipt};
 pub use ibc::core::channel::types::timeout::TimeoutHeight;
 pub use ibc::core::channel::types::Version;
@@ -11,9 +11,12 @@
 //! needs to be called with an empty instruction data and additional account
 //! (passed as the last account) whose data is interpreted as the instruction.
 //!
-//! The account data must be a length-prefixed slice of bytes.  In other words,
-//! borsh-serialised `Vec<u8>`.  The account may contain trailing bytes which
-//! are ignored.
+//! The account data must be a length-prefixed slice of bytes (in other words,
+//! borsh-serialised `Vec<u8>`) followed by SHA-256 hash of it (including the
+//! length prefix).  The hash is stored by write-account program’s Commit
+//! operation once it verifies that the data has been uploaded correctly and
+//! accounts without a matching hash are rejected.  The account may contain
+//! trailing bytes which are ignored.
 //!
 //! This module provides types to help use this feature of the Solana IBC
 //! contract.  [`Accounts`] is used to add the account with instruction data to
@@ -43,7 +46,7 @@
 //! let instruction_len = instruction_data.len() as u32;
 //! instruction_data.splice(..0, instruction_len.to_le_bytes());
 //!
-//! /* ... write instruction_data to account ix_data_account ... */
+//! /* ... write instruction_data to account ix_data_account and commit it ... */
 //!
 //! program
 //!     .request()
@@ -56,6 +59,11 @@
 //!     .signer(&*payer)
 //!     .send_with_spinner_and_config(/* ... */)?;
 //! ```
+//!
+//! With `client` feature enabled, [`ChunkedInstruction`] automates the above:
+//! it splits the instruction data into write-account program calls, builds
+//! the final call and frees the write account afterwards so that the rent is
+//! refunded to the payer.
 use anchor_lang::prelude::borsh;
 use anchor_lang::solana_program;
 use borsh::maybestd::io;
@@ -100,17 +108,30 @@ impl<T: anchor_lang::ToAccountMetas> anchor_lang::ToAccountMetas
 }
 
 /// Interprets data in the last account as instruction data.
+///
+/// Fails with `InvalidAccountData` if the data hasn’t been verified, i.e. if
+/// it isn’t followed by its SHA-256 hash.
 #[allow(dead_code)]
 pub(crate) fn get_ix_data<'a>(
     accounts: &mut Vec<AccountInfo<'a>>,
 ) -> Result<&'a [u8], ProgramError> {
     let account = accounts.pop().ok_or(ProgramError::NotEnoughAccountKeys)?;
     let data = alloc::rc::Rc::try_unwrap(account.data).ok().unwrap();
-    let (len, data) = stdx::split_at::<4, _>(data.into_inner())
+    let data: &'a [u8] = data.into_inner();
+    let (len, rest) = stdx::split_at::<4, _>(data)
         .ok_or(ProgramError::InvalidInstructionData)?;
     let len = usize::try_from(u32::from_le_bytes(*len))
         .map_err(|_| ProgramError::ArithmeticOverflow)?;
-    data.get(..len).ok_or(ProgramError::InvalidInstructionData)
+    let ix_data =
+        rest.get(..len).ok_or(ProgramError::InvalidInstructionData)?;
+    let hash = rest
+        .get(len..)
+        .and_then(|tail| tail.get(..solana_program::hash::HASH_BYTES))
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if solana_program::hash::hash(&data[..len + 4]).as_ref() != hash {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(ix_data)
 }
 
 impl anchor_lang::Discriminator for Instruction {
@@ -129,6 +150,133 @@ impl anchor_lang::InstructionData for Instruction {
     fn data(&self) -> Vec<u8> { Vec::new() }
 }
 
+/// Instructions calling the smart contract with instruction data stored in
+/// a write account.
+///
+/// Use [`ChunkedInstruction::new`] to convert an arbitrary instruction or
+/// [`ChunkedInstruction::deliver`] to build a `deliver` call for given IBC
+/// message.  Instructions in [`Self::writes`] must be sent first, each in its
+/// own transaction, and once they are confirmed all instructions in
+/// [`Self::call`] must be sent in a single transaction.
+#[cfg(feature = "client")]
+pub struct ChunkedInstruction {
+    /// Instructions filling the write account with the instruction data.
+    ///
+    /// Each instruction must be sent in a separate transaction signed by the
+    /// payer.  The instructions are idempotent so a failed one can be simply
+    /// retried.
+    pub writes: Vec<solana_program::instruction::Instruction>,
+
+    /// Instructions to be sent in a single transaction once the write account
+    /// is filled.
+    ///
+    /// The first instruction verifies that the write account holds the
+    /// expected data, the second is the actual call and the last one frees the
+    /// write account returning its rent to the payer.  Caller may prepend
+    /// compute budget instructions as needed.
+    pub call: Vec<solana_program::instruction::Instruction>,
+
+    /// Instruction freeing the write account.
+    ///
+    /// It’s already part of [`Self::call`] but may be used on its own to
+    /// recover the rent if the call can’t be made.
+    pub free: solana_program::instruction::Instruction,
+
+    /// Address of the write account holding the instruction data.
+    pub write_account: Pubkey,
+}
+
+#[cfg(feature = "client")]
+impl ChunkedInstruction {
+    /// Converts `instruction` into a chunked call.
+    ///
+    /// `write_program` is the address of the write-account program and `seed`
+    /// is used (together with `payer`) to derive address of the write account.
+    /// Payer must sign all the transactions and pays for the rent of the
+    /// write account (which is refunded once the account is freed).
+    /// `chunk_size` limits size of data written in a single transaction; if
+    /// `None`, write-account program’s default is used.
+    pub fn new(
+        write_program: &Pubkey,
+        payer: Pubkey,
+        seed: &[u8],
+        instruction: solana_program::instruction::Instruction,
+        chunk_size: Option<core::num::NonZeroU16>,
+    ) -> Result<Self, ProgramError> {
+        let solana_program::instruction::Instruction {
+            program_id,
+            mut accounts,
+            data,
+        } = instruction;
+        let len = u32::try_from(data.len())
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        let data = [&len.to_le_bytes()[..], data.as_slice()].concat();
+
+        let (mut chunks, write_account, bump) =
+            write::instruction::WriteIter::new(
+                write_program,
+                payer,
+                seed,
+                data.clone(),
+            )?;
+        if let Some(chunk_size) = chunk_size {
+            chunks.chunk_size = chunk_size;
+        }
+        let writes = chunks.collect();
+
+        let commit = write::instruction::commit(
+            *write_program,
+            payer,
+            Some(write_account),
+            seed,
+            bump,
+            &data,
+        )?;
+        accounts.push(AccountMeta::new_readonly(write_account, false));
+        let call = solana_program::instruction::Instruction {
+            program_id,
+            accounts,
+            data: Vec::new(),
+        };
+        let free = write::instruction::free(
+            *write_program,
+            payer,
+            Some(write_account),
+            seed,
+            bump,
+        )?;
+
+        Ok(Self {
+            writes,
+            call: vec![commit, call, free.clone()],
+            free,
+            write_account,
+        })
+    }
+
+    /// Builds a chunked call delivering given IBC message.
+    ///
+    /// This is a shorthand for [`Self::new`] with `deliver` instruction of the
+    /// Solana IBC smart contract deployed at `program_id`.
+    pub fn deliver(
+        program_id: Pubkey,
+        write_program: &Pubkey,
+        payer: Pubkey,
+        seed: &[u8],
+        accounts: crate::accounts::Deliver,
+        message: crate::ibc::MsgEnvelope,
+        chunk_size: Option<core::num::NonZeroU16>,
+    ) -> Result<Self, ProgramError> {
+        use anchor_lang::{InstructionData, ToAccountMetas};
+
+        let instruction = solana_program::instruction::Instruction {
+            program_id,
+            accounts: accounts.to_account_metas(None),
+            data: crate::instruction::Deliver { message }.data(),
+        };
+        Self::new(write_program, payer, seed, instruction, chunk_size)
+    }
+}
 
 #[test]
 fn test_get_ix_data() {
@@ -161,8 +309,88 @@ fn test_get_ix_data() {
         assert_eq!(&key1, accounts[0].key);
     };
 
+    // Appends hash of the data marking it as verified.
+    let commit = |data: &[u8]| {
+        let hash = solana_program::hash::hash(data);
+        [data, hash.as_ref()].concat()
+    };
+
     check(Err(ProgramError::InvalidInstructionData), &[][..]);
-    check(Ok(&[][..]), &[0, 0, 0, 0, 1, 2, 3, 4][..]);
-    check(Ok(&[1][..]), &[1, 0, 0, 0, 1, 2, 3, 4][..]);
+    check(Ok(&[][..]), &commit(&[0, 0, 0, 0]));
+    check(Ok(&[1][..]), &commit(&[1, 0, 0, 0, 1]));
     check(Err(ProgramError::InvalidInstructionData), &[1, 0, 0, 0][..]);
+
+    // Trailing bytes after the hash are ignored.
+    check(
+        Ok(&[1][..]),
+        &[&commit(&[1, 0, 0, 0, 1])[..], &[2, 3, 4][..]].concat(),
+    );
+
+    // Unverified data is rejected.
+    check(Err(ProgramError::InvalidAccountData), &[0, 0, 0, 0][..]);
+    check(Err(ProgramError::InvalidAccountData), &[1, 0, 0, 0, 1, 2, 3, 4][..]);
+    let mut data = commit(&[1, 0, 0, 0, 1]);
+    data.pop();
+    check(Err(ProgramError::InvalidAccountData), &data);
+
+    // Data changed after it was verified is rejected.
+    let mut data = commit(&[2, 0, 0, 0, 1, 2]);
+    data[5] = 3;
+    check(Err(ProgramError::InvalidAccountData), &data);
+    let mut data = commit(&[2, 0, 0, 0, 1, 2]);
+    data[6] ^= 1;
+    check(Err(ProgramError::InvalidAccountData), &data);
+}
+
+#[test]
+#[cfg(feature = "client")]
+fn test_chunked_instruction() {
+    let program_id = Pubkey::new_unique();
+    let write_program = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let data = (0..=255).cycle().take(1200).collect::<Vec<u8>>();
+    let instruction = solana_program::instruction::Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(account, false)],
+        data: data.clone(),
+    };
+
+    let chunked = ChunkedInstruction::new(
+        &write_program,
+        payer,
+        b"seed",
+        instruction,
+        core::num::NonZeroU16::new(100),
+    )
+    .unwrap();
+
+    // Reassemble the data from the write instructions.  Write instruction data
+    // is: discriminant, seed length, seed, bump, offset and the chunk.
+    let mut written = Vec::new();
+    for write in chunked.writes.iter() {
+        assert_eq!(write_program, write.program_id);
+        let (offset, chunk) = write.data[7..].split_at(4);
+        let offset = u32::from_le_bytes(offset.try_into().unwrap());
+        assert_eq!(written.len(), offset as usize);
+        assert!(chunk.len() <= 100);
+        written.extend_from_slice(chunk);
+    }
+    assert_eq!(1204 / 100 + 1, chunked.writes.len());
+    assert_eq!(&1200u32.to_le_bytes()[..], &written[..4]);
+    assert_eq!(data.as_slice(), &written[4..]);
+
+    assert_eq!(3, chunked.call.len());
+    let call = &chunked.call[1];
+    assert_eq!(program_id, call.program_id);
+    assert!(call.data.is_empty());
+    assert_eq!(
+        vec![
+            AccountMeta::new(account, false),
+            AccountMeta::new_readonly(chunked.write_account, false),
+        ],
+        call.accounts
+    );
+    assert_eq!(write_program, chunked.call[0].program_id);
+    assert_eq!(chunked.free, chunked.call[2]);
 }
@@ -11,8 +11,11 @@ use anchor_lang::prelude::*;
 use anchor_lang::solana_program;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::metadata::Metadata;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token::{Mint, Token};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, TokenInterface};
 use borsh::BorshDeserialize;
+use bytemuck::TransparentWrapper;
 use guestchain::config::UpdateConfig;
 use lib::hash::CryptoHash;
 use storage::{PrivateStorage, TransferAccounts};
@@ -29,12 +32,39 @@ pub const ESCROW: &[u8] = b"escrow";
 pub const METADATA: &[u8] = b"metadata";
 
 pub const FEE_SEED: &[u8] = b"fee";
+pub const HOOK_SEED: &[u8] = b"hook";
+pub const HOOK_STATS_SEED: &[u8] = b"hook_stats";
+pub const RELAYER_STATS_SEED: &[u8] = b"relayer_stats";
+pub const PENDING_CLAIM_SEED: &[u8] = b"pending_claim";
+pub const NFT_MINT_SEED: &[u8] = b"nft_mint";
+pub const NFT_ESCROW_SEED: &[u8] = b"nft_escrow";
+pub const UPGRADE_AUTHORITY_SEED: &[u8] = b"upgrade_authority";
+pub const LOOKUP_TABLE_SEED: &[u8] = b"lookup_table";
+pub const CANDIDATE_BOND_SEED: &[u8] = b"candidate_bond";
 
 pub const WSOL_ADDRESS: &str = "So11111111111111111111111111111111111111112";
 
 pub const MINIMUM_FEE_ACCOUNT_BALANCE: u64 =
     solana_program::native_token::LAMPORTS_PER_SOL;
 
+/// Size of the private storage account when it’s initialised.
+///
+/// Pruning consensus states never shrinks the account below this size.
+pub const STORAGE_INITIAL_SPACE: usize = 10240;
+
+/// Free space left in the private storage account when it’s shrunk after
+/// pruning consensus states.
+///
+/// Equal to the most an account can grow by within a single instruction so
+/// that pruning doesn’t leave the storage with less room than a single
+/// `realloc_accounts` call could add.
+pub const STORAGE_HEADROOM: usize =
+    solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+
+/// Part of rent reclaimed by `prune_consensus_states` paid to the caller in
+/// basis points.  The rest goes to the fee account.
+pub const PRUNE_REWARD_BPS: u64 = 5000;
+
 pub const BRIDGE_ESCROW_PROGRAM_ID: Pubkey =
     solana_program::pubkey!("AhfoGVmS19tvkEG2hBuZJ1D6qYEjyFmXZ1qPoFD6H4Mj");
 
@@ -55,8 +85,13 @@ pub mod events;
 mod execution_context;
 mod ibc;
 pub mod ix_data_account;
+pub mod lookup_table;
 #[cfg_attr(not(feature = "mocks"), path = "no-mocks.rs")]
 mod mocks;
+#[cfg(all(feature = "mocks", not(target_os = "solana")))]
+pub use mocks::TestContext;
+pub mod nft_transfer;
+pub mod self_check;
 pub mod storage;
 #[cfg(test)]
 mod tests;
@@ -106,7 +141,8 @@ pub mod solana_ibc {
 
     use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
     use anchor_spl::metadata::{
-        create_metadata_accounts_v3, CreateMetadataAccountsV3,
+        create_metadata_accounts_v3, update_metadata_accounts_v2,
+        CreateMetadataAccountsV3, UpdateMetadataAccountsV2,
     };
 
     use super::*;
@@ -129,7 +165,7 @@ pub mod solana_ibc {
         #[cfg(feature = "witness")]
         {
             let storage = &mut ctx.accounts.storage;
-            let clock = Clock::get()?;
+            let clock = mocks::clock()?;
             let slot = clock.slot;
             let timestamp = clock.unix_timestamp as u64;
             storage
@@ -149,9 +185,13 @@ pub mod solana_ibc {
     /// The request fails if there’s a pending guest block or conditions for
     /// creating a new block haven’t been met.
     ///
+    /// If the sender is a validator, they are recorded as the block’s
+    /// proposer in the epoch statistics (see [`epoch_validator_stats`]).
+    ///
     /// TODO(mina86): Per the guest blockchain paper, generating a guest block
-    /// should offer rewards to account making the generate block call.  This is
-    /// currently not implemented and will be added at a later time.
+    /// should offer rewards to account making the generate block call.  At the
+    /// moment only validators signing blocks are rewarded (see
+    /// [`claim_block_rewards`]).
     pub fn generate_block(ctx: Context<Chain>) -> Result<()> {
         let provable = storage::get_provable_from(
             &ctx.accounts.trie,
@@ -159,7 +199,8 @@ pub mod solana_ibc {
             &ctx.accounts.witness,
             &ctx.accounts.sender,
         )?;
-        ctx.accounts.chain.generate_block(&provable)
+        let proposer = chain::PubKey::from(*ctx.accounts.sender.key);
+        ctx.accounts.chain.generate_block(&provable, proposer)
     }
 
     /// Accepts pending block’s signature from the validator.
@@ -170,16 +211,22 @@ pub mod solana_ibc {
     /// `signature` is signature of the pending guest block made with private
     /// key corresponding to the sender account’s public key.
     ///
-    /// TODO(mina86): At the moment the call doesn’t provide rewards and doesn’t
-    /// allow to submit signatures for finalised guest blocks.  Those features
-    /// will be added at a later time.
+    /// Once the block is finalised, each validator who signed it is credited
+    /// a share of the block reward when the epoch ends (see
+    /// [`claim_block_rewards`]).  If the block ends an epoch, hash of the
+    /// epoch’s summary and commitment of the rewards ledger are committed in
+    /// the trie so that they become part of the first block of the next
+    /// epoch.
+    ///
+    /// TODO(mina86): At the moment the call doesn’t allow to submit signatures
+    /// for finalised guest blocks.  This feature will be added at a later time.
     pub fn sign_block(
         ctx: Context<ChainWithVerifier>,
         // Note: 64 = ed25519::Signature::LENGTH.  `anchor build` doesn’t like
         // non-literals in array sizes.  Yeah, it’s dumb.
         signature: [u8; 64],
     ) -> Result<()> {
-        let provable = storage::get_provable_from(
+        let mut provable = storage::get_provable_from(
             &ctx.accounts.trie,
             #[cfg(feature = "witness")]
             &ctx.accounts.witness,
@@ -192,11 +239,48 @@ pub mod solana_ibc {
             &signature.into(),
             &verifier,
         )? {
+            ctx.accounts.chain.commit_epoch_summary(&mut provable)?;
             ctx.accounts.chain.maybe_generate_block(&provable)?;
         }
         Ok(())
     }
 
+    /// Rotates key of a guest validator candidate keeping its stake.
+    ///
+    /// Sender of the transaction is the candidate with the old key.
+    /// `proof_of_possession` is signature of
+    /// [`chain::key_rotation_message`] made with private key corresponding to
+    /// `new_pubkey`.  It’s verified the same way block signatures are, i.e.
+    /// the signature must be included in call to Ed25519 native program
+    /// preceding this instruction.
+    ///
+    /// If the candidate is a validator in the current epoch, it remains one
+    /// with the old key until the epoch changes and needs to sign blocks with
+    /// the old key until then.
+    pub fn rotate_validator_key(
+        ctx: Context<ChainWithVerifier>,
+        new_pubkey: Pubkey,
+        // Note: 64 = ed25519::Signature::LENGTH.
+        proof_of_possession: [u8; 64],
+    ) -> Result<()> {
+        let provable = storage::get_provable_from(
+            &ctx.accounts.trie,
+            #[cfg(feature = "witness")]
+            &ctx.accounts.witness,
+            &ctx.accounts.sender,
+        )?;
+        let mut verifier = sigverify::Verifier::default();
+        verifier.set_ix_sysvar(&ctx.accounts.ix_sysvar)?;
+        let chain = &mut ctx.accounts.chain;
+        chain.maybe_generate_block(&provable)?;
+        chain.rotate_validator_key(
+            (*ctx.accounts.sender.key).into(),
+            new_pubkey.into(),
+            &proof_of_possession.into(),
+            &verifier,
+        )
+    }
+
     /// Changes stake of a guest validator.
     ///
     /// Sender’s stake will be set to the given amount.  Note that if sender is
@@ -223,12 +307,15 @@ pub mod solana_ibc {
         chain.set_stake(validator.into(), amount)
     }
 
-    /// Changes stake of multiple guest chain validators
+    /// Queues changes of stake of multiple guest chain validators.
     ///
-    /// Sender’s stake will be set to the given amount.  Note that if sender is
-    /// a validator in current epoch, their stake in current epoch won’t change.
-    /// This also means that reducing stake takes effect only after the epoch
-    /// changes.
+    /// Each change is applied to the candidates set immediately and fails the
+    /// instruction if it would violate chain configuration minimums.  The
+    /// change is recorded together with the number of the guest epoch whose
+    /// validators set first includes it and a `StakeChangeQueued` event is
+    /// emitted.  Tokens backing removed stake shouldn’t be released before
+    /// that epoch starts.  Changes which haven’t taken effect yet can be
+    /// inspected with `pending_stake_changes`.
     ///
     /// Can only be called through CPI from another staking program whose
     /// id is mentioned below.
@@ -248,6 +335,119 @@ pub mod solana_ibc {
         chain.update_stake(stake_changes)
     }
 
+    /// Registers sender as a guest validator candidate bonding given amount
+    /// of SOL.
+    ///
+    /// The lamports are moved into a [`storage::CandidateBond`] PDA and the
+    /// amount is queued as sender’s stake (see `update_stake`).  The amount
+    /// must be at least chain’s minimum validator stake.  Lets validators join
+    /// without going through a staking program.
+    ///
+    /// Guest stake is denominated in base units of 9-decimal tokens (which is
+    /// what the restaking program accepts) so one lamport counts as one unit
+    /// of stake.
+    pub fn register_candidate(
+        ctx: Context<RegisterCandidate>,
+        amount: u64,
+    ) -> Result<()> {
+        let chain = &mut ctx.accounts.chain;
+        if u128::from(amount) < chain.min_validator_stake()? {
+            return Err(error!(error::Error::NotEnoughValidatorStake));
+        }
+        let provable = storage::get_provable_from(
+            &ctx.accounts.trie,
+            #[cfg(feature = "witness")]
+            &ctx.accounts.witness,
+            &ctx.accounts.validator,
+        )?;
+        chain.maybe_generate_block(&provable)?;
+
+        let validator = ctx.accounts.validator.key();
+        let activation_epoch =
+            chain.queue_stake_change(validator.into(), i128::from(amount))?;
+
+        let bond = &mut ctx.accounts.bond;
+        solana_program::program::invoke(
+            &solana_program::system_instruction::transfer(
+                &validator,
+                &bond.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.validator.to_account_info(),
+                bond.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        bond.validator = validator;
+        bond.amount = amount;
+        bond.unbonding_epoch = None;
+
+        events::emit(events::CandidateBonded {
+            validator,
+            amount,
+            activation_epoch,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Starts unbonding SOL bonded with `register_candidate`.
+    ///
+    /// Queues removal of the bonded stake.  Once the change is applied,
+    /// the lamports can be withdrawn with `withdraw_candidate_bond`.
+    pub fn unbond_candidate(ctx: Context<UnbondCandidate>) -> Result<()> {
+        let bond = &mut ctx.accounts.bond;
+        if bond.unbonding_epoch.is_some() {
+            return Err(error!(error::Error::CandidateBondUnbonding));
+        }
+        let chain = &mut ctx.accounts.chain;
+        let provable = storage::get_provable_from(
+            &ctx.accounts.trie,
+            #[cfg(feature = "witness")]
+            &ctx.accounts.witness,
+            &ctx.accounts.validator,
+        )?;
+        chain.maybe_generate_block(&provable)?;
+
+        let activation_epoch = chain.queue_stake_change(
+            bond.validator.into(),
+            -i128::from(bond.amount),
+        )?;
+        bond.unbonding_epoch = Some(activation_epoch);
+
+        events::emit(events::CandidateUnbonding {
+            validator: bond.validator,
+            amount: bond.amount,
+            activation_epoch,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Closes candidate’s bond account returning the lamports to the
+    /// candidate.
+    ///
+    /// The bond must have been unbonded with `unbond_candidate` and the
+    /// guest epoch in which removal of the bonded stake takes effect must
+    /// have started (see [`storage::CandidateBond::is_unlocked`]).  Other
+    /// stake changes of the candidate, e.g. ones made through the restaking
+    /// program, don’t affect the bond.
+    pub fn withdraw_candidate_bond(
+        ctx: Context<WithdrawCandidateBond>,
+    ) -> Result<()> {
+        let bond = &ctx.accounts.bond;
+        if !bond.is_unlocked(&ctx.accounts.chain)? {
+            return Err(error!(error::Error::CandidateBondLocked));
+        }
+        events::emit(events::CandidateBondWithdrawn {
+            validator: bond.validator,
+            amount: bond.amount,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
     pub fn set_fee_amount<'a, 'info>(
         ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
         new_amount: u64,
@@ -262,108 +462,296 @@ pub mod solana_ibc {
         Ok(())
     }
 
-    /// Sets up new fee collector proposal which wont be changed until the new
-    /// fee collector calls `accept_fee_collector_change`. If the method is
-    /// called for the first time, the fee collector would just be set without
-    /// needing for any approval.
-    pub fn setup_fee_collector<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, SetupFeeCollector<'info>>,
-        new_fee_collector: Pubkey,
+    /// Sets schedule of fees charged for outgoing transfers.
+    ///
+    /// If `hashed_full_denom` is given, sets schedule for that token only.
+    /// Otherwise sets the default schedule used for tokens without their own.
+    /// Passing `None` as `schedule` removes it.  With no schedule configured,
+    /// flat fee set with `set_fee_amount` is charged.
+    ///
+    /// Can only be called by fee collector.
+    pub fn set_fee_schedule<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        hashed_full_denom: Option<CryptoHash>,
+        schedule: Option<storage::FeeSchedule>,
     ) -> Result<()> {
-        let private_storage = &mut ctx.accounts.storage;
+        if !schedule.as_ref().map_or(true, storage::FeeSchedule::is_valid) {
+            return Err(error!(error::Error::InvalidFeeSchedule));
+        }
+        let storage = &mut ctx.accounts.storage;
+        match (hashed_full_denom, schedule) {
+            (None, schedule) => storage.fee_schedule = schedule,
+            (Some(denom), Some(schedule)) => {
+                storage.denom_fee_schedules.insert(denom, schedule);
+            }
+            (Some(denom), None) => {
+                storage.denom_fee_schedules.remove(&denom);
+            }
+        }
+        msg!("Fee schedule for {:?} set to {:?}", hashed_full_denom, schedule);
+        Ok(())
+    }
 
-        let signer = ctx.accounts.fee_collector.key();
+    /// Returns fee in lamports `sender` would be charged for transferring
+    /// `amount` of given token with `send_transfer`.
+    ///
+    /// `amount` is expressed in units used in ICS-20 packets.  Meant to be
+    /// called via transaction simulation by UIs and SDKs.
+    pub fn quote_fee(
+        ctx: Context<StorageQuery>,
+        sender: Pubkey,
+        hashed_full_denom: CryptoHash,
+        amount: u64,
+    ) -> Result<u64> {
+        let storage = &ctx.accounts.storage;
+        Ok(if storage.fee_exemptions.contains(&sender) {
+            0
+        } else {
+            storage.transfer_fee(&hashed_full_denom, amount)
+        })
+    }
 
-        if private_storage.fee_collector == Pubkey::default() {
-            private_storage.fee_collector = new_fee_collector;
-        } else if signer == private_storage.fee_collector {
-            private_storage.new_fee_collector_proposal = Some(new_fee_collector)
+    /// Sets minimum amount of given token which can be transferred.
+    ///
+    /// `amount` is expressed in units used in ICS-20 packets and applies to
+    /// both outgoing and incoming transfers.  Incoming transfers below the
+    /// minimum are rejected with an error acknowledgement which refunds the
+    /// sender on the counterparty chain.  Setting the amount to zero removes
+    /// the limit.
+    pub fn set_min_transfer_amount<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        hashed_full_denom: CryptoHash,
+        amount: u64,
+    ) -> Result<()> {
+        let amounts = &mut ctx.accounts.storage.min_transfer_amounts;
+        if amount == 0 {
+            amounts.remove(&hashed_full_denom);
         } else {
-            return Err(error!(error::Error::InvalidFeeCollector));
+            amounts.insert(hashed_full_denom, amount);
         }
-
+        events::emit(events::MinTransferAmountUpdate {
+            hashed_full_denom,
+            amount,
+        })
+        .map_err(ProgramError::BorshIoError)?;
         Ok(())
     }
 
-    pub fn accept_fee_collector_change<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, SetupFeeCollector<'info>>,
+    /// Sets or removes rate limit of transfers of given token.
+    ///
+    /// Transfers which would exceed inbound or outbound quota within the
+    /// sliding window are rejected with `RateLimitExceeded` error.  Incoming
+    /// transfers are acknowledged with an error which refunds the sender on
+    /// the counterparty chain.  Refunds of failed outgoing transfers aren’t
+    /// limited.  Changing configuration preserves recorded usage.  Passing
+    /// `None` as `config` removes the limit.
+    pub fn set_rate_limit<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        hashed_full_denom: CryptoHash,
+        config: Option<storage::RateLimitConfig>,
     ) -> Result<()> {
-        let private_storage = &mut ctx.accounts.storage;
-
-        let signer = ctx.accounts.fee_collector.key();
-
-        if let Some(new_admin) = private_storage.new_fee_collector_proposal {
-            if signer != new_admin {
-                return Err(error!(error::Error::InvalidFeeCollector));
+        if !config.as_ref().map_or(true, storage::RateLimitConfig::is_valid) {
+            return Err(error!(error::Error::InvalidRateLimit));
+        }
+        let limits = &mut ctx.accounts.storage.rate_limits;
+        match (limits.get_mut(&hashed_full_denom), config) {
+            (Some(limit), Some(config)) => limit.config = config,
+            (None, Some(config)) => {
+                let limit = storage::RateLimit::new(config);
+                limits.insert(hashed_full_denom, limit);
+            }
+            (_, None) => {
+                limits.remove(&hashed_full_denom);
             }
-            private_storage.fee_collector = new_admin;
-            private_storage.new_fee_collector_proposal = None;
-        } else {
-            return Err(error!(error::Error::FeeCollectorChangeProposalNotSet));
         }
-
+        msg!("Rate limit for {} set to {:?}", hashed_full_denom, config);
         Ok(())
     }
 
-    pub fn collect_fees<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, CollectFees<'info>>,
+    /// Adds or removes an account from the fee exemption list.
+    ///
+    /// Transfers sent by exempted accounts aren’t charged any fees.  Programs calling `send_transfer` through CPI are
+    /// exempted by adding the PDA they sign the transfer with.
+    pub fn set_fee_exemption<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        account: Pubkey,
+        exempt: bool,
     ) -> Result<()> {
-        let fee_account = &ctx.accounts.fee_account;
-        let minimum_balance = Rent::get()?
-            .minimum_balance(fee_account.data_len()) +
-            MINIMUM_FEE_ACCOUNT_BALANCE;
-        let mut available_balance = fee_account.try_borrow_mut_lamports()?;
-        if **available_balance > minimum_balance {
-            **ctx.accounts.fee_collector.try_borrow_mut_lamports()? +=
-                **available_balance - minimum_balance;
-            **available_balance = minimum_balance;
-        } else {
-            return Err(error!(error::Error::InsufficientFeesToCollect));
+        let exemptions = &mut ctx.accounts.storage.fee_exemptions;
+        let pos = exemptions.iter().position(|key| key == &account);
+        match (pos, exempt) {
+            (None, true) => exemptions.push(account),
+            (Some(pos), false) => {
+                exemptions.swap_remove(pos);
+            }
+            _ => (),
         }
+        msg!("Fee exemption for {} set to {}", account, exempt);
+        Ok(())
+    }
 
+    /// Halts or resumes part of IBC processing.
+    ///
+    /// Meant for emergencies such as a light client or escrow exploit.
+    /// `Inbound` halts delivery of all packet messages, `Outbound` halts
+    /// sending transfers and `ClientUpdates` halts updating and upgrading
+    /// clients.  Guest blockchain keeps generating blocks regardless.
+    pub fn set_halt_flag<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        flag: storage::HaltFlag,
+        halted: bool,
+    ) -> Result<()> {
+        let flags = &mut ctx.accounts.storage.halt_flags;
+        *flags.get_mut(flag) = halted;
+        events::emit(events::HaltFlagsUpdate { flags: *flags })
+            .map_err(ProgramError::BorshIoError)?;
         Ok(())
     }
 
-    /// Called to create token mint for wrapped tokens
+    /// Sets how to handle receiver token accounts which have a delegate or
+    /// close authority set when receiving transfers.
     ///
-    /// It has to be ensured that the right denom is hashed
-    /// and proper decimals are passed.
+    /// See [`storage::ReceiverAccountPolicy`].
+    pub fn set_receiver_account_policy<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        policy: storage::ReceiverAccountPolicy,
+    ) -> Result<()> {
+        let storage = &mut ctx.accounts.storage;
+        let previous = storage.receiver_account_policy;
+        storage.receiver_account_policy = policy;
+        msg!(
+            "Receiver account policy updated to {:?} from {:?}",
+            policy,
+            previous
+        );
+        Ok(())
+    }
+
+    /// Sets or removes display metadata override of a wrapped token.
     ///
-    /// Note: The denom will always contain port and channel id
-    /// of solana.
-    pub fn init_mint<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, InitMint<'info>>,
-        effective_decimals: u8,
+    /// The override is only recorded in the private storage.  It’s copied
+    /// into token’s Metaplex metadata account with `sync_denom_metadata`
+    /// instruction.  Removing the override doesn’t revert the metadata
+    /// account.
+    pub fn set_denom_metadata<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
         hashed_full_denom: CryptoHash,
-        original_decimals: u8,
-        token_name: String,
-        token_symbol: String,
-        token_uri: String,
+        metadata: Option<storage::DenomMetadata>,
     ) -> Result<()> {
-        let private_storage = &mut ctx.accounts.storage;
-
-        if effective_decimals > original_decimals {
-            return Err(error!(error::Error::InvalidDecimals));
+        let storage = &mut ctx.accounts.storage;
+        if !storage.assets.contains_key(&hashed_full_denom) {
+            return Err(error!(error::Error::UnknownAsset));
+        }
+        match metadata.clone() {
+            Some(metadata) if !metadata.is_valid() => {
+                return Err(error!(error::Error::InvalidDenomMetadata));
+            }
+            Some(metadata) => {
+                storage.denom_metadata.insert(hashed_full_denom, metadata);
+            }
+            None => {
+                storage.denom_metadata.remove(&hashed_full_denom);
+            }
         }
+        events::emit(events::DenomMetadataUpdate {
+            hashed_full_denom,
+            metadata,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
 
-        if !private_storage.assets.contains_key(&hashed_full_denom) {
-            private_storage.assets.insert(hashed_full_denom, storage::Asset {
-                original_decimals,
-                effective_decimals_on_sol: effective_decimals,
-            });
+    /// Sets pause flags of given channel.
+    ///
+    /// While sending is paused, `send_transfer` over the channel fails.  While
+    /// receiving is paused, incoming transfers are acknowledged with an error
+    /// so that the tokens are refunded on the counterparty chain.
+    /// Acknowledgements and timeouts of packets already sent are processed
+    /// as usual.
+    ///
+    /// Can only be called by fee collector.
+    pub fn set_channel_pause<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetFeeAmount<'info>>,
+        port_id: ibc::PortId,
+        channel_id: ibc::ChannelId,
+        pause: storage::ChannelPause,
+    ) -> Result<()> {
+        let key = trie_ids::PortChannelPK::try_from(&port_id, &channel_id)
+            .map_err(|err| {
+                error::Error::ContextError(ibc::ContextError::ChannelError(err))
+            })
+            .map_err(move |err| error!((&err)))?;
+        let storage = &mut ctx.accounts.storage;
+        let previous = if pause.is_paused() {
+            storage.paused_channels.insert(key, pause)
         } else {
-            return Err(error!(error::Error::AssetAlreadyExists));
+            storage.paused_channels.remove(&key)
         }
+        .unwrap_or_default();
+        msg!(
+            "Pause of {}/{} updated to {:?} from {:?}",
+            port_id,
+            channel_id,
+            pause,
+            previous
+        );
+        events::emit(events::ChannelPauseUpdate { port_id, channel_id, pause })
+            .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Returns pause flags of given channel.
+    ///
+    /// Meant for UIs which want to warn users before they attempt a transfer
+    /// over a paused channel.
+    pub fn channel_pause_status(
+        ctx: Context<StorageQuery>,
+        port_id: ibc::PortId,
+        channel_id: ibc::ChannelId,
+    ) -> Result<storage::ChannelPause> {
+        Ok(ctx.accounts.storage.channel_pause(&port_id, &channel_id))
+    }
+
+    /// Returns highest sequences of packets received and acknowledged (or
+    /// timed out) on given channel.
+    ///
+    /// Meant for monitoring which wants to notice lost relayer transactions;
+    /// gaps are also reported with `SequenceGap` events as they’re detected.
+    pub fn last_seen_sequences(
+        ctx: Context<StorageQuery>,
+        port_id: ibc::PortId,
+        channel_id: ibc::ChannelId,
+    ) -> Result<storage::LastSeenSequences> {
+        Ok(ctx.accounts.storage.last_seen_sequences(&port_id, &channel_id))
+    }
+
+    /// Copies display metadata override of a wrapped token into its Metaplex
+    /// metadata account.
+    ///
+    /// Can be called by anyone since it only applies override set by the fee
+    /// collector.  Only works for tokens created with `init_mint`; tokens
+    /// created with `init_mint_2022` keep the metadata in the mint.
+    pub fn sync_denom_metadata(
+        ctx: Context<SyncDenomMetadata>,
+        hashed_full_denom: CryptoHash,
+    ) -> Result<()> {
+        let metadata = ctx
+            .accounts
+            .storage
+            .denom_metadata
+            .get(&hashed_full_denom)
+            .cloned()
+            .ok_or_else(|| error!(error::Error::NoDenomMetadata))?;
 
         let bump = ctx.bumps.mint_authority;
         let seeds = [MINT_ESCROW_SEED, core::slice::from_ref(&bump)];
         let seeds = seeds.as_ref();
         let seeds = core::slice::from_ref(&seeds);
 
-        let token_data: DataV2 = DataV2 {
-            name: token_name,
-            symbol: token_symbol,
-            uri: token_uri,
+        let token_data = DataV2 {
+            name: metadata.name,
+            symbol: metadata.symbol,
+            uri: metadata.uri,
             seller_fee_basis_points: 0,
             creators: None,
             collection: None,
@@ -372,91 +760,577 @@ pub mod solana_ibc {
 
         let metadata_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_metadata_program.to_account_info(),
-            CreateMetadataAccountsV3 {
-                payer: ctx.accounts.sender.to_account_info(),
-                update_authority: ctx.accounts.mint_authority.to_account_info(),
-                mint: ctx.accounts.token_mint.to_account_info(),
+            UpdateMetadataAccountsV2 {
                 metadata: ctx.accounts.metadata.to_account_info(),
-                mint_authority: ctx.accounts.mint_authority.to_account_info(),
-                system_program: ctx.accounts.system_program.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
+                update_authority: ctx.accounts.mint_authority.to_account_info(),
             },
             seeds,
         );
-
-        create_metadata_accounts_v3(
+        update_metadata_accounts_v2(
             metadata_ctx,
-            token_data,
-            false,
-            true,
+            None,
+            Some(token_data),
+            None,
             None,
         )?;
 
+        msg!("Denom metadata of {} synced", hashed_full_denom);
         Ok(())
     }
 
-    #[allow(unused_variables)]
-    pub fn deliver<'a, 'info>(
-        mut ctx: Context<'a, 'a, 'a, 'info, Deliver<'info>>,
-        message: ibc::MsgEnvelope,
+    /// Transfers tokens held in receiver’s pending claim account to given
+    /// token account.
+    ///
+    /// Tokens end up in the pending claim account when a transfer is received
+    /// into a token account with a delegate or close authority set and
+    /// [`storage::ReceiverAccountPolicy::Escrow`] policy is in effect.  Only
+    /// the receiver can claim them.
+    pub fn claim_pending_transfer(
+        ctx: Context<ClaimPendingTransfer>,
     ) -> Result<()> {
-        #[cfg(not(feature = "mocks"))]
-        if !relayer::check_id(ctx.accounts.sender.key) {
-            msg!("Only {} can call this method", relayer::ID);
-            return Err(error!(error::Error::InvalidSigner));
-        }
-
-        let sig_verify_program_id =
-            ctx.accounts.chain.sig_verify_program_id()?;
-
-        let mut store = storage::from_ctx!(ctx, with accounts);
-        let mut router = store.clone();
-
-        if let Some((last, rest)) = ctx.remaining_accounts.split_last() {
-            let mut verifier = sigverify::Verifier::default();
-            if verifier
-                .set_sigverify_account(
-                    unsafe { core::mem::transmute(last) },
-                    &sig_verify_program_id,
-                )
-                .is_ok()
-            {
-                global().set_verifier(verifier);
-                ctx.remaining_accounts = rest;
-            }
+        let accounts = &ctx.accounts;
+        let amount = accounts.pending_claim_account.amount;
+        if amount == 0 {
+            return Err(error!(error::Error::NoPendingTransfer));
         }
-        let height = store.borrow().chain.head()?.block_height;
-        // height just before the data is added to the trie.
-        msg!("Current Block height {}", height);
-        let previous_root = *store.borrow().provable.hash();
-
-        ::ibc::core::entrypoint::dispatch(&mut store, &mut router, message)
-            .map_err(error::Error::ContextError)
-            .map_err(move |err| error!((&err)))?;
 
-        #[cfg(feature = "witness")]
-        {
-            let root = *store.borrow().provable.hash();
-            if previous_root != root {
-                msg!("Writing local consensus state");
-                let clock = Clock::get()?;
-                let slot = clock.slot;
-                let timestamp = clock.unix_timestamp as u64;
-                store
-                    .borrow_mut()
-                    .private
-                    .add_local_consensus_state(slot, timestamp, root)
-                    .unwrap();
-            }
-        }
+        let receiver = accounts.receiver.key();
+        let bump = ctx.bumps.pending_claim_authority;
+        let seeds = [
+            PENDING_CLAIM_SEED,
+            receiver.as_ref(),
+            core::slice::from_ref(&bump),
+        ];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
 
+        let transfer_instruction = token_interface::TransferChecked {
+            from: accounts.pending_claim_account.to_account_info(),
+            mint: accounts.token_mint.to_account_info(),
+            to: accounts.destination.to_account_info(),
+            authority: accounts.pending_claim_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            transfer_instruction,
+            seeds,
+        );
+        token_interface::transfer_checked(
+            cpi_ctx,
+            amount,
+            accounts.token_mint.decimals,
+        )?;
+        msg!("Claimed {} pending tokens for {}", amount, receiver);
         Ok(())
     }
 
-    /// Called to set up a connection, channel and store the next
-    /// sequence.  Will panic if called without `mocks` feature.
-    pub fn mock_deliver<'a, 'info>(
-        ctx: Context<'a, 'a, 'a, 'info, MockDeliver<'info>>,
+    /// Sets up new fee collector proposal which wont be changed until the new
+    /// fee collector calls `accept_fee_collector_change`. If the method is
+    /// called for the first time, the fee collector would just be set without
+    /// needing for any approval.
+    pub fn setup_fee_collector<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetupFeeCollector<'info>>,
+        new_fee_collector: Pubkey,
+    ) -> Result<()> {
+        let private_storage = &mut ctx.accounts.storage;
+
+        let signer = ctx.accounts.fee_collector.key();
+
+        if private_storage.fee_collector == Pubkey::default() {
+            private_storage.fee_collector = new_fee_collector;
+        } else if signer == private_storage.fee_collector {
+            private_storage.new_fee_collector_proposal =
+                Some(new_fee_collector);
+            private_storage.new_fee_collector_multisig = None;
+        } else {
+            return Err(error!(error::Error::InvalidFeeCollector));
+        }
+
+        Ok(())
+    }
+
+    /// Sets up new fee collector proposal pointing at a vault of
+    /// a Squads-style multisig.
+    ///
+    /// Works like `setup_fee_collector` except that the new fee collector is
+    /// the vault PDA derived from `multisig`.  The `multisig` account must be
+    /// owned by the multisig program.  Unlike with `setup_fee_collector`, the
+    /// proposal always needs to be accepted with `accept_fee_collector_change`
+    /// executed through the multisig which proves it controls the vault.
+    pub fn setup_multisig_fee_collector<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetupMultisigFeeCollector<'info>>,
+        vault_index: u8,
+    ) -> Result<()> {
+        let private_storage = &mut ctx.accounts.storage;
+
+        let signer = ctx.accounts.fee_collector.key();
+        if private_storage.fee_collector != Pubkey::default() &&
+            signer != private_storage.fee_collector
+        {
+            return Err(error!(error::Error::InvalidFeeCollector));
+        }
+
+        let multisig = &ctx.accounts.multisig;
+        if multisig.data_is_empty() ||
+            *multisig.owner != ctx.accounts.multisig_program.key()
+        {
+            return Err(error!(error::Error::InvalidMultisig));
+        }
+
+        let multisig = storage::MultisigFeeCollector {
+            program_id: ctx.accounts.multisig_program.key(),
+            multisig: multisig.key(),
+            vault_index,
+        };
+        msg!("Proposing multisig vault {} as fee collector", multisig.vault());
+        private_storage.new_fee_collector_proposal = Some(multisig.vault());
+        private_storage.new_fee_collector_multisig = Some(multisig);
+
+        Ok(())
+    }
+
+    pub fn accept_fee_collector_change<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SetupFeeCollector<'info>>,
+    ) -> Result<()> {
+        let private_storage = &mut ctx.accounts.storage;
+
+        let signer = ctx.accounts.fee_collector.key();
+
+        if let Some(new_admin) = private_storage.new_fee_collector_proposal {
+            if signer != new_admin {
+                return Err(error!(error::Error::InvalidFeeCollector));
+            }
+            private_storage.fee_collector = new_admin;
+            private_storage.new_fee_collector_proposal = None;
+            private_storage.fee_collector_multisig =
+                private_storage.new_fee_collector_multisig.take();
+        } else {
+            return Err(error!(error::Error::FeeCollectorChangeProposalNotSet));
+        }
+
+        Ok(())
+    }
+
+    /// Configures governance of program upgrades.
+    ///
+    /// Upgrades proposed with `propose_upgrade` need `threshold` of
+    /// `approvers` to approve them and can be executed with `execute_upgrade`
+    /// no sooner than `timelock_secs` seconds after the proposal.  For the
+    /// governance to be effective, the program’s upgrade authority must be
+    /// set to the PDA with `[UPGRADE_AUTHORITY_SEED]` seeds afterwards.
+    ///
+    /// Can only be called by fee collector and only once.
+    pub fn setup_upgrade_governance(
+        ctx: Context<SetFeeAmount>,
+        approvers: Vec<Pubkey>,
+        threshold: u8,
+        timelock_secs: u64,
+    ) -> Result<()> {
+        let storage = &mut ctx.accounts.storage;
+        if storage.upgrade_governance.is_some() {
+            return Err(error!(error::Error::InvalidUpgradeGovernance));
+        }
+        let governance = storage::UpgradeGovernance::new(
+            approvers,
+            threshold,
+            timelock_secs,
+        )
+        .ok_or_else(|| error!(error::Error::InvalidUpgradeGovernance))?;
+        msg!(
+            "Upgrade governance: {} of {} approvers, {}s timelock",
+            threshold,
+            governance.approvers.len(),
+            timelock_secs
+        );
+        storage.upgrade_governance = Some(governance);
+        Ok(())
+    }
+
+    /// Proposes upgrading the program to one held in `buffer` account.
+    ///
+    /// The buffer’s authority must already be the upgrade authority PDA so
+    /// that nobody can modify the buffer once the upgrade is proposed.  Hash
+    /// of the program is recorded and verified again when the upgrade is
+    /// executed.  Hashing the program may require raising the compute unit
+    /// limit of the transaction.
+    ///
+    /// Replaces any pending proposal.  The proposer’s approval is counted
+    /// towards the threshold.
+    ///
+    /// Can only be called by one of upgrade approvers.
+    pub fn propose_upgrade(ctx: Context<ProposeUpgrade>) -> Result<()> {
+        let buffer = ctx.accounts.buffer.key();
+        let buffer_hash = upgrade_buffer_hash(
+            &ctx.accounts.buffer,
+            ctx.accounts.upgrade_authority.key,
+        )?;
+        let governance = upgrade_governance(&mut ctx.accounts.storage)?;
+        let now = mocks::clock()?.unix_timestamp;
+        let proposer = ctx.accounts.approver.key();
+        governance
+            .propose(proposer, buffer, buffer_hash, now)
+            .map_err(|err| error!((&err)))?;
+        let executable_at = now.saturating_add(
+            i64::try_from(governance.timelock_secs).unwrap_or(i64::MAX),
+        );
+        msg!("Upgrade to {} proposed by {}", buffer, proposer);
+        events::emit(events::UpgradeProposed {
+            proposer,
+            buffer,
+            buffer_hash,
+            executable_at,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Approves pending upgrade to program held in `buffer` account.
+    ///
+    /// The buffer is passed to make sure approver approves the upgrade they
+    /// expect rather than one which has replaced it in the meantime.
+    ///
+    /// Can only be called by one of upgrade approvers.
+    pub fn approve_upgrade(
+        ctx: Context<UpgradeGovernanceAction>,
+        buffer: Pubkey,
+    ) -> Result<()> {
+        let governance = upgrade_governance(&mut ctx.accounts.storage)?;
+        let approver = ctx.accounts.approver.key();
+        let approvals = governance
+            .approve(approver, &buffer)
+            .map_err(|err| error!((&err)))?;
+        msg!("Upgrade to {} approved by {}", buffer, approver);
+        events::emit(events::UpgradeApproved {
+            approver,
+            buffer,
+            approvals: approvals as u8,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Executes pending upgrade once it’s been approved and the timelock has
+    /// passed.
+    ///
+    /// Fails if the program held in the buffer doesn’t match the hash
+    /// recorded when the upgrade was proposed.  Anyone can call this method.
+    /// Lamports of the buffer account are transferred to `spill` account.
+    pub fn execute_upgrade(ctx: Context<ExecuteUpgrade>) -> Result<()> {
+        let governance = upgrade_governance(&mut ctx.accounts.storage)?;
+        let now = mocks::clock()?.unix_timestamp;
+        let proposal =
+            governance.take_ready(now).map_err(|err| error!((&err)))?;
+        if proposal.buffer != ctx.accounts.buffer.key() {
+            return Err(error!(error::Error::NoUpgradeProposal));
+        }
+
+        let authority = &ctx.accounts.upgrade_authority;
+        let buffer_hash =
+            upgrade_buffer_hash(&ctx.accounts.buffer, authority.key)?;
+        if buffer_hash != proposal.buffer_hash {
+            return Err(error!(error::Error::InvalidUpgradeBuffer));
+        }
+        let ix = solana_program::bpf_loader_upgradeable::upgrade(
+            &crate::ID,
+            &proposal.buffer,
+            authority.key,
+            ctx.accounts.spill.key,
+        );
+        let bump = ctx.bumps.upgrade_authority;
+        let seeds = [UPGRADE_AUTHORITY_SEED, core::slice::from_ref(&bump)];
+        solana_program::program::invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.program.to_account_info(),
+                ctx.accounts.buffer.to_account_info(),
+                ctx.accounts.spill.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                authority.to_account_info(),
+                ctx.accounts.bpf_loader.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        msg!("Program upgraded from {}", proposal.buffer);
+        events::emit(events::UpgradeExecuted { buffer: proposal.buffer })
+            .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Creates the account holding transfer hooks statistics.
+    ///
+    /// See [`storage::HookStats`].  Anyone can create the account.
+    pub fn init_hook_stats(_ctx: Context<InitHookStats>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Creates the account holding relayers’ delivery statistics.
+    ///
+    /// See [`storage::RelayerStats`].  Anyone can create the account.
+    pub fn init_relayer_stats(_ctx: Context<InitRelayerStats>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns delivery statistics of given relayer or `None` if the relayer
+    /// isn’t tracked.
+    pub fn relayer_stats(
+        ctx: Context<RelayerStatsQuery>,
+        relayer: Pubkey,
+    ) -> Result<Option<storage::RelayerStatsEntry>> {
+        Ok(ctx.accounts.relayer_stats.get(&relayer).cloned())
+    }
+
+    /// Removes statistics of relayers which haven’t delivered any messages
+    /// within last `max_idle_slots` slots.
+    pub fn prune_relayer_stats(
+        ctx: Context<PruneRelayerStats>,
+        max_idle_slots: u64,
+    ) -> Result<()> {
+        let min_slot = mocks::clock()?.slot.saturating_sub(max_idle_slots);
+        let pruned = ctx.accounts.relayer_stats.prune(min_slot);
+        msg!("Pruned {} relayer stats entries", pruned);
+        Ok(())
+    }
+
+    pub fn collect_fees<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, CollectFees<'info>>,
+    ) -> Result<()> {
+        let fee_account = &ctx.accounts.fee_account;
+        // Block rewards which haven’t been claimed yet are paid out from the
+        // fee account so they cannot be collected.
+        let unclaimed_rewards = ctx
+            .accounts
+            .chain
+            .unclaimed_rewards()
+            .map_or(0, |amount| u64::try_from(amount).unwrap_or(u64::MAX));
+        let minimum_balance = (Rent::get()?
+            .minimum_balance(fee_account.data_len()) +
+            MINIMUM_FEE_ACCOUNT_BALANCE)
+            .saturating_add(unclaimed_rewards);
+        let mut available_balance = fee_account.try_borrow_mut_lamports()?;
+        if **available_balance > minimum_balance {
+            **ctx.accounts.fee_collector.try_borrow_mut_lamports()? +=
+                **available_balance - minimum_balance;
+            **available_balance = minimum_balance;
+        } else {
+            return Err(error!(error::Error::InsufficientFeesToCollect));
+        }
+
+        Ok(())
+    }
+
+    /// Called to create token mint for wrapped tokens
+    ///
+    /// It has to be ensured that the right denom is hashed
+    /// and proper decimals are passed.
+    ///
+    /// Note: The denom will always contain port and channel id
+    /// of solana.
+    pub fn init_mint<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, InitMint<'info>>,
+        effective_decimals: u8,
+        hashed_full_denom: CryptoHash,
+        original_decimals: u8,
+        token_name: String,
+        token_symbol: String,
+        token_uri: String,
+    ) -> Result<()> {
+        add_asset(
+            &mut ctx.accounts.storage,
+            hashed_full_denom,
+            effective_decimals,
+            original_decimals,
+        )?;
+
+        let bump = ctx.bumps.mint_authority;
+        let seeds = [MINT_ESCROW_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+
+        let token_data: DataV2 = DataV2 {
+            name: token_name,
+            symbol: token_symbol,
+            uri: token_uri,
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        };
+
+        let metadata_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_metadata_program.to_account_info(),
+            CreateMetadataAccountsV3 {
+                payer: ctx.accounts.sender.to_account_info(),
+                update_authority: ctx.accounts.mint_authority.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+                metadata: ctx.accounts.metadata.to_account_info(),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            seeds,
+        );
+
+        create_metadata_accounts_v3(
+            metadata_ctx,
+            token_data,
+            false,
+            true,
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    /// Creates token mint for wrapped tokens using the Token-2022 program.
+    ///
+    /// Works like `init_mint` except that it doesn’t depend on Metaplex
+    /// Token Metadata program.  Instead, token name, symbol and URI are
+    /// stored in the mint itself using Token-2022 metadata pointer and token
+    /// metadata extensions.
+    pub fn init_mint_2022<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, InitMint2022<'info>>,
+        effective_decimals: u8,
+        hashed_full_denom: CryptoHash,
+        original_decimals: u8,
+        token_name: String,
+        token_symbol: String,
+        token_uri: String,
+    ) -> Result<()> {
+        use spl_token_2022::extension::{metadata_pointer, ExtensionType};
+
+        add_asset(
+            &mut ctx.accounts.storage,
+            hashed_full_denom,
+            effective_decimals,
+            original_decimals,
+        )?;
+
+        let mint = ctx.accounts.token_mint.to_account_info();
+        let authority = ctx.accounts.mint_authority.to_account_info();
+        let token_program = ctx.accounts.token_program.key();
+
+        // The metadata is written into the mint account which gets resized by
+        // the Token-2022 program.  The account must have enough lamports to
+        // be rent-exempt after that.
+        let space = ExtensionType::try_calculate_account_len::<
+            spl_token_2022::state::Mint,
+        >(&[ExtensionType::MetadataPointer])?;
+        let metadata_space =
+            spl_token_metadata_interface::state::TokenMetadata {
+                mint: mint.key(),
+                name: token_name.clone(),
+                symbol: token_symbol.clone(),
+                uri: token_uri.clone(),
+                ..Default::default()
+            }
+            .tlv_size_of()?;
+        let lamports = Rent::get()?.minimum_balance(space + metadata_space);
+
+        let bump = ctx.bumps.token_mint;
+        let mint_seeds =
+            [MINT, hashed_full_denom.as_ref(), core::slice::from_ref(&bump)];
+        solana_program::program::invoke_signed(
+            &solana_program::system_instruction::create_account(
+                ctx.accounts.sender.key,
+                mint.key,
+                lamports,
+                space as u64,
+                &token_program,
+            ),
+            &[
+                ctx.accounts.sender.to_account_info(),
+                mint.clone(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&mint_seeds[..]],
+        )?;
+
+        solana_program::program::invoke(
+            &metadata_pointer::instruction::initialize(
+                &token_program,
+                mint.key,
+                Some(*authority.key),
+                Some(*mint.key),
+            )?,
+            &[mint.clone()],
+        )?;
+        solana_program::program::invoke(
+            &spl_token_2022::instruction::initialize_mint2(
+                &token_program,
+                mint.key,
+                authority.key,
+                None,
+                effective_decimals,
+            )?,
+            &[mint.clone()],
+        )?;
+
+        let bump = ctx.bumps.mint_authority;
+        let authority_seeds = [MINT_ESCROW_SEED, core::slice::from_ref(&bump)];
+        solana_program::program::invoke_signed(
+            &spl_token_metadata_interface::instruction::initialize(
+                &token_program,
+                mint.key,
+                authority.key,
+                mint.key,
+                authority.key,
+                token_name,
+                token_symbol,
+                token_uri,
+            ),
+            &[mint, authority],
+            &[&authority_seeds[..]],
+        )?;
+
+        Ok(())
+    }
+
+    /// Creates token mint for a wrapped NFT received over ICS-721 channel.
+    ///
+    /// Can be called by anyone (usually the relayer) before delivering the
+    /// packet carrying the token.  The mint is a PDA derived from hashes of
+    /// the full class identifier (i.e. including Solana port and channel)
+    /// and the token identifier; see [`nft_transfer::get_nft_mint`].  The
+    /// token is minted when the packet is received.
+    pub fn init_nft_mint(
+        _ctx: Context<InitNftMint>,
+        _hashed_full_class_id: CryptoHash,
+        _hashed_token_id: CryptoHash,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn deliver<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, Deliver<'info>>,
+        message: ibc::MsgEnvelope,
+    ) -> Result<()> {
+        deliver_messages(ctx, core::iter::once(message))
+    }
+
+    /// Delivers multiple IBC messages in a single instruction.
+    ///
+    /// Messages are dispatched in order and if any of them fails, the entire
+    /// instruction fails.  This allows relayer to submit client update
+    /// together with packets proven against it (e.g. `UpdateClient` followed
+    /// by `RecvPacket` and `Acknowledgement` messages) such that they are
+    /// processed atomically in the same slot.
+    ///
+    /// All messages share the same set of accounts.  In particular, at most
+    /// one token transfer receiver can be handled in a single batch.
+    pub fn deliver_batch<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, Deliver<'info>>,
+        messages: Vec<ibc::MsgEnvelope>,
+    ) -> Result<()> {
+        deliver_messages(ctx, messages)
+    }
+
+    /// Called to set up a connection, channel and store the next
+    /// sequence.  Will panic if called without `mocks` feature.
+    pub fn mock_deliver<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, MockDeliver<'info>>,
         port_id: ibc::PortId,
         commitment_prefix: ibc::CommitmentPrefix,
         client_id: ibc::ClientId,
@@ -487,8 +1361,28 @@ pub mod solana_ibc {
         if full_denom != hashed_full_denom {
             return Err(error!(error::Error::InvalidSendTransferParams));
         }
+        ctx.accounts
+            .storage
+            .halt_flags
+            .check(storage::HaltFlag::Outbound)
+            .map_err(|err| error!((&err)))?;
+        if ctx
+            .accounts
+            .storage
+            .channel_pause(&msg.port_id_on_a, &msg.chan_id_on_a)
+            .send
+        {
+            return Err(error!(error::Error::ChannelPaused));
+        }
 
-        let fee_amount = ctx.accounts.storage.fee_in_lamports;
+        let amount = primitive_types::U256::from(msg.packet_data.token.amount);
+        let fee_amount = ctx.accounts.storage.transfer_fee(
+            &hashed_full_denom,
+            u64::try_from(amount).unwrap_or(u64::MAX),
+        );
+        let sender_key = ctx.accounts.sender.key();
+        let fee_exempt =
+            ctx.accounts.storage.fee_exemptions.contains(&sender_key);
 
         let mut store = storage::from_ctx!(ctx, with accounts);
         let mut token_ctx = store.clone();
@@ -509,14 +1403,26 @@ pub mod solana_ibc {
         let sender = ctx.accounts.sender.to_account_info();
         let system_program = ctx.accounts.system_program.to_account_info();
 
-        solana_program::program::invoke(
-            &solana_program::system_instruction::transfer(
-                &sender.key(),
-                &fee_collector.key(),
-                fee_amount,
-            ),
-            &[sender.clone(), fee_collector.clone(), system_program.clone()],
-        )?;
+        if fee_exempt {
+            events::emit(events::FeeExemptionUsed {
+                sender: sender_key,
+                fee_in_lamports: fee_amount,
+            })
+            .map_err(ProgramError::BorshIoError)?;
+        } else {
+            solana_program::program::invoke(
+                &solana_program::system_instruction::transfer(
+                    &sender.key(),
+                    &fee_collector.key(),
+                    fee_amount,
+                ),
+                &[
+                    sender.clone(),
+                    fee_collector.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
 
         ibc::apps::transfer::handler::send_transfer(
             &mut store,
@@ -527,6 +1433,18 @@ pub mod solana_ibc {
         .map_err(|err| error!((&err)))
     }
 
+    /// Sends a non-fungible token over an ICS-721 channel.
+    ///
+    /// Solana-native NFTs are escrowed and must have Metaplex metadata
+    /// account passed so that their class can be verified.  Wrapped NFTs are
+    /// burned.  See [`nft_transfer`] module for details.
+    pub fn send_nft_transfer<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, SendNftTransfer<'info>>,
+        msg: nft_transfer::MsgNftTransfer,
+    ) -> Result<()> {
+        nft_transfer::send_nft_transfer(ctx, msg)
+    }
+
     /// Reallocates the specified account to the new length.
     ///
     /// Would fail if the account is not owned by the program.
@@ -552,6 +1470,62 @@ pub mod solana_ibc {
         Ok(account.realloc(new_length, false)?)
     }
 
+    /// Removes consensus states of given client which are older than client’s
+    /// trusting period and reclaims rent of the freed space.
+    ///
+    /// Can be called by anyone.  A few most recent consensus states are
+    /// always kept (see [`storage::MIN_CONSENSUS_STATES`]).  Afterwards the
+    /// private storage account is shrunk leaving [`STORAGE_HEADROOM`] bytes
+    /// of free space but never below [`STORAGE_INITIAL_SPACE`] bytes.
+    /// [`PRUNE_REWARD_BPS`] of the rent freed by shrinking the account is
+    /// paid to the caller and the rest goes to the fee account.  Lamports
+    /// above the rent-exempt minimum of the old size are left in the account.
+    /// Fails if there’s nothing to prune.
+    pub fn prune_consensus_states<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, PruneConsensusStates<'info>>,
+        client_id: ibc::ClientId,
+    ) -> Result<()> {
+        let mut store = storage::from_ctx!(ctx);
+        let pruned = store
+            .prune_consensus_states_impl(&client_id)
+            .map_err(error::Error::from)
+            .map_err(|err| error!((&err)))?;
+        core::mem::drop(store);
+        if pruned == 0 {
+            return Err(error!(error::Error::NothingToPrune));
+        }
+
+        let storage = ctx.accounts.storage.to_account_info();
+        let used = 8 + ctx.accounts.storage.try_to_vec()?.len();
+        let old_length = storage.data_len();
+        let new_length = (used + STORAGE_HEADROOM)
+            .max(STORAGE_INITIAL_SPACE)
+            .min(old_length);
+        storage.realloc(new_length, false)?;
+
+        let rent = Rent::get()?;
+        let reclaimed = rent
+            .minimum_balance(old_length)
+            .saturating_sub(rent.minimum_balance(new_length));
+        let mut balance = storage.try_borrow_mut_lamports()?;
+        let reward = u64::try_from(
+            u128::from(reclaimed) * u128::from(PRUNE_REWARD_BPS) / 10_000,
+        )
+        .unwrap();
+        **balance -= reclaimed;
+        **ctx.accounts.sender.try_borrow_mut_lamports()? += reward;
+        **ctx.accounts.fee_account.try_borrow_mut_lamports()? +=
+            reclaimed - reward;
+
+        msg!(
+            "Pruned {} consensus states of {}; reclaimed {} lamports",
+            pruned,
+            client_id,
+            reclaimed
+        );
+        Ok(())
+    }
+
     pub fn update_chain_config(
         ctx: Context<UpdateChainConfig>,
         config_payload: UpdateConfig,
@@ -560,6 +1534,147 @@ pub mod solana_ibc {
         chain.update_chain_config(config_payload)
     }
 
+    /// Switches the guest blockchain to version one block fingerprints.
+    ///
+    /// The fingerprint domain is the hash of this program’s id and the most
+    /// recent entry of the SlotHashes sysvar.  This makes signatures made by
+    /// validators unusable on a redeployment of the chain with the same
+    /// genesis block.  Validators need no changes since they sign
+    /// fingerprints as calculated by the chain but light clients of the chain
+    /// need to be configured with the domain reported in the emitted event.
+    ///
+    /// Can only be called by fee collector.
+    pub fn set_fingerprint_domain(
+        ctx: Context<SetFingerprintDomain>,
+    ) -> Result<()> {
+        let data = ctx.accounts.slot_hashes.try_borrow_data()?;
+        // SlotHashes is a u64 length followed by (slot, hash) entries, most
+        // recent first.
+        let entry = data
+            .get(..48)
+            .filter(|data| data[..8] != [0; 8])
+            .ok_or_else(|| error!(error::Error::InvalidSlotHashes))?;
+        let slot = u64::from_le_bytes(entry[8..16].try_into().unwrap());
+        let hash = &entry[16..];
+        let domain = CryptoHash::digestv(&[
+            crate::ID.as_ref(),
+            &slot.to_le_bytes(),
+            hash,
+        ]);
+        ctx.accounts.chain.set_fingerprint_version(
+            guestchain::block::FingerprintVersion::V1 { domain },
+        )?;
+        msg!("Fingerprint domain set to {} (slot {})", domain, slot);
+        events::emit(events::FingerprintDomainUpdate { domain, slot })
+            .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Sets reward in lamports for each guest block finalised by quorum of
+    /// validators.
+    ///
+    /// Rewards of blocks finalised during an epoch are split between
+    /// validators proportionally to the number of blocks they’ve signed when
+    /// the epoch ends.  Can only be called by fee collector.
+    pub fn set_block_reward(
+        ctx: Context<UpdateChainConfig>,
+        block_reward: u64,
+    ) -> Result<()> {
+        let previous = ctx.accounts.chain.set_block_reward(block_reward)?;
+        msg!("Block reward updated to {} from {}", block_reward, previous);
+        Ok(())
+    }
+
+    /// Pays out all block rewards accrued by the validator.
+    ///
+    /// Rewards are paid out from the fee account.  Fails if the validator has
+    /// nothing to claim or if paying the rewards would bring the fee account
+    /// below its minimum balance.
+    pub fn claim_block_rewards(ctx: Context<ClaimBlockRewards>) -> Result<()> {
+        let validator = chain::PubKey::from(*ctx.accounts.validator.key);
+        let amount = ctx.accounts.chain.claim_block_rewards(&validator)?;
+
+        let fee_account = &ctx.accounts.fee_account;
+        let minimum_balance = Rent::get()?
+            .minimum_balance(fee_account.data_len()) +
+            MINIMUM_FEE_ACCOUNT_BALANCE;
+        let mut available_balance = fee_account.try_borrow_mut_lamports()?;
+        if (**available_balance).saturating_sub(minimum_balance) < amount {
+            return Err(error!(error::Error::InsufficientRewardsFunds));
+        }
+        **available_balance -= amount;
+        **ctx.accounts.validator.try_borrow_mut_lamports()? += amount;
+
+        events::emit(events::BlockRewardsClaimed { validator, amount })
+            .map_err(ProgramError::BorshIoError)?;
+        Ok(())
+    }
+
+    /// Returns performance of given validator in the last finished guest
+    /// blockchain epoch.
+    ///
+    /// The statistics include number of blocks the validator has proposed and
+    /// signed as well as its uptime.  Hash of the full epoch summary is
+    /// committed in the trie under [`trie_ids::TrieKey::for_epoch_summary`]
+    /// key.  Fails if no epoch has finished yet.
+    pub fn epoch_validator_stats(
+        ctx: Context<ChainQuery>,
+        validator: Pubkey,
+    ) -> Result<chain::EpochValidatorStats> {
+        ctx.accounts.chain.epoch_validator_stats(&validator.into())
+    }
+
+    /// Returns guest validators’ stake changes which haven’t taken effect yet
+    /// in the order they were made by `update_stake`.
+    pub fn pending_stake_changes(
+        ctx: Context<ChainQuery>,
+    ) -> Result<Vec<chain::PendingStakeChange>> {
+        ctx.accounts.chain.pending_stake_changes()
+    }
+
+    /// Returns snapshot of given guest blockchain epoch with proof of its
+    /// commitment in the trie.
+    ///
+    /// Hash of each epoch’s snapshot is committed in the trie under
+    /// [`trie_ids::TrieKey::for_epoch`] key when the epoch starts.  The
+    /// snapshot itself is only returned for the current epoch; validators
+    /// sets of past epochs can be recovered from `NewBlock` events and
+    /// verified against the returned commitment.  Doesn’t modify any
+    /// accounts and can be called by anyone.
+    pub fn query_epoch(
+        ctx: Context<ChainTrieQuery>,
+        epoch_number: u64,
+    ) -> Result<chain::EpochProof> {
+        let snapshot = ctx.accounts.chain.epoch_snapshot(epoch_number)?;
+        let trie: solana_trie::TrieAccount<_> =
+            solana_trie::TrieAccount::from_account_info(
+                &ctx.accounts.trie,
+                &crate::ID,
+            )?;
+        let key = trie_ids::TrieKey::for_epoch(epoch_number);
+        let (commitment, proof) = trie
+            .prove(&key)
+            .map_err(|_| error!(error::Error::Internal("query_epoch")))?;
+        Ok(chain::EpochProof {
+            snapshot,
+            commitment,
+            state_root: *trie.hash(),
+            proof: proof.try_to_vec()?,
+        })
+    }
+
+    /// Verifies integrity of the program’s accounts and returns a report.
+    ///
+    /// Checks consistency between the private storage, the trie, the witness
+    /// and the fee account; see [`self_check::Report`] for list of performed
+    /// checks.  Doesn’t modify any accounts and can be called by anyone.  Meant
+    /// to be run after deployments and migrations as well as by monitoring.
+    pub fn self_check(ctx: Context<SelfCheck>) -> Result<self_check::Report> {
+        let report = self_check::run(ctx.accounts)?;
+        msg!("Self-check passed: {}; {:?}", report.is_ok(), report);
+        Ok(report)
+    }
+
     /// Method which updates the connection delay of a particular connection
     ///
     /// Fails if the connection doesnt exist.
@@ -603,14 +1718,177 @@ pub mod solana_ibc {
         })
         .map_err(move |err| error!((&err)))?;
 
-        store
-            .store_connection(
-                &ibc::path::ConnectionPath(connection_id),
-                updated_connection,
-            )
-            .map_err(error::Error::ContextError)
-            .map_err(move |err| error!((&err)))?;
+        store
+            .store_connection(
+                &ibc::path::ConnectionPath(connection_id),
+                updated_connection,
+            )
+            .map_err(error::Error::ContextError)
+            .map_err(move |err| error!((&err)))?;
+
+        Ok(())
+    }
+
+    /// Method which updates parameters of a cf-solana light client.
+    ///
+    /// `params` is a protobuf-encoded `MsgUpdateClientParams` message.  Fails
+    /// if the client isn’t a cf-solana client, if it is frozen or if the new
+    /// parameters are out of bounds.
+    /// Can only be called by fee collector.
+    pub fn update_client_params(
+        ctx: Context<UpdateConnectionDelay>,
+        client_id: ibc::ClientId,
+        params: Vec<u8>,
+    ) -> Result<()> {
+        let msg = cf_solana::MsgUpdateClientParams::decode(&params)
+            .map_err(|_| error!(error::Error::InvalidClientParams))?;
+
+        let mut store = storage::from_ctx!(ctx);
+
+        let state = match store
+            .client_state(&client_id)
+            .map_err(error::Error::ContextError)
+            .map_err(move |err| error!((&err)))?
+        {
+            client_state::AnyClientState::Rollup(state) => state,
+            _ => return Err(error!(error::Error::InvalidClientParams)),
+        };
+        let updated_state = state.with_params(&msg).map_err(|err| {
+            msg!("update_client_params: {}", err);
+            error!(error::Error::InvalidClientParams)
+        })?;
+
+        events::emit(events::ClientParamsUpdate {
+            client_id: events::client_id(&client_id),
+            old_trusting_period_ns: state.trusting_period_ns,
+            new_trusting_period_ns: updated_state.trusting_period_ns,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+
+        store
+            .store_client_state(
+                ibc::path::ClientStatePath(client_id),
+                updated_state.into(),
+            )
+            .map_err(error::Error::ContextError)
+            .map_err(move |err| error!((&err)))?;
+
+        Ok(())
+    }
+
+    /// Creates the program’s address lookup table and fills it with the
+    /// program’s static accounts.
+    ///
+    /// `recent_slot` must be a recent slot; together with the authority PDA
+    /// (with [`LOOKUP_TABLE_SEED`] seed) it determines address of the table.
+    /// Can be called by anyone but only once.  The payer pays rent of the
+    /// table.  See [`lookup_table`] module.
+    pub fn create_lookup_table(
+        ctx: Context<CreateLookupTable>,
+        recent_slot: u64,
+    ) -> Result<()> {
+        use solana_program::address_lookup_table::instruction;
+
+        let storage = &mut ctx.accounts.storage;
+        if storage.lookup_table.is_some() {
+            return Err(error!(error::Error::LookupTableAlreadyCreated));
+        }
+
+        let accounts = &ctx.accounts;
+        let (create, address) = instruction::create_lookup_table_signed(
+            accounts.authority.key(),
+            accounts.payer.key(),
+            recent_slot,
+        );
+        if address != accounts.lookup_table.key() {
+            return Err(error!(error::Error::InvalidLookupTable));
+        }
+        let extend = instruction::extend_lookup_table(
+            address,
+            accounts.authority.key(),
+            Some(accounts.payer.key()),
+            lookup_table::static_addresses(&crate::ID),
+        );
+
+        let bump = ctx.bumps.authority;
+        let seeds = [LOOKUP_TABLE_SEED, core::slice::from_ref(&bump)];
+        let account_infos = [
+            accounts.lookup_table.to_account_info(),
+            accounts.authority.to_account_info(),
+            accounts.payer.to_account_info(),
+            accounts.system_program.to_account_info(),
+        ];
+        solana_program::program::invoke_signed(&create, &account_infos, &[
+            &seeds[..],
+        ])?;
+        solana_program::program::invoke_signed(&extend, &account_infos, &[
+            &seeds[..],
+        ])?;
+
+        ctx.accounts.storage.lookup_table = Some(address);
+        msg!("Created lookup table {}", address);
+        Ok(())
+    }
+
+    /// Adds mint and escrow accounts of given tokens to the program’s address
+    /// lookup table.
+    ///
+    /// For each hashed full denom, the mint and escrow account of the token
+    /// must be passed in remaining accounts if they exist.  Only accounts
+    /// which are passed, exist and aren’t in the table yet are added.  This
+    /// way the table can’t be filled with accounts not used by the program.
+    /// Can be called by anyone; the payer pays for the additional rent.
+    pub fn extend_lookup_table<'a, 'info>(
+        ctx: Context<'a, 'a, 'a, 'info, ExtendLookupTable<'info>>,
+        hashed_full_denoms: Vec<CryptoHash>,
+    ) -> Result<()> {
+        use solana_program::address_lookup_table::instruction;
+
+        let accounts = &ctx.accounts;
+        if accounts.storage.lookup_table != Some(accounts.lookup_table.key()) {
+            return Err(error!(error::Error::InvalidLookupTable));
+        }
+        let existing = lookup_table::table_addresses(
+            &accounts.lookup_table.try_borrow_data()?,
+        )?;
+        let mut addresses = Vec::new();
+        for hashed_full_denom in hashed_full_denoms.iter() {
+            for address in
+                lookup_table::denom_addresses(&crate::ID, hashed_full_denom)
+            {
+                let exists = ctx.remaining_accounts.iter().any(|account| {
+                    account.key == &address && account.lamports() != 0
+                });
+                if exists &&
+                    !existing.contains(&address) &&
+                    !addresses.contains(&address)
+                {
+                    addresses.push(address);
+                }
+            }
+        }
+        if addresses.is_empty() {
+            return Ok(());
+        }
 
+        let extend = instruction::extend_lookup_table(
+            accounts.lookup_table.key(),
+            accounts.authority.key(),
+            Some(accounts.payer.key()),
+            addresses,
+        );
+        let bump = ctx.bumps.authority;
+        let seeds = [LOOKUP_TABLE_SEED, core::slice::from_ref(&bump)];
+        solana_program::program::invoke_signed(
+            &extend,
+            &[
+                accounts.lookup_table.to_account_info(),
+                accounts.authority.to_account_info(),
+                accounts.payer.to_account_info(),
+                accounts.system_program.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
         Ok(())
     }
 }
@@ -627,7 +1905,7 @@ pub struct Initialise<'info> {
     /// This account isn’t used directly by the instruction.  It is however
     /// initialised.
     #[account(init, payer = sender, seeds = [SOLANA_IBC_STORAGE_SEED],
-              bump, space = 10240)]
+              bump, space = STORAGE_INITIAL_SPACE)]
     storage: Account<'info, storage::PrivateStorage>,
 
     /// The guest blockchain data.
@@ -713,6 +1991,82 @@ pub struct SetStake<'info> {
     instruction: UncheckedAccount<'info>,
 }
 
+#[derive(Accounts)]
+pub struct RegisterCandidate<'info> {
+    #[account(mut)]
+    validator: Signer<'info>,
+
+    #[account(init, payer = validator,
+              seeds = [CANDIDATE_BOND_SEED, validator.key().as_ref()], bump,
+              space = 8 + storage::CandidateBond::INIT_SPACE)]
+    bond: Account<'info, storage::CandidateBond>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    /// The account holding the trie which corresponds to guest blockchain’s
+    /// state root.
+    ///
+    /// CHECK: Account’s owner is checked by [`storage::get_provable_from`]
+    /// function.
+    #[account(mut, seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+
+    /// The witness account holding trie’s state root.
+    ///
+    /// CHECK: Account’s owner and address is checked by
+    /// [`storage::get_provable_from`] function.
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [WITNESS_SEED, trie.key().as_ref()], bump)]
+    witness: UncheckedAccount<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnbondCandidate<'info> {
+    validator: Signer<'info>,
+
+    #[account(mut, seeds = [CANDIDATE_BOND_SEED, validator.key().as_ref()],
+              bump)]
+    bond: Account<'info, storage::CandidateBond>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    /// The account holding the trie which corresponds to guest blockchain’s
+    /// state root.
+    ///
+    /// CHECK: Account’s owner is checked by [`storage::get_provable_from`]
+    /// function.
+    #[account(mut, seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+
+    /// The witness account holding trie’s state root.
+    ///
+    /// CHECK: Account’s owner and address is checked by
+    /// [`storage::get_provable_from`] function.
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [WITNESS_SEED, trie.key().as_ref()], bump)]
+    witness: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCandidateBond<'info> {
+    #[account(mut)]
+    validator: Signer<'info>,
+
+    #[account(mut, close = validator,
+              seeds = [CANDIDATE_BOND_SEED, validator.key().as_ref()], bump)]
+    bond: Account<'info, storage::CandidateBond>,
+
+    /// The guest blockchain data.
+    #[account(seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+}
+
 #[derive(Accounts)]
 pub struct ChainWithVerifier<'info> {
     sender: Signer<'info>,
@@ -744,6 +2098,48 @@ pub struct ChainWithVerifier<'info> {
     system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct InitHookStats<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    #[account(init, payer = payer, seeds = [HOOK_STATS_SEED], bump,
+              space = 8 + storage::HookStats::INIT_SPACE)]
+    hook_stats: Account<'info, storage::HookStats>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitRelayerStats<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    #[account(init, payer = payer, seeds = [RELAYER_STATS_SEED], bump,
+              space = 8 + storage::RelayerStats::INIT_SPACE)]
+    relayer_stats: Account<'info, storage::RelayerStats>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RelayerStatsQuery<'info> {
+    #[account(seeds = [RELAYER_STATS_SEED], bump)]
+    relayer_stats: Account<'info, storage::RelayerStats>,
+}
+
+#[derive(Accounts)]
+pub struct PruneRelayerStats<'info> {
+    fee_collector: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump, has_one = fee_collector)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    #[account(mut, seeds = [RELAYER_STATS_SEED], bump)]
+    relayer_stats: Account<'info, storage::RelayerStats>,
+}
+
 #[derive(Accounts)]
 pub struct SetFeeAmount<'info> {
     fee_collector: Signer<'info>,
@@ -762,6 +2158,97 @@ pub struct SetupFeeCollector<'info> {
     storage: Account<'info, storage::PrivateStorage>,
 }
 
+#[derive(Accounts)]
+pub struct SetupMultisigFeeCollector<'info> {
+    fee_collector: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The multisig account whose vault becomes the fee collector.
+    ///
+    /// CHECK: Account’s owner is checked in the instruction.
+    multisig: UncheckedAccount<'info>,
+
+    /// CHECK: The multisig program.
+    #[account(executable)]
+    multisig_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeGovernanceAction<'info> {
+    approver: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeUpgrade<'info> {
+    approver: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The program’s upgrade authority.
+    ///
+    /// CHECK: Only its address is used.
+    #[account(seeds = [UPGRADE_AUTHORITY_SEED], bump)]
+    upgrade_authority: UncheckedAccount<'info>,
+
+    /// Buffer holding the new program.
+    ///
+    /// CHECK: Contents are checked in the instruction.
+    #[account(owner = solana_program::bpf_loader_upgradeable::ID)]
+    buffer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteUpgrade<'info> {
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The program’s upgrade authority.
+    ///
+    /// CHECK: Only used as a signer of the upgrade instruction.
+    #[account(seeds = [UPGRADE_AUTHORITY_SEED], bump)]
+    upgrade_authority: UncheckedAccount<'info>,
+
+    /// CHECK: This program.
+    #[account(mut, address = crate::ID)]
+    program: UncheckedAccount<'info>,
+
+    /// The program’s data account.
+    ///
+    /// CHECK: Verified by the BPF loader.
+    #[account(mut)]
+    program_data: UncheckedAccount<'info>,
+
+    /// Buffer holding the new program.
+    ///
+    /// CHECK: Checked against the proposal in the instruction and verified
+    /// by the BPF loader.
+    #[account(mut, owner = solana_program::bpf_loader_upgradeable::ID)]
+    buffer: UncheckedAccount<'info>,
+
+    /// Account receiving lamports of the buffer account.
+    ///
+    /// CHECK: Any account can receive the lamports.
+    #[account(mut)]
+    spill: UncheckedAccount<'info>,
+
+    rent: Sysvar<'info, Rent>,
+    clock: Sysvar<'info, Clock>,
+
+    /// CHECK: The upgradeable BPF loader.
+    #[account(address = solana_program::bpf_loader_upgradeable::ID)]
+    bpf_loader: UncheckedAccount<'info>,
+}
+
 #[derive(Accounts)]
 pub struct CollectFees<'info> {
     fee_collector: Signer<'info>,
@@ -771,11 +2258,111 @@ pub struct CollectFees<'info> {
               has_one = fee_collector)]
     storage: Account<'info, storage::PrivateStorage>,
 
+    /// The guest blockchain data.
+    #[account(seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    #[account(mut, seeds = [FEE_SEED], bump)]
+    /// CHECK:
+    fee_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBlockRewards<'info> {
+    #[account(mut)]
+    validator: Signer<'info>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
     #[account(mut, seeds = [FEE_SEED], bump)]
     /// CHECK:
     fee_account: UncheckedAccount<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ChainQuery<'info> {
+    /// The guest blockchain data.
+    #[account(seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+}
+
+#[derive(Accounts)]
+pub struct ChainTrieQuery<'info> {
+    /// The guest blockchain data.
+    #[account(seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    /// The account holding provable IBC storage, i.e. the trie.
+    ///
+    /// CHECK: Account’s owner is checked by
+    /// [`solana_trie::TrieAccount::from_account_info`] function.
+    #[account(seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StorageQuery<'info> {
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, PrivateStorage>,
+}
+
+#[derive(Accounts)]
+pub struct SelfCheck<'info> {
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, PrivateStorage>,
+
+    /// The account holding provable IBC storage, i.e. the trie.
+    ///
+    /// CHECK: Only the data is read and validated by the instruction.
+    #[account(seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+
+    /// The witness account holding trie’s state root.
+    ///
+    /// CHECK: Only the data is read and validated by the instruction.
+    #[cfg(feature = "witness")]
+    #[account(seeds = [WITNESS_SEED, trie.key().as_ref()], bump)]
+    witness: UncheckedAccount<'info>,
+
+    #[account(seeds = [FEE_SEED], bump)]
+    /// CHECK:
+    fee_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(hashed_full_denom: CryptoHash)]
+pub struct SyncDenomMetadata<'info> {
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, PrivateStorage>,
+
+    /// CHECK: Update authority of the metadata account.
+    #[account(seeds = [MINT_ESCROW_SEED], bump)]
+    mint_authority: UncheckedAccount<'info>,
+
+    #[account(seeds = [MINT, hashed_full_denom.as_ref()], bump)]
+    token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [
+            METADATA,
+            token_metadata_program.key().as_ref(),
+            token_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    /// CHECK:
+    metadata: UncheckedAccount<'info>,
+
+    token_metadata_program: Program<'info, Metadata>,
+}
+
 #[derive(Accounts)]
 #[instruction(decimals: u8, hashed_full_denom: CryptoHash)]
 pub struct InitMint<'info> {
@@ -803,16 +2390,39 @@ pub struct InitMint<'info> {
     #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
     storage: Account<'info, PrivateStorage>,
 
-    #[account(init, payer = sender,
-              seeds = [MINT, hashed_full_denom.as_ref()],
-              bump, mint::decimals = decimals, mint::authority = mint_authority)]
-    token_mint: Account<'info, Mint>,
+    #[account(init, payer = sender,
+              seeds = [MINT, hashed_full_denom.as_ref()],
+              bump, mint::decimals = decimals, mint::authority = mint_authority)]
+    token_mint: Account<'info, Mint>,
+
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+
+    rent: Sysvar<'info, Rent>,
+    token_metadata_program: Program<'info, Metadata>,
+}
+
+#[derive(Accounts)]
+#[instruction(decimals: u8, hashed_full_denom: CryptoHash)]
+pub struct InitMint2022<'info> {
+    #[account(mut, constraint = sender.key == &storage.fee_collector)]
+    sender: Signer<'info>,
+
+    /// CHECK:
+    #[account(init_if_needed, payer = sender, seeds = [MINT_ESCROW_SEED],
+              bump, space = 0)]
+    mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, PrivateStorage>,
+
+    /// CHECK: The account is created and initialised as Token-2022 mint by
+    /// the instruction.
+    #[account(mut, seeds = [MINT, hashed_full_denom.as_ref()], bump)]
+    token_mint: UncheckedAccount<'info>,
 
-    token_program: Program<'info, Token>,
+    token_program: Program<'info, Token2022>,
     system_program: Program<'info, System>,
-
-    rent: Sysvar<'info, Rent>,
-    token_metadata_program: Program<'info, Metadata>,
 }
 
 #[derive(Accounts, Clone)]
@@ -850,21 +2460,57 @@ pub struct Deliver<'info> {
     /// CHECK:
     mint_authority: Option<UncheckedAccount<'info>>,
     #[account(mut)]
-    token_mint: Option<Box<Account<'info, Mint>>>,
+    token_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
     #[account(mut)]
     escrow_account: Option<UncheckedAccount<'info>>,
     #[account(init_if_needed, payer = sender,
         associated_token::mint = token_mint,
-        associated_token::authority = receiver)]
-    receiver_token_account: Option<Box<Account<'info, TokenAccount>>>,
+        associated_token::authority = receiver,
+        associated_token::token_program = token_program)]
+    receiver_token_account:
+        Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
 
     #[account(mut, seeds = [FEE_SEED], bump)]
     /// CHECK:
     fee_collector: Option<UncheckedAccount<'info>>,
 
     associated_token_program: Option<Program<'info, AssociatedToken>>,
-    token_program: Option<Program<'info, Token>>,
+    token_program: Option<Interface<'info, TokenInterface>>,
     system_program: Program<'info, System>,
+
+    /// Receiver’s pending claim token account.  Only needed if receiver token
+    /// account has delegate or close authority set and
+    /// [`storage::ReceiverAccountPolicy::Escrow`] policy is in effect.
+    ///
+    /// CHECK: Address is checked when crediting the account.
+    #[account(mut)]
+    pending_claim_account: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPendingTransfer<'info> {
+    receiver: Signer<'info>,
+
+    /// CHECK: PDA owning receiver’s pending claim token accounts.
+    #[account(seeds = [PENDING_CLAIM_SEED, receiver.key().as_ref()], bump)]
+    pending_claim_authority: UncheckedAccount<'info>,
+
+    token_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    #[account(mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = pending_claim_authority,
+        associated_token::token_program = token_program)]
+    pending_claim_account:
+        Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// Token account to transfer the tokens to.
+    #[account(mut,
+        token::mint = token_mint,
+        token::token_program = token_program)]
+    destination: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -933,19 +2579,106 @@ pub struct SendTransfer<'info> {
     /// CHECK:
     mint_authority: Option<UncheckedAccount<'info>>,
     #[account(mut)]
-    token_mint: Option<Box<Account<'info, Mint>>>,
+    token_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
     #[account(init_if_needed, payer = sender, seeds = [
         ESCROW, hashed_full_denom.as_ref()
-    ], bump, token::mint = token_mint, token::authority = mint_authority)]
-    escrow_account: Option<Box<Account<'info, TokenAccount>>>,
-    #[account(mut, associated_token::mint = token_mint, associated_token::authority = sender)]
-    receiver_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    ], bump, token::mint = token_mint, token::authority = mint_authority,
+    token::token_program = token_program)]
+    escrow_account:
+        Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(mut, associated_token::mint = token_mint, associated_token::authority = sender,
+              associated_token::token_program = token_program)]
+    receiver_token_account:
+        Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
 
     #[account(init_if_needed, payer = sender, seeds = [FEE_SEED], bump, space = 0)]
     /// CHECK:
     fee_collector: Option<UncheckedAccount<'info>>,
 
-    token_program: Option<Program<'info, Token>>,
+    token_program: Option<Interface<'info, TokenInterface>>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SendNftTransfer<'info> {
+    #[account(mut)]
+    sender: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The account holding provable IBC storage, i.e. the trie.
+    ///
+    /// CHECK: Account’s owner is checked by [`storage::get_provable_from`]
+    /// function.
+    #[account(mut, seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+
+    /// The witness account holding trie’s state root.
+    ///
+    /// CHECK: Account’s owner and address is checked by
+    /// [`storage::get_provable_from`] function.
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [WITNESS_SEED, trie.key().as_ref()], bump)]
+    witness: UncheckedAccount<'info>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Box<Account<'info, chain::ChainData>>,
+
+    /// CHECK:
+    #[account(seeds = [MINT_ESCROW_SEED], bump)]
+    mint_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    token_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(mut, associated_token::mint = token_mint,
+              associated_token::authority = sender,
+              associated_token::token_program = token_program)]
+    sender_token_account:
+        Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Escrow account of the NFT.  Only needed if the NFT is sent from its
+    /// source chain.
+    #[account(init_if_needed, payer = sender,
+              seeds = [NFT_ESCROW_SEED, token_mint.key().as_ref()], bump,
+              token::mint = token_mint, token::authority = mint_authority,
+              token::token_program = token_program)]
+    escrow_account:
+        Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    /// Metaplex metadata of the NFT.  Only needed for Solana-native NFTs.
+    ///
+    /// CHECK: Address and owner are checked when verifying the class.
+    metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(init_if_needed, payer = sender, seeds = [FEE_SEED], bump, space = 0)]
+    /// CHECK:
+    fee_collector: UncheckedAccount<'info>,
+
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(hashed_full_class_id: CryptoHash, hashed_token_id: CryptoHash)]
+pub struct InitNftMint<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    /// CHECK:
+    #[account(init_if_needed, payer = payer, seeds = [MINT_ESCROW_SEED],
+              bump, space = 0)]
+    mint_authority: UncheckedAccount<'info>,
+
+    #[account(init, payer = payer,
+              seeds = [
+                  NFT_MINT_SEED,
+                  hashed_full_class_id.as_ref(),
+                  hashed_token_id.as_ref(),
+              ],
+              bump, mint::decimals = 0, mint::authority = mint_authority)]
+    token_mint: Account<'info, Mint>,
+
+    token_program: Program<'info, Token>,
     system_program: Program<'info, System>,
 }
 
@@ -962,6 +2695,74 @@ pub struct UpdateChainConfig<'info> {
     chain: Account<'info, chain::ChainData>,
 }
 
+#[derive(Accounts)]
+pub struct SetFingerprintDomain<'info> {
+    fee_collector: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump, has_one = fee_collector)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    /// CHECK: The SlotHashes sysvar; read directly since it’s too large to
+    /// be deserialised.
+    #[account(address = solana_program::sysvar::slot_hashes::ID)]
+    slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateLookupTable<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// CHECK: PDA which is authority of the lookup table.
+    #[account(seeds = [LOOKUP_TABLE_SEED], bump)]
+    authority: UncheckedAccount<'info>,
+
+    /// CHECK: The lookup table being created; its address is checked by the
+    /// instruction.
+    #[account(mut)]
+    lookup_table: UncheckedAccount<'info>,
+
+    /// CHECK: The address lookup table program.
+    #[account(address = solana_program::address_lookup_table::program::ID)]
+    address_lookup_table_program: UncheckedAccount<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendLookupTable<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// CHECK: PDA which is authority of the lookup table.
+    #[account(seeds = [LOOKUP_TABLE_SEED], bump)]
+    authority: UncheckedAccount<'info>,
+
+    /// CHECK: The program’s lookup table; address is checked by the
+    /// instruction against the one stored in private storage.
+    #[account(mut)]
+    lookup_table: UncheckedAccount<'info>,
+
+    /// CHECK: The address lookup table program.
+    #[account(address = solana_program::address_lookup_table::program::ID)]
+    address_lookup_table_program: UncheckedAccount<'info>,
+
+    system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 #[instruction(new_length: usize)]
 pub struct ReallocAccounts<'info> {
@@ -974,6 +2775,39 @@ pub struct ReallocAccounts<'info> {
     system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct PruneConsensusStates<'info> {
+    #[account(mut)]
+    sender: Signer<'info>,
+
+    /// The account holding private IBC storage.
+    #[account(mut, seeds = [SOLANA_IBC_STORAGE_SEED], bump)]
+    storage: Account<'info, storage::PrivateStorage>,
+
+    /// The guest blockchain data.
+    #[account(mut, seeds = [CHAIN_SEED], bump)]
+    chain: Account<'info, chain::ChainData>,
+
+    /// The account holding provable IBC storage, i.e. the trie.
+    ///
+    /// CHECK: Account’s owner is checked by [`storage::get_provable_from`]
+    /// function.
+    #[account(mut, seeds = [TRIE_SEED], bump)]
+    trie: UncheckedAccount<'info>,
+
+    /// The witness account holding trie’s state root.
+    ///
+    /// CHECK: Account’s owner and address is checked by
+    /// [`storage::get_provable_from`] function.
+    #[cfg(feature = "witness")]
+    #[account(mut, seeds = [WITNESS_SEED, trie.key().as_ref()], bump)]
+    witness: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [FEE_SEED], bump)]
+    /// CHECK:
+    fee_account: UncheckedAccount<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateConnectionDelay<'info> {
     pub sender: Signer<'info>,
@@ -1008,6 +2842,9 @@ impl ibc::Router for storage::IbcStorage<'_, '_> {
         let module_id = core::borrow::Borrow::borrow(module_id);
         match module_id {
             ibc::apps::transfer::types::MODULE_ID_STR => Some(self),
+            nft_transfer::MODULE_ID_STR => {
+                Some(nft_transfer::NftModule::wrap_ref(self))
+            }
             _ => None,
         }
     }
@@ -1019,6 +2856,9 @@ impl ibc::Router for storage::IbcStorage<'_, '_> {
         let module_id = core::borrow::Borrow::borrow(module_id);
         match module_id {
             ibc::apps::transfer::types::MODULE_ID_STR => Some(self),
+            nft_transfer::MODULE_ID_STR => {
+                Some(nft_transfer::NftModule::wrap_mut(self))
+            }
             _ => None,
         }
     }
@@ -1030,11 +2870,305 @@ impl ibc::Router for storage::IbcStorage<'_, '_> {
                     ibc::apps::transfer::types::MODULE_ID_STR.to_string(),
                 ))
             }
+            nft_transfer::PORT_ID_STR => Some(ibc::ModuleId::new(
+                nft_transfer::MODULE_ID_STR.to_string(),
+            )),
             _ => None,
         }
     }
 }
 
+/// Registers a new wrapped asset in the private storage.
+///
+/// Fails if the asset already exists or effective decimals are greater than
+/// original decimals.
+fn add_asset(
+    private_storage: &mut PrivateStorage,
+    hashed_full_denom: CryptoHash,
+    effective_decimals: u8,
+    original_decimals: u8,
+) -> Result<()> {
+    if effective_decimals > original_decimals {
+        return Err(error!(error::Error::InvalidDecimals));
+    }
+    if private_storage.assets.contains_key(&hashed_full_denom) {
+        return Err(error!(error::Error::AssetAlreadyExists));
+    }
+    private_storage.assets.insert(hashed_full_denom, storage::Asset {
+        original_decimals,
+        effective_decimals_on_sol: effective_decimals,
+    });
+    Ok(())
+}
+
+/// Dispatches IBC messages delivered via `deliver` or `deliver_batch`
+/// instructions.
+#[allow(unused_variables)]
+fn deliver_messages<'a, 'info>(
+    mut ctx: Context<'a, 'a, 'a, 'info, Deliver<'info>>,
+    messages: impl IntoIterator<Item = ibc::MsgEnvelope>,
+) -> Result<()> {
+    #[cfg(not(feature = "mocks"))]
+    if !relayer::check_id(ctx.accounts.sender.key) {
+        msg!("Only {} can call this method", relayer::ID);
+        return Err(error!(error::Error::InvalidSigner));
+    }
+
+    let sig_verify_program_id = ctx.accounts.chain.sig_verify_program_id()?;
+    let halt_flags = ctx.accounts.storage.halt_flags;
+    let pending_claim_account = ctx
+        .accounts
+        .pending_claim_account
+        .as_deref()
+        .map(ToAccountInfo::to_account_info);
+
+    let mut store = storage::from_ctx!(ctx, with accounts);
+    store.borrow_mut().accounts.pending_claim_account = pending_claim_account;
+    let mut router = store.clone();
+
+    if let Some((last, rest)) = ctx.remaining_accounts.split_last() {
+        let mut verifier = sigverify::Verifier::default();
+        if verifier
+            .set_sigverify_account(
+                unsafe { core::mem::transmute(last) },
+                &sig_verify_program_id,
+            )
+            .is_ok()
+        {
+            global().set_verifier(verifier);
+            ctx.remaining_accounts = rest;
+        }
+    }
+    let height = store.borrow().chain.head()?.block_height;
+    // height just before the data is added to the trie.
+    msg!("Current Block height {}", height);
+    let previous_root = *store.borrow().provable.hash();
+    let sender_lamports = ctx.accounts.sender.lamports();
+    let mut delivery = storage::RelayerDelivery::default();
+
+    #[cfg(feature = "ack-proof")]
+    let mut ack_paths = Vec::new();
+    for message in messages {
+        if let Some(flag) = message_halt_flag(&message) {
+            halt_flags.check(flag).map_err(|err| error!((&err)))?;
+        }
+        #[cfg(feature = "ack-proof")]
+        if let ibc::MsgEnvelope::Packet(ibc::PacketMsg::Recv(msg)) = &message {
+            ack_paths.push(ibc::path::AckPath::new(
+                &msg.packet.port_id_on_b,
+                &msg.packet.chan_id_on_b,
+                msg.packet.seq_on_a,
+            ));
+        }
+        let seen_sequence = message_sequence(&message);
+        count_delivery(&mut delivery, &message);
+        ::ibc::core::entrypoint::dispatch(&mut store, &mut router, message)
+            .map_err(error::Error::ContextError)
+            .map_err(move |err| error!((&err)))?;
+        if let Some(err) = store.borrow_mut().hook_error.take() {
+            return Err(err.into());
+        }
+        if let Some(seen_sequence) = seen_sequence {
+            record_sequence(&store, seen_sequence)?;
+        }
+    }
+
+    #[cfg(feature = "witness")]
+    {
+        let root = *store.borrow().provable.hash();
+        if previous_root != root {
+            msg!("Writing local consensus state");
+            let clock = mocks::clock()?;
+            let slot = clock.slot;
+            let timestamp = clock.unix_timestamp as u64;
+            store
+                .borrow_mut()
+                .private
+                .add_local_consensus_state(slot, timestamp, root)
+                .unwrap();
+        }
+    }
+
+    #[cfg(feature = "ack-proof")]
+    emit_ack_proofs(&store, ack_paths)?;
+
+    delivery.fees_paid =
+        sender_lamports.saturating_sub(ctx.accounts.sender.lamports());
+    update_relayer_stats(
+        ctx.remaining_accounts,
+        ctx.accounts.sender.key,
+        delivery,
+    );
+
+    #[cfg(feature = "heap-stats")]
+    allocator::log_stats();
+
+    Ok(())
+}
+
+/// Returns halt flag which, when set, prevents delivery of given message.
+///
+/// Submitting misbehaviour is never halted since it only ever freezes
+/// a client.
+fn message_halt_flag(message: &ibc::MsgEnvelope) -> Option<storage::HaltFlag> {
+    match message {
+        ibc::MsgEnvelope::Client(
+            ibc::ClientMsg::UpdateClient(_) | ibc::ClientMsg::UpgradeClient(_),
+        ) => Some(storage::HaltFlag::ClientUpdates),
+        ibc::MsgEnvelope::Packet(_) => Some(storage::HaltFlag::Inbound),
+        _ => None,
+    }
+}
+
+/// Increments counter in `delivery` corresponding to kind of given message.
+fn count_delivery(
+    delivery: &mut storage::RelayerDelivery,
+    message: &ibc::MsgEnvelope,
+) {
+    let counter = match message {
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::Recv(_)) => {
+            &mut delivery.packets_delivered
+        }
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::Ack(_)) => &mut delivery.acks,
+        ibc::MsgEnvelope::Packet(
+            ibc::PacketMsg::Timeout(_) | ibc::PacketMsg::TimeoutOnClose(_),
+        ) => &mut delivery.timeouts,
+        _ => return,
+    };
+    *counter += 1;
+}
+
+/// Records messages delivered by `relayer` in the relayer statistics account.
+///
+/// The account is looked up among `accounts`.  If the relayer didn’t pass it
+/// or no packet messages were delivered, does nothing.  Failure to update the
+/// statistics doesn’t fail the delivery.
+fn update_relayer_stats(
+    accounts: &[AccountInfo],
+    relayer: &Pubkey,
+    delivery: storage::RelayerDelivery,
+) {
+    if delivery.packets_delivered == 0 &&
+        delivery.acks == 0 &&
+        delivery.timeouts == 0
+    {
+        return;
+    }
+    let stats = accounts
+        .iter()
+        .filter(|account| account.owner == &crate::ID && account.is_writable)
+        .find_map(|account| {
+            Account::<storage::RelayerStats>::try_from(account).ok()
+        });
+    let mut stats = match stats {
+        Some(stats) => stats,
+        None => return,
+    };
+    let slot = mocks::clock().map_or(0, |clock| clock.slot);
+    stats.record(relayer, delivery, slot);
+    if let Err(err) = stats.exit(&crate::ID) {
+        msg!("Failed updating relayer stats: {}", err);
+    }
+}
+
+/// Returns channel, kind and sequence of the packet carried by given message
+/// for tracking in [`storage::LastSeenSequences`].
+///
+/// Returns `None` for messages which don’t carry packets.
+fn message_sequence(
+    message: &ibc::MsgEnvelope,
+) -> Option<(ibc::PortId, ibc::ChannelId, storage::SeenSequenceKind, u64)> {
+    let (packet, kind, on_b) = match message {
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::Recv(msg)) => {
+            (&msg.packet, storage::SeenSequenceKind::Recv, true)
+        }
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::Ack(msg)) => {
+            (&msg.packet, storage::SeenSequenceKind::Ack, false)
+        }
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::Timeout(msg)) => {
+            (&msg.packet, storage::SeenSequenceKind::Ack, false)
+        }
+        ibc::MsgEnvelope::Packet(ibc::PacketMsg::TimeoutOnClose(msg)) => {
+            (&msg.packet, storage::SeenSequenceKind::Ack, false)
+        }
+        _ => return None,
+    };
+    let (port_id, channel_id) = if on_b {
+        (&packet.port_id_on_b, &packet.chan_id_on_b)
+    } else {
+        (&packet.port_id_on_a, &packet.chan_id_on_a)
+    };
+    Some((port_id.clone(), channel_id.clone(), kind, packet.seq_on_a.value()))
+}
+
+/// Records sequence of a delivered packet and emits
+/// [`events::SequenceGap`] event if it skipped over unprocessed sequences.
+fn record_sequence(
+    store: &storage::IbcStorage,
+    (port_id, channel_id, kind, sequence): (
+        ibc::PortId,
+        ibc::ChannelId,
+        storage::SeenSequenceKind,
+        u64,
+    ),
+) -> Result<()> {
+    let key = match trie_ids::PortChannelPK::try_from(&port_id, &channel_id) {
+        Ok(key) => key,
+        Err(_) => return Ok(()),
+    };
+    let gap = store
+        .borrow_mut()
+        .private
+        .last_seen_sequences
+        .entry(key)
+        .or_insert(Default::default())
+        .observe(kind, sequence);
+    if let Some((first_missing, last_missing)) = gap {
+        events::emit(events::SequenceGap {
+            port_id,
+            channel_id,
+            kind,
+            first_missing,
+            last_missing,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+    }
+    Ok(())
+}
+
+/// Emits proofs of acknowledgements written for given paths.
+///
+/// Paths for which no acknowledgement has been written (e.g. because the
+/// application acknowledges packets asynchronously) are skipped.
+#[cfg(feature = "ack-proof")]
+fn emit_ack_proofs(
+    store: &storage::IbcStorage,
+    ack_paths: Vec<ibc::path::AckPath>,
+) -> Result<()> {
+    let store = store.borrow();
+    for path in ack_paths {
+        let proof = cf_solana::proof::generate_for_trie(
+            &*store.provable,
+            path.clone().into(),
+        )
+        .map_err(|_| {
+            error!(error::Error::Internal("Failed generating ack proof"))
+        })?;
+        if proof.value.is_none() {
+            continue;
+        }
+        events::emit(events::AcknowledgementProof {
+            port_id: path.port_id,
+            channel_id: path.channel_id,
+            sequence: path.sequence.value(),
+            root: proof.root,
+            proof: proof.proof,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+    }
+    Ok(())
+}
+
 /// Checks whether current instruction is a CPI whose caller is a staking
 /// program.
 ///
@@ -1073,6 +3207,73 @@ fn check_staking_program(program_id: &Pubkey) -> Result<()> {
     }
 }
 
+/// Returns upgrade governance configuration or `UpgradeGovernanceNotConfigured`
+/// error if it hasn’t been set up.
+fn upgrade_governance(
+    storage: &mut PrivateStorage,
+) -> Result<&mut storage::UpgradeGovernance> {
+    storage
+        .upgrade_governance
+        .as_mut()
+        .ok_or_else(|| error::Error::UpgradeGovernanceNotConfigured.into())
+}
+
+/// Returns hash of the program held in an upgradeable loader buffer account.
+///
+/// Fails with `InvalidUpgradeBuffer` if the account isn’t a buffer whose
+/// authority is `authority`.
+fn upgrade_buffer_hash(
+    buffer: &AccountInfo,
+    authority: &Pubkey,
+) -> Result<CryptoHash> {
+    let data = buffer.try_borrow_data()?;
+    buffer_program_hash(&data, authority)
+        .ok_or_else(|| error!(error::Error::InvalidUpgradeBuffer))
+}
+
+/// Parses data of an upgradeable loader buffer account and returns hash of the
+/// program it holds if buffer’s authority is `authority`.
+///
+/// The buffer starts with bincode-encoded `UpgradeableLoaderState::Buffer`,
+/// i.e. a four-byte little-endian variant index equal one followed by
+/// optional authority address.
+fn buffer_program_hash(data: &[u8], authority: &Pubkey) -> Option<CryptoHash> {
+    let (variant, data) = stdx::split_at::<4, u8>(data)?;
+    let (is_some, data) = stdx::split_at::<1, u8>(data)?;
+    let (address, program) = stdx::split_at::<32, u8>(data)?;
+    (u32::from_le_bytes(*variant) == 1 &&
+        is_some == &[1] &&
+        address == &authority.to_bytes())
+        .then(|| CryptoHash::digest(program))
+}
+
+#[test]
+fn test_buffer_program_hash() {
+    use solana_program::bpf_loader_upgradeable::UpgradeableLoaderState;
+
+    let authority = Pubkey::new_unique();
+    let program = [1, 2, 3, 4, 5];
+    let mut data = alloc::vec![1, 0, 0, 0, 1];
+    data.extend_from_slice(authority.as_ref());
+    assert_eq!(UpgradeableLoaderState::size_of_buffer_metadata(), data.len());
+    data.extend_from_slice(&program);
+
+    let want = CryptoHash::digest(&program);
+    assert_eq!(Some(want), buffer_program_hash(&data, &authority));
+    let other = Pubkey::new_unique();
+    assert_eq!(None, buffer_program_hash(&data, &other));
+
+    // Buffer without authority.
+    let mut bad = data.clone();
+    bad[4] = 0;
+    assert_eq!(None, buffer_program_hash(&bad, &authority));
+    // Program data account rather than a buffer.
+    let mut bad = data.clone();
+    bad[0] = 3;
+    assert_eq!(None, buffer_program_hash(&bad, &authority));
+    assert_eq!(None, buffer_program_hash(&data[..20], &authority));
+}
+
 #[test]
 fn test_staking_program() {
     const GOOD_ONE: &str = "8n3FHwYxFgQCQc2FNFkwDUf9mcqupxXcCvgfHbApMLv3";
@@ -1082,3 +3283,140 @@ fn test_staking_program() {
     check_staking_program(&GOOD_TWO.parse().unwrap()).unwrap();
     check_staking_program(&BAD.parse().unwrap()).unwrap_err();
 }
+
+#[test]
+fn test_multisig_fee_collector() {
+    use anchor_lang::{Discriminator, InstructionData};
+
+    fn account(
+        key: Pubkey,
+        owner: Pubkey,
+        is_signer: bool,
+        data: Vec<u8>,
+    ) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            is_signer,
+            true,
+            Box::leak(Box::new(1_000_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    fn run(
+        accounts: Vec<AccountInfo<'static>>,
+        ix: impl InstructionData,
+    ) -> core::result::Result<(), ProgramError> {
+        entry(&crate::ID, Box::leak(accounts.into_boxed_slice()), &ix.data())
+    }
+
+    fn err(err: error::Error) -> core::result::Result<(), ProgramError> {
+        Err(anchor_lang::error::Error::from(err).into())
+    }
+
+    fn anchor_err(
+        code: anchor_lang::error::ErrorCode,
+    ) -> core::result::Result<(), ProgramError> {
+        Err(ProgramError::Custom(code.into()))
+    }
+
+    let system = solana_program::system_program::ID;
+    let (storage_key, _) =
+        Pubkey::find_program_address(&[SOLANA_IBC_STORAGE_SEED], &crate::ID);
+    let mut data = PrivateStorage::DISCRIMINATOR.to_vec();
+    data.resize(STORAGE_INITIAL_SPACE, 0);
+    let storage = account(storage_key, crate::ID, false, data);
+    let get_storage = || {
+        let data = storage.try_borrow_data().unwrap();
+        PrivateStorage::try_deserialize(&mut &data[..]).unwrap()
+    };
+
+    // A mock multisig program and a multisig account it owns.
+    let multisig_program_id = Pubkey::new_unique();
+    let mut multisig_program =
+        account(multisig_program_id, system, false, vec![1]);
+    multisig_program.executable = true;
+    let multisig_key = Pubkey::new_unique();
+    let multisig = account(multisig_key, multisig_program_id, false, vec![1]);
+    let vault = storage::MultisigFeeCollector {
+        program_id: multisig_program_id,
+        multisig: multisig_key,
+        vault_index: 0,
+    }
+    .vault();
+
+    // Signer accounts.  Vault can only sign through the multisig program
+    // invoking this program with `invoke_signed`.
+    let admin_key = Pubkey::new_unique();
+    let admin = || account(admin_key, system, true, Vec::new());
+    let vault_signer = || account(vault, system, true, Vec::new());
+
+    // Set up regular fee collector.
+    run(vec![admin(), storage.clone()], instruction::SetupFeeCollector {
+        new_fee_collector: admin_key,
+    })
+    .unwrap();
+    assert_eq!(admin_key, get_storage().fee_collector);
+
+    // Multisig account must be owned by the multisig program.
+    let bogus = account(Pubkey::new_unique(), system, false, vec![1]);
+    assert_eq!(
+        err(error::Error::InvalidMultisig),
+        run(
+            vec![admin(), storage.clone(), bogus, multisig_program.clone()],
+            instruction::SetupMultisigFeeCollector { vault_index: 0 },
+        )
+    );
+
+    // Propose the vault as the new fee collector.
+    run(
+        vec![admin(), storage.clone(), multisig, multisig_program],
+        instruction::SetupMultisigFeeCollector { vault_index: 0 },
+    )
+    .unwrap();
+    assert_eq!(Some(vault), get_storage().new_fee_collector_proposal);
+
+    // Only the vault can accept the change.
+    assert_eq!(
+        err(error::Error::InvalidFeeCollector),
+        run(
+            vec![admin(), storage.clone()],
+            instruction::AcceptFeeCollectorChange {}
+        )
+    );
+    run(
+        vec![vault_signer(), storage.clone()],
+        instruction::AcceptFeeCollectorChange {},
+    )
+    .unwrap();
+    let private = get_storage();
+    assert_eq!(vault, private.fee_collector);
+    assert_eq!(
+        Some(multisig_key),
+        private.fee_collector_multisig.map(|multisig| multisig.multisig)
+    );
+
+    // Gated setters now require vault’s signature.
+    run(vec![vault_signer(), storage.clone()], instruction::SetFeeAmount {
+        new_amount: 42,
+    })
+    .unwrap();
+    assert_eq!(42, get_storage().fee_in_lamports);
+    assert_eq!(
+        anchor_err(anchor_lang::error::ErrorCode::ConstraintHasOne),
+        run(vec![admin(), storage.clone()], instruction::SetFeeAmount {
+            new_amount: 1
+        })
+    );
+    let unsigned_vault = account(vault, system, false, Vec::new());
+    assert_eq!(
+        anchor_err(anchor_lang::error::ErrorCode::AccountNotSigner),
+        run(vec![unsigned_vault, storage.clone()], instruction::SetFeeAmount {
+            new_amount: 1
+        })
+    );
+    assert_eq!(42, get_storage().fee_in_lamports);
+}
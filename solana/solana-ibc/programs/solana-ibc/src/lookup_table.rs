@@ -0,0 +1,186 @@
+//! Support for address lookup tables (ALTs) of the Solana IBC smart contract.
+//!
+//! Delivering IBC messages (especially ones triggering hooks) requires many
+//! accounts and with each address taking 32 bytes transactions easily exceed
+//! the 1232-byte and 64-account limits.  Versioned (v0) transactions can refer
+//! to accounts through address lookup tables using a one-byte index instead.
+//!
+//! The smart contract maintains a single lookup table whose authority is
+//! a PDA with [`crate::LOOKUP_TABLE_SEED`] seed.  The table is created with
+//! `create_lookup_table` instruction which fills it with the contract’s
+//! static accounts (see [`static_addresses`]) and can be extended by anyone
+//! with mint and escrow accounts of registered tokens through
+//! `extend_lookup_table` instruction.  Address of the table is stored in
+//! [`crate::storage::PrivateStorage::lookup_table`].
+//!
+//! With `client` feature enabled, [`compile_message`] builds a v0 message
+//! which uses the table for all accounts it contains.
+use anchor_lang::solana_program;
+use lib::hash::CryptoHash;
+use solana_program::pubkey::Pubkey;
+
+/// Returns static accounts of the smart contract deployed at `program_id`.
+///
+/// Those are accounts which are used by most instructions and whose addresses
+/// don’t depend on instruction arguments: the program itself, its storage,
+/// trie and chain accounts, mint authority, fee collector, hook and relayer
+/// statistics PDAs as well as system and token programs.
+pub fn static_addresses(program_id: &Pubkey) -> Vec<Pubkey> {
+    let pda =
+        |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, program_id).0;
+    let trie = pda(&[crate::TRIE_SEED]);
+    #[allow(unused_mut)]
+    let mut addresses = vec![
+        *program_id,
+        pda(&[crate::SOLANA_IBC_STORAGE_SEED]),
+        pda(&[crate::CHAIN_SEED]),
+        trie,
+        pda(&[crate::MINT_ESCROW_SEED]),
+        pda(&[crate::FEE_SEED]),
+        pda(&[crate::HOOK_STATS_SEED]),
+        pda(&[crate::RELAYER_STATS_SEED]),
+        solana_program::system_program::ID,
+        solana_program::sysvar::instructions::ID,
+        anchor_spl::token::ID,
+        anchor_spl::token_2022::ID,
+        anchor_spl::associated_token::ID,
+    ];
+    #[cfg(feature = "witness")]
+    addresses.push(pda(&[crate::WITNESS_SEED, trie.as_ref()]));
+    addresses
+}
+
+/// Returns addresses of the mint and escrow accounts of token with given
+/// hashed full denom.
+///
+/// Only one of them exists for any given token: the mint if the token is
+/// wrapped by the contract and the escrow if it’s a native token.
+pub fn denom_addresses(
+    program_id: &Pubkey,
+    hashed_full_denom: &CryptoHash,
+) -> [Pubkey; 2] {
+    let pda = |seed: &[u8]| {
+        Pubkey::find_program_address(
+            &[seed, hashed_full_denom.as_ref()],
+            program_id,
+        )
+        .0
+    };
+    [pda(crate::MINT), pda(crate::ESCROW)]
+}
+
+/// Returns addresses stored in the lookup table with given account data.
+pub(crate) fn table_addresses(
+    data: &[u8],
+) -> Result<Vec<Pubkey>, solana_program::program_error::ProgramError> {
+    use solana_program::address_lookup_table::state::AddressLookupTable;
+    AddressLookupTable::deserialize(data)
+        .map(|table| table.addresses.to_vec())
+        .map_err(|_| {
+            solana_program::program_error::ProgramError::InvalidAccountData
+        })
+}
+
+/// Compiles a v0 message using the lookup table to reference accounts.
+///
+/// `table` is the smart contract’s lookup table; it can be constructed from
+/// the account’s data with [`lookup_table_account`].  Accounts of
+/// `instructions` which are in the table are referenced through it; all the
+/// other accounts (including all signers) are stored in the message directly.
+/// The message has to be signed and sent as a versioned transaction.
+#[cfg(feature = "client")]
+pub fn compile_message(
+    payer: &Pubkey,
+    instructions: &[solana_program::instruction::Instruction],
+    table: solana_program::address_lookup_table::AddressLookupTableAccount,
+    recent_blockhash: solana_program::hash::Hash,
+) -> Result<
+    solana_program::message::VersionedMessage,
+    solana_program::message::CompileError,
+> {
+    solana_program::message::v0::Message::try_compile(
+        payer,
+        instructions,
+        &[table],
+        recent_blockhash,
+    )
+    .map(solana_program::message::VersionedMessage::V0)
+}
+
+/// Constructs lookup table account description from its address and data.
+#[cfg(feature = "client")]
+pub fn lookup_table_account(
+    key: Pubkey,
+    data: &[u8],
+) -> Result<
+    solana_program::address_lookup_table::AddressLookupTableAccount,
+    solana_program::program_error::ProgramError,
+> {
+    Ok(solana_program::address_lookup_table::AddressLookupTableAccount {
+        key,
+        addresses: table_addresses(data)?,
+    })
+}
+
+#[test]
+fn test_static_addresses() {
+    let program_id = Pubkey::new_unique();
+    let addresses = static_addresses(&program_id);
+    assert_eq!(program_id, addresses[0]);
+    let mut sorted = addresses.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(addresses.len(), sorted.len());
+
+    let hash = CryptoHash::test(42);
+    let [mint, escrow] = denom_addresses(&program_id, &hash);
+    assert_ne!(mint, escrow);
+    assert!(!addresses.contains(&mint));
+    assert!(!addresses.contains(&escrow));
+}
+
+#[test]
+#[cfg(feature = "client")]
+fn test_compile_message() {
+    use solana_program::instruction::{AccountMeta, Instruction};
+
+    let program_id = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let table =
+        solana_program::address_lookup_table::AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: static_addresses(&program_id),
+        };
+    let accounts = core::iter::once(AccountMeta::new(payer, true))
+        .chain(core::iter::once(AccountMeta::new_readonly(other, false)))
+        .chain(
+            table.addresses[1..]
+                .iter()
+                .map(|key| AccountMeta::new(*key, false)),
+        )
+        .collect();
+    let instruction = Instruction { program_id, accounts, data: vec![1, 2, 3] };
+
+    let message = compile_message(
+        &payer,
+        &[instruction],
+        table.clone(),
+        Default::default(),
+    )
+    .unwrap();
+    let message = match message {
+        solana_program::message::VersionedMessage::V0(message) => message,
+        _ => unreachable!(),
+    };
+    // Payer, the other account and the program are stored directly (program
+    // ids can’t be loaded from lookup tables).
+    assert_eq!(vec![payer, other, program_id], message.account_keys);
+    assert_eq!(1, message.address_table_lookups.len());
+    let lookup = &message.address_table_lookups[0];
+    assert_eq!(table.key, lookup.account_key);
+    assert_eq!(
+        table.addresses.len() - 1,
+        lookup.writable_indexes.len() + lookup.readonly_indexes.len()
+    );
+}
@@ -3,6 +3,109 @@ use anchor_lang::prelude::*;
 use crate::ibc::ExecutionContext;
 use crate::{ibc, storage, MockDeliver};
 
+#[cfg(not(target_os = "solana"))]
+std::thread_local! {
+    /// Clock set by the active [`TestContext`], if any.
+    static MOCK_CLOCK: core::cell::RefCell<Option<Clock>> =
+        core::cell::RefCell::new(None);
+}
+
+/// Returns Solana’s clock.
+///
+/// If a [`TestContext`] is active on the current thread, returns clock
+/// controlled by it rather than fetching the sysvar.  This lets tests cover
+/// packet timeouts and client expiry without waiting for time to pass.
+pub(crate) fn clock() -> Result<Clock, ProgramError> {
+    #[cfg(not(target_os = "solana"))]
+    if let Some(clock) = MOCK_CLOCK.with(|clock| clock.borrow().clone()) {
+        return Ok(clock);
+    }
+    Clock::get()
+}
+
+/// Test context controlling the clock the program observes.
+///
+/// While the context is alive, host height (i.e. Solana slot) and timestamp
+/// used by the program, including when generating guest blocks, are taken
+/// from the mock clock rather than from the sysvar.  The clock only moves
+/// when advanced explicitly which makes tests deterministic.  Dropping the
+/// context restores the real clock.
+///
+/// The clock is per-thread and only one context may be alive on a thread at
+/// a time.
+#[cfg(not(target_os = "solana"))]
+pub struct TestContext {
+    _not_send: core::marker::PhantomData<*const ()>,
+}
+
+#[cfg(not(target_os = "solana"))]
+impl TestContext {
+    /// Starts mocking the clock at given slot and Unix timestamp (in
+    /// seconds).
+    ///
+    /// Panics if another context is alive on the current thread.
+    pub fn new(slot: u64, unix_timestamp: i64) -> Self {
+        MOCK_CLOCK.with(|clock| {
+            let mut clock = clock.borrow_mut();
+            assert!(clock.is_none(), "TestContext already active");
+            *clock = Some(Clock {
+                slot,
+                epoch_start_timestamp: unix_timestamp,
+                epoch: epoch_of(slot),
+                leader_schedule_epoch: epoch_of(slot) + 1,
+                unix_timestamp,
+            });
+        });
+        Self { _not_send: Default::default() }
+    }
+
+    /// Returns the current mock clock.
+    pub fn clock(&self) -> Clock { clock().unwrap() }
+
+    /// Advances host height by `slots` and timestamp by `duration`.
+    ///
+    /// Sub-second part of the duration is ignored since Solana’s clock has
+    /// second precision.
+    pub fn advance(&self, slots: u64, duration: core::time::Duration) {
+        MOCK_CLOCK.with(|clock| {
+            let mut clock = clock.borrow_mut();
+            let clock = clock.as_mut().unwrap();
+            let secs = i64::try_from(duration.as_secs()).unwrap();
+            clock.slot = clock.slot.checked_add(slots).unwrap();
+            clock.unix_timestamp =
+                clock.unix_timestamp.checked_add(secs).unwrap();
+            let epoch = epoch_of(clock.slot);
+            if epoch != clock.epoch {
+                clock.epoch = epoch;
+                clock.leader_schedule_epoch = epoch + 1;
+                clock.epoch_start_timestamp = clock.unix_timestamp;
+            }
+        })
+    }
+
+    /// Advances host height by `slots` keeping the timestamp.
+    pub fn advance_slots(&self, slots: u64) {
+        self.advance(slots, core::time::Duration::ZERO)
+    }
+
+    /// Advances timestamp by `duration` keeping the host height.
+    pub fn advance_time(&self, duration: core::time::Duration) {
+        self.advance(0, duration)
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+impl Drop for TestContext {
+    fn drop(&mut self) { MOCK_CLOCK.with(|clock| clock.borrow_mut().take()); }
+}
+
+/// Returns number of Solana epoch given slot belongs to assuming default
+/// epoch schedule.
+#[cfg(not(target_os = "solana"))]
+fn epoch_of(slot: u64) -> u64 {
+    slot / anchor_lang::solana_program::clock::DEFAULT_SLOTS_PER_EPOCH
+}
+
 
 pub(crate) fn mock_deliver<'a, 'info>(
     ctx: Context<'a, 'a, 'a, 'info, MockDeliver<'info>>,
@@ -21,6 +124,7 @@ pub(crate) fn mock_deliver<'a, 'info>(
         )?,
         chain: &mut ctx.accounts.chain,
         accounts: Default::default(),
+        hook_error: None,
     });
 
     let connection_id_on_a = ibc::ConnectionId::new(0);
@@ -141,3 +245,30 @@ pub(crate) fn mock_deliver<'a, 'info>(
 
     Ok(())
 }
+
+#[test]
+fn test_mock_clock() {
+    use core::time::Duration;
+
+    let ctx = TestContext::new(100, 1_000);
+    assert_eq!((100, 1_000), (ctx.clock().slot, ctx.clock().unix_timestamp));
+
+    ctx.advance_slots(5);
+    ctx.advance_time(Duration::from_millis(30_500));
+    let clock = clock().unwrap();
+    assert_eq!((105, 1_030), (clock.slot, clock.unix_timestamp));
+    assert_eq!(0, clock.epoch);
+
+    // Guest blocks use timestamps in nanoseconds.
+    let (host_height, host_timestamp) = crate::chain::get_host_head().unwrap();
+    assert_eq!(105, u64::from(host_height));
+    assert_eq!(1_030_000_000_000, host_timestamp.get());
+
+    let slots = anchor_lang::solana_program::clock::DEFAULT_SLOTS_PER_EPOCH;
+    ctx.advance(slots, Duration::from_secs(60));
+    let clock = ctx.clock();
+    assert_eq!((1, 1_090), (clock.epoch, clock.epoch_start_timestamp));
+
+    drop(ctx);
+    assert!(self::clock().is_err());
+}
@@ -0,0 +1,821 @@
+//! ICS-721 non-fungible token transfer.
+//!
+//! The module is registered under [`PORT_ID_STR`] port and handles transfers
+//! of single NFTs, i.e. mints with zero decimals and supply of one.  NFTs
+//! native to Solana are identified by their Metaplex collection (or the mint
+//! itself if the token doesn’t belong to a verified collection) as class and
+//! mint address as token identifier.  When sent over IBC they are held in an
+//! escrow account owned by the program and released when they come back.
+//!
+//! NFTs received from counterparty chains are minted as wrapped tokens.
+//! Similarly to denom traces of fungible tokens, the full class identifier
+//! is the original class prefixed by port and channel the token has been
+//! received on.  Traces are recorded in [`PrivateStorage::nft_classes`] so
+//! that the class can be recovered when the token is sent back.  Mint of
+//! a wrapped token is a PDA derived from hashes of the full class identifier
+//! and token identifier and must be created with `init_nft_mint` instruction
+//! before the packet is delivered.
+//!
+//! At the moment each packet must carry exactly one token.  Compressed NFTs
+//! (Metaplex Bubblegum) aren’t supported yet; those will require Merkle proofs
+//! of the leaf to be passed alongside the transfer and are expected to reuse
+//! the class tracking implemented here.
+//!
+//! [`PrivateStorage::nft_classes`]: crate::storage::PrivateStorage::nft_classes
+
+use std::result::Result;
+use std::str::FromStr;
+
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::mpl_token_metadata;
+use anchor_spl::token_interface::{self, Burn, MintTo, TransferChecked};
+use lib::hash::CryptoHash;
+use serde::{Deserialize, Serialize};
+use spl_token_2022::extension::StateWithExtensions;
+
+use crate::storage::{self, IbcStorage, NftClass, TransferAccounts};
+use crate::{error, events, ibc, MINT_ESCROW_SEED};
+
+/// Port the NFT transfer module is bound to.
+pub const PORT_ID_STR: &str = "nft-transfer";
+
+/// Identifier of the NFT transfer module in the IBC router.
+pub const MODULE_ID_STR: &str = "nft-transfer";
+
+/// Channel version supported by the module.
+pub const VERSION: &str = "ics721-1";
+
+/// Packet data of an ICS-721 transfer.
+///
+/// Field names follow the ICS-721 specification so that the data is
+/// compatible with other implementations.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftPacketData {
+    pub class_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_data: Option<String>,
+    pub token_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_uris: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_data: Vec<String>,
+    pub sender: String,
+    pub receiver: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub memo: String,
+}
+
+impl NftPacketData {
+    /// Returns identifier of the single token carried by the packet.
+    fn single_token_id(&self) -> Result<&str, NftTransferError> {
+        match self.token_ids.as_slice() {
+            [token_id] => Ok(token_id.as_str()),
+            ids => Err(NftTransferError::UnsupportedTokenCount(ids.len())),
+        }
+    }
+}
+
+/// Message sent to `send_nft_transfer` instruction.
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct MsgNftTransfer {
+    pub port_id_on_a: ibc::PortId,
+    pub chan_id_on_a: ibc::ChannelId,
+    /// Class of the token.  For Solana-native NFTs that’s the address of
+    /// the verified collection or of the mint if the token doesn’t belong to
+    /// a collection.  For wrapped NFTs that’s the full class identifier.
+    pub class_id: String,
+    /// Identifier of the token.  For Solana-native NFTs that’s the address
+    /// of the mint.
+    pub token_id: String,
+    pub receiver: String,
+    pub memo: String,
+    pub timeout_height_on_b: ibc::TimeoutHeight,
+    pub timeout_timestamp_on_b: ibc::Timestamp,
+}
+
+/// Error handling an NFT transfer.
+#[derive(Clone, Debug, PartialEq, Eq, derive_more::Display)]
+pub enum NftTransferError {
+    #[display(fmt = "Invalid NFT packet data")]
+    InvalidPacketData,
+    #[display(fmt = "Expected exactly one token but got {}", _0)]
+    UnsupportedTokenCount(usize),
+    #[display(fmt = "Invalid NFT receiver")]
+    InvalidReceiver,
+    #[display(fmt = "Missing account: {}", _0)]
+    MissingAccount(&'static str),
+    #[display(fmt = "Invalid account: {}", _0)]
+    InvalidAccount(&'static str),
+    #[display(fmt = "Unknown NFT class")]
+    UnknownClass,
+    #[display(fmt = "Token is not an NFT")]
+    NotAnNft,
+    #[display(fmt = "NFT doesn’t belong to the class")]
+    ClassMismatch,
+    #[display(fmt = "Invalid channel: {}", _0)]
+    InvalidChannel(String),
+    #[display(fmt = "Channel is paused")]
+    ChannelPaused,
+    #[display(fmt = "{}", _0)]
+    Halted(String),
+    #[display(fmt = "Token program call failed: {}", _0)]
+    Cpi(String),
+}
+
+impl From<NftTransferError> for ibc::StatusValue {
+    fn from(err: NftTransferError) -> Self {
+        Self::new(err.to_string()).expect("Error message is never empty")
+    }
+}
+
+/// Returns prefix of class identifiers of tokens received over given channel.
+pub fn class_prefix(
+    port_id: &ibc::PortId,
+    channel_id: &ibc::ChannelId,
+) -> String {
+    format!("{port_id}/{channel_id}/")
+}
+
+/// Returns address of the mint of a wrapped NFT.
+pub fn get_nft_mint(
+    hashed_full_class_id: &CryptoHash,
+    hashed_token_id: &CryptoHash,
+) -> Pubkey {
+    let seeds = [
+        crate::NFT_MINT_SEED,
+        hashed_full_class_id.as_slice(),
+        hashed_token_id.as_slice(),
+    ];
+    Pubkey::find_program_address(&seeds, &crate::ID).0
+}
+
+/// Returns address of the account escrowing NFT with given mint.
+pub fn get_nft_escrow(token_mint: &Pubkey) -> Pubkey {
+    let seeds = [crate::NFT_ESCROW_SEED, token_mint.as_ref()];
+    Pubkey::find_program_address(&seeds, &crate::ID).0
+}
+
+/// Returns mint of the token identified by local class and token identifier.
+///
+/// If the class is a known wrapped class, the mint is a PDA derived from the
+/// identifiers.  Otherwise the token is a Solana-native NFT and its
+/// identifier is the mint address.
+fn local_mint(
+    private: &storage::PrivateStorage,
+    class_id: &str,
+    token_id: &str,
+) -> Result<Pubkey, NftTransferError> {
+    let hashed_class_id = CryptoHash::digest(class_id.as_bytes());
+    if private.nft_classes.contains_key(&hashed_class_id) {
+        let hashed_token_id = CryptoHash::digest(token_id.as_bytes());
+        Ok(get_nft_mint(&hashed_class_id, &hashed_token_id))
+    } else {
+        Pubkey::from_str(token_id)
+            .map_err(|_| NftTransferError::InvalidPacketData)
+    }
+}
+
+/// Returns `(decimals, supply)` of given mint.
+fn get_mint_info(mint: &AccountInfo) -> Result<(u8, u64), NftTransferError> {
+    let data =
+        mint.try_borrow_data().map_err(|_| NftTransferError::NotAnNft)?;
+    let mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+            .map_err(|_| NftTransferError::NotAnNft)?;
+    Ok((mint.base.decimals, mint.base.supply))
+}
+
+fn get_account<'a, 'b>(
+    account: &'a Option<AccountInfo<'b>>,
+    name: &'static str,
+) -> Result<&'a AccountInfo<'b>, NftTransferError> {
+    account.as_ref().ok_or(NftTransferError::MissingAccount(name))
+}
+
+/// The NFT transfer IBC module.
+///
+/// It’s a thin wrapper around the IBC storage so that the router can
+/// dispatch to a different [`ibc::Module`] implementation than the one used
+/// for fungible token transfers.
+#[derive(Debug)]
+#[repr(transparent)]
+pub(crate) struct NftModule<'a, 'b>(IbcStorage<'a, 'b>);
+
+// SAFETY: NftModule is a repr(transparent) wrapper around IbcStorage.
+unsafe impl<'a, 'b> bytemuck::TransparentWrapper<IbcStorage<'a, 'b>>
+    for NftModule<'a, 'b>
+{
+}
+
+impl NftModule<'_, '_> {
+    fn check_halt_flag(
+        &self,
+        flag: storage::HaltFlag,
+    ) -> Result<(), NftTransferError> {
+        self.0
+            .borrow()
+            .private
+            .halt_flags
+            .check(flag)
+            .map_err(|err| NftTransferError::Halted(err.to_string()))
+    }
+
+    /// Checks that the mint account passed to the instruction is the
+    /// expected one.
+    fn check_mint(&self, expected: &Pubkey) -> Result<(), NftTransferError> {
+        let store = self.0.borrow();
+        let mint = get_account(&store.accounts.token_mint, "token_mint")?;
+        if mint.key != expected {
+            return Err(NftTransferError::InvalidAccount("token_mint"));
+        }
+        Ok(())
+    }
+
+    /// Checks that the receiver account is the expected one.
+    fn check_receiver(&self, receiver: &str) -> Result<(), NftTransferError> {
+        let receiver = Pubkey::from_str(receiver)
+            .map_err(|_| NftTransferError::InvalidReceiver)?;
+        let store = self.0.borrow();
+        let account = get_account(&store.accounts.receiver, "receiver")?;
+        if account.key != &receiver {
+            return Err(NftTransferError::InvalidAccount("receiver"));
+        }
+        Ok(())
+    }
+
+    /// Transfers NFT from the escrow account to the receiver’s token account.
+    fn unescrow(&self, token_mint: &Pubkey) -> Result<(), NftTransferError> {
+        self.check_mint(token_mint)?;
+        let store = self.0.borrow();
+        let accounts = &store.accounts;
+        let escrow = get_account(&accounts.escrow_account, "escrow_account")?;
+        if escrow.key != &get_nft_escrow(token_mint) {
+            return Err(NftTransferError::InvalidAccount("escrow_account"));
+        }
+        let (_, bump) =
+            Pubkey::find_program_address(&[MINT_ESCROW_SEED], &crate::ID);
+        let seeds = [MINT_ESCROW_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+        let cpi_ctx = CpiContext::new_with_signer(
+            get_account(&accounts.token_program, "token_program")?.clone(),
+            TransferChecked {
+                from: escrow.clone(),
+                mint: get_account(&accounts.token_mint, "token_mint")?.clone(),
+                to: get_account(&accounts.token_account, "token_account")?
+                    .clone(),
+                authority: get_account(
+                    &accounts.mint_authority,
+                    "mint_authority",
+                )?
+                .clone(),
+            },
+            seeds,
+        );
+        token_interface::transfer_checked(cpi_ctx, 1, 0)
+            .map_err(|err| NftTransferError::Cpi(err.to_string()))
+    }
+
+    /// Mints wrapped NFT to the receiver’s token account.
+    ///
+    /// Fails if the token has already been minted.
+    fn mint(&self, token_mint: &Pubkey) -> Result<(), NftTransferError> {
+        self.check_mint(token_mint)?;
+        let store = self.0.borrow();
+        let accounts = &store.accounts;
+        let mint = get_account(&accounts.token_mint, "token_mint")?;
+        if get_mint_info(mint)? != (0, 0) {
+            return Err(NftTransferError::NotAnNft);
+        }
+        let (_, bump) =
+            Pubkey::find_program_address(&[MINT_ESCROW_SEED], &crate::ID);
+        let seeds = [MINT_ESCROW_SEED, core::slice::from_ref(&bump)];
+        let seeds = seeds.as_ref();
+        let seeds = core::slice::from_ref(&seeds);
+        let cpi_ctx = CpiContext::new_with_signer(
+            get_account(&accounts.token_program, "token_program")?.clone(),
+            MintTo {
+                mint: mint.clone(),
+                to: get_account(&accounts.token_account, "token_account")?
+                    .clone(),
+                authority: get_account(
+                    &accounts.mint_authority,
+                    "mint_authority",
+                )?
+                .clone(),
+            },
+            seeds,
+        );
+        token_interface::mint_to(cpi_ctx, 1)
+            .map_err(|err| NftTransferError::Cpi(err.to_string()))
+    }
+
+    /// Handles a received packet; returns acknowledgement status value.
+    fn recv_packet(
+        &mut self,
+        packet: &ibc::Packet,
+    ) -> Result<ibc::StatusValue, NftTransferError> {
+        self.check_halt_flag(storage::HaltFlag::Inbound)?;
+        let paused = self
+            .0
+            .borrow()
+            .private
+            .channel_pause(&packet.port_id_on_b, &packet.chan_id_on_b)
+            .receive;
+        if paused {
+            return Err(NftTransferError::ChannelPaused);
+        }
+
+        let data = serde_json::from_slice::<NftPacketData>(&packet.data)
+            .map_err(|_| NftTransferError::InvalidPacketData)?;
+        let token_id = data.single_token_id()?;
+        self.check_receiver(&data.receiver)?;
+
+        let prefix = class_prefix(&packet.port_id_on_a, &packet.chan_id_on_a);
+        if let Some(class_id) = data.class_id.strip_prefix(&prefix) {
+            // The token is coming back to the chain it’s been sent from.
+            let mint =
+                local_mint(&self.0.borrow().private, class_id, token_id)?;
+            self.unescrow(&mint)?;
+        } else {
+            let full_class_id = format!(
+                "{}{}",
+                class_prefix(&packet.port_id_on_b, &packet.chan_id_on_b),
+                data.class_id
+            );
+            let hashed_class_id = CryptoHash::digest(full_class_id.as_bytes());
+            let hashed_token_id = CryptoHash::digest(token_id.as_bytes());
+            self.mint(&get_nft_mint(&hashed_class_id, &hashed_token_id))?;
+            let mut store = self.0.borrow_mut();
+            let nft_classes = &mut store.private.nft_classes;
+            if !nft_classes.contains_key(&hashed_class_id) {
+                nft_classes.insert(hashed_class_id, NftClass {
+                    class_id: full_class_id,
+                    class_uri: data.class_uri,
+                });
+            }
+        }
+        Ok(ibc::apps::transfer::types::ack_success_b64())
+    }
+
+    /// Returns NFT to the sender after failed transfer and refunds the fee.
+    fn refund(&mut self, packet: &ibc::Packet) -> Result<(), NftTransferError> {
+        let data = serde_json::from_slice::<NftPacketData>(&packet.data)
+            .map_err(|_| NftTransferError::InvalidPacketData)?;
+        let token_id = data.single_token_id()?;
+        self.check_receiver(&data.sender)?;
+
+        let mint =
+            local_mint(&self.0.borrow().private, &data.class_id, token_id)?;
+        let prefix = class_prefix(&packet.port_id_on_a, &packet.chan_id_on_a);
+        if data.class_id.starts_with(&prefix) {
+            // The token has been burned when sending; mint it again.
+            self.mint(&mint)?;
+        } else {
+            self.unescrow(&mint)?;
+        }
+
+        let store = self.0.borrow();
+        let accounts = &store.accounts;
+        let receiver = get_account(&accounts.receiver, "receiver")?;
+        let fee_collector =
+            get_account(&accounts.fee_collector, "fee_collector")?;
+        **fee_collector.try_borrow_mut_lamports().unwrap() -=
+            store.private.fee_in_lamports;
+        **receiver.try_borrow_mut_lamports().unwrap() +=
+            store.private.fee_in_lamports;
+        Ok(())
+    }
+}
+
+/// Checks channel parameters common to channel opening handshake steps.
+fn check_channel(
+    order: ibc::chan::Order,
+    version: &ibc::Version,
+) -> Result<ibc::Version, ibc::ChannelError> {
+    if order != ibc::chan::Order::Unordered {
+        return Err(ibc::ChannelError::AppModule {
+            description: format!("Expected unordered channel; got {order}"),
+        });
+    }
+    if version.as_str().is_empty() {
+        return Ok(ibc::Version::new(VERSION.to_string()));
+    }
+    if version.as_str() != VERSION {
+        return Err(ibc::ChannelError::AppModule {
+            description: format!(
+                "Expected version {VERSION}; got {}",
+                version.as_str()
+            ),
+        });
+    }
+    Ok(version.clone())
+}
+
+fn check_version(version: &ibc::Version) -> Result<(), ibc::ChannelError> {
+    if version.as_str() != VERSION {
+        return Err(ibc::ChannelError::AppModule {
+            description: format!(
+                "Expected version {VERSION}; got {}",
+                version.as_str()
+            ),
+        });
+    }
+    Ok(())
+}
+
+impl ibc::Module for NftModule<'_, '_> {
+    fn on_chan_open_init_validate(
+        &self,
+        order: ibc::chan::Order,
+        _connection_hops: &[ibc::ConnectionId],
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+        _counterparty: &ibc::chan::Counterparty,
+        version: &ibc::Version,
+    ) -> Result<ibc::Version, ibc::ChannelError> {
+        check_channel(order, version)
+    }
+
+    fn on_chan_open_init_execute(
+        &mut self,
+        order: ibc::chan::Order,
+        _connection_hops: &[ibc::ConnectionId],
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+        _counterparty: &ibc::chan::Counterparty,
+        version: &ibc::Version,
+    ) -> Result<(ibc::ModuleExtras, ibc::Version), ibc::ChannelError> {
+        Ok((ibc::ModuleExtras::empty(), check_channel(order, version)?))
+    }
+
+    fn on_chan_open_try_validate(
+        &self,
+        order: ibc::chan::Order,
+        _connection_hops: &[ibc::ConnectionId],
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+        _counterparty: &ibc::chan::Counterparty,
+        counterparty_version: &ibc::Version,
+    ) -> Result<ibc::Version, ibc::ChannelError> {
+        check_version(counterparty_version)?;
+        check_channel(order, counterparty_version)
+    }
+
+    fn on_chan_open_try_execute(
+        &mut self,
+        order: ibc::chan::Order,
+        _connection_hops: &[ibc::ConnectionId],
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+        _counterparty: &ibc::chan::Counterparty,
+        counterparty_version: &ibc::Version,
+    ) -> Result<(ibc::ModuleExtras, ibc::Version), ibc::ChannelError> {
+        let version = check_channel(order, counterparty_version)?;
+        Ok((ibc::ModuleExtras::empty(), version))
+    }
+
+    fn on_chan_open_ack_validate(
+        &self,
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+        counterparty_version: &ibc::Version,
+    ) -> Result<(), ibc::ChannelError> {
+        check_version(counterparty_version)
+    }
+
+    fn on_chan_close_init_validate(
+        &self,
+        _port_id: &ibc::PortId,
+        _channel_id: &ibc::ChannelId,
+    ) -> Result<(), ibc::ChannelError> {
+        // Closing the channel would make escrowed tokens unrecoverable.
+        Err(ibc::ChannelError::AppModule {
+            description: "NFT transfer channels cannot be closed".into(),
+        })
+    }
+
+    fn on_recv_packet_execute(
+        &mut self,
+        packet: &ibc::Packet,
+        _relayer: &ibc::Signer,
+    ) -> (ibc::ModuleExtras, ibc::Acknowledgement) {
+        let status = match self.recv_packet(packet) {
+            Ok(value) => ibc::AcknowledgementStatus::success(value),
+            Err(err) => {
+                msg!("NFT transfer failed: {}", err);
+                ibc::AcknowledgementStatus::error(err.into())
+            }
+        };
+        (ibc::ModuleExtras::empty(), status.into())
+    }
+
+    fn on_acknowledgement_packet_validate(
+        &self,
+        packet: &ibc::Packet,
+        acknowledgement: &ibc::Acknowledgement,
+        _relayer: &ibc::Signer,
+    ) -> Result<(), ibc::PacketError> {
+        serde_json::from_slice::<ibc::AcknowledgementStatus>(
+            acknowledgement.as_bytes(),
+        )
+        .map_err(|err| ibc::PacketError::AppModule {
+            description: err.to_string(),
+        })?;
+        serde_json::from_slice::<NftPacketData>(&packet.data).map_err(
+            |err| ibc::PacketError::AppModule { description: err.to_string() },
+        )?;
+        Ok(())
+    }
+
+    fn on_acknowledgement_packet_execute(
+        &mut self,
+        packet: &ibc::Packet,
+        acknowledgement: &ibc::Acknowledgement,
+        _relayer: &ibc::Signer,
+    ) -> (ibc::ModuleExtras, Result<(), ibc::PacketError>) {
+        let result = serde_json::from_slice::<ibc::AcknowledgementStatus>(
+            acknowledgement.as_bytes(),
+        )
+        .map_err(|err| err.to_string())
+        .and_then(|status| {
+            if status.is_successful() {
+                Ok(())
+            } else {
+                self.refund(packet).map_err(|err| err.to_string())
+            }
+        })
+        .map_err(|description| ibc::PacketError::AppModule { description });
+        (ibc::ModuleExtras::empty(), result)
+    }
+
+    fn on_timeout_packet_validate(
+        &self,
+        packet: &ibc::Packet,
+        _relayer: &ibc::Signer,
+    ) -> Result<(), ibc::PacketError> {
+        serde_json::from_slice::<NftPacketData>(&packet.data).map_err(
+            |err| ibc::PacketError::AppModule { description: err.to_string() },
+        )?;
+        Ok(())
+    }
+
+    fn on_timeout_packet_execute(
+        &mut self,
+        packet: &ibc::Packet,
+        _relayer: &ibc::Signer,
+    ) -> (ibc::ModuleExtras, Result<(), ibc::PacketError>) {
+        let result = self.refund(packet).map_err(|err| {
+            ibc::PacketError::AppModule { description: err.to_string() }
+        });
+        (ibc::ModuleExtras::empty(), result)
+    }
+}
+
+/// Verifies Metaplex metadata of a Solana-native NFT.
+///
+/// Checks that the mint is an NFT belonging to given class and returns URI
+/// of the token.
+fn check_native_nft(
+    mint: &InterfaceAccount<token_interface::Mint>,
+    metadata: Option<&AccountInfo>,
+    class_id: &str,
+    token_id: &str,
+) -> Result<String, NftTransferError> {
+    if mint.key().to_string() != token_id {
+        return Err(NftTransferError::InvalidAccount("token_mint"));
+    }
+    if mint.decimals != 0 || mint.supply != 1 {
+        return Err(NftTransferError::NotAnNft);
+    }
+    let metadata =
+        metadata.ok_or(NftTransferError::MissingAccount("metadata"))?;
+    let (expected, _) =
+        mpl_token_metadata::accounts::Metadata::find_pda(&mint.key());
+    if metadata.key != &expected || metadata.owner != &mpl_token_metadata::ID {
+        return Err(NftTransferError::InvalidAccount("metadata"));
+    }
+    let data = metadata
+        .try_borrow_data()
+        .map_err(|_| NftTransferError::InvalidAccount("metadata"))?;
+    let metadata =
+        mpl_token_metadata::accounts::Metadata::safe_deserialize(&data)
+            .map_err(|_| NftTransferError::InvalidAccount("metadata"))?;
+    let class = metadata
+        .collection
+        .filter(|collection| collection.verified)
+        .map_or_else(|| mint.key(), |collection| collection.key);
+    if class.to_string() != class_id {
+        return Err(NftTransferError::ClassMismatch);
+    }
+    Ok(metadata.uri.trim_end_matches('\0').to_string())
+}
+
+/// Handles `send_nft_transfer` instruction.
+pub(crate) fn send_nft_transfer<'a, 'info>(
+    ctx: Context<'a, 'a, 'a, 'info, crate::SendNftTransfer<'info>>,
+    msg: MsgNftTransfer,
+) -> anchor_lang::Result<()> {
+    let nft_err =
+        |err: NftTransferError| error!(error::Error::NftTransferError(err));
+
+    let private = &ctx.accounts.storage;
+    private
+        .halt_flags
+        .check(storage::HaltFlag::Outbound)
+        .map_err(|err| error!((&err)))?;
+    if private.channel_pause(&msg.port_id_on_a, &msg.chan_id_on_a).send {
+        return Err(error!(error::Error::ChannelPaused));
+    }
+    if !msg.timeout_height_on_b.is_set() && !msg.timeout_timestamp_on_b.is_set()
+    {
+        return Err(error::Error::InvalidTimeout.into());
+    }
+
+    // Figure out whether the token is sent from its source chain and, if
+    // so, verify it’s a legitimate NFT of the given class.
+    let token_mint = ctx.accounts.token_mint.key();
+    let hashed_class_id = CryptoHash::digest(msg.class_id.as_bytes());
+    let prefix = class_prefix(&msg.port_id_on_a, &msg.chan_id_on_a);
+    let sender_is_source = !msg.class_id.starts_with(&prefix);
+    let (class_uri, token_uri) = match private.nft_classes.get(&hashed_class_id)
+    {
+        Some(class) => {
+            let hashed_token_id = CryptoHash::digest(msg.token_id.as_bytes());
+            if token_mint != get_nft_mint(&hashed_class_id, &hashed_token_id) {
+                return Err(nft_err(NftTransferError::ClassMismatch));
+            }
+            (class.class_uri.clone(), None)
+        }
+        None if sender_is_source => {
+            let uri = check_native_nft(
+                &ctx.accounts.token_mint,
+                ctx.accounts.metadata.as_ref().map(|acc| acc.as_ref()),
+                &msg.class_id,
+                &msg.token_id,
+            )
+            .map_err(nft_err)?;
+            (None, Some(uri))
+        }
+        None => return Err(nft_err(NftTransferError::UnknownClass)),
+    };
+    if sender_is_source {
+        let escrow = ctx.accounts.escrow_account.as_ref().map(|acc| acc.key());
+        if escrow != Some(get_nft_escrow(&token_mint)) {
+            return Err(nft_err(NftTransferError::InvalidAccount(
+                "escrow_account",
+            )));
+        }
+    }
+
+    let fee_amount = ctx.accounts.storage.fee_in_lamports;
+    let sender_key = ctx.accounts.sender.key();
+    let fee_exempt = ctx.accounts.storage.fee_exemptions.contains(&sender_key);
+    let sender = ctx.accounts.sender.to_account_info();
+    let fee_collector = ctx.accounts.fee_collector.to_account_info();
+    if fee_exempt {
+        events::emit(events::FeeExemptionUsed {
+            sender: sender_key,
+            fee_in_lamports: fee_amount,
+        })
+        .map_err(ProgramError::BorshIoError)?;
+    } else {
+        solana_program::program::invoke(
+            &solana_program::system_instruction::transfer(
+                &sender_key,
+                &fee_collector.key(),
+                fee_amount,
+            ),
+            &[
+                sender.clone(),
+                fee_collector.clone(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    // Escrow or burn the token.
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let token_account = ctx.accounts.sender_token_account.to_account_info();
+    let mint = ctx.accounts.token_mint.to_account_info();
+    let cpi_result = if sender_is_source {
+        let escrow = ctx.accounts.escrow_account.as_ref().ok_or_else(|| {
+            nft_err(NftTransferError::MissingAccount("escrow_account"))
+        })?;
+        let cpi_ctx = CpiContext::new(token_program.clone(), TransferChecked {
+            from: token_account.clone(),
+            mint: mint.clone(),
+            to: escrow.to_account_info(),
+            authority: sender.clone(),
+        });
+        token_interface::transfer_checked(cpi_ctx, 1, 0)
+    } else {
+        let cpi_ctx = CpiContext::new(token_program.clone(), Burn {
+            mint: mint.clone(),
+            from: token_account.clone(),
+            authority: sender.clone(),
+        });
+        token_interface::burn(cpi_ctx, 1)
+    };
+    cpi_result
+        .map_err(|err| nft_err(NftTransferError::Cpi(err.to_string())))?;
+
+    let accounts = TransferAccounts {
+        sender: Some(sender),
+        token_account: Some(token_account),
+        token_mint: Some(mint),
+        token_program: Some(token_program),
+        fee_collector: Some(fee_collector),
+        ..Default::default()
+    };
+    let mut store = storage::from_ctx!(ctx, accounts = accounts);
+
+    let chan_end_path =
+        ibc::path::ChannelEndPath::new(&msg.port_id_on_a, &msg.chan_id_on_a);
+    let chan_end = ibc::ValidationContext::channel_end(&store, &chan_end_path)
+        .map_err(|err| error!((&error::Error::ContextError(err))))?;
+    let counterparty = chan_end.counterparty();
+    let chan_id_on_b = counterparty.channel_id().cloned().ok_or_else(|| {
+        nft_err(NftTransferError::InvalidChannel(msg.chan_id_on_a.to_string()))
+    })?;
+    let seq_send_path =
+        ibc::path::SeqSendPath::new(&msg.port_id_on_a, &msg.chan_id_on_a);
+    let seq_on_a =
+        ibc::ValidationContext::get_next_sequence_send(&store, &seq_send_path)
+            .map_err(|err| error!((&error::Error::ContextError(err))))?;
+
+    let data = NftPacketData {
+        class_id: msg.class_id,
+        class_uri,
+        class_data: None,
+        token_ids: vec![msg.token_id],
+        token_uris: token_uri.into_iter().collect(),
+        token_data: Vec::new(),
+        sender: sender_key.to_string(),
+        receiver: msg.receiver,
+        memo: msg.memo,
+    };
+    let packet = ibc::Packet {
+        seq_on_a,
+        port_id_on_a: msg.port_id_on_a,
+        chan_id_on_a: msg.chan_id_on_a,
+        port_id_on_b: counterparty.port_id().clone(),
+        chan_id_on_b,
+        data: serde_json::to_vec(&data).unwrap(),
+        timeout_height_on_b: msg.timeout_height_on_b,
+        timeout_timestamp_on_b: msg.timeout_timestamp_on_b,
+    };
+    ::ibc::core::channel::handler::send_packet(&mut store, packet)
+        .map_err(|err| error!((&error::Error::ContextError(err))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_data_json() {
+        let data = NftPacketData {
+            class_id: "nft-transfer/channel-1/stars".into(),
+            class_uri: Some("https://example.com/class".into()),
+            class_data: None,
+            token_ids: vec!["1".into()],
+            token_uris: vec!["https://example.com/1".into()],
+            token_data: Vec::new(),
+            sender: "stars1sender".into(),
+            receiver: "receiver".into(),
+            memo: String::new(),
+        };
+        let json = serde_json::to_string(&data).unwrap();
+        assert_eq!(
+            r#"{"classId":"nft-transfer/channel-1/stars","classUri":"https://example.com/class","tokenIds":["1"],"tokenUris":["https://example.com/1"],"sender":"stars1sender","receiver":"receiver"}"#,
+            json
+        );
+        assert_eq!(data, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn test_single_token_id() {
+        let json = r#"{"classId":"c","tokenIds":["1","2"],"sender":"s","receiver":"r"}"#;
+        let data = serde_json::from_str::<NftPacketData>(json).unwrap();
+        assert_eq!(
+            Err(NftTransferError::UnsupportedTokenCount(2)),
+            data.single_token_id()
+        );
+        let data = NftPacketData { token_ids: vec!["1".into()], ..data };
+        assert_eq!(Ok("1"), data.single_token_id());
+    }
+
+    #[test]
+    fn test_class_prefix() {
+        let port = ibc::PortId::from_str(PORT_ID_STR).unwrap();
+        let chan = ibc::ChannelId::new(3);
+        let prefix = class_prefix(&port, &chan);
+        assert_eq!("nft-transfer/channel-3/", prefix);
+        assert!("nft-transfer/channel-3/class".starts_with(&prefix));
+        assert!(!"nft-transfer/channel-30/class".starts_with(&prefix));
+    }
+}
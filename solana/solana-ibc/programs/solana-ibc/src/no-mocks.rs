@@ -4,6 +4,12 @@ use anchor_lang::prelude::*;
 
 use crate::{ibc, MockDeliver};
 
+/// Returns Solana’s clock.
+///
+/// In `mocks` builds tests can control the clock; see `TestContext`.
+#[inline]
+pub(crate) fn clock() -> Result<Clock, ProgramError> { Clock::get() }
+
 pub(crate) fn mock_deliver<'a, 'info>(
     ctx: Context<'a, 'a, 'a, 'info, MockDeliver<'info>>,
     port_id: ibc::PortId,
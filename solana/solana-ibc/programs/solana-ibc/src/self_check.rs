@@ -0,0 +1,140 @@
+//! Integrity checks of the program’s accounts.
+//!
+//! After migrations or reallocations, inconsistencies between the private
+//! storage, the trie and the witness can go unnoticed until an IBC operation
+//! fails.  `self_check` instruction runs checks implemented in this module
+//! and returns a [`Report`] which can be inspected after deployments and by
+//! monitoring.
+
+use anchor_lang::prelude::*;
+
+use crate::storage::PrivateStorage;
+
+/// Result of the `self_check` instruction.
+///
+/// Each field describes result of a single check.  All checks passed if
+/// [`Report::is_ok`] returns `true`.
+///
+/// Note that the private storage account is deserialised before any checks
+/// are run so if its discriminator or layout doesn’t match what the program
+/// expects, the instruction fails rather than returning a report.
+#[derive(Clone, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct Report {
+    /// Size of the private storage account data.
+    pub storage_size: u64,
+
+    /// Size of the serialised private storage including the discriminator.
+    ///
+    /// Must not exceed `storage_size`.
+    pub storage_used: u64,
+
+    /// Whether the trie account starts with a valid trie header.
+    pub trie_header_valid: bool,
+
+    /// Whether the witness account is well-formed and holds the same state
+    /// root as the trie.  `None` if the program has been built without
+    /// witness support.
+    pub witness_consistent: Option<bool>,
+
+    /// Whether the fee account holds enough lamports to be rent-exempt.
+    pub fee_account_rent_exempt: bool,
+
+    /// Number of registered clients.
+    pub clients: u32,
+
+    /// Indices of clients which violate storage invariants.
+    ///
+    /// Client at index `N` must have `<client-type>-<N>` identifier,
+    /// a decodable client state and at least one consensus state.
+    pub invalid_clients: Vec<u32>,
+
+    /// Number of consensus states stored for all clients.
+    pub consensus_states: u32,
+
+    /// Number of consensus states stored compressed; see
+    /// [`crate::storage::ClientConsensusState`].
+    pub compressed_consensus_states: u32,
+
+    /// Total size of consensus states in the private storage account.
+    pub consensus_states_size: u64,
+}
+
+impl Report {
+    /// Returns whether all checks passed.
+    pub fn is_ok(&self) -> bool {
+        self.storage_used <= self.storage_size &&
+            self.trie_header_valid &&
+            self.witness_consistent != Some(false) &&
+            self.fee_account_rent_exempt &&
+            self.invalid_clients.is_empty()
+    }
+}
+
+/// Runs all the checks and returns the report.
+pub(crate) fn run(accounts: &crate::SelfCheck) -> Result<Report> {
+    let storage = accounts.storage.to_account_info();
+    let storage_used = 8 + accounts.storage.try_to_vec()?.len();
+
+    let trie_root =
+        solana_trie::decode_root_hash(&accounts.trie.try_borrow_data()?);
+
+    #[cfg(feature = "witness")]
+    let witness_consistent = {
+        let data = accounts.witness.try_borrow_data()?;
+        let witness_root =
+            bytemuck::try_from_bytes::<solana_trie::witness::Data>(&data)
+                .ok()
+                .and_then(|witness| witness.decode().ok())
+                .map(|(root, _timestamp)| *root);
+        Some(trie_root.is_some() && witness_root == trie_root)
+    };
+    #[cfg(not(feature = "witness"))]
+    let witness_consistent = None;
+
+    let (mut consensus_states, mut compressed_consensus_states) = (0, 0);
+    let mut consensus_states_size = 0;
+    let states = accounts
+        .storage
+        .clients
+        .iter()
+        .flat_map(|client| client.consensus_states.values());
+    for state in states {
+        consensus_states += 1;
+        compressed_consensus_states += u32::from(state.is_compressed());
+        consensus_states_size += state.stored_size() as u64;
+    }
+
+    let fee_account = &accounts.fee_account;
+    let fee_account_rent_exempt =
+        Rent::get()?.is_exempt(fee_account.lamports(), fee_account.data_len());
+
+    Ok(Report {
+        storage_size: storage.data_len() as u64,
+        storage_used: storage_used as u64,
+        trie_header_valid: trie_root.is_some(),
+        witness_consistent,
+        fee_account_rent_exempt,
+        clients: accounts.storage.clients.len() as u32,
+        invalid_clients: invalid_clients(&accounts.storage),
+        consensus_states,
+        compressed_consensus_states,
+        consensus_states_size,
+    })
+}
+
+/// Returns indices of clients which violate storage invariants.
+fn invalid_clients(storage: &PrivateStorage) -> Vec<u32> {
+    storage
+        .clients
+        .iter()
+        .enumerate()
+        .filter(|(index, client)| {
+            let id_ok = trie_ids::ClientIdx::try_from(&client.client_id)
+                .is_ok_and(|idx| idx == *index);
+            !id_ok ||
+                client.client_state.get().is_err() ||
+                client.consensus_states.is_empty()
+        })
+        .map(|(index, _)| index as u32)
+        .collect()
+}
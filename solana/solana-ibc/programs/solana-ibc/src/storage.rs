@@ -22,6 +22,12 @@ pub mod map;
 /// top states only.  Any older states will be dropped.
 const MAX_CONSENSUS_STATES: usize = 64;
 
+/// Minimum number of per-client consensus states to keep when pruning.
+///
+/// `prune_consensus_states` instruction never removes this many most recent
+/// states regardless of how old they are.
+pub(crate) const MIN_CONSENSUS_STATES: usize = 4;
+
 /// A triple of send, receive and acknowledge sequences.
 ///
 /// This is effectively a triple of `Option<Sequence>` values.  They are kept
@@ -121,6 +127,44 @@ impl ClientStore {
             states[min_idx] = (height, state);
         }
     }
+
+    /// Removes consensus states processed at or before `cutoff_ns` host time
+    /// keeping `keep` states with greatest heights regardless of their age.
+    ///
+    /// Returns heights of the removed states.
+    pub(crate) fn prune_consensus_states(
+        &mut self,
+        cutoff_ns: u64,
+        keep: usize,
+    ) -> Vec<ibc::Height> {
+        let mut states = Vec::from(core::mem::take(&mut self.consensus_states));
+        let pruned =
+            Self::prune_consensus_states_impl(&mut states, cutoff_ns, keep);
+        self.consensus_states = states.into();
+        pruned
+    }
+
+    fn prune_consensus_states_impl(
+        states: &mut Vec<(ibc::Height, ClientConsensusState)>,
+        cutoff_ns: u64,
+        keep: usize,
+    ) -> Vec<ibc::Height> {
+        states.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let mut pruned = Vec::new();
+        let mut index = 0;
+        states.retain(|(height, state)| {
+            index += 1;
+            let expired = index > keep &&
+                state
+                    .processed_time()
+                    .map_or(false, |time| time.get() <= cutoff_ns);
+            if expired {
+                pruned.push(*height);
+            }
+            !expired
+        });
+        pruned
+    }
 }
 
 /// Per-client per-height private storage.
@@ -143,6 +187,13 @@ impl ClientStore {
 ///
 /// To make it possible to quickly access individual ‘fields’ getter methods are
 /// provided.
+///
+/// With `compress-states` feature enabled, the serialised state is stored
+/// deflate-compressed if that makes it smaller.  In that case processed time
+/// and height are followed by [`COMPRESSED_STATE_MARKER`] and the compressed
+/// data.  States are decompressed on access so compressed and uncompressed
+/// states can be freely mixed; in particular, states stored before the
+/// feature was enabled (or after it was disabled) remain readable.
 #[derive(Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct ClientConsensusState(
     Serialised<(NonZeroU64, guestchain::BlockHeight, AnyConsensusState)>,
@@ -159,9 +210,52 @@ impl ClientConsensusState {
         processed_height: guestchain::BlockHeight,
         state: &AnyConsensusState,
     ) -> Result<Self, ibc::ClientError> {
-        Serialised::new(&(processed_time, processed_height, state))
+        let this = Serialised::new(&(processed_time, processed_height, state))
             .map(Serialised::transmute)
-            .map(Self)
+            .map(Self)?;
+        #[cfg(feature = "compress-states")]
+        let this = this.compress();
+        Ok(this)
+    }
+
+    /// Compresses the serialised state if that makes it smaller.
+    #[cfg(feature = "compress-states")]
+    fn compress(self) -> Self {
+        let bytes = self.0.as_bytes();
+        if bytes.len() < 16 || self.is_compressed() {
+            return self;
+        }
+        let (header, state) = bytes.split_at(16);
+        let compressed = miniz_oxide::deflate::compress_to_vec(state, 6);
+        if compressed.len() + 1 >= state.len() {
+            return self;
+        }
+        let mut bytes = Vec::with_capacity(16 + 1 + compressed.len());
+        bytes.extend_from_slice(header);
+        bytes.push(COMPRESSED_STATE_MARKER);
+        bytes.extend_from_slice(&compressed);
+        Self(Serialised(bytes, core::marker::PhantomData))
+    }
+
+    /// Returns whether the serialised state is stored compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.0.as_bytes().get(16) == Some(&COMPRESSED_STATE_MARKER)
+    }
+
+    /// Returns size of the object as stored in the account.
+    pub fn stored_size(&self) -> usize { 2 + self.0.as_bytes().len() }
+
+    /// Returns the serialised consensus state decompressing it if necessary.
+    fn serialised_state(
+        &self,
+    ) -> Result<alloc::borrow::Cow<'_, [u8]>, ibc::ClientError> {
+        match self.0.as_bytes().get(16..) {
+            Some([COMPRESSED_STATE_MARKER, data @ ..]) => decompress(data),
+            Some(serialised) => Ok(alloc::borrow::Cow::Borrowed(serialised)),
+            None => Err(ibc::ClientError::ClientSpecific {
+                description: "Internal: Bad AnyConsensusState".into(),
+            }),
+        }
     }
 
     /// Returns processed time for this client consensus state.
@@ -184,8 +278,8 @@ impl ClientConsensusState {
 
     /// Returns the consensus state.
     pub fn state(&self) -> Result<AnyConsensusState, ibc::ClientError> {
-        let bytes = self.0.as_bytes().get(16..).unwrap_or(&[]);
-        AnyConsensusState::try_from_slice(bytes).map_err(make_err)
+        AnyConsensusState::try_from_slice(&self.serialised_state()?)
+            .map_err(make_err)
     }
 
     /// Returns digest of the consensus state with client id mixed in.
@@ -199,17 +293,43 @@ impl ClientConsensusState {
         &self,
         client_id: &ibc::ClientId,
     ) -> Result<CryptoHash, ibc::ClientError> {
-        match self.0.as_bytes().get(16..) {
-            Some(serialised) => {
-                Ok(cf_guest::digest_with_client_id(client_id, serialised))
-            }
-            None => Err(ibc::ClientError::ClientSpecific {
-                description: "Internal: Bad AnyConsensusState".into(),
-            }),
-        }
+        let serialised = self.serialised_state()?;
+        Ok(cf_guest::digest_with_client_id(client_id, &serialised))
     }
 }
 
+/// Byte following processed time and height in [`ClientConsensusState`]
+/// indicating that the serialised state is compressed.
+///
+/// Uncompressed state starts with borsh-serialised `AnyConsensusState` whose
+/// first byte is the variant tag.  This value must therefore not be used as
+/// any of the tags.
+const COMPRESSED_STATE_MARKER: u8 = 0xFE;
+
+/// Decompresses deflate-compressed serialised consensus state.
+#[cfg(feature = "compress-states")]
+fn decompress(
+    data: &[u8],
+) -> Result<alloc::borrow::Cow<'_, [u8]>, ibc::ClientError> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, u16::MAX.into())
+        .map(alloc::borrow::Cow::Owned)
+        .map_err(|err| ibc::ClientError::ClientSpecific {
+            description: format!("Bad compressed AnyConsensusState: {err:?}"),
+        })
+}
+
+/// Fails since decompression requires `compress-states` feature.
+#[cfg(not(feature = "compress-states"))]
+fn decompress(
+    _data: &[u8],
+) -> Result<alloc::borrow::Cow<'_, [u8]>, ibc::ClientError> {
+    Err(ibc::ClientError::ClientSpecific {
+        description: "Compressed AnyConsensusState requires compress-states \
+                      feature"
+            .into(),
+    })
+}
+
 /// A shared reference to a [`ClientStore`] together with its index.
 pub struct ClientRef<'a> {
     #[allow(dead_code)]
@@ -287,7 +407,7 @@ impl Default for PortChannelStore {
 }
 
 #[account]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 /// The private IBC storage, i.e. data which doesn’t require proofs.
 pub struct PrivateStorage {
     /// Per-client information.
@@ -313,11 +433,956 @@ pub struct PrivateStorage {
 
     pub assets: map::Map<CryptoHash, Asset>,
 
-    // Fee to be charged for each transfer
+    // Flat fee to be charged for each transfer unless `fee_schedule` is set
     pub fee_in_lamports: u64,
 
     #[cfg(feature = "witness")]
     pub local_consensus_state: VecDeque<(u64, u64, CryptoHash)>,
+
+    /// Minimum amount which can be transferred of given token.
+    ///
+    /// Keys are hashes of full denoms (as used in `assets`) and values are
+    /// amounts expressed in units used in ICS-20 packets (i.e. before
+    /// decimals conversion).  Tokens without an entry have no minimum.
+    pub min_transfer_amounts: map::Map<CryptoHash, u64>,
+
+    /// Senders which aren’t charged fees on outgoing transfers.
+    ///
+    /// Managed by the fee collector.  Programs sending transfers through CPI
+    /// are exempted by listing the PDA they sign the transfer with.
+    pub fee_exemptions: Vec<Pubkey>,
+
+    /// Flags halting parts of IBC processing in case of an emergency.
+    pub halt_flags: HaltFlags,
+
+    /// Multisig whose vault is the current fee collector.
+    ///
+    /// `None` if the fee collector is a regular key.
+    pub fee_collector_multisig: Option<MultisigFeeCollector>,
+
+    /// Multisig whose vault is proposed in `new_fee_collector_proposal`.
+    pub new_fee_collector_multisig: Option<MultisigFeeCollector>,
+
+    /// How to handle receiver token accounts with a delegate or close
+    /// authority set.
+    pub receiver_account_policy: ReceiverAccountPolicy,
+
+    /// Display metadata overrides of wrapped tokens.
+    ///
+    /// Keys are hashes of full denoms (as used in `assets`).  Managed by the
+    /// fee collector with `set_denom_metadata` instruction and copied into
+    /// token’s Metaplex metadata account with `sync_denom_metadata`.
+    pub denom_metadata: map::Map<CryptoHash, DenomMetadata>,
+
+    /// Pause flags of individual channels.
+    ///
+    /// Only channels with at least one flag set have an entry.  Managed by
+    /// the fee collector with `set_channel_pause` instruction.
+    pub paused_channels: map::Map<trie_ids::PortChannelPK, ChannelPause>,
+
+    /// Traces of wrapped NFT classes received over ICS-721 channels.
+    ///
+    /// Keys are hashes of full class identifiers, i.e. ones prefixed with
+    /// port and channel the class has been received on.  Entries are added
+    /// when the first NFT of the class is received.
+    pub nft_classes: map::Map<CryptoHash, NftClass>,
+
+    /// Governance of program upgrades.
+    ///
+    /// `None` until configured by the fee collector with
+    /// `setup_upgrade_governance` instruction.
+    pub upgrade_governance: Option<UpgradeGovernance>,
+
+    /// Default schedule of fees charged for outgoing transfers.
+    ///
+    /// `None` if only the flat `fee_in_lamports` is charged.  Set by the fee
+    /// collector with `set_fee_schedule` instruction.
+    pub fee_schedule: Option<FeeSchedule>,
+
+    /// Per-denom overrides of `fee_schedule`.
+    ///
+    /// Keys are hashes of full denoms (as used in `assets`).
+    pub denom_fee_schedules: map::Map<CryptoHash, FeeSchedule>,
+
+    /// Address of the program’s address lookup table.
+    ///
+    /// `None` until created with `create_lookup_table` instruction.  See
+    /// [`crate::lookup_table`] module.
+    pub lookup_table: Option<Pubkey>,
+
+    /// Highest sequences of packets received and resolved on each channel.
+    ///
+    /// Used to detect gaps in packet sequences left by lost relayer
+    /// transactions; see [`LastSeenSequences`].  Channels with no packets
+    /// processed yet have no entry.
+    pub last_seen_sequences:
+        map::Map<trie_ids::PortChannelPK, LastSeenSequences>,
+
+    /// Rate limits of transfers of individual tokens.
+    ///
+    /// Keys are hashes of full denoms (as used in `assets`).  Managed by the
+    /// fee collector with `set_rate_limit` instruction.  Tokens without an
+    /// entry aren’t limited.
+    pub rate_limits: map::Map<CryptoHash, RateLimit>,
+}
+
+/// Flags halting parts of IBC processing.
+///
+/// Set by the fee collector with `set_halt_flag` instruction in case of
+/// a light client or escrow exploit.  Halting doesn’t affect guest blockchain
+/// block generation.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct HaltFlags {
+    /// Whether delivering packets (i.e. receive, acknowledgement and timeout
+    /// messages) is halted.
+    pub inbound: bool,
+
+    /// Whether sending packets is halted.
+    pub outbound: bool,
+
+    /// Whether updating and upgrading clients is halted.
+    pub client_updates: bool,
+}
+
+/// Policy for receiver token accounts which have a delegate or close authority
+/// set.
+///
+/// Delegate or close authority of a token account can move tokens out of it
+/// (or close it once emptied) without the owner’s signature so tokens received
+/// into such account may be swept by a third party.  Set by the fee collector
+/// with `set_receiver_account_policy` instruction.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum ReceiverAccountPolicy {
+    /// Credit the receiver’s token account regardless.
+    #[default]
+    Allow,
+    /// Credit the receiver’s token account but log a warning.
+    Warn,
+    /// Reject the transfer.  The packet is acknowledged with an error so the
+    /// tokens are refunded on the source chain.
+    Reject,
+    /// Credit the pending claim account instead.  It’s an associated token
+    /// account of a per-receiver PDA with `[PENDING_CLAIM_SEED, receiver]`
+    /// seeds from which the receiver withdraws the tokens with
+    /// `claim_pending_transfer` instruction.
+    Escrow,
+}
+
+/// Squads-style multisig whose vault acts as the fee collector.
+///
+/// The multisig program signs transactions approved by enough members with the
+/// vault PDA (via `invoke_signed`) so privileged instructions which require the
+/// fee collector’s signature need multiple approvals.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct MultisigFeeCollector {
+    /// Address of the multisig program.
+    pub program_id: Pubkey,
+
+    /// Address of the multisig account owned by the program.
+    pub multisig: Pubkey,
+
+    /// Index of the multisig’s vault used as the fee collector.
+    pub vault_index: u8,
+}
+
+impl MultisigFeeCollector {
+    /// Returns address of the multisig’s vault.
+    ///
+    /// The vault is a PDA of the multisig program with
+    /// `["multisig", multisig, "vault", vault_index]` seeds which is the
+    /// scheme used by Squads v4.
+    pub fn vault(&self) -> Pubkey {
+        let seeds = [
+            b"multisig".as_ref(),
+            self.multisig.as_ref(),
+            b"vault".as_ref(),
+            core::slice::from_ref(&self.vault_index),
+        ];
+        Pubkey::find_program_address(&seeds, &self.program_id).0
+    }
+}
+
+/// Governance of program upgrades.
+///
+/// Once configured, the program’s BPF upgrade authority is expected to be
+/// transferred to the PDA with `[UPGRADE_AUTHORITY_SEED]` seeds.  The program
+/// can then only be upgraded through `execute_upgrade` instruction which
+/// requires a proposal approved by `threshold` of `approvers` and at least
+/// `timelock_secs` seconds to pass since the proposal has been made.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct UpgradeGovernance {
+    /// Keys which can propose and approve upgrades.
+    pub approvers: Vec<Pubkey>,
+
+    /// Number of approvals needed to execute an upgrade.
+    pub threshold: u8,
+
+    /// Minimum number of seconds between proposal and execution of an
+    /// upgrade.
+    pub timelock_secs: u64,
+
+    /// Currently pending upgrade proposal, if any.
+    pub proposal: Option<UpgradeProposal>,
+}
+
+/// A pending program upgrade; see [`UpgradeGovernance`].
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct UpgradeProposal {
+    /// Buffer account holding the new program.
+    pub buffer: Pubkey,
+
+    /// Hash of the new program held in the buffer at the time of the
+    /// proposal.
+    pub buffer_hash: CryptoHash,
+
+    /// Unix timestamp of when the upgrade has been proposed.
+    pub proposed_at: i64,
+
+    /// Approvers who approved the upgrade.  The proposer approves the upgrade
+    /// implicitly.
+    pub approvals: Vec<Pubkey>,
+}
+
+impl UpgradeGovernance {
+    /// Maximum number of approvers.
+    pub const MAX_APPROVERS: usize = 16;
+
+    /// Creates new governance configuration verifying its parameters.
+    ///
+    /// Returns `None` if there are no or too many approvers, approvers aren’t
+    /// unique or the threshold is zero or greater than number of approvers.
+    pub fn new(
+        approvers: Vec<Pubkey>,
+        threshold: u8,
+        timelock_secs: u64,
+    ) -> Option<Self> {
+        let count = approvers.len();
+        if count == 0 ||
+            count > Self::MAX_APPROVERS ||
+            threshold == 0 ||
+            usize::from(threshold) > count ||
+            (1..count).any(|i| approvers[..i].contains(&approvers[i]))
+        {
+            return None;
+        }
+        Some(Self { approvers, threshold, timelock_secs, proposal: None })
+    }
+
+    /// Returns `NotUpgradeApprover` error if `key` isn’t an approver.
+    pub fn check_approver(
+        &self,
+        key: &Pubkey,
+    ) -> Result<(), crate::error::Error> {
+        if self.approvers.contains(key) {
+            Ok(())
+        } else {
+            Err(crate::error::Error::NotUpgradeApprover)
+        }
+    }
+
+    /// Records a new upgrade proposal made by `proposer` at time `now`.
+    ///
+    /// Any previously pending proposal is discarded together with its
+    /// approvals.
+    pub fn propose(
+        &mut self,
+        proposer: Pubkey,
+        buffer: Pubkey,
+        buffer_hash: CryptoHash,
+        now: i64,
+    ) -> Result<(), crate::error::Error> {
+        self.check_approver(&proposer)?;
+        self.proposal = Some(UpgradeProposal {
+            buffer,
+            buffer_hash,
+            proposed_at: now,
+            approvals: alloc::vec![proposer],
+        });
+        Ok(())
+    }
+
+    /// Adds `approver`’s approval to pending proposal of upgrade with
+    /// `buffer`.
+    ///
+    /// Returns number of approvals the proposal has.  Approving a proposal
+    /// more than once has no effect.
+    pub fn approve(
+        &mut self,
+        approver: Pubkey,
+        buffer: &Pubkey,
+    ) -> Result<usize, crate::error::Error> {
+        self.check_approver(&approver)?;
+        let proposal = self
+            .proposal
+            .as_mut()
+            .filter(|proposal| proposal.buffer == *buffer)
+            .ok_or(crate::error::Error::NoUpgradeProposal)?;
+        if !proposal.approvals.contains(&approver) {
+            proposal.approvals.push(approver);
+        }
+        Ok(proposal.approvals.len())
+    }
+
+    /// Takes the pending proposal if it can be executed at time `now`.
+    ///
+    /// On error, the proposal is left untouched.
+    pub fn take_ready(
+        &mut self,
+        now: i64,
+    ) -> Result<UpgradeProposal, crate::error::Error> {
+        let proposal = self
+            .proposal
+            .as_ref()
+            .ok_or(crate::error::Error::NoUpgradeProposal)?;
+        if proposal.approvals.len() < usize::from(self.threshold) {
+            return Err(crate::error::Error::InsufficientUpgradeApprovals);
+        }
+        let elapsed = now.saturating_sub(proposal.proposed_at);
+        if u64::try_from(elapsed).map_or(true, |s| s < self.timelock_secs) {
+            return Err(crate::error::Error::UpgradeTimelockActive);
+        }
+        Ok(self.proposal.take().unwrap())
+    }
+}
+
+/// SOL bonded by a validator candidate who registered with
+/// `register_candidate` instruction.
+///
+/// Held in a PDA with `[CANDIDATE_BOND_SEED, validator]` seeds which also
+/// holds the bonded lamports.  The bond counts as candidate’s stake in the
+/// guest blockchain.  Unbonding with `unbond_candidate` queues removal of the
+/// stake and once that’s applied `withdraw_candidate_bond` closes the account
+/// returning the lamports to the candidate.
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct CandidateBond {
+    /// The candidate.
+    pub validator: Pubkey,
+
+    /// Bonded amount in lamports.  One lamport counts as one unit of guest
+    /// stake.
+    pub amount: u64,
+
+    /// Number of the guest epoch from which the bond no longer counts as
+    /// candidate’s stake or `None` if it hasn’t been unbonded.
+    pub unbonding_epoch: Option<u64>,
+}
+
+impl CandidateBond {
+    /// Returns whether the bond can be withdrawn.
+    ///
+    /// That’s the case once the bond has been unbonded and the guest epoch in
+    /// which removal of the bonded stake takes effect has started.  Stake
+    /// changes are applied to the candidates set when they are made so from
+    /// that epoch on the bond no longer counts towards candidate’s voting
+    /// power.
+    pub fn is_unlocked(
+        &self,
+        chain: &crate::chain::ChainData,
+    ) -> Result<bool, crate::chain::ChainNotInitialised> {
+        let epoch = chain.epoch_number()?;
+        Ok(self.unbonding_epoch.is_some_and(|unbonding| epoch >= unbonding))
+    }
+}
+
+/// Counters describing outcomes of transfer hooks.
+///
+/// Held in a PDA with `[HOOK_STATS_SEED]` seeds created with
+/// `init_hook_stats` instruction.  The account is updated when the relayer
+/// passes it among remaining accounts of `deliver` instruction which lets
+/// dashboards read hooks’ health without parsing transaction logs.
+#[account]
+#[derive(Debug, Default, InitSpace)]
+pub struct HookStats {
+    /// Number of received transfers for which a hook was invoked.
+    pub invocations: u64,
+
+    /// Number of hook invocations which succeeded.
+    pub successes: u64,
+
+    /// Number of failures due to an uninitialised account being passed for
+    /// the hook.
+    pub uninitialised_account_failures: u64,
+
+    /// Number of failed calls to the bridge escrow program.
+    pub bridge_escrow_failures: u64,
+
+    /// Number of failed memo hooks.
+    pub memo_hook_failures: u64,
+
+    /// Slot of the last failure or zero if there were no failures.
+    pub last_failure_slot: u64,
+}
+
+/// Class of a hook failure recorded in [`HookStats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookFailure {
+    UninitialisedAccount,
+    BridgeEscrow,
+    MemoHook,
+}
+
+impl HookStats {
+    /// Records outcome of a hook invocation.
+    pub fn record(&mut self, result: Result<(), HookFailure>, slot: u64) {
+        self.invocations = self.invocations.saturating_add(1);
+        let counter = match result {
+            Ok(()) => &mut self.successes,
+            Err(HookFailure::UninitialisedAccount) => {
+                &mut self.uninitialised_account_failures
+            }
+            Err(HookFailure::BridgeEscrow) => &mut self.bridge_escrow_failures,
+            Err(HookFailure::MemoHook) => &mut self.memo_hook_failures,
+        };
+        *counter = counter.saturating_add(1);
+        if result.is_err() {
+            self.last_failure_slot = slot;
+        }
+    }
+}
+
+/// Maximum number of relayers tracked in [`RelayerStats`].
+pub const MAX_RELAYER_STATS: usize = 32;
+
+/// Per-relayer delivery counters.
+///
+/// Held in a PDA with `[RELAYER_STATS_SEED]` seeds created with
+/// `init_relayer_stats` instruction.  The account is updated when the relayer
+/// passes it among remaining accounts of `deliver` instruction which lets an
+/// incentive program reward relayers without an off-chain indexer.
+///
+/// The account holds at most [`MAX_RELAYER_STATS`] entries.  When a new
+/// relayer shows up and the account is full, the least recently active entry
+/// is evicted.  Fee collector can additionally prune idle entries with
+/// `prune_relayer_stats` instruction.
+#[account]
+#[derive(Debug, Default, InitSpace)]
+pub struct RelayerStats {
+    #[max_len(MAX_RELAYER_STATS)]
+    pub entries: Vec<RelayerStatsEntry>,
+}
+
+/// Delivery counters of a single relayer.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    InitSpace,
+)]
+pub struct RelayerStatsEntry {
+    /// Address of the relayer, i.e. signer of the `deliver` instruction.
+    pub relayer: Pubkey,
+
+    /// Number of received packets delivered by the relayer.
+    pub packets_delivered: u64,
+
+    /// Number of acknowledgements delivered by the relayer.
+    pub acks: u64,
+
+    /// Number of timeouts delivered by the relayer.
+    pub timeouts: u64,
+
+    /// Lamports the relayer paid while delivering messages, e.g. rent of
+    /// receivers’ token accounts.  Doesn’t include transaction fees which
+    /// aren’t visible to the program.
+    pub fees_paid: u64,
+
+    /// Slot in which the relayer was first recorded.
+    pub first_slot: u64,
+
+    /// Slot of the relayer’s last delivery.
+    pub last_slot: u64,
+}
+
+/// Counts of packet messages delivered in a single `deliver` call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelayerDelivery {
+    pub packets_delivered: u64,
+    pub acks: u64,
+    pub timeouts: u64,
+    pub fees_paid: u64,
+}
+
+impl RelayerStats {
+    /// Returns counters of given relayer.
+    pub fn get(&self, relayer: &Pubkey) -> Option<&RelayerStatsEntry> {
+        self.entries.iter().find(|entry| &entry.relayer == relayer)
+    }
+
+    /// Adds delivery counters to the relayer’s entry.
+    ///
+    /// If the relayer has no entry and the account is full, evicts entry of
+    /// the least recently active relayer.
+    pub fn record(
+        &mut self,
+        relayer: &Pubkey,
+        delivery: RelayerDelivery,
+        slot: u64,
+    ) {
+        let idx = self.entries.iter().position(|e| &e.relayer == relayer);
+        let idx = match idx {
+            Some(idx) => idx,
+            None => {
+                let entry = RelayerStatsEntry {
+                    relayer: *relayer,
+                    first_slot: slot,
+                    ..Default::default()
+                };
+                if self.entries.len() < MAX_RELAYER_STATS {
+                    self.entries.push(entry);
+                    self.entries.len() - 1
+                } else {
+                    let (idx, _) = self
+                        .entries
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, entry)| entry.last_slot)
+                        .unwrap();
+                    self.entries[idx] = entry;
+                    idx
+                }
+            }
+        };
+        let entry = &mut self.entries[idx];
+        let add = |counter: &mut u64, value: u64| {
+            *counter = counter.saturating_add(value)
+        };
+        add(&mut entry.packets_delivered, delivery.packets_delivered);
+        add(&mut entry.acks, delivery.acks);
+        add(&mut entry.timeouts, delivery.timeouts);
+        add(&mut entry.fees_paid, delivery.fees_paid);
+        entry.last_slot = slot;
+    }
+
+    /// Removes entries of relayers which haven’t delivered anything since
+    /// `min_slot`.  Returns number of removed entries.
+    pub fn prune(&mut self, min_slot: u64) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.last_slot >= min_slot);
+        len - self.entries.len()
+    }
+}
+
+/// Pause flags of a single channel.
+///
+/// Unlike [`HaltFlags`] which affect the whole bridge, these let the fee
+/// collector stop transfers over a single channel, e.g. when its counterparty
+/// has been compromised.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct ChannelPause {
+    /// Whether sending transfers over the channel is paused.
+    pub send: bool,
+
+    /// Whether receiving transfers over the channel is paused.  Received
+    /// packets are acknowledged with an error so the tokens are refunded on
+    /// the source chain.
+    pub receive: bool,
+}
+
+impl ChannelPause {
+    /// Returns whether any of the flags is set.
+    pub fn is_paused(&self) -> bool { self.send || self.receive }
+}
+
+/// Highest sequences of packets processed on a single channel.
+///
+/// Updated when packets are delivered.  When a packet skips over sequences
+/// which haven’t been seen yet, a [`crate::events::SequenceGap`] event is
+/// emitted so that monitoring can notice lost relayer transactions before
+/// users do.  Returned by `last_seen_sequences` instruction.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct LastSeenSequences {
+    /// Highest sequence of a packet received from the counterparty or zero
+    /// if none has been received.
+    pub recv: u64,
+
+    /// Highest sequence of a sent packet which has been acknowledged or has
+    /// timed out or zero if none has been.
+    pub ack: u64,
+}
+
+/// Kind of a sequence tracked in [`LastSeenSequences`].
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum SeenSequenceKind {
+    /// Sequence of a received packet.
+    Recv,
+    /// Sequence of an acknowledged or timed out sent packet.
+    Ack,
+}
+
+impl LastSeenSequences {
+    /// Records sequence of a processed packet.
+    ///
+    /// Returns inclusive range of sequences which have been skipped over, if
+    /// any.  Packets with sequences lower than the highest seen so far fill
+    /// earlier gaps and are not reported.
+    pub fn observe(
+        &mut self,
+        kind: SeenSequenceKind,
+        sequence: u64,
+    ) -> Option<(u64, u64)> {
+        let last = match kind {
+            SeenSequenceKind::Recv => &mut self.recv,
+            SeenSequenceKind::Ack => &mut self.ack,
+        };
+        if sequence <= *last {
+            return None;
+        }
+        let first_missing = *last + 1;
+        *last = sequence;
+        (first_missing < sequence).then_some((first_missing, sequence - 1))
+    }
+}
+
+/// Single halt flag; see [`HaltFlags`].
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum HaltFlag {
+    Inbound,
+    Outbound,
+    ClientUpdates,
+}
+
+impl HaltFlags {
+    /// Returns whether given flag is set.
+    pub fn get(&self, flag: HaltFlag) -> bool {
+        match flag {
+            HaltFlag::Inbound => self.inbound,
+            HaltFlag::Outbound => self.outbound,
+            HaltFlag::ClientUpdates => self.client_updates,
+        }
+    }
+
+    /// Returns mutable reference to given flag.
+    pub fn get_mut(&mut self, flag: HaltFlag) -> &mut bool {
+        match flag {
+            HaltFlag::Inbound => &mut self.inbound,
+            HaltFlag::Outbound => &mut self.outbound,
+            HaltFlag::ClientUpdates => &mut self.client_updates,
+        }
+    }
+
+    /// Returns `Halted` error if given flag is set.
+    pub fn check(&self, flag: HaltFlag) -> Result<(), crate::error::Error> {
+        if self.get(flag) {
+            Err(crate::error::Error::Halted)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Schedule of fees charged for outgoing transfers.
+///
+/// The fee consists of a flat part and a part proportional to the transferred
+/// amount and is clamped to `[min_lamports, max_lamports]` range.  The
+/// proportional part is calculated from the amount expressed in units used in
+/// ICS-20 packets and charged in lamports so per-denom overrides should pick
+/// `amount_bps` appropriate for the token’s value.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct FeeSchedule {
+    /// Flat part of the fee in lamports.
+    pub flat_lamports: u64,
+
+    /// Part of the fee proportional to the amount in basis points.
+    pub amount_bps: u16,
+
+    /// Minimum fee in lamports.
+    pub min_lamports: u64,
+
+    /// Maximum fee in lamports.
+    pub max_lamports: u64,
+}
+
+impl FeeSchedule {
+    /// Checks that the basis points don’t exceed 100% and that the bounds
+    /// form a valid range.
+    pub fn is_valid(&self) -> bool {
+        self.amount_bps <= 10_000 && self.min_lamports <= self.max_lamports
+    }
+
+    /// Returns fee for transferring given amount.
+    pub fn fee(&self, amount: u64) -> u64 {
+        let part = u128::from(amount) * u128::from(self.amount_bps) / 10_000;
+        let fee = self.flat_lamports.saturating_add(part as u64);
+        fee.max(self.min_lamports).min(self.max_lamports)
+    }
+}
+
+/// Quota of a token which can be transferred within a rate limit window.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum RateLimitQuota {
+    /// Absolute amount expressed in token’s units on Solana.
+    Amount(u64),
+
+    /// Part of token’s supply in basis points.
+    ///
+    /// For tokens minted by the bridge the supply is the mint’s supply.  For
+    /// native tokens it’s the balance of the escrow account.  Wrapped SOL is
+    /// held in native SOL rather than in the escrow account so its supply is
+    /// zero and absolute quota should be used instead.
+    SupplyBps(u16),
+}
+
+/// Direction of a transfer limited by [`RateLimit`].
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub enum TransferDirection {
+    /// Tokens received from the counterparty, i.e. unescrowed or minted.
+    Inbound,
+    /// Tokens sent to the counterparty, i.e. escrowed or burned.
+    Outbound,
+}
+
+/// Configuration of a token’s rate limit.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct RateLimitConfig {
+    /// Length of the sliding window in guest blocks.
+    pub window: u64,
+
+    /// Quota of received tokens or `None` if they aren’t limited.
+    pub inbound: Option<RateLimitQuota>,
+
+    /// Quota of sent tokens or `None` if they aren’t limited.
+    pub outbound: Option<RateLimitQuota>,
+}
+
+/// Amounts transferred in one direction used to enforce a [`RateLimit`].
+///
+/// The sliding window is approximated with two fixed windows: the current one
+/// and the one preceding it.  Amount transferred in the previous window is
+/// weighted by how much of it overlaps with the sliding window.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct RateLimitUsage {
+    /// Guest block height at which the current fixed window started.
+    pub window_start: u64,
+    /// Amount transferred in the previous fixed window.
+    pub previous: u64,
+    /// Amount transferred in the current fixed window.
+    pub current: u64,
+}
+
+/// Rate limit of transfers of a single token.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
+pub struct RateLimit {
+    pub config: RateLimitConfig,
+    pub inbound: RateLimitUsage,
+    pub outbound: RateLimitUsage,
+}
+
+impl RateLimitQuota {
+    /// Returns maximum amount which can be transferred given token’s supply.
+    pub fn limit(&self, supply: u64) -> u64 {
+        match *self {
+            Self::Amount(amount) => amount,
+            Self::SupplyBps(bps) => {
+                (u128::from(supply) * u128::from(bps) / 10_000) as u64
+            }
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Checks that the window isn’t empty and basis points don’t exceed 100%.
+    pub fn is_valid(&self) -> bool {
+        let valid_quota = |quota: &Option<RateLimitQuota>| match quota {
+            Some(RateLimitQuota::SupplyBps(bps)) => *bps <= 10_000,
+            _ => true,
+        };
+        self.window != 0 &&
+            valid_quota(&self.inbound) &&
+            valid_quota(&self.outbound)
+    }
+}
+
+impl RateLimitUsage {
+    /// Moves the fixed windows forward so that the current one includes guest
+    /// block at given height.
+    fn advance(&mut self, height: u64, window: u64) {
+        let elapsed = height.saturating_sub(self.window_start);
+        if elapsed >= 2 * window {
+            *self = Self {
+                window_start: height - elapsed % window,
+                previous: 0,
+                current: 0,
+            };
+        } else if elapsed >= window {
+            *self = Self {
+                window_start: self.window_start + window,
+                previous: self.current,
+                current: 0,
+            };
+        }
+    }
+
+    /// Returns amount transferred within the sliding window ending at guest
+    /// block at given height.
+    ///
+    /// The usage must have been advanced to the height.
+    fn used(&self, height: u64, window: u64) -> u64 {
+        let overlap = window - (height - self.window_start);
+        let previous = u128::from(self.previous) * u128::from(overlap) /
+            u128::from(window);
+        (previous as u64).saturating_add(self.current)
+    }
+}
+
+impl RateLimit {
+    /// Creates a new rate limit with no transfers recorded.
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            inbound: Default::default(),
+            outbound: Default::default(),
+        }
+    }
+
+    /// Records transfer of given amount at guest block at given height.
+    ///
+    /// `supply` is called to get token’s supply if the quota is a percentage
+    /// of it.  Returns `RateLimitExceeded` error if the transfer would exceed
+    /// the quota in which case nothing is recorded.
+    pub fn consume(
+        &mut self,
+        direction: TransferDirection,
+        height: u64,
+        amount: u64,
+        supply: impl FnOnce() -> Result<u64, crate::error::Error>,
+    ) -> Result<(), crate::error::Error> {
+        let (quota, usage) = match direction {
+            TransferDirection::Inbound => {
+                (self.config.inbound, &mut self.inbound)
+            }
+            TransferDirection::Outbound => {
+                (self.config.outbound, &mut self.outbound)
+            }
+        };
+        let quota = match quota {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        let window = self.config.window;
+        usage.advance(height, window);
+        let limit = match quota {
+            RateLimitQuota::Amount(amount) => amount,
+            RateLimitQuota::SupplyBps(_) => quota.limit(supply()?),
+        };
+        let used = usage.used(height, window);
+        if used.saturating_add(amount) > limit {
+            return Err(crate::error::Error::RateLimitExceeded);
+        }
+        usage.current = usage.current.saturating_add(amount);
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
@@ -326,8 +1391,88 @@ pub struct Asset {
     pub effective_decimals_on_sol: u8,
 }
 
+/// Trace of a wrapped NFT class; see [`crate::nft_transfer`].
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct NftClass {
+    /// Full class identifier including the port and channel prefixes.
+    pub class_id: String,
+
+    /// URI of the class as sent by the counterparty chain, if any.
+    pub class_uri: Option<String>,
+}
+
+/// Display metadata of a wrapped token overriding the one the token mint has
+/// been initialised with.
+#[derive(
+    Clone, Debug, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct DenomMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl DenomMetadata {
+    /// Maximum length of the name accepted by Metaplex Token Metadata.
+    pub const MAX_NAME_LENGTH: usize = 32;
+    /// Maximum length of the symbol accepted by Metaplex Token Metadata.
+    pub const MAX_SYMBOL_LENGTH: usize = 10;
+    /// Maximum length of the URI accepted by Metaplex Token Metadata.
+    pub const MAX_URI_LENGTH: usize = 200;
+
+    /// Checks that the fields fit in Metaplex metadata account.
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty() &&
+            !self.symbol.is_empty() &&
+            self.name.len() <= Self::MAX_NAME_LENGTH &&
+            self.symbol.len() <= Self::MAX_SYMBOL_LENGTH &&
+            self.uri.len() <= Self::MAX_URI_LENGTH
+    }
+}
 
 impl PrivateStorage {
+    /// Returns fee in lamports charged for an outgoing transfer of given
+    /// amount of given token.
+    ///
+    /// Uses per-denom schedule if one is set, the default schedule otherwise
+    /// and falls back to flat `fee_in_lamports` if neither is configured.
+    pub fn transfer_fee(
+        &self,
+        hashed_full_denom: &CryptoHash,
+        amount: u64,
+    ) -> u64 {
+        self.denom_fee_schedules
+            .get(hashed_full_denom)
+            .or(self.fee_schedule.as_ref())
+            .map_or(self.fee_in_lamports, |schedule| schedule.fee(amount))
+    }
+
+    /// Returns pause flags of given channel.
+    pub fn channel_pause(
+        &self,
+        port_id: &ibc::PortId,
+        channel_id: &ibc::ChannelId,
+    ) -> ChannelPause {
+        trie_ids::PortChannelPK::try_from(port_id, channel_id)
+            .ok()
+            .and_then(|key| self.paused_channels.get(&key).copied())
+            .unwrap_or_default()
+    }
+
+    /// Returns highest sequences of packets processed on given channel.
+    pub fn last_seen_sequences(
+        &self,
+        port_id: &ibc::PortId,
+        channel_id: &ibc::ChannelId,
+    ) -> LastSeenSequences {
+        trie_ids::PortChannelPK::try_from(port_id, channel_id)
+            .ok()
+            .and_then(|key| self.last_seen_sequences.get(&key).copied())
+            .unwrap_or_default()
+    }
+
     /// Returns number of known clients; or counter for the next client.
     pub fn client_counter(&self) -> u64 {
         u64::try_from(self.clients.len()).unwrap()
@@ -473,6 +1618,9 @@ pub struct TransferAccounts<'a> {
     pub mint_authority: Option<AccountInfo<'a>>,
     pub token_program: Option<AccountInfo<'a>>,
     pub fee_collector: Option<AccountInfo<'a>>,
+    /// Pending claim token account credited instead of `token_account` if
+    /// [`ReceiverAccountPolicy::Escrow`] applies.
+    pub pending_claim_account: Option<AccountInfo<'a>>,
     /// Contains the list of accounts required for the hooks
     /// if present
     pub remaining_accounts: Vec<AccountInfo<'a>>,
@@ -484,6 +1632,12 @@ pub(crate) struct IbcStorageInner<'a, 'b> {
     pub provable: TrieAccount<'a, 'b>,
     pub accounts: TransferAccounts<'b>,
     pub chain: &'a mut crate::chain::ChainData,
+    /// Error of a transfer hook which ran after the tokens were credited.
+    ///
+    /// Such failure can’t be reported with an error acknowledgement so it’s
+    /// recorded here and makes the whole instruction fail once the message
+    /// is dispatched.
+    pub hook_error: Option<ProgramError>,
 }
 
 /// A reference-counted reference to the IBC storage.
@@ -578,6 +1732,7 @@ macro_rules! from_ctx {
                 .fee_collector
                 .as_deref()
                 .map(ToAccountInfo::to_account_info),
+            pending_claim_account: None,
             remaining_accounts: remaining_accounts.to_vec()
         };
         $crate::storage::from_ctx!($ctx, accounts = accounts)
@@ -601,6 +1756,7 @@ macro_rules! from_ctx {
             provable,
             chain,
             accounts: $accounts,
+            hook_error: None,
         })
     }};
 }
@@ -721,3 +1877,319 @@ fn test_sequence_triple() {
         triple.to_hash().as_array(),
     );
 }
+
+#[test]
+fn test_hook_stats() {
+    let mut stats = HookStats::default();
+    stats.record(Ok(()), 10);
+    stats.record(Err(HookFailure::BridgeEscrow), 11);
+    stats.record(Ok(()), 12);
+    stats.record(Err(HookFailure::MemoHook), 13);
+    stats.record(Err(HookFailure::MemoHook), 14);
+    stats.record(Ok(()), 15);
+
+    assert_eq!(6, stats.invocations);
+    assert_eq!(3, stats.successes);
+    assert_eq!(0, stats.uninitialised_account_failures);
+    assert_eq!(1, stats.bridge_escrow_failures);
+    assert_eq!(2, stats.memo_hook_failures);
+    assert_eq!(14, stats.last_failure_slot);
+}
+
+#[test]
+fn test_relayer_stats() {
+    let relayers: Vec<Pubkey> =
+        (0..=MAX_RELAYER_STATS).map(|_| Pubkey::new_unique()).collect();
+    let delivery = RelayerDelivery {
+        packets_delivered: 2,
+        acks: 1,
+        timeouts: 0,
+        fees_paid: 100,
+    };
+
+    let mut stats = RelayerStats::default();
+    stats.record(&relayers[0], delivery, 10);
+    stats.record(&relayers[0], delivery, 12);
+    let want = RelayerStatsEntry {
+        relayer: relayers[0],
+        packets_delivered: 4,
+        acks: 2,
+        timeouts: 0,
+        fees_paid: 200,
+        first_slot: 10,
+        last_slot: 12,
+    };
+    assert_eq!(Some(&want), stats.get(&relayers[0]));
+    assert_eq!(None, stats.get(&relayers[1]));
+
+    // Fill the account.  Relayer 1 is the least recently active one.
+    for (idx, relayer) in relayers[1..MAX_RELAYER_STATS].iter().enumerate() {
+        stats.record(relayer, delivery, 11 + idx as u64);
+    }
+    stats.record(&relayers[0], delivery, 100);
+    assert_eq!(MAX_RELAYER_STATS, stats.entries.len());
+
+    // New relayer evicts relayer 1.
+    stats.record(&relayers[MAX_RELAYER_STATS], delivery, 101);
+    assert_eq!(MAX_RELAYER_STATS, stats.entries.len());
+    assert_eq!(None, stats.get(&relayers[1]));
+    assert_eq!(6, stats.get(&relayers[0]).unwrap().packets_delivered);
+    let entry = stats.get(&relayers[MAX_RELAYER_STATS]).unwrap();
+    assert_eq!((101, 101, 1), (entry.first_slot, entry.last_slot, entry.acks));
+
+    // Pruning leaves only relayers active since given slot.
+    assert_eq!(MAX_RELAYER_STATS - 2, stats.prune(100));
+    assert_eq!(2, stats.entries.len());
+    assert!(stats.get(&relayers[0]).is_some());
+    assert_eq!(0, stats.prune(100));
+}
+
+#[test]
+fn test_multisig_fee_collector_vault() {
+    let multisig = MultisigFeeCollector {
+        program_id: Pubkey::new_unique(),
+        multisig: Pubkey::new_unique(),
+        vault_index: 0,
+    };
+    let vault = multisig.vault();
+    let seeds = [
+        b"multisig".as_ref(),
+        multisig.multisig.as_ref(),
+        b"vault".as_ref(),
+        &[0],
+    ];
+    let (want, bump) =
+        Pubkey::find_program_address(&seeds, &multisig.program_id);
+    assert_eq!(want, vault);
+    assert!(!vault.is_on_curve());
+    let seeds = [seeds[0], seeds[1], seeds[2], seeds[3], &[bump]];
+    assert_eq!(
+        Ok(vault),
+        Pubkey::create_program_address(&seeds, &multisig.program_id)
+    );
+
+    let other = MultisigFeeCollector { vault_index: 1, ..multisig.clone() };
+    assert_ne!(vault, other.vault());
+    let other = MultisigFeeCollector {
+        program_id: Pubkey::new_unique(),
+        ..multisig.clone()
+    };
+    assert_ne!(vault, other.vault());
+}
+
+#[test]
+fn test_denom_metadata_is_valid() {
+    let metadata = DenomMetadata {
+        name: "Wrapped Atom".into(),
+        symbol: "wATOM".into(),
+        uri: "https://example.com/atom.json".into(),
+    };
+    assert!(metadata.is_valid());
+    assert!(DenomMetadata { uri: String::new(), ..metadata.clone() }.is_valid());
+
+    assert!(
+        !DenomMetadata { name: String::new(), ..metadata.clone() }.is_valid()
+    );
+    assert!(
+        !DenomMetadata { symbol: String::new(), ..metadata.clone() }.is_valid()
+    );
+    assert!(
+        !DenomMetadata { name: "n".repeat(33), ..metadata.clone() }.is_valid()
+    );
+    assert!(!DenomMetadata { symbol: "s".repeat(11), ..metadata.clone() }
+        .is_valid());
+    assert!(!DenomMetadata { uri: "u".repeat(201), ..metadata }.is_valid());
+}
+
+#[test]
+fn test_prune_consensus_states() {
+    let state = |time: u64| {
+        let bytes = time.to_le_bytes().to_vec();
+        ClientConsensusState(Serialised(bytes, core::marker::PhantomData))
+    };
+    let height = |height: u64| ibc::Height::new(1, height).unwrap();
+    let make_states = || {
+        [(5, 50), (1, 10), (3, 30), (2, 20), (4, 40), (6, 60)]
+            .into_iter()
+            .map(|(h, time)| (height(h), state(time)))
+            .collect::<Vec<_>>()
+    };
+    let heights = |states: &[(ibc::Height, ClientConsensusState)]| {
+        let mut heights: Vec<_> =
+            states.iter().map(|(h, _)| h.revision_height()).collect();
+        heights.sort();
+        heights
+    };
+
+    // States processed at or before cutoff are removed.
+    let mut states = make_states();
+    let pruned = ClientStore::prune_consensus_states_impl(&mut states, 30, 2);
+    assert_eq!(vec![height(3), height(2), height(1)], pruned);
+    assert_eq!(vec![4, 5, 6], heights(&states));
+
+    // Most recent states are kept even if they are old.
+    let mut states = make_states();
+    let pruned = ClientStore::prune_consensus_states_impl(&mut states, 100, 2);
+    assert_eq!(4, pruned.len());
+    assert_eq!(vec![5, 6], heights(&states));
+
+    // Nothing to prune.
+    let mut states = make_states();
+    let pruned = ClientStore::prune_consensus_states_impl(&mut states, 5, 0);
+    assert!(pruned.is_empty());
+    assert_eq!(vec![1, 2, 3, 4, 5, 6], heights(&states));
+}
+
+#[test]
+#[cfg(feature = "compress-states")]
+fn test_consensus_state_compression() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&42u64.to_le_bytes());
+    bytes.extend_from_slice(&24u64.to_le_bytes());
+    bytes.extend((0..1000u32).map(|n| (n % 7) as u8));
+    let state = ClientConsensusState(Serialised(bytes, Default::default()));
+    assert!(!state.is_compressed());
+
+    let compressed = state.clone().compress();
+    assert!(compressed.is_compressed());
+    assert!(compressed.stored_size() < state.stored_size());
+    assert_eq!(state.processed_time(), compressed.processed_time());
+    assert_eq!(state.processed_height(), compressed.processed_height());
+    let client_id =
+        <ibc::ClientId as core::str::FromStr>::from_str("foo-bar-1").unwrap();
+    assert_eq!(
+        state.digest(&client_id).unwrap(),
+        compressed.digest(&client_id).unwrap()
+    );
+
+    // Compressing again is a no-op.
+    assert_eq!(
+        compressed.0.as_bytes(),
+        compressed.clone().compress().0.as_bytes()
+    );
+}
+
+#[test]
+fn test_upgrade_governance() {
+    use crate::error::Error;
+
+    let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let stranger = Pubkey::new_unique();
+    let buffer = Pubkey::new_unique();
+    let buffer_hash = CryptoHash::test(1);
+
+    assert_eq!(None, UpgradeGovernance::new(Vec::new(), 1, 0));
+    assert_eq!(None, UpgradeGovernance::new(keys.clone(), 0, 0));
+    assert_eq!(None, UpgradeGovernance::new(keys.clone(), 4, 0));
+    let dup = alloc::vec![keys[0], keys[1], keys[0]];
+    assert_eq!(None, UpgradeGovernance::new(dup, 2, 0));
+
+    let mut gov = UpgradeGovernance::new(keys.clone(), 2, 100).unwrap();
+    assert!(matches!(gov.take_ready(1000), Err(Error::NoUpgradeProposal)));
+    assert!(matches!(
+        gov.propose(stranger, buffer, buffer_hash, 1000),
+        Err(Error::NotUpgradeApprover)
+    ));
+    gov.propose(keys[0], buffer, buffer_hash, 1000).unwrap();
+    assert!(matches!(
+        gov.take_ready(2000),
+        Err(Error::InsufficientUpgradeApprovals)
+    ));
+    assert!(matches!(gov.approve(keys[0], &buffer), Ok(1)));
+    assert!(matches!(
+        gov.approve(keys[1], &Pubkey::new_unique()),
+        Err(Error::NoUpgradeProposal)
+    ));
+    assert!(matches!(
+        gov.approve(stranger, &buffer),
+        Err(Error::NotUpgradeApprover)
+    ));
+    assert!(matches!(gov.approve(keys[1], &buffer), Ok(2)));
+    assert!(matches!(gov.take_ready(1099), Err(Error::UpgradeTimelockActive)));
+    assert!(matches!(
+        gov.take_ready(1100),
+        Ok(p) if p.buffer == buffer && p.buffer_hash == buffer_hash
+    ));
+    assert_eq!(None, gov.proposal);
+}
+
+#[test]
+fn test_fee_schedule() {
+    let schedule = FeeSchedule {
+        flat_lamports: 1000,
+        amount_bps: 10,
+        min_lamports: 2000,
+        max_lamports: 1_000_000,
+    };
+    assert!(schedule.is_valid());
+    assert!(!FeeSchedule { amount_bps: 10_001, ..schedule }.is_valid());
+    assert!(!FeeSchedule { min_lamports: 2_000_000, ..schedule }.is_valid());
+    assert_eq!(2000, schedule.fee(0));
+    assert_eq!(2000, schedule.fee(1_000_000));
+    assert_eq!(11_000, schedule.fee(10_000_000));
+    assert_eq!(1_000_000, schedule.fee(u64::MAX));
+}
+
+#[test]
+fn test_last_seen_sequences() {
+    use SeenSequenceKind::{Ack, Recv};
+
+    let mut seen = LastSeenSequences::default();
+    assert_eq!(None, seen.observe(Recv, 1));
+    assert_eq!(None, seen.observe(Recv, 2));
+    assert_eq!(Some((3, 4)), seen.observe(Recv, 5));
+    // Filling the gap or repeating sequences isn’t reported.
+    assert_eq!(None, seen.observe(Recv, 3));
+    assert_eq!(None, seen.observe(Recv, 5));
+    assert_eq!(Some((6, 6)), seen.observe(Recv, 7));
+    assert_eq!(LastSeenSequences { recv: 7, ack: 0 }, seen);
+
+    assert_eq!(Some((1, 2)), seen.observe(Ack, 3));
+    assert_eq!(None, seen.observe(Ack, 4));
+    assert_eq!(LastSeenSequences { recv: 7, ack: 4 }, seen);
+}
+
+#[test]
+fn test_rate_limit() {
+    use TransferDirection::{Inbound, Outbound};
+
+    let config = RateLimitConfig {
+        window: 10,
+        inbound: Some(RateLimitQuota::Amount(100)),
+        outbound: Some(RateLimitQuota::SupplyBps(1_000)),
+    };
+    assert!(config.is_valid());
+    assert!(!RateLimitConfig { window: 0, ..config }.is_valid());
+    assert!(!RateLimitConfig {
+        outbound: Some(RateLimitQuota::SupplyBps(10_001)),
+        ..config
+    }
+    .is_valid());
+
+    let supply = || Ok(1_000);
+    let mut limit = RateLimit::new(config);
+    let mut consume = |direction, height, amount| {
+        limit.consume(direction, height, amount, supply).is_ok()
+    };
+
+    // Quotas are tracked separately in each direction.
+    assert!(consume(Inbound, 1, 60));
+    assert!(consume(Inbound, 5, 40));
+    assert!(!consume(Inbound, 9, 1));
+    assert!(consume(Outbound, 9, 100));
+    assert!(!consume(Outbound, 9, 1));
+
+    // Half of the previous window overlaps with the sliding window.
+    assert!(!consume(Inbound, 15, 51));
+    assert!(consume(Inbound, 15, 50));
+    // At the start of the next window, all of the previous one does.
+    assert!(consume(Inbound, 20, 50));
+    assert!(!consume(Inbound, 20, 1));
+
+    // Long pause resets the usage.
+    assert!(consume(Inbound, 100, 100));
+    assert_eq!(
+        RateLimitUsage { window_start: 100, previous: 0, current: 100 },
+        limit.inbound
+    );
+}
@@ -230,7 +230,7 @@ fn anchor_test_deliver() -> Result<()> {
         &write_account_program_id,
         authority.pubkey(),
         WRITE_ACCOUNT_SEED,
-        instruction_data,
+        instruction_data.clone(),
     )
     .unwrap();
     // Note: We’re using small chunks size on purpose to test the behaviour of
@@ -250,6 +250,27 @@ fn anchor_test_deliver() -> Result<()> {
     }
     let (write_account, write_account_bump) = chunks.into_account();
 
+    // Mark the data as verified.  Solana IBC rejects unverified data.
+    let commit = write::instruction::commit(
+        write_account_program_id,
+        authority.pubkey(),
+        Some(write_account),
+        WRITE_ACCOUNT_SEED,
+        write_account_bump,
+        &instruction_data,
+    )
+    .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[commit],
+        Some(&authority.pubkey()),
+        &[&*authority],
+        blockhash,
+    );
+    let sig = sol_rpc_client
+        .send_and_confirm_transaction_with_spinner(&transaction)
+        .unwrap();
+    println!("  Signature {sig}");
+
     println!("\nCreating Mock Client");
     let sig = program
         .request()
@@ -270,6 +291,7 @@ fn anchor_test_deliver() -> Result<()> {
                 receiver_token_account: None,
                 associated_token_program: None,
                 token_program: None,
+                pending_claim_account: None,
             },
             chunk_account,
         ))
@@ -327,6 +349,7 @@ fn anchor_test_deliver() -> Result<()> {
             receiver_token_account: None,
             associated_token_program: None,
             token_program: None,
+            pending_claim_account: None,
         })
         .args(instruction::Deliver { message })
         .payer(authority.clone())
@@ -636,6 +659,7 @@ fn anchor_test_deliver() -> Result<()> {
             receiver_token_account: Some(receiver_token_address),
             associated_token_program: Some(anchor_spl::associated_token::ID),
             token_program: Some(anchor_spl::token::ID),
+            pending_claim_account: None,
         })
         .args(instruction::Deliver { message })
         .payer(authority.clone())
@@ -799,6 +823,7 @@ fn anchor_test_deliver() -> Result<()> {
             receiver_token_account: Some(receiver_wrapped_sol_acc),
             associated_token_program: Some(anchor_spl::associated_token::ID),
             token_program: Some(anchor_spl::token::ID),
+            pending_claim_account: None,
         })
         .args(instruction::Deliver { message })
         .payer(authority.clone())
@@ -831,6 +856,7 @@ fn anchor_test_deliver() -> Result<()> {
         .accounts(accounts::CollectFees {
             fee_collector,
             storage,
+            chain,
             fee_account: fee_collector_pda,
         })
         .args(instruction::CollectFees {})
@@ -0,0 +1,342 @@
+//! Generic hooks executed after a successful ICS-20 transfer.
+//!
+//! If memo of a received fungible token packet is a JSON object with an `svm`
+//! field, the field describes Solana instructions which are invoked (via CPI)
+//! once the tokens are transferred.  The memo has the following format:
+//!
+//! ```json
+//! {
+//!     "svm": {
+//!         "instructions": [
+//!             {
+//!                 "program_id": "<base58 program address>",
+//!                 "accounts": [
+//!                     {
+//!                         "pubkey": "<base58 address>",
+//!                         "is_signer": false,
+//!                         "is_writable": true
+//!                     }
+//!                 ],
+//!                 "data": "<base64 instruction data>"
+//!             }
+//!         ]
+//!     }
+//! }
+//! ```
+//!
+//! All accounts used by the instructions (including the invoked programs)
+//! must be passed by the relayer as remaining accounts of the `deliver`
+//! instruction.
+//!
+//! The only account which may be marked as a signer is the hook authority.
+//! It is a PDA derived from `[HOOK_SEED, channel_id, hash(sender)]` seeds
+//! where `channel_id` is the receiving channel on this chain and `sender` is
+//! the sender of the transfer on the counterparty chain.  The smart contract
+//! signs the calls on behalf of that PDA which lets the invoked programs
+//! authenticate the counterparty sender.
+//!
+//! The memo is checked before the tokens are credited.  If it’s malformed or
+//! any of the instructions isn’t permitted, the packet is acknowledged with
+//! an error and the tokens are refunded on the counterparty chain.  If any of
+//! the instructions fails once the tokens have been credited, the whole
+//! `deliver` transaction fails.
+use anchor_lang::prelude::*;
+use base64::Engine;
+use lib::hash::CryptoHash;
+use spl_token::solana_program::instruction::Instruction;
+use spl_token::solana_program::program::invoke_signed;
+
+use crate::ibc::apps::transfer::types::packet::PacketData;
+use crate::{ibc, HOOK_SEED};
+
+/// Top-level structure of the memo.  Fields other than `svm` are ignored.
+#[derive(serde::Deserialize)]
+struct Memo {
+    svm: Option<SvmMemo>,
+}
+
+#[derive(serde::Deserialize)]
+struct SvmMemo {
+    instructions: Vec<SvmInstruction>,
+}
+
+#[derive(serde::Deserialize)]
+struct SvmInstruction {
+    program_id: String,
+    #[serde(default)]
+    accounts: Vec<SvmAccount>,
+    #[serde(default)]
+    data: String,
+}
+
+#[derive(serde::Deserialize)]
+struct SvmAccount {
+    pubkey: String,
+    #[serde(default)]
+    is_signer: bool,
+    #[serde(default)]
+    is_writable: bool,
+}
+
+/// Returns address and bump of the hook authority PDA for given receiving
+/// channel and counterparty sender.
+pub fn hook_authority(
+    channel_id: &ibc::ChannelId,
+    sender: &str,
+) -> (Pubkey, u8) {
+    let sender = CryptoHash::digest(sender.as_bytes());
+    Pubkey::find_program_address(
+        &[HOOK_SEED, channel_id.as_str().as_bytes(), sender.as_slice()],
+        &crate::ID,
+    )
+}
+
+/// Instructions encoded in the memo of a received packet together with the
+/// hook authority which signs them.
+pub(super) struct MemoHooks {
+    instructions: Vec<Instruction>,
+    channel_id: ibc::ChannelId,
+    sender: CryptoHash,
+    bump: u8,
+}
+
+/// Parses and checks instructions encoded in the memo of a received packet.
+///
+/// Returns `None` if the memo isn’t a JSON object or doesn’t have an `svm`
+/// field.  Returns error acknowledgement if the `svm` field is malformed or
+/// any of the instructions is not permitted.
+///
+/// This must be called before the tokens are credited.  Rejecting the packet
+/// afterwards would refund the sender on the counterparty chain while the
+/// tokens remain credited on this chain.
+pub(super) fn parse_memo_hooks(
+    packet: &ibc::Packet,
+) -> Result<Option<MemoHooks>, ibc::AcknowledgementStatus> {
+    let data =
+        serde_json::from_slice::<PacketData>(&packet.data).map_err(|_| {
+            ibc::AcknowledgementStatus::error(
+                ibc::TokenTransferError::PacketDataDeserialization.into(),
+            )
+        })?;
+    let (authority, bump) =
+        hook_authority(&packet.chan_id_on_b, data.sender.as_ref());
+    let instructions = match memo_instructions(data.memo.as_ref(), &authority)
+        .map_err(to_ack)?
+    {
+        Some(instructions) => instructions,
+        None => return Ok(None),
+    };
+    Ok(Some(MemoHooks {
+        instructions,
+        channel_id: packet.chan_id_on_b.clone(),
+        sender: CryptoHash::digest(data.sender.as_ref().as_bytes()),
+        bump,
+    }))
+}
+
+impl MemoHooks {
+    /// Invokes the instructions signing them as the hook authority.
+    ///
+    /// The tokens have already been credited when this is called so failure
+    /// cannot be reported with an error acknowledgement.  Instead, the error
+    /// is returned and the caller fails the whole `deliver` instruction.
+    /// The packet then either gets delivered again or times out and the
+    /// sender is refunded.
+    pub(super) fn invoke(
+        self,
+        accounts: &[AccountInfo],
+    ) -> Result<(), ProgramError> {
+        let seeds = [
+            HOOK_SEED,
+            self.channel_id.as_str().as_bytes(),
+            self.sender.as_slice(),
+            core::slice::from_ref(&self.bump),
+        ];
+        for instruction in self.instructions {
+            invoke_signed(&instruction, accounts, &[&seeds[..]]).map_err(
+                |err| {
+                    msg!("Memo hook failed: {}", err);
+                    err
+                },
+            )?;
+        }
+        msg!("Hook: Memo instructions executed");
+        Ok(())
+    }
+}
+
+/// Parses and checks instructions encoded in the memo.
+///
+/// Returns `None` if the memo isn’t a JSON object with an `svm` field.
+fn memo_instructions(
+    memo: &str,
+    authority: &Pubkey,
+) -> Result<Option<Vec<Instruction>>, String> {
+    let instructions = match serde_json::from_str::<serde_json::Value>(memo) {
+        Ok(value) if value.get("svm").is_some() => parse_memo(value)?,
+        _ => return Ok(None),
+    };
+    for instruction in instructions.iter() {
+        check_instruction(instruction, authority)?;
+    }
+    Ok(Some(instructions))
+}
+
+/// Parses `svm` field of the memo into list of instructions.
+fn parse_memo(memo: serde_json::Value) -> Result<Vec<Instruction>, String> {
+    let memo = serde_json::from_value::<Memo>(memo)
+        .map_err(|err| format!("Invalid memo: {err}"))?;
+    let parse_key = |key: &str| {
+        key.parse::<Pubkey>().map_err(|_| format!("Invalid address: {key}"))
+    };
+    memo.svm
+        .map_or_else(Vec::new, |svm| svm.instructions)
+        .into_iter()
+        .map(|ix| {
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|acc| {
+                    Ok(AccountMeta {
+                        pubkey: parse_key(&acc.pubkey)?,
+                        is_signer: acc.is_signer,
+                        is_writable: acc.is_writable,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(&ix.data)
+                .map_err(|err| format!("Invalid instruction data: {err}"))?;
+            let program_id = parse_key(&ix.program_id)?;
+            Ok(Instruction { program_id, accounts, data })
+        })
+        .collect()
+}
+
+/// Checks whether instruction can be invoked by the hook.
+///
+/// Calling this program recursively is forbidden and the only signer the
+/// instruction may request is the hook authority.  Without the latter check,
+/// a packet could make use of signatures of accounts which signed the
+/// `deliver` transaction (e.g. the relayer).
+fn check_instruction(
+    instruction: &Instruction,
+    authority: &Pubkey,
+) -> Result<(), String> {
+    if instruction.program_id == crate::ID {
+        return Err("Hook cannot call IBC program".into());
+    }
+    match instruction
+        .accounts
+        .iter()
+        .find(|acc| acc.is_signer && &acc.pubkey != authority)
+    {
+        Some(acc) => Err(format!("Hook cannot sign as {}", acc.pubkey)),
+        None => Ok(()),
+    }
+}
+
+fn to_ack(msg: String) -> ibc::AcknowledgementStatus {
+    let err = ibc::TokenTransferError::Other(msg);
+    ibc::AcknowledgementStatus::error(err.into())
+}
+
+#[test]
+fn test_parse_memo() {
+    let program_id = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let memo = format!(
+        r#"{{"svm": {{"instructions": [{{
+            "program_id": "{program_id}",
+            "accounts": [{{"pubkey": "{account}", "is_writable": true}}],
+            "data": "AQID"
+        }}]}}}}"#
+    );
+    let memo = serde_json::from_str(&memo).unwrap();
+    let want = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(account, false)],
+        data: vec![1, 2, 3],
+    };
+    assert_eq!(Ok(vec![want]), parse_memo(memo));
+
+    for memo in [
+        r#"{"svm": {}}"#,
+        r#"{"svm": {"instructions": [{"program_id": "foo"}]}}"#,
+        r#"{"svm": {"instructions": [{
+            "program_id": "11111111111111111111111111111111",
+            "data": "not base64!"
+        }]}}"#,
+    ] {
+        let value = serde_json::from_str(memo).unwrap();
+        assert!(parse_memo(value).is_err(), "memo: {memo}");
+    }
+}
+
+#[test]
+fn test_memo_instructions() {
+    let (authority, _) =
+        hook_authority(&ibc::ChannelId::new(0), "cosmos1sender");
+    let program_id = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+
+    for memo in ["", "not json", "{}", r#"{"forward": {}}"#] {
+        assert_eq!(Ok(None), memo_instructions(memo, &authority), "{memo}");
+    }
+
+    let memo = |program_id: &Pubkey, signer: &Pubkey| {
+        format!(
+            r#"{{"svm": {{"instructions": [{{
+                "program_id": "{program_id}",
+                "accounts": [{{"pubkey": "{signer}", "is_signer": true}}]
+            }}]}}}}"#
+        )
+    };
+    let want = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new_readonly(authority, true)],
+        data: Vec::new(),
+    };
+    assert_eq!(
+        Ok(Some(vec![want])),
+        memo_instructions(&memo(&program_id, &authority), &authority)
+    );
+
+    // Malformed memos and disallowed instructions are rejected so that the
+    // packet can be acknowledged with an error before tokens are credited.
+    for memo in [
+        r#"{"svm": {}}"#.into(),
+        memo(&program_id, &other),
+        memo(&crate::ID, &authority),
+    ] {
+        memo_instructions(&memo, &authority).unwrap_err();
+    }
+}
+
+#[test]
+fn test_check_instruction() {
+    let (authority, _) =
+        hook_authority(&ibc::ChannelId::new(0), "cosmos1sender");
+    let other = Pubkey::new_unique();
+    let ix = |program_id, accounts| Instruction {
+        program_id,
+        accounts,
+        data: Vec::new(),
+    };
+    let program_id = Pubkey::new_unique();
+
+    check_instruction(
+        &ix(program_id, vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(other, false),
+        ]),
+        &authority,
+    )
+    .unwrap();
+    check_instruction(
+        &ix(program_id, vec![AccountMeta::new(other, true)]),
+        &authority,
+    )
+    .unwrap_err();
+    check_instruction(&ix(crate::ID, Vec::new()), &authority).unwrap_err();
+}
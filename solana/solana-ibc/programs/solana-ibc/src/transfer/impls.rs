@@ -2,21 +2,27 @@ use std::cmp::Ordering;
 use std::str::FromStr;
 
 use ::ibc::apps::transfer::types::PrefixedDenom;
-use anchor_lang::prelude::{CpiContext, Pubkey};
+use anchor_lang::prelude::{AccountInfo, CpiContext, Pubkey};
 use anchor_lang::solana_program::msg;
-use anchor_spl::token::{Burn, CloseAccount, MintTo, Transfer};
+use anchor_spl::token_interface::{
+    self, Burn, CloseAccount, MintTo, TransferChecked,
+};
 use lib::hash::CryptoHash;
 use primitive_types::U256;
 use spl_token::solana_program::rent::Rent;
 use spl_token::solana_program::sysvar::Sysvar;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
 
 use crate::ibc::apps::transfer::context::{
     TokenTransferExecutionContext, TokenTransferValidationContext,
 };
 use crate::ibc::apps::transfer::types::{Amount, Memo, PrefixedCoin};
 use crate::ibc::{ChannelId, PortId, TokenTransferError};
-use crate::storage::IbcStorage;
-use crate::{ibc, MINT_ESCROW_SEED};
+use crate::storage::{
+    IbcStorage, ReceiverAccountPolicy, TransferAccounts, TransferDirection,
+};
+use crate::{ibc, MINT_ESCROW_SEED, PENDING_CLAIM_SEED};
 
 /// Account identifier on Solana, i.e. account’s public key.
 #[derive(
@@ -53,12 +59,174 @@ pub fn get_token_mint(
     Ok(Pubkey::find_program_address(&seeds, &crate::ID).0)
 }
 
-fn get_token_account(owner: &Pubkey, token_mint: &Pubkey) -> Pubkey {
-    let seeds =
-        [owner.as_ref(), anchor_spl::token::ID.as_ref(), token_mint.as_ref()];
+/// Returns associated token account of given owner.
+///
+/// `token_program` is either the legacy Token program or the Token-2022
+/// program which owns the `token_mint`.
+fn get_token_account(
+    owner: &Pubkey,
+    token_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    let seeds = [owner.as_ref(), token_program.as_ref(), token_mint.as_ref()];
     Pubkey::find_program_address(&seeds, &anchor_spl::associated_token::ID).0
 }
 
+/// Returns the PDA which owns pending claim token accounts of given receiver.
+pub fn get_pending_claim_authority(receiver: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PENDING_CLAIM_SEED, receiver.as_ref()],
+        &crate::ID,
+    )
+}
+
+/// Returns whether token account has a delegate or close authority set.
+///
+/// Works for accounts owned by the legacy Token program as well as the
+/// Token-2022 program.
+fn has_third_party_authority(
+    token_account: &AccountInfo,
+) -> Result<bool, TokenTransferError> {
+    let data = token_account
+        .try_borrow_data()
+        .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+    let account =
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+            .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+    Ok(account.base.delegate.is_some() ||
+        account.base.close_authority.is_some())
+}
+
+/// Returns token account which should be credited with received tokens.
+///
+/// Normally that’s receiver’s token account.  However, if it has a delegate
+/// or close authority set, the result depends on the `policy`.  With
+/// [`ReceiverAccountPolicy::Escrow`] this is the receiver’s pending claim
+/// account which must be passed in the `accounts`.
+fn get_receiving_account<'a>(
+    accounts: &TransferAccounts<'a>,
+    policy: ReceiverAccountPolicy,
+) -> Result<AccountInfo<'a>, TokenTransferError> {
+    let token_account = accounts
+        .token_account
+        .as_ref()
+        .ok_or(TokenTransferError::ParseAccountFailure)?;
+    if policy == ReceiverAccountPolicy::Allow ||
+        !has_third_party_authority(token_account)?
+    {
+        return Ok(token_account.clone());
+    }
+    match policy {
+        ReceiverAccountPolicy::Allow | ReceiverAccountPolicy::Warn => {
+            msg!(
+                "Warning: token account {} has delegate or close authority set",
+                token_account.key
+            );
+            Ok(token_account.clone())
+        }
+        ReceiverAccountPolicy::Reject => Err(TokenTransferError::Other(
+            "Receiver token account has delegate or close authority set".into(),
+        )),
+        ReceiverAccountPolicy::Escrow => {
+            let receiver = accounts
+                .receiver
+                .as_ref()
+                .ok_or(TokenTransferError::ParseAccountFailure)?;
+            let token_mint = accounts
+                .token_mint
+                .as_ref()
+                .ok_or(TokenTransferError::ParseAccountFailure)?;
+            let token_program = accounts
+                .token_program
+                .as_ref()
+                .ok_or(TokenTransferError::ParseAccountFailure)?;
+            let authority = get_pending_claim_authority(receiver.key).0;
+            let pending_claim_account = get_token_account(
+                &authority,
+                token_mint.key,
+                token_program.key,
+            );
+            msg!(
+                "Token account {} has delegate or close authority set; \
+                 crediting pending claim account {}",
+                token_account.key,
+                pending_claim_account
+            );
+            accounts
+                .pending_claim_account
+                .as_ref()
+                .filter(|account| pending_claim_account == *account.key)
+                .cloned()
+                .ok_or(TokenTransferError::ParseAccountFailure)
+        }
+    }
+}
+
+/// Returns number of decimals of the mint and the Token-2022 transfer fee
+/// which would be charged when transferring `amount` of the token.
+///
+/// Works for mints owned by the legacy Token program as well as the Token-2022
+/// program.  The fee is zero if the mint has no transfer fee extension.
+fn get_mint_info(
+    token_mint: &AccountInfo,
+    amount: u64,
+) -> Result<(u8, u64), TokenTransferError> {
+    let data = token_mint
+        .try_borrow_data()
+        .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+    let mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+            .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+    let fee = match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => {
+            let epoch = crate::mocks::clock()
+                .map_err(|err| TokenTransferError::Other(err.to_string()))?
+                .epoch;
+            config.calculate_epoch_fee(epoch, amount).ok_or(
+                TokenTransferError::InvalidAmount(
+                    uint::FromDecStrErr::InvalidLength,
+                ),
+            )?
+        }
+        Err(_) => 0,
+    };
+    Ok((mint.base.decimals, fee))
+}
+
+/// Returns supply of the token which percentage rate limit quotas are
+/// calculated against.
+///
+/// See [`crate::storage::RateLimitQuota::SupplyBps`].
+fn get_token_supply(
+    accounts: &TransferAccounts,
+    minted: bool,
+) -> Result<u64, TokenTransferError> {
+    if minted {
+        let token_mint = accounts
+            .token_mint
+            .as_ref()
+            .ok_or(TokenTransferError::ParseAccountFailure)?;
+        let data = token_mint
+            .try_borrow_data()
+            .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+            .map(|mint| mint.base.supply)
+            .map_err(|_| TokenTransferError::ParseAccountFailure)
+    } else {
+        let escrow_account = accounts
+            .escrow_account
+            .as_ref()
+            .ok_or(TokenTransferError::ParseAccountFailure)?;
+        let data = escrow_account
+            .try_borrow_data()
+            .map_err(|_| TokenTransferError::ParseAccountFailure)?;
+        // Escrow account of wrapped SOL is closed after each transfer so it
+        // may not hold a token account.
+        Ok(StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+            .map_or(0, |account| account.base.amount))
+    }
+}
+
 /// Direction of an escrow operation.
 enum EscrowOp {
     Escrow,
@@ -74,6 +242,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
         coin: &PrefixedCoin,
         _memo: &Memo,
     ) -> Result<(), TokenTransferError> {
+        self.consume_rate_limit(TransferDirection::Outbound, coin, false)?;
         self.escrow_coins_execute_impl(EscrowOp::Escrow, coin)
     }
 
@@ -84,6 +253,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
         _channel_id: &ChannelId,
         coin: &PrefixedCoin,
     ) -> Result<(), TokenTransferError> {
+        self.consume_rate_limit(TransferDirection::Inbound, coin, false)?;
         self.escrow_coins_execute_impl(EscrowOp::Unescrow, coin)
     }
 
@@ -98,6 +268,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             amt.denom.trace_path,
             amt.denom.base_denom
         );
+        self.consume_rate_limit(TransferDirection::Inbound, amt, true)?;
         let store = self.borrow();
 
         let private_storage = &store.private;
@@ -110,16 +281,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             .get(&hashed_full_denom)
             .ok_or(TokenTransferError::InvalidToken)?;
 
-        let converted_amount = convert_decimals(
-            &amt.amount,
-            asset.original_decimals,
-            asset.effective_decimals_on_sol,
-        )
-        .ok_or(TokenTransferError::InvalidAmount(
-            uint::FromDecStrErr::InvalidLength,
-        ))?;
-
-        let amount_to_mint = check_amount_overflow(converted_amount)?;
+        let amount_to_mint = to_local_amount(asset, &amt.amount, false)?;
 
         msg!(
             "Original amount {} converted amount {} original decimals {} \
@@ -133,10 +295,10 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
         let (_mint_auth_key, mint_auth_bump) =
             Pubkey::find_program_address(&[MINT_ESCROW_SEED], &crate::ID);
         let accounts = &store.accounts;
-        let receiver = accounts
-            .token_account
-            .clone()
-            .ok_or(TokenTransferError::ParseAccountFailure)?;
+        let receiver = get_receiving_account(
+            accounts,
+            private_storage.receiver_account_policy,
+        )?;
         let token_program = accounts
             .token_program
             .clone()
@@ -166,7 +328,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             seeds, //signer PDA
         );
 
-        anchor_spl::token::mint_to(cpi_ctx, amount_to_mint).unwrap();
+        token_interface::mint_to(cpi_ctx, amount_to_mint).unwrap();
         Ok(())
     }
 
@@ -182,6 +344,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             amt.denom.trace_path,
             amt.denom.base_denom
         );
+        self.consume_rate_limit(TransferDirection::Outbound, amt, true)?;
         let store = self.borrow();
         let private_storage = &store.private;
 
@@ -193,15 +356,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             .get(&hashed_full_denom)
             .ok_or(TokenTransferError::InvalidToken)?;
 
-        let converted_amount = convert_decimals(
-            &amt.amount,
-            asset.original_decimals,
-            asset.effective_decimals_on_sol,
-        )
-        .ok_or(TokenTransferError::InvalidAmount(
-            uint::FromDecStrErr::InvalidLength,
-        ))?;
-        let amount_to_burn = check_amount_overflow(converted_amount)?;
+        let amount_to_burn = to_local_amount(asset, &amt.amount, true)?;
         let (_mint_authority_key, bump) =
             Pubkey::find_program_address(&[MINT_ESCROW_SEED], &crate::ID);
         let accounts = &store.accounts;
@@ -235,7 +390,7 @@ impl TokenTransferExecutionContext for IbcStorage<'_, '_> {
             seeds, //signer PDA
         );
 
-        anchor_spl::token::burn(cpi_ctx, amount_to_burn).unwrap();
+        token_interface::burn(cpi_ctx, amount_to_burn).unwrap();
         Ok(())
     }
 }
@@ -248,13 +403,11 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
     }
 
     fn can_send_coins(&self) -> Result<(), TokenTransferError> {
-        // TODO: check if this is correct
-        Ok(())
+        self.check_halt_flag(crate::storage::HaltFlag::Outbound)
     }
 
     fn can_receive_coins(&self) -> Result<(), TokenTransferError> {
-        // TODO: check if this is correct
-        Ok(())
+        self.check_halt_flag(crate::storage::HaltFlag::Inbound)
     }
 
     fn escrow_coins_validate(
@@ -302,6 +455,7 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
            - token mint ( with seeds as `mint` as prefixed constant, portId, channelId and denom )
            - mint authority
         */
+        self.check_min_transfer_amount(coin)?;
         let token_mint = get_token_mint(&coin.denom)?;
 
         let store = self.borrow();
@@ -312,6 +466,10 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
         {
             return Err(TokenTransferError::ParseAccountFailure);
         }
+        let token_program = accounts
+            .token_program
+            .as_ref()
+            .ok_or(TokenTransferError::ParseAccountFailure)?;
         let token_account = accounts
             .token_account
             .as_ref()
@@ -325,7 +483,8 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
             .as_ref()
             .ok_or(TokenTransferError::ParseAccountFailure)?;
 
-        let receiver_token_account = get_token_account(&account.0, &token_mint);
+        let receiver_token_account =
+            get_token_account(&account.0, &token_mint, token_program.key);
 
         if account.0 != *receiver.key {
             msg!("Token account not found {} {:?}", account, receiver.key);
@@ -347,7 +506,8 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
             );
             return Err(TokenTransferError::ParseAccountFailure);
         }
-        Ok(())
+        get_receiving_account(accounts, store.private.receiver_account_policy)
+            .map(|_| ())
     }
 
     fn burn_coins_validate(
@@ -366,6 +526,7 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
            The token mint should be a PDA with seeds as ``
         */
         msg!("This is coin while burning {:?}", coin);
+        self.check_min_transfer_amount(coin)?;
         let token_mint = get_token_mint(&coin.denom)?;
         let store = self.borrow();
         let accounts = &store.accounts;
@@ -375,6 +536,10 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
         {
             return Err(TokenTransferError::ParseAccountFailure);
         }
+        let token_program = accounts
+            .token_program
+            .as_ref()
+            .ok_or(TokenTransferError::ParseAccountFailure)?;
         let token_account = accounts
             .token_account
             .as_ref()
@@ -388,7 +553,8 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
             .as_ref()
             .ok_or(TokenTransferError::ParseAccountFailure)?;
 
-        let sender_token_account = get_token_account(&account.0, &token_mint);
+        let sender_token_account =
+            get_token_account(&account.0, &token_mint, token_program.key);
 
         if account.0 != *sender.key {
             msg!("Token account not found {} {:?}", account, sender.key);
@@ -415,6 +581,105 @@ impl TokenTransferValidationContext for IbcStorage<'_, '_> {
 }
 
 impl IbcStorage<'_, '_> {
+    /// Checks that given halt flag isn’t set.
+    fn check_halt_flag(
+        &self,
+        flag: crate::storage::HaltFlag,
+    ) -> Result<(), TokenTransferError> {
+        self.borrow()
+            .private
+            .halt_flags
+            .check(flag)
+            .map_err(|err| TokenTransferError::Other(err.to_string()))
+    }
+
+    /// Checks that transferred amount isn’t below the minimum configured for
+    /// the denom via `set_min_transfer_amount`.
+    fn check_min_transfer_amount(
+        &self,
+        coin: &PrefixedCoin,
+    ) -> Result<(), TokenTransferError> {
+        check_min_amount(&self.borrow().private.min_transfer_amounts, coin)
+    }
+
+    /// Calls `f` with minimum transfer amounts disabled.
+    ///
+    /// Used when validating refunds of failed and timed out transfers.  The
+    /// minimum was enforced when the tokens were sent and changing it since
+    /// mustn’t make the tokens unrecoverable.
+    pub(crate) fn without_min_transfer_amounts<R>(
+        &self,
+        f: impl FnOnce(&Self) -> R,
+    ) -> R {
+        let amounts = core::mem::take(
+            &mut self.borrow_mut().private.min_transfer_amounts,
+        );
+        let result = f(self);
+        self.borrow_mut().private.min_transfer_amounts = amounts;
+        result
+    }
+
+    /// Records transfer of given coin against token’s rate limit configured
+    /// via `set_rate_limit`.
+    ///
+    /// `minted` indicates whether the token is minted by the bridge rather
+    /// than escrowed.  Fails if the transfer would exceed the quota.
+    fn consume_rate_limit(
+        &mut self,
+        direction: TransferDirection,
+        coin: &PrefixedCoin,
+        minted: bool,
+    ) -> Result<(), TokenTransferError> {
+        let hashed_full_denom =
+            CryptoHash::digest(coin.denom.to_string().as_bytes());
+        let mut store = self.borrow_mut();
+        let store = &mut *store;
+        let limit = match store.private.rate_limits.get_mut(&hashed_full_denom)
+        {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let amount = if minted {
+            let asset = store
+                .private
+                .assets
+                .get(&hashed_full_denom)
+                .ok_or(TokenTransferError::InvalidToken)?;
+            to_local_amount(asset, &coin.amount, false)?
+        } else {
+            check_amount_overflow(coin.amount)?
+        };
+        let accounts = &store.accounts;
+        let supply = || Ok(get_token_supply(accounts, minted)?);
+        store
+            .chain
+            .head()
+            .map_err(crate::error::Error::from)
+            .and_then(|head| {
+                let height = head.block_height.into();
+                limit.consume(direction, height, amount, supply)
+            })
+            .map_err(|err| {
+                msg!("Transfer of {} {} rejected: {}", amount, coin.denom, err);
+                TokenTransferError::Other(err.to_string())
+            })
+    }
+
+    /// Calls `f` with rate limits disabled.
+    ///
+    /// Refunds of failed and timed out transfers return tokens which have
+    /// already been counted as sent so they mustn’t be rejected.
+    pub(crate) fn without_rate_limits<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let limits =
+            core::mem::take(&mut self.borrow_mut().private.rate_limits);
+        let result = f(self);
+        self.borrow_mut().private.rate_limits = limits;
+        result
+    }
+
     fn escrow_coins_validate_impl(
         &self,
         op: EscrowOp,
@@ -441,6 +706,7 @@ impl IbcStorage<'_, '_> {
            - to account should match escrow
            - from account should match token account
         */
+        self.check_min_transfer_amount(coin)?;
         let store = self.borrow();
         let accounts = &store.accounts;
         if accounts.token_program.is_none() || accounts.token_mint.is_none() {
@@ -476,8 +742,19 @@ impl IbcStorage<'_, '_> {
                 .filter(|_| accounts.mint_authority.is_some()),
         }
         .filter(|acc| account.0 == *acc.key)
-        .map(|_| ())
-        .ok_or(TokenTransferError::ParseAccountFailure)
+        .ok_or(TokenTransferError::ParseAccountFailure)?;
+
+        // Wrapped SOL is paid out to the receiver’s wallet directly so receiver
+        // token account doesn’t matter.
+        if matches!(op, EscrowOp::Unescrow) &&
+            coin.denom.base_denom.as_str() != crate::WSOL_ADDRESS
+        {
+            get_receiving_account(
+                accounts,
+                store.private.receiver_account_policy,
+            )?;
+        }
+        Ok(())
     }
 
     fn escrow_coins_execute_impl(
@@ -496,6 +773,10 @@ impl IbcStorage<'_, '_> {
             .token_program
             .as_ref()
             .ok_or(TokenTransferError::ParseAccountFailure)?;
+        let token_mint = accounts
+            .token_mint
+            .as_ref()
+            .ok_or(TokenTransferError::ParseAccountFailure)?;
 
         let token_account = accounts
             .token_account
@@ -510,6 +791,8 @@ impl IbcStorage<'_, '_> {
         let escrow_account_rent =
             rent.minimum_balance(escrow_account.data_len());
 
+        let is_wsol = coin.denom.base_denom.as_str() == crate::WSOL_ADDRESS;
+        let receiving_account;
         let (sender, receiver, authority) = match op {
             EscrowOp::Escrow => {
                 let auth = accounts
@@ -523,7 +806,15 @@ impl IbcStorage<'_, '_> {
                     .mint_authority
                     .as_ref()
                     .ok_or(TokenTransferError::ParseAccountFailure)?;
-                (escrow_account, token_account, auth)
+                receiving_account = if is_wsol {
+                    token_account.clone()
+                } else {
+                    get_receiving_account(
+                        accounts,
+                        store.private.receiver_account_policy,
+                    )?
+                };
+                (escrow_account, &receiving_account, auth)
             }
         };
 
@@ -534,9 +825,7 @@ impl IbcStorage<'_, '_> {
         // Close the wsol account so that the receiver gets the amount in native SOL
         // instead of wrapped SOL which is unusable if the wallet doesnt have any
         // SOL to pay for the fees.
-        if matches!(op, EscrowOp::Unescrow) &&
-            coin.denom.base_denom.as_str() == crate::WSOL_ADDRESS
-        {
+        if matches!(op, EscrowOp::Unescrow) && is_wsol {
             let receiver = accounts
                 .receiver
                 .as_ref()
@@ -550,9 +839,22 @@ impl IbcStorage<'_, '_> {
             return Ok(());
         }
 
+        // Token-2022 transfer fee is withheld from the amount credited to the
+        // destination account.  When escrowing this would leave the escrow
+        // holding less than what the packet claims so such transfers are
+        // rejected.  When unescrowing, the fee is borne by the receiver.
+        let (decimals, fee) = get_mint_info(token_mint, amount)?;
+        if matches!(op, EscrowOp::Escrow) && fee != 0 {
+            msg!("Token transfer fee of {} is not supported", fee);
+            return Err(TokenTransferError::Other(
+                "Tokens with transfer fee cannot be escrowed".into(),
+            ));
+        }
+
         // Below is the actual instruction that we are going to send to the Token program.
-        let transfer_instruction = Transfer {
+        let transfer_instruction = TransferChecked {
             from: sender.clone(),
+            mint: token_mint.clone(),
             to: receiver.clone(),
             authority: authority.clone(),
         };
@@ -561,14 +863,12 @@ impl IbcStorage<'_, '_> {
             transfer_instruction,
             seeds, //signer PDA
         );
-        anchor_spl::token::transfer(cpi_ctx, amount).unwrap();
+        token_interface::transfer_checked(cpi_ctx, amount, decimals).unwrap();
 
         // Closing the wsol account after transferring the amount to the escrow
         // so that the escrow account holds the wsol deposits in native SOL which
         // can be transferred to the receiver instead of sending wrapped sol.
-        if matches!(op, EscrowOp::Escrow) &&
-            coin.denom.base_denom.as_str() == crate::WSOL_ADDRESS
-        {
+        if matches!(op, EscrowOp::Escrow) && is_wsol {
             let mint_authority = accounts
                 .mint_authority
                 .as_ref()
@@ -593,7 +893,7 @@ impl IbcStorage<'_, '_> {
                 sender.key,
                 escrow_account_rent
             );
-            anchor_spl::token::close_account(cpi_ctx).unwrap();
+            token_interface::close_account(cpi_ctx).unwrap();
             // Closing the account transfers all the lamports to the
             // destination account including the initial rent paid
             // for creation of the account by the sender. So we need
@@ -612,12 +912,75 @@ impl IbcStorage<'_, '_> {
 /// Solana supports transfers whose amount fits `u64`.  This function checks
 /// whether the token transfer amount overflows that type. If it does it returns
 /// an error or otherwise returns the amount downcast to `u64`.
-fn check_amount_overflow(amount: Amount) -> Result<u64, TokenTransferError> {
+pub(crate) fn check_amount_overflow(
+    amount: Amount,
+) -> Result<u64, TokenTransferError> {
     u64::try_from(primitive_types::U256::from(amount)).map_err(|_| {
         TokenTransferError::InvalidAmount(uint::FromDecStrErr::InvalidLength)
     })
 }
 
+/// Checks that transferred amount isn’t below the minimum configured in
+/// `amounts` for the coin’s denom.
+///
+/// `amounts` is keyed by hashes of full denoms and denoms without an entry
+/// have no minimum.
+fn check_min_amount(
+    amounts: &crate::storage::map::Map<CryptoHash, u64>,
+    coin: &PrefixedCoin,
+) -> Result<(), TokenTransferError> {
+    let hashed_full_denom =
+        CryptoHash::digest(coin.denom.to_string().as_bytes());
+    match amounts.get(&hashed_full_denom) {
+        Some(&min) if U256::from(coin.amount) < U256::from(min) => {
+            Err(TokenTransferError::Other(format!(
+                "Transfer amount {} is below minimum of {min}",
+                coin.amount
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Converts amount of a wrapped token as expressed in a packet (i.e. using
+/// token’s original decimals) into amount of the token on Solana.
+///
+/// If the token has fewer decimals on Solana than originally, the amount is
+/// truncated.  That’s fine when minting (the dust stays in counterparty’s
+/// escrow) but when burning it would let the sender burn less than what the
+/// counterparty releases.  If `exact` is true, amounts which cannot be
+/// represented on Solana without loss of precision are therefore rejected.
+pub(crate) fn to_local_amount(
+    asset: &crate::storage::Asset,
+    amount: &Amount,
+    exact: bool,
+) -> Result<u64, TokenTransferError> {
+    let invalid_amount = || {
+        TokenTransferError::InvalidAmount(uint::FromDecStrErr::InvalidLength)
+    };
+    let converted = convert_decimals(
+        amount,
+        asset.original_decimals,
+        asset.effective_decimals_on_sol,
+    )
+    .ok_or_else(invalid_amount)?;
+    if exact &&
+        convert_decimals(
+            &converted,
+            asset.effective_decimals_on_sol,
+            asset.original_decimals,
+        ) != Some(*amount)
+    {
+        msg!(
+            "Amount {} cannot be represented with {} decimals",
+            amount,
+            asset.effective_decimals_on_sol
+        );
+        return Err(invalid_amount());
+    }
+    check_amount_overflow(converted)
+}
+
 fn convert_decimals(
     amount: &Amount,
     original_decimals: u8,
@@ -644,9 +1007,14 @@ fn convert_decimals(
 mod tests {
     use std::str::FromStr;
 
-    use ibc::apps::transfer::types::Amount;
+    use ibc::apps::transfer::types::{Amount, PrefixedCoin, PrefixedDenom};
+    use lib::hash::CryptoHash;
+    use primitive_types::U256;
 
-    use crate::transfer::impls::{check_amount_overflow, convert_decimals};
+    use crate::storage::map::Map;
+    use crate::transfer::impls::{
+        check_amount_overflow, check_min_amount, convert_decimals,
+    };
 
     fn ok(src: &str, input_decimals: u8, output_decimals: u8, dst: &str) {
         let src = src.chars().filter(|chr| *chr != '_').collect::<String>();
@@ -692,4 +1060,71 @@ mod tests {
             "1_000_000_000_000_000_000_000_000_000_000_0",
         );
     }
+
+    const NATIVE: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const WRAPPED: &str = "transfer/channel-0/uatom";
+
+    fn coin(denom: &str, amount: u64) -> PrefixedCoin {
+        PrefixedCoin {
+            denom: PrefixedDenom::from_str(denom).unwrap(),
+            amount: Amount::from(U256::from(amount)),
+        }
+    }
+
+    fn min_amounts(entries: &[(&str, u64)]) -> Map<CryptoHash, u64> {
+        let entries = entries.iter().map(|&(denom, min)| {
+            let denom = PrefixedDenom::from_str(denom).unwrap().to_string();
+            (CryptoHash::digest(denom.as_bytes()), min)
+        });
+        Map::from(entries.collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_min_amount_send() {
+        let amounts = min_amounts(&[(NATIVE, 100), (WRAPPED, 10)]);
+        // Escrowing native token.
+        assert!(check_min_amount(&amounts, &coin(NATIVE, 99)).is_err());
+        assert!(check_min_amount(&amounts, &coin(NATIVE, 100)).is_ok());
+        assert!(check_min_amount(&amounts, &coin(NATIVE, 101)).is_ok());
+        // Burning wrapped token.
+        assert!(check_min_amount(&amounts, &coin(WRAPPED, 9)).is_err());
+        assert!(check_min_amount(&amounts, &coin(WRAPPED, 10)).is_ok());
+        // Tokens without a minimum are unrestricted.
+        let amounts = min_amounts(&[(NATIVE, 100)]);
+        assert!(check_min_amount(&amounts, &coin(WRAPPED, 1)).is_ok());
+    }
+
+    #[test]
+    fn test_min_amount_receive() {
+        let amounts = min_amounts(&[(NATIVE, 100), (WRAPPED, 10)]);
+        // Minting wrapped token.
+        assert!(check_min_amount(&amounts, &coin(WRAPPED, 9)).is_err());
+        assert!(check_min_amount(&amounts, &coin(WRAPPED, 10)).is_ok());
+        // Unescrowing native token returning home.  The counterparty’s
+        // prefix is stripped by then so the minimum for the base denom
+        // applies.
+        assert!(check_min_amount(&amounts, &coin(NATIVE, 99)).is_err());
+        assert!(check_min_amount(&amounts, &coin(NATIVE, 100)).is_ok());
+        let prefixed = format!("transfer/channel-1/{NATIVE}");
+        assert!(check_min_amount(&amounts, &coin(&prefixed, 1)).is_ok());
+    }
+
+    #[test]
+    fn test_min_amount_refund() {
+        // Tokens were sent when minimum was lower and it has been raised
+        // since.  Refunds are validated with minimums taken out of the
+        // storage (see `IbcStorage::without_min_transfer_amounts`) so
+        // tokens can still be recovered.
+        let mut amounts = min_amounts(&[(NATIVE, 100), (WRAPPED, 100)]);
+        let native = coin(NATIVE, 50);
+        let wrapped = coin(WRAPPED, 50);
+        assert!(check_min_amount(&amounts, &native).is_err());
+        assert!(check_min_amount(&amounts, &wrapped).is_err());
+
+        let taken = core::mem::take(&mut amounts);
+        assert!(check_min_amount(&amounts, &native).is_ok());
+        assert!(check_min_amount(&amounts, &wrapped).is_ok());
+        amounts = taken;
+        assert!(check_min_amount(&amounts, &native).is_err());
+    }
 }
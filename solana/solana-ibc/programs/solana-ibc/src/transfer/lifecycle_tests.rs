@@ -0,0 +1,326 @@
+//! Randomised tests of token accounting across the packet lifecycle.
+//!
+//! The tests model a Solana chain talking to a single counterparty.  Packets
+//! are sent in both directions and then, in random order, received, timed out
+//! or acknowledged with success or error.  Token movements on the Solana side
+//! are calculated the same way the program does it while the counterparty is
+//! a simple reference implementation of ICS-20.  After each step we check
+//! that tokens are neither created nor destroyed, i.e. that escrowed and
+//! minted amounts match what has been sent minus what has been refunded.
+
+use primitive_types::U256;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::impls::{check_amount_overflow, to_local_amount};
+use crate::ibc::apps::transfer::types::Amount;
+use crate::storage::Asset;
+
+/// Token taking part in the transfers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Denom {
+    /// Token native to Solana which is escrowed when sent out.
+    Native,
+    /// Token native to the counterparty which is minted on Solana.
+    Wrapped,
+}
+
+/// Direction in which a packet travels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    /// From Solana to the counterparty.
+    Out,
+    /// From the counterparty to Solana.
+    In,
+}
+
+/// Packet in flight.
+#[derive(Clone, Debug)]
+struct Packet {
+    direction: Direction,
+    denom: Denom,
+    /// Amount as encoded in the packet.  For wrapped token this uses token’s
+    /// original decimals.
+    amount: u128,
+}
+
+/// State of both chains.
+#[derive(Debug)]
+struct World {
+    asset: Asset,
+
+    /// Native tokens held by the user on Solana.
+    native_balance: u64,
+    /// Native tokens held in the escrow account on Solana.
+    native_escrow: u64,
+    /// Vouchers of the native token minted on the counterparty.
+    native_vouchers: u128,
+
+    /// Supply of the wrapped token on Solana (all held by the user).
+    wrapped_supply: u64,
+    /// Wrapped token held in the counterparty’s escrow.
+    wrapped_escrow: u128,
+
+    in_flight: Vec<Packet>,
+}
+
+impl World {
+    fn new(asset: Asset) -> Self {
+        Self {
+            asset,
+            native_balance: 1_000_000_000_000,
+            native_escrow: 0,
+            native_vouchers: 0,
+            wrapped_supply: 0,
+            wrapped_escrow: 0,
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Converts amount of wrapped token on Solana into packet amount.
+    fn to_packet_amount(&self, amount: u64) -> u128 {
+        let shift = |decimals: u8| U256::exp10(decimals.into());
+        let amount = U256::from(amount);
+        let amount = match self
+            .asset
+            .original_decimals
+            .cmp(&self.asset.effective_decimals_on_sol)
+        {
+            core::cmp::Ordering::Greater => {
+                amount *
+                    shift(
+                        self.asset.original_decimals -
+                            self.asset.effective_decimals_on_sol,
+                    )
+            }
+            core::cmp::Ordering::Equal => amount,
+            core::cmp::Ordering::Less => {
+                amount /
+                    shift(
+                        self.asset.effective_decimals_on_sol -
+                            self.asset.original_decimals,
+                    )
+            }
+        };
+        amount.as_u128()
+    }
+
+    /// Sends native token from Solana; escrows it.
+    fn send_native(&mut self, amount: u64) {
+        let amount =
+            check_amount_overflow(Amount::from(U256::from(amount))).unwrap();
+        if amount > self.native_balance {
+            return;
+        }
+        self.native_balance -= amount;
+        self.native_escrow += amount;
+        self.push(Direction::Out, Denom::Native, amount.into());
+    }
+
+    /// Sends wrapped token from Solana; burns it.
+    fn send_wrapped(&mut self, amount: u128) {
+        let burn =
+            match to_local_amount(&self.asset, &make_amount(amount), true) {
+                Ok(burn) => burn,
+                Err(_) => return,
+            };
+        if burn == 0 || burn > self.wrapped_supply {
+            return;
+        }
+        self.wrapped_supply -= burn;
+        self.push(Direction::Out, Denom::Wrapped, amount);
+    }
+
+    /// Counterparty sends back vouchers of the native token; burns them.
+    fn return_native(&mut self, amount: u128) {
+        if amount > self.native_vouchers {
+            return;
+        }
+        self.native_vouchers -= amount;
+        self.push(Direction::In, Denom::Native, amount);
+    }
+
+    /// Counterparty sends its token to Solana; escrows it.
+    fn receive_wrapped(&mut self, amount: u128) {
+        self.wrapped_escrow += amount;
+        self.push(Direction::In, Denom::Wrapped, amount);
+    }
+
+    fn push(&mut self, direction: Direction, denom: Denom, amount: u128) {
+        self.in_flight.push(Packet { direction, denom, amount });
+    }
+
+    /// Delivers packet to its destination.  Returns whether the receiving
+    /// chain acknowledged it with success.
+    fn recv(&mut self, packet: &Packet) -> bool {
+        match (packet.direction, packet.denom) {
+            (Direction::Out, Denom::Native) => {
+                self.native_vouchers += packet.amount;
+                true
+            }
+            (Direction::Out, Denom::Wrapped) => {
+                assert!(self.wrapped_escrow >= packet.amount, "{self:?}");
+                self.wrapped_escrow -= packet.amount;
+                true
+            }
+            (Direction::In, Denom::Native) => {
+                let amount =
+                    check_amount_overflow(make_amount(packet.amount)).unwrap();
+                assert!(self.native_escrow >= amount, "{self:?}");
+                self.native_escrow -= amount;
+                self.native_balance += amount;
+                true
+            }
+            (Direction::In, Denom::Wrapped) => {
+                let amount = make_amount(packet.amount);
+                match to_local_amount(&self.asset, &amount, false) {
+                    Ok(mint) => match self.wrapped_supply.checked_add(mint) {
+                        Some(supply) => {
+                            self.wrapped_supply = supply;
+                            true
+                        }
+                        None => false,
+                    },
+                    Err(_) => false,
+                }
+            }
+        }
+    }
+
+    /// Refunds packet on its source chain after a timeout or an error
+    /// acknowledgement.
+    fn refund(&mut self, packet: &Packet) {
+        match (packet.direction, packet.denom) {
+            (Direction::Out, Denom::Native) => {
+                let amount =
+                    check_amount_overflow(make_amount(packet.amount)).unwrap();
+                self.native_escrow -= amount;
+                self.native_balance += amount;
+            }
+            (Direction::Out, Denom::Wrapped) => {
+                let amount = make_amount(packet.amount);
+                let mint =
+                    to_local_amount(&self.asset, &amount, false).unwrap();
+                self.wrapped_supply += mint;
+            }
+            (Direction::In, Denom::Native) => {
+                self.native_vouchers += packet.amount;
+            }
+            (Direction::In, Denom::Wrapped) => {
+                self.wrapped_escrow -= packet.amount;
+            }
+        }
+    }
+
+    /// Resolves a random packet in flight.
+    fn resolve(&mut self, rng: &mut impl Rng) {
+        if self.in_flight.is_empty() {
+            return;
+        }
+        let idx = rng.gen_range(0..self.in_flight.len());
+        let packet = self.in_flight.swap_remove(idx);
+        // Timeout or delivery with either success or error acknowledgement.
+        match rng.gen_range(0..3) {
+            0 => self.refund(&packet),
+            1 => {
+                if !self.recv(&packet) {
+                    self.refund(&packet);
+                }
+            }
+            _ => {
+                // Error acknowledgement means the receiving chain didn’t
+                // change its state.
+                self.refund(&packet);
+            }
+        }
+    }
+
+    fn in_flight_amount(&self, direction: Direction, denom: Denom) -> u128 {
+        self.in_flight
+            .iter()
+            .filter(|packet| {
+                packet.direction == direction && packet.denom == denom
+            })
+            .map(|packet| packet.amount)
+            .sum()
+    }
+
+    fn check_invariants(&self) {
+        // Native token: everything in the escrow is either represented by
+        // vouchers on the counterparty or in flight.
+        assert_eq!(1_000_000_000_000, self.native_balance + self.native_escrow);
+        assert_eq!(
+            u128::from(self.native_escrow),
+            self.native_vouchers +
+                self.in_flight_amount(Direction::Out, Denom::Native) +
+                self.in_flight_amount(Direction::In, Denom::Native),
+            "{self:?}"
+        );
+
+        // Wrapped token: counterparty’s escrow backs all the tokens on Solana
+        // and in flight.  When Solana uses fewer decimals, dust of received
+        // amounts stays in the escrow so there may be more in it.
+        let backed = self.to_packet_amount(self.wrapped_supply) +
+            self.in_flight_amount(Direction::Out, Denom::Wrapped) +
+            self.in_flight_amount(Direction::In, Denom::Wrapped);
+        if self.asset.original_decimals > self.asset.effective_decimals_on_sol {
+            assert!(self.wrapped_escrow >= backed, "{self:?}");
+        } else {
+            assert_eq!(self.wrapped_escrow, backed, "{self:?}");
+        }
+    }
+}
+
+fn make_amount(amount: u128) -> Amount { Amount::from(U256::from(amount)) }
+
+/// Generates a random amount with random number of digits such that small
+/// amounts (which are most likely to expose rounding issues) are common.
+fn random_amount(rng: &mut impl Rng, max_digits: u32) -> u128 {
+    let digits = rng.gen_range(0..=max_digits);
+    rng.gen_range(1..=10u128.pow(digits))
+}
+
+#[test]
+fn stress_packet_lifecycle() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([1; 32]);
+    for _ in 0..lib::test_utils::get_iteration_count(100) {
+        let asset = Asset {
+            original_decimals: rng.gen_range(0..=18),
+            effective_decimals_on_sol: rng.gen_range(0..=9),
+        };
+        // Limit wrapped token amounts such that its supply on Solana never
+        // overflows.
+        let digits = u32::from(asset.original_decimals) + 3;
+        let mut world = World::new(asset);
+        for _ in 0..200 {
+            let action = [0, 1, 2, 3, 4, 4, 4].choose(&mut rng).unwrap();
+            match *action {
+                0 => world.send_native(random_amount(&mut rng, 12) as u64),
+                1 => world.send_wrapped(random_amount(&mut rng, digits)),
+                2 => world.return_native(random_amount(&mut rng, 12)),
+                3 => world.receive_wrapped(random_amount(&mut rng, digits)),
+                _ => world.resolve(&mut rng),
+            }
+            world.check_invariants();
+        }
+        while !world.in_flight.is_empty() {
+            world.resolve(&mut rng);
+            world.check_invariants();
+        }
+    }
+}
+
+#[test]
+fn test_burn_requires_exact_amount() {
+    let asset = Asset { original_decimals: 9, effective_decimals_on_sol: 6 };
+    let get = |amount: u128, exact: bool| {
+        to_local_amount(&asset, &make_amount(amount), exact).ok()
+    };
+    assert_eq!(Some(0), get(999, false));
+    assert_eq!(None, get(999, true));
+    assert_eq!(Some(1), get(1999, false));
+    assert_eq!(None, get(1999, true));
+    assert_eq!(Some(2), get(2000, true));
+}
@@ -1,5 +1,5 @@
 use std::result::Result;
-use std::str::{self, FromStr};
+use std::str;
 
 use anchor_lang::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -8,10 +8,13 @@ use spl_token::solana_program::program::invoke;
 
 use crate::ibc::apps::transfer::types::packet::PacketData;
 use crate::ibc::apps::transfer::types::proto::transfer::v2::FungibleTokenPacketData;
-use crate::storage::IbcStorage;
+use crate::storage::{HookFailure, HookStats, IbcStorage, PrivateStorage};
 use crate::{ibc, BRIDGE_ESCROW_PROGRAM_ID};
 
+mod hooks;
 pub(crate) mod impls;
+#[cfg(test)]
+mod lifecycle_tests;
 
 impl ibc::Module for IbcStorage<'_, '_> {
     fn on_chan_open_init_validate(
@@ -130,6 +133,22 @@ impl ibc::Module for IbcStorage<'_, '_> {
             "Received packet: {:?}",
             str::from_utf8(packet.data.as_ref()).expect("Invalid packet data")
         );
+        let paused = self
+            .borrow()
+            .private
+            .channel_pause(&packet.port_id_on_b, &packet.chan_id_on_b)
+            .receive;
+        if paused {
+            // Acknowledge with an error so the tokens are refunded on the
+            // counterparty chain.
+            let status = ibc::TokenTransferError::Other(format!(
+                "Channel {} is paused",
+                packet.chan_id_on_b
+            ))
+            .into();
+            let ack = ibc::AcknowledgementStatus::error(status).into();
+            return (ibc::ModuleExtras::empty(), ack);
+        }
         let ft_packet_data =
             serde_json::from_slice::<FtPacketData>(&packet.data)
                 .expect("Invalid packet data");
@@ -144,6 +163,20 @@ impl ibc::Module for IbcStorage<'_, '_> {
             .into_bytes(),
             ..packet.clone()
         };
+
+        // Memo is checked before the tokens are credited.  Rejecting it
+        // afterwards would refund the sender on the counterparty chain while
+        // the tokens remain credited here.
+        let memo_hooks = match hooks::parse_memo_hooks(&maybe_ft_packet) {
+            Ok(memo_hooks) => memo_hooks,
+            Err(status) => {
+                let store = self.borrow();
+                let accounts = &store.accounts.remaining_accounts;
+                update_hook_stats(accounts, Err(HookFailure::MemoHook));
+                return (ibc::ModuleExtras::empty(), status.into());
+            }
+        };
+
         let (extras, mut ack) =
             ibc::apps::transfer::module::on_recv_packet_execute(
                 self,
@@ -162,11 +195,12 @@ impl ibc::Module for IbcStorage<'_, '_> {
             false
         };
 
+        let mut hook_error = None;
         if success {
             let store = self.borrow();
             let accounts = &store.accounts.remaining_accounts;
             // Check if any account is not initialized and return the uninitialized account
-            if let Some(uninitialized_account) =
+            let result = if let Some(uninitialized_account) =
                 accounts.iter().find(|account| account.lamports() == 0)
             {
                 let status = ibc::TokenTransferError::Other(format!(
@@ -174,15 +208,31 @@ impl ibc::Module for IbcStorage<'_, '_> {
                     uninitialized_account.key
                 ))
                 .into();
-                ack = ibc::AcknowledgementStatus::error(status).into();
+                let status = ibc::AcknowledgementStatus::error(status);
+                Err((HookFailure::UninitialisedAccount, status))
             } else {
-                let result =
-                    call_bridge_escrow(accounts, &maybe_ft_packet.data);
-                if let Err(status) = result {
+                call_bridge_escrow(accounts, &maybe_ft_packet.data)
+                    .map_err(|status| (HookFailure::BridgeEscrow, status))
+                    .map(|escrow_called| {
+                        let memo_called = memo_hooks.is_some();
+                        if let Some(memo_hooks) = memo_hooks {
+                            hook_error = memo_hooks.invoke(accounts).err();
+                        }
+                        escrow_called || memo_called
+                    })
+            };
+            match result {
+                Ok(false) => (),
+                Ok(true) => update_hook_stats(accounts, Ok(())),
+                Err((failure, status)) => {
+                    update_hook_stats(accounts, Err(failure));
                     ack = status.into();
                 }
             }
         }
+        if hook_error.is_some() {
+            self.borrow_mut().hook_error = hook_error;
+        }
 
         // Since the ack status can change based on the hook above, log it.
         msg!(
@@ -200,12 +250,14 @@ impl ibc::Module for IbcStorage<'_, '_> {
         acknowledgement: &ibc::Acknowledgement,
         relayer: &ibc::Signer,
     ) -> Result<(), ibc::PacketError> {
-        ibc::apps::transfer::module::on_acknowledgement_packet_validate(
-            self,
-            packet,
-            acknowledgement,
-            relayer,
-        )
+        self.without_min_transfer_amounts(|store| {
+            ibc::apps::transfer::module::on_acknowledgement_packet_validate(
+                store,
+                packet,
+                acknowledgement,
+                relayer,
+            )
+        })
         .map_err(|e| ibc::PacketError::AppModule { description: e.to_string() })
     }
 
@@ -214,9 +266,11 @@ impl ibc::Module for IbcStorage<'_, '_> {
         packet: &ibc::Packet,
         relayer: &ibc::Signer,
     ) -> Result<(), ibc::PacketError> {
-        ibc::apps::transfer::module::on_timeout_packet_validate(
-            self, packet, relayer,
-        )
+        self.without_min_transfer_amounts(|store| {
+            ibc::apps::transfer::module::on_timeout_packet_validate(
+                store, packet, relayer,
+            )
+        })
         .map_err(|e| ibc::PacketError::AppModule { description: e.to_string() })
     }
 
@@ -272,13 +326,14 @@ impl ibc::Module for IbcStorage<'_, '_> {
         acknowledgement: &ibc::Acknowledgement,
         relayer: &ibc::Signer,
     ) -> (ibc::ModuleExtras, Result<(), ibc::PacketError>) {
-        let result =
+        let result = self.without_rate_limits(|store| {
             ibc::apps::transfer::module::on_acknowledgement_packet_execute(
-                self,
+                store,
                 packet,
                 acknowledgement,
                 relayer,
-            );
+            )
+        });
 
         let status = serde_json::from_slice::<ibc::AcknowledgementStatus>(
             acknowledgement.as_bytes(),
@@ -301,10 +356,9 @@ impl ibc::Module for IbcStorage<'_, '_> {
             let private = &store.private;
             let receiver = accounts.receiver.clone().unwrap();
             let fee_collector = accounts.fee_collector.clone().unwrap();
-            **fee_collector.try_borrow_mut_lamports().unwrap() -=
-                private.fee_in_lamports;
-            **receiver.try_borrow_mut_lamports().unwrap() +=
-                private.fee_in_lamports;
+            let fee = packet_fee(private, &packet.data);
+            **fee_collector.try_borrow_mut_lamports().unwrap() -= fee;
+            **receiver.try_borrow_mut_lamports().unwrap() += fee;
         }
 
         (
@@ -320,9 +374,11 @@ impl ibc::Module for IbcStorage<'_, '_> {
         packet: &ibc::Packet,
         relayer: &ibc::Signer,
     ) -> (ibc::ModuleExtras, Result<(), ibc::PacketError>) {
-        let result = ibc::apps::transfer::module::on_timeout_packet_execute(
-            self, packet, relayer,
-        );
+        let result = self.without_rate_limits(|store| {
+            ibc::apps::transfer::module::on_timeout_packet_execute(
+                store, packet, relayer,
+            )
+        });
         // refund the fee as the timeout has been successfully processed
         if result.1.is_ok() {
             let store = self.borrow();
@@ -330,10 +386,9 @@ impl ibc::Module for IbcStorage<'_, '_> {
             let private = &store.private;
             let receiver = accounts.receiver.clone().unwrap();
             let fee_collector = accounts.fee_collector.clone().unwrap();
-            **fee_collector.try_borrow_mut_lamports().unwrap() -=
-                private.fee_in_lamports;
-            **receiver.try_borrow_mut_lamports().unwrap() +=
-                private.fee_in_lamports;
+            let fee = packet_fee(private, &packet.data);
+            **fee_collector.try_borrow_mut_lamports().unwrap() -= fee;
+            **receiver.try_borrow_mut_lamports().unwrap() += fee;
         }
         (
             result.0,
@@ -408,15 +463,58 @@ impl From<FtPacketData> for FungibleTokenPacketData {
     }
 }
 
+/// Returns fee charged for sending packet with given data.
+///
+/// The fee is recalculated from the packet’s denom and amount so it matches
+/// what `send_transfer` charged as long as the fee schedule hasn’t changed in
+/// the meantime.
+fn packet_fee(private: &PrivateStorage, data: &[u8]) -> u64 {
+    match serde_json::from_slice::<PacketData>(data) {
+        Ok(data) => {
+            let denom = data.token.denom.to_string();
+            let amount = primitive_types::U256::from(data.token.amount);
+            private.transfer_fee(
+                &lib::hash::CryptoHash::digest(denom.as_bytes()),
+                u64::try_from(amount).unwrap_or(u64::MAX),
+            )
+        }
+        Err(_) => private.fee_in_lamports,
+    }
+}
+
+/// Records outcome of a transfer hook in the hook statistics account.
+///
+/// The account is looked up among `accounts`.  If the relayer didn’t pass it,
+/// does nothing.  Failure to update the statistics doesn’t affect the packet.
+fn update_hook_stats(
+    accounts: &[AccountInfo],
+    result: Result<(), HookFailure>,
+) {
+    let stats = accounts
+        .iter()
+        .filter(|account| account.owner == &crate::ID && account.is_writable)
+        .find_map(|account| Account::<HookStats>::try_from(account).ok());
+    let mut stats = match stats {
+        Some(stats) => stats,
+        None => return,
+    };
+    let slot = crate::mocks::clock().map_or(0, |clock| clock.slot);
+    stats.record(result, slot);
+    if let Err(err) = stats.exit(&crate::ID) {
+        msg!("Failed updating hook stats: {}", err);
+    }
+}
+
 /// Calls bridge escrow after receiving packet if necessary.
 ///
 /// If the packet is for a [`HOOK_TOKEN_ADDRESS`] token, parses the transfer
 /// memo and invokes bridge escrow contract with instruction encoded in it.
-/// (see [`parse_bridge_memo`] for format of the memo).
+/// (see [`parse_bridge_memo`] for format of the memo).  Returns whether the
+/// contract has been invoked.
 fn call_bridge_escrow(
     accounts: &[AccountInfo],
     data: &[u8],
-) -> Result<(), ibc::AcknowledgementStatus> {
+) -> Result<bool, ibc::AcknowledgementStatus> {
     // Perform hooks
     let data = serde_json::from_slice::<PacketData>(data).map_err(|_| {
         ibc::AcknowledgementStatus::error(
@@ -427,7 +525,7 @@ fn call_bridge_escrow(
     // The hook would only be called if the transferred token is the one we are
     // interested in
     if !check_denom_is_hook_address(data.token.denom.base_denom.as_str()) {
-        return Ok(());
+        return Ok(false);
     }
 
     // The memo is a string and the structure is as follow:
@@ -478,28 +576,25 @@ fn call_bridge_escrow(
         )
     })?;
     msg!("Hook: Bridge escrow call successful");
-    Ok(())
+    Ok(true)
 }
 
 /// Parses memo of a transaction directed at the bridge escrow.
 ///
-/// Memo is a JSON object with a `memo` field of the form
-/// `{ memo: "N,account-0,account-1,...,account-N-1,intent-id,embedded-memo" }`.
-/// Embedded memo can contain commas.  Returns `intent-id` and `embedded-memo`
-/// or `None` if the memo does not conform to this format.  Note that no
+/// Memo is a JSON object with a `memo` field holding an encoded
+/// [`intent_memo::IntentMemo`].  Returns `intent-id` and `embedded-memo` or
+/// `None` if the memo does not conform to this format.  Note that no
 /// validation on accounts is performed.
 fn parse_bridge_memo(memo: &str) -> Option<(String, String)> {
     let parsed = serde_json::from_str::<serde_json::Value>(memo).ok()?;
     let memo_str = parsed.get("memo")?.as_str()?;
-    let (count, rest) = memo_str.split_once(',')?;
-    let mut current = rest;
-    // Skip accounts
-    for _ in 0..usize::from_str(count).ok()? {
-        let (_, rest) = current.split_once(',')?;
-        current = rest;
+    match intent_memo::IntentMemo::decode(memo_str) {
+        Ok(memo) => Some((memo.intent_id, memo.memo)),
+        Err(err) => {
+            msg!("Invalid bridge escrow memo: {}", err);
+            None
+        }
     }
-    let (intent, memo) = current.split_once(',')?;
-    Some((intent.to_string(), memo.to_string()))
 }
 
 /// Checks whether the base denom matches the expected hook address.
@@ -536,6 +631,14 @@ fn test_parse_bridge_memo() {
         ("intent", "", "{\"memo\":\"1,account0,intent,\"}"),
         ("", "memo", "{\"memo\":\"1,account0,,memo\"}"),
         ("", "", "{\"memo\":\"1,account0,,\"}"),
+        (
+            "intent",
+            "memo,comma",
+            concat!(
+                r#"{"memo":"{\"version\":1,\"accounts\":[\"account0\"],"#,
+                r#"\"intent_id\":\"intent\",\"memo\":\"memo,comma\"}"}"#,
+            ),
+        ),
     ] {
         assert_eq!(
             Some((intent.to_string(), memo.to_string())),
@@ -3,9 +3,8 @@ use core::num::NonZeroU64;
 use std::str::FromStr;
 use std::time::Duration;
 
-use anchor_lang::prelude::{Pubkey, SolanaSysvar};
+use anchor_lang::prelude::Pubkey;
 use lib::hash::CryptoHash;
-use spl_token::solana_program::clock::Clock;
 
 use crate::client_state::AnyClientState;
 use crate::consensus_state::AnyConsensusState;
@@ -51,7 +50,7 @@ impl ibc::ValidationContext for IbcStorage<'_, '_> {
 
     fn host_height(&self) -> Result<ibc::Height> {
         let height = if cfg!(feature = "witness") {
-            Clock::get()
+            crate::mocks::clock()
                 .map_err(|e| ibc::ClientError::ClientSpecific {
                     description: e.to_string(),
                 })?
@@ -64,7 +63,7 @@ impl ibc::ValidationContext for IbcStorage<'_, '_> {
 
     fn host_timestamp(&self) -> Result<ibc::Timestamp> {
         let timestamp = if cfg!(feature = "witness") {
-            let clock = Clock::get().map_err(|e| {
+            let clock = crate::mocks::clock().map_err(|e| {
                 ibc::ClientError::ClientSpecific { description: e.to_string() }
             })?;
             clock.unix_timestamp as u64 * 10u64.pow(9)
@@ -2,6 +2,12 @@ use cf_solana::types::PubKey;
 use solana_geyser_plugin_interface::geyser_plugin_interface;
 use solana_sdk::pubkey::Pubkey;
 
+/// Seed of the IBC storage account PDA.
+///
+/// This must match `solana_ibc::SOLANA_IBC_STORAGE_SEED`.  The crate isn’t
+/// a dependency since it’s built against a different Solana version.
+const SOLANA_IBC_STORAGE_SEED: &[u8] = b"private";
+
 #[derive(Debug, Clone, serde::Deserialize)]
 #[serde(deny_unknown_fields)]
 struct FileConfig {
@@ -10,6 +16,14 @@ struct FileConfig {
     trie_program: PubKey,
     root_account: PubKey,
     bind_address: std::net::SocketAddr,
+    #[serde(default)]
+    pubsub_bind_address: Option<std::net::SocketAddr>,
+    #[serde(default)]
+    ibc_program: Option<PubKey>,
+    #[serde(default)]
+    log: Option<crate::logging::Config>,
+    #[serde(default)]
+    store: Option<crate::store::Config>,
 }
 
 #[derive(Debug, Clone)]
@@ -17,6 +31,17 @@ pub struct Config {
     pub root_account: Pubkey,
     pub witness_account: Pubkey,
     pub bind_address: std::net::SocketAddr,
+    /// Address of the WebSocket server handling subscriptions.  If not set,
+    /// the server is not started.
+    pub pubsub_bind_address: Option<std::net::SocketAddr>,
+    /// IBC storage account whose changes are recorded if IBC program has been
+    /// specified in the configuration.
+    pub storage_account: Option<Pubkey>,
+    /// Logging configuration.  If not set, validator’s logger is used.
+    pub log: Option<crate::logging::Config>,
+    /// Persistent store configuration.  If not set, slot data is kept in
+    /// memory only.
+    pub store: Option<crate::store::Config>,
 }
 
 #[derive(Debug, derive_more::From, derive_more::Display)]
@@ -48,7 +73,19 @@ impl Config {
                     trie_program,
                     root_account,
                 })?;
-        Ok(Self { root_account, witness_account, bind_address })
+        let storage_account = cfg.ibc_program.map(|program| {
+            let program = Pubkey::from(program);
+            Pubkey::find_program_address(&[SOLANA_IBC_STORAGE_SEED], &program).0
+        });
+        Ok(Self {
+            root_account,
+            witness_account,
+            bind_address,
+            pubsub_bind_address: cfg.pubsub_bind_address,
+            storage_account,
+            log: cfg.log,
+            store: cfg.store,
+        })
     }
 }
 
@@ -4,8 +4,10 @@ extern crate core;
 use solana_geyser_plugin_interface::geyser_plugin_interface::GeyserPlugin;
 
 mod config;
+mod logging;
 mod plugin;
 mod rpc;
+mod store;
 mod types;
 mod utils;
 mod worker;
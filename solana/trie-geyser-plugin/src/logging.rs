@@ -0,0 +1,174 @@
+//! Structured logging configuration.
+//!
+//! By default the plugin emits its events through the logger provided by the
+//! validator (see [`GeyserPlugin::setup_logger`]).  If `log` section is
+//! present in the configuration file, the plugin installs its own `tracing`
+//! subscriber instead which can write to journald and/or rotating log files.
+//! For example:
+//!
+//! ```json
+//! "log": {
+//!     "level": "info",
+//!     "filters": { "rpc": "warn", "worker": "debug" },
+//!     "targets": [
+//!         { "type": "journald" },
+//!         { "type": "file", "directory": "/var/log/wittrie",
+//!           "rotation": "daily", "max_files": 7 }
+//!     ]
+//! }
+//! ```
+//!
+//! [`GeyserPlugin::setup_logger`]: solana_geyser_plugin_interface::geyser_plugin_interface::GeyserPlugin::setup_logger
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+type Subscriber = tracing_subscriber::layer::Layered<EnvFilter, Registry>;
+type BoxedLayer = Box<dyn Layer<Subscriber> + Send + Sync>;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Default log level, e.g. `info`.
+    #[serde(default = "default_level")]
+    level: String,
+
+    /// Per-module log levels.  Keys are paths of the plugin’s modules, e.g.
+    /// `rpc`, `worker` or `plugin`.
+    #[serde(default)]
+    filters: BTreeMap<String, String>,
+
+    /// Where to write the logs.
+    targets: Vec<Target>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum Target {
+    /// Writes human-readable logs to standard error.
+    Stderr,
+    /// Sends structured events to systemd journal.
+    Journald {
+        #[serde(default)]
+        syslog_identifier: Option<String>,
+    },
+    /// Writes logs to files in given directory rotating them periodically.
+    File {
+        directory: PathBuf,
+        #[serde(default = "default_prefix")]
+        prefix: String,
+        #[serde(default)]
+        rotation: Rotation,
+        /// Maximum number of log files to keep.  Oldest files are deleted.
+        #[serde(default)]
+        max_files: Option<usize>,
+        /// Whether to write events as JSON objects rather than plain text.
+        #[serde(default)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Default, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Rotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+fn default_level() -> String { "info".into() }
+fn default_prefix() -> String { "witnessed-trie-plugin.log".into() }
+
+#[derive(Debug, derive_more::From, derive_more::Display)]
+pub enum Error {
+    IO(std::io::Error),
+    Filter(tracing_subscriber::filter::ParseError),
+    Appender(tracing_appender::rolling::InitError),
+    Global(tracing::subscriber::SetGlobalDefaultError),
+}
+
+/// Handle keeping the log writers alive.
+///
+/// Logs written to files are buffered by a background thread.  Dropping this
+/// object flushes the buffers and terminates the thread.
+pub struct Guard(#[allow(dead_code)] Vec<WorkerGuard>);
+
+/// Installs global `tracing` subscriber as described by the configuration.
+pub fn init(config: &Config) -> Result<Guard, Error> {
+    let mut guards = Vec::new();
+    let layers = config
+        .targets
+        .iter()
+        .map(|target| target.layer(&mut guards))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let subscriber =
+        tracing_subscriber::registry().with(config.filter()?).with(layers);
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok(Guard(guards))
+}
+
+impl Config {
+    /// Builds filter from default log level and per-module filters.
+    fn filter(&self) -> Result<EnvFilter, Error> {
+        let crate_name = module_path!().split("::").next().unwrap();
+        let mut directives = self.level.clone();
+        for (module, level) in self.filters.iter() {
+            directives.push_str(&format!(",{crate_name}::{module}={level}"));
+        }
+        Ok(EnvFilter::builder().parse(directives)?)
+    }
+}
+
+impl Target {
+    fn layer(
+        &self,
+        guards: &mut Vec<WorkerGuard>,
+    ) -> Result<BoxedLayer, Error> {
+        Ok(match self {
+            Self::Stderr => fmt::layer().with_writer(std::io::stderr).boxed(),
+            Self::Journald { syslog_identifier } => {
+                let layer = tracing_journald::layer()?;
+                match syslog_identifier {
+                    Some(id) => layer.with_syslog_identifier(id.clone()),
+                    None => layer,
+                }
+                .boxed()
+            }
+            Self::File { directory, prefix, rotation, max_files, json } => {
+                let mut builder = rolling::RollingFileAppender::builder()
+                    .rotation(rotation.into())
+                    .filename_prefix(prefix);
+                if let Some(max_files) = max_files {
+                    builder = builder.max_log_files(*max_files);
+                }
+                let appender = builder.build(directory)?;
+                let (writer, guard) = tracing_appender::non_blocking(appender);
+                guards.push(guard);
+                let layer = fmt::layer().with_writer(writer).with_ansi(false);
+                if *json {
+                    layer.json().boxed()
+                } else {
+                    layer.boxed()
+                }
+            }
+        })
+    }
+}
+
+impl From<&Rotation> for rolling::Rotation {
+    fn from(rotation: &Rotation) -> Self {
+        match rotation {
+            Rotation::Minutely => Self::MINUTELY,
+            Rotation::Hourly => Self::HOURLY,
+            Rotation::Daily => Self::DAILY,
+            Rotation::Never => Self::NEVER,
+        }
+    }
+}
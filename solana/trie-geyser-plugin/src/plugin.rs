@@ -3,7 +3,7 @@ use solana_geyser_plugin_interface::geyser_plugin_interface::{
     ReplicaBlockInfoVersions, ReplicaTransactionInfoVersions,
 };
 
-use crate::{config, rpc, types, utils, worker};
+use crate::{config, logging, rpc, store, types, utils, worker};
 
 type Result<T = (), E = GeyserPluginError> = ::core::result::Result<T, E>;
 
@@ -13,11 +13,17 @@ pub(crate) struct Plugin(Option<Inner>);
 struct Inner {
     /// The RPC server.
     server: jsonrpc_http_server::Server,
+    /// The WebSocket server handling subscriptions if enabled.
+    pubsub_server: Option<jsonrpc_ws_server::Server>,
 
     /// The worker thread.
     worker: std::thread::JoinHandle<()>,
     /// Channel for sending messages to the worker.
     sender: crossbeam_channel::Sender<worker::Message>,
+
+    /// Handle for the log writers if plugin uses its own logging
+    /// configuration.  Kept so that buffered logs are flushed on unload.
+    _log_guard: Option<logging::Guard>,
 }
 
 impl Plugin {
@@ -34,16 +40,16 @@ impl GeyserPlugin for Plugin {
     fn name(&self) -> &'static str { "witnessed-trie-plugin" }
 
     /// Initialises the logger.
+    ///
+    /// Events are forwarded to validator’s logger unless `log` section is
+    /// present in plugin’s configuration file.  See [`logging`] module.
     fn setup_logger(
         &self,
         logger: &'static dyn log::Log,
         level: log::LevelFilter,
     ) -> Result {
-        eprintln!("setup_logger({level:?})");
         log::set_max_level(level);
         log::set_logger(logger).map_err(utils::custom_err)?;
-        log::info!("Info");
-        log::error!("Error");
         Ok(())
     }
 
@@ -55,23 +61,52 @@ impl GeyserPlugin for Plugin {
     /// file.  For that reason, whenever this would return an Err result, it
     /// also logs that error.
     fn on_load(&mut self, config_file: &str, _is_reload: bool) -> Result {
-        eprintln!("on_load");
         if self.0.is_some() {
             let msg = "Plugin has been initialised already";
-            log::error!("{msg}");
+            tracing::error!("{msg}");
             return Err(utils::custom_err(msg));
         }
 
         let config =
             config::Config::load(config_file.as_ref()).map_err(|err| {
-                log::error!("{config_file}: {err}");
+                tracing::error!("{config_file}: {err}");
                 err
             })?;
 
-        let (server, db) = rpc::spawn_server(&config.bind_address)?;
-        let (worker, sender) = worker::spawn_worker(config, db)?;
+        let log_guard = match config.log.as_ref().map(logging::init) {
+            None => None,
+            Some(Ok(guard)) => Some(guard),
+            Some(Err(err)) => {
+                tracing::error!("{config_file}: log: {err}");
+                return Err(utils::custom_err(err.to_string()));
+            }
+        };
 
-        self.0 = Some(Inner { worker, sender, server });
+        let store = config
+            .store
+            .as_ref()
+            .map(|cfg| {
+                store::Store::open(cfg).map_err(|err| {
+                    tracing::error!("{}: {err}", cfg.path.display());
+                    utils::custom_err(err)
+                })
+            })
+            .transpose()?;
+        let (server, db) =
+            rpc::spawn_server(&config.bind_address, store.clone())?;
+        let pubsub_server = config
+            .pubsub_bind_address
+            .map(|addr| rpc::spawn_pubsub_server(&addr, db.clone()))
+            .transpose()?;
+        let (worker, sender) = worker::spawn_worker(config, db, store)?;
+
+        self.0 = Some(Inner {
+            worker,
+            sender,
+            server,
+            pubsub_server,
+            _log_guard: log_guard,
+        });
         Ok(())
     }
 
@@ -82,17 +117,20 @@ impl GeyserPlugin for Plugin {
             None => return,
         };
         inner.server.close();
+        if let Some(server) = inner.pubsub_server {
+            server.close();
+        }
         core::mem::drop(inner.sender);
         let err = match inner.worker.join() {
             Ok(()) => return,
             Err(err) => err,
         };
         if let Some(msg) = err.downcast_ref::<&str>() {
-            log::error!("worker thread panicked: {msg}")
+            tracing::error!("worker thread panicked: {msg}")
         } else if let Some(msg) = err.downcast_ref::<String>() {
-            log::error!("worker thread panicked: {msg}")
+            tracing::error!("worker thread panicked: {msg}")
         } else {
-            log::error!("worker thread panicked with unknown message")
+            tracing::error!("worker thread panicked with unknown message")
         }
     }
 
@@ -109,8 +147,7 @@ impl GeyserPlugin for Plugin {
         slot: u64,
         is_startup: bool,
     ) -> Result {
-        eprintln!("update_account(_, {slot}, {is_startup})");
-        log::info!("update_account(_, {slot}, {is_startup})");
+        tracing::trace!(slot, is_startup, "update_account");
         if is_startup && slot == 0 {
             return Ok(());
         }
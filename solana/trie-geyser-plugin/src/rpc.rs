@@ -1,17 +1,21 @@
 use alloc::collections::VecDeque;
+use std::collections::HashMap;
 use std::sync::Arc;
 // use tokio::sync::{RwLock, Notify};
 // use tokio::net::{TcpListener, TcpStream};
 use std::sync::RwLock;
 
+use cf_solana::proof::AccountHashData;
 use jsonrpc_http_server::jsonrpc_core;
+use jsonrpc_pubsub::typed::{Sink, Subscriber};
+use jsonrpc_pubsub::{PubSubHandler, Session, SubscriptionId};
 use solana_geyser_plugin_interface::geyser_plugin_interface::GeyserPluginError;
-use witnessed_trie_geyser::api::Methods as _;
+use witnessed_trie_geyser::api::{Methods as _, PubSub as _};
 
-use crate::utils;
+use crate::{store, utils};
 
 pub type Result<T, E = jsonrpc_core::Error> = core::result::Result<T, E>;
-pub(crate) use witnessed_trie_geyser::api::SlotData;
+pub(crate) use witnessed_trie_geyser::api::{SlotData, SlotNotification};
 
 /// Mximum number of past slot for which data is stored.
 ///
@@ -23,6 +27,9 @@ const MAX_SLOTS: usize = 128;
 /// Name of the RPC server thread.
 const THREAD_NAME: &str = "witnessed-trie-rpc";
 
+/// Maximum number of entries returned by `getSlotDataRange`.
+const MAX_RANGE_SLOTS: usize = 256;
+
 pub(crate) struct Database {
     /// Slot numbers for each corresponding entry in `slot_data` list.
     ///
@@ -38,6 +45,22 @@ pub(crate) struct Database {
     /// Data for slot whose number is stored in corresponding entry in
     /// `slot_nums` list.  See `slot_nums` for more context.
     slot_data: VecDeque<Arc<SlotData>>,
+
+    /// States of the IBC storage account in slots in which it changed ordered
+    /// by slot number.
+    ///
+    /// Like `slot_nums`, has a limited size (see `MAX_SLOTS`).
+    storage_updates: VecDeque<(u64, Arc<AccountHashData>)>,
+
+    /// Clients subscribed to `proofsSubscribe` notifications.
+    subscribers: HashMap<SubscriptionId, Sink<SlotNotification>>,
+
+    /// Identifier to assign to the next subscriber.
+    next_subscriber_id: u64,
+
+    /// Persistent store used to look up slots which are no longer kept in
+    /// memory.  The worker writes to the store directly.
+    store: Option<store::Store>,
 }
 
 pub(crate) type DBHandle = Arc<RwLock<Database>>;
@@ -47,22 +70,49 @@ impl Default for Database {
         Self {
             slot_nums: VecDeque::with_capacity(MAX_SLOTS),
             slot_data: VecDeque::with_capacity(MAX_SLOTS),
+            storage_updates: VecDeque::with_capacity(MAX_SLOTS),
+            subscribers: HashMap::new(),
+            next_subscriber_id: 0,
+            store: None,
         }
     }
 }
 
 impl Database {
-    /// Creates new empty database.
-    pub fn new() -> DBHandle { Arc::new(Default::default()) }
+    /// Creates new database.
+    ///
+    /// If persistent store is given, the most recent entries are loaded from
+    /// it so that data for slots rooted before the validator has restarted
+    /// are available.
+    pub fn new(store: Option<store::Store>) -> Result<DBHandle, store::Error> {
+        let mut db = Self::default();
+        if let Some(store) = store {
+            for (slot, data) in store.get_latest_slots(MAX_SLOTS)? {
+                db.slot_nums.push_back(slot);
+                db.slot_data.push_back(data);
+            }
+            db.storage_updates =
+                store.get_latest_storage_updates(MAX_SLOTS)?.into();
+            if let Some(slot) = db.slot_nums.back() {
+                tracing::info!(
+                    "{THREAD_NAME}: loaded {} slots from store; latest is \
+                     {slot}",
+                    db.slot_nums.len()
+                );
+            }
+            db.store = Some(store);
+        }
+        Ok(Arc::new(RwLock::new(db)))
+    }
 
-    /// Adds a new entry to the database.
+    /// Adds a new entry to the database and notifies subscribers about it.
     ///
     /// `slot` must be greater than slot number for any existing entry.  Data is
     /// not inserted if this is not the case.
     pub fn add(&mut self, slot: u64, data: SlotData) {
         if let Some(&last) = self.slot_nums.back() {
             if last >= slot {
-                log::error!(
+                tracing::error!(
                     "{THREAD_NAME}: trying to insert rooted slot {slot} out \
                      of order; latest is {last}"
                 );
@@ -73,8 +123,75 @@ impl Database {
             self.slot_nums.pop_front();
             self.slot_data.pop_front();
         }
+        let data = Arc::new(data);
         self.slot_nums.push_back(slot);
-        self.slot_data.push_back(Arc::new(data));
+        self.slot_data.push_back(data.clone());
+        self.notify_subscribers(slot, data);
+    }
+
+    /// Pushes notification about a new slot to all subscribers.
+    ///
+    /// Subscribers which cannot receive the notification (e.g. because they
+    /// have disconnected) are removed.
+    fn notify_subscribers(&mut self, slot: u64, data: Arc<SlotData>) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let bank_hash = data.delta_hash_proof.calculate_bank_hash();
+        let notification = SlotNotification { slot, bank_hash, data };
+        self.subscribers.retain(|id, sink| {
+            let res = sink.notify(Ok(notification.clone()));
+            if let Err(err) = &res {
+                tracing::debug!(?id, "dropping subscriber: {err}");
+            }
+            res.is_ok()
+        });
+    }
+
+    /// Registers a new `proofsSubscribe` subscriber.
+    fn subscribe(&mut self, subscriber: Subscriber<SlotNotification>) {
+        let id = SubscriptionId::Number(self.next_subscriber_id);
+        self.next_subscriber_id += 1;
+        if let Ok(sink) = subscriber.assign_id(id.clone()) {
+            self.subscribers.insert(id, sink);
+        }
+    }
+
+    /// Removes a `proofsSubscribe` subscriber.  Returns whether subscriber with
+    /// given identifier existed.
+    fn unsubscribe(&mut self, id: &SubscriptionId) -> bool {
+        self.subscribers.remove(id).is_some()
+    }
+
+    /// Records state of the IBC storage account at given slot.
+    ///
+    /// `slot` must be greater than slot number for any existing update.  The
+    /// update is not inserted if this is not the case.
+    pub fn add_storage_update(&mut self, slot: u64, account: AccountHashData) {
+        if let Some(&(last, _)) = self.storage_updates.back() {
+            if last >= slot {
+                tracing::error!(
+                    "{THREAD_NAME}: trying to insert storage update for slot \
+                     {slot} out of order; latest is {last}"
+                );
+                return;
+            }
+        }
+        if self.storage_updates.len() == MAX_SLOTS {
+            self.storage_updates.pop_front();
+        }
+        self.storage_updates.push_back((slot, Arc::new(account)));
+    }
+
+    /// Returns states of the IBC storage account in slots after `since_slot`.
+    pub fn get_storage_updates(
+        &self,
+        since_slot: u64,
+    ) -> Vec<(u64, Arc<AccountHashData>)> {
+        let start = self
+            .storage_updates
+            .partition_point(|(slot, _)| *slot <= since_slot);
+        self.storage_updates.range(start..).cloned().collect()
     }
 
     /// Returns list of all slots for which data exists.
@@ -90,21 +207,56 @@ impl Database {
     }
 
     /// Returns data for given slot.
-    pub fn get(&self, slot: u64) -> Option<Arc<SlotData>> {
-        self.slot_nums
-            .binary_search(&slot)
-            .ok()
-            .map(|index| self.slot_data[index].clone())
+    ///
+    /// Falls back to the persistent store if the slot isn’t kept in memory.
+    pub fn get(
+        &self,
+        slot: u64,
+    ) -> Result<Option<Arc<SlotData>>, store::Error> {
+        if let Ok(index) = self.slot_nums.binary_search(&slot) {
+            return Ok(Some(self.slot_data[index].clone()));
+        }
+        match &self.store {
+            Some(store) => Ok(store.get_slot(slot)?.map(Arc::new)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns data for slots in `start..=end` range ordered by slot number.
+    ///
+    /// At most `MAX_RANGE_SLOTS` entries are returned.  To get the rest,
+    /// caller needs to repeat the query starting past the last returned slot.
+    pub fn get_range(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<(u64, Arc<SlotData>)>, store::Error> {
+        if let Some(store) = &self.store {
+            return store.get_slot_range(start, end, MAX_RANGE_SLOTS);
+        }
+        let first = self.slot_nums.partition_point(|slot| *slot < start);
+        Ok(self
+            .slot_nums
+            .range(first..)
+            .zip(self.slot_data.range(first..))
+            .take_while(|(slot, _)| **slot <= end)
+            .take(MAX_RANGE_SLOTS)
+            .map(|(slot, data)| (*slot, data.clone()))
+            .collect())
     }
 }
 
 pub(crate) fn spawn_server(
     bind_address: &std::net::SocketAddr,
+    store: Option<store::Store>,
 ) -> Result<(jsonrpc_http_server::Server, DBHandle), GeyserPluginError> {
     let mut io = jsonrpc_core::MetaIoHandler::default();
     io.extend_with(Server.to_delegate());
 
-    let db = Database::new();
+    let db = Database::new(store).map_err(|err| {
+        tracing::error!("store: {err}");
+        utils::custom_err(err)
+    })?;
     let server = {
         let db = db.clone();
         jsonrpc_http_server::ServerBuilder::with_meta_extractor(
@@ -118,12 +270,32 @@ pub(crate) fn spawn_server(
     .cors_max_age(86400)
     .start_http(bind_address)
     .map_err(|err| {
-        log::error!("{bind_address}: {err}");
+        tracing::error!("{bind_address}: {err}");
         utils::custom_err(err)
     })?;
     Ok((server, db))
 }
 
+/// Starts WebSocket server handling `proofsSubscribe` subscriptions.
+pub(crate) fn spawn_pubsub_server(
+    bind_address: &std::net::SocketAddr,
+    db: DBHandle,
+) -> Result<jsonrpc_ws_server::Server, GeyserPluginError> {
+    let mut io = PubSubHandler::new(jsonrpc_core::MetaIoHandler::default());
+    io.extend_with(PubSubServer(db).to_delegate());
+
+    jsonrpc_ws_server::ServerBuilder::with_meta_extractor(
+        io,
+        |context: &jsonrpc_ws_server::RequestContext| {
+            Arc::new(Session::new(context.sender()))
+        },
+    )
+    .start(bind_address)
+    .map_err(|err| {
+        tracing::error!("{bind_address}: {err}");
+        utils::custom_err(err)
+    })
+}
 
 struct Server;
 
@@ -135,11 +307,23 @@ impl Server {
         match meta.try_read() {
             Ok(db) => Ok(func(&db)),
             Err(err) => {
-                log::error!("{err}");
+                tracing::error!("{err}");
                 Err(jsonrpc_core::Error::internal_error())
             }
         }
     }
+
+    /// Like [`Self::read`] but for functions which may fail reading from the
+    /// persistent store.
+    fn read_store<T>(
+        meta: &DBHandle,
+        func: impl FnOnce(&Database) -> Result<T, store::Error>,
+    ) -> Result<T> {
+        Self::read(meta, func)?.map_err(|err| {
+            tracing::error!("store: {err}");
+            jsonrpc_core::Error::internal_error()
+        })
+    }
 }
 
 impl witnessed_trie_geyser::api::Methods for Server {
@@ -161,6 +345,45 @@ impl witnessed_trie_geyser::api::Methods for Server {
         meta: Self::Metadata,
         slot: u64,
     ) -> Result<Option<Arc<SlotData>>> {
-        Self::read(&meta, |meta| meta.get(slot))
+        Self::read_store(&meta, |meta| meta.get(slot))
+    }
+
+    fn get_slot_data_range(
+        &self,
+        meta: Self::Metadata,
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<Vec<(u64, Arc<SlotData>)>> {
+        Self::read_store(&meta, |meta| meta.get_range(start_slot, end_slot))
+    }
+
+    fn get_storage_updates(
+        &self,
+        meta: Self::Metadata,
+        since_slot: u64,
+    ) -> Result<Vec<(u64, Arc<AccountHashData>)>> {
+        Self::read(&meta, |meta| meta.get_storage_updates(since_slot))
+    }
+}
+
+struct PubSubServer(DBHandle);
+
+impl witnessed_trie_geyser::api::PubSub for PubSubServer {
+    type Metadata = Arc<Session>;
+
+    fn proofs_subscribe(
+        &self,
+        _meta: Self::Metadata,
+        subscriber: Subscriber<SlotNotification>,
+    ) {
+        self.0.write().unwrap().subscribe(subscriber)
+    }
+
+    fn proofs_unsubscribe(
+        &self,
+        _meta: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        Ok(self.0.write().unwrap().unsubscribe(&id))
     }
 }
@@ -0,0 +1,175 @@
+//! Persistent storage of slot data.
+//!
+//! The RPC server keeps data for only the most recent slots in memory and
+//! loses it when the validator restarts.  If `store` section is present in
+//! plugin’s configuration, the worker additionally writes every record to an
+//! embedded database on disk.  After restart, the in-memory database is
+//! populated from it and relayers can query slots which are no longer kept in
+//! memory with `getSlotDataRange` method.
+//!
+//! Records are kept for `retention_slots` slots counting back from the most
+//! recently written one.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cf_solana::proof::AccountHashData;
+
+use crate::rpc::SlotData;
+
+/// Name of the tree holding slot data.
+const SLOTS_TREE: &str = "slots";
+
+/// Name of the tree holding states of the IBC storage account.
+const STORAGE_UPDATES_TREE: &str = "storage-updates";
+
+/// Configuration of the persistent store.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Directory the database is kept in.  Created if it doesn’t exist.
+    pub path: PathBuf,
+
+    /// Number of slots for which records are kept.
+    #[serde(default = "default_retention_slots")]
+    pub retention_slots: u64,
+}
+
+/// About two days worth of slots.
+fn default_retention_slots() -> u64 { 432_000 }
+
+#[derive(Debug, derive_more::From, derive_more::Display)]
+pub enum Error {
+    Db(sled::Error),
+    Serde(serde_json::Error),
+}
+
+impl std::error::Error for Error {}
+
+/// Handle to the on-disk database.
+///
+/// The handle is cheap to clone and all clones refer to the same database.
+/// The worker writes to it while the RPC server reads from it.
+#[derive(Clone)]
+pub(crate) struct Store {
+    /// Slot number → JSON-serialised [`SlotData`].
+    slots: sled::Tree,
+    /// Slot number → JSON-serialised [`AccountHashData`] of the IBC storage
+    /// account.
+    storage_updates: sled::Tree,
+    /// Number of slots for which records are kept.
+    retention_slots: u64,
+}
+
+impl Store {
+    /// Opens (or creates) the database.
+    pub fn open(config: &Config) -> Result<Self, Error> {
+        let db = sled::open(&config.path)?;
+        Ok(Self {
+            slots: db.open_tree(SLOTS_TREE)?,
+            storage_updates: db.open_tree(STORAGE_UPDATES_TREE)?,
+            retention_slots: config.retention_slots,
+        })
+    }
+
+    /// Writes data of a rooted slot and removes records which fell out of the
+    /// retention window.
+    pub fn put_slot(&self, slot: u64, data: &SlotData) -> Result<(), Error> {
+        self.slots.insert(slot.to_be_bytes(), serde_json::to_vec(data)?)?;
+        self.prune(slot)
+    }
+
+    /// Writes state of the IBC storage account at given slot.
+    pub fn put_storage_update(
+        &self,
+        slot: u64,
+        account: &AccountHashData,
+    ) -> Result<(), Error> {
+        let value = serde_json::to_vec(account)?;
+        self.storage_updates.insert(slot.to_be_bytes(), value)?;
+        Ok(())
+    }
+
+    /// Returns data of given slot.
+    pub fn get_slot(&self, slot: u64) -> Result<Option<SlotData>, Error> {
+        self.slots
+            .get(slot.to_be_bytes())?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Returns data of slots in `start..=end` range ordered by slot number.
+    ///
+    /// At most `limit` entries are returned.
+    pub fn get_slot_range(
+        &self,
+        start: u64,
+        end: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, Arc<SlotData>)>, Error> {
+        read_range(&self.slots, start..=end, limit)
+    }
+
+    /// Returns data of the last `count` slots ordered by slot number.
+    pub fn get_latest_slots(
+        &self,
+        count: usize,
+    ) -> Result<Vec<(u64, Arc<SlotData>)>, Error> {
+        read_latest(&self.slots, count)
+    }
+
+    /// Returns the last `count` states of the IBC storage account ordered by
+    /// slot number.
+    pub fn get_latest_storage_updates(
+        &self,
+        count: usize,
+    ) -> Result<Vec<(u64, Arc<AccountHashData>)>, Error> {
+        read_latest(&self.storage_updates, count)
+    }
+
+    /// Removes records for slots older than the retention window ending at
+    /// `latest_slot`.
+    fn prune(&self, latest_slot: u64) -> Result<(), Error> {
+        let first = latest_slot.saturating_sub(self.retention_slots);
+        for tree in [&self.slots, &self.storage_updates] {
+            for key in tree.range(..first.to_be_bytes()).keys() {
+                tree.remove(key?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a key–value pair read from a tree.
+fn decode<T: serde::de::DeserializeOwned>(
+    (key, value): (sled::IVec, sled::IVec),
+) -> Result<(u64, Arc<T>), Error> {
+    let slot = <[u8; 8]>::try_from(key.as_ref())
+        .map(u64::from_be_bytes)
+        .map_err(|_| sled::Error::Unsupported(format!("bad key: {key:?}")))?;
+    Ok((slot, Arc::new(serde_json::from_slice(&value)?)))
+}
+
+fn read_range<T: serde::de::DeserializeOwned>(
+    tree: &sled::Tree,
+    range: std::ops::RangeInclusive<u64>,
+    limit: usize,
+) -> Result<Vec<(u64, Arc<T>)>, Error> {
+    let range = range.start().to_be_bytes()..=range.end().to_be_bytes();
+    tree.range(range).take(limit).map(|entry| decode(entry?)).collect()
+}
+
+fn read_latest<T: serde::de::DeserializeOwned>(
+    tree: &sled::Tree,
+    count: usize,
+) -> Result<Vec<(u64, Arc<T>)>, Error> {
+    let mut entries = tree
+        .iter()
+        .rev()
+        .take(count)
+        .map(|entry| decode(entry?))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.reverse();
+    Ok(entries)
+}
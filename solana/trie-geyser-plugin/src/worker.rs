@@ -4,7 +4,7 @@ use cf_solana::proof::AccountHashData;
 use solana_geyser_plugin_interface::geyser_plugin_interface::GeyserPluginError;
 use solana_sdk::pubkey::Pubkey;
 
-use crate::{config, rpc, types, utils};
+use crate::{config, rpc, store, types, utils};
 
 /// Name of the RPC server thread.
 const THREAD_NAME: &str = "witnessed-trie-worker";
@@ -48,6 +48,8 @@ struct Worker {
     slots: BTreeMap<u64, SlotAccumulator>,
     /// Database handle
     db: rpc::DBHandle,
+    /// Persistent store the slot data is additionally written to.
+    store: Option<store::Store>,
 }
 
 /// State of a slot which hasn’t been rooted yet.
@@ -65,6 +67,7 @@ struct SlotAccumulator {
 pub(crate) fn spawn_worker(
     config: config::Config,
     db: rpc::DBHandle,
+    store: Option<store::Store>,
 ) -> Result<
     (std::thread::JoinHandle<()>, crossbeam_channel::Sender<Message>),
     GeyserPluginError,
@@ -72,10 +75,10 @@ pub(crate) fn spawn_worker(
     let (sender, receiver) = crossbeam_channel::unbounded();
     std::thread::Builder::new()
         .name(THREAD_NAME.into())
-        .spawn(move || worker(config, receiver, db))
+        .spawn(move || worker(config, receiver, db, store))
         .map(|handle| (handle, sender))
         .map_err(|err| {
-            log::error!("{err}");
+            tracing::error!("{err}");
             utils::custom_err(err)
         })
 }
@@ -84,8 +87,9 @@ fn worker(
     config: config::Config,
     receiver: crossbeam_channel::Receiver<Message>,
     db: rpc::DBHandle,
+    store: Option<store::Store>,
 ) {
-    let mut worker = Worker { config, slots: Default::default(), db };
+    let mut worker = Worker { config, slots: Default::default(), db, store };
     for msg in receiver {
         match msg {
             Message::Account(msg) => worker.handle_account(msg),
@@ -96,7 +100,7 @@ fn worker(
             }
         }
     }
-    log::info!("{THREAD_NAME}: terminating");
+    tracing::info!("{THREAD_NAME}: terminating");
 }
 
 impl Worker {
@@ -113,11 +117,11 @@ impl Worker {
             }
         }
 
-        log::debug!(
-            "#{}-{}: account update: {}",
-            info.slot,
-            info.write_version,
-            info.account.key(),
+        tracing::debug!(
+            slot = info.slot,
+            write_version = info.write_version,
+            account = %info.account.key(),
+            "account update",
         );
 
         let entry = self.slots.entry(info.slot).or_default();
@@ -154,23 +158,38 @@ impl Worker {
                     if key == slot {
                         break value;
                     }
-                    log::debug!("#{key}: dropping accumulator");
+                    tracing::debug!(slot = key, "dropping accumulator");
                 }
                 _ => {
-                    log::error!("#{slot}: accumulator not found");
+                    tracing::error!(slot, "accumulator not found");
                     return;
                 }
             }
         };
 
+        // Record new state of the IBC storage account if it changed.
+        let storage_account = self
+            .config
+            .storage_account
+            .and_then(|pubkey| entry.accounts.get(&pubkey));
+        if let Some((_, account)) = storage_account {
+            tracing::debug!(slot, "storage account modified");
+            if let Some(store) = &self.store {
+                if let Err(err) = store.put_storage_update(slot, account) {
+                    tracing::error!(slot, "store: {err}");
+                }
+            }
+            self.db.write().unwrap().add_storage_update(slot, account.clone());
+        }
+
         // If the trie or witness accounts are not in collection of changed
         // accounts, don’t do anything.
         if !entry.accounts.contains_key(&self.config.witness_account) {
-            log::debug!("#{slot}: witness account not modified");
+            tracing::debug!(slot, "witness account not modified");
             return;
         }
         if !entry.accounts.contains_key(&self.config.root_account) {
-            log::debug!("#{slot}: trie account not modified");
+            tracing::debug!(slot, "trie account not modified");
             return;
         }
 
@@ -178,7 +197,7 @@ impl Worker {
         let block = if let Some(block) = entry.block {
             block
         } else {
-            log::error!("#{slot}: missing block info");
+            tracing::error!(slot, "missing block info");
             return;
         };
 
@@ -221,7 +240,15 @@ impl Worker {
         let data =
             rpc::SlotData { delta_hash_proof, witness_proof, root_account };
 
-        log::info!("#{slot}: adding to database");
+        // Persist the data before adding it to the in-memory database.  This
+        // way disk I/O doesn’t happen while holding the database lock.
+        if let Some(store) = &self.store {
+            if let Err(err) = store.put_slot(slot, &data) {
+                tracing::error!(slot, "store: {err}");
+            }
+        }
+
+        tracing::info!(slot, "adding to database");
         self.db.write().unwrap().add(slot, data);
     }
 }
@@ -47,6 +47,18 @@ impl<D: DataRef> Allocator<D> {
         Some(addr)
     }
 
+    /// Returns number of blocks on the free list.
+    fn free_list_len(&self) -> usize {
+        let mut count = 0;
+        let mut addr = self.first_free;
+        while let Some(idx) = addr.and_then(Addr::usize) {
+            let next = self.data.get(idx..idx + 4).unwrap().try_into().unwrap();
+            addr = Addr::new(u32::from_ne_bytes(next));
+            count += 1;
+        }
+        count
+    }
+
     /// Grabs a next available block.  Returns `None` if account run out of
     /// space.
     fn alloc_next_block(&mut self) -> Option<Addr> {
@@ -195,6 +207,44 @@ impl<D: DataRef + Sized> memory::Allocator for Allocator<D> {
         bytes.next_free = next_free;
         self.first_free = Some(Addr::from(ptr));
     }
+
+    /// Returns statistics of the memory pool.
+    ///
+    /// Number of free blocks is determined by walking the free list so this
+    /// takes time linear in number of freed blocks.
+    fn stats(&self) -> Option<memory::Stats> {
+        let first = Header::ENCODED_SIZE as u32;
+        let total = (self.next_block.u32() - first) as usize / RawNode::SIZE;
+        let free = self.free_list_len();
+        Some(memory::Stats { allocated: total - free, free })
+    }
+
+    /// Moves the value to the block at the head of the free list if it’s at
+    /// a lower address than `ptr`.
+    ///
+    /// If `ptr` is the last block in the pool, rather than adding it to the
+    /// free list, the pool is shrunk.  This way, relocating blocks from the
+    /// end of the pool compacts the data.
+    fn relocate(&mut self, ptr: Ptr) -> Option<Ptr> {
+        let addr = Addr::from(ptr);
+        if self.first_free?.u32() > addr.u32() {
+            return None;
+        }
+        let new_ptr = Ptr::from(self.alloc_from_freelist().unwrap());
+        let value = *self.get(ptr);
+        self.set(new_ptr, value);
+        if addr.succ().map(Addr::u32) == Some(self.next_block.u32()) {
+            // Mark block as free so that double-free is still detected.
+            let bytes =
+                bytemuck::TransparentWrapper::peel_mut(self.get_mut(ptr));
+            let bytes: &mut FreeRawNode = bytemuck::must_cast_mut(bytes);
+            bytes.marker.fill(0);
+            self.next_block = addr;
+        } else {
+            self.free(ptr);
+        }
+        Some(new_ptr)
+    }
 }
 
 #[test]
@@ -210,3 +260,41 @@ fn test_double_free_detection() {
     alloc.free(ptr);
     alloc.free(ptr);
 }
+
+#[test]
+fn test_stats_and_relocate() {
+    use memory::Allocator as _;
+    use sealable_trie::nodes::Reference;
+
+    let (mut alloc, _root) = Allocator::new([0; 740]).unwrap();
+    assert_eq!(Some(memory::Stats::default()), alloc.stats());
+
+    let hashes = (0..5).map(CryptoHash::test).collect::<Vec<_>>();
+    let ptrs = hashes
+        .iter()
+        .map(|hash| {
+            let child = Reference::value(false, hash);
+            alloc.alloc(RawNode::branch(child, child)).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let want = memory::Stats { allocated: 5, free: 0 };
+    assert_eq!(Some(want), alloc.stats());
+    assert_eq!(None, alloc.relocate(ptrs[4]));
+
+    alloc.free(ptrs[1]);
+    alloc.free(ptrs[3]);
+    assert_eq!(Some(memory::Stats { allocated: 3, free: 2 }), alloc.stats());
+
+    // Head of the free list is ptrs[3] which is above ptrs[2].
+    assert_eq!(None, alloc.relocate(ptrs[2]));
+
+    // Relocating the last block shrinks the pool.
+    let value = *alloc.get(ptrs[4]);
+    assert_eq!(Some(ptrs[3]), alloc.relocate(ptrs[4]));
+    assert_eq!(&value, alloc.get(ptrs[3]));
+    assert_eq!(Some(memory::Stats { allocated: 3, free: 1 }), alloc.stats());
+
+    assert_eq!(Some(ptrs[1]), alloc.relocate(ptrs[3]));
+    assert_eq!(&value, alloc.get(ptrs[1]));
+    assert_eq!(Some(memory::Stats { allocated: 3, free: 0 }), alloc.stats());
+}
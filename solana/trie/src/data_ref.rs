@@ -149,3 +149,102 @@ impl<D: DataRef + ?Sized> DataRef for core::cell::RefMut<'_, D> {
     #[inline]
     fn enlarge(&mut self, _min_size: usize) -> bool { false }
 }
+
+
+/// Account data stored in a memory-mapped file.
+///
+/// This lets host-side tools operate on trie snapshots stored on disk without
+/// copying the entire account into memory.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MmapData {
+    map: memmap2::MmapMut,
+    /// The underlying file if the mapping is shared, i.e. changes are written
+    /// back to the file.  `None` for copy-on-write mappings.
+    file: Option<std::fs::File>,
+}
+
+#[cfg(feature = "std")]
+impl MmapData {
+    /// Maps given file in copy-on-write mode.
+    ///
+    /// Changes made to the data (e.g. header update when
+    /// [`crate::TrieAccount`] is dropped) are not written back to the file.
+    /// This is the mode to use when reading snapshots and generating proofs.
+    /// Such mapping cannot be enlarged.
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: Caller is responsible for ensuring the file isn’t modified
+        // by other processes while it’s mapped.
+        let map = unsafe { memmap2::MmapOptions::new().map_copy(&file) }?;
+        Ok(Self { map, file: None })
+    }
+
+    /// Maps given file in shared read-write mode.
+    ///
+    /// Changes made to the data are written back to the file.  Enlarging the
+    /// data extends the file and remaps it.
+    pub fn open_mut(
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Self> {
+        let file =
+            std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+        // SAFETY: Caller is responsible for ensuring the file isn’t modified
+        // by other processes while it’s mapped.
+        let map = unsafe { memmap2::MmapMut::map_mut(&file) }?;
+        Ok(Self { map, file: Some(file) })
+    }
+
+    /// Flushes outstanding changes to the file.
+    ///
+    /// Does nothing for copy-on-write mappings.
+    pub fn flush(&self) -> std::io::Result<()> {
+        match self.file {
+            Some(_) => self.map.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl DataRef for MmapData {
+    #[inline]
+    fn len(&self) -> usize { self.map.len() }
+
+    fn get<I: core::slice::SliceIndex<[u8]>>(
+        &self,
+        index: I,
+    ) -> Option<&I::Output> {
+        self.map[..].get(index)
+    }
+
+    fn get_mut<I: core::slice::SliceIndex<[u8]>>(
+        &mut self,
+        index: I,
+    ) -> Option<&mut I::Output> {
+        self.map[..].get_mut(index)
+    }
+
+    fn enlarge(&mut self, min_size: usize) -> bool {
+        if min_size <= self.map.len() {
+            return true;
+        }
+        let file = match self.file.as_ref() {
+            Some(file) => file,
+            None => return false,
+        };
+        let remap = || {
+            self.map.flush()?;
+            file.set_len(min_size as u64)?;
+            // SAFETY: Same as in open_mut.
+            unsafe { memmap2::MmapMut::map_mut(file) }
+        };
+        match remap() {
+            Ok(map) => {
+                self.map = map;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
@@ -12,10 +12,13 @@ mod account;
 mod alloc;
 mod data_ref;
 mod header;
+pub mod snapshot;
 pub mod witness;
 
 pub use account::ResizableAccount;
 pub use data_ref::DataRef;
+#[cfg(feature = "std")]
+pub use data_ref::MmapData;
 pub use sealable_trie::Trie;
 
 
@@ -110,6 +113,17 @@ impl<'a, 'info, W: witness::OptRef>
     }
 }
 
+/// Returns root hash of the trie stored in given account data.
+///
+/// Unlike [`TrieAccount::new`], this doesn’t need mutable access to the data
+/// and never modifies it.  Returns `None` if the data is too short or doesn’t
+/// start with a valid trie header (e.g. has unknown magic number).
+pub fn decode_root_hash(data: &[u8]) -> Option<lib::hash::CryptoHash> {
+    let hdr = data.get(..header::Header::ENCODED_SIZE)?;
+    let hdr = <[u8; header::Header::ENCODED_SIZE]>::try_from(hdr).ok()?;
+    header::Header::decode(&hdr).map(|hdr| hdr.root_hash)
+}
+
 /// Checks ownership information of the account.
 fn check_account(
     account: &AccountInfo,
@@ -241,4 +255,52 @@ fn test_trie_resize() {
         1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
         1, 1, 1, 1, 1,
     ], data.as_slice());
+
+    assert_eq!(
+        Some(lib::hash::CryptoHash::from(
+            <[u8; 32]>::try_from(&data[12..44]).unwrap()
+        )),
+        decode_root_hash(&data)
+    );
+    assert_eq!(
+        Some(sealable_trie::trie::EMPTY_TRIE_ROOT),
+        decode_root_hash(&[0; 72])
+    );
+    assert_eq!(None, decode_root_hash(&data[..71]));
+    data[0] ^= 1;
+    assert_eq!(None, decode_root_hash(&data));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_mmap_data() {
+    const ONE: lib::hash::CryptoHash = lib::hash::CryptoHash([1; 32]);
+    const TWO: lib::hash::CryptoHash = lib::hash::CryptoHash([2; 32]);
+
+    let path = std::env::temp_dir()
+        .join(format!("solana-trie-test-mmap-{}", std::process::id()));
+    std::fs::write(&path, [0; 72]).unwrap();
+
+    // Shared mapping writes changes back to the file and grows it as needed.
+    {
+        let mut trie =
+            TrieAccount::new(MmapData::open_mut(&path).unwrap()).unwrap();
+        assert_eq!(Ok(()), trie.set(&[0], &ONE));
+        assert_eq!(Ok(()), trie.set(&[1], &TWO));
+    }
+    let data = std::fs::read(&path).unwrap();
+    assert!(data.len() > 72);
+
+    // Copy-on-write mapping can be modified but the file is left unchanged.
+    {
+        let mut trie =
+            TrieAccount::new(MmapData::open(&path).unwrap()).unwrap();
+        assert_eq!(Ok(Some(ONE)), trie.get(&[0]));
+        assert_eq!(Ok(Some(TWO)), trie.get(&[1]));
+        assert_eq!(Ok(()), trie.set(&[0], &TWO));
+        assert_eq!(Ok(Some(TWO)), trie.get(&[0]));
+    }
+    assert_eq!(data, std::fs::read(&path).unwrap());
+
+    std::fs::remove_file(&path).unwrap();
 }
@@ -0,0 +1,294 @@
+//! Snapshots of the trie for off-chain state synchronisation.
+//!
+//! A snapshot is a compact representation of the trie which includes only
+//! nodes reachable from the root.  It can be exported from the trie account’s
+//! data with [`export`] and imported with [`import`] (or
+//! [`TrieAccount::from_snapshot`]) which verifies hashes of all the nodes
+//! against the root hash recorded in the snapshot.  This allows new relayers
+//! to bootstrap their state and auditors to check the state of the trie
+//! without trusting the source of the snapshot beyond the root hash.
+//!
+//! The snapshot has the following format (all integers are encoded using
+//! little-endian):
+//!
+//! ```ignore
+//! magic:     [u8; 8]
+//! root_ptr:  u32             — zero if root node isn’t stored
+//! root_hash: [u8; 32]
+//! count:     u32             — number of nodes which follow
+//! nodes:     [(u32, [u8; 72]); count]
+//!                            — pointer and raw representation of each node
+//!                              sorted by the pointer
+//! ```
+//!
+//! Nodes keep their pointers so that importing the snapshot doesn’t need to
+//! rewrite references.  Holes between nodes become free blocks of the
+//! imported trie.
+
+use std::collections::BTreeSet;
+
+use lib::hash::CryptoHash;
+use memory::Ptr;
+use sealable_trie::nodes::{Node, NodeRef, RawNode, Reference};
+
+use crate::header::Header;
+use crate::TrieAccount;
+
+/// Magic number identifying version 1 of the snapshot format.
+const MAGIC_V1: [u8; 8] = *b"TRIESNP1";
+
+/// Size of the snapshot header, i.e. everything before the nodes.
+const HEADER_SIZE: usize = 8 + 4 + 32 + 4;
+
+/// Size of a single node entry in the snapshot.
+const ENTRY_SIZE: usize = 4 + RawNode::SIZE;
+
+/// Error exporting or importing a snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Account data doesn’t start with a valid trie header.
+    BadHeader,
+    /// Snapshot is truncated or has an invalid magic number or pointer.
+    BadSnapshot,
+    /// Node referenced from the trie is missing.
+    MissingNode(Ptr),
+    /// Node cannot be decoded.
+    BadNode(Ptr),
+    /// Hash of the node doesn’t match hash its parent (or the root) refers to.
+    BadHash(Ptr),
+    /// Node is stored more than once or referenced more than once.
+    DuplicateNode(Ptr),
+    /// Snapshot includes nodes not reachable from the root.
+    UnreachableNode(Ptr),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmtr: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::BadHeader => fmtr.write_str("invalid trie header"),
+            Self::BadSnapshot => fmtr.write_str("invalid snapshot"),
+            Self::MissingNode(ptr) => write!(fmtr, "missing node {ptr}"),
+            Self::BadNode(ptr) => write!(fmtr, "invalid node {ptr}"),
+            Self::BadHash(ptr) => write!(fmtr, "hash mismatch at node {ptr}"),
+            Self::DuplicateNode(ptr) => write!(fmtr, "duplicate node {ptr}"),
+            Self::UnreachableNode(ptr) => {
+                write!(fmtr, "unreachable node {ptr}")
+            }
+        }
+    }
+}
+
+/// Exports snapshot of the trie stored in given account data.
+///
+/// Only nodes reachable from the root are included in the snapshot.  Since
+/// the data is borrowed for the duration of the call, the snapshot is
+/// consistent.  While walking the trie, hashes of all the nodes are verified
+/// so a corrupted account results in an error.
+pub fn export(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let hdr = data
+        .get(..Header::ENCODED_SIZE)
+        .and_then(|hdr| <&[u8; Header::ENCODED_SIZE]>::try_from(hdr).ok())
+        .and_then(Header::decode)
+        .ok_or(Error::BadHeader)?;
+    let ptrs = walk(data, hdr.root_ptr, &hdr.root_hash)?;
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + ptrs.len() * ENTRY_SIZE);
+    out.extend_from_slice(&MAGIC_V1);
+    out.extend_from_slice(&hdr.root_ptr.map_or(0, Ptr::get).to_le_bytes());
+    out.extend_from_slice(hdr.root_hash.as_slice());
+    out.extend_from_slice(&(ptrs.len() as u32).to_le_bytes());
+    for ptr in ptrs {
+        // walk has verified the node is within the data.
+        let block = &data[offset(ptr).unwrap()..][..RawNode::SIZE];
+        out.extend_from_slice(&ptr.get().to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    Ok(out)
+}
+
+/// Imports snapshot and returns trie account data with its contents.
+///
+/// Verifies that the nodes in the snapshot form a trie whose hash equals
+/// root hash recorded in the snapshot and that there are no extraneous nodes.
+/// The returned data can be used with [`TrieAccount::new`].
+pub fn import(snapshot: &[u8]) -> Result<Vec<u8>, Error> {
+    let (head, entries) = stdx::split_at::<HEADER_SIZE, u8>(snapshot)
+        .ok_or(Error::BadSnapshot)?;
+    let (magic, head) = stdx::split_array_ref::<8, 40, HEADER_SIZE>(head);
+    let (root_ptr, head) = stdx::split_array_ref::<4, 36, 40>(head);
+    let (root_hash, count) = stdx::split_array_ref::<32, 4, 36>(head);
+    if *magic != MAGIC_V1 {
+        return Err(Error::BadSnapshot);
+    }
+    let root_ptr = Ptr::new(u32::from_le_bytes(*root_ptr))
+        .map_err(|_| Error::BadSnapshot)?;
+    let root_hash = CryptoHash::from(*root_hash);
+    let count = u32::from_le_bytes(*count) as usize;
+    if entries.len() !=
+        count.checked_mul(ENTRY_SIZE).ok_or(Error::BadSnapshot)?
+    {
+        return Err(Error::BadSnapshot);
+    }
+
+    // Copy nodes into their blocks.
+    let mut data = vec![0; Header::ENCODED_SIZE];
+    let mut stored = BTreeSet::new();
+    for entry in entries.chunks_exact(ENTRY_SIZE) {
+        let (ptr, node) = entry.split_at(4);
+        let ptr = u32::from_le_bytes(ptr.try_into().unwrap());
+        let ptr = Ptr::new(ptr).ok().flatten().ok_or(Error::BadSnapshot)?;
+        let start = offset(ptr).ok_or(Error::BadSnapshot)?;
+        if !stored.insert(ptr) {
+            return Err(Error::DuplicateNode(ptr));
+        }
+        if data.len() < start + RawNode::SIZE {
+            data.resize(start + RawNode::SIZE, 0);
+        }
+        data[start..start + RawNode::SIZE].copy_from_slice(node);
+    }
+
+    // Verify the trie and make sure there are no unreachable nodes.
+    let reachable = walk(&data, root_ptr, &root_hash)?;
+    if let Some(ptr) = stored.difference(&reachable).next() {
+        return Err(Error::UnreachableNode(*ptr));
+    }
+
+    // Link holes into a free list.  Free blocks are all zero so their
+    // double-free markers are already set.  Lower addresses are put at the
+    // head of the list so they are allocated first.
+    let mut first_free = 0;
+    let end = data.len() / RawNode::SIZE;
+    for idx in (1..end).rev() {
+        let ptr = Ptr::new(idx as u32).unwrap().unwrap();
+        if !stored.contains(&ptr) {
+            let start = idx * RawNode::SIZE;
+            data[start..start + 4].copy_from_slice(&first_free.to_ne_bytes());
+            first_free = start as u32;
+        }
+    }
+
+    let hdr = Header {
+        root_ptr,
+        root_hash,
+        next_block: data.len() as u32,
+        first_free,
+    };
+    data[..Header::ENCODED_SIZE].copy_from_slice(&hdr.encode());
+    Ok(data)
+}
+
+impl TrieAccount<Vec<u8>, ()> {
+    /// Imports snapshot into a trie held in memory.
+    ///
+    /// See [`import`] for description of verification performed on the
+    /// snapshot.
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<Self, Error> {
+        Self::new(import(snapshot)?).ok_or(Error::BadHeader)
+    }
+}
+
+/// Walks the trie starting at the root verifying hashes of all the nodes.
+///
+/// Returns set of all the nodes reachable from the root.
+fn walk(
+    data: &[u8],
+    root_ptr: Option<Ptr>,
+    root_hash: &CryptoHash,
+) -> Result<BTreeSet<Ptr>, Error> {
+    let mut visited = BTreeSet::new();
+    let mut stack = Vec::new();
+    stack.extend(root_ptr.map(|ptr| (ptr, *root_hash)));
+    while let Some((ptr, hash)) = stack.pop() {
+        if !visited.insert(ptr) {
+            return Err(Error::DuplicateNode(ptr));
+        }
+        let block = offset(ptr)
+            .and_then(|start| data.get(start..start + RawNode::SIZE))
+            .ok_or(Error::MissingNode(ptr))?;
+        let raw: &RawNode =
+            bytemuck::TransparentWrapper::wrap_ref(block.try_into().unwrap());
+        let node = raw.decode().map_err(|_| Error::BadNode(ptr))?;
+        if node.hash() != hash {
+            return Err(Error::BadHash(ptr));
+        }
+        let children = match node {
+            Node::Branch { ref children } => &children[..],
+            Node::Extension { ref child, .. } => core::slice::from_ref(child),
+        };
+        for child in children {
+            if let Reference::Node(NodeRef { ptr: Some(ptr), hash }) = child {
+                stack.push((*ptr, **hash));
+            }
+        }
+    }
+    Ok(visited)
+}
+
+/// Returns offset of the block pointed by given pointer.
+///
+/// Returns `None` if end of the block doesn’t fit `u32` in which case the
+/// pointer cannot be used by the trie allocator.
+fn offset(ptr: Ptr) -> Option<usize> {
+    let end = ptr.get().checked_add(1)?.checked_mul(RawNode::SIZE as u32)?;
+    Some(end as usize - RawNode::SIZE)
+}
+
+#[test]
+fn test_snapshot() {
+    let mut data = vec![0; Header::ENCODED_SIZE];
+    let mut trie = TrieAccount::<_, ()>::new(&mut data).unwrap();
+    for n in 0..20u8 {
+        trie.set(&[n, n], &CryptoHash::test(n.into())).unwrap();
+    }
+    // Create some holes in the data.
+    for n in 0..5u8 {
+        trie.del(&[n * 2, n * 2]).unwrap();
+    }
+    trie.seal(&[11, 11]).unwrap();
+    let root_hash = *trie.hash();
+    let stats = trie.alloc_stats().unwrap();
+    drop(trie);
+
+    let snapshot = export(&data).unwrap();
+    assert!(snapshot.len() < data.len());
+    assert_eq!(HEADER_SIZE + stats.allocated * ENTRY_SIZE, snapshot.len());
+
+    let mut trie = TrieAccount::from_snapshot(&snapshot).unwrap();
+    assert_eq!(&root_hash, trie.hash());
+    assert_eq!(Ok(None), trie.get(&[0, 0]));
+    assert_eq!(Ok(Some(CryptoHash::test(1))), trie.get(&[1, 1]));
+    assert_eq!(Err(sealable_trie::Error::Sealed), trie.get(&[11, 11]));
+    assert_eq!(Ok(Some(CryptoHash::test(19))), trie.get(&[19, 19]));
+    assert_eq!(Some(stats.allocated), trie.alloc_stats().map(|s| s.allocated));
+
+    // Imported trie can be modified.
+    trie.set(&[42], &CryptoHash::test(42)).unwrap();
+    assert_eq!(Ok(Some(CryptoHash::test(42))), trie.get(&[42]));
+
+    // Re-exporting imported data results in the same snapshot.
+    assert_eq!(Ok(snapshot.clone()), export(&import(&snapshot).unwrap()));
+
+    // Tampering with a node is detected.
+    let mut bad = snapshot.clone();
+    *bad.last_mut().unwrap() ^= 1;
+    assert!(matches!(import(&bad), Err(Error::BadHash(_) | Error::BadNode(_))));
+
+    // Tampering with the root hash is detected.
+    let mut bad = snapshot.clone();
+    bad[12] ^= 1;
+    assert!(matches!(import(&bad), Err(Error::BadHash(_))));
+
+    // Truncated snapshot is rejected.
+    assert_eq!(
+        Err(Error::BadSnapshot),
+        import(&snapshot[..snapshot.len() - 1])
+    );
+}
+
+#[test]
+fn test_empty_snapshot() {
+    let snapshot = export(&[0; 72]).unwrap();
+    assert_eq!(HEADER_SIZE, snapshot.len());
+    let trie = TrieAccount::from_snapshot(&snapshot).unwrap();
+    assert_eq!(&sealable_trie::trie::EMPTY_TRIE_ROOT, trie.hash());
+}
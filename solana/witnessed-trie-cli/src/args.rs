@@ -1,5 +1,8 @@
 use core::str::FromStr;
+use std::path::PathBuf;
 
+use base64::Engine;
+use lib::hash::CryptoHash;
 use solana_sdk::pubkey::{Pubkey, MAX_SEED_LEN};
 use solana_sdk::signer::keypair::{read_keypair_file, Keypair};
 use wittrie::api;
@@ -15,11 +18,12 @@ pub struct Opts {
     /// The name this program was called with.
     pub argv0: String,
 
-    /// Solana cluster’s RPC URL.
-    pub rpc_url: String,
+    /// Solana cluster’s RPC URL.  `None` if the command works offline.
+    pub rpc_url: Option<String>,
 
-    /// Key pair to use when sending Solana transactions.
-    pub keypair: Keypair,
+    /// Key pair to use when sending Solana transactions.  `None` if the
+    /// command doesn’t send any transactions.
+    pub keypair: Option<Keypair>,
     /// Priority fee.
     pub priority: u64,
 
@@ -31,6 +35,42 @@ pub struct Opts {
     pub witness_account: Pubkey,
     /// Instruction data to send to the witnessed trie program.
     pub data: api::OwnedData,
+    /// If set, rather than sending a transaction, perform given query.
+    pub query: Option<Query>,
+    /// Path to the trie root account data to use rather than fetching the
+    /// account from the cluster.
+    pub account_dump: Option<PathBuf>,
+}
+
+/// Query which doesn’t send any transactions.
+pub enum Query {
+    /// Generate proof for given key.
+    Prove(Vec<u8>),
+    /// Verify proof for given key and value against given root.
+    Verify {
+        root: CryptoHash,
+        key: Vec<u8>,
+        value: Option<CryptoHash>,
+        proof: Vec<u8>,
+    },
+    /// List keys and values with given prefix.
+    DumpKeys(Vec<u8>),
+    /// Export snapshot of the trie to given file.
+    Export(PathBuf),
+    /// Verify snapshot of the trie and optionally save it as account data.
+    Import { snapshot: PathBuf, output: Option<PathBuf> },
+}
+
+impl Query {
+    /// Returns whether the query needs to contact the cluster.
+    fn needs_rpc(&self, account_dump: bool) -> bool {
+        match self {
+            Self::Prove(_) | Self::DumpKeys(_) | Self::Export(_) => {
+                !account_dump
+            }
+            Self::Verify { .. } | Self::Import { .. } => false,
+        }
+    }
 }
 
 /// Prints usage information.
@@ -52,10 +92,27 @@ fn usage(argv0: &str, full: bool) {
         "    -p --program-id=<id>  Id of the wittrie program\n",
         "    -s --seed=<seed>      Seed of the root trie PDA; empty by default\n",
         "    -b --bump=<bump>      Bump of the root trie PDA; calculated by default\n",
+        "    -d --account-dump=<path>\n",
+        "                          Read trie root account data from <path> (e.g. saved\n",
+        "                          with ‘solana account -o <path>’) rather than\n",
+        "                          fetching it from the cluster\n",
         "<op> is one of:\n",
         "     set  <key> <value>   Sets <key> to hash(<value>)\n",
         "     del  <key>           Deletes <key>\n",
         "     seal <key>           Seals <key>\n",
+        "Alternatively, rather than <op>s, one of the following queries can be given\n",
+        "which don’t send any transactions:\n",
+        "     prove <key>          Prints proof for <key>\n",
+        "     verify <root> <key> <value> <proof>\n",
+        "                          Verifies <proof> for <key>; <root>, <value> and\n",
+        "                          <proof> are base64-encoded as printed by prove;\n",
+        "                          <value> is ‘null’ for non-membership proofs\n",
+        "     dump-keys [<prefix>] Prints all unsealed keys starting with <prefix>\n",
+        "     export <path>        Saves snapshot of the trie to <path>\n",
+        "     import <path> [<output>]\n",
+        "                          Verifies trie snapshot saved in <path> and, if\n",
+        "                          given, writes it to <output> as account data which\n",
+        "                          can be used with --account-dump\n",
     ));
 }
 
@@ -84,6 +141,8 @@ fn parse_impl(prog: &mut Prog) -> Result<Opts, bool> {
     let mut root_seed: RootSeed = Default::default();
     let mut bump = None;
     let mut ops = Vec::new();
+    let mut query = None;
+    let mut account_dump = None;
 
     // Parse command line arguments
     while let Some(arg) = prog.next() {
@@ -121,6 +180,20 @@ fn parse_impl(prog: &mut Prog) -> Result<Opts, bool> {
             prog.parse_flag(&arg, "-b", "--bump", u8::from_str)?
         {
             bump = Some(value);
+        } else if let Some(path) =
+            prog.parse_flag(&arg, "-d", "--account-dump", |path| {
+                Result::<_, core::convert::Infallible>::Ok(PathBuf::from(path))
+            })?
+        {
+            account_dump = Some(path);
+        } else if let Some(value) = parse_query(prog, &arg).map_err(|err| {
+            eprintln!("{prog}: {arg}: {err}");
+            false
+        })? {
+            if query.replace(value).is_some() {
+                eprintln!("{prog}: at most one query can be given");
+                return Err(false);
+            }
         } else {
             ops.push(parse_op(prog, &arg).map_err(|err| {
                 eprintln!("{prog}: {arg}: {err}");
@@ -129,12 +202,28 @@ fn parse_impl(prog: &mut Prog) -> Result<Opts, bool> {
         }
     }
 
-    // Get Solana config.
+    if query.is_some() && !ops.is_empty() {
+        eprintln!("{prog}: queries can’t be mixed with other operations");
+        return Err(false);
+    }
+
+    // Get Solana config.  Queries don’t need a key pair and offline queries
+    // don’t need RPC URL so don’t require Solana config in those cases.
     let mut config = None;
-    let rpc_url =
-        rpc_url.map_or_else(|| get_default_rpc_url(prog, &mut config), Ok)?;
-    let keypair =
-        keypair.map_or_else(|| get_default_keypair(prog, &mut config), Ok)?;
+    let needs_rpc =
+        query.as_ref().map_or(true, |q| q.needs_rpc(account_dump.is_some()));
+    let rpc_url = match rpc_url {
+        Some(url) => Some(url),
+        None if needs_rpc => Some(get_default_rpc_url(prog, &mut config)?),
+        None => None,
+    };
+    let keypair = match keypair {
+        Some(keypair) => Some(keypair),
+        None if query.is_none() => {
+            Some(get_default_keypair(prog, &mut config)?)
+        }
+        None => None,
+    };
 
     // Get account addresses.
     let (root_account, root_bump) =
@@ -164,6 +253,8 @@ fn parse_impl(prog: &mut Prog) -> Result<Opts, bool> {
         root_account,
         witness_account,
         data,
+        query,
+        account_dump,
     })
 }
 
@@ -278,10 +369,7 @@ fn parse_op(prog: &mut Prog, arg: &str) -> Result<api::OwnedOp, &'static str> {
     };
 
     let key = prog.next().ok_or("missing <key>")?;
-    if key.is_empty() || key.len() > 255 {
-        return Err("<key> must be between 1 and 255 bytes");
-    }
-    let key = key.into_bytes();
+    let key = parse_key(key)?;
 
     Ok(match kind {
         api::OpDiscriminants::Set => {
@@ -294,6 +382,72 @@ fn parse_op(prog: &mut Prog, arg: &str) -> Result<api::OwnedOp, &'static str> {
     })
 }
 
+/// Parses a query from command line arguments.
+///
+/// Returns `Ok(None)` if `arg` isn’t a query.
+fn parse_query(
+    prog: &mut Prog,
+    arg: &str,
+) -> Result<Option<Query>, &'static str> {
+    Ok(Some(match arg {
+        "prove" => {
+            let key = prog.next().ok_or("missing <key>")?;
+            Query::Prove(parse_key(key)?)
+        }
+        "verify" => {
+            let root = prog.next().ok_or("missing <root>")?;
+            let key = prog.next().ok_or("missing <key>")?;
+            let value = prog.next().ok_or("missing <value>")?;
+            let proof = prog.next().ok_or("missing <proof>")?;
+            let value = if value == "null" {
+                None
+            } else {
+                Some(parse_hash(&value).ok_or("invalid <value>")?)
+            };
+            Query::Verify {
+                root: parse_hash(&root).ok_or("invalid <root>")?,
+                key: parse_key(key)?,
+                value,
+                proof: parse_base64(&proof).ok_or("invalid <proof>")?,
+            }
+        }
+        "dump-keys" => {
+            let prefix = prog.next().unwrap_or_default();
+            if prefix.len() > 255 {
+                return Err("<prefix> must be at most 255 bytes");
+            }
+            Query::DumpKeys(prefix.into_bytes())
+        }
+        "export" => {
+            let path = prog.next().ok_or("missing <path>")?;
+            Query::Export(path.into())
+        }
+        "import" => {
+            let snapshot = prog.next().ok_or("missing <path>")?.into();
+            Query::Import { snapshot, output: prog.next().map(PathBuf::from) }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Decodes base64-encoded value.
+fn parse_base64(value: &str) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD.decode(value).ok()
+}
+
+/// Decodes base64-encoded hash.
+fn parse_hash(value: &str) -> Option<CryptoHash> {
+    CryptoHash::try_from(parse_base64(value)?.as_slice()).ok()
+}
+
+/// Parses a trie key checking that it’s between 1 and 255 bytes.
+fn parse_key(key: String) -> Result<Vec<u8>, &'static str> {
+    if key.is_empty() || key.len() > 255 {
+        return Err("<key> must be between 1 and 255 bytes");
+    }
+    Ok(key.into_bytes())
+}
+
 
 /// Loads Solana CLI configuration file if not already loaded.
 fn load_solana_config<'a>(
@@ -41,9 +41,28 @@ where
 }
 
 fn run(opts: &args::Opts) -> Result<ExitCode, Error> {
+    match opts.query {
+        Some(args::Query::Prove(ref key)) => return prove(opts, key),
+        Some(args::Query::Verify {
+            ref root,
+            ref key,
+            ref value,
+            ref proof,
+        }) => return verify(root, key, value.as_ref(), proof),
+        Some(args::Query::DumpKeys(ref prefix)) => {
+            return dump_keys(opts, prefix)
+        }
+        Some(args::Query::Export(ref path)) => return export(opts, path),
+        Some(args::Query::Import { ref snapshot, ref output }) => {
+            return import(snapshot, output.as_deref())
+        }
+        None => (),
+    }
+
+    let keypair = opts.keypair.as_ref().ok_or(Error::Msg("no keypair"))?;
+
     // Connect
-    println!("Connecting to {}...", opts.rpc_url);
-    let client = solana_client::rpc_client::RpcClient::new(&opts.rpc_url);
+    let client = connect(opts)?;
     let blockhash = client.get_latest_blockhash()?;
     println!("Latest blockhash: {blockhash}");
 
@@ -53,7 +72,7 @@ fn run(opts: &args::Opts) -> Result<ExitCode, Error> {
     let instruction = solana_sdk::instruction::Instruction {
         program_id: opts.program_id,
         accounts: vec![
-            acc_meta(opts.keypair.pubkey(), true, true),
+            acc_meta(keypair.pubkey(), true, true),
             acc_meta(opts.root_account, false, true),
             acc_meta(opts.witness_account, false, true),
             acc_meta(solana_sdk::system_program::ID, false, false),
@@ -66,7 +85,7 @@ fn run(opts: &args::Opts) -> Result<ExitCode, Error> {
     ][(opts.priority == 0) as usize..];
     let message = solana_sdk::message::Message::new_with_blockhash(
         instructions,
-        Some(&opts.keypair.pubkey()),
+        Some(&keypair.pubkey()),
         &blockhash,
     );
     let mut tx = solana_sdk::transaction::Transaction::new_unsigned(message);
@@ -87,7 +106,7 @@ fn run(opts: &args::Opts) -> Result<ExitCode, Error> {
     println!("Sending transaction to {program_id}...");
     let blockhash = client.get_latest_blockhash()?;
     println!("Latest blockhash: {blockhash}");
-    tx.sign(&[&opts.keypair], blockhash);
+    tx.sign(&[keypair], blockhash);
     let sig = client.send_and_confirm_transaction(&tx)?;
     println!("Signature: {sig}");
 
@@ -152,12 +171,159 @@ fn run(opts: &args::Opts) -> Result<ExitCode, Error> {
     Ok(ExitCode::SUCCESS)
 }
 
+/// Connects to the cluster.
+fn connect(
+    opts: &args::Opts,
+) -> Result<solana_client::rpc_client::RpcClient, Error> {
+    let rpc_url = opts.rpc_url.as_ref().ok_or(Error::Msg("no RPC URL"))?;
+    eprintln!("Connecting to {}...", rpc_url);
+    Ok(solana_client::rpc_client::RpcClient::new(rpc_url))
+}
+
+/// Loads the trie root account data.
+///
+/// If account dump was given, reads the account data from the file.
+/// Otherwise, fetches the account from the cluster.
+fn load_account_data(opts: &args::Opts) -> Result<Vec<u8>, Error> {
+    Ok(if let Some(path) = opts.account_dump.as_ref() {
+        eprintln!("Reading root account from {}...", path.display());
+        std::fs::read(path)?
+    } else {
+        let client = connect(opts)?;
+        eprintln!("Fetching root account {}...", opts.root_account);
+        let account = client.get_account(&opts.root_account)?;
+        if account.owner != opts.program_id {
+            return Err("root account not owned by the wittrie program".into());
+        }
+        account.data
+    })
+}
+
+/// Loads the trie root account.
+fn load_trie(
+    opts: &args::Opts,
+) -> Result<solana_trie::TrieAccount<Vec<u8>, ()>, Error> {
+    solana_trie::TrieAccount::new(load_account_data(opts)?)
+        .ok_or(Error::Msg("unable to decode trie account"))
+}
+
+/// Loads the trie root account and prints proof for given key.
+///
+/// The proof is printed as a JSON object with the trie root hash, the key, the
+/// value (or `null` if the key is not present) and Borsh-serialised
+/// `sealable_trie::proof::Proof`, all binary values encoded using base64.  The
+/// proof is a membership proof if the key is present and a non-membership
+/// proof otherwise.
+fn prove(opts: &args::Opts, key: &[u8]) -> Result<ExitCode, Error> {
+    use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+
+    let trie = load_trie(opts)?;
+    let (value, proof) = trie.prove(key)?;
+    if !proof.verify(trie.hash(), key, value.as_ref()) {
+        return Err("generated proof failed verification".into());
+    }
+    let proof = borsh::to_vec(&proof).unwrap();
+
+    let value = value.map_or_else(
+        || "null".to_string(),
+        |value| format!("\"{}\"", BASE64_ENGINE.encode(value.as_slice())),
+    );
+    println!("{{");
+    println!(
+        "  \"root\": \"{}\",",
+        BASE64_ENGINE.encode(trie.hash().as_slice())
+    );
+    println!("  \"key\": \"{}\",", BASE64_ENGINE.encode(key));
+    println!("  \"value\": {value},");
+    println!("  \"proof\": \"{}\"", BASE64_ENGINE.encode(proof));
+    println!("}}");
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Verifies Borsh-serialised proof for given key and value.
+///
+/// `value` is `None` for non-membership proofs.  Doesn’t need access to the
+/// cluster.
+fn verify(
+    root: &lib::hash::CryptoHash,
+    key: &[u8],
+    value: Option<&lib::hash::CryptoHash>,
+    proof: &[u8],
+) -> Result<ExitCode, Error> {
+    let proof = borsh::from_slice::<sealable_trie::proof::Proof>(proof)
+        .map_err(|_| Error::Msg("unable to decode proof"))?;
+    if proof.verify(root, key, value) {
+        println!("Proof is valid");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("Proof is invalid");
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Loads the trie root account and prints all keys with given prefix.
+///
+/// Each line contains base64-encoded key and value hash.  Sealed entries are
+/// skipped since their values are no longer known.
+fn dump_keys(opts: &args::Opts, prefix: &[u8]) -> Result<ExitCode, Error> {
+    use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+
+    let trie = load_trie(opts)?;
+    println!("Root: {}", BASE64_ENGINE.encode(trie.hash().as_slice()));
+    for (key, value) in trie.iter_prefix(prefix)? {
+        println!(
+            "{} {}",
+            BASE64_ENGINE.encode(key),
+            BASE64_ENGINE.encode(value.as_slice())
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Loads the trie root account and saves its snapshot to given file.
+///
+/// See [`solana_trie::snapshot`] for description of the format.
+fn export(
+    opts: &args::Opts,
+    path: &std::path::Path,
+) -> Result<ExitCode, Error> {
+    let snapshot = solana_trie::snapshot::export(&load_account_data(opts)?)?;
+    std::fs::write(path, &snapshot)?;
+    println!("Wrote {} bytes to {}", snapshot.len(), path.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Verifies trie snapshot and optionally writes it out as account data.
+///
+/// Doesn’t need access to the cluster.  Account data written to `output` can
+/// be passed with `--account-dump` to other queries.
+fn import(
+    snapshot: &std::path::Path,
+    output: Option<&std::path::Path>,
+) -> Result<ExitCode, Error> {
+    use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+
+    let data = solana_trie::snapshot::import(&std::fs::read(snapshot)?)?;
+    let root = solana_trie::decode_root_hash(&data).unwrap();
+    println!("Snapshot is valid");
+    println!("Root: {}", BASE64_ENGINE.encode(root.as_slice()));
+    if let Some(output) = output {
+        std::fs::write(output, &data)?;
+        println!("Wrote {} bytes to {}", data.len(), output.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
 #[derive(derive_more::From, derive_more::Display)]
 enum Error {
     None,
     Msg(&'static str),
     Client(solana_client::client_error::ClientError),
     B64Decode(base64::DecodeError),
+    IO(std::io::Error),
+    Trie(sealable_trie::Error),
+    Snapshot(solana_trie::snapshot::Error),
 }
 
 impl Error {
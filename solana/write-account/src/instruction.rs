@@ -162,6 +162,60 @@ pub fn free(
     })
 }
 
+/// Generates instruction data for Commit operation.
+///
+/// The instruction verifies that the Write account starts with given `data`
+/// and fails otherwise.  If the data matches, its hash is stored right after
+/// it marking the data as verified.  Readers of the Write account (such as
+/// Solana IBC’s instruction data account) reject data which hasn’t been
+/// verified so this must be called once the upload completes.
+///
+/// `seed` and `bump` specifies seed and bump of the Write PDA.  If
+/// `write_account` is not given, it’s going to be generated from provided
+/// Write program id, Payer account, seed and bump.
+pub fn commit(
+    write_program_id: Pubkey,
+    payer: Pubkey,
+    write_account: Option<Pubkey>,
+    seed: &[u8],
+    bump: u8,
+    data: &[u8],
+) -> Result<Instruction> {
+    let length = u32::try_from(data.len())
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let hash = solana_program::hash::hash(data);
+    let data = [
+        /* discriminant: */ b"\x01",
+        /* seed_len: */ &[check_seed(seed)?][..],
+        /* seed: */ seed,
+        /* bump: */ &[bump],
+        /* length: */ &length.to_le_bytes()[..],
+        /* hash: */ hash.as_ref(),
+    ]
+    .concat();
+
+    let write_account = match write_account {
+        None => Pubkey::create_program_address(
+            &[payer.as_ref(), seed, &[bump]],
+            &write_program_id,
+        )?,
+        Some(acc) => acc,
+    };
+
+    Ok(Instruction {
+        program_id: write_program_id,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(write_account, false),
+            AccountMeta::new_readonly(
+                solana_program::system_program::ID,
+                false,
+            ),
+        ],
+        data,
+    })
+}
+
 /// Checks that seed is below the maximum length; returns length cast to `u8`.
 fn check_seed(seed: &[u8]) -> Result<u8> {
     if seed.len() <= solana_program::pubkey::MAX_SEED_LEN {
@@ -50,19 +50,52 @@ solana_program::entrypoint!(process_instruction);
 ///
 /// Note: `data` may be empty in which case the instruction will just create or
 /// resize the Write account.
+///
+/// If the first byte of the instruction is one rather than zero, executes
+/// a Commit operation.  The instruction is then represented by the following
+/// structure (with accounts same as above):
+///
+/// ```ignore
+/// #[repr(C, packed)]
+/// struct CommitInstruction {
+///     always_one: u8,  // always 1u8,
+///     seed_len: u8,  // at most 31
+///     seed: [u8; seed_len],
+///     bump: u8,
+///     length: u32,
+///     hash: [u8; 32],
+/// }
+/// ```
+///
+/// The operation calculates SHA-256 hash of the first `length` bytes of the
+/// Write account and fails if it doesn’t match `hash`.  Otherwise, it marks
+/// the data as verified by storing the hash in the 32 bytes following the
+/// data (growing the account if necessary; System program is needed in that
+/// case).  Programs reading the account (such as Solana IBC’s instruction data
+/// account) recompute the hash and reject the data unless it matches, so that
+/// data with missing or corrupted chunks is never used.  Any later change to
+/// the data invalidates it.
 fn process_instruction<'a>(
     program_id: &Pubkey,
     accounts: &'a [AccountInfo],
     mut instruction: &'a [u8],
 ) -> Result {
-    if read(&mut instruction, u8::from_le_bytes)? != 0 {
-        return Err(ProgramError::InvalidInstructionData);
-    }
-    let accounts = Accounts::get(program_id, accounts, &mut instruction)?;
-    if instruction.is_empty() {
-        handle_free(accounts)
-    } else {
-        handle_write(program_id, accounts, instruction)
+    match read(&mut instruction, u8::from_le_bytes)? {
+        0 => {
+            let accounts =
+                Accounts::get(program_id, accounts, &mut instruction)?;
+            if instruction.is_empty() {
+                handle_free(accounts)
+            } else {
+                handle_write(program_id, accounts, instruction)
+            }
+        }
+        1 => {
+            let accounts =
+                Accounts::get(program_id, accounts, &mut instruction)?;
+            handle_commit(program_id, accounts, instruction)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
     }
 }
 
@@ -152,6 +185,54 @@ fn setup_write_account(
 }
 
 
+/// Handles Commit operation.
+///
+/// Verifies that SHA-256 hash of the first `length` bytes of the Write account
+/// equals the one given in the instruction and stores the hash after the data
+/// marking it as verified.
+fn handle_commit(
+    program_id: &Pubkey,
+    accounts: Accounts,
+    mut data: &[u8],
+) -> Result {
+    let length = read_usize(&mut data, u32::from_le_bytes)?;
+    let hash = read(&mut data, solana_program::hash::Hash::new_from_array)?;
+    if !data.is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if accounts.write.lamports() == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let size = length
+        .checked_add(solana_program::hash::HASH_BYTES)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    setup_write_account(program_id, accounts, size)?;
+    commit(&mut accounts.write.try_borrow_mut_data()?, length, &hash)
+}
+
+/// Verifies hash of the first `length` bytes of `account` and stores it
+/// right after them.
+///
+/// `account` must be at least `length + 32` bytes long.
+fn commit(
+    account: &mut [u8],
+    length: usize,
+    hash: &solana_program::hash::Hash,
+) -> Result {
+    let end = length
+        .checked_add(solana_program::hash::HASH_BYTES)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let account =
+        account.get_mut(..end).ok_or(ProgramError::AccountDataTooSmall)?;
+    let (payload, tail) = account.split_at_mut(length);
+    if solana_program::hash::hash(payload) != *hash {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    tail.copy_from_slice(hash.as_ref());
+    Ok(())
+}
+
+
 /// Handles Free operation.
 fn handle_free(accounts: Accounts) -> Result {
     {
@@ -282,3 +363,46 @@ fn read_slice<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
     *bytes = tail;
     Ok(head)
 }
+
+
+#[test]
+fn test_commit() {
+    use solana_program::hash::{hash, Hash, HASH_BYTES};
+
+    let payload = b"\x05\0\0\0hello";
+    let good = hash(payload);
+
+    // Hash is stored right after the data; trailing bytes are untouched.
+    let mut account = [&payload[..], &[0xff; HASH_BYTES + 1][..]].concat();
+    commit(&mut account, payload.len(), &good).unwrap();
+    assert_eq!(payload, &account[..payload.len()]);
+    assert_eq!(good.as_ref(), &account[payload.len()..][..HASH_BYTES]);
+    assert_eq!(0xff, account[account.len() - 1]);
+
+    // Mismatching hash leaves the account as is.
+    let mut account = [&payload[..], &[0; HASH_BYTES][..]].concat();
+    assert_eq!(
+        Err(ProgramError::InvalidAccountData),
+        commit(&mut account, payload.len(), &Hash::default())
+    );
+    assert_eq!([0; HASH_BYTES], account[payload.len()..]);
+
+    // Corrupted data is rejected.
+    let mut account = [&payload[..], &[0; HASH_BYTES][..]].concat();
+    account[4] = b'j';
+    assert_eq!(
+        Err(ProgramError::InvalidAccountData),
+        commit(&mut account, payload.len(), &good)
+    );
+
+    // No room for the hash.
+    let mut account = [&payload[..], &[0; HASH_BYTES - 1][..]].concat();
+    assert_eq!(
+        Err(ProgramError::AccountDataTooSmall),
+        commit(&mut account, payload.len(), &good)
+    );
+    assert_eq!(
+        Err(ProgramError::AccountDataTooSmall),
+        commit(&mut account, payload.len() + HASH_BYTES, &good)
+    );
+}
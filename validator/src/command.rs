@@ -2,9 +2,6 @@ use std::fmt::{Debug, Display};
 use std::fs;
 use std::str::FromStr;
 
-use anchor_client::solana_sdk::signature::{
-    read_keypair_file, Keypair, Signer,
-};
 use anchor_lang::solana_program::pubkey::Pubkey;
 use clap::{arg, command, Args, Parser, Subcommand};
 use dialoguer::theme::ColorfulTheme;
@@ -12,8 +9,13 @@ use dialoguer::Input;
 use log::LevelFilter;
 use serde::de::Error as SerdeError;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sigverify::ed25519::SecretKey;
+use zeroize::Zeroizing;
 
+use crate::rotate::rotate_key;
+use crate::signer::{LocalSigner, RemoteSignerConfig};
 use crate::stake::stake;
+use crate::timeout::TimeoutConfig;
 use crate::utils::{config_file, setup_logging};
 use crate::validator::run_validator;
 
@@ -23,21 +25,57 @@ pub struct Config {
     pub ws_url: String,
     pub program_id: String,
     pub keypair: InnerKeypair,
+    /// Key used before the last key rotation.
+    ///
+    /// Until the epoch changes, the previous key may still be in the
+    /// validators set so the validator signs blocks with either key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_keypair: Option<InnerKeypair>,
     #[serde(default)]
     pub priority_fees: u64,
     pub log_level: String,
+    /// Address to serve Prometheus metrics on, e.g. `127.0.0.1:9100`.
+    ///
+    /// Metrics are not served if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_addr: Option<String>,
+    /// Amount of accrued block rewards in lamports at which the validator
+    /// claims the rewards and stakes them.
+    ///
+    /// Rewards are not restaked automatically if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restake_threshold: Option<u64>,
+    /// Remote signing services holding additional block signing keys.
+    ///
+    /// The validator signs pending blocks with every configured key which is
+    /// in the block’s validators set.  `keypair` is still needed to pay
+    /// transaction fees and to claim and restake block rewards.  See
+    /// [`crate::signer`] for description of the protocol.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote_signers: Vec<RemoteSignerConfig>,
+    /// Automatic timing out of packets the counterparty hasn’t received.
+    ///
+    /// Packets are not timed out if not set.  See [`crate::timeout`] for
+    /// description of the configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeouts: Option<TimeoutConfig>,
 }
 
+/// Validator’s key stored in the configuration file.
+///
+/// It’s serialised as a 64-byte `secret || public` keypair (same as in Solana
+/// keypair files) but held in memory as a [`SecretKey`] which is zeroed when
+/// dropped.
 #[derive(derive_more::From, derive_more::Into)]
-pub struct InnerKeypair(Keypair);
+pub struct InnerKeypair(SecretKey);
 
 impl Serialize for InnerKeypair {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let bytes = &self.0.to_bytes()[..];
-        serde_bytes::Bytes::new(bytes).serialize(serializer)
+        let bytes = self.0.to_keypair_bytes();
+        serde_bytes::Bytes::new(&bytes[..]).serialize(serializer)
     }
 }
 
@@ -47,24 +85,47 @@ impl<'d> Deserialize<'d> for InnerKeypair {
         D: Deserializer<'d>,
     {
         let bytes = <serde_bytes::ByteBuf>::deserialize(deserializer)?;
-        Keypair::from_bytes(bytes.as_ref())
+        let bytes = Zeroizing::new(bytes.into_vec());
+        SecretKey::from_keypair_bytes(bytes.as_slice())
             .map(Self)
-            .map_err(SerdeError::custom)
+            .map_err(|_| SerdeError::custom("invalid keypair"))
     }
 }
 
+impl InnerKeypair {
+    /// Returns the public key of the keypair.
+    pub fn pubkey(&self) -> Pubkey { self.0.pubkey().into() }
+
+    /// Converts the keypair into a signer.
+    pub fn into_signer(self) -> LocalSigner { LocalSigner::from(self.0) }
+}
+
 impl Display for InnerKeypair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Display::fmt(&self.0.pubkey(), f)
+        Display::fmt(&self.pubkey(), f)
     }
 }
 
 impl Debug for InnerKeypair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("PublicKey").field(&self.0.pubkey()).finish()
+        f.debug_tuple("PublicKey").field(&self.pubkey()).finish()
     }
 }
 
+/// Reads a Solana keypair file, i.e. a JSON array of 64 bytes.
+///
+/// Unlike `read_keypair_file` from Solana SDK, all intermediate copies of the
+/// key material are zeroed.
+fn read_keypair_file(path: &str) -> SecretKey {
+    let data = Zeroizing::new(
+        fs::read_to_string(path).expect("Unable to read keypair file"),
+    );
+    let bytes: Vec<u8> =
+        serde_json::from_str(&data).expect("Invalid keypair file");
+    let bytes = Zeroizing::new(bytes);
+    SecretKey::from_keypair_bytes(&bytes).expect("Invalid keypair file")
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[command(arg_required_else_help(true))]
@@ -82,6 +143,8 @@ enum Commands {
     Init(InitArgs),
     /// Command to stake on the validator
     Stake(StakeArgs),
+    /// Command to replace the validator’s key keeping its stake
+    RotateKey(RotateKeyArgs),
 }
 
 #[derive(Args, Debug)]
@@ -108,6 +171,14 @@ struct RunArgs {
     /// Log Level
     #[arg(short, long)]
     log_level: Option<LevelFilter>,
+
+    /// Address to serve Prometheus metrics on
+    #[arg(long)]
+    metrics_addr: Option<String>,
+
+    /// Accrued block rewards in lamports at which to claim and restake them
+    #[arg(long)]
+    restake_threshold: Option<u64>,
 }
 
 #[derive(Args, Debug)]
@@ -134,6 +205,14 @@ struct InitArgs {
     /// Log Level
     #[arg(short, long)]
     log_level: Option<LevelFilter>,
+
+    /// Address to serve Prometheus metrics on
+    #[arg(long)]
+    metrics_addr: Option<String>,
+
+    /// Accrued block rewards in lamports at which to claim and restake them
+    #[arg(long)]
+    restake_threshold: Option<u64>,
 }
 
 #[derive(Args, Debug)]
@@ -170,6 +249,20 @@ struct StakeArgs {
     log_level: Option<LevelFilter>,
 }
 
+#[derive(Args, Debug)]
+struct RotateKeyArgs {
+    /// Path to the new private key
+    #[arg(long)]
+    new_keypair_path: String,
+
+    #[arg(long)]
+    priority_fees: Option<u64>,
+
+    /// Log Level
+    #[arg(short, long)]
+    log_level: Option<LevelFilter>,
+}
+
 #[derive(Clone, Debug)]
 pub enum Values {
     Yes,
@@ -201,15 +294,14 @@ pub fn process_command() {
     match args.command {
         Commands::Run(cmd) => {
             let config_file = config_file();
-            let config_data = fs::read_to_string(config_file).expect(
-                "Failed to read config file; make sure you’ve run init \
-                 command first.",
-            );
+            let config_data =
+                Zeroizing::new(fs::read_to_string(config_file).expect(
+                    "Failed to read config file; make sure you’ve run init \
+                     command first.",
+                ));
             let default_config: Config = toml::from_str(&config_data).unwrap();
             let keypair = if let Some(keypair_path) = cmd.keypair_path {
-                let keypair = read_keypair_file(keypair_path)
-                    .expect("Unable to read keypair file");
-                keypair.into()
+                read_keypair_file(&keypair_path).into()
             } else {
                 default_config.keypair
             };
@@ -218,11 +310,18 @@ pub fn process_command() {
                 ws_url: cmd.ws_url.unwrap_or(default_config.ws_url),
                 program_id: cmd.program_id.unwrap_or(default_config.program_id),
                 keypair,
+                previous_keypair: default_config.previous_keypair,
                 priority_fees: cmd.priority_fees.unwrap_or(0),
                 log_level: cmd
                     .log_level
                     .unwrap_or(LevelFilter::Info)
                     .to_string(),
+                metrics_addr: cmd.metrics_addr.or(default_config.metrics_addr),
+                restake_threshold: cmd
+                    .restake_threshold
+                    .or(default_config.restake_threshold),
+                remote_signers: default_config.remote_signers,
+                timeouts: default_config.timeouts,
             };
             setup_logging(LevelFilter::from_str(&config.log_level).unwrap());
             run_validator(config)
@@ -240,35 +339,38 @@ pub fn process_command() {
                 }
                 log::info!("Overwriting config file");
             }
-            let keypair = read_keypair_file(&cmd.keypair_path)
-                .expect("Unable to read keypair file");
-            // let keypair = keypair.to_bytes().to_vec();
+            let keypair = read_keypair_file(&cmd.keypair_path);
             let config = Config {
                 rpc_url: cmd.rpc_url,
                 ws_url: cmd.ws_url,
                 program_id: cmd.program_id,
                 keypair: keypair.into(),
+                previous_keypair: None,
                 priority_fees: cmd.priority_fees.unwrap_or(0),
                 log_level: cmd
                     .log_level
                     .unwrap_or(LevelFilter::Info)
                     .to_string(),
+                metrics_addr: cmd.metrics_addr,
+                restake_threshold: cmd.restake_threshold,
+                remote_signers: Vec::new(),
+                timeouts: None,
             };
-            let toml_in_string = toml::to_string(&config).unwrap();
-            fs::write(config_file, toml_in_string).unwrap();
+            let toml_in_string =
+                Zeroizing::new(toml::to_string(&config).unwrap());
+            fs::write(config_file, toml_in_string.as_bytes()).unwrap();
             log::info!("New Config {:?}", config);
         }
         Commands::Stake(cmd) => {
             let config_file = config_file();
-            let config_data = fs::read_to_string(config_file).expect(
-                "Failed to read config file; make sure you’ve run init \
-                 command first.",
-            );
+            let config_data =
+                Zeroizing::new(fs::read_to_string(config_file).expect(
+                    "Failed to read config file; make sure you’ve run init \
+                     command first.",
+                ));
             let default_config: Config = toml::from_str(&config_data).unwrap();
             let keypair = if let Some(keypair_path) = cmd.keypair_path {
-                let keypair = read_keypair_file(keypair_path)
-                    .expect("Unable to read keypair file");
-                keypair.into()
+                read_keypair_file(&keypair_path).into()
             } else {
                 default_config.keypair
             };
@@ -277,16 +379,44 @@ pub fn process_command() {
                 ws_url: cmd.ws_url.unwrap_or(default_config.ws_url),
                 program_id: cmd.program_id.unwrap_or(default_config.program_id),
                 keypair,
+                previous_keypair: default_config.previous_keypair,
                 priority_fees: cmd.priority_fees.unwrap_or(0),
                 log_level: cmd
                     .log_level
                     .unwrap_or(LevelFilter::Info)
                     .to_string(),
+                metrics_addr: default_config.metrics_addr,
+                restake_threshold: default_config.restake_threshold,
+                remote_signers: default_config.remote_signers,
+                timeouts: default_config.timeouts,
             };
             setup_logging(LevelFilter::from_str(&config.log_level).unwrap());
             let token_mint = Pubkey::from_str(&cmd.token_mint).unwrap();
             stake(config, cmd.amount, token_mint);
         }
+        Commands::RotateKey(cmd) => {
+            let config_file = config_file();
+            let config_data =
+                Zeroizing::new(fs::read_to_string(&config_file).expect(
+                    "Failed to read config file; make sure you’ve run init \
+                     command first.",
+                ));
+            let mut config: Config = toml::from_str(&config_data).unwrap();
+            if let Some(priority_fees) = cmd.priority_fees {
+                config.priority_fees = priority_fees;
+            }
+            setup_logging(
+                cmd.log_level.unwrap_or(
+                    LevelFilter::from_str(&config.log_level).unwrap(),
+                ),
+            );
+            let new_keypair = read_keypair_file(&cmd.new_keypair_path);
+            let config = rotate_key(config, new_keypair);
+            let toml_in_string =
+                Zeroizing::new(toml::to_string(&config).unwrap());
+            fs::write(config_file, toml_in_string.as_bytes()).unwrap();
+            log::info!("New Config {:?}", config);
+        }
     }
 }
 
@@ -1,5 +1,9 @@
 mod command;
+mod metrics;
+mod rotate;
+mod signer;
 mod stake;
+mod timeout;
 mod utils;
 mod validator;
 
@@ -0,0 +1,170 @@
+//! Prometheus metrics of the validator.
+//!
+//! If `metrics_addr` is configured, the validator serves its metrics over HTTP
+//! on that address in Prometheus text exposition format.  Every request gets
+//! the same response regardless of the path so scrapers can use the default
+//! `/metrics` path.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Counters and gauges describing validator’s operation.
+///
+/// Updated by the validator loop and read by the HTTP server thread.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Number of blocks the validator has signed.
+    blocks_signed: AtomicU64,
+
+    /// Number of blocks finalised without validator’s signature even though
+    /// the validator was in the block’s validators set.
+    missed_blocks: AtomicU64,
+
+    /// Sum of signature latencies in milliseconds.
+    ///
+    /// Latency is measured from the moment the validator notices a pending
+    /// block to the moment the signature transaction is confirmed.
+    signature_latency_ms_sum: AtomicU64,
+
+    /// Latency of the last signature in milliseconds.
+    last_signature_latency_ms: AtomicU64,
+
+    /// Validator’s current stake as a candidate.
+    stake: AtomicU64,
+
+    /// Block rewards accrued by the validator which haven’t been claimed yet.
+    accrued_rewards: AtomicU64,
+
+    /// Number of times accrued rewards were claimed and restaked.
+    restakes: AtomicU64,
+
+    /// Number of packets timed out by the validator.
+    packets_timed_out: AtomicU64,
+}
+
+impl Metrics {
+    /// Records a successfully submitted block signature.
+    pub fn block_signed(&self, latency: Duration) {
+        let latency = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+        self.blocks_signed.fetch_add(1, Ordering::Relaxed);
+        self.signature_latency_ms_sum.fetch_add(latency, Ordering::Relaxed);
+        self.last_signature_latency_ms.store(latency, Ordering::Relaxed);
+    }
+
+    /// Records a block which was finalised without validator’s signature.
+    pub fn block_missed(&self) {
+        self.missed_blocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records successful claim and restake of accrued rewards.
+    pub fn restaked(&self) { self.restakes.fetch_add(1, Ordering::Relaxed); }
+
+    /// Records packets timed out by the validator.
+    pub fn packets_timed_out(&self, count: u64) {
+        self.packets_timed_out.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Sets current stake and accrued rewards of the validator.
+    pub fn set_balances(&self, stake: u128, accrued_rewards: u128) {
+        let saturate = |value: u128| u64::try_from(value).unwrap_or(u64::MAX);
+        self.stake.store(saturate(stake), Ordering::Relaxed);
+        self.accrued_rewards
+            .store(saturate(accrued_rewards), Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let get = |value: &AtomicU64| value.load(Ordering::Relaxed);
+        let ms_to_secs = |value: u64| value as f64 / 1000.0;
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            ));
+        };
+        metric(
+            "validator_blocks_signed_total",
+            "counter",
+            "Number of blocks signed by the validator.",
+            get(&self.blocks_signed).to_string(),
+        );
+        metric(
+            "validator_missed_blocks_total",
+            "counter",
+            "Number of blocks finalised without the validator’s signature.",
+            get(&self.missed_blocks).to_string(),
+        );
+        metric(
+            "validator_signature_latency_seconds_sum",
+            "counter",
+            "Total time between noticing pending blocks and signing them.",
+            ms_to_secs(get(&self.signature_latency_ms_sum)).to_string(),
+        );
+        metric(
+            "validator_last_signature_latency_seconds",
+            "gauge",
+            "Time between noticing the last signed block and signing it.",
+            ms_to_secs(get(&self.last_signature_latency_ms)).to_string(),
+        );
+        metric(
+            "validator_stake",
+            "gauge",
+            "Current stake of the validator.",
+            get(&self.stake).to_string(),
+        );
+        metric(
+            "validator_accrued_rewards_lamports",
+            "gauge",
+            "Block rewards accrued by the validator and not yet claimed.",
+            get(&self.accrued_rewards).to_string(),
+        );
+        metric(
+            "validator_restakes_total",
+            "counter",
+            "Number of times accrued rewards were claimed and restaked.",
+            get(&self.restakes).to_string(),
+        );
+        metric(
+            "validator_packets_timed_out_total",
+            "counter",
+            "Number of IBC packets timed out by the validator.",
+            get(&self.packets_timed_out).to_string(),
+        );
+        out
+    }
+}
+
+/// Starts a thread serving the metrics over HTTP on given address.
+pub fn serve(addr: &str, metrics: Arc<Metrics>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!("Serving metrics on {}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let res = stream.and_then(|stream| respond(stream, &metrics));
+            if let Err(err) = res {
+                log::debug!("Failed serving metrics: {err}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Responds to a single HTTP request with rendered metrics.
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // The response doesn’t depend on the request so we only need to consume
+    // the request before responding.
+    let mut buf = [0; 1024];
+    let _ = stream.read(&mut buf)?;
+    let body = metrics.render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; \
+         version=0.0.4\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n{body}",
+        body.len()
+    )
+}
@@ -0,0 +1,100 @@
+use std::rc::Rc;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::{Client, ClientError, Cluster};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use sigverify::ed25519::SecretKey;
+use solana_ibc::chain::{ChainData, PubKey};
+use solana_ibc::{accounts, instruction};
+
+use crate::command::Config;
+use crate::signer::LocalSigner;
+use crate::utils;
+
+/// Replaces validator’s key with `new_key` keeping its stake.
+///
+/// Returns updated configuration with `new_key` as the key and the old
+/// key as the previous key.  If the validator is in the current epoch, it
+/// remains a validator with the old key until the epoch changes so the
+/// validator needs to keep signing with both keys during the transition.
+pub fn rotate_key(config: Config, new_key: SecretKey) -> Config {
+    let old_key = SecretKey::from(config.keypair);
+    let validator = Rc::new(LocalSigner::from(old_key.clone()));
+    let client = Client::new_with_options(
+        Cluster::from_str(&config.rpc_url).expect("Invalid cluster"),
+        validator.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program_id = Pubkey::from_str(&config.program_id).unwrap();
+    let program = client.program(program_id).unwrap();
+
+    let trie =
+        Pubkey::find_program_address(&[solana_ibc::TRIE_SEED], &program_id).0;
+    let chain =
+        Pubkey::find_program_address(&[solana_ibc::CHAIN_SEED], &program_id).0;
+    #[cfg(feature = "witness")]
+    let witness = Pubkey::find_program_address(
+        &[solana_ibc::WITNESS_SEED, trie.as_ref()],
+        &program_id,
+    )
+    .0;
+
+    let chain_account: ChainData = program.account(chain).unwrap();
+    let genesis = chain_account.genesis().unwrap();
+    let old_pubkey = PubKey::from(validator.pubkey());
+    let new_pubkey = new_key.pubkey();
+    let message = solana_ibc::chain::key_rotation_message(
+        &genesis,
+        &old_pubkey,
+        &new_pubkey,
+    );
+    let signature = <[u8; 64]>::from(new_key.sign(&message));
+
+    for tries in 1..6 {
+        let tx = program
+            .request()
+            .instruction(ComputeBudgetInstruction::set_compute_unit_price(
+                config.priority_fees,
+            ))
+            .instruction(utils::new_ed25519_instruction_with_signature(
+                new_pubkey.as_ref(),
+                &signature,
+                &message,
+            ))
+            .accounts(accounts::ChainWithVerifier {
+                sender: validator.pubkey(),
+                chain,
+                trie,
+                #[cfg(feature = "witness")]
+                witness,
+                ix_sysvar:
+                    anchor_lang::solana_program::sysvar::instructions::ID,
+                system_program: anchor_lang::system_program::ID,
+            })
+            .args(instruction::RotateValidatorKey {
+                new_pubkey: new_pubkey.clone().into(),
+                proof_of_possession: signature,
+            })
+            .payer(validator.clone())
+            .signer(&*validator)
+            .send();
+        if let Err(err @ ClientError::SolanaClientError(_)) = tx {
+            log::error!("Couldnt not send the transaction: {:?}", err);
+        } else if let Ok(tx) = tx {
+            println!("This is key rotation signature:\n  {}", tx);
+            return Config {
+                keypair: new_key.into(),
+                previous_keypair: Some(old_key.into()),
+                ..config
+            };
+        }
+        sleep(Duration::from_millis(500));
+        log::info!("Retrying to send the transaction: Attempt {}", tries);
+    }
+    panic!("Could not send the transaction, please try again");
+}
@@ -0,0 +1,220 @@
+//! Block signing keys.
+//!
+//! By default the validator signs guest blocks with the keypair stored in its
+//! configuration file.  To avoid keeping validator’s key on disk, blocks can
+//! instead be signed by a remote signing service (e.g. a threshold signer or
+//! an HSM front-end) configured in `remote_signers` section of the
+//! configuration file.  For example:
+//!
+//! ```toml
+//! [[remote_signers]]
+//! url = "http://127.0.0.1:8645"
+//! pubkey = "8ry6pz8W6vjfFEmdNLzmmxfzQSDkbTUAZtwmbQz9UTgA"
+//! timeout_ms = 2000
+//! max_retries = 3
+//! ```
+//!
+//! The service is called with JSON-RPC 2.0 over HTTP.  The request is
+//! a `sign` call with `pubkey` (base58-encoded public key) and `message`
+//! (base64-encoded message) parameters.  The result must be an object with
+//! a `signature` field holding base58-encoded Ed25519 signature of the
+//! message.  The signature is verified before it’s used.
+//!
+//! The local key is held as [`LocalSigner`] which zeroes the key material
+//! when dropped.
+//!
+//! Remote signers sign both the guest block fingerprint and the Solana
+//! transaction submitting the signature (since the transaction’s sender must
+//! be the validator).  Transaction fees are paid by the local keypair.
+
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anchor_client::solana_sdk::signature::{Signature, Signer, SignerError};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sigverify::ed25519::SecretKey;
+
+/// Signer using a secret key held in memory.
+///
+/// The key is zeroed when the signer is dropped and, unlike `Keypair`, its
+/// `Debug` representation doesn’t include the secret.
+#[derive(Clone, Debug, derive_more::From)]
+pub struct LocalSigner(SecretKey);
+
+impl Signer for LocalSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0.pubkey().into())
+    }
+
+    fn try_sign_message(
+        &self,
+        message: &[u8],
+    ) -> Result<Signature, SignerError> {
+        Ok(Signature::from(<[u8; 64]>::from(self.0.sign(message))))
+    }
+
+    fn is_interactive(&self) -> bool { false }
+}
+
+/// Configuration of a remote signing service.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteSignerConfig {
+    /// URL of the JSON-RPC endpoint of the signing service.
+    pub url: String,
+
+    /// Base58-encoded public key the service signs with.
+    pub pubkey: String,
+
+    /// Timeout of a single request in milliseconds.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Number of times a failed request is retried.
+    ///
+    /// Requests are retried on connection errors, timeouts and server errors.
+    /// Errors reported by the signing service are not retried.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+}
+
+fn default_timeout_ms() -> u64 { 5000 }
+fn default_max_retries() -> usize { 3 }
+
+/// Signer which delegates signing to a remote signing service.
+pub struct RemoteSigner {
+    url: String,
+    pubkey: Pubkey,
+    max_retries: usize,
+    client: reqwest::blocking::Client,
+}
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'static str,
+    params: SignParams<'a>,
+}
+
+#[derive(Serialize)]
+struct SignParams<'a> {
+    pubkey: &'a str,
+    message: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    #[serde(default)]
+    result: Option<SignResult>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct SignResult {
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RemoteSigner {
+    pub fn new(config: &RemoteSignerConfig) -> Result<Self, SignerError> {
+        let pubkey = Pubkey::from_str(&config.pubkey)
+            .map_err(|err| SignerError::Custom(err.to_string()))?;
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|err| SignerError::Custom(err.to_string()))?;
+        Ok(Self {
+            url: config.url.clone(),
+            pubkey,
+            max_retries: config.max_retries,
+            client,
+        })
+    }
+
+    /// Sends a single signing request to the service.
+    ///
+    /// On failure returns the error and whether the request can be retried.
+    fn request(
+        &self,
+        id: u64,
+        message: &str,
+    ) -> Result<Signature, (SignerError, bool)> {
+        let pubkey = self.pubkey.to_string();
+        let request = SignRequest {
+            jsonrpc: "2.0",
+            id,
+            method: "sign",
+            params: SignParams { pubkey: &pubkey, message },
+        };
+        let response =
+            self.client.post(&self.url).json(&request).send().map_err(
+                |err| (SignerError::Connection(err.to_string()), true),
+            )?;
+        let status = response.status();
+        if !status.is_success() {
+            let err = SignerError::Connection(format!("HTTP {status}"));
+            return Err((err, status.is_server_error()));
+        }
+        let response: SignResponse = response
+            .json()
+            .map_err(|err| (SignerError::Protocol(err.to_string()), false))?;
+        match (response.result, response.error) {
+            (_, Some(err)) => {
+                let msg = format!("{} (code {})", err.message, err.code);
+                Err((SignerError::Custom(msg), false))
+            }
+            (Some(result), None) => Signature::from_str(&result.signature)
+                .map_err(|err| (SignerError::Protocol(err.to_string()), false)),
+            (None, None) => {
+                let msg = "response has no result".to_string();
+                Err((SignerError::Protocol(msg), false))
+            }
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> { Ok(self.pubkey) }
+
+    fn try_sign_message(
+        &self,
+        message: &[u8],
+    ) -> Result<Signature, SignerError> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(message);
+        let mut tries = 0;
+        let signature = loop {
+            match self.request(tries as u64, &encoded) {
+                Ok(signature) => break signature,
+                Err((err, true)) if tries < self.max_retries => {
+                    tries += 1;
+                    log::warn!(
+                        "Remote signer {} failed: {err}; retrying ({}/{})",
+                        self.url,
+                        tries,
+                        self.max_retries
+                    );
+                    sleep(Duration::from_millis(500 * tries as u64));
+                }
+                Err((err, _)) => return Err(err),
+            }
+        };
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Protocol(format!(
+                "Remote signer {} returned invalid signature",
+                self.url
+            )));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool { false }
+}
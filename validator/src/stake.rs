@@ -5,18 +5,19 @@ use std::time::Duration;
 
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
 use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
-use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::solana_sdk::signature::{Keypair, Signature};
 use anchor_client::solana_sdk::signer::Signer;
-use anchor_client::{Client, ClientError, Cluster};
+use anchor_client::{Client, ClientError, Cluster, Program};
 use anchor_lang::solana_program::instruction::AccountMeta;
 use anchor_lang::solana_program::pubkey::Pubkey;
 use anchor_lang::solana_program::sysvar::SysvarId;
 use restaking::{accounts, Service};
 
 use crate::command::Config;
+use crate::signer::LocalSigner;
 
 pub fn stake(config: Config, amount: u64, token_mint: Pubkey) {
-    let validator = Rc::new(Keypair::from(config.keypair));
+    let validator = Rc::new(config.keypair.into_signer());
     let client = Client::new_with_options(
         Cluster::from_str(&config.rpc_url).expect("Invalid cluster"),
         validator.clone(),
@@ -24,7 +25,34 @@ pub fn stake(config: Config, amount: u64, token_mint: Pubkey) {
     );
     let program = client.program(restaking::ID).unwrap();
     let solana_ibc_program_id = Pubkey::from_str(&config.program_id).unwrap();
+    log::info!("This is priority fee {:?}", config.priority_fees);
+    match deposit(
+        &program,
+        &validator,
+        solana_ibc_program_id,
+        token_mint,
+        amount,
+        config.priority_fees,
+    ) {
+        Ok(tx) => println!("This is staking signature:\n  {}", tx),
+        Err(_) => panic!("Could not send the transaction, please try again"),
+    }
+}
 
+/// Deposits `amount` of `token_mint` tokens in the restaking program staking
+/// them on the validator.
+///
+/// If `token_mint` is the native mint, stakes `amount` lamports from
+/// validator’s wallet rather than tokens from its token account.
+pub fn deposit(
+    program: &Program<Rc<LocalSigner>>,
+    validator: &Rc<LocalSigner>,
+    solana_ibc_program_id: Pubkey,
+    token_mint: Pubkey,
+    amount: u64,
+    priority_fees: u64,
+) -> Result<Signature, ClientError> {
+    let native = token_mint == anchor_spl::token::spl_token::native_mint::ID;
     let receipt_token_keypair = Keypair::new();
     let receipt_token_key = receipt_token_keypair.pubkey();
     let staking_params = Pubkey::find_program_address(
@@ -87,22 +115,24 @@ pub fn stake(config: Config, amount: u64, token_mint: Pubkey) {
             &validator.pubkey(),
             &receipt_token_key,
         );
-    log::info!("This is priority fee {:?}", config.priority_fees);
+    let mut tx = Err(ClientError::AccountNotFound);
     for tries in 1..6 {
-        let tx = program
+        let request = program
             .request()
             .instruction(ComputeBudgetInstruction::set_compute_unit_limit(
                 500_000u32,
             ))
             .instruction(ComputeBudgetInstruction::set_compute_unit_price(
-                config.priority_fees,
+                priority_fees,
             ))
             .accounts(accounts::Deposit {
                 depositor: validator.pubkey(),
+                rent_payer: validator.pubkey(),
                 vault_params,
                 staking_params,
                 token_mint,
-                depositor_token_account,
+                depositor_token_account: (!native)
+                    .then_some(depositor_token_account),
                 vault_token_account,
                 receipt_token_mint: receipt_token_key,
                 receipt_token_account,
@@ -132,23 +162,74 @@ pub fn stake(config: Config, amount: u64, token_mint: Pubkey) {
                     is_signer: false,
                     is_writable: true,
                 },
-            ])
-            .args(restaking::instruction::Deposit {
-                service: Service::GuestChain { validator: validator.pubkey() },
-                amount,
-            })
+            ]);
+        let service = Service::GuestChain { validator: validator.pubkey() };
+        let request = if native {
+            request.args(restaking::instruction::DepositSol { service, amount })
+        } else {
+            request.args(restaking::instruction::Deposit { service, amount })
+        };
+        tx = request
             .payer(validator.clone())
-            .signer(&*validator)
+            .signer(&**validator)
             .signer(&receipt_token_keypair)
             .send();
-        if let Err(err @ ClientError::SolanaClientError(_)) = tx {
-            log::error!("Couldnt not send the transaction: {:?}", err);
-        } else if let Ok(tx) = tx {
-            println!("This is staking signature:\n  {}", tx);
-            return;
+        match tx {
+            Err(ref err @ ClientError::SolanaClientError(_)) => {
+                log::error!("Couldnt not send the transaction: {:?}", err);
+            }
+            Ok(_) => return tx,
+            Err(_) => (),
         }
         sleep(Duration::from_millis(500));
         log::info!("Retrying to send the transaction: Attempt {}", tries);
     }
-    panic!("Could not send the transaction, please try again");
+    tx
+}
+
+/// Claims validator’s accrued block rewards and stakes them.
+///
+/// `amount` is the amount of lamports to stake.  It should not exceed the
+/// amount of accrued rewards.
+pub fn restake_rewards(
+    solana_ibc: &Program<Rc<LocalSigner>>,
+    restaking: &Program<Rc<LocalSigner>>,
+    validator: &Rc<LocalSigner>,
+    amount: u64,
+    priority_fees: u64,
+) -> Result<Signature, ClientError> {
+    let solana_ibc_program_id = solana_ibc.id();
+    let chain = Pubkey::find_program_address(
+        &[solana_ibc::CHAIN_SEED],
+        &solana_ibc_program_id,
+    )
+    .0;
+    let fee_account = Pubkey::find_program_address(
+        &[solana_ibc::FEE_SEED],
+        &solana_ibc_program_id,
+    )
+    .0;
+    let tx = solana_ibc
+        .request()
+        .instruction(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fees,
+        ))
+        .accounts(solana_ibc::accounts::ClaimBlockRewards {
+            validator: validator.pubkey(),
+            chain,
+            fee_account,
+        })
+        .args(solana_ibc::instruction::ClaimBlockRewards {})
+        .payer(validator.clone())
+        .signer(&**validator)
+        .send()?;
+    log::info!("Block rewards claimed -> Transaction: {}", tx);
+    deposit(
+        restaking,
+        validator,
+        solana_ibc_program_id,
+        anchor_spl::token::spl_token::native_mint::ID,
+        amount,
+        priority_fees,
+    )
 }
@@ -0,0 +1,521 @@
+//! Automatic timing out of IBC packets.
+//!
+//! Tokens sent from Solana are escrowed (or burned) until the packet is
+//! acknowledged or timed out.  If the counterparty never receives the packet
+//! and no relayer submits `MsgTimeout`, the funds remain stuck.  When
+//! `timeouts` section is present in the configuration file, the validator
+//! watches packets sent by the solana-ibc program and once they time out
+//! submits `MsgTimeout` refunding the sender.  For example:
+//!
+//! ```toml
+//! [timeouts]
+//! write_program_id = "FufGpHqMQgGVjtMH9AV8YMrJYq8zaK6USRsJkZP4yDjo"
+//!
+//! [timeouts.counterparties]
+//! "07-tendermint-0" = "http://127.0.0.1:26657"
+//! ```
+//!
+//! `counterparties` maps IDs of light clients on Solana to Tendermint RPC
+//! endpoints of the chains they track.  Packets on channels whose client is
+//! not listed are ignored.  The non-membership proof of the packet receipt is
+//! queried from the counterparty at the latest height known to the light
+//! client on Solana thus the client must be kept up to date by a relayer.
+//!
+//! Packets are discovered by decoding `SendPacket` events from transactions
+//! of the solana-ibc program.  Packets sent before the validator started are
+//! only found if they are among the most recent [`INITIAL_SCAN_LIMIT`]
+//! transactions.  Only ICS-20 packets on unordered channels are supported.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroU16;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anchor_client::solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_client::solana_sdk::signer::Signer;
+use anchor_client::Program;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_ibc::ibc::{self, ClientStateCommon, ConsensusState};
+use solana_ibc::ix_data_account::ChunkedInstruction;
+use solana_ibc::storage::PrivateStorage;
+use solana_ibc_events::Event;
+
+use crate::signer::LocalSigner;
+
+/// Number of most recent transactions of the solana-ibc program scanned for
+/// sent packets when the validator starts.
+pub const INITIAL_SCAN_LIMIT: usize = 1000;
+
+/// Configuration of the packet timeouts automation.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    /// Address of the write-account program used to deliver `MsgTimeout`
+    /// which doesn’t fit in a single transaction.
+    pub write_program_id: String,
+
+    /// Tendermint RPC endpoints of counterparty chains keyed by ID of the
+    /// light client tracking the chain on Solana.
+    #[serde(default)]
+    pub counterparties: BTreeMap<String, String>,
+}
+
+type Result<T = (), E = Box<dyn std::error::Error>> =
+    core::result::Result<T, E>;
+
+/// Key identifying a packet sent from Solana.
+type PacketKey = (ibc::PortId, ibc::ChannelId, ibc::Sequence);
+
+/// Watches sent packets and times out the ones counterparty hasn’t received.
+pub struct TimeoutRelayer {
+    config: TimeoutConfig,
+    write_program: Pubkey,
+
+    /// Packets whose commitments may still be in the trie.
+    pending: BTreeMap<PacketKey, ibc::Packet>,
+
+    /// The most recent transaction scanned for events.
+    last_signature: Option<Signature>,
+}
+
+impl TimeoutRelayer {
+    pub fn new(config: TimeoutConfig) -> Result<Self> {
+        let write_program = Pubkey::from_str(&config.write_program_id)?;
+        Ok(Self {
+            config,
+            write_program,
+            pending: Default::default(),
+            last_signature: None,
+        })
+    }
+
+    /// Scans new transactions for sent packets and times out packets whose
+    /// timeout has passed.
+    ///
+    /// Returns number of packets which have been timed out.
+    pub fn poll(
+        &mut self,
+        program: &Program<Rc<LocalSigner>>,
+        payer: &Rc<LocalSigner>,
+        priority_fees: u64,
+    ) -> Result<usize> {
+        self.scan_transactions(program)?;
+        if self.pending.is_empty() {
+            return Ok(0);
+        }
+
+        let storage_key = Pubkey::find_program_address(
+            &[solana_ibc::SOLANA_IBC_STORAGE_SEED],
+            &program.id(),
+        )
+        .0;
+        let trie_key = Pubkey::find_program_address(
+            &[solana_ibc::TRIE_SEED],
+            &program.id(),
+        )
+        .0;
+        let storage: PrivateStorage = program.account(storage_key)?;
+        let trie_account = program
+            .rpc()
+            .get_account_with_commitment(
+                &trie_key,
+                CommitmentConfig::confirmed(),
+            )?
+            .value
+            .ok_or("trie account not found")?;
+        let trie = solana_trie::TrieAccount::<_, ()>::new(trie_account.data)
+            .ok_or("invalid trie account")?;
+
+        let mut timed_out = 0;
+        let pending = core::mem::take(&mut self.pending);
+        for (key, packet) in pending {
+            let path = ibc::path::CommitmentPath::new(
+                &packet.port_id_on_a,
+                &packet.chan_id_on_a,
+                packet.seq_on_a,
+            );
+            let trie_key = trie_ids::TrieKey::try_from(&path)?;
+            if trie.get(&trie_key).ok().flatten().is_none() {
+                // Packet has already been acknowledged or timed out.
+                continue;
+            }
+            match self.try_timeout(
+                program,
+                payer,
+                priority_fees,
+                &storage,
+                &packet,
+            ) {
+                Ok(true) => timed_out += 1,
+                Ok(false) => {
+                    self.pending.insert(key, packet);
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to time out packet {}/{}/{}: {err}",
+                        packet.port_id_on_a,
+                        packet.chan_id_on_a,
+                        packet.seq_on_a
+                    );
+                    self.pending.insert(key, packet);
+                }
+            }
+        }
+        Ok(timed_out)
+    }
+
+    /// Fetches transactions of the program since the last scan and records
+    /// packets sent and resolved in them.
+    fn scan_transactions(
+        &mut self,
+        program: &Program<Rc<LocalSigner>>,
+    ) -> Result {
+        let rpc = program.rpc();
+        let program_id = program.id();
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before: None,
+            until: self.last_signature,
+            limit: Some(INITIAL_SCAN_LIMIT),
+            commitment: Some(CommitmentConfig::confirmed()),
+        };
+        let signatures =
+            rpc.get_signatures_for_address_with_config(&program_id, config)?;
+
+        // Signatures are returned newest first.
+        for status in signatures.iter().rev() {
+            let signature = Signature::from_str(&status.signature)?;
+            if status.err.is_some() {
+                self.last_signature = Some(signature);
+                continue;
+            }
+            let encoding =
+                solana_transaction_status::UiTransactionEncoding::Base64;
+            let meta =
+                rpc.get_transaction(&signature, encoding)?.transaction.meta;
+            let events = meta.iter().flat_map(|meta| {
+                solana_ibc_events::rpc::events(&program_id, meta)
+            });
+            for event in events {
+                match event {
+                    Ok(Event::IbcEvent(event)) => self.handle_event(event),
+                    Ok(_) => (),
+                    Err(err) => log::warn!("Failed to decode event: {err}"),
+                }
+            }
+            self.last_signature = Some(signature);
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: ibc::IbcEvent) {
+        match event {
+            ibc::IbcEvent::SendPacket(ev) => {
+                let packet = ibc::Packet {
+                    seq_on_a: *ev.seq_on_a(),
+                    port_id_on_a: ev.port_id_on_a().clone(),
+                    chan_id_on_a: ev.chan_id_on_a().clone(),
+                    port_id_on_b: ev.port_id_on_b().clone(),
+                    chan_id_on_b: ev.chan_id_on_b().clone(),
+                    data: ev.packet_data().to_vec(),
+                    timeout_height_on_b: *ev.timeout_height_on_b(),
+                    timeout_timestamp_on_b: *ev.timeout_timestamp_on_b(),
+                };
+                let key = (
+                    packet.port_id_on_a.clone(),
+                    packet.chan_id_on_a.clone(),
+                    packet.seq_on_a,
+                );
+                self.pending.insert(key, packet);
+            }
+            ibc::IbcEvent::AcknowledgePacket(ev) => {
+                let key = (
+                    ev.port_id_on_a().clone(),
+                    ev.chan_id_on_a().clone(),
+                    *ev.seq_on_a(),
+                );
+                self.pending.remove(&key);
+            }
+            ibc::IbcEvent::TimeoutPacket(ev) => {
+                let key = (
+                    ev.port_id_on_a().clone(),
+                    ev.chan_id_on_a().clone(),
+                    *ev.seq_on_a(),
+                );
+                self.pending.remove(&key);
+            }
+            _ => (),
+        }
+    }
+
+    /// Submits `MsgTimeout` for the packet if it has timed out.
+    ///
+    /// Returns `false` if the packet hasn’t timed out yet or can’t be timed
+    /// out by the validator (e.g. because its counterparty isn’t configured).
+    fn try_timeout(
+        &self,
+        program: &Program<Rc<LocalSigner>>,
+        payer: &Rc<LocalSigner>,
+        priority_fees: u64,
+        storage: &PrivateStorage,
+        packet: &ibc::Packet,
+    ) -> Result<bool> {
+        let pk = trie_ids::PortChannelPK::try_from(
+            &packet.port_id_on_a,
+            &packet.chan_id_on_a,
+        )?;
+        let channel = storage
+            .port_channel
+            .get(&pk)
+            .map(|store| store.channel_end())
+            .transpose()?
+            .flatten()
+            .ok_or("channel not found")?;
+        if channel.ordering != ibc::chan::Order::Unordered {
+            return Ok(false);
+        }
+        let connection_id =
+            channel.connection_hops.first().ok_or("channel has no hops")?;
+        let connection = trie_ids::ConnectionIdx::try_from(connection_id)
+            .ok()
+            .and_then(|idx| storage.connections.get(usize::from(idx)))
+            .ok_or("connection not found")?
+            .get()?;
+        let client_id = connection.client_id();
+        let rpc_url = match self.config.counterparties.get(client_id.as_str()) {
+            Some(url) => url,
+            None => return Ok(false),
+        };
+
+        // The proof is verified against the latest consensus state of the
+        // counterparty known to the light client.  The packet can be timed
+        // out only if it has timed out at that height.
+        let client = storage.client(client_id)?;
+        let height = client.client_state.get()?.latest_height();
+        let timestamp = client
+            .consensus_states
+            .get(&height)
+            .ok_or("consensus state not found")?
+            .state()?
+            .timestamp();
+        if !packet.timed_out(&timestamp, height) {
+            return Ok(false);
+        }
+
+        let receipt_path = ibc::path::ReceiptPath::new(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            packet.seq_on_a,
+        );
+        let proof = match query_non_membership(
+            rpc_url,
+            &receipt_path.to_string(),
+            height.revision_height(),
+        )? {
+            Some(proof) => proof,
+            None => {
+                log::info!(
+                    "Packet {}/{}/{} has been received by the counterparty",
+                    packet.port_id_on_a,
+                    packet.chan_id_on_a,
+                    packet.seq_on_a
+                );
+                return Ok(false);
+            }
+        };
+
+        let message = ibc::MsgTimeout {
+            packet: packet.clone(),
+            next_seq_recv_on_b: packet.seq_on_a,
+            proof_unreceived_on_b: proof.try_into()?,
+            proof_height_on_b: height,
+            signer: ibc::Signer::from(payer.pubkey().to_string()),
+        };
+        let message =
+            ibc::MsgEnvelope::Packet(ibc::PacketMsg::Timeout(message));
+        let accounts = refund_accounts(program, payer.pubkey(), packet)?;
+        let chunked = ChunkedInstruction::deliver(
+            program.id(),
+            &self.write_program,
+            payer.pubkey(),
+            b"timeout",
+            accounts,
+            message,
+            NonZeroU16::new(500),
+        )?;
+
+        for write in chunked.writes {
+            program.request().instruction(write).send()?;
+        }
+        let mut request = program
+            .request()
+            .instruction(ComputeBudgetInstruction::set_compute_unit_limit(
+                1_000_000,
+            ))
+            .instruction(ComputeBudgetInstruction::set_compute_unit_price(
+                priority_fees,
+            ));
+        for instruction in chunked.call {
+            request = request.instruction(instruction);
+        }
+        match request.send() {
+            Ok(tx) => {
+                log::info!(
+                    "Packet {}/{}/{} timed out -> Transaction: {tx}",
+                    packet.port_id_on_a,
+                    packet.chan_id_on_a,
+                    packet.seq_on_a
+                );
+                Ok(true)
+            }
+            Err(err) => {
+                // Recover rent of the write account.
+                if let Err(err) =
+                    program.request().instruction(chunked.free).send()
+                {
+                    log::warn!("Failed to free the write account: {err}");
+                }
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Returns accounts for the `deliver` instruction refunding ICS-20 transfer
+/// sent in given packet.
+fn refund_accounts(
+    program: &Program<Rc<LocalSigner>>,
+    payer: Pubkey,
+    packet: &ibc::Packet,
+) -> Result<solana_ibc::accounts::Deliver> {
+    use ibc::apps::transfer::types::packet::PacketData;
+
+    let program_id = program.id();
+    let pda =
+        |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &program_id).0;
+
+    let data: PacketData = serde_json::from_slice(&packet.data)?;
+    let sender = Pubkey::from_str(data.sender.as_ref())?;
+    let denom = &data.token.denom;
+    let hashed_denom =
+        lib::hash::CryptoHash::digest(denom.to_string().as_bytes());
+
+    // Tokens originating from Solana have been escrowed while tokens received
+    // from elsewhere have been burned and need to be minted back.
+    let (token_mint, escrow_account) =
+        if ibc::apps::transfer::types::is_sender_chain_source(
+            packet.port_id_on_a.clone(),
+            packet.chan_id_on_a.clone(),
+            denom,
+        ) {
+            let mint = Pubkey::from_str(denom.base_denom.as_str())?;
+            (mint, Some(pda(&[solana_ibc::ESCROW, hashed_denom.as_slice()])))
+        } else {
+            (pda(&[solana_ibc::MINT, hashed_denom.as_slice()]), None)
+        };
+    let token_program = program.rpc().get_account(&token_mint)?.owner;
+    let receiver_token_account = Pubkey::find_program_address(
+        &[sender.as_ref(), token_program.as_ref(), token_mint.as_ref()],
+        &anchor_spl::associated_token::ID,
+    )
+    .0;
+
+    let trie = pda(&[solana_ibc::TRIE_SEED]);
+    Ok(solana_ibc::accounts::Deliver {
+        sender: payer,
+        receiver: Some(sender),
+        storage: pda(&[solana_ibc::SOLANA_IBC_STORAGE_SEED]),
+        trie,
+        #[cfg(feature = "witness")]
+        witness: pda(&[solana_ibc::WITNESS_SEED, trie.as_ref()]),
+        chain: pda(&[solana_ibc::CHAIN_SEED]),
+        mint_authority: Some(pda(&[solana_ibc::MINT_ESCROW_SEED])),
+        token_mint: Some(token_mint),
+        escrow_account,
+        receiver_token_account: Some(receiver_token_account),
+        fee_collector: Some(pda(&[solana_ibc::FEE_SEED])),
+        associated_token_program: Some(anchor_spl::associated_token::ID),
+        token_program: Some(token_program),
+        system_program: anchor_lang::system_program::ID,
+        pending_claim_account: None,
+    })
+}
+
+/// Queries Tendermint RPC for proof that nothing is stored under given IBC
+/// path.
+///
+/// `height` is the height of the consensus state the proof is verified
+/// against.  Since Tendermint block header at height `H` commits to the
+/// application state after block `H - 1`, the state is queried at `H - 1`.
+///
+/// Returns `None` if a value is stored under the path.  Otherwise returns the
+/// proof encoded as ICS-23 `MerkleProof` message.
+fn query_non_membership(
+    rpc_url: &str,
+    path: &str,
+    height: u64,
+) -> Result<Option<Vec<u8>>> {
+    #[derive(Deserialize)]
+    struct Response {
+        result: ResponseResult,
+    }
+
+    #[derive(Deserialize)]
+    struct ResponseResult {
+        response: AbciQuery,
+    }
+
+    #[derive(Deserialize)]
+    struct AbciQuery {
+        code: u32,
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(rename = "proofOps")]
+        proof_ops: Option<ProofOps>,
+    }
+
+    #[derive(Deserialize)]
+    struct ProofOps {
+        ops: Vec<ProofOp>,
+    }
+
+    #[derive(Deserialize)]
+    struct ProofOp {
+        data: String,
+    }
+
+    let url = format!(
+        "{}/abci_query?path=\"/store/ibc/key\"&data=0x{}&height={}&prove=true",
+        rpc_url.trim_end_matches('/'),
+        hex(path.as_bytes()),
+        height - 1,
+    );
+    let response: Response = reqwest::blocking::get(url)?.json()?;
+    let query = response.result.response;
+    if query.code != 0 {
+        return Err(
+            format!("abci_query failed with code {}", query.code).into()
+        );
+    }
+    if query.value.is_some_and(|value| !value.is_empty()) {
+        return Ok(None);
+    }
+
+    // MerkleProof message is a repeated CommitmentProof field and each proof
+    // operation holds an encoded CommitmentProof so the operations can be
+    // copied verbatim as length-delimited fields.
+    let ops = query.proof_ops.ok_or("missing proof")?.ops;
+    let mut proof = Vec::new();
+    for op in ops {
+        let data = base64::engine::general_purpose::STANDARD.decode(op.data)?;
+        prost::encoding::bytes::encode(1, &data, &mut proof);
+    }
+    Ok(Some(proof))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -5,7 +5,7 @@ use std::thread::sleep;
 use std::time::Duration;
 
 use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
-use anchor_client::solana_sdk::signature::{Keypair, Signature};
+use anchor_client::solana_sdk::signature::Signature;
 use anchor_client::solana_sdk::signer::Signer;
 use anchor_client::{ClientError, Program};
 use anchor_lang::solana_program::instruction::Instruction;
@@ -15,6 +15,8 @@ use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use solana_ibc::{accounts, instruction};
 
+use crate::signer::LocalSigner;
+
 /// Displays the error if present, waits for few seconds and
 /// retries execution.
 ///
@@ -54,7 +56,7 @@ pub fn setup_logging(log_level: log::LevelFilter) {
     env_logger::builder().filter_level(log_level).format_timestamp(None).init();
 }
 
-fn new_ed25519_instruction_with_signature(
+pub fn new_ed25519_instruction_with_signature(
     pubkey: &[u8; 32],
     signature: &[u8],
     message: &[u8],
@@ -110,12 +112,17 @@ pub struct BundleStatusResponse {
     id: u64,
 }
 
+/// Submits block signature made by `validator`.
+///
+/// The transaction is signed by `validator` (which may be a remote signer)
+/// and fees are paid by `payer`.
 #[allow(clippy::too_many_arguments)]
 pub fn submit_call(
-    program: &Program<Rc<Keypair>>,
+    program: &Program<Rc<LocalSigner>>,
     signature: Signature,
     message: &[u8],
-    validator: &Rc<Keypair>,
+    payer: &Rc<LocalSigner>,
+    validator: &dyn Signer,
     chain: Pubkey,
     trie: Pubkey,
     max_retries: usize,
@@ -154,7 +161,7 @@ pub fn submit_call(
                 system_program: system_program::ID,
             })
             .args(instruction::SignBlock { signature: signature.into() })
-            .payer(validator.clone())
+            .payer(payer.clone())
             .signer(validator)
             .send();
         if let Err(err @ ClientError::SolanaClientError(_)) = tx {
@@ -170,8 +177,8 @@ pub fn submit_call(
 }
 
 pub fn submit_generate_block_call(
-    program: &Program<Rc<Keypair>>,
-    validator: &Rc<Keypair>,
+    program: &Program<Rc<LocalSigner>>,
+    validator: &Rc<LocalSigner>,
     chain: Pubkey,
     trie: Pubkey,
     max_retries: usize,
@@ -217,3 +224,33 @@ pub fn submit_generate_block_call(
     log::error!("Max retries for signing the block exceeded");
     tx
 }
+
+/// Fetches transaction `tx` and logs events solana-ibc program emitted in it.
+///
+/// Errors are logged rather than returned since the events are only
+/// informative.
+pub fn log_events(program: &Program<Rc<LocalSigner>>, tx: &Signature) {
+    use solana_ibc_events::Event;
+
+    let encoding = solana_transaction_status::UiTransactionEncoding::Base64;
+    let meta = match program.rpc().get_transaction(tx, encoding) {
+        Ok(resp) => resp.transaction.meta,
+        Err(err) => {
+            log::warn!("Failed to fetch transaction {tx}: {err}");
+            return;
+        }
+    };
+    let program_id = program.id();
+    let events = meta
+        .iter()
+        .flat_map(|meta| solana_ibc_events::rpc::events(&program_id, meta));
+    for event in events {
+        match event {
+            Ok(Event::BlockFinalised(event)) => {
+                log::info!("Block {} finalised", event.block_height)
+            }
+            Ok(event) => log::debug!("Event: {event:?}"),
+            Err(err) => log::warn!("Failed to decode event: {err}"),
+        }
+    }
+}
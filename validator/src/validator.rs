@@ -1,21 +1,42 @@
 use std::num::NonZeroU64;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
-use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::solana_sdk::signer::Signer;
 use anchor_client::{Client, Cluster};
 use anchor_lang::solana_program::pubkey::Pubkey;
 use solana_ibc::chain::ChainData;
 
 use crate::command::Config;
-use crate::utils;
+use crate::metrics::{self, Metrics};
+use crate::signer::{LocalSigner, RemoteSigner};
+use crate::timeout::TimeoutRelayer;
+use crate::{stake, utils};
 
 pub fn run_validator(config: Config) {
-    let validator = Rc::new(Keypair::from(config.keypair));
+    let validator = Rc::new(config.keypair.into_signer());
+
+    // While key rotation is in progress, the previous key may still be in the
+    // validators set of the current epoch.  Sign with whichever of the keys
+    // is in the set until the epoch changes.  Remote signers are used the
+    // same way with the local keypair paying the transaction fees.
+    let mut signers: Vec<Box<dyn Signer>> =
+        vec![Box::new(LocalSigner::clone(&validator))];
+    signers.extend(
+        config
+            .previous_keypair
+            .map(|keypair| Box::new(keypair.into_signer()) as Box<dyn Signer>),
+    );
+    for remote in config.remote_signers.iter() {
+        let signer = RemoteSigner::new(remote)
+            .expect("Invalid remote signer configuration");
+        log::info!("Using remote signer {} at {}", signer.pubkey(), remote.url);
+        signers.push(Box::new(signer));
+    }
     let client = Client::new_with_options(
         Cluster::from_str(&config.rpc_url).expect("Invalid cluster"),
         validator.clone(),
@@ -23,6 +44,7 @@ pub fn run_validator(config: Config) {
     );
     let program =
         client.program(Pubkey::from_str(&config.program_id).unwrap()).unwrap();
+    let restaking = client.program(restaking::ID).unwrap();
 
     let trie = Pubkey::find_program_address(
         &[solana_ibc::TRIE_SEED],
@@ -35,55 +57,155 @@ pub fn run_validator(config: Config) {
     )
     .0;
 
+    let metrics = Arc::new(Metrics::default());
+    if let Some(addr) = config.metrics_addr.as_ref() {
+        metrics::serve(addr, metrics.clone())
+            .expect("Failed to start metrics server");
+    }
+
+    let mut timeouts = config.timeouts.map(|config| {
+        TimeoutRelayer::new(config).expect("Invalid timeouts configuration")
+    });
+
     log::info!("Validator running");
 
     let max_tries = 5;
 
+    // Height of the pending block the validator is expected to sign, time when
+    // it was first noticed and whether the validator has signed it.
+    let mut expected: Option<(guestchain::BlockHeight, Instant, bool)> = None;
+
     loop {
         sleep(Duration::from_secs(5));
         let chain_account: ChainData = program.account(chain).unwrap();
-        if chain_account.pending_block().unwrap().is_some() {
-            if let Some(pending_block) =
-                chain_account.pending_block().unwrap().as_ref()
-            {
-                if pending_block
-                    .signers
-                    .get(&validator.pubkey().into())
-                    .is_some()
-                {
-                    log::info!("You have already signed the pending block");
-                    continue;
+
+        let pending_height = chain_account
+            .pending_block()
+            .unwrap()
+            .map(|_| chain_account.head().unwrap().block_height);
+        if let Some((height, _, signed)) = expected {
+            if Some(height) != pending_height {
+                if !signed {
+                    log::warn!(
+                        "Block {} finalised without our signature",
+                        height
+                    );
+                    metrics.block_missed();
                 }
-                log::info!(
-                    "Found block {:?}",
-                    chain_account.pending_block().unwrap().unwrap()
-                );
-                let fingerprint = &chain_account
-                    .pending_block()
-                    .unwrap()
-                    .unwrap()
-                    .fingerprint;
-                let signature = validator.sign_message(fingerprint.as_slice());
-                log::info!(
-                    "This is the signature of signed block {:?}",
-                    signature.to_string()
-                );
-                let tx = utils::submit_call(
+                expected = None;
+            }
+        }
+
+        let pubkey = validator.pubkey();
+        let stake = chain_account
+            .candidate(pubkey)
+            .unwrap()
+            .map_or(0, |candidate| candidate.stake.get());
+        let accrued = chain_account.block_rewards(&pubkey.into()).unwrap();
+        metrics.set_balances(stake, accrued);
+        if let Some(threshold) = config.restake_threshold {
+            if accrued > 0 && accrued >= u128::from(threshold) {
+                let amount = u64::try_from(accrued).unwrap_or(u64::MAX);
+                log::info!("Restaking {} lamports of block rewards", amount);
+                match stake::restake_rewards(
                     &program,
-                    signature,
-                    fingerprint.as_slice(),
+                    &restaking,
                     &validator,
-                    chain,
-                    trie,
-                    max_tries,
-                    &config.priority_fees,
-                );
-                match tx {
+                    amount,
+                    config.priority_fees,
+                ) {
                     Ok(tx) => {
-                        log::info!("Block signed -> Transaction: {}", tx);
+                        log::info!("Rewards restaked -> Transaction: {}", tx);
+                        metrics.restaked();
                     }
                     Err(err) => {
-                        log::error!("Failed to send the transaction {err}")
+                        log::error!("Failed to restake the rewards {err}")
+                    }
+                }
+            }
+        }
+
+        if let Some(timeouts) = timeouts.as_mut() {
+            match timeouts.poll(&program, &validator, config.priority_fees) {
+                Ok(0) => (),
+                Ok(count) => {
+                    log::info!("Timed out {count} packets");
+                    metrics.packets_timed_out(count as u64);
+                }
+                Err(err) => log::error!("Failed to time out packets: {err}"),
+            }
+        }
+
+        if chain_account.pending_block().unwrap().is_some() {
+            if let Some(pending_block) =
+                chain_account.pending_block().unwrap().as_ref()
+            {
+                log::info!("Found block {:?}", pending_block);
+                for signer in signers.iter() {
+                    if chain_account
+                        .validator(signer.pubkey())
+                        .unwrap()
+                        .is_none()
+                    {
+                        log::debug!(
+                            "{} is not a validator of the pending block",
+                            signer.pubkey()
+                        );
+                        continue;
+                    }
+                    let (_, first_seen, signed) =
+                        expected.get_or_insert_with(|| {
+                            (pending_height.unwrap(), Instant::now(), false)
+                        });
+                    if pending_block
+                        .signers
+                        .get(&signer.pubkey().into())
+                        .is_some()
+                    {
+                        log::info!(
+                            "{} has already signed the pending block",
+                            signer.pubkey()
+                        );
+                        *signed = true;
+                        continue;
+                    }
+                    let fingerprint = &pending_block.fingerprint;
+                    let signature =
+                        match signer.try_sign_message(fingerprint.as_slice()) {
+                            Ok(signature) => signature,
+                            Err(err) => {
+                                log::error!(
+                                    "Failed to sign the block with {}: {err}",
+                                    signer.pubkey()
+                                );
+                                continue;
+                            }
+                        };
+                    log::info!(
+                        "This is the signature of signed block {:?}",
+                        signature.to_string()
+                    );
+                    let tx = utils::submit_call(
+                        &program,
+                        signature,
+                        fingerprint.as_slice(),
+                        &validator,
+                        &**signer,
+                        chain,
+                        trie,
+                        max_tries,
+                        &config.priority_fees,
+                    );
+                    match tx {
+                        Ok(tx) => {
+                            log::info!("Block signed -> Transaction: {}", tx);
+                            utils::log_events(&program, &tx);
+                            *signed = true;
+                            metrics.block_signed(first_seen.elapsed());
+                        }
+                        Err(err) => {
+                            log::error!("Failed to send the transaction {err}")
+                        }
                     }
                 }
             } else {
@@ -128,6 +250,7 @@ pub fn run_validator(config: Config) {
                 match tx {
                     Ok(tx) => {
                         log::info!("New block created -> Transaction: {}", tx);
+                        utils::log_events(&program, &tx);
                     }
                     Err(err) => {
                         log::error!("Failed to send the transaction {err}")